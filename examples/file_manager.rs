@@ -2,7 +2,7 @@ use hanfried_db::datatypes::varchar::Varchar;
 use hanfried_db::datatypes::varint::Varint;
 use hanfried_db::datatypes::varpair::Varpair;
 use hanfried_db::file_management::block_id::{BlockId, DbFilename};
-use hanfried_db::file_management::file_manager::FileManager;
+use hanfried_db::file_management::file_manager::{Durability, FileManager};
 use hanfried_db::file_management::page::Page;
 use hanfried_db::utils;
 use std::num::NonZeroUsize;
@@ -15,6 +15,7 @@ fn main() {
         "/tmp/test".to_string(),
         block_size,
         NonZeroUsize::new(100).unwrap(),
+        Durability::Sync,
     )
     .unwrap();
     println!("{file_manager:?}");
@@ -42,8 +43,8 @@ fn main() {
     println!("{page1:?} {s:?} {i:?}");
     file_manager.write(&block, &page1).unwrap();
 
-    let mut page2 = Page::new(block_size);
-    file_manager.read(&block, &mut page2).unwrap();
+    let page2 = Page::new(block_size);
+    file_manager.read(&block, &page2).unwrap();
     // let s = page2.get_string(pos_string);
     // let i = page2.get_i32(pos_int);
     let record = page2.get::<Varpair<Varchar, Varint>>(pos_string);
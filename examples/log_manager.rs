@@ -2,6 +2,7 @@ use hanfried_db::datatypes::varchar::Varchar;
 use hanfried_db::datatypes::varint::Varint;
 use hanfried_db::datatypes::varpair::Varpair;
 use hanfried_db::db_management_system::hfdb::HanfriedDb;
+use hanfried_db::file_management::file_manager::Durability;
 use hanfried_db::file_management::page::Page;
 use hanfried_db::memory_management::log_manager::{LogManager, LogSequenceNumber};
 use hanfried_db::utils::logging::init_logging;
@@ -53,6 +54,7 @@ fn main() {
         log_file.to_string(),
         8,
         100,
+        Durability::Sync,
     )
     .unwrap();
     println!("{hanfried_db:?}");
@@ -0,0 +1,93 @@
+use hanfried_db::shell::options::{ShellInput, ShellOptions};
+use hanfried_db::shell::repl::{Flow, Shell};
+use hanfried_db::utils::logging::init_logging_to_stderr;
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+const HISTORY_FILE: &str = ".hfdb_history";
+
+fn main() -> ExitCode {
+    init_logging_to_stderr();
+
+    let options = match ShellOptions::from_args(std::env::args().skip(1)) {
+        Ok(options) if options.help => {
+            println!("{}", ShellOptions::usage("hfdb-cli"));
+            return ExitCode::SUCCESS;
+        }
+        Ok(options) => options,
+        Err(e) => {
+            eprintln!("{}", e);
+            eprintln!("{}", ShellOptions::usage("hfdb-cli"));
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let hanfried_db = match options.database.open_db() {
+        Ok(hanfried_db) => hanfried_db,
+        Err(e) => {
+            eprintln!(
+                "Opening database in {} failed: {:?}",
+                options.database.db_directory, e
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    let mut shell = Shell::new(&hanfried_db, options.mode);
+    let mut stdout = std::io::stdout().lock();
+
+    if !options.inputs.is_empty() {
+        for input in &options.inputs {
+            let script = match input {
+                ShellInput::Command(sql) => sql.clone(),
+                ShellInput::File(path) => match std::fs::read_to_string(path) {
+                    Ok(script) => script,
+                    Err(e) => {
+                        eprintln!("Reading {} failed: {}", path, e);
+                        return ExitCode::FAILURE;
+                    }
+                },
+            };
+            match shell.run_script(&script, &mut stdout) {
+                Ok(Flow::Continue) => {}
+                Ok(Flow::Quit) => break,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    return ExitCode::FAILURE;
+                }
+            }
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let stdin = std::io::stdin();
+    if stdin.is_terminal() {
+        if let Some(home) = std::env::var_os("HOME") {
+            shell = shell.with_history_file(PathBuf::from(home).join(HISTORY_FILE));
+        }
+        println!(
+            "HanfriedDB shell on {}, enter .help for help",
+            options.database.db_directory
+        );
+        return match shell.interactive(&mut stdin.lock(), &mut stdout, &mut std::io::stderr()) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let mut script = String::new();
+    if let Err(e) = stdin.lock().read_to_string(&mut script) {
+        eprintln!("Reading stdin failed: {}", e);
+        return ExitCode::FAILURE;
+    }
+    match shell.run_script(&script, &mut stdout) {
+        Ok(_) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use hanfried_db::server::http::HttpServer;
+use hanfried_db::server::options::ServerOptions;
+use hanfried_db::utils::logging::init_logging;
+use log::{error, info};
+use std::net::TcpListener;
+use std::process::ExitCode;
+
+fn main() -> ExitCode {
+    init_logging();
+
+    let options =
+        match ServerOptions::from_args(HttpServer::DEFAULT_LISTEN, std::env::args().skip(1)) {
+            Ok(options) if options.help => {
+                println!(
+                    "{}",
+                    ServerOptions::usage("hfdb-http", HttpServer::DEFAULT_LISTEN)
+                );
+                return ExitCode::SUCCESS;
+            }
+            Ok(options) => options,
+            Err(e) => {
+                eprintln!("{}", e);
+                eprintln!(
+                    "{}",
+                    ServerOptions::usage("hfdb-http", HttpServer::DEFAULT_LISTEN)
+                );
+                return ExitCode::FAILURE;
+            }
+        };
+    info!("Starting HTTP server with {:?}", options);
+
+    let hanfried_db = match options.open_db() {
+        Ok(hanfried_db) => hanfried_db,
+        Err(e) => {
+            error!(
+                "Opening database in {} failed: {:?}",
+                options.db_directory, e
+            );
+            return ExitCode::FAILURE;
+        }
+    };
+    info!("Recovered {:?}", hanfried_db.recovery_statistics);
+
+    let listener = match TcpListener::bind(&options.listen) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Listening on {} failed: {}", options.listen, e);
+            return ExitCode::FAILURE;
+        }
+    };
+    match HttpServer::new(&hanfried_db).serve(listener) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("Accepting connections failed: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
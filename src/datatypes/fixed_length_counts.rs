@@ -1,4 +1,4 @@
-use crate::datatypes::HfdbSerializableDatatype;
+use crate::datatypes::{HfdbKeyDatatype, HfdbSerializableDatatype};
 use std::num::NonZeroUsize;
 
 #[derive(Debug, Eq, PartialEq)]
@@ -18,6 +18,22 @@ impl HfdbSerializableDatatype for TinyCount {
     }
 }
 
+impl HfdbKeyDatatype for TinyCount {
+    fn key_length(&self) -> usize {
+        1
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        buffer[..1].copy_from_slice(&self.0.to_be_bytes());
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let mut bytes = [0u8; 1];
+        bytes.copy_from_slice(&buffer[..1]);
+        (Self(u8::from_be_bytes(bytes)), 1)
+    }
+}
+
 impl From<u8> for TinyCount {
     fn from(value: u8) -> Self {
         Self(value)
@@ -65,6 +81,22 @@ impl HfdbSerializableDatatype for SmallCount {
     }
 }
 
+impl HfdbKeyDatatype for SmallCount {
+    fn key_length(&self) -> usize {
+        2
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        buffer[..2].copy_from_slice(&self.0.to_be_bytes());
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let mut bytes = [0u8; 2];
+        bytes.copy_from_slice(&buffer[..2]);
+        (Self(u16::from_be_bytes(bytes)), 2)
+    }
+}
+
 impl From<u16> for SmallCount {
     fn from(value: u16) -> Self {
         Self(value)
@@ -112,6 +144,22 @@ impl HfdbSerializableDatatype for Count {
     }
 }
 
+impl HfdbKeyDatatype for Count {
+    fn key_length(&self) -> usize {
+        4
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        buffer[..4].copy_from_slice(&self.0.to_be_bytes());
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&buffer[..4]);
+        (Self(u32::from_be_bytes(bytes)), 4)
+    }
+}
+
 impl From<u32> for Count {
     fn from(value: u32) -> Self {
         Self(value)
@@ -160,6 +208,22 @@ impl HfdbSerializableDatatype for BigCount {
     }
 }
 
+impl HfdbKeyDatatype for BigCount {
+    fn key_length(&self) -> usize {
+        8
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        buffer[..8].copy_from_slice(&self.0.to_be_bytes());
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buffer[..8]);
+        (Self(u64::from_be_bytes(bytes)), 8)
+    }
+}
+
 impl From<u64> for BigCount {
     fn from(value: u64) -> Self {
         Self(value)
@@ -208,6 +272,22 @@ impl HfdbSerializableDatatype for HugeCount {
     }
 }
 
+impl HfdbKeyDatatype for HugeCount {
+    fn key_length(&self) -> usize {
+        16
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        buffer[..16].copy_from_slice(&self.0.to_be_bytes());
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&buffer[..16]);
+        (Self(u128::from_be_bytes(bytes)), 16)
+    }
+}
+
 impl From<u128> for HugeCount {
     fn from(value: u128) -> Self {
         Self(value)
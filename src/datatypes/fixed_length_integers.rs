@@ -1,4 +1,4 @@
-use crate::datatypes::HfdbSerializableDatatype;
+use crate::datatypes::{HfdbKeyDatatype, HfdbSerializableDatatype};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct TinyInteger(i8);
@@ -17,6 +17,23 @@ impl HfdbSerializableDatatype for TinyInteger {
     }
 }
 
+/// Big endian with inverted sign bit, so negative values sort first
+impl HfdbKeyDatatype for TinyInteger {
+    fn key_length(&self) -> usize {
+        1
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        buffer[..1].copy_from_slice(&((self.0 as u8) ^ (1 << (8 - 1))).to_be_bytes());
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let mut bytes = [0u8; 1];
+        bytes.copy_from_slice(&buffer[..1]);
+        (Self((u8::from_be_bytes(bytes) ^ (1 << (8 - 1))) as i8), 1)
+    }
+}
+
 impl From<i8> for TinyInteger {
     fn from(value: i8) -> Self {
         Self(value)
@@ -46,6 +63,25 @@ impl HfdbSerializableDatatype for SmallInteger {
     }
 }
 
+impl HfdbKeyDatatype for SmallInteger {
+    fn key_length(&self) -> usize {
+        2
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        buffer[..2].copy_from_slice(&((self.0 as u16) ^ (1 << (16 - 1))).to_be_bytes());
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let mut bytes = [0u8; 2];
+        bytes.copy_from_slice(&buffer[..2]);
+        (
+            Self((u16::from_be_bytes(bytes) ^ (1 << (16 - 1))) as i16),
+            2,
+        )
+    }
+}
+
 impl From<i16> for SmallInteger {
     fn from(value: i16) -> Self {
         Self(value)
@@ -75,6 +111,25 @@ impl HfdbSerializableDatatype for Integer {
     }
 }
 
+impl HfdbKeyDatatype for Integer {
+    fn key_length(&self) -> usize {
+        4
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        buffer[..4].copy_from_slice(&((self.0 as u32) ^ (1 << (32 - 1))).to_be_bytes());
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let mut bytes = [0u8; 4];
+        bytes.copy_from_slice(&buffer[..4]);
+        (
+            Self((u32::from_be_bytes(bytes) ^ (1 << (32 - 1))) as i32),
+            4,
+        )
+    }
+}
+
 impl From<i32> for Integer {
     fn from(value: i32) -> Self {
         Self(value)
@@ -105,6 +160,25 @@ impl HfdbSerializableDatatype for BigInteger {
     }
 }
 
+impl HfdbKeyDatatype for BigInteger {
+    fn key_length(&self) -> usize {
+        8
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        buffer[..8].copy_from_slice(&((self.0 as u64) ^ (1 << (64 - 1))).to_be_bytes());
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let mut bytes = [0u8; 8];
+        bytes.copy_from_slice(&buffer[..8]);
+        (
+            Self((u64::from_be_bytes(bytes) ^ (1 << (64 - 1))) as i64),
+            8,
+        )
+    }
+}
+
 impl From<i64> for BigInteger {
     fn from(value: i64) -> Self {
         Self(value)
@@ -135,6 +209,25 @@ impl HfdbSerializableDatatype for HugeInteger {
     }
 }
 
+impl HfdbKeyDatatype for HugeInteger {
+    fn key_length(&self) -> usize {
+        16
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        buffer[..16].copy_from_slice(&((self.0 as u128) ^ (1 << (128 - 1))).to_be_bytes());
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&buffer[..16]);
+        (
+            Self((u128::from_be_bytes(bytes) ^ (1 << (128 - 1))) as i128),
+            16,
+        )
+    }
+}
+
 impl From<i128> for HugeInteger {
     fn from(value: i128) -> Self {
         Self(value)
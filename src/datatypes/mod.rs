@@ -1,5 +1,6 @@
 pub mod fixed_length_counts;
 pub mod fixed_length_integers;
+pub mod varbinary;
 pub mod varchar;
 pub mod varcount;
 pub mod varint;
@@ -11,6 +12,82 @@ pub trait HfdbSerializableDatatype {
     fn deserialize(buffer: &[u8]) -> Self;
 }
 
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum SortOrder {
+    Ascending,
+    Descending,
+}
+
+/// Order preserving (memcomparable) key encoding: comparing the encoded bytes lexicographically
+/// gives the order of the values. The encodings are self delimiting, so keys can be concatenated
+/// (see Varpair) and still compare column by column. The descending encoding is the ascending one
+/// with all bits inverted.
+pub trait HfdbKeyDatatype: Sized {
+    fn key_length(&self) -> usize;
+    fn serialize_key_ascending(&self, buffer: &mut [u8]);
+    /// Value and the number of bytes it was encoded with
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize);
+
+    fn serialize_key(&self, order: SortOrder, buffer: &mut [u8]) {
+        self.serialize_key_ascending(buffer);
+        if order == SortOrder::Descending {
+            invert(&mut buffer[..self.key_length()]);
+        }
+    }
+
+    fn deserialize_key(order: SortOrder, buffer: &[u8]) -> (Self, usize) {
+        match order {
+            SortOrder::Ascending => Self::deserialize_key_ascending(buffer),
+            SortOrder::Descending => {
+                let mut inverted = buffer.to_vec();
+                invert(&mut inverted);
+                Self::deserialize_key_ascending(&inverted)
+            }
+        }
+    }
+}
+
+fn invert(buffer: &mut [u8]) {
+    buffer.iter_mut().for_each(|byte| *byte = !*byte);
+}
+
+/// Escapes zero bytes (as 0x00 0xFF) and terminates with 0x00 0x01, so that a string sorts before
+/// all strings it is a prefix of
+fn serialize_key_bytes(data: &[u8], buffer: &mut [u8]) {
+    let mut offset = 0;
+    for byte in data {
+        buffer[offset] = *byte;
+        offset += 1;
+        if *byte == 0 {
+            buffer[offset] = 0xFF;
+            offset += 1;
+        }
+    }
+    buffer[offset..offset + 2].copy_from_slice(&[0x00, 0x01]);
+}
+
+fn key_bytes_length(data: &[u8]) -> usize {
+    data.len() + data.iter().filter(|byte| **byte == 0).count() + 2
+}
+
+fn deserialize_key_bytes(buffer: &[u8]) -> (Vec<u8>, usize) {
+    let mut data = vec![];
+    let mut offset = 0;
+    loop {
+        match (buffer[offset], buffer[offset + 1]) {
+            (0x00, 0x01) => return (data, offset + 2),
+            (0x00, _) => {
+                data.push(0);
+                offset += 2;
+            }
+            (byte, _) => {
+                data.push(byte);
+                offset += 1;
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use crate::datatypes::fixed_length_counts::{
@@ -19,11 +96,12 @@ mod tests {
     use crate::datatypes::fixed_length_integers::{
         BigInteger, HugeInteger, Integer, SmallInteger, TinyInteger,
     };
+    use crate::datatypes::varbinary::Varbinary;
     use crate::datatypes::varchar::Varchar;
     use crate::datatypes::varcount::Varcount;
     use crate::datatypes::varint::Varint;
     use crate::datatypes::varpair::Varpair;
-    use crate::datatypes::HfdbSerializableDatatype;
+    use crate::datatypes::{HfdbKeyDatatype, HfdbSerializableDatatype, SortOrder};
     use std::fmt::Debug;
 
     fn check_serialize_deserialize<T>(buffer: &mut [u8], value: T)
@@ -96,6 +174,14 @@ mod tests {
         check_serialize_deserialize(&mut buffer, Varchar::from("abc"));
     }
 
+    #[test]
+    fn test_serialize_deserialize_varbinary() {
+        let mut buffer = [0u8; 300];
+        check_serialize_deserialize(&mut buffer, Varbinary::from(vec![]));
+        check_serialize_deserialize(&mut buffer, Varbinary::from(vec![0u8, 255, 42]));
+        check_serialize_deserialize(&mut buffer, Varbinary::from(vec![7u8; 200]));
+    }
+
     #[test]
     fn test_serialize_deserialize_varpair() {
         let mut buffer = [0u8; 100];
@@ -152,4 +238,139 @@ mod tests {
         check_serialize_deserialize(&mut buffer, HugeCount::from(u128::MIN));
         check_serialize_deserialize(&mut buffer, HugeCount::from(u128::MAX));
     }
+
+    /// Checks values given in ascending order keep their order when encoded as keys
+    fn check_key_order<T>(values: Vec<T>)
+    where
+        T: HfdbKeyDatatype + Debug + Eq,
+    {
+        for order in [SortOrder::Ascending, SortOrder::Descending] {
+            let keys: Vec<Vec<u8>> = values
+                .iter()
+                .map(|value| {
+                    let mut key = vec![0u8; value.key_length()];
+                    value.serialize_key(order, &mut key);
+                    key
+                })
+                .collect();
+            for (value, key) in values.iter().zip(&keys) {
+                let mut buffer = key.clone();
+                buffer.extend([0x42u8, 0x00]);
+                assert_eq!(
+                    T::deserialize_key(order, &buffer),
+                    (T::deserialize_key(order, key).0, key.len())
+                );
+                assert_eq!(&T::deserialize_key(order, key).0, value, "{:?}", order);
+            }
+            for (pair, values) in keys.windows(2).zip(values.windows(2)) {
+                match order {
+                    SortOrder::Ascending => assert!(pair[0] < pair[1], "{:?}", values),
+                    SortOrder::Descending => assert!(pair[0] > pair[1], "{:?}", values),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_key_order() {
+        check_key_order(
+            vec![i8::MIN, -1, 0, 1, i8::MAX]
+                .into_iter()
+                .map(TinyInteger::from)
+                .collect(),
+        );
+        check_key_order(
+            vec![i16::MIN, -300, -1, 0, 300, i16::MAX]
+                .into_iter()
+                .map(SmallInteger::from)
+                .collect(),
+        );
+        check_key_order(
+            vec![i32::MIN, -70000, -1, 0, 1, 70000, i32::MAX]
+                .into_iter()
+                .map(Integer::from)
+                .collect(),
+        );
+        check_key_order(
+            vec![i64::MIN, -1, 0, i64::MAX]
+                .into_iter()
+                .map(BigInteger::from)
+                .collect(),
+        );
+        check_key_order(
+            vec![i128::MIN, -1, 0, i128::MAX]
+                .into_iter()
+                .map(HugeInteger::from)
+                .collect(),
+        );
+        check_key_order(
+            vec![0u8, 1, u8::MAX]
+                .into_iter()
+                .map(TinyCount::from)
+                .collect(),
+        );
+        check_key_order(
+            vec![0u16, 256, u16::MAX]
+                .into_iter()
+                .map(SmallCount::from)
+                .collect(),
+        );
+        check_key_order(
+            vec![0u32, 256, u32::MAX]
+                .into_iter()
+                .map(Count::from)
+                .collect(),
+        );
+        check_key_order(
+            vec![0u64, 256, u64::MAX]
+                .into_iter()
+                .map(BigCount::from)
+                .collect(),
+        );
+        check_key_order(
+            vec![0u128, 256, u128::MAX]
+                .into_iter()
+                .map(HugeCount::from)
+                .collect(),
+        );
+
+        let mut varcounts = vec![0u64];
+        let mut varints = vec![i64::MIN, -1, 0, i64::MAX];
+        for power in 0..63 {
+            let nth_power = 1u64 << power;
+            varcounts.extend([nth_power - 1, nth_power, nth_power + 1]);
+            varints.extend([
+                nth_power as i64,
+                (nth_power as i64) - 1,
+                -(nth_power as i64),
+                -(nth_power as i64) - 1,
+            ]);
+        }
+        varcounts.extend([u64::MAX - 1, u64::MAX]);
+        varcounts.sort();
+        varcounts.dedup();
+        varints.sort();
+        varints.dedup();
+        check_key_order(varcounts.into_iter().map(Varcount::from).collect());
+        check_key_order(varints.into_iter().map(Varint::from).collect());
+
+        check_key_order(
+            vec!["", "\0", "\0\0", "\0a", "a", "a\0", "ab", "b"]
+                .into_iter()
+                .map(Varchar::from)
+                .collect(),
+        );
+        check_key_order(
+            vec![vec![], vec![0u8], vec![0, 255], vec![1], vec![255, 255]]
+                .into_iter()
+                .map(Varbinary::from)
+                .collect(),
+        );
+        check_key_order(vec![
+            Varpair::from((Varint::from(-1), Varchar::from("z"))),
+            Varpair::from((Varint::from(0), Varchar::from(""))),
+            Varpair::from((Varint::from(0), Varchar::from("a"))),
+            Varpair::from((Varint::from(1), Varchar::from(""))),
+        ]);
+    }
 }
@@ -0,0 +1,72 @@
+use crate::datatypes::varcount::Varcount;
+use crate::datatypes::{
+    deserialize_key_bytes, key_bytes_length, serialize_key_bytes, HfdbKeyDatatype,
+    HfdbSerializableDatatype,
+};
+
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Varbinary {
+    varlength: Varcount,
+    data: Vec<u8>,
+}
+
+impl Varbinary {
+    pub fn as_slice(&self) -> &[u8] {
+        self.data.as_slice()
+    }
+}
+
+impl HfdbSerializableDatatype for Varbinary {
+    fn serialized_length(&self) -> usize {
+        self.varlength.serialized_length() + self.data.len()
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) {
+        self.varlength.serialize(buffer);
+        let offset_data = self.varlength.serialized_length();
+        buffer[offset_data..offset_data + self.data.len()].copy_from_slice(self.data.as_slice());
+    }
+
+    fn deserialize(buffer: &[u8]) -> Self {
+        let varlength = Varcount::deserialize(buffer);
+        let offset_data = varlength.serialized_length();
+        let data = buffer[offset_data..offset_data + usize::from(&varlength)].to_vec();
+        Self { varlength, data }
+    }
+}
+
+impl HfdbKeyDatatype for Varbinary {
+    fn key_length(&self) -> usize {
+        key_bytes_length(self.data.as_slice())
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        serialize_key_bytes(self.data.as_slice(), buffer)
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let (data, length) = deserialize_key_bytes(buffer);
+        (Self::from(data), length)
+    }
+}
+
+impl From<Vec<u8>> for Varbinary {
+    fn from(value: Vec<u8>) -> Self {
+        Self {
+            varlength: Varcount::from(value.len()),
+            data: value,
+        }
+    }
+}
+
+impl From<&[u8]> for Varbinary {
+    fn from(value: &[u8]) -> Self {
+        Self::from(value.to_vec())
+    }
+}
+
+impl From<&Varbinary> for Vec<u8> {
+    fn from(value: &Varbinary) -> Self {
+        value.data.clone()
+    }
+}
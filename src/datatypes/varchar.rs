@@ -1,5 +1,8 @@
 use crate::datatypes::varcount::Varcount;
-use crate::datatypes::HfdbSerializableDatatype;
+use crate::datatypes::{
+    deserialize_key_bytes, key_bytes_length, serialize_key_bytes, HfdbKeyDatatype,
+    HfdbSerializableDatatype,
+};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Varchar {
@@ -28,6 +31,24 @@ impl HfdbSerializableDatatype for Varchar {
     }
 }
 
+impl HfdbKeyDatatype for Varchar {
+    fn key_length(&self) -> usize {
+        key_bytes_length(self.data.as_bytes())
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        serialize_key_bytes(self.data.as_bytes(), buffer)
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let (data, length) = deserialize_key_bytes(buffer);
+        (
+            Self::from(String::from_utf8_lossy(&data).to_string()),
+            length,
+        )
+    }
+}
+
 impl From<String> for Varchar {
     fn from(value: String) -> Self {
         Self {
@@ -1,4 +1,4 @@
-use crate::datatypes::HfdbSerializableDatatype;
+use crate::datatypes::{HfdbKeyDatatype, HfdbSerializableDatatype};
 use std::num::NonZeroUsize;
 
 #[derive(Debug, Eq, PartialEq, Copy, Clone)]
@@ -72,6 +72,30 @@ impl HfdbSerializableDatatype for Varcount {
     }
 }
 
+/// Number of significant bytes followed by these bytes big endian: a longer value is a larger one
+impl HfdbKeyDatatype for Varcount {
+    fn key_length(&self) -> usize {
+        1 + significant_bytes(self.0)
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        let length = significant_bytes(self.0);
+        buffer[0] = length as u8;
+        buffer[1..=length].copy_from_slice(&self.0.to_be_bytes()[8 - length..]);
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let length = buffer[0] as usize;
+        let mut bytes = [0u8; 8];
+        bytes[8 - length..].copy_from_slice(&buffer[1..=length]);
+        (Self(u64::from_be_bytes(bytes)), 1 + length)
+    }
+}
+
+pub(crate) fn significant_bytes(value: u64) -> usize {
+    (64 - value.leading_zeros() as usize).div_ceil(8)
+}
+
 impl From<usize> for Varcount {
     fn from(value: usize) -> Self {
         Self(value as u64)
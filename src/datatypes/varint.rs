@@ -1,4 +1,5 @@
-use crate::datatypes::HfdbSerializableDatatype;
+use crate::datatypes::varcount::significant_bytes;
+use crate::datatypes::{HfdbKeyDatatype, HfdbSerializableDatatype};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Varint(i64);
@@ -136,6 +137,40 @@ impl HfdbSerializableDatatype for Varint {
     }
 }
 
+/// Like Varcount, with the number of significant bytes in a prefix byte counting up from 0x80 for
+/// positive values and down from 0x7F for negative ones (significant bytes of their complement)
+impl HfdbKeyDatatype for Varint {
+    fn key_length(&self) -> usize {
+        1 + Self::key_bytes(self.0)
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        let length = Self::key_bytes(self.0);
+        buffer[0] = if self.0 < 0 {
+            0x7F - length as u8
+        } else {
+            0x80 + length as u8
+        };
+        buffer[1..=length].copy_from_slice(&self.0.to_be_bytes()[8 - length..]);
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let (negative, length) = match buffer[0] {
+            prefix if prefix < 0x80 => (true, (0x7F - prefix) as usize),
+            prefix => (false, (prefix - 0x80) as usize),
+        };
+        let mut bytes = if negative { [0xFFu8; 8] } else { [0u8; 8] };
+        bytes[8 - length..].copy_from_slice(&buffer[1..=length]);
+        (Self(i64::from_be_bytes(bytes)), 1 + length)
+    }
+}
+
+impl Varint {
+    fn key_bytes(value: i64) -> usize {
+        significant_bytes(if value < 0 { !value } else { value } as u64)
+    }
+}
+
 impl From<i8> for Varint {
     fn from(value: i8) -> Self {
         Self(value as i64)
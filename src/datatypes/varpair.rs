@@ -1,4 +1,4 @@
-use crate::datatypes::HfdbSerializableDatatype;
+use crate::datatypes::{HfdbKeyDatatype, HfdbSerializableDatatype};
 
 #[derive(Debug, Eq, PartialEq)]
 pub struct Varpair<T, U>
@@ -42,6 +42,29 @@ where
     }
 }
 
+/// Key of the left value followed by the key of the right one, so pairs compare lexicographically
+impl<T, U> HfdbKeyDatatype for Varpair<T, U>
+where
+    T: HfdbSerializableDatatype + HfdbKeyDatatype,
+    U: HfdbSerializableDatatype + HfdbKeyDatatype,
+{
+    fn key_length(&self) -> usize {
+        self.left.key_length() + self.right.key_length()
+    }
+
+    fn serialize_key_ascending(&self, buffer: &mut [u8]) {
+        self.left.serialize_key_ascending(buffer);
+        self.right
+            .serialize_key_ascending(&mut buffer[self.left.key_length()..]);
+    }
+
+    fn deserialize_key_ascending(buffer: &[u8]) -> (Self, usize) {
+        let (left, left_length) = T::deserialize_key_ascending(buffer);
+        let (right, right_length) = U::deserialize_key_ascending(&buffer[left_length..]);
+        (Self { left, right }, left_length + right_length)
+    }
+}
+
 impl<T, U> From<(T, U)> for Varpair<T, U>
 where
     T: HfdbSerializableDatatype,
@@ -1,7 +1,13 @@
 use crate::file_management::block_id::DbFilename;
-use crate::file_management::file_manager::{FileManager, FileManagerBuilder, IoError};
+use crate::file_management::file_manager::{Durability, FileManager, FileManagerBuilder};
 use crate::memory_management::buffer_manager::{BufferManager, BufferManagerBuilder};
 use crate::memory_management::log_manager::{LogManager, LogManagerBuilder};
+use crate::metadata_management::catalog::Catalog;
+use crate::transaction_management::lock_table::{LockTable, LockTableBuilder};
+use crate::transaction_management::recovery_manager::{
+    RecoveryError, RecoveryManager, RecoveryStatistics,
+};
+use crate::transaction_management::transaction_manager::TransactionManager;
 use std::num::NonZeroUsize;
 use std::time::Duration;
 
@@ -10,12 +16,16 @@ pub struct HanfriedDb {
     pub file_manager: FileManager,
     pub log_manager: LogManager,
     pub buffer_manager: BufferManager,
+    pub recovery_statistics: RecoveryStatistics,
+    pub transaction_manager: TransactionManager,
+    pub catalog: Catalog,
 }
 
 pub struct HanfriedDbBuilder {
     file_manager_builder: FileManagerBuilder,
     log_manager_builder: LogManagerBuilder,
     buffer_manager_builder: BufferManagerBuilder,
+    lock_table_builder: LockTableBuilder,
 }
 
 impl HanfriedDbBuilder {
@@ -24,6 +34,7 @@ impl HanfriedDbBuilder {
             file_manager_builder: FileManagerBuilder::new(db_directory),
             log_manager_builder: LogManagerBuilder::new(),
             buffer_manager_builder: BufferManagerBuilder::new(),
+            lock_table_builder: LockTableBuilder::new(),
         }
     }
 
@@ -32,6 +43,7 @@ impl HanfriedDbBuilder {
             file_manager_builder: FileManagerBuilder::unittest(sub_directory_name),
             log_manager_builder: LogManagerBuilder::unittest(),
             buffer_manager_builder: BufferManagerBuilder::unittest(),
+            lock_table_builder: LockTableBuilder::unittest(),
         }
     }
 
@@ -40,6 +52,12 @@ impl HanfriedDbBuilder {
         self
     }
 
+    /// When written data reaches stable storage, by default every commit survives a power loss
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.file_manager_builder = self.file_manager_builder.durability(durability);
+        self
+    }
+
     pub fn log_manager(mut self, config: fn(LogManagerBuilder) -> LogManagerBuilder) -> Self {
         self.log_manager_builder = config(self.log_manager_builder);
         self
@@ -53,17 +71,44 @@ impl HanfriedDbBuilder {
         self
     }
 
-    pub fn build(self) -> HanfriedDb {
-        let file_manager = self.file_manager_builder.build().unwrap();
-        let log_manager = self.log_manager_builder.build(&file_manager).unwrap();
+    pub fn lock_table(mut self, config: fn(LockTableBuilder) -> LockTableBuilder) -> Self {
+        self.lock_table_builder = config(self.lock_table_builder);
+        self
+    }
+
+    /// Opens the database, recovering it from the log
+    pub fn build(self) -> Result<HanfriedDb, RecoveryError> {
+        let file_manager = self
+            .file_manager_builder
+            .build()
+            .map_err(RecoveryError::StdIoError)?;
+        let log_manager = self
+            .log_manager_builder
+            .build(&file_manager)
+            .map_err(RecoveryError::StdIoError)?;
         let buffer_manager = self
             .buffer_manager_builder
             .build(&file_manager, &log_manager);
-        HanfriedDb {
+        let recovery_statistics =
+            RecoveryManager::new(&file_manager, &log_manager, &buffer_manager).recover()?;
+        let transaction_manager = TransactionManager::new(
+            &file_manager,
+            &log_manager,
+            &buffer_manager,
+            &self
+                .lock_table_builder
+                .build(buffer_manager.wait_for_graph()),
+            recovery_statistics.max_transaction_number,
+        );
+        let catalog = HanfriedDb::bootstrap_catalog(&transaction_manager)?;
+        Ok(HanfriedDb {
             file_manager,
             log_manager,
             buffer_manager,
-        }
+            recovery_statistics,
+            transaction_manager,
+            catalog,
+        })
     }
 }
 
@@ -74,19 +119,64 @@ impl HanfriedDb {
         log_file: String,
         pool_size: usize,
         max_open_files: usize,
-    ) -> Result<Self, IoError> {
+        durability: Durability,
+    ) -> Result<Self, RecoveryError> {
         let fm = FileManager::new(
             db_directory,
             NonZeroUsize::new(block_size).unwrap(),
             NonZeroUsize::new(max_open_files).unwrap(),
+            durability,
         )
-        .unwrap();
-        let lm = LogManager::new(&fm, &DbFilename::from(log_file))?;
+        .map_err(RecoveryError::StdIoError)?;
+        let lm =
+            LogManager::new(&fm, &DbFilename::from(log_file)).map_err(RecoveryError::StdIoError)?;
         let bm = BufferManager::new(&fm, &lm, pool_size, Duration::from_secs(10));
+        let recovery_statistics = RecoveryManager::new(&fm, &lm, &bm).recover()?;
+        let lt = LockTable::new(Duration::from_secs(10), bm.wait_for_graph());
+        let tm = TransactionManager::new(
+            &fm,
+            &lm,
+            &bm,
+            &lt,
+            recovery_statistics.max_transaction_number,
+        );
+        let catalog = Self::bootstrap_catalog(&tm)?;
         Ok(Self {
             file_manager: fm,
             log_manager: lm,
             buffer_manager: bm,
+            recovery_statistics,
+            transaction_manager: tm,
+            catalog,
         })
     }
+
+    /// Catalog with its tables created if the db directory is empty
+    fn bootstrap_catalog(
+        transaction_manager: &TransactionManager,
+    ) -> Result<Catalog, RecoveryError> {
+        let catalog = Catalog::new();
+        catalog
+            .bootstrap(transaction_manager)
+            .map_err(RecoveryError::CatalogError)?;
+        Ok(catalog)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::transaction_management::recovery_manager::RecoveryError;
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn test_build_reports_catalog_errors() {
+        init_logging();
+        // Too small for the records of the catalog tables
+        let result = HanfriedDbBuilder::unittest("hfdb_catalog_error")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(40).unwrap()))
+            .build();
+        assert!(matches!(result, Err(RecoveryError::CatalogError(_))));
+    }
 }
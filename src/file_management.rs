@@ -1,3 +1,4 @@
 pub mod block_id;
 pub mod file_manager;
 pub mod page;
+pub mod temp_file;
@@ -1,7 +1,7 @@
 use std::fmt::Display;
 use std::sync::Arc;
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct DbFilename(Arc<String>);
 
 // Todo: Probably put it into a BTreeMap or ResourceSyncCache with a number to it
@@ -31,7 +31,7 @@ impl From<String> for DbFilename {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct BlockId {
     filename: DbFilename,
     block_number: usize,
@@ -1,26 +1,55 @@
 use crate::file_management::block_id::{BlockId, DbFilename};
 use crate::file_management::page::Page;
+use crate::utils::crc32c::crc32c;
 use crate::utils::sync_resource_cache::SyncResourceCache;
 use log::info;
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
 use std::fs;
 use std::fs::{File, OpenOptions};
 use std::io::{Read, Seek, Write};
 use std::num::NonZeroUsize;
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
+use std::time::Duration;
 
+/// Length of the header in front of each page on disk, holding the CRC-32C checksum of the page
+pub const PAGE_HEADER_LENGTH: usize = 4;
+
+/// When written data reaches stable storage. Writes only hand the data to the operating system,
+/// which survives a crash of the process but not a power loss, unless the file is synced.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Durability {
+    /// Every log flush syncs the log file, so a committed transaction survives a power loss
+    Sync,
+    /// Like Sync, but a log flush waits the given time first, so the transactions committing
+    /// meanwhile share one sync of the log file: more throughput for longer commits
+    GroupCommit(Duration),
+    /// Nothing is synced, for tests only
+    Relaxed,
+}
+
+/// Reads and writes pages as blocks of files in the db directory. On disk a block consists of
+/// the page header and the page of `block_size` bytes.
 #[derive(Debug, Clone)]
 pub struct FileManager {
     db_directory: String,
     pub block_size: NonZeroUsize,
+    durability: Durability,
     file_cache: Arc<SyncResourceCache<String, Arc<Mutex<File>>>>,
+    /// Files written since they were synced last
+    unsynced_files: Arc<Mutex<HashSet<DbFilename>>>,
+    syncs: Arc<AtomicUsize>,
+    temp_files_created: Arc<AtomicUsize>,
 }
 
 pub struct FileManagerBuilder {
     db_directory: String,
     block_size: NonZeroUsize,
     max_open_files: NonZeroUsize,
+    durability: Durability,
+    fresh_db_directory: bool,
 }
 
 impl FileManagerBuilder {
@@ -34,12 +63,16 @@ impl FileManagerBuilder {
             db_directory,
             block_size: Self::DEFAULT_BLOCK_SIZE,
             max_open_files: Self::DEFAULT_MAX_OPEN_FILES,
+            durability: Durability::Sync,
+            fresh_db_directory: false,
         }
     }
 
     pub fn unittest(db_sub_directory: &str) -> Self {
         let db_directory = Self::UNITTEST_DB_DIR;
         Self::new(format!("{db_directory}/{db_sub_directory}"))
+            .durability(Durability::Relaxed)
+            .fresh_db_directory(true)
     }
 
     pub fn block_size(mut self, block_size: NonZeroUsize) -> Self {
@@ -52,20 +85,86 @@ impl FileManagerBuilder {
         self
     }
 
+    pub fn durability(mut self, durability: Durability) -> Self {
+        self.durability = durability;
+        self
+    }
+
+    /// Remove everything inside db_directory before building, so that unittests do not see
+    /// (and recover) data or log files of former test runs
+    pub fn fresh_db_directory(mut self, fresh_db_directory: bool) -> Self {
+        self.fresh_db_directory = fresh_db_directory;
+        self
+    }
+
     pub fn build(self) -> Result<FileManager, IoError> {
-        FileManager::new(self.db_directory, self.block_size, self.max_open_files)
+        let db_root = Path::new(self.db_directory.as_str());
+        if self.fresh_db_directory && db_root.exists() {
+            info!("Remove existing db root: {:?}", db_root);
+            fs::remove_dir_all(db_root).map_err(|error| IoError::Io {
+                error,
+                context: format!("remove existing db root {db_root:?}"),
+            })?;
+        }
+        FileManager::new(
+            self.db_directory,
+            self.block_size,
+            self.max_open_files,
+            self.durability,
+        )
     }
 }
 
 #[derive(Debug)]
-pub struct IoError {
-    error: std::io::Error,
-    context: String,
+pub enum IoError {
+    /// Failed file operation, with what the FileManager was doing
+    Io {
+        error: std::io::Error,
+        context: String,
+    },
+    /// The block to read starts at or behind the end of its file
+    BlockBeyondEndOfFile(BlockId),
+    /// The file ends within the block to read, after `length` bytes of it
+    TruncatedBlock { block: BlockId, length: usize },
+    /// The block read does not match its checksum: it was torn by a crash while being written,
+    /// or changed on disk afterwards
+    ChecksumMismatch {
+        block: BlockId,
+        stored: u32,
+        computed: u32,
+    },
 }
 
 impl Display for IoError {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} context: {}", self.error, self.context)
+        match self {
+            IoError::Io { error, context } => write!(f, "{} context: {}", error, context),
+            IoError::BlockBeyondEndOfFile(block) => write!(
+                f,
+                "block {} beyond end of file {}",
+                block.block_number(),
+                block.filename()
+            ),
+            IoError::TruncatedBlock { block, length } => write!(
+                f,
+                "block {} of file {} truncated to {} bytes",
+                block.block_number(),
+                block.filename(),
+                length
+            ),
+            IoError::ChecksumMismatch {
+                block,
+                stored,
+                computed,
+            } => write!(
+                f,
+                "checksum mismatch of block {} of file {}: stored {:08x}, computed {:08x}",
+                block.block_number(),
+                block.filename(),
+                stored,
+                computed
+            ),
+        }
     }
 }
 
@@ -74,18 +173,31 @@ impl FileManager {
         db_directory: String,
         block_size: NonZeroUsize,
         max_size: NonZeroUsize,
+        durability: Durability,
     ) -> Result<FileManager, IoError> {
         let db_root: &Path = Path::new(db_directory.as_str());
         if !db_root.exists() {
             info!("Create db root: {:?}", db_root);
-            fs::create_dir_all(db_root).map_err(|error| IoError {
+            fs::create_dir_all(db_root).map_err(|error| IoError::Io {
                 error,
                 context: format!("create db root {db_root:?}"),
             })?;
+            if durability != Durability::Relaxed {
+                let parent = db_root
+                    .parent()
+                    .filter(|parent| !parent.as_os_str().is_empty())
+                    .unwrap_or(Path::new("."));
+                File::open(parent)
+                    .and_then(|directory| directory.sync_all())
+                    .map_err(|error| IoError::Io {
+                        error,
+                        context: format!("sync parent directory of db root {db_root:?}"),
+                    })?;
+            }
         }
 
         let temp_files: Vec<PathBuf> = fs::read_dir(db_root)
-            .map_err(|error| IoError {
+            .map_err(|error| IoError::Io {
                 error,
                 context: format!("read_dir db root {db_root:?}"),
             })?
@@ -120,73 +232,252 @@ impl FileManager {
         Ok(FileManager {
             db_directory,
             block_size,
+            durability,
             file_cache: Arc::new(SyncResourceCache::new(usize::from(max_size))),
+            unsynced_files: Arc::new(Mutex::new(HashSet::new())),
+            syncs: Arc::new(AtomicUsize::new(0)),
+            temp_files_created: Arc::new(AtomicUsize::new(0)),
         })
     }
 
+    /// Opens the file, creating it if it does not exist. The db directory is synced after
+    /// creating a file, else the file may be gone after a power loss with all synced data in it.
     fn get_file(&self, filename: &DbFilename) -> Result<Arc<Mutex<File>>, IoError> {
         self.file_cache.get_or_create(filename.to_string(), || {
+            let path = Path::new(self.db_directory.as_str()).join(filename.as_str());
+            let created = !path.exists();
             let f = OpenOptions::new()
                 .read(true)
                 .write(true)
                 .create(true)
                 .truncate(false)
-                .open(Path::new(self.db_directory.as_str()).join(filename.as_str()))
-                .map_err(|error| IoError {
+                .open(path)
+                .map_err(|error| IoError::Io {
                     error,
                     context: format!("get_file open file {}", filename),
                 })?;
+            if created && self.durability != Durability::Relaxed {
+                File::open(self.db_directory.as_str())
+                    .and_then(|directory| directory.sync_all())
+                    .map_err(|error| IoError::Io {
+                        error,
+                        context: format!("get_file sync db directory creating file {}", filename),
+                    })?;
+            }
             Ok(Arc::new(Mutex::new(f)))
         })
     }
 
+    pub fn durability(&self) -> Durability {
+        self.durability
+    }
+
+    fn mark_unsynced(&self, filename: &DbFilename) {
+        if self.durability != Durability::Relaxed {
+            self.unsynced_files.lock().unwrap().insert(filename.clone());
+        }
+    }
+
+    /// Brings the data written to the file to stable storage, unless the durability is relaxed
+    pub fn sync(&self, filename: &DbFilename) -> Result<(), IoError> {
+        if self.durability == Durability::Relaxed {
+            return Ok(());
+        }
+        // Marked as synced before syncing, so writes during the sync mark the file again
+        self.unsynced_files.lock().unwrap().remove(filename);
+        let synced = self.get_file(filename).and_then(|file_binding| {
+            let file = file_binding.lock().unwrap();
+            file.sync_data().map_err(|error| IoError::Io {
+                error,
+                context: format!("sync file {}", filename),
+            })
+        });
+        if synced.is_err() {
+            self.mark_unsynced(filename);
+        }
+        synced?;
+        self.syncs.fetch_add(1, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Syncs all files written since their last sync, so the data written so far survives a power
+    /// loss, as needed before a checkpoint
+    pub fn sync_all(&self) -> Result<(), IoError> {
+        // Each sync unmarks its file, so the files not synced yet stay marked if a sync fails
+        let filenames: Vec<DbFilename> = self
+            .unsynced_files
+            .lock()
+            .unwrap()
+            .iter()
+            .cloned()
+            .collect();
+        for filename in filenames {
+            self.sync(&filename)?;
+        }
+        Ok(())
+    }
+
+    /// Number of syncs done so far
+    pub fn syncs_count(&self) -> usize {
+        self.syncs.load(Ordering::Relaxed)
+    }
+
+    /// New file name with the "temp" prefix, such files are removed when the FileManager is
+    /// created, so temporary files left over by a crash do not pile up
+    pub fn temp_filename(&self) -> DbFilename {
+        DbFilename::from(format!(
+            "temp{}",
+            self.temp_files_created.fetch_add(1, Ordering::Relaxed)
+        ))
+    }
+
+    /// Closes and deletes the file, a file that does not exist is no error
+    pub fn remove(&self, filename: &DbFilename) -> Result<(), IoError> {
+        self.file_cache.remove(&filename.to_string());
+        self.unsynced_files.lock().unwrap().remove(filename);
+        match fs::remove_file(Path::new(self.db_directory.as_str()).join(filename.as_str())) {
+            Err(error) if error.kind() != std::io::ErrorKind::NotFound => Err(IoError::Io {
+                error,
+                context: format!("remove file {}", filename),
+            }),
+            _ => Ok(()),
+        }
+    }
+
     pub fn open_files_count(&self) -> usize {
         self.file_cache.len_open()
     }
 
+    /// Length of a block on disk: the page header followed by the page
+    fn disk_block_size(&self) -> usize {
+        PAGE_HEADER_LENGTH + self.block_size.get()
+    }
+
+    /// Reads the block into the page, verifying its checksum. A block that was never written
+    /// (all zeros) reads as an empty page, a block behind the end of the file is an error.
     pub fn read(&self, block: &BlockId, page: &Page) -> Result<(), IoError> {
         let file_binding = self.get_file(block.filename())?;
         let mut file = file_binding.lock().unwrap();
-        let block_size = self.block_size;
         let seek_from =
-            std::io::SeekFrom::Start((block.block_number() * usize::from(block_size)) as u64);
-        file.seek(seek_from).map_err(|error| IoError {
+            std::io::SeekFrom::Start((block.block_number() * self.disk_block_size()) as u64);
+        file.seek(seek_from).map_err(|error| IoError::Io {
             error,
             context: format!("read seek file block {:?}", block),
         })?;
-        let mut buf: Vec<u8> = vec![0; usize::from(block_size)];
-        let _bytes_read = file.read(&mut buf);
-        page.set_contents(buf.as_slice());
+        let mut buf: Vec<u8> = vec![0; self.disk_block_size()];
+        let bytes_read = read_block(&mut *file, &mut buf).map_err(|error| IoError::Io {
+            error,
+            context: format!("read file block {:?}", block),
+        })?;
+        match bytes_read {
+            0 => return Err(IoError::BlockBeyondEndOfFile(block.clone())),
+            length if length < buf.len() => {
+                return Err(IoError::TruncatedBlock {
+                    block: block.clone(),
+                    length,
+                })
+            }
+            _ => {}
+        }
+        Self::verify_checksum(block, &buf)?;
+        page.set_contents(&buf[PAGE_HEADER_LENGTH..]);
         Ok(())
     }
 
+    /// Writes the page with a header holding its checksum
     pub fn write(&self, block: &BlockId, page: &Page) -> Result<(), IoError> {
+        let contents = page.get_contents();
+        let mut buf = Vec::with_capacity(self.disk_block_size());
+        buf.extend_from_slice(&crc32c(&contents).to_le_bytes());
+        buf.extend_from_slice(&contents);
         let file_binding = self.get_file(block.filename())?;
         let mut file = file_binding.lock().unwrap();
         // println!("Locked file {:?} {:?}", block, file);
         let seek_from =
-            std::io::SeekFrom::Start((block.block_number() * usize::from(self.block_size)) as u64);
-        file.seek(seek_from).map_err(|error| IoError {
+            std::io::SeekFrom::Start((block.block_number() * self.disk_block_size()) as u64);
+        file.seek(seek_from).map_err(|error| IoError::Io {
             error,
             context: format!("write seek file block {:?}", block),
         })?;
-        file.write_all(page.get_contents().as_slice())
-            .map_err(|error| IoError {
+        file.write_all(buf.as_slice())
+            .map_err(|error| IoError::Io {
                 error,
                 context: format!("write write_all page contents file block {:?}", block),
             })?;
-        file.flush().map_err(|error| IoError {
+        file.flush().map_err(|error| IoError::Io {
             error,
             context: format!("write flush file block {:?}", block),
         })?;
+        self.mark_unsynced(block.filename());
+        Ok(())
+    }
+
+    fn verify_checksum(block: &BlockId, disk_block: &[u8]) -> Result<(), IoError> {
+        let (header, contents) = disk_block.split_at(PAGE_HEADER_LENGTH);
+        let stored = u32::from_le_bytes(header.try_into().unwrap());
+        if stored == 0 && contents.iter().all(|&byte| byte == 0) {
+            return Ok(());
+        }
+        let computed = crc32c(contents);
+        if stored != computed {
+            return Err(IoError::ChecksumMismatch {
+                block: block.clone(),
+                stored,
+                computed,
+            });
+        }
         Ok(())
     }
 
+    /// Verifies the checksums of all blocks of all files in the db directory and returns the
+    /// corrupt blocks. Only blocks written to disk are verified, so flush the buffers first. An
+    /// incomplete block at the end of a file counts as corrupt, too.
+    pub fn verify_files(&self) -> Result<Vec<BlockId>, IoError> {
+        let db_root = Path::new(self.db_directory.as_str());
+        let mut filenames: Vec<String> = fs::read_dir(db_root)
+            .map_err(|error| IoError::Io {
+                error,
+                context: format!("verify_files read_dir db root {db_root:?}"),
+            })?
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_ok_and(|file_type| file_type.is_file()))
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .collect();
+        filenames.sort();
+
+        let mut corrupt_blocks = vec![];
+        let mut buf: Vec<u8> = vec![0; self.disk_block_size()];
+        for filename in filenames {
+            let filename = DbFilename::from(filename);
+            let file_binding = self.get_file(&filename)?;
+            let mut file = file_binding.lock().unwrap();
+            let io_error = |error| IoError::Io {
+                error,
+                context: format!("verify_files read file {}", filename),
+            };
+            let length = file.seek(std::io::SeekFrom::End(0)).map_err(io_error)? as usize;
+            file.rewind().map_err(io_error)?;
+            for block_number in 0..length.div_ceil(self.disk_block_size()) {
+                let block = BlockId::new(filename.clone(), block_number);
+                let remaining = length - block_number * self.disk_block_size();
+                if remaining < self.disk_block_size() {
+                    corrupt_blocks.push(block);
+                    break;
+                }
+                file.read_exact(&mut buf).map_err(io_error)?;
+                if Self::verify_checksum(&block, &buf).is_err() {
+                    corrupt_blocks.push(block);
+                }
+            }
+        }
+        Ok(corrupt_blocks)
+    }
+
     pub fn block_length(&self, filename: &DbFilename) -> Result<usize, IoError> {
         // let mut file = self.get_file(filename)?;
         let file_binding = self.get_file(filename).unwrap();
         let mut file = file_binding.lock().unwrap();
-        self._block_length(&mut file).map_err(|error| IoError {
+        self._block_length(&mut file).map_err(|error| IoError::Io {
             error,
             context: format!("block length {}", filename),
         })
@@ -194,36 +485,75 @@ impl FileManager {
 
     pub fn _block_length(&self, file: &mut File) -> Result<usize, std::io::Error> {
         let eof_offset = file.seek(std::io::SeekFrom::End(0))?;
-        Ok(eof_offset as usize / self.block_size)
+        Ok(eof_offset as usize / self.disk_block_size())
     }
 
+    /// Cuts the file after the given number of blocks, dropping an incomplete block at its end
+    pub fn truncate(&self, filename: &DbFilename, block_length: usize) -> Result<(), IoError> {
+        let file_binding = self.get_file(filename)?;
+        let file = file_binding.lock().unwrap();
+        file.set_len((block_length * self.disk_block_size()) as u64)
+            .map_err(|error| IoError::Io {
+                error,
+                context: format!("truncate file {} to {} blocks", filename, block_length),
+            })?;
+        self.mark_unsynced(filename);
+        Ok(())
+    }
+
+    /// Extends the file by an empty block, so concurrent appends always get different blocks
     pub fn append(&self, filename: &DbFilename) -> Result<BlockId, IoError> {
         let file_binding = self.get_file(filename).unwrap();
         let mut file = file_binding.lock().unwrap();
         let block = BlockId::new(
             filename.clone(),
-            self._block_length(&mut file).map_err(|error| IoError {
+            self._block_length(&mut file).map_err(|error| IoError::Io {
                 error,
                 context: format!("append block length filename {}", filename),
             })?,
         );
         let seek_from =
-            std::io::SeekFrom::Start((block.block_number() * usize::from(self.block_size)) as u64);
-        file.seek(seek_from).map_err(|error| IoError {
+            std::io::SeekFrom::Start((block.block_number() * self.disk_block_size()) as u64);
+        file.seek(seek_from).map_err(|error| IoError::Io {
             error,
             context: format!("block length {}", filename),
         })?;
+        file.write_all(vec![0u8; self.disk_block_size()].as_slice())
+            .map_err(|error| IoError::Io {
+                error,
+                context: format!("append write empty block {:?}", block),
+            })?;
+        self.mark_unsynced(filename);
         Ok(block)
     }
 }
 
+/// Reads until the buffer is full or the end of the file, a single read may return fewer bytes
+/// than available. Returns the number of bytes read.
+fn read_block(reader: &mut impl Read, buf: &mut [u8]) -> std::io::Result<usize> {
+    let mut bytes_read = 0;
+    while bytes_read < buf.len() {
+        match reader.read(&mut buf[bytes_read..]) {
+            Ok(0) => break,
+            Ok(length) => bytes_read += length,
+            Err(error) if error.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(error) => return Err(error),
+        }
+    }
+    Ok(bytes_read)
+}
+
 #[cfg(test)]
 mod tests {
     use crate::datatypes::varcount::Varcount;
     use crate::datatypes::varpair::Varpair;
     use crate::file_management::block_id::{BlockId, DbFilename};
-    use crate::file_management::file_manager::FileManagerBuilder;
+    use crate::file_management::file_manager::{
+        read_block, FileManagerBuilder, IoError, PAGE_HEADER_LENGTH,
+    };
     use crate::file_management::page::Page;
+    use std::fs::OpenOptions;
+    use std::io::{Cursor, Read, Seek, SeekFrom, Write};
     use std::num::NonZeroUsize;
     use std::sync::atomic::AtomicBool;
     use std::sync::Arc;
@@ -268,9 +598,13 @@ mod tests {
                 let fm = file_manager.clone();
                 parallel_read_threads.push(thread::spawn(move || {
                     let block = BlockId::new(fname, block_nr);
-                    let mut page = Page::new(TEST_FILES_BLOCKSIZE);
+                    let page = Page::new(TEST_FILES_BLOCKSIZE);
                     loop {
-                        fm.read(&block, &mut page).unwrap();
+                        match fm.read(&block, &page) {
+                            // The block is not written yet and the page is still empty
+                            Ok(()) | Err(IoError::BlockBeyondEndOfFile(_)) => {}
+                            Err(e) => panic!("{}", e),
+                        }
                         let (&file_nr_got, &block_nr_got) =
                             page.get::<Varpair<Varcount, Varcount>>(0).as_tuple();
                         // let file_nr_got = page.get_i32(0);
@@ -308,6 +642,10 @@ mod tests {
                 .unwrap(),
         );
 
+        for file_nr in 0..TEST_FILES_SOME {
+            let fname = DbFilename::from(format!("testfile_{}", file_nr));
+            file_manager.append(&fname).unwrap();
+        }
         let testing_finished = Arc::new(AtomicBool::new(false));
 
         let mut parallel_read_threads_some_files: Vec<JoinHandle<()>> = Vec::new();
@@ -320,11 +658,11 @@ mod tests {
                     thread_nr
                 );
                 loop {
-                    let mut page = Page::new(TEST_FILES_BLOCKSIZE);
+                    let page = Page::new(TEST_FILES_BLOCKSIZE);
                     for file_nr in 0..TEST_FILES_SOME {
                         let fname = DbFilename::from(format!("testfile_{}", file_nr));
                         let block = BlockId::new(fname, 0);
-                        fm.read(&block, &mut page).unwrap();
+                        fm.read(&block, &page).unwrap();
                     }
                     if testing_finished.load(std::sync::atomic::Ordering::Relaxed) {
                         break;
@@ -336,12 +674,12 @@ mod tests {
         let fm = file_manager.clone();
         thread::spawn(move || {
             for file_nr in 0..TEST_FILES_MAX {
-                let mut page = Page::new(TEST_FILES_BLOCKSIZE);
+                let page = Page::new(TEST_FILES_BLOCKSIZE);
                 page.set(0, &Varcount::from(file_nr));
                 let fname = DbFilename::from(format!("testfile_write_{}", file_nr));
                 let block = BlockId::new(fname, 0);
                 println!("write to file_nr: {}", file_nr);
-                fm.write(&block, &mut page).unwrap();
+                fm.write(&block, &page).unwrap();
             }
         })
         .join()
@@ -349,21 +687,129 @@ mod tests {
 
         let fm = file_manager.clone();
         for file_nr in 0..TEST_FILES_MAX {
-            let mut page = Page::new(TEST_FILES_BLOCKSIZE);
+            let page = Page::new(TEST_FILES_BLOCKSIZE);
             let fname = DbFilename::from(format!("testfile_write_{}", file_nr));
             let block = BlockId::new(fname, 0);
-            fm.read(&block, &mut page).unwrap();
+            fm.read(&block, &page).unwrap();
             let file_nr_got = page.get::<Varcount>(0);
             assert_eq!(usize::from(&file_nr_got), file_nr);
         }
 
         testing_finished.store(true, std::sync::atomic::Ordering::Relaxed);
 
-        let mut thread_nr = 0;
-        for t in parallel_read_threads_some_files {
+        for (thread_nr, t) in parallel_read_threads_some_files.into_iter().enumerate() {
             println!("Stop read thread {thread_nr:?}");
             t.join().unwrap();
-            thread_nr += 1;
         }
     }
+
+    #[test]
+    fn test_file_manager_checksums() {
+        let block_size = NonZeroUsize::new(64).unwrap();
+        let file_manager = FileManagerBuilder::unittest("file_manager_checksums")
+            .block_size(block_size)
+            .build()
+            .unwrap();
+        let filename = DbFilename::from("checked");
+        let page = Page::new(block_size);
+        for block_number in 0..3 {
+            page.set(0, &Varcount::from(block_number));
+            file_manager
+                .write(&BlockId::new(filename.clone(), block_number), &page)
+                .unwrap();
+        }
+        let empty = file_manager.append(&filename).unwrap();
+        file_manager.read(&empty, &page).unwrap();
+        assert_eq!(page.get_contents(), vec![0u8; 64]);
+        assert_eq!(file_manager.verify_files().unwrap(), vec![]);
+
+        // Flip a bit of the page of block 1 behind the back of the file manager
+        let path = "/data/hanfried-db-unittest/file_manager_checksums/checked";
+        let disk_block_size = (PAGE_HEADER_LENGTH + 64) as u64;
+        let mut file = OpenOptions::new().write(true).open(path).unwrap();
+        file.seek(SeekFrom::Start(
+            disk_block_size + PAGE_HEADER_LENGTH as u64 + 10,
+        ))
+        .unwrap();
+        file.write_all(&[1]).unwrap();
+        let corrupt = BlockId::new(filename.clone(), 1);
+        match file_manager.read(&corrupt, &page) {
+            Err(IoError::ChecksumMismatch { block, .. }) => assert_eq!(block, corrupt),
+            result => panic!("expected checksum mismatch, got {:?}", result),
+        }
+        file_manager
+            .read(&BlockId::new(filename.clone(), 2), &page)
+            .unwrap();
+        assert_eq!(usize::from(&page.get::<Varcount>(0)), 2);
+
+        // A torn write leaving half a block at the end
+        file.seek(SeekFrom::End(0)).unwrap();
+        file.write_all(&[7; 20]).unwrap();
+        assert_eq!(
+            file_manager.verify_files().unwrap(),
+            vec![corrupt, BlockId::new(filename, 4)]
+        );
+    }
+
+    /// Reader returning at most three bytes per read and being interrupted before every other read
+    struct ShortReads<R: Read> {
+        reader: R,
+        interrupt: bool,
+    }
+
+    impl<R: Read> Read for ShortReads<R> {
+        fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+            self.interrupt = !self.interrupt;
+            if self.interrupt {
+                return Err(std::io::ErrorKind::Interrupted.into());
+            }
+            let length = buf.len().min(3);
+            self.reader.read(&mut buf[..length])
+        }
+    }
+
+    #[test]
+    fn test_file_manager_short_reads_and_end_of_file() {
+        let data: Vec<u8> = (0..20).collect();
+        let mut buf = [0u8; 16];
+        let mut reader = ShortReads {
+            reader: Cursor::new(&data),
+            interrupt: false,
+        };
+        assert_eq!(read_block(&mut reader, &mut buf).unwrap(), 16);
+        assert_eq!(buf.as_slice(), &data[..16]);
+        assert_eq!(read_block(&mut reader, &mut buf).unwrap(), 4);
+        assert_eq!(&buf[..4], &data[16..]);
+        assert_eq!(read_block(&mut reader, &mut buf).unwrap(), 0);
+
+        let block_size = NonZeroUsize::new(64).unwrap();
+        let file_manager = FileManagerBuilder::unittest("file_manager_end_of_file")
+            .block_size(block_size)
+            .build()
+            .unwrap();
+        let block = BlockId::new(DbFilename::from("truncated"), 0);
+        let page = Page::new(block_size);
+        page.set(0, &Varcount::from(7usize));
+        file_manager.write(&block, &page).unwrap();
+        file_manager
+            .write(&block.with_other_block_number(1), &page)
+            .unwrap();
+        let beyond = block.with_other_block_number(2);
+        assert!(matches!(
+            file_manager.read(&beyond, &page),
+            Err(IoError::BlockBeyondEndOfFile(b)) if b == beyond
+        ));
+
+        // Cut the file within block 1
+        let path = "/data/hanfried-db-unittest/file_manager_end_of_file/truncated";
+        let file = OpenOptions::new().write(true).open(path).unwrap();
+        file.set_len((PAGE_HEADER_LENGTH + 64 + 10) as u64).unwrap();
+        assert!(matches!(
+            file_manager.read(&block.with_other_block_number(1), &page),
+            Err(IoError::TruncatedBlock { length: 10, .. })
+        ));
+        let read_page = Page::new(block_size);
+        file_manager.read(&block, &read_page).unwrap();
+        assert_eq!(usize::from(&read_page.get::<Varcount>(0)), 7);
+    }
 }
@@ -62,6 +62,14 @@ impl Page {
     //     4 + s.len()
     // }
 
+    pub fn get_raw_bytes(&self, offset: usize, length: usize) -> Vec<u8> {
+        self.byte_buffer.lock().unwrap()[offset..offset + length].to_vec()
+    }
+
+    pub fn set_raw_bytes(&self, offset: usize, value: &[u8]) {
+        self.byte_buffer.lock().unwrap()[offset..offset + value.len()].copy_from_slice(value);
+    }
+
     pub fn get_contents(&self) -> Vec<u8> {
         self.byte_buffer.lock().unwrap().to_vec()
     }
@@ -0,0 +1,162 @@
+use crate::file_management::block_id::{BlockId, DbFilename};
+use crate::file_management::file_manager::{FileManager, IoError};
+use crate::file_management::page::Page;
+use log::warn;
+
+/// Scratch file for data not fitting into memory, e.g. the runs of an external sort. It is
+/// written sequentially as a stream of bytes and then read sequentially, page by page through
+/// its own page, bypassing buffer pool and log: its content is never needed after a crash.
+/// The file is removed when dropped.
+#[derive(Debug)]
+pub struct TempFile {
+    file_manager: FileManager,
+    filename: DbFilename,
+    page: Page,
+    /// Bytes written, all of them are on disk after rewind
+    length: usize,
+    /// Read position, None while writing
+    position: Option<usize>,
+    loaded_block: Option<usize>,
+}
+
+impl TempFile {
+    pub fn new(file_manager: &FileManager) -> Self {
+        Self {
+            file_manager: file_manager.clone(),
+            filename: file_manager.temp_filename(),
+            page: Page::new(file_manager.block_size),
+            length: 0,
+            position: None,
+            loaded_block: None,
+        }
+    }
+
+    pub fn filename(&self) -> &DbFilename {
+        &self.filename
+    }
+
+    /// Number of bytes written
+    pub fn len(&self) -> usize {
+        self.length
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+
+    /// Appends the bytes, each page is written once it is full
+    pub fn write(&mut self, bytes: &[u8]) -> Result<(), IoError> {
+        assert!(self.position.is_none(), "TempFile written after rewind");
+        let block_size = self.file_manager.block_size.get();
+        let mut written = 0;
+        while written < bytes.len() {
+            let offset = self.length % block_size;
+            let chunk_length = (block_size - offset).min(bytes.len() - written);
+            self.page
+                .set_raw_bytes(offset, &bytes[written..written + chunk_length]);
+            written += chunk_length;
+            self.length += chunk_length;
+            if self.length.is_multiple_of(block_size) {
+                self.write_page(self.length / block_size - 1)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the last, partially filled page, reading starts at the first byte afterwards
+    pub fn rewind(&mut self) -> Result<(), IoError> {
+        let block_size = self.file_manager.block_size.get();
+        if self.position.is_none() && !self.length.is_multiple_of(block_size) {
+            self.write_page(self.length / block_size)?;
+        }
+        self.position = Some(0);
+        self.loaded_block = None;
+        Ok(())
+    }
+
+    /// Bytes not read yet
+    pub fn remaining(&self) -> usize {
+        self.length - self.position.unwrap_or(self.length)
+    }
+
+    /// Next bytes of the file, the length must not exceed the remaining bytes
+    pub fn read(&mut self, length: usize) -> Result<Vec<u8>, IoError> {
+        assert!(length <= self.remaining(), "TempFile read beyond its end");
+        let block_size = self.file_manager.block_size.get();
+        let mut position = self.position.unwrap_or(0);
+        let mut bytes = Vec::with_capacity(length);
+        while bytes.len() < length {
+            let block_number = position / block_size;
+            if self.loaded_block != Some(block_number) {
+                self.file_manager.read(
+                    &BlockId::new(self.filename.clone(), block_number),
+                    &self.page,
+                )?;
+                self.loaded_block = Some(block_number);
+            }
+            let offset = position % block_size;
+            let chunk_length = (block_size - offset).min(length - bytes.len());
+            bytes.extend(self.page.get_raw_bytes(offset, chunk_length));
+            position += chunk_length;
+        }
+        self.position = Some(position);
+        Ok(bytes)
+    }
+
+    fn write_page(&self, block_number: usize) -> Result<(), IoError> {
+        self.file_manager.write(
+            &BlockId::new(self.filename.clone(), block_number),
+            &self.page,
+        )
+    }
+}
+
+impl Drop for TempFile {
+    fn drop(&mut self) {
+        if let Err(error) = self.file_manager.remove(&self.filename) {
+            warn!("Failed to remove temp file {}: {}", self.filename, error);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_management::file_manager::FileManagerBuilder;
+    use crate::file_management::temp_file::TempFile;
+    use std::num::NonZeroUsize;
+    use std::path::Path;
+
+    #[test]
+    fn test_temp_file_write_rewind_read_and_remove() {
+        let file_manager = FileManagerBuilder::unittest("temp_file")
+            .block_size(NonZeroUsize::new(64).unwrap())
+            .build()
+            .unwrap();
+        let mut temp_file = TempFile::new(&file_manager);
+        let bytes: Vec<u8> = (0..=255).collect();
+        temp_file.write(&bytes[..10]).unwrap();
+        temp_file.write(&bytes[10..]).unwrap();
+        temp_file.write(&bytes[..100]).unwrap();
+        assert_eq!(temp_file.len(), 356);
+        assert_eq!(temp_file.remaining(), 0);
+
+        temp_file.rewind().unwrap();
+        assert_eq!(temp_file.read(1).unwrap(), vec![0]);
+        assert_eq!(temp_file.read(200).unwrap(), bytes[1..201].to_vec());
+        assert_eq!(temp_file.remaining(), 155);
+        assert_eq!(temp_file.read(155).unwrap()[55..], bytes[..100]);
+        assert_eq!(temp_file.remaining(), 0);
+
+        temp_file.rewind().unwrap();
+        assert_eq!(temp_file.read(256).unwrap(), bytes);
+
+        let path =
+            Path::new("/data/hanfried-db-unittest/temp_file").join(temp_file.filename().as_str());
+        assert!(path.exists());
+        assert!(temp_file.filename().as_str().starts_with("temp"));
+        let other = TempFile::new(&file_manager);
+        assert_ne!(other.filename(), temp_file.filename());
+        drop(temp_file);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,5 @@
+pub mod btree;
+pub mod hash_index;
+pub mod index;
+pub mod index_file;
+pub mod inverted_index;
@@ -0,0 +1,749 @@
+use crate::datatypes::fixed_length_counts::{Count, SmallCount, TinyCount};
+use crate::datatypes::HfdbSerializableDatatype;
+use crate::file_management::block_id::{BlockId, DbFilename};
+use crate::index_management::index_file::IndexFile;
+use crate::record_management::layout::{Layout, LayoutError};
+use crate::record_management::record_page::SlotId;
+use crate::record_management::schema::{ColumnType, Schema};
+use crate::record_management::table_scan::RecordId;
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use log::debug;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+use std::ops::Bound;
+
+const ROOT_BLOCK_NUMBER: usize = 0;
+const LEVEL_OFFSET: usize = 0;
+const ENTRY_COUNT_OFFSET: usize = 1;
+const LINK_OFFSET: usize = 3;
+const HEADER_LENGTH: usize = 7;
+const RECORD_ID_LENGTH: usize = 6;
+const CHILD_LENGTH: usize = 4;
+
+/// Index entry: a key with the record id of a record version holding it. The key is kept in its
+/// order preserving encoding (see Layout::encode_key), entries are ordered by the encoded key
+/// bytes and record id, so duplicate keys are ordered, too. In inner nodes an entry is the
+/// separator pointing to the child holding the entries not less than it.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Entry {
+    key: Vec<u8>,
+    block_number: usize,
+    slot: usize,
+    child: usize,
+}
+
+impl Entry {
+    fn compare(&self, key: &[u8], block_number: usize, slot: usize) -> Ordering {
+        (&self.key[..], self.block_number, self.slot).cmp(&(key, block_number, slot))
+    }
+}
+
+/// Node of the tree within one block.
+///
+/// Layout: level (0 for leaves), number of entries and link, followed by the entries. The link of
+/// a leaf is the block number of its right sibling (0 for none, as the root never is a sibling),
+/// the link of an inner node is its leftmost child. An entry is the length of its encoded key,
+/// the encoded key, the record id and in inner nodes the child block number.
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Node {
+    level: usize,
+    link: usize,
+    entries: Vec<Entry>,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.level == 0
+    }
+
+    /// Position of the first entry not less than the given one, Ok if it is equal
+    fn position(&self, key: &[u8], block_number: usize, slot: usize) -> Result<usize, usize> {
+        self.entries
+            .binary_search_by(|entry| entry.compare(key, block_number, slot))
+    }
+
+    /// Index of the child to descend into: 0 for the link, i for the child of entry i - 1
+    fn child_index(&self, key: &[u8], block_number: usize, slot: usize) -> usize {
+        match self.position(key, block_number, slot) {
+            Ok(position) => position + 1,
+            Err(position) => position,
+        }
+    }
+
+    fn child(&self, child_index: usize) -> usize {
+        match child_index {
+            0 => self.link,
+            index => self.entries[index - 1].child,
+        }
+    }
+}
+
+/// B+tree index over one column of a table, mapping keys to the record ids of the record versions
+/// holding them. The root always is block 0 of the index file; when it splits, its entries move to
+/// two new blocks. Nodes are merged with a sibling when an entry is removed and both fit into one
+/// block.
+///
+/// Changes read the nodes on their path with shared locks and change them with exclusive locks,
+/// held until the transaction finishes. Lookups read the nodes without locks (see IndexFile), so
+/// they never block writers: a split writes the new right node before linking it, so a lookup
+/// finds entries moved to it by following the leaf links. The blocks of merged nodes are not
+/// reused, as a lookup may still be on its way to them, so the index file never shrinks.
+///
+/// The index contains entries of all record versions, readers have to check the visibility of
+/// the records (and their keys, see Table).
+#[derive(Debug, Clone)]
+pub struct BTreeIndex {
+    file: IndexFile,
+    table_filename: DbFilename,
+    key_layout: Layout,
+    block_size: usize,
+    max_key_length: usize,
+}
+
+impl BTreeIndex {
+    pub fn new(
+        transaction: &Transaction,
+        filename: &DbFilename,
+        table_filename: &DbFilename,
+        key_type: ColumnType,
+    ) -> Result<Self, BTreeError> {
+        let block_size = usize::from(transaction.block_size());
+        let max_key_length = Self::max_key_length_for(block_size)
+            .ok_or(BTreeError::BlockSizeTooSmall(block_size))?;
+        let file = IndexFile::new(transaction, filename).map_err(BTreeError::TransactionError)?;
+        Ok(Self {
+            block_size,
+            max_key_length,
+            file,
+            table_filename: table_filename.clone(),
+            key_layout: Layout::new(Schema::new().with_nullable_column("key", key_type)),
+        })
+    }
+
+    pub fn filename(&self) -> &DbFilename {
+        self.file.filename()
+    }
+
+    /// Longest encoded key, so that every node split leaves at least two entries in each node
+    pub fn max_key_length(&self) -> usize {
+        self.max_key_length
+    }
+
+    /// None if the blocks are too small for four entries of inner nodes
+    fn max_key_length_for(block_size: usize) -> Option<usize> {
+        (block_size.checked_sub(HEADER_LENGTH)? / 4)
+            .checked_sub(2 + RECORD_ID_LENGTH + CHILD_LENGTH)
+            .filter(|&length| length > 0)
+    }
+
+    /// Adds the entry, nothing happens if it exists already
+    pub fn insert(&self, key: &Value, record_id: &RecordId) -> Result<(), BTreeError> {
+        let entry = self.entry(key, record_id)?;
+        self.insert_into(ROOT_BLOCK_NUMBER, entry)?;
+        Ok(())
+    }
+
+    /// Removes the entry, returns whether it existed
+    pub fn delete(&self, key: &Value, record_id: &RecordId) -> Result<bool, BTreeError> {
+        let entry = self.entry(key, record_id)?;
+        self.delete_from(ROOT_BLOCK_NUMBER, &entry)
+    }
+
+    /// Record ids of all entries with the key
+    pub fn lookup(&self, key: &Value) -> Result<Vec<RecordId>, BTreeError> {
+        Ok(self
+            .range(Bound::Included(key), Bound::Included(key))?
+            .into_iter()
+            .map(|(_, record_id)| record_id)
+            .collect())
+    }
+
+    /// Entries with keys within the bounds, in key order
+    pub fn range(
+        &self,
+        low: Bound<&Value>,
+        high: Bound<&Value>,
+    ) -> Result<Vec<(Value, RecordId)>, BTreeError> {
+        self.range_limited(low, high, usize::MAX)
+    }
+
+    /// Entries with keys within the bounds in key order, the first limit ones and the other
+    /// entries with the key of the last one, so that a scan can go on after that key
+    pub fn range_limited(
+        &self,
+        low: Bound<&Value>,
+        high: Bound<&Value>,
+        limit: usize,
+    ) -> Result<Vec<(Value, RecordId)>, BTreeError> {
+        let low = self.encode_bound(low)?;
+        let high = self.encode_bound(high)?;
+        let start = match &low {
+            Bound::Included(key) | Bound::Excluded(key) => key.clone(),
+            Bound::Unbounded => vec![],
+        };
+        let mut block_number = ROOT_BLOCK_NUMBER;
+        let mut node = self.read_node(block_number)?;
+        while !node.is_leaf() {
+            block_number = node.child(node.child_index(&start, 0, 0));
+            node = self.read_node(block_number)?;
+        }
+        let mut entries = vec![];
+        loop {
+            for entry in &node.entries {
+                let after_low = match &low {
+                    Bound::Included(key) => entry.key >= *key,
+                    Bound::Excluded(key) => entry.key > *key,
+                    Bound::Unbounded => true,
+                };
+                let before_high = match &high {
+                    Bound::Included(key) => entry.key <= *key,
+                    Bound::Excluded(key) => entry.key < *key,
+                    Bound::Unbounded => true,
+                };
+                let beyond_limit = entries.len() >= limit
+                    && entries.last().is_none_or(|(key, _)| *key != entry.key);
+                if !before_high || beyond_limit {
+                    return self.decode_entries(entries);
+                }
+                if after_low {
+                    entries.push((entry.key.clone(), self.record_id(entry)));
+                }
+            }
+            if node.link == 0 {
+                return self.decode_entries(entries);
+            }
+            node = self.read_node(node.link)?;
+        }
+    }
+
+    /// Number of levels of the tree, 1 if the root is a leaf
+    pub fn height(&self) -> Result<usize, BTreeError> {
+        Ok(self.read_node(ROOT_BLOCK_NUMBER)?.level + 1)
+    }
+
+    fn entry(&self, key: &Value, record_id: &RecordId) -> Result<Entry, BTreeError> {
+        let entry = Entry {
+            key: self.encode_key(key)?,
+            block_number: record_id.block.block_number(),
+            slot: usize::from(record_id.slot),
+            child: 0,
+        };
+        if entry.key.len() > self.max_key_length() {
+            return Err(BTreeError::KeyTooLarge(entry.key.len()));
+        }
+        Ok(entry)
+    }
+
+    fn record_id(&self, entry: &Entry) -> RecordId {
+        RecordId::new(
+            BlockId::new(self.table_filename.clone(), entry.block_number),
+            SlotId::from(entry.slot),
+        )
+    }
+
+    /// Inserts into the subtree, returns the separator for the new right sibling if the node split
+    fn insert_into(&self, block_number: usize, entry: Entry) -> Result<Option<Entry>, BTreeError> {
+        let mut node = self.read_node_for_update(block_number)?;
+        let position = node.position(&entry.key, entry.block_number, entry.slot);
+        if node.is_leaf() {
+            match position {
+                Ok(_) => return Ok(None),
+                Err(position) => node.entries.insert(position, entry),
+            }
+        } else {
+            let child = node.child(node.child_index(&entry.key, entry.block_number, entry.slot));
+            match self.insert_into(child, entry)? {
+                Some(separator) => {
+                    let position = node
+                        .position(&separator.key, separator.block_number, separator.slot)
+                        .unwrap_or_else(|position| position);
+                    node.entries.insert(position, separator);
+                }
+                None => return Ok(None),
+            }
+        }
+        if self.encoded_length(&node) <= self.block_size {
+            self.write_node(block_number, &node)?;
+            return Ok(None);
+        }
+        self.split(block_number, node)
+    }
+
+    fn split(&self, block_number: usize, node: Node) -> Result<Option<Entry>, BTreeError> {
+        let mut left = node;
+        let mut entries = left.entries.split_off(left.entries.len() / 2);
+        let right_block_number = self.append()?;
+        let (right, separator) = if left.is_leaf() {
+            let separator = Entry {
+                child: right_block_number,
+                ..entries[0].clone()
+            };
+            let right = Node {
+                level: 0,
+                link: left.link,
+                entries,
+            };
+            left.link = right_block_number;
+            (right, separator)
+        } else {
+            let mut separator = entries.remove(0);
+            let right = Node {
+                level: left.level,
+                link: separator.child,
+                entries,
+            };
+            separator.child = right_block_number;
+            (right, separator)
+        };
+        self.write_node(right_block_number, &right)?;
+        if block_number != ROOT_BLOCK_NUMBER {
+            self.write_node(block_number, &left)?;
+            return Ok(Some(separator));
+        }
+        let left_block_number = self.append()?;
+        self.write_node(left_block_number, &left)?;
+        let root = Node {
+            level: left.level + 1,
+            link: left_block_number,
+            entries: vec![separator],
+        };
+        self.write_node(ROOT_BLOCK_NUMBER, &root)?;
+        debug!(
+            "BTreeIndex {}: root split, height is {} now",
+            self.file.filename(),
+            root.level + 1
+        );
+        Ok(None)
+    }
+
+    fn delete_from(&self, block_number: usize, entry: &Entry) -> Result<bool, BTreeError> {
+        let mut node = self.read_node_for_update(block_number)?;
+        if node.is_leaf() {
+            return match node.position(&entry.key, entry.block_number, entry.slot) {
+                Ok(position) => {
+                    node.entries.remove(position);
+                    self.write_node(block_number, &node)?;
+                    Ok(true)
+                }
+                Err(_) => Ok(false),
+            };
+        }
+        let child_index = node.child_index(&entry.key, entry.block_number, entry.slot);
+        if !self.delete_from(node.child(child_index), entry)? {
+            return Ok(false);
+        }
+        if self.merge_children(&mut node, child_index)? {
+            if block_number == ROOT_BLOCK_NUMBER && node.entries.is_empty() {
+                let child = self.read_node_for_update(node.link)?;
+                self.write_node(ROOT_BLOCK_NUMBER, &child)?;
+                debug!(
+                    "BTreeIndex {}: root collapsed, height is {} now",
+                    self.file.filename(),
+                    child.level + 1
+                );
+            } else {
+                self.write_node(block_number, &node)?;
+            }
+        }
+        Ok(true)
+    }
+
+    /// Merges the child with its right (or left) sibling if both fit into one block, returns
+    /// whether the parent node changed
+    fn merge_children(&self, parent: &mut Node, child_index: usize) -> Result<bool, BTreeError> {
+        let right_index = match child_index {
+            index if index < parent.entries.len() => index + 1,
+            0 => return Ok(false),
+            index => index,
+        };
+        let left_block_number = parent.child(right_index - 1);
+        let mut left = self.read_node_for_update(left_block_number)?;
+        let right = self.read_node_for_update(parent.child(right_index))?;
+        let mut merged = left.entries.clone();
+        if !left.is_leaf() {
+            merged.push(Entry {
+                child: right.link,
+                ..parent.entries[right_index - 1].clone()
+            });
+        }
+        merged.extend(right.entries);
+        let merged_node = Node {
+            level: left.level,
+            link: if left.is_leaf() {
+                right.link
+            } else {
+                left.link
+            },
+            entries: merged,
+        };
+        if self.encoded_length(&merged_node) > self.block_size {
+            return Ok(false);
+        }
+        left = merged_node;
+        self.write_node(left_block_number, &left)?;
+        parent.entries.remove(right_index - 1);
+        Ok(true)
+    }
+
+    fn append(&self) -> Result<usize, BTreeError> {
+        self.file.append().map_err(BTreeError::TransactionError)
+    }
+
+    fn encode_key(&self, key: &Value) -> Result<Vec<u8>, BTreeError> {
+        let mut row = self.key_layout.row();
+        row.set_value("key", key.clone())
+            .map_err(BTreeError::LayoutError)?;
+        self.key_layout
+            .encode_key(&row)
+            .map_err(BTreeError::LayoutError)
+    }
+
+    fn encode_bound(&self, bound: Bound<&Value>) -> Result<Bound<Vec<u8>>, BTreeError> {
+        Ok(match bound {
+            Bound::Included(key) => Bound::Included(self.encode_key(key)?),
+            Bound::Excluded(key) => Bound::Excluded(self.encode_key(key)?),
+            Bound::Unbounded => Bound::Unbounded,
+        })
+    }
+
+    fn decode_entries(
+        &self,
+        entries: Vec<(Vec<u8>, RecordId)>,
+    ) -> Result<Vec<(Value, RecordId)>, BTreeError> {
+        entries
+            .into_iter()
+            .map(|(key, record_id)| {
+                let key = self
+                    .key_layout
+                    .decode_key(&key)
+                    .get_value("key")
+                    .map_err(BTreeError::LayoutError)?
+                    .clone();
+                Ok((key, record_id))
+            })
+            .collect()
+    }
+
+    fn encoded_length(&self, node: &Node) -> usize {
+        let child_length = if node.is_leaf() { 0 } else { CHILD_LENGTH };
+        HEADER_LENGTH
+            + node
+                .entries
+                .iter()
+                .map(|entry| 2 + entry.key.len() + RECORD_ID_LENGTH + child_length)
+                .sum::<usize>()
+    }
+
+    /// Node read by a lookup, without lock
+    fn read_node(&self, block_number: usize) -> Result<Node, BTreeError> {
+        let bytes = self
+            .file
+            .read(block_number)
+            .map_err(BTreeError::TransactionError)?;
+        self.decode_node(&bytes)
+    }
+
+    /// Node read by a change, with a shared lock held until the transaction finishes
+    fn read_node_for_update(&self, block_number: usize) -> Result<Node, BTreeError> {
+        let bytes = self
+            .file
+            .read_for_update(block_number)
+            .map_err(BTreeError::TransactionError)?;
+        self.decode_node(&bytes)
+    }
+
+    fn decode_node(&self, bytes: &[u8]) -> Result<Node, BTreeError> {
+        let level = usize::from(&TinyCount::deserialize(&bytes[LEVEL_OFFSET..]));
+        let entry_count = usize::from(&SmallCount::deserialize(&bytes[ENTRY_COUNT_OFFSET..]));
+        let link = usize::from(&Count::deserialize(&bytes[LINK_OFFSET..]));
+        let mut offset = HEADER_LENGTH;
+        let mut entries = Vec::with_capacity(entry_count);
+        for _ in 0..entry_count {
+            let key_length = usize::from(&SmallCount::deserialize(&bytes[offset..]));
+            offset += 2;
+            let key = bytes[offset..offset + key_length].to_vec();
+            offset += key_length;
+            let block_number = usize::from(&Count::deserialize(&bytes[offset..]));
+            let slot = usize::from(&SmallCount::deserialize(&bytes[offset + 4..]));
+            offset += RECORD_ID_LENGTH;
+            let child = if level == 0 {
+                0
+            } else {
+                offset += CHILD_LENGTH;
+                usize::from(&Count::deserialize(&bytes[offset - CHILD_LENGTH..]))
+            };
+            entries.push(Entry {
+                key,
+                block_number,
+                slot,
+                child,
+            });
+        }
+        Ok(Node {
+            level,
+            link,
+            entries,
+        })
+    }
+
+    fn write_node(&self, block_number: usize, node: &Node) -> Result<(), BTreeError> {
+        let mut bytes = vec![0u8; self.block_size];
+        TinyCount::from(node.level).serialize(&mut bytes[LEVEL_OFFSET..ENTRY_COUNT_OFFSET]);
+        SmallCount::from(node.entries.len()).serialize(&mut bytes[ENTRY_COUNT_OFFSET..LINK_OFFSET]);
+        Count::from(node.link).serialize(&mut bytes[LINK_OFFSET..HEADER_LENGTH]);
+        let mut offset = HEADER_LENGTH;
+        for entry in &node.entries {
+            SmallCount::from(entry.key.len()).serialize(&mut bytes[offset..offset + 2]);
+            offset += 2;
+            bytes[offset..offset + entry.key.len()].copy_from_slice(&entry.key);
+            offset += entry.key.len();
+            Count::from(entry.block_number).serialize(&mut bytes[offset..offset + 4]);
+            SmallCount::from(entry.slot).serialize(&mut bytes[offset + 4..offset + 6]);
+            offset += RECORD_ID_LENGTH;
+            if !node.is_leaf() {
+                Count::from(entry.child).serialize(&mut bytes[offset..offset + CHILD_LENGTH]);
+                offset += CHILD_LENGTH;
+            }
+        }
+        self.file
+            .write(block_number, &bytes)
+            .map_err(BTreeError::TransactionError)
+    }
+}
+
+#[derive(Debug)]
+pub enum BTreeError {
+    TransactionError(TransactionError),
+    LayoutError(LayoutError),
+    KeyTooLarge(usize),
+    BlockSizeTooSmall(usize),
+}
+
+impl Display for BTreeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BTreeError::TransactionError(e) => write!(f, "BTreeIndex {}", e),
+            BTreeError::LayoutError(e) => write!(f, "BTreeIndex {}", e),
+            BTreeError::KeyTooLarge(length) => {
+                write!(f, "BTreeIndex: key of {} bytes is too large", length)
+            }
+            BTreeError::BlockSizeTooSmall(block_size) => {
+                write!(f, "BTreeIndex: block size {} is too small", block_size)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::file_management::block_id::{BlockId, DbFilename};
+    use crate::index_management::btree::{BTreeError, BTreeIndex};
+    use crate::record_management::record_page::SlotId;
+    use crate::record_management::schema::ColumnType;
+    use crate::record_management::table_scan::RecordId;
+    use crate::record_management::value::Value;
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+    use std::ops::Bound;
+
+    fn record_id(nr: usize) -> RecordId {
+        RecordId::new(
+            BlockId::new(DbFilename::from("btree.tbl"), nr / 10),
+            SlotId::from(nr % 10),
+        )
+    }
+
+    #[test]
+    fn test_btree_insert_lookup_range_and_delete() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("btree")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(200).unwrap()))
+            .build()
+            .unwrap();
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let index = BTreeIndex::new(
+            &tx,
+            &DbFilename::from("btree.idx"),
+            &DbFilename::from("btree.tbl"),
+            ColumnType::Integer,
+        )
+        .unwrap();
+        // Keys 0..100 in a shuffled order, each key twice
+        let keys: Vec<i64> = (0..200).map(|nr| (nr * 37) % 100).collect();
+        for (nr, key) in keys.iter().enumerate() {
+            index.insert(&Value::from(*key), &record_id(nr)).unwrap();
+        }
+        index.insert(&Value::from(keys[0]), &record_id(0)).unwrap();
+        assert!(index.height().unwrap() >= 3, "The tree has to grow");
+
+        assert_eq!(
+            index.lookup(&Value::from(37i64)).unwrap(),
+            vec![record_id(1), record_id(101)]
+        );
+        assert!(index.lookup(&Value::from(100i64)).unwrap().is_empty());
+        let range = index
+            .range(
+                Bound::Excluded(&Value::from(10i64)),
+                Bound::Included(&Value::from(20i64)),
+            )
+            .unwrap();
+        assert_eq!(range.len(), 20);
+        assert_eq!(range[0].0, Value::from(11i64));
+        assert_eq!(range[19].0, Value::from(20i64));
+        let all = index.range(Bound::Unbounded, Bound::Unbounded).unwrap();
+        assert_eq!(all.len(), 200);
+        assert!(all.windows(2).all(|pair| pair[0].0 <= pair[1].0));
+        // The limit keeps all entries of the last key
+        let limited = index
+            .range_limited(Bound::Included(&Value::from(10i64)), Bound::Unbounded, 3)
+            .unwrap();
+        let limited_keys: Vec<&Value> = limited.iter().map(|(key, _)| key).collect();
+        assert_eq!(
+            limited_keys,
+            vec![
+                &Value::from(10i64),
+                &Value::from(10i64),
+                &Value::from(11i64),
+                &Value::from(11i64)
+            ]
+        );
+        assert!(matches!(
+            index.insert(&Value::from("text"), &record_id(0)),
+            Err(BTreeError::LayoutError(_))
+        ));
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let index = BTreeIndex::new(
+            &tx,
+            &DbFilename::from("btree.idx"),
+            &DbFilename::from("btree.tbl"),
+            ColumnType::Integer,
+        )
+        .unwrap();
+        for (nr, key) in keys.iter().enumerate() {
+            assert!(index.delete(&Value::from(*key), &record_id(nr)).unwrap());
+            if nr == 100 {
+                assert_eq!(
+                    index.lookup(&Value::from(37i64)).unwrap(),
+                    vec![record_id(101)]
+                );
+            }
+        }
+        assert!(!index.delete(&Value::from(keys[0]), &record_id(0)).unwrap());
+        assert!(index
+            .range(Bound::Unbounded, Bound::Unbounded)
+            .unwrap()
+            .is_empty());
+        assert_eq!(index.height().unwrap(), 1, "Merging has to shrink the tree");
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_btree_lookups_do_not_block_changes() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("btree_lookups")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(200).unwrap()))
+            .build()
+            .unwrap();
+        let open = |tx| {
+            BTreeIndex::new(
+                tx,
+                &DbFilename::from("btree.idx"),
+                &DbFilename::from("btree.tbl"),
+                ColumnType::Integer,
+            )
+            .unwrap()
+        };
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let index = open(&tx);
+        for nr in 0..50 {
+            index
+                .insert(&Value::from(nr as i64), &record_id(nr))
+                .unwrap();
+        }
+        tx.commit().unwrap();
+
+        let reader = hfdb.transaction_manager.begin().unwrap();
+        let reading_index = open(&reader);
+        assert_eq!(
+            reading_index.lookup(&Value::from(7i64)).unwrap(),
+            vec![record_id(7)]
+        );
+        let writer = hfdb.transaction_manager.begin().unwrap();
+        let writing_index = open(&writer);
+        for nr in 50..100 {
+            writing_index
+                .insert(&Value::from(nr as i64), &record_id(nr))
+                .unwrap();
+        }
+        assert!(writing_index
+            .delete(&Value::from(7i64), &record_id(7))
+            .unwrap());
+        writer.commit().unwrap();
+        assert_eq!(
+            reading_index
+                .range(Bound::Unbounded, Bound::Unbounded)
+                .unwrap()
+                .len(),
+            99
+        );
+        reader.commit().unwrap();
+    }
+
+    #[test]
+    fn test_btree_orders_encoded_keys() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("btree_encoded_keys")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(200).unwrap()))
+            .build()
+            .unwrap();
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let index = BTreeIndex::new(
+            &tx,
+            &DbFilename::from("btree.idx"),
+            &DbFilename::from("btree.tbl"),
+            ColumnType::Varint,
+        )
+        .unwrap();
+        let mut keys = vec![i64::MIN, i64::MAX];
+        for power in 0..62 {
+            keys.extend([1i64 << power, -(1i64 << power)]);
+        }
+        for (nr, key) in keys.iter().enumerate() {
+            index.insert(&Value::from(*key), &record_id(nr)).unwrap();
+        }
+        index.insert(&Value::Null, &record_id(keys.len())).unwrap();
+        keys.sort();
+        let all = index.range(Bound::Unbounded, Bound::Unbounded).unwrap();
+        assert_eq!(all[0].0, Value::Null);
+        assert_eq!(
+            all[1..]
+                .iter()
+                .map(|(key, _)| key.clone())
+                .collect::<Vec<_>>(),
+            keys.into_iter().map(Value::from).collect::<Vec<_>>()
+        );
+        let range = index
+            .range(
+                Bound::Included(&Value::from(-256i64)),
+                Bound::Excluded(&Value::from(256i64)),
+            )
+            .unwrap();
+        assert_eq!(range.len(), 17);
+        assert_eq!(range[0].0, Value::from(-256i64));
+        assert_eq!(range[16].0, Value::from(128i64));
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_btree_max_key_length() {
+        assert_eq!(BTreeIndex::max_key_length_for(0), None);
+        assert_eq!(BTreeIndex::max_key_length_for(40), None);
+        assert_eq!(BTreeIndex::max_key_length_for(58), None);
+        assert_eq!(BTreeIndex::max_key_length_for(59), Some(1));
+        assert_eq!(BTreeIndex::max_key_length_for(4096), Some(1010));
+    }
+}
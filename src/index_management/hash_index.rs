@@ -0,0 +1,744 @@
+use crate::datatypes::fixed_length_counts::{Count, SmallCount, TinyCount};
+use crate::datatypes::HfdbSerializableDatatype;
+use crate::file_management::block_id::{BlockId, DbFilename};
+use crate::index_management::index_file::IndexFile;
+use crate::record_management::record_page::SlotId;
+use crate::record_management::table_scan::RecordId;
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use log::debug;
+use std::fmt::{Display, Formatter};
+use std::marker::PhantomData;
+
+const LOCAL_DEPTH_OFFSET: usize = 0;
+const ENTRY_COUNT_OFFSET: usize = 1;
+const OVERFLOW_OFFSET: usize = 3;
+const HEAD_OFFSET: usize = 7;
+const HEADER_LENGTH: usize = 11;
+const RECORD_ID_LENGTH: usize = 6;
+const DIRECTORY_ENTRY_LENGTH: usize = 4;
+const GLOBAL_DEPTH_OFFSET: usize = 0;
+const FREE_BLOCK_OFFSET: usize = 1;
+const DIRECTORY_HEADER_LENGTH: usize = 5;
+const MAX_GLOBAL_DEPTH: usize = 24;
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+struct Entry {
+    key: Vec<u8>,
+    block_number: usize,
+    slot: usize,
+}
+
+impl Entry {
+    fn encoded_length(&self) -> usize {
+        2 + self.key.len() + RECORD_ID_LENGTH
+    }
+}
+
+/// Bucket within one block: local depth, number of entries, the block number of the next
+/// overflow bucket (0 for none, the first bucket never overflows another one) and the block
+/// number of the first bucket of its chain, followed by the entries: the length of the key, the
+/// serialized key and the record id. A free block is an empty bucket with the next free block as
+/// overflow and itself as first bucket.
+#[derive(Debug, Clone, Default)]
+struct Bucket {
+    local_depth: usize,
+    overflow: usize,
+    head: usize,
+    entries: Vec<Entry>,
+}
+
+impl Bucket {
+    fn encoded_length(&self) -> usize {
+        HEADER_LENGTH
+            + self
+                .entries
+                .iter()
+                .map(Entry::encoded_length)
+                .sum::<usize>()
+    }
+}
+
+/// Persistent extendible hash index, mapping serialized keys to record ids.
+///
+/// The directory of 2^global depth bucket block numbers is stored in its own file (the index
+/// filename with ".dir" appended): the global depth and the first free bucket block in block 0,
+/// the block numbers from block 1 on. A bucket whose local depth equals the global depth doubles
+/// the directory when it overflows, then it is split into two buckets by the next bit of the key
+/// hash. Entries with the same hash can not be split, they go to overflow buckets chained to the
+/// bucket. Overflow buckets getting empty are unlinked from their chain and their blocks reused
+/// from a free list, buckets are not merged.
+///
+/// Changes read with shared locks (see IndexFile), lookups without locks. A split writes the new
+/// bucket before the directory points to it and only then removes the moved entries from the
+/// old bucket, entries stay in their block. Lookups check afterwards that the directory still
+/// points to the bucket read, and start over at the first bucket of a chain if they come across
+/// an overflow bucket of another chain, as its block was freed and reused meanwhile.
+///
+/// Keys are compared by their serialized bytes, so their serialization has to be unique.
+#[derive(Debug, Clone)]
+pub struct HashIndex<K> {
+    buckets: IndexFile,
+    directory: IndexFile,
+    table_filename: DbFilename,
+    max_key_length: usize,
+    key_type: PhantomData<K>,
+}
+
+impl<K: HfdbSerializableDatatype> HashIndex<K> {
+    pub fn new(
+        transaction: &Transaction,
+        filename: &DbFilename,
+        table_filename: &DbFilename,
+    ) -> Result<Self, HashIndexError> {
+        let block_size = usize::from(transaction.block_size());
+        let max_key_length = Self::max_key_length_for(block_size)
+            .ok_or(HashIndexError::BlockSizeTooSmall(block_size))?;
+        let buckets =
+            IndexFile::new(transaction, filename).map_err(HashIndexError::TransactionError)?;
+        let directory = IndexFile::new(transaction, &DbFilename::from(format!("{}.dir", filename)))
+            .map_err(HashIndexError::TransactionError)?;
+        let index = Self {
+            buckets,
+            directory,
+            table_filename: table_filename.clone(),
+            max_key_length,
+            key_type: PhantomData,
+        };
+        index.ensure_directory_blocks(1)?;
+        Ok(index)
+    }
+
+    pub fn filename(&self) -> &DbFilename {
+        self.buckets.filename()
+    }
+
+    /// Longest serialized key, so that at least two entries fit into a bucket
+    pub fn max_key_length(&self) -> usize {
+        self.max_key_length
+    }
+
+    /// None if the blocks are too small for two entries
+    fn max_key_length_for(block_size: usize) -> Option<usize> {
+        (block_size.checked_sub(HEADER_LENGTH)? / 2)
+            .checked_sub(2 + RECORD_ID_LENGTH)
+            .filter(|&length| length > 0)
+    }
+
+    /// Adds the entry, nothing happens if it exists already
+    pub fn insert(&self, key: &K, record_id: &RecordId) -> Result<(), HashIndexError> {
+        let entry = self.entry(key, record_id)?;
+        let hash = Self::hash(&entry.key);
+        loop {
+            let (global_depth, bucket_number) = self.bucket_number(hash, true)?;
+            let mut chain = self.read_chain(bucket_number, true)?;
+            if chain
+                .iter()
+                .any(|(_, bucket)| bucket.entries.contains(&entry))
+            {
+                return Ok(());
+            }
+            for (block_number, bucket) in chain.iter_mut() {
+                if bucket.encoded_length() + entry.encoded_length() <= self.buckets.block_size() {
+                    bucket.entries.push(entry);
+                    return self.write_bucket(*block_number, bucket);
+                }
+            }
+            let local_depth = chain[0].1.local_depth;
+            let same_hash = chain
+                .iter()
+                .flat_map(|(_, bucket)| &bucket.entries)
+                .all(|other| Self::hash(&other.key) == hash);
+            if same_hash || local_depth == MAX_GLOBAL_DEPTH {
+                return self.add_overflow(chain, entry);
+            }
+            if local_depth == global_depth {
+                self.double_directory(global_depth)?;
+            }
+            self.split(chain)?;
+        }
+    }
+
+    /// Removes the entry, returns whether it existed. An overflow bucket getting empty is
+    /// unlinked from the chain and its block freed.
+    pub fn delete(&self, key: &K, record_id: &RecordId) -> Result<bool, HashIndexError> {
+        let entry = self.entry(key, record_id)?;
+        let (_, bucket_number) = self.bucket_number(Self::hash(&entry.key), true)?;
+        let mut chain = self.read_chain(bucket_number, true)?;
+        for index in 0..chain.len() {
+            let (block_number, bucket) = &mut chain[index];
+            if let Some(position) = bucket.entries.iter().position(|other| *other == entry) {
+                bucket.entries.remove(position);
+                if index == 0 || !bucket.entries.is_empty() {
+                    self.write_bucket(*block_number, bucket)?;
+                    return Ok(true);
+                }
+                let (block_number, overflow) = (*block_number, bucket.overflow);
+                let (previous_block_number, previous) = &mut chain[index - 1];
+                previous.overflow = overflow;
+                self.write_bucket(*previous_block_number, previous)?;
+                self.free(block_number)?;
+                return Ok(true);
+            }
+        }
+        Ok(false)
+    }
+
+    /// Record ids of all entries with the key
+    pub fn lookup(&self, key: &K) -> Result<Vec<RecordId>, HashIndexError> {
+        let key = Self::serialize(key);
+        let hash = Self::hash(&key);
+        loop {
+            let (_, bucket_number) = self.bucket_number(hash, false)?;
+            let chain = self.read_chain(bucket_number, false)?;
+            // A split may have moved the entries to a new bucket meanwhile
+            if self.bucket_number(hash, false)?.1 != bucket_number {
+                continue;
+            }
+            return Ok(chain
+                .into_iter()
+                .flat_map(|(_, bucket)| bucket.entries)
+                .filter(|entry| entry.key == key)
+                .map(|entry| self.record_id(&entry))
+                .collect());
+        }
+    }
+
+    /// Block numbers of all buckets the directory points to
+    pub fn bucket_numbers(&self) -> Result<Vec<usize>, HashIndexError> {
+        let mut bucket_numbers = self.read_directory(false)?;
+        bucket_numbers.sort();
+        bucket_numbers.dedup();
+        Ok(bucket_numbers)
+    }
+
+    /// Entries of the bucket including its overflow buckets
+    pub fn bucket(&self, bucket_number: usize) -> Result<Vec<(K, RecordId)>, HashIndexError> {
+        Ok(self
+            .read_chain(bucket_number, false)?
+            .into_iter()
+            .flat_map(|(_, bucket)| bucket.entries)
+            .map(|entry| (K::deserialize(&entry.key), self.record_id(&entry)))
+            .collect())
+    }
+
+    pub fn global_depth(&self) -> Result<usize, HashIndexError> {
+        self.read_global_depth(false)
+    }
+
+    fn serialize(key: &K) -> Vec<u8> {
+        let mut bytes = vec![0u8; key.serialized_length()];
+        key.serialize(&mut bytes);
+        bytes
+    }
+
+    fn entry(&self, key: &K, record_id: &RecordId) -> Result<Entry, HashIndexError> {
+        let key = Self::serialize(key);
+        if key.len() > self.max_key_length() {
+            return Err(HashIndexError::KeyTooLarge(key.len()));
+        }
+        Ok(Entry {
+            key,
+            block_number: record_id.block.block_number(),
+            slot: usize::from(record_id.slot),
+        })
+    }
+
+    fn record_id(&self, entry: &Entry) -> RecordId {
+        RecordId::new(
+            BlockId::new(self.table_filename.clone(), entry.block_number),
+            SlotId::from(entry.slot),
+        )
+    }
+
+    /// FNV-1a, stable across program versions as the hashes determine the persistent bucket
+    fn hash(key: &[u8]) -> u64 {
+        key.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x0000_0100_0000_01b3)
+        })
+    }
+
+    /// Reads a block of the index, with a shared lock if it is read for an update
+    fn read_block(
+        file: &IndexFile,
+        block_number: usize,
+        for_update: bool,
+    ) -> Result<Vec<u8>, HashIndexError> {
+        match for_update {
+            true => file.read_for_update(block_number),
+            false => file.read(block_number),
+        }
+        .map_err(HashIndexError::TransactionError)
+    }
+
+    fn read_global_depth(&self, for_update: bool) -> Result<usize, HashIndexError> {
+        let header = Self::read_block(&self.directory, 0, for_update)?;
+        Ok(usize::from(&TinyCount::deserialize(
+            &header[GLOBAL_DEPTH_OFFSET..],
+        )))
+    }
+
+    /// Global depth and first free bucket block
+    fn read_directory_header(&self) -> Result<(usize, usize), HashIndexError> {
+        let header = Self::read_block(&self.directory, 0, true)?;
+        Ok((
+            usize::from(&TinyCount::deserialize(&header[GLOBAL_DEPTH_OFFSET..])),
+            usize::from(&Count::deserialize(&header[FREE_BLOCK_OFFSET..])),
+        ))
+    }
+
+    fn write_directory_header(
+        &self,
+        global_depth: usize,
+        free_block: usize,
+    ) -> Result<(), HashIndexError> {
+        let mut header = vec![0u8; DIRECTORY_HEADER_LENGTH];
+        TinyCount::from(global_depth)
+            .serialize(&mut header[GLOBAL_DEPTH_OFFSET..FREE_BLOCK_OFFSET]);
+        Count::from(free_block).serialize(&mut header[FREE_BLOCK_OFFSET..DIRECTORY_HEADER_LENGTH]);
+        self.directory
+            .write(0, &header)
+            .map_err(HashIndexError::TransactionError)
+    }
+
+    /// Block for a new bucket, taken from the free list or appended
+    fn allocate(&self) -> Result<usize, HashIndexError> {
+        let (global_depth, free_block) = self.read_directory_header()?;
+        if free_block == 0 {
+            return self
+                .buckets
+                .append()
+                .map_err(HashIndexError::TransactionError);
+        }
+        let next_free_block = self.read_bucket(free_block, true)?.overflow;
+        self.write_directory_header(global_depth, next_free_block)?;
+        Ok(free_block)
+    }
+
+    /// Adds the block of an unlinked overflow bucket to the free list
+    fn free(&self, block_number: usize) -> Result<(), HashIndexError> {
+        let (global_depth, free_block) = self.read_directory_header()?;
+        self.write_bucket(
+            block_number,
+            &Bucket {
+                overflow: free_block,
+                head: block_number,
+                ..Bucket::default()
+            },
+        )?;
+        self.write_directory_header(global_depth, block_number)
+    }
+
+    fn bucket_number(&self, hash: u64, for_update: bool) -> Result<(usize, usize), HashIndexError> {
+        let global_depth = self.read_global_depth(for_update)?;
+        let index = (hash & ((1 << global_depth) - 1)) as usize;
+        let per_block = self.directory_entries_per_block();
+        let block = Self::read_block(&self.directory, 1 + index / per_block, for_update)?;
+        let offset = (index % per_block) * DIRECTORY_ENTRY_LENGTH;
+        Ok((
+            global_depth,
+            usize::from(&Count::deserialize(&block[offset..])),
+        ))
+    }
+
+    fn directory_entries_per_block(&self) -> usize {
+        self.directory.block_size() / DIRECTORY_ENTRY_LENGTH
+    }
+
+    fn read_directory(&self, for_update: bool) -> Result<Vec<usize>, HashIndexError> {
+        let length = 1 << self.read_global_depth(for_update)?;
+        let per_block = self.directory_entries_per_block();
+        let mut bucket_numbers = Vec::with_capacity(length);
+        for block_number in 0..length.div_ceil(per_block) {
+            let block = Self::read_block(&self.directory, 1 + block_number, for_update)?;
+            for index in 0..per_block.min(length - bucket_numbers.len()) {
+                let offset = index * DIRECTORY_ENTRY_LENGTH;
+                bucket_numbers.push(usize::from(&Count::deserialize(&block[offset..])));
+            }
+        }
+        Ok(bucket_numbers)
+    }
+
+    fn write_directory(&self, bucket_numbers: &[usize]) -> Result<(), HashIndexError> {
+        let per_block = self.directory_entries_per_block();
+        self.ensure_directory_blocks(bucket_numbers.len().div_ceil(per_block))?;
+        for (block_number, chunk) in bucket_numbers.chunks(per_block).enumerate() {
+            let mut bytes = vec![0u8; chunk.len() * DIRECTORY_ENTRY_LENGTH];
+            for (index, bucket_number) in chunk.iter().enumerate() {
+                let offset = index * DIRECTORY_ENTRY_LENGTH;
+                Count::from(*bucket_number)
+                    .serialize(&mut bytes[offset..offset + DIRECTORY_ENTRY_LENGTH]);
+            }
+            self.directory
+                .write(1 + block_number, &bytes)
+                .map_err(HashIndexError::TransactionError)?;
+        }
+        let (_, free_block) = self.read_directory_header()?;
+        self.write_directory_header(bucket_numbers.len().trailing_zeros() as usize, free_block)
+    }
+
+    fn ensure_directory_blocks(&self, blocks: usize) -> Result<(), HashIndexError> {
+        while self
+            .directory
+            .block_length()
+            .map_err(HashIndexError::TransactionError)?
+            < 1 + blocks
+        {
+            self.directory
+                .append()
+                .map_err(HashIndexError::TransactionError)?;
+        }
+        Ok(())
+    }
+
+    fn double_directory(&self, global_depth: usize) -> Result<(), HashIndexError> {
+        let mut bucket_numbers = self.read_directory(true)?;
+        bucket_numbers.extend_from_within(..);
+        self.write_directory(&bucket_numbers)?;
+        debug!(
+            "HashIndex {}: directory doubled, global depth is {} now",
+            self.filename(),
+            global_depth + 1
+        );
+        Ok(())
+    }
+
+    /// Moves the entries of the bucket chain with the next bit of their hashes set to a new
+    /// bucket, overflow buckets getting empty are unlinked and freed
+    fn split(&self, chain: Vec<(usize, Bucket)>) -> Result<(), HashIndexError> {
+        let bucket_number = chain[0].0;
+        let local_depth = chain[0].1.local_depth;
+        let is_upper = |entry: &Entry| Self::hash(&entry.key) >> local_depth & 1 == 1;
+        let upper: Vec<Entry> = chain
+            .iter()
+            .flat_map(|(_, bucket)| &bucket.entries)
+            .filter(|entry| is_upper(entry))
+            .cloned()
+            .collect();
+        let new_bucket_number = self.allocate()?;
+        self.write_chain(new_bucket_number, local_depth + 1, upper)?;
+        let mut bucket_numbers = self.read_directory(true)?;
+        for (index, number) in bucket_numbers.iter_mut().enumerate() {
+            if *number == bucket_number && index >> local_depth & 1 == 1 {
+                *number = new_bucket_number;
+            }
+        }
+        self.write_directory(&bucket_numbers)?;
+
+        let (mut kept, mut emptied) = (vec![], vec![]);
+        for (index, (block_number, mut bucket)) in chain.into_iter().enumerate() {
+            bucket.local_depth = local_depth + 1;
+            bucket.entries.retain(|entry| !is_upper(entry));
+            match index == 0 || !bucket.entries.is_empty() {
+                true => kept.push((block_number, bucket)),
+                false => emptied.push(block_number),
+            }
+        }
+        let overflows: Vec<usize> = kept[1..].iter().map(|(nr, _)| *nr).chain([0]).collect();
+        for ((block_number, mut bucket), overflow) in kept.into_iter().zip(overflows) {
+            bucket.overflow = overflow;
+            self.write_bucket(block_number, &bucket)?;
+        }
+        for block_number in emptied {
+            self.free(block_number)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the entries to the bucket, using free or new blocks for overflow buckets
+    fn write_chain(
+        &self,
+        bucket_number: usize,
+        local_depth: usize,
+        entries: Vec<Entry>,
+    ) -> Result<(), HashIndexError> {
+        let new_bucket = || Bucket {
+            local_depth,
+            head: bucket_number,
+            ..Bucket::default()
+        };
+        let mut block_number = bucket_number;
+        let mut bucket = new_bucket();
+        for entry in entries {
+            if bucket.encoded_length() + entry.encoded_length() > self.buckets.block_size() {
+                bucket.overflow = self.allocate()?;
+                self.write_bucket(block_number, &bucket)?;
+                block_number = bucket.overflow;
+                bucket = new_bucket();
+            }
+            bucket.entries.push(entry);
+        }
+        self.write_bucket(block_number, &bucket)
+    }
+
+    fn add_overflow(
+        &self,
+        mut chain: Vec<(usize, Bucket)>,
+        entry: Entry,
+    ) -> Result<(), HashIndexError> {
+        let overflow = self.allocate()?;
+        let head = chain[0].0;
+        let (last_block_number, last) = chain.last_mut().unwrap();
+        self.write_bucket(
+            overflow,
+            &Bucket {
+                local_depth: last.local_depth,
+                overflow: 0,
+                head,
+                entries: vec![entry],
+            },
+        )?;
+        last.overflow = overflow;
+        self.write_bucket(*last_block_number, last)
+    }
+
+    /// The bucket followed by its overflow buckets
+    fn read_chain(
+        &self,
+        bucket_number: usize,
+        for_update: bool,
+    ) -> Result<Vec<(usize, Bucket)>, HashIndexError> {
+        let mut chain = vec![];
+        let mut block_number = bucket_number;
+        loop {
+            let bucket = self.read_bucket(block_number, for_update)?;
+            if block_number != bucket_number && bucket.head != bucket_number {
+                // Freed and reused by another chain after the previous bucket was read
+                chain.clear();
+                block_number = bucket_number;
+                continue;
+            }
+            let overflow = bucket.overflow;
+            chain.push((block_number, bucket));
+            if overflow == 0 {
+                return Ok(chain);
+            }
+            block_number = overflow;
+        }
+    }
+
+    fn read_bucket(&self, block_number: usize, for_update: bool) -> Result<Bucket, HashIndexError> {
+        let bytes = Self::read_block(&self.buckets, block_number, for_update)?;
+        let entry_count = usize::from(&SmallCount::deserialize(&bytes[ENTRY_COUNT_OFFSET..]));
+        let mut offset = HEADER_LENGTH;
+        let mut entries = Vec::with_capacity(entry_count);
+        for _ in 0..entry_count {
+            let key_length = usize::from(&SmallCount::deserialize(&bytes[offset..]));
+            let key = bytes[offset + 2..offset + 2 + key_length].to_vec();
+            offset += 2 + key_length;
+            entries.push(Entry {
+                key,
+                block_number: usize::from(&Count::deserialize(&bytes[offset..])),
+                slot: usize::from(&SmallCount::deserialize(&bytes[offset + 4..])),
+            });
+            offset += RECORD_ID_LENGTH;
+        }
+        Ok(Bucket {
+            local_depth: usize::from(&TinyCount::deserialize(&bytes[LOCAL_DEPTH_OFFSET..])),
+            overflow: usize::from(&Count::deserialize(&bytes[OVERFLOW_OFFSET..])),
+            head: usize::from(&Count::deserialize(&bytes[HEAD_OFFSET..])),
+            entries,
+        })
+    }
+
+    fn write_bucket(&self, block_number: usize, bucket: &Bucket) -> Result<(), HashIndexError> {
+        let mut bytes = vec![0u8; bucket.encoded_length()];
+        TinyCount::from(bucket.local_depth)
+            .serialize(&mut bytes[LOCAL_DEPTH_OFFSET..ENTRY_COUNT_OFFSET]);
+        SmallCount::from(bucket.entries.len())
+            .serialize(&mut bytes[ENTRY_COUNT_OFFSET..OVERFLOW_OFFSET]);
+        Count::from(bucket.overflow).serialize(&mut bytes[OVERFLOW_OFFSET..HEAD_OFFSET]);
+        Count::from(bucket.head).serialize(&mut bytes[HEAD_OFFSET..HEADER_LENGTH]);
+        let mut offset = HEADER_LENGTH;
+        for entry in &bucket.entries {
+            SmallCount::from(entry.key.len()).serialize(&mut bytes[offset..offset + 2]);
+            bytes[offset + 2..offset + 2 + entry.key.len()].copy_from_slice(&entry.key);
+            offset += 2 + entry.key.len();
+            Count::from(entry.block_number).serialize(&mut bytes[offset..offset + 4]);
+            SmallCount::from(entry.slot).serialize(&mut bytes[offset + 4..offset + 6]);
+            offset += RECORD_ID_LENGTH;
+        }
+        self.buckets
+            .write(block_number, &bytes)
+            .map_err(HashIndexError::TransactionError)
+    }
+}
+
+#[derive(Debug)]
+pub enum HashIndexError {
+    TransactionError(TransactionError),
+    KeyTooLarge(usize),
+    BlockSizeTooSmall(usize),
+}
+
+impl Display for HashIndexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HashIndexError::TransactionError(e) => write!(f, "HashIndex {}", e),
+            HashIndexError::KeyTooLarge(length) => {
+                write!(f, "HashIndex: key of {} bytes is too large", length)
+            }
+            HashIndexError::BlockSizeTooSmall(block_size) => {
+                write!(f, "HashIndex: block size {} is too small", block_size)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datatypes::fixed_length_integers::BigInteger;
+    use crate::datatypes::varchar::Varchar;
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::file_management::block_id::{BlockId, DbFilename};
+    use crate::index_management::hash_index::HashIndex;
+    use crate::record_management::record_page::SlotId;
+    use crate::record_management::table_scan::RecordId;
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+
+    fn record_id(nr: usize) -> RecordId {
+        RecordId::new(
+            BlockId::new(DbFilename::from("hash.tbl"), nr / 10),
+            SlotId::from(nr % 10),
+        )
+    }
+
+    #[test]
+    fn test_hash_index_grows_and_finds_keys() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("hash_index")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .build()
+            .unwrap();
+        let filename = DbFilename::from("hash.idx");
+        let table_filename = DbFilename::from("hash.tbl");
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let index = HashIndex::<BigInteger>::new(&tx, &filename, &table_filename).unwrap();
+        for nr in 0..300 {
+            index
+                .insert(&BigInteger::from(nr as i64 % 150), &record_id(nr))
+                .unwrap();
+        }
+        index.insert(&BigInteger::from(0), &record_id(0)).unwrap();
+        assert!(
+            index.global_depth().unwrap() >= 4,
+            "The directory has to grow"
+        );
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let index = HashIndex::<BigInteger>::new(&tx, &filename, &table_filename).unwrap();
+        assert_eq!(
+            index.lookup(&BigInteger::from(42)).unwrap(),
+            vec![record_id(42), record_id(192)]
+        );
+        assert!(index.lookup(&BigInteger::from(150)).unwrap().is_empty());
+        let mut entries = 0;
+        for bucket_number in index.bucket_numbers().unwrap() {
+            entries += index.bucket(bucket_number).unwrap().len();
+        }
+        assert_eq!(entries, 300);
+
+        assert!(index.delete(&BigInteger::from(42), &record_id(42)).unwrap());
+        assert!(!index.delete(&BigInteger::from(42), &record_id(42)).unwrap());
+        assert_eq!(
+            index.lookup(&BigInteger::from(42)).unwrap(),
+            vec![record_id(192)]
+        );
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_hash_index_overflows_duplicate_keys() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("hash_index_duplicates")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .build()
+            .unwrap();
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let index = HashIndex::<Varchar>::new(
+            &tx,
+            &DbFilename::from("hash.idx"),
+            &DbFilename::from("hash.tbl"),
+        )
+        .unwrap();
+        for nr in 0..50 {
+            index
+                .insert(&Varchar::from("duplicate"), &record_id(nr))
+                .unwrap();
+        }
+        index
+            .insert(&Varchar::from("other"), &record_id(50))
+            .unwrap();
+        assert_eq!(index.lookup(&Varchar::from("duplicate")).unwrap().len(), 50);
+        assert_eq!(
+            index.lookup(&Varchar::from("other")).unwrap(),
+            vec![record_id(50)]
+        );
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_hash_index_reuses_emptied_overflow_buckets() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("hash_index_free_list")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .build()
+            .unwrap();
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let index = HashIndex::<Varchar>::new(
+            &tx,
+            &DbFilename::from("hash.idx"),
+            &DbFilename::from("hash.tbl"),
+        )
+        .unwrap();
+        let key = Varchar::from("duplicate");
+        for nr in 0..50 {
+            index.insert(&key, &record_id(nr)).unwrap();
+        }
+        let (_, bucket_number) = index
+            .bucket_number(
+                HashIndex::<Varchar>::hash(&HashIndex::serialize(&key)),
+                false,
+            )
+            .unwrap();
+        let chain_length = index.read_chain(bucket_number, false).unwrap().len();
+        let blocks = index.buckets.block_length().unwrap();
+        assert!(chain_length > 5);
+
+        for nr in 5..50 {
+            assert!(index.delete(&key, &record_id(nr)).unwrap());
+        }
+        assert!(index.read_chain(bucket_number, false).unwrap().len() < 3);
+        assert_eq!(
+            index.lookup(&key).unwrap(),
+            (0..5).map(record_id).collect::<Vec<_>>()
+        );
+
+        for nr in 5..50 {
+            index.insert(&key, &record_id(nr)).unwrap();
+        }
+        assert_eq!(
+            index.read_chain(bucket_number, false).unwrap().len(),
+            chain_length
+        );
+        assert_eq!(
+            index.buckets.block_length().unwrap(),
+            blocks,
+            "Freed blocks have to be reused"
+        );
+        assert_eq!(index.lookup(&key).unwrap().len(), 50);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_hash_index_max_key_length() {
+        assert_eq!(HashIndex::<BigInteger>::max_key_length_for(0), None);
+        assert_eq!(HashIndex::<BigInteger>::max_key_length_for(27), None);
+        assert_eq!(HashIndex::<BigInteger>::max_key_length_for(29), Some(1));
+        assert_eq!(
+            HashIndex::<BigInteger>::max_key_length_for(4096),
+            Some(2034)
+        );
+    }
+}
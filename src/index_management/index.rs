@@ -0,0 +1,185 @@
+use crate::datatypes::varbinary::Varbinary;
+use crate::index_management::btree::{BTreeError, BTreeIndex};
+use crate::index_management::hash_index::{HashIndex, HashIndexError};
+use crate::metadata_management::catalog::{IndexInfo, IndexType, TableInfo};
+use crate::record_management::layout::{Layout, LayoutError};
+use crate::record_management::schema::Schema;
+use crate::record_management::table_scan::RecordId;
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::Transaction;
+use std::fmt::{Display, Formatter};
+use std::ops::Bound;
+
+/// Index of a table column, of any of the index types registered in the catalog.
+///
+/// Hash indexes store the key encoded with a one column layout, so any column type can be used.
+/// They support only lookups by key, range queries need a B-tree.
+#[derive(Debug, Clone)]
+pub enum Index {
+    BTree(BTreeIndex),
+    Hash(HashIndex<Varbinary>, Layout),
+}
+
+impl Index {
+    pub fn open(
+        transaction: &Transaction,
+        index_info: &IndexInfo,
+        table_info: &TableInfo,
+    ) -> Result<Self, IndexError> {
+        let column = table_info
+            .layout
+            .schema()
+            .column(&index_info.column_name)
+            .ok_or_else(|| {
+                IndexError::LayoutError(LayoutError::UnknownColumn(index_info.column_name.clone()))
+            })?;
+        match index_info.index_type {
+            IndexType::BTree => BTreeIndex::new(
+                transaction,
+                &index_info.filename,
+                &table_info.filename,
+                column.column_type,
+            )
+            .map(Index::BTree)
+            .map_err(IndexError::BTreeError),
+            IndexType::Hash => {
+                HashIndex::new(transaction, &index_info.filename, &table_info.filename)
+                    .map(|index| {
+                        Index::Hash(
+                            index,
+                            Layout::new(
+                                Schema::new().with_nullable_column("key", column.column_type),
+                            ),
+                        )
+                    })
+                    .map_err(IndexError::HashIndexError)
+            }
+        }
+    }
+
+    pub fn index_type(&self) -> IndexType {
+        match self {
+            Index::BTree(_) => IndexType::BTree,
+            Index::Hash(_, _) => IndexType::Hash,
+        }
+    }
+
+    /// Adds the entry, nothing happens if it exists already
+    pub fn insert(&self, key: &Value, record_id: &RecordId) -> Result<(), IndexError> {
+        match self {
+            Index::BTree(index) => index.insert(key, record_id).map_err(IndexError::BTreeError),
+            Index::Hash(index, key_layout) => index
+                .insert(&Self::encode_key(key_layout, key)?, record_id)
+                .map_err(IndexError::HashIndexError),
+        }
+    }
+
+    /// Removes the entry, returns whether it existed
+    pub fn delete(&self, key: &Value, record_id: &RecordId) -> Result<bool, IndexError> {
+        match self {
+            Index::BTree(index) => index.delete(key, record_id).map_err(IndexError::BTreeError),
+            Index::Hash(index, key_layout) => index
+                .delete(&Self::encode_key(key_layout, key)?, record_id)
+                .map_err(IndexError::HashIndexError),
+        }
+    }
+
+    /// Entries with the key
+    pub fn lookup(&self, key: &Value) -> Result<Vec<(Value, RecordId)>, IndexError> {
+        match self {
+            Index::BTree(index) => index
+                .range(Bound::Included(key), Bound::Included(key))
+                .map_err(IndexError::BTreeError),
+            Index::Hash(index, key_layout) => Ok(index
+                .lookup(&Self::encode_key(key_layout, key)?)
+                .map_err(IndexError::HashIndexError)?
+                .into_iter()
+                .map(|record_id| (key.clone(), record_id))
+                .collect()),
+        }
+    }
+
+    /// Entries with keys within the bounds in key order, B-tree indexes only
+    pub fn range(
+        &self,
+        lower: Bound<&Value>,
+        upper: Bound<&Value>,
+    ) -> Result<Vec<(Value, RecordId)>, IndexError> {
+        self.range_limited(lower, upper, usize::MAX)
+    }
+
+    /// The first limit entries with keys within the bounds in key order and the other entries
+    /// with the key of the last one, B-tree indexes only
+    pub fn range_limited(
+        &self,
+        lower: Bound<&Value>,
+        upper: Bound<&Value>,
+        limit: usize,
+    ) -> Result<Vec<(Value, RecordId)>, IndexError> {
+        match self {
+            Index::BTree(index) => index
+                .range_limited(lower, upper, limit)
+                .map_err(IndexError::BTreeError),
+            Index::Hash(_, _) => Err(IndexError::RangeNotSupported(IndexType::Hash)),
+        }
+    }
+
+    /// All entries, in key order for B-tree indexes, by bucket for hash indexes
+    pub fn entries(&self) -> Result<Vec<(Value, RecordId)>, IndexError> {
+        match self {
+            Index::BTree(index) => index
+                .range(Bound::Unbounded, Bound::Unbounded)
+                .map_err(IndexError::BTreeError),
+            Index::Hash(index, key_layout) => {
+                let mut entries = vec![];
+                for bucket_number in index.bucket_numbers().map_err(IndexError::HashIndexError)? {
+                    for (key, record_id) in index
+                        .bucket(bucket_number)
+                        .map_err(IndexError::HashIndexError)?
+                    {
+                        let key = key_layout
+                            .decode(&Vec::from(&key))
+                            .get_value("key")
+                            .map_err(IndexError::LayoutError)?
+                            .clone();
+                        entries.push((key, record_id));
+                    }
+                }
+                Ok(entries)
+            }
+        }
+    }
+
+    fn encode_key(key_layout: &Layout, key: &Value) -> Result<Varbinary, IndexError> {
+        let mut row = key_layout.row();
+        row.set_value("key", key.clone())
+            .map_err(IndexError::LayoutError)?;
+        key_layout
+            .encode(&row)
+            .map(Varbinary::from)
+            .map_err(IndexError::LayoutError)
+    }
+}
+
+#[derive(Debug)]
+pub enum IndexError {
+    BTreeError(BTreeError),
+    HashIndexError(HashIndexError),
+    LayoutError(LayoutError),
+    RangeNotSupported(IndexType),
+}
+
+impl Display for IndexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexError::BTreeError(e) => write!(f, "Index {}", e),
+            IndexError::HashIndexError(e) => write!(f, "Index {}", e),
+            IndexError::LayoutError(e) => write!(f, "Index {}", e),
+            IndexError::RangeNotSupported(index_type) => write!(
+                f,
+                "Index: {} indexes do not support range queries",
+                index_type.name()
+            ),
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::file_management::block_id::{BlockId, DbFilename};
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+
+/// File of index blocks, read and written as a whole through a transaction.
+///
+/// Writes take the exclusive lock held until the transaction finishes and the exclusive latch of
+/// the block. Lookups only read under the shared latch, so they never wait for writers and see
+/// every block as it was before or after a write, including uncommitted changes. Changes to the
+/// index structure have to be ordered so that no entry is missing for such readers in between.
+/// Changes read with read_for_update, taking a shared lock, so concurrent changes are serialized.
+#[derive(Debug, Clone)]
+pub struct IndexFile {
+    transaction: Transaction,
+    filename: DbFilename,
+    block_size: usize,
+}
+
+impl IndexFile {
+    /// Opens the file, appending an empty first block if it has none yet
+    pub fn new(transaction: &Transaction, filename: &DbFilename) -> Result<Self, TransactionError> {
+        if transaction.block_length(filename)? == 0 {
+            transaction.append(filename)?;
+        }
+        Ok(Self {
+            transaction: transaction.clone(),
+            filename: filename.clone(),
+            block_size: usize::from(transaction.block_size()),
+        })
+    }
+
+    pub fn filename(&self) -> &DbFilename {
+        &self.filename
+    }
+
+    pub fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    pub fn block_length(&self) -> Result<usize, TransactionError> {
+        self.transaction.block_length(&self.filename)
+    }
+
+    /// Appends an empty block, returns its block number
+    pub fn append(&self) -> Result<usize, TransactionError> {
+        Ok(self.transaction.append(&self.filename)?.block_number())
+    }
+
+    /// Reads the block under its shared latch without taking a lock
+    pub fn read(&self, block_number: usize) -> Result<Vec<u8>, TransactionError> {
+        self.with_pinned(block_number, |block| {
+            self.transaction.read_latched(block, || {
+                self.transaction.read_raw_bytes(block, 0, self.block_size)
+            })?
+        })
+    }
+
+    /// Reads the block taking a shared lock held until the transaction finishes
+    pub fn read_for_update(&self, block_number: usize) -> Result<Vec<u8>, TransactionError> {
+        self.with_pinned(block_number, |block| {
+            self.transaction.get_raw_bytes(block, 0, self.block_size)
+        })
+    }
+
+    /// Writes only the bytes differing from the current content of the block, to keep the log
+    /// records small. Shorter content is padded with zeros.
+    pub fn write(&self, block_number: usize, bytes: &[u8]) -> Result<(), TransactionError> {
+        let mut bytes = bytes.to_vec();
+        bytes.resize(self.block_size, 0);
+        self.with_pinned(block_number, |block| {
+            self.transaction.write_latched(block, || {
+                let current = self.transaction.read_raw_bytes(block, 0, self.block_size)?;
+                let first = (0..self.block_size).find(|&i| bytes[i] != current[i]);
+                let last = (0..self.block_size).rfind(|&i| bytes[i] != current[i]);
+                match (first, last) {
+                    (Some(first), Some(last)) => {
+                        self.transaction
+                            .set_raw_bytes(block, first, &bytes[first..=last])
+                    }
+                    _ => Ok(()),
+                }
+            })?
+        })
+    }
+
+    fn with_pinned<R>(
+        &self,
+        block_number: usize,
+        action: impl FnOnce(&BlockId) -> Result<R, TransactionError>,
+    ) -> Result<R, TransactionError> {
+        let block = BlockId::new(self.filename.clone(), block_number);
+        self.transaction.pin(&block)?;
+        let result = action(&block);
+        self.transaction.unpin(&block)?;
+        result
+    }
+}
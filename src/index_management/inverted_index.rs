@@ -0,0 +1,459 @@
+use crate::datatypes::fixed_length_counts::{BigCount, Count, SmallCount};
+use crate::datatypes::varcount::Varcount;
+use crate::datatypes::HfdbSerializableDatatype;
+use crate::file_management::block_id::{BlockId, DbFilename};
+use crate::index_management::btree::{BTreeError, BTreeIndex};
+use crate::index_management::index_file::IndexFile;
+use crate::record_management::record_page::SlotId;
+use crate::record_management::schema::ColumnType;
+use crate::record_management::table_scan::RecordId;
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use std::collections::BTreeMap;
+use std::fmt::{Display, Formatter};
+
+const STATISTICS_BLOCK_NUMBER: usize = 0;
+const NEXT_OFFSET: usize = 0;
+const USED_OFFSET: usize = 4;
+const BLOCK_HEADER_LENGTH: usize = 6;
+const TAIL_OFFSET: usize = 6;
+const DOCUMENT_FREQUENCY_OFFSET: usize = 10;
+const LAST_DOCUMENT_OFFSET: usize = 14;
+const HEAD_HEADER_LENGTH: usize = 22;
+
+/// Occurrences of a term in one document: the positions of the tokens within the document
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Posting {
+    pub document: u64,
+    pub positions: Vec<usize>,
+}
+
+impl Posting {
+    pub fn term_frequency(&self) -> usize {
+        self.positions.len()
+    }
+}
+
+/// Numbers over all indexed documents, kept in block 0 of the postings file
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq)]
+pub struct CollectionStatistics {
+    pub next_document: u64,
+    pub document_count: u64,
+    pub total_length: u64,
+}
+
+impl CollectionStatistics {
+    pub fn average_length(&self) -> f64 {
+        match self.document_count {
+            0 => 0.0,
+            count => self.total_length as f64 / count as f64,
+        }
+    }
+}
+
+/// Header of the first block of a posting list
+#[derive(Debug, Clone, Copy)]
+struct ListHeader {
+    tail: usize,
+    document_frequency: usize,
+    last_document: u64,
+}
+
+/// Inverted index mapping terms to posting lists, for full-text search.
+///
+/// The dictionary is a B+tree on the terms, the record id of an entry points to the head block of
+/// the posting list in the postings file. A posting list is a chain of blocks, each starting with
+/// the number of the next block (0 for the last one) and the number of bytes used. The head block
+/// also holds the tail block, the number of postings and the last document. The postings are a
+/// byte stream continued in the next block: the difference to the previous document number, the
+/// term frequency and the differences between the positions, all as Varcount.
+///
+/// Documents get increasing numbers, so postings are only ever appended. Removing a document only
+/// updates the statistics, its postings stay in the lists and readers have to skip documents that
+/// no longer exist.
+///
+/// Changes read the blocks they update with shared locks, searches read without locks (see
+/// IndexFile). The head block of a list is written last, so a search never reads more postings
+/// than the list holds.
+#[derive(Debug, Clone)]
+pub struct InvertedIndex {
+    dictionary: BTreeIndex,
+    postings: IndexFile,
+    block_size: usize,
+}
+
+impl InvertedIndex {
+    pub fn new(
+        transaction: &Transaction,
+        dictionary_filename: &DbFilename,
+        postings_filename: &DbFilename,
+    ) -> Result<Self, InvertedIndexError> {
+        let postings = IndexFile::new(transaction, postings_filename)
+            .map_err(InvertedIndexError::TransactionError)?;
+        Ok(Self {
+            dictionary: BTreeIndex::new(
+                transaction,
+                dictionary_filename,
+                postings_filename,
+                ColumnType::Varbinary,
+            )
+            .map_err(InvertedIndexError::BTreeError)?,
+            block_size: postings.block_size(),
+            postings,
+        })
+    }
+
+    /// Longest term the dictionary can hold
+    pub fn max_term_length(&self) -> usize {
+        // Null bitmap and Varcount length of the encoded key
+        self.dictionary.max_key_length() - 3
+    }
+
+    pub fn statistics(&self) -> Result<CollectionStatistics, InvertedIndexError> {
+        Ok(Self::decode_statistics(
+            &self.read(STATISTICS_BLOCK_NUMBER)?,
+        ))
+    }
+
+    fn statistics_for_update(&self) -> Result<CollectionStatistics, InvertedIndexError> {
+        Ok(Self::decode_statistics(
+            &self.read_for_update(STATISTICS_BLOCK_NUMBER)?,
+        ))
+    }
+
+    fn decode_statistics(bytes: &[u8]) -> CollectionStatistics {
+        CollectionStatistics {
+            next_document: u64::from(&BigCount::deserialize(&bytes[0..])),
+            document_count: u64::from(&BigCount::deserialize(&bytes[8..])),
+            total_length: u64::from(&BigCount::deserialize(&bytes[16..])),
+        }
+    }
+
+    /// Adds the document with the positions of its terms and its length in tokens, returns the
+    /// number it got
+    pub fn add_document(
+        &self,
+        terms: &BTreeMap<Vec<u8>, Vec<usize>>,
+        length: usize,
+    ) -> Result<u64, InvertedIndexError> {
+        let mut statistics = self.statistics_for_update()?;
+        let document = statistics.next_document;
+        for (term, positions) in terms {
+            if term.len() > self.max_term_length() {
+                return Err(InvertedIndexError::TermTooLong(term.len()));
+            }
+            let head = match self.head(term)? {
+                Some(head) => head,
+                None => self.create_list(term)?,
+            };
+            self.append(head, document, positions)?;
+        }
+        statistics.next_document += 1;
+        statistics.document_count += 1;
+        statistics.total_length += length as u64;
+        self.write_statistics(&statistics)?;
+        Ok(document)
+    }
+
+    /// Removes the document of the given length from the statistics
+    pub fn remove_document(&self, length: usize) -> Result<(), InvertedIndexError> {
+        let mut statistics = self.statistics_for_update()?;
+        statistics.document_count = statistics.document_count.saturating_sub(1);
+        statistics.total_length = statistics.total_length.saturating_sub(length as u64);
+        self.write_statistics(&statistics)
+    }
+
+    /// Number of documents containing the term, including removed ones
+    pub fn document_frequency(&self, term: &[u8]) -> Result<usize, InvertedIndexError> {
+        match self.head(term)? {
+            Some(head) => Ok(Self::list_header(&self.read(head)?).document_frequency),
+            None => Ok(0),
+        }
+    }
+
+    /// Postings of the term in document order, including those of removed documents
+    pub fn postings(&self, term: &[u8]) -> Result<Vec<Posting>, InvertedIndexError> {
+        let Some(head) = self.head(term)? else {
+            return Ok(vec![]);
+        };
+        let mut block_number = head;
+        let mut block = self.read(head)?;
+        let header = Self::list_header(&block);
+        let mut stream = vec![];
+        loop {
+            let start = Self::data_start(block_number == head);
+            let used = usize::from(&SmallCount::deserialize(&block[USED_OFFSET..]));
+            stream.extend_from_slice(&block[start..start + used]);
+            block_number = usize::from(&Count::deserialize(&block[NEXT_OFFSET..]));
+            if block_number == 0 {
+                break;
+            }
+            block = self.read(block_number)?;
+        }
+
+        let mut offset = 0;
+        let mut next = |stream: &[u8]| {
+            let value = Varcount::deserialize(&stream[offset..]);
+            offset += value.serialized_length();
+            u64::from(&value)
+        };
+        let mut postings = Vec::with_capacity(header.document_frequency);
+        let mut document = 0;
+        for _ in 0..header.document_frequency {
+            document += next(&stream);
+            let frequency = next(&stream) as usize;
+            let mut position = 0;
+            let mut positions = Vec::with_capacity(frequency);
+            for _ in 0..frequency {
+                position += next(&stream) as usize;
+                positions.push(position);
+            }
+            postings.push(Posting {
+                document,
+                positions,
+            });
+        }
+        Ok(postings)
+    }
+
+    fn head(&self, term: &[u8]) -> Result<Option<usize>, InvertedIndexError> {
+        Ok(self
+            .dictionary
+            .lookup(&Value::from(term.to_vec()))
+            .map_err(InvertedIndexError::BTreeError)?
+            .first()
+            .map(|record_id| record_id.block.block_number()))
+    }
+
+    fn create_list(&self, term: &[u8]) -> Result<usize, InvertedIndexError> {
+        let head = self
+            .postings
+            .append()
+            .map_err(InvertedIndexError::TransactionError)?;
+        let mut block = vec![0u8; self.block_size];
+        Count::from(head).serialize(&mut block[TAIL_OFFSET..DOCUMENT_FREQUENCY_OFFSET]);
+        self.write(head, &block)?;
+        let record_id = RecordId::new(
+            BlockId::new(self.postings.filename().clone(), head),
+            SlotId::from(0),
+        );
+        self.dictionary
+            .insert(&Value::from(term.to_vec()), &record_id)
+            .map_err(InvertedIndexError::BTreeError)?;
+        Ok(head)
+    }
+
+    /// Appends the posting to the stream in the tail block, chaining new blocks as needed
+    fn append(
+        &self,
+        head: usize,
+        document: u64,
+        positions: &[usize],
+    ) -> Result<(), InvertedIndexError> {
+        let mut head_block = self.read_for_update(head)?;
+        let mut header = Self::list_header(&head_block);
+        let delta = match header.document_frequency {
+            0 => document,
+            _ => document - header.last_document,
+        };
+        let mut values = vec![Varcount::from(delta), Varcount::from(positions.len())];
+        let mut previous = 0;
+        for &position in positions {
+            values.push(Varcount::from(position - previous));
+            previous = position;
+        }
+        let mut encoded = vec![0u8; values.iter().map(|v| v.serialized_length()).sum()];
+        let mut offset = 0;
+        for value in values {
+            value.serialize(&mut encoded[offset..]);
+            offset += value.serialized_length();
+        }
+
+        let mut block_number = header.tail;
+        let mut block = match block_number == head {
+            true => head_block.clone(),
+            false => self.read_for_update(block_number)?,
+        };
+        let mut remaining = encoded.as_slice();
+        loop {
+            let start = Self::data_start(block_number == head);
+            let used = usize::from(&SmallCount::deserialize(&block[USED_OFFSET..]));
+            let length = remaining.len().min(self.block_size - start - used);
+            block[start + used..start + used + length].copy_from_slice(&remaining[..length]);
+            SmallCount::from(used + length).serialize(&mut block[USED_OFFSET..BLOCK_HEADER_LENGTH]);
+            remaining = &remaining[length..];
+            if remaining.is_empty() {
+                break;
+            }
+            let next = self
+                .postings
+                .append()
+                .map_err(InvertedIndexError::TransactionError)?;
+            Count::from(next).serialize(&mut block[NEXT_OFFSET..USED_OFFSET]);
+            self.store(head, &mut head_block, block_number, block)?;
+            block_number = next;
+            block = vec![0u8; self.block_size];
+        }
+        self.store(head, &mut head_block, block_number, block)?;
+
+        header.tail = block_number;
+        header.document_frequency += 1;
+        header.last_document = document;
+        Count::from(header.tail).serialize(&mut head_block[TAIL_OFFSET..DOCUMENT_FREQUENCY_OFFSET]);
+        Count::from(header.document_frequency)
+            .serialize(&mut head_block[DOCUMENT_FREQUENCY_OFFSET..LAST_DOCUMENT_OFFSET]);
+        BigCount::from(header.last_document)
+            .serialize(&mut head_block[LAST_DOCUMENT_OFFSET..HEAD_HEADER_LENGTH]);
+        self.write(head, &head_block)
+    }
+
+    /// Writes the block, or keeps it as the head block to be written last
+    fn store(
+        &self,
+        head: usize,
+        head_block: &mut Vec<u8>,
+        block_number: usize,
+        block: Vec<u8>,
+    ) -> Result<(), InvertedIndexError> {
+        match block_number == head {
+            true => *head_block = block,
+            false => self.write(block_number, &block)?,
+        }
+        Ok(())
+    }
+
+    fn list_header(head_block: &[u8]) -> ListHeader {
+        ListHeader {
+            tail: usize::from(&Count::deserialize(&head_block[TAIL_OFFSET..])),
+            document_frequency: usize::from(&Count::deserialize(
+                &head_block[DOCUMENT_FREQUENCY_OFFSET..],
+            )),
+            last_document: u64::from(&BigCount::deserialize(&head_block[LAST_DOCUMENT_OFFSET..])),
+        }
+    }
+
+    fn data_start(is_head: bool) -> usize {
+        match is_head {
+            true => HEAD_HEADER_LENGTH,
+            false => BLOCK_HEADER_LENGTH,
+        }
+    }
+
+    fn write_statistics(
+        &self,
+        statistics: &CollectionStatistics,
+    ) -> Result<(), InvertedIndexError> {
+        let mut bytes = vec![0u8; 24];
+        BigCount::from(statistics.next_document).serialize(&mut bytes[0..8]);
+        BigCount::from(statistics.document_count).serialize(&mut bytes[8..16]);
+        BigCount::from(statistics.total_length).serialize(&mut bytes[16..24]);
+        self.write(STATISTICS_BLOCK_NUMBER, &bytes)
+    }
+
+    fn read(&self, block_number: usize) -> Result<Vec<u8>, InvertedIndexError> {
+        self.postings
+            .read(block_number)
+            .map_err(InvertedIndexError::TransactionError)
+    }
+
+    fn read_for_update(&self, block_number: usize) -> Result<Vec<u8>, InvertedIndexError> {
+        self.postings
+            .read_for_update(block_number)
+            .map_err(InvertedIndexError::TransactionError)
+    }
+
+    fn write(&self, block_number: usize, bytes: &[u8]) -> Result<(), InvertedIndexError> {
+        self.postings
+            .write(block_number, bytes)
+            .map_err(InvertedIndexError::TransactionError)
+    }
+}
+
+#[derive(Debug)]
+pub enum InvertedIndexError {
+    TransactionError(TransactionError),
+    BTreeError(BTreeError),
+    TermTooLong(usize),
+}
+
+impl Display for InvertedIndexError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InvertedIndexError::TransactionError(e) => write!(f, "InvertedIndex {}", e),
+            InvertedIndexError::BTreeError(e) => write!(f, "InvertedIndex {}", e),
+            InvertedIndexError::TermTooLong(length) => {
+                write!(f, "InvertedIndex: term of {} bytes is too long", length)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::file_management::block_id::DbFilename;
+    use crate::index_management::inverted_index::{InvertedIndex, Posting};
+    use crate::utils::logging::init_logging;
+    use std::collections::BTreeMap;
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn test_inverted_index_postings_span_blocks() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("inverted_index")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(200).unwrap()))
+            .build()
+            .unwrap();
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let index = InvertedIndex::new(
+            &tx,
+            &DbFilename::from("inverted.dict"),
+            &DbFilename::from("inverted.post"),
+        )
+        .unwrap();
+        for document in 0..100usize {
+            let mut terms = BTreeMap::new();
+            terms.insert(b"every".to_vec(), vec![0, 7, 300 + document]);
+            if document % 10 == 3 {
+                terms.insert(b"some".to_vec(), vec![1]);
+            }
+            assert_eq!(index.add_document(&terms, 4).unwrap(), document as u64);
+        }
+        index.remove_document(4).unwrap();
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let index = InvertedIndex::new(
+            &tx,
+            &DbFilename::from("inverted.dict"),
+            &DbFilename::from("inverted.post"),
+        )
+        .unwrap();
+        let statistics = index.statistics().unwrap();
+        assert_eq!(statistics.next_document, 100);
+        assert_eq!(statistics.document_count, 99);
+        assert_eq!(statistics.average_length(), 4.0);
+
+        let every = index.postings(b"every").unwrap();
+        assert_eq!(every.len(), 100);
+        assert_eq!(
+            every[42],
+            Posting {
+                document: 42,
+                positions: vec![0, 7, 342]
+            }
+        );
+        assert_eq!(index.document_frequency(b"some").unwrap(), 10);
+        assert_eq!(
+            index
+                .postings(b"some")
+                .unwrap()
+                .iter()
+                .map(|posting| posting.document)
+                .collect::<Vec<_>>(),
+            (0..10).map(|i| i * 10 + 3).collect::<Vec<_>>()
+        );
+        assert!(index.postings(b"none").unwrap().is_empty());
+        tx.commit().unwrap();
+    }
+}
@@ -1,5 +1,12 @@
 pub mod datatypes;
 pub mod db_management_system;
 pub mod file_management;
+pub mod index_management;
 pub mod memory_management;
+pub mod metadata_management;
+pub mod query_processing;
+pub mod record_management;
+pub mod server;
+pub mod shell;
+pub mod transaction_management;
 pub mod utils;
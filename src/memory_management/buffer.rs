@@ -6,9 +6,9 @@ use log::debug;
 use std::fmt::Display;
 use std::num::NonZeroUsize;
 use std::ops::DerefMut;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Condvar, Mutex, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
-#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
 pub struct TransactionNumber(NonZeroUsize);
 
 impl From<u64> for TransactionNumber {
@@ -17,6 +17,18 @@ impl From<u64> for TransactionNumber {
     }
 }
 
+impl From<TransactionNumber> for u64 {
+    fn from(nr: TransactionNumber) -> Self {
+        nr.0.get() as u64
+    }
+}
+
+impl Display for TransactionNumber {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
 #[derive(Debug)]
 struct BufferData {
     page: Page,
@@ -24,6 +36,12 @@ struct BufferData {
     transaction: Option<TransactionNumber>,
     log_sequence_number: Option<LogSequenceNumber>,
     pins_count: usize,
+    /// The page is being written back and replaced by the block, see [Buffer::load]
+    loading: bool,
+    /// Block the page belonged to while it is written back
+    writing_back: Option<BlockId>,
+    /// A copy of the page is being written by [Buffer::flush]
+    flushing: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -31,6 +49,9 @@ pub struct Buffer {
     file_manager: FileManager,
     log_manager: LogManager,
     data: Arc<Mutex<BufferData>>,
+    /// Notified when loading ends
+    loaded: Arc<Condvar>,
+    latch: Arc<RwLock<()>>,
 }
 
 impl Display for Buffer {
@@ -50,10 +71,25 @@ impl Buffer {
                 transaction: None,
                 log_sequence_number: None,
                 pins_count: 0,
+                loading: false,
+                writing_back: None,
+                flushing: false,
             })),
+            loaded: Arc::new(Condvar::new()),
+            latch: Arc::new(RwLock::new(())),
         }
     }
 
+    /// Short-term latch for reading a page consistently while it is changed by several
+    /// modifications (e.g. moving records), independent of transaction locks
+    pub fn latch_shared(&self) -> RwLockReadGuard<'_, ()> {
+        self.latch.read().unwrap()
+    }
+
+    pub fn latch_exclusive(&self) -> RwLockWriteGuard<'_, ()> {
+        self.latch.write().unwrap()
+    }
+
     pub fn page(&self) -> Page {
         self.data.lock().unwrap().page.clone()
     }
@@ -64,7 +100,7 @@ impl Buffer {
 
     pub fn modify_page<R>(
         &mut self,
-        modifier: fn(&mut Page) -> R,
+        modifier: impl FnOnce(&mut Page) -> R,
         transaction_number: TransactionNumber,
         log_sequence_number: Option<LogSequenceNumber>,
     ) -> R {
@@ -88,57 +124,131 @@ impl Buffer {
         !self.is_pinned()
     }
 
+    /// Not pinned and not being flushed, so it can be assigned to another block
+    pub fn is_replaceable(&self) -> bool {
+        let data = self.data.lock().unwrap();
+        data.pins_count == 0 && !data.flushing
+    }
+
     pub fn modifying_transaction_number(&self) -> Option<TransactionNumber> {
         self.data.lock().unwrap().transaction
     }
 
-    pub fn assign_to_block(&mut self, block_id: BlockId) -> Result<(), IoError> {
-        let mut data_guard = self.data.lock().unwrap();
-        let locked_data = data_guard.deref_mut();
+    /// Block whose page is being written back, it must not be read before that ends
+    pub fn writing_back(&self) -> Option<BlockId> {
+        self.data.lock().unwrap().writing_back.clone()
+    }
+
+    /// Assigns the unpinned buffer to the block without any I/O, so it can be done under the
+    /// buffer pool lock. The buffer is loading until [Buffer::load] is done.
+    pub fn start_loading(&self, block_id: BlockId) {
+        let mut data = self.data.lock().unwrap();
         debug!(
-            "Buffer: Assigning block {:?} (previous: {:?}) buffer {:?}",
-            block_id, locked_data.block, self
+            "Buffer: Start loading block {:?} (previous: {:?})",
+            block_id, data.block
         );
+        if data.transaction.is_some() {
+            data.writing_back = data.block.clone();
+        }
+        data.block = Some(block_id);
+        data.loading = true;
+    }
 
-        self._flush(locked_data)?;
+    /// Writes back the former page if it was modified and reads the block started loading.
+    /// Only the pinning thread calls it, others pinning the block wait in [Buffer::wait_loaded].
+    /// If writing back fails, the buffer keeps the former block, if reading fails, it has none.
+    pub fn load(&self) -> Result<(), IoError> {
+        let (block, writing_back, log_sequence_number, page) = {
+            let data = self.data.lock().unwrap();
+            (
+                data.block
+                    .clone()
+                    .expect("Buffer: Block not set when loading"),
+                data.writing_back.clone(),
+                data.log_sequence_number,
+                data.page.clone(),
+            )
+        };
+        let result = self.write_back(writing_back, log_sequence_number, &page);
+        let written_back = result.is_ok();
+        let result = result.and_then(|_| self.file_manager.read(&block, &page));
 
-        locked_data.block = Some(block_id.clone());
-        debug!(
-            "Buffer: Assigning to block={:?}, read file_manager={:?} contents={:?}",
-            &block_id, self.file_manager, locked_data.page
-        );
-        self.file_manager.read(&block_id, &locked_data.page)?;
-        debug!(
-            "Buffer: Assigning to block={:?}, set pins_count=0",
-            &block_id
-        );
+        let mut data = self.data.lock().unwrap();
+        if !written_back {
+            data.block = data.writing_back.clone();
+        } else if result.is_err() {
+            // The page does not hold the block, a later pin has to read it again
+            data.block = None;
+        } else {
+            debug!("Buffer: Loaded block {:?}", block);
+        }
+        if written_back {
+            data.transaction = None;
+            data.log_sequence_number = None;
+        }
+        data.writing_back = None;
+        data.loading = false;
+        self.loaded.notify_all();
+        result
+    }
 
-        locked_data.pins_count = 0;
-        Ok(())
+    fn write_back(
+        &self,
+        block: Option<BlockId>,
+        log_sequence_number: Option<LogSequenceNumber>,
+        page: &Page,
+    ) -> Result<(), IoError> {
+        let Some(block) = block else {
+            return Ok(());
+        };
+        debug!("Buffer: Write back block {:?}", block);
+        if let Some(lsn) = log_sequence_number {
+            self.log_manager.flush_before_page_write(lsn)?;
+        }
+        self.file_manager.write(&block, page)
     }
 
-    fn _flush(&self, locked_data: &mut BufferData) -> Result<(), IoError> {
-        if locked_data.transaction.is_some() {
-            debug!("Buffer: Flush {}", self);
-            let block = locked_data
-                .block
-                .clone()
-                .expect("Buffer: Block not set when trying to flush");
-            if let Some(lsn) = locked_data.log_sequence_number {
-                self.log_manager.flush(lsn)?;
-            }
-            self.file_manager.write(&block, &locked_data.page)?;
-            locked_data.transaction = None;
-        } else {
-            debug!("Flushing? No transaction number => no flush")
+    /// Waits until the buffer is loaded, true if it holds the block then
+    pub fn wait_loaded(&self, block_id: &BlockId) -> bool {
+        let mut data = self.data.lock().unwrap();
+        while data.loading {
+            data = self.loaded.wait(data).unwrap();
         }
-        Ok(())
+        data.block.as_ref() == Some(block_id)
     }
 
+    /// Writes the page if it was modified. A copy of it is written without holding the buffer
+    /// data, so the buffer can be found and modified meanwhile, but it is not replaced.
     pub fn flush(&self) -> Result<(), IoError> {
-        let mut data_guard = self.data.lock().unwrap();
-        let locked_data = data_guard.deref_mut();
-        self._flush(locked_data)
+        let (block, log_sequence_number, page, transaction) = {
+            let mut data = self.data.lock().unwrap();
+            while data.loading || data.flushing {
+                data = self.loaded.wait(data).unwrap();
+            }
+            let Some(transaction) = data.transaction.take() else {
+                debug!("Flushing? No transaction number => no flush");
+                return Ok(());
+            };
+            debug!("Buffer: Flush block {:?}", data.block);
+            data.flushing = true;
+            (
+                data.block
+                    .clone()
+                    .expect("Buffer: Block not set when trying to flush"),
+                data.log_sequence_number,
+                Page::from_vec(data.page.get_contents()),
+                transaction,
+            )
+        };
+        let result = self.write_back(Some(block), log_sequence_number, &page);
+
+        let mut data = self.data.lock().unwrap();
+        if result.is_err() && data.transaction.is_none() {
+            data.transaction = Some(transaction);
+        }
+        data.flushing = false;
+        self.loaded.notify_all();
+        result
     }
 
     pub fn increment_pins_count(&self) {
@@ -174,7 +284,7 @@ mod tests {
     #[test]
     fn test_buffer_cloning() {
         let file_manager = FileManagerBuilder::unittest("buffer_test_cloning")
-            .block_size(NonZeroUsize::new(100 as usize).unwrap())
+            .block_size(NonZeroUsize::new(100).unwrap())
             .build()
             .unwrap();
         let log_manager =
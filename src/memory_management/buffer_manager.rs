@@ -1,9 +1,13 @@
 use crate::file_management::block_id::BlockId;
 use crate::file_management::file_manager::{FileManager, IoError};
 use crate::memory_management::buffer::{Buffer, TransactionNumber};
-use crate::memory_management::buffer_manager::BufferManagerError::{DeadLockTimeout, NoCapacity};
+use crate::memory_management::buffer_manager::BufferManagerError::{
+    DeadLockTimeout, DeadLockVictim, NoCapacity,
+};
 use crate::memory_management::log_manager::LogManager;
+use crate::transaction_management::wait_for_graph::{WaitForGraph, WaitKind};
 use log::{debug, warn};
+use std::collections::{BTreeSet, HashMap};
 use std::fmt::{Display, Formatter};
 use std::ops::DerefMut;
 use std::sync::{Arc, Condvar, Mutex};
@@ -14,6 +18,8 @@ pub struct BufferManager {
     pool: Vec<Buffer>,
     num_available: Arc<Mutex<usize>>,
     buffer_available: Arc<Condvar>,
+    pins_by_transaction: Arc<Mutex<HashMap<Option<TransactionNumber>, usize>>>,
+    wait_for_graph: WaitForGraph,
     deadlock_waiting_duration: Duration,
 }
 
@@ -83,10 +89,16 @@ impl BufferManager {
             pool,
             num_available: Arc::new(Mutex::new(pool_size)),
             buffer_available: Arc::new(Condvar::new()),
+            pins_by_transaction: Arc::new(Mutex::new(HashMap::new())),
+            wait_for_graph: WaitForGraph::new(),
             deadlock_waiting_duration,
         }
     }
 
+    pub fn pool_size(&self) -> usize {
+        self.pool.len()
+    }
+
     pub fn num_available(&self) -> usize {
         *self
             .num_available
@@ -94,18 +106,43 @@ impl BufferManager {
             .expect("failed to lock num_available in BufferManager:num_available")
     }
 
+    /// Shared with the LockTable, so deadlocks of transactions waiting for buffers and locks are
+    /// detected
+    pub fn wait_for_graph(&self) -> &WaitForGraph {
+        &self.wait_for_graph
+    }
+
     pub fn flush_all(&self, transaction_number: TransactionNumber) -> Result<(), IoError> {
-        for buffer in self
+        let result = self
             .pool
             .iter()
             .filter(|buffer| buffer.modifying_transaction_number() == Some(transaction_number))
-        {
-            buffer.flush()?
-        }
-        Ok(())
+            .try_for_each(|buffer| buffer.flush());
+        // Buffers being flushed could not be replaced meanwhile
+        self.notify_loaded();
+        result
+    }
+
+    /// Flushes the buffers modified by any transaction
+    pub fn flush_modified(&self) -> Result<(), IoError> {
+        let result = self
+            .pool
+            .iter()
+            .filter(|buffer| buffer.modifying_transaction_number().is_some())
+            .try_for_each(|buffer| buffer.flush());
+        self.notify_loaded();
+        result
     }
 
     pub fn unpin(&self, buffer: &Buffer) {
+        self.unpin_for(buffer, None)
+    }
+
+    pub fn unpin_for_transaction(&self, buffer: &Buffer, transaction_number: TransactionNumber) {
+        self.unpin_for(buffer, Some(transaction_number))
+    }
+
+    fn unpin_for(&self, buffer: &Buffer, transaction_number: Option<TransactionNumber>) {
         let mut num_available_guard = self
             .num_available
             .lock()
@@ -116,47 +153,125 @@ impl BufferManager {
             buffer, num_available
         );
         buffer.decrement_pins_count();
-        *num_available += 1;
-        debug!(
-            "Unpinned buffer (notify other threads): {:?} num_available_after={}",
-            buffer, num_available
-        );
-        self.buffer_available.notify_one();
+        self.count_pin(transaction_number, false);
+        if buffer.is_not_pinned() {
+            *num_available += 1;
+            debug!(
+                "Unpinned buffer (notify other threads): {:?} num_available_after={}",
+                buffer, num_available
+            );
+            self.buffer_available.notify_one();
+        }
     }
 
     pub fn pin(&self, block_id: &BlockId) -> Result<Buffer, BufferManagerError> {
-        self.try_to_pin(block_id)
+        self.try_to_pin(block_id, None).map(|(buffer, _)| buffer)
     }
 
-    fn try_to_pin(&self, block_id: &BlockId) -> Result<Buffer, BufferManagerError> {
-        let existing_buffer = self
-            .pool
-            .iter()
-            .find(|buffer| buffer.block() == Some(block_id.clone()));
-        debug!(
-            "try to pin: existing_buffer: {:?} for block_id {:?}",
-            existing_buffer, block_id
-        );
-        let buffer = match existing_buffer {
-            Some(buffer) => buffer.clone(),
-            None => {
-                let mut buffer = self.choose_unpinned_buffer()?;
-                buffer
-                    .assign_to_block(block_id.clone())
-                    .map_err(BufferManagerError::StdIoError)?;
-                buffer
+    /// Pins done for a transaction let a transaction waiting for a buffer know which transactions
+    /// it waits for, so deadlocks can be detected. Also tells whether the block had to be read
+    /// from disk or was already in the buffer pool.
+    pub fn pin_for_transaction(
+        &self,
+        block_id: &BlockId,
+        transaction_number: TransactionNumber,
+    ) -> Result<(Buffer, bool), BufferManagerError> {
+        self.try_to_pin(block_id, Some(transaction_number))
+    }
+
+    /// Pins the buffer holding the block, or an unpinned one to load the block into. Finding and
+    /// pinning the buffer happens while holding the num_available lock, so two threads can
+    /// neither assign the same unpinned buffer nor the same block to two buffers. Writing back
+    /// the former page and reading the block is done after releasing the lock, threads pinning
+    /// the same block meanwhile wait for the buffer to be loaded.
+    fn try_to_pin(
+        &self,
+        block_id: &BlockId,
+        transaction_number: Option<TransactionNumber>,
+    ) -> Result<(Buffer, bool), BufferManagerError> {
+        loop {
+            let (buffer, read_from_disk) = self.reserve(block_id, transaction_number)?;
+            if read_from_disk {
+                if let Err(e) = buffer.load() {
+                    self.unpin_for(&buffer, transaction_number);
+                    self.notify_loaded();
+                    return Err(BufferManagerError::StdIoError(e));
+                }
+                self.notify_loaded();
+                return Ok((buffer, true));
+            }
+            if buffer.wait_loaded(block_id) {
+                return Ok((buffer, false));
+            }
+            // Loading the block failed, try again
+            self.unpin_for(&buffer, transaction_number);
+        }
+    }
+
+    /// Pins the buffer assigned to the block, or assigns an unpinned buffer to it and tells
+    /// that it has to be loaded
+    fn reserve(
+        &self,
+        block_id: &BlockId,
+        transaction_number: Option<TransactionNumber>,
+    ) -> Result<(Buffer, bool), BufferManagerError> {
+        let mut num_available_guard = self
+            .num_available
+            .lock()
+            .expect("Locking failed for num_available in BufferManager try_to_pin");
+        let start_time = Instant::now();
+        let (buffer, read_from_disk) = loop {
+            let existing_buffer = self
+                .pool
+                .iter()
+                .find(|buffer| buffer.block() == Some(block_id.clone()));
+            debug!(
+                "try to pin: existing_buffer: {:?} for block_id {:?}",
+                existing_buffer, block_id
+            );
+            if let Some(buffer) = existing_buffer {
+                break (buffer.clone(), false);
+            }
+            // Reading the block before its page is written back would miss changes
+            let written_back = !self
+                .pool
+                .iter()
+                .any(|buffer| buffer.writing_back().as_ref() == Some(block_id));
+            if written_back {
+                if let Some(buffer) = self.choose_unpinned_buffer() {
+                    buffer.start_loading(block_id.clone());
+                    break (buffer, true);
+                }
+                if let Some(transaction_number) = transaction_number {
+                    self.wait_for_pinning_transactions(transaction_number)?;
+                }
+            }
+            let waited = start_time.elapsed();
+            if waited >= self.deadlock_waiting_duration {
+                warn!("BufferManager: Deadlock Timout trying to choose unpinned buffer");
+                if let Some(transaction_number) = transaction_number {
+                    self.wait_for_graph.stop_waiting(transaction_number);
+                }
+                return Err(DeadLockTimeout);
             }
+            debug!(
+                "No unpinned buffer available, wait at most {:?} to get some unpinned",
+                self.deadlock_waiting_duration - waited
+            );
+            num_available_guard = self
+                .buffer_available
+                .wait_timeout(num_available_guard, self.deadlock_waiting_duration - waited)
+                .map_err(|_| DeadLockTimeout)?
+                .0;
         };
+        if let Some(transaction_number) = transaction_number {
+            self.wait_for_graph.stop_waiting(transaction_number);
+        }
         debug!(
             "Now pin buffer: {:?} num_available={}",
-            buffer,
-            self.num_available.lock().unwrap()
+            buffer, num_available_guard
         );
         if buffer.is_not_pinned() {
-            let mut num_available_guard = self
-                .num_available
-                .lock()
-                .expect("Locking failed for num_available in BufferManager try_to_pin");
             let num_available = num_available_guard.deref_mut();
             debug!(
                 "Decrement num_available={}, buffer={:?}",
@@ -165,34 +280,60 @@ impl BufferManager {
             *num_available -= 1;
         }
         buffer.increment_pins_count();
-        Ok(buffer)
+        self.count_pin(transaction_number, true);
+        Ok((buffer, read_from_disk))
     }
 
-    fn choose_unpinned_buffer(&self) -> Result<Buffer, BufferManagerError> {
-        let start_time = Instant::now();
-        while start_time.elapsed() <= self.deadlock_waiting_duration {
-            let unpinned_buffer = self.pool.iter().find(|buffer| buffer.is_not_pinned());
-            debug!("Choosing unpinned buffer: {:?}", unpinned_buffer);
-            if let Some(buffer) = unpinned_buffer {
-                return Ok(buffer.clone());
-            }
-            debug!(
-                "No unpinned buffer available, wait at most {:?} to get some unpinned",
-                self.deadlock_waiting_duration
-            );
+    /// Wakes the threads waiting for a page to be written back or flushed. Taking the
+    /// num_available lock makes sure they either see the write done or wait already.
+    fn notify_loaded(&self) {
+        let _num_available_guard = self.num_available.lock().unwrap();
+        self.buffer_available.notify_all();
+    }
 
-            let waiting_result = self.buffer_available.wait_timeout(
-                self.num_available
-                    .lock()
-                    .expect("Locking num_available in BufferManager choose_unpinned_buffer"),
-                self.deadlock_waiting_duration,
-            );
-            if waiting_result.is_err() {
-                warn!("BufferManager: Deadlock Timout trying to choose unpinned buffer");
-                return Err(DeadLockTimeout);
+    /// Any transaction holding a pin may free a buffer. Pins without a transaction might be
+    /// released anytime, so there is no waiting for transactions then.
+    fn wait_for_pinning_transactions(
+        &self,
+        transaction_number: TransactionNumber,
+    ) -> Result<(), BufferManagerError> {
+        let pins_by_transaction = self.pins_by_transaction.lock().unwrap();
+        if pins_by_transaction.contains_key(&None) {
+            return Ok(());
+        }
+        let holders: BTreeSet<TransactionNumber> = pins_by_transaction
+            .keys()
+            .flatten()
+            .filter(|holder| **holder != transaction_number)
+            .copied()
+            .collect();
+        self.wait_for_graph
+            .wait_for(
+                transaction_number,
+                WaitKind::AnyOf,
+                holders,
+                &self.buffer_available,
+            )
+            .map_err(|victim| DeadLockVictim(victim.0))
+    }
+
+    fn count_pin(&self, transaction_number: Option<TransactionNumber>, pinned: bool) {
+        let mut pins_by_transaction = self.pins_by_transaction.lock().unwrap();
+        let pins = pins_by_transaction.entry(transaction_number).or_default();
+        if pinned {
+            *pins += 1;
+        } else {
+            *pins = pins.saturating_sub(1);
+            if *pins == 0 {
+                pins_by_transaction.remove(&transaction_number);
             }
         }
-        Err(DeadLockTimeout)
+    }
+
+    fn choose_unpinned_buffer(&self) -> Option<Buffer> {
+        let unpinned_buffer = self.pool.iter().find(|buffer| buffer.is_replaceable());
+        debug!("Choosing unpinned buffer: {:?}", unpinned_buffer);
+        unpinned_buffer.cloned()
     }
 }
 
@@ -218,7 +359,7 @@ mod tests {
         init_logging();
 
         let file_manager = FileManagerBuilder::unittest("buffer_test")
-            .block_size(NonZeroUsize::new(100 as usize).unwrap())
+            .block_size(NonZeroUsize::new(100).unwrap())
             .build()
             .unwrap();
         let log_manager =
@@ -233,6 +374,9 @@ mod tests {
         );
 
         let block = BlockId::new(DbFilename::from("testfile"), 1);
+        for _ in 0..=4 {
+            file_manager.append(block.filename()).unwrap();
+        }
         let bm = buffer_manager.clone();
         let block1 = block.clone();
         let t1 = thread::spawn(move || {
@@ -285,9 +429,9 @@ mod tests {
         );
         buffer_manager.unpin(&buffer);
 
-        let mut page1 = Page::new(file_manager.block_size);
+        let page1 = Page::new(file_manager.block_size);
         file_manager
-            .read(&block, &mut page1)
+            .read(&block, &page1)
             .expect("Error reading block");
         assert_eq!(
             // page1.get_i32(80),
@@ -297,14 +441,71 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_buffers_concurrent_replacement() {
+        init_logging();
+
+        let file_manager = FileManagerBuilder::unittest("buffer_test_replacement")
+            .block_size(NonZeroUsize::new(100).unwrap())
+            .build()
+            .unwrap();
+        let log_manager =
+            LogManager::new(&file_manager, &DbFilename::from("test_buffers.log")).unwrap();
+        let buffer_manager =
+            BufferManager::new(&file_manager, &log_manager, 3, Duration::from_secs(10));
+        let block = BlockId::new(DbFilename::from("testfile"), 0);
+        for _ in 0..8 {
+            file_manager.append(block.filename()).unwrap();
+        }
+
+        // Every increment pins another block, so pages are written back and read again all the
+        // time, concurrently to pins of the same blocks
+        let threads: Vec<_> = (0..8)
+            .map(|thread_nr| {
+                let buffer_manager = buffer_manager.clone();
+                let block = block.clone();
+                thread::spawn(move || {
+                    for increment in 0..50 {
+                        let block = block.with_other_block_number((thread_nr + increment) % 8);
+                        let mut buffer = buffer_manager.pin(&block).unwrap();
+                        buffer.modify_page(
+                            |page| {
+                                let n = i32::from(page.get::<Integer>(0));
+                                page.set(0, &Integer::from(n + 1));
+                            },
+                            TransactionNumber::from(1),
+                            None,
+                        );
+                        buffer_manager.unpin(&buffer);
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        buffer_manager
+            .flush_all(TransactionNumber::from(1))
+            .unwrap();
+        assert_eq!(buffer_manager.num_available(), 3);
+        let page = Page::new(file_manager.block_size);
+        for block_number in 0..8 {
+            file_manager
+                .read(&block.with_other_block_number(block_number), &page)
+                .unwrap();
+            assert_eq!(i32::from(page.get::<Integer>(0)), 50);
+        }
+    }
+
     #[test]
     fn test_buffers_deadlock() {
         init_logging();
 
         let hfdb = HanfriedDbBuilder::unittest("buffer_test_deadlock")
-            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100 as usize).unwrap()))
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
             .buffer_manager(|bm| bm.pool_size(3))
-            .build();
+            .build()
+            .unwrap();
 
         let bm = &hfdb.buffer_manager;
         let test_filename = DbFilename::from("testfile");
@@ -312,6 +513,9 @@ mod tests {
         let block1 = block0.with_other_block_number(1);
         let block2 = block0.with_other_block_number(2);
         let block3 = block0.with_other_block_number(3);
+        for _ in 0..4 {
+            hfdb.file_manager.append(block0.filename()).unwrap();
+        }
 
         assert_eq!(bm.num_available(), 3);
         let _buffer0 = bm.pin(&block0).unwrap();
@@ -326,13 +530,9 @@ mod tests {
         assert_eq!(bm.num_available(), 0);
 
         match bm.pin(&block3) {
-            Err(DeadLockTimeout) => assert!(true),
-            Err(other_error) => assert!(
-                false,
-                "Expected dead lock, but got other_error: {}",
-                other_error
-            ),
-            Ok(buffer) => assert!(false, "Expected dead lock, but got buffer {}", buffer),
+            Err(DeadLockTimeout) => {}
+            Err(other_error) => panic!("Expected dead lock, but got other_error: {}", other_error),
+            Ok(buffer) => panic!("Expected dead lock, but got buffer {}", buffer),
         }
         bm.unpin(&buffer2);
         bm.pin(&block3).unwrap();
@@ -493,6 +693,7 @@ pub enum BufferManagerError {
     StdIoError(IoError),
     NoCapacity,
     DeadLockTimeout,
+    DeadLockVictim(TransactionNumber),
 }
 
 impl Display for BufferManagerError {
@@ -501,6 +702,11 @@ impl Display for BufferManagerError {
             Self::StdIoError(e) => write!(f, "BufferManager IoError {}", e),
             NoCapacity => write!(f, "BufferManager: No capacity available"),
             DeadLockTimeout => write!(f, "BufferManager: DeadlockTimeout"),
+            DeadLockVictim(transaction_number) => write!(
+                f,
+                "BufferManager: transaction {} chosen as deadlock victim",
+                transaction_number
+            ),
         }
     }
 }
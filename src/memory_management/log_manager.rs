@@ -1,14 +1,14 @@
 use crate::datatypes::fixed_length_counts::SmallCount;
 use crate::file_management::block_id::{BlockId, DbFilename};
-use crate::file_management::file_manager::{FileManager, IoError};
+use crate::file_management::file_manager::{Durability, FileManager, IoError};
 use crate::file_management::page::Page;
-use log::debug;
+use log::{debug, warn};
 use std::fmt::Display;
-use std::sync::{Arc, Mutex, MutexGuard};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
 
 type OffsetInsidePageBlock = SmallCount;
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq, Ord, PartialOrd)]
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Ord, PartialOrd)]
 pub struct LogSequenceNumber(usize);
 
 impl LogSequenceNumber {
@@ -38,6 +38,17 @@ struct LogHead {
     page: Page,
     block: BlockId,
     position: LogPosition,
+    /// The block was written with records, which may be synced already. Rewriting it could
+    /// tear them, so the next record goes to a new block.
+    written: bool,
+}
+
+#[derive(Debug, Default)]
+struct SyncState {
+    /// Latest log sequence number known to be on stable storage
+    synced: LogSequenceNumber,
+    /// A thread is syncing the log file, the others wait for it
+    syncing: bool,
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +56,8 @@ pub struct LogManager {
     file_manager: FileManager,
     log_file: DbFilename,
     head: Arc<Mutex<LogHead>>,
+    /// Notified whenever a sync of the log file ends
+    sync_state: Arc<(Mutex<SyncState>, Condvar)>,
 }
 
 pub struct LogManagerBuilder {
@@ -83,6 +96,10 @@ impl LogManagerBuilder {
 }
 
 impl LogManager {
+    /// Log manager appending to a new block after the last one of the log file. A block is
+    /// written with records only once, so a last block torn by a crash while being written, or
+    /// appended but never written, holds only records of a flush that did not complete. It is
+    /// the end of the log and cut off.
     pub fn new(file_manager: &FileManager, log_file: &DbFilename) -> Result<LogManager, IoError> {
         debug!(
             "Create new log manager, file_manager={:?}, log_file={:?}",
@@ -92,7 +109,17 @@ impl LogManager {
         // let fm = fm_binding.deref_mut();
         let fm = file_manager.clone();
         let mut log_page = Page::new(fm.block_size);
-        let current_block: BlockId = match fm.block_length(log_file)? {
+        let mut log_size = fm.block_length(log_file)?;
+        if log_size > 0 {
+            let last_block = BlockId::new(log_file.clone(), log_size - 1);
+            if !Self::read_intact(&fm, &last_block, &log_page)? {
+                warn!("LogManager: cutting off torn last block {:?}", last_block);
+                log_size -= 1;
+            }
+        }
+        // Also drops an incomplete block the crash left behind the last one
+        fm.truncate(log_file, log_size)?;
+        let current_block: BlockId = match log_size {
             0 => Self::append_new_block(log_file, &fm, &mut log_page)?,
             log_size => {
                 let block_id = BlockId::new(log_file.clone(), log_size - 1);
@@ -100,6 +127,7 @@ impl LogManager {
                 block_id
             }
         };
+        let written = usize::from(&log_page.get::<OffsetInsidePageBlock>(0)) < fm.block_size.get();
 
         let log_manager = LogManager {
             file_manager: file_manager.clone(),
@@ -111,12 +139,26 @@ impl LogManager {
                     latest: LogSequenceNumber(0),
                     last_saved: LogSequenceNumber(0),
                 },
+                written,
             })),
+            sync_state: Arc::new((Mutex::new(SyncState::default()), Condvar::new())),
         };
         debug!("created log_manager={:?}", log_manager);
         Ok(log_manager)
     }
 
+    /// Reads the block, false if it fails its checksum or has no valid boundary
+    fn read_intact(fm: &FileManager, block_id: &BlockId, page: &Page) -> Result<bool, IoError> {
+        match fm.read(block_id, page) {
+            Ok(()) => {
+                let boundary = usize::from(&page.get::<OffsetInsidePageBlock>(0));
+                Ok((4..=fm.block_size.get()).contains(&boundary))
+            }
+            Err(IoError::ChecksumMismatch { .. }) => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
     fn append_new_block(
         log_file: &DbFilename,
         fm: &FileManager,
@@ -132,12 +174,78 @@ impl LogManager {
         Ok(block_id)
     }
 
+    /// Writes the log up to the log sequence number, and syncs it unless the durability is
+    /// relaxed. With group commit, the sync waits for the commits of other transactions first.
     pub fn flush(&self, log_sequence_number: LogSequenceNumber) -> Result<(), IoError> {
-        let mut head = self.head.lock().unwrap();
-        if log_sequence_number >= head.position.latest {
-            self._flush(&mut head)?;
+        self.flush_with_delay(log_sequence_number, true)
+    }
+
+    /// Writes and syncs the log up to the log sequence number before a page changed by it is
+    /// written, without waiting for the group commit delay: only commits are batched
+    pub fn flush_before_page_write(
+        &self,
+        log_sequence_number: LogSequenceNumber,
+    ) -> Result<(), IoError> {
+        self.flush_with_delay(log_sequence_number, false)
+    }
+
+    fn flush_with_delay(
+        &self,
+        log_sequence_number: LogSequenceNumber,
+        group_commit: bool,
+    ) -> Result<(), IoError> {
+        {
+            let mut head = self.head.lock().unwrap();
+            if log_sequence_number > head.position.last_saved {
+                self._flush(&mut head)?;
+            }
+        }
+        match self.file_manager.durability() {
+            Durability::Sync => self.sync(log_sequence_number, None),
+            Durability::GroupCommit(delay) if group_commit => {
+                self.sync(log_sequence_number, Some(delay))
+            }
+            Durability::GroupCommit(_) => self.sync(log_sequence_number, None),
+            Durability::Relaxed => Ok(()),
         }
-        Ok(())
+    }
+
+    /// Syncs the log file, unless a concurrent sync already covered the log sequence number.
+    /// One thread at a time syncs, the others wait for it and sync again only if their records
+    /// were saved too late to be covered. The syncing thread waits for the delay first, so the
+    /// flushes meanwhile are covered, too, and their threads do not sync again.
+    fn sync(
+        &self,
+        log_sequence_number: LogSequenceNumber,
+        delay: Option<std::time::Duration>,
+    ) -> Result<(), IoError> {
+        let (state, sync_ended) = &*self.sync_state;
+        let mut guard = state.lock().unwrap();
+        loop {
+            if log_sequence_number <= guard.synced {
+                return Ok(());
+            }
+            if !guard.syncing {
+                break;
+            }
+            guard = sync_ended.wait(guard).unwrap();
+        }
+        guard.syncing = true;
+        drop(guard);
+
+        if let Some(delay) = delay {
+            std::thread::sleep(delay);
+        }
+        let last_saved = self.head.lock().unwrap().position.last_saved;
+        let synced = self.file_manager.sync(&self.log_file);
+
+        let mut guard = state.lock().unwrap();
+        guard.syncing = false;
+        if synced.is_ok() {
+            guard.synced = guard.synced.max(last_saved);
+        }
+        sync_ended.notify_all();
+        synced
     }
 
     fn _flush(&self, head_lock_guard: &mut MutexGuard<LogHead>) -> Result<(), IoError> {
@@ -148,19 +256,26 @@ impl LogManager {
         self.file_manager
             .write(&head_lock_guard.block, &head_lock_guard.page)?;
         head_lock_guard.position.last_saved = head_lock_guard.position.latest;
+        head_lock_guard.written = true;
         Ok(())
     }
 
+    /// Adds the record to the head block, or to a new block if it does not fit or the head block
+    /// was written already. So every flush starts a new block, leaving the rest of the former
+    /// one unused.
     pub fn append(&self, log_record: &[u8]) -> Result<LogPosition, IoError> {
         // println!("Append log record: {:?} current head {:?}", log_record, self.head.lock().unwrap());
         let mut head = self.head.lock().unwrap();
         let mut boundary = head.page.get::<OffsetInsidePageBlock>(0);
         let record_size = log_record.len();
         let bytes_needed = record_size + 4;
-        if (usize::from(&boundary)) < bytes_needed + 4 {
-            self._flush(&mut head)?;
+        if head.written || (usize::from(&boundary)) < bytes_needed + 4 {
+            if head.position.latest > head.position.last_saved {
+                self._flush(&mut head)?;
+            }
             head.block =
                 Self::append_new_block(&self.log_file, &self.file_manager, &mut head.page)?;
+            head.written = false;
             boundary = head.page.get(0);
         }
         let record_pos = usize::from(&boundary) - bytes_needed;
@@ -229,11 +344,12 @@ mod tests {
     use crate::datatypes::varint::Varint;
     use crate::datatypes::varpair::Varpair;
     use crate::file_management::block_id::DbFilename;
-    use crate::file_management::file_manager::FileManagerBuilder;
+    use crate::file_management::file_manager::{Durability, FileManagerBuilder};
     use crate::file_management::page::Page;
     use crate::memory_management::log_manager::{LogManager, LogPosition, LogSequenceNumber};
     use std::num::NonZeroUsize;
     use std::thread;
+    use std::time::{Duration, Instant};
 
     fn create_log_record(s: &str, n: i32) -> Vec<u8> {
         let n_pos = s.len() + 4;
@@ -316,7 +432,7 @@ mod tests {
         for record_nr in 0..start {
             let s = format!("record{}", record_nr);
             assert!(
-                log_records.contains(&(s, (record_nr + 100) as i32)),
+                log_records.contains(&(s, record_nr + 100)),
                 "before flush: record_nr {} in log_records {:?}",
                 record_nr,
                 log_records
@@ -364,4 +480,89 @@ mod tests {
             );
         }
     }
+
+    /// Syncs of the log file for 8 threads appending and flushing a log record each at once
+    fn log_syncs(sub_directory_name: &str, durability: Durability) -> usize {
+        let file_manager = FileManagerBuilder::unittest(sub_directory_name)
+            .durability(durability)
+            .build()
+            .unwrap();
+        let log_manager = LogManager::new(&file_manager, &DbFilename::from("durable.log")).unwrap();
+        let syncs_before = file_manager.syncs_count();
+        let threads: Vec<_> = (0..8)
+            .map(|n| {
+                let log_manager = log_manager.clone();
+                thread::spawn(move || {
+                    let position = log_manager.append(&create_log_record("commit", n)).unwrap();
+                    log_manager.flush(position.latest).unwrap();
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        // Flushing again does not sync again
+        log_manager.flush(LogSequenceNumber::from(8)).unwrap();
+        assert_eq!(get_log_records(&log_manager).len(), 8);
+        file_manager.syncs_count() - syncs_before
+    }
+
+    #[test]
+    fn test_log_manager_durability() {
+        assert_eq!(log_syncs("log_manager_relaxed", Durability::Relaxed), 0);
+        let syncs = log_syncs("log_manager_sync", Durability::Sync);
+        assert!((1..=8).contains(&syncs), "{syncs} syncs");
+        // The first flush waits until (nearly) all threads flushed, then syncs for all of them
+        let syncs = log_syncs(
+            "log_manager_group_commit",
+            Durability::GroupCommit(Duration::from_millis(200)),
+        );
+        assert!((1..=2).contains(&syncs), "{syncs} syncs");
+    }
+
+    #[test]
+    fn test_log_manager_group_commit_does_not_block_synced_flushes() {
+        let file_manager = FileManagerBuilder::unittest("log_manager_group_commit_waiting")
+            .durability(Durability::GroupCommit(Duration::from_millis(300)))
+            .build()
+            .unwrap();
+        let log_manager = LogManager::new(&file_manager, &DbFilename::from("durable.log")).unwrap();
+        let synced = log_manager.append(&create_log_record("commit", 0)).unwrap();
+        log_manager.flush(synced.latest).unwrap();
+
+        let syncing = {
+            let log_manager = log_manager.clone();
+            thread::spawn(move || {
+                let position = log_manager.append(&create_log_record("commit", 1)).unwrap();
+                log_manager.flush(position.latest).unwrap();
+            })
+        };
+        thread::sleep(Duration::from_millis(50));
+        // Waiting for the group commit delay does not hold up flushes synced before
+        let started = Instant::now();
+        log_manager.flush(synced.latest).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(200));
+        syncing.join().unwrap();
+        assert_eq!(get_log_records(&log_manager).len(), 2);
+    }
+
+    #[test]
+    fn test_log_manager_flush_before_page_write_does_not_wait_for_group() {
+        let file_manager = FileManagerBuilder::unittest("log_manager_page_write")
+            .durability(Durability::GroupCommit(Duration::from_millis(500)))
+            .build()
+            .unwrap();
+        let log_manager = LogManager::new(&file_manager, &DbFilename::from("durable.log")).unwrap();
+        let syncs_before = file_manager.syncs_count();
+        let position = log_manager.append(&create_log_record("change", 0)).unwrap();
+        let started = Instant::now();
+        log_manager
+            .flush_before_page_write(position.latest)
+            .unwrap();
+        assert!(started.elapsed() < Duration::from_millis(250));
+        assert_eq!(file_manager.syncs_count() - syncs_before, 1);
+        // A commit covered by that sync does not wait either
+        log_manager.flush(position.latest).unwrap();
+        assert!(started.elapsed() < Duration::from_millis(250));
+    }
 }
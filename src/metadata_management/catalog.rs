@@ -0,0 +1,700 @@
+use crate::file_management::block_id::{BlockId, DbFilename};
+use crate::file_management::file_manager::IoError;
+use crate::record_management::layout::{Layout, LayoutError, Row};
+use crate::record_management::schema::{Column, ColumnType, Schema};
+use crate::record_management::table_scan::{RecordId, TableScan, TableScanError};
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use crate::transaction_management::transaction_manager::TransactionManager;
+use log::info;
+use std::fmt::{Display, Formatter};
+
+/// Name, file and layout of a table
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TableInfo {
+    pub name: String,
+    pub filename: DbFilename,
+    pub layout: Layout,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum IndexType {
+    BTree,
+    Hash,
+}
+
+impl IndexType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            IndexType::BTree => "BTree",
+            IndexType::Hash => "Hash",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<IndexType> {
+        [IndexType::BTree, IndexType::Hash]
+            .into_iter()
+            .find(|index_type| index_type.name() == name)
+    }
+}
+
+/// Index on one column of a table
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct IndexInfo {
+    pub name: String,
+    pub table_name: String,
+    pub column_name: String,
+    pub index_type: IndexType,
+    pub filename: DbFilename,
+}
+
+/// Named statistic of a table (column_name None) or of one of its columns
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Statistic {
+    pub table_name: String,
+    pub column_name: Option<String>,
+    pub name: String,
+    pub value: i64,
+}
+
+/// Tables, columns, indexes, views and statistics of the database, stored in catalog tables
+/// which describe themselves. All lookups read the snapshot of the transaction, so metadata
+/// changes are transactional like any other change.
+///
+/// Creating and dropping objects takes an exclusive lock on the first block of the tables
+/// catalog table, so DDL statements are executed one after another. Their existence checks only
+/// see objects of transactions committed before the snapshot was taken, though.
+#[derive(Debug, Clone)]
+pub struct Catalog {
+    tables: TableInfo,
+    columns: TableInfo,
+    indexes: TableInfo,
+    views: TableInfo,
+    statistics: TableInfo,
+}
+
+impl Default for Catalog {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Catalog {
+    pub const TABLES: &'static str = "hfdb_tables";
+    pub const COLUMNS: &'static str = "hfdb_columns";
+    pub const INDEXES: &'static str = "hfdb_indexes";
+    pub const VIEWS: &'static str = "hfdb_views";
+    pub const STATISTICS: &'static str = "hfdb_statistics";
+
+    pub fn new() -> Self {
+        Self {
+            tables: Self::catalog_table(
+                Self::TABLES,
+                Schema::new()
+                    .with_column("table_name", ColumnType::Varchar)
+                    .with_column("filename", ColumnType::Varchar),
+            ),
+            columns: Self::catalog_table(
+                Self::COLUMNS,
+                Schema::new()
+                    .with_column("table_name", ColumnType::Varchar)
+                    .with_column("position", ColumnType::SmallCount)
+                    .with_column("column_name", ColumnType::Varchar)
+                    .with_column("column_type", ColumnType::Varchar)
+                    .with_column("nullable", ColumnType::TinyCount),
+            ),
+            indexes: Self::catalog_table(
+                Self::INDEXES,
+                Schema::new()
+                    .with_column("index_name", ColumnType::Varchar)
+                    .with_column("table_name", ColumnType::Varchar)
+                    .with_column("column_name", ColumnType::Varchar)
+                    .with_column("index_type", ColumnType::Varchar)
+                    .with_column("filename", ColumnType::Varchar),
+            ),
+            views: Self::catalog_table(
+                Self::VIEWS,
+                Schema::new()
+                    .with_column("view_name", ColumnType::Varchar)
+                    .with_column("definition", ColumnType::Varchar),
+            ),
+            statistics: Self::catalog_table(
+                Self::STATISTICS,
+                Schema::new()
+                    .with_column("table_name", ColumnType::Varchar)
+                    .with_nullable_column("column_name", ColumnType::Varchar)
+                    .with_column("statistic", ColumnType::Varchar)
+                    .with_column("value", ColumnType::BigInteger),
+            ),
+        }
+    }
+
+    fn catalog_table(name: &str, schema: Schema) -> TableInfo {
+        TableInfo {
+            name: name.to_string(),
+            filename: DbFilename::from(format!("{}.cat", name)),
+            layout: Layout::new(schema),
+        }
+    }
+
+    /// Registers the catalog tables in themselves if the database is empty, returns whether it was
+    pub fn bootstrap(
+        &self,
+        transaction_manager: &TransactionManager,
+    ) -> Result<bool, CatalogError> {
+        let block_length = transaction_manager
+            .file_manager()
+            .block_length(&self.tables.filename)
+            .map_err(CatalogError::IoError)?;
+        if block_length > 0 {
+            return Ok(false);
+        }
+        info!("Catalog: bootstrapping catalog tables");
+        let transaction = transaction_manager
+            .begin()
+            .map_err(CatalogError::TransactionError)?;
+        for table in [
+            &self.tables,
+            &self.columns,
+            &self.indexes,
+            &self.views,
+            &self.statistics,
+        ] {
+            self.register_table(&transaction, table)?;
+        }
+        transaction
+            .commit()
+            .map_err(CatalogError::TransactionError)?;
+        Ok(true)
+    }
+
+    pub fn create_table(
+        &self,
+        transaction: &Transaction,
+        name: &str,
+        schema: Schema,
+    ) -> Result<TableInfo, CatalogError> {
+        self.lock(transaction)?;
+        if self.table(transaction, name)?.is_some() || self.view(transaction, name)?.is_some() {
+            return Err(CatalogError::AlreadyExists(name.to_string()));
+        }
+        let table = TableInfo {
+            name: name.to_string(),
+            filename: DbFilename::from(format!(
+                "tbl_{}_{}.tbl",
+                transaction.transaction_number(),
+                name
+            )),
+            layout: Layout::new(schema),
+        };
+        self.register_table(transaction, &table)?;
+        Ok(table)
+    }
+
+    /// Removes the table with its columns, indexes and statistics from the catalog. The data
+    /// files stay, a table created with the same name later gets new ones.
+    pub fn drop_table(&self, transaction: &Transaction, name: &str) -> Result<(), CatalogError> {
+        self.lock(transaction)?;
+        if self.delete_rows(transaction, &self.tables, "table_name", name)? == 0 {
+            return Err(CatalogError::TableNotFound(name.to_string()));
+        }
+        self.delete_rows(transaction, &self.columns, "table_name", name)?;
+        self.delete_rows(transaction, &self.indexes, "table_name", name)?;
+        self.delete_rows(transaction, &self.statistics, "table_name", name)?;
+        Ok(())
+    }
+
+    pub fn table(
+        &self,
+        transaction: &Transaction,
+        name: &str,
+    ) -> Result<Option<TableInfo>, CatalogError> {
+        let filename = match self
+            .rows(transaction, &self.tables, "table_name", name)?
+            .first()
+        {
+            Some((_, row)) => DbFilename::from(Self::text(row, "filename")?),
+            None => return Ok(None),
+        };
+        let mut columns = vec![];
+        for (_, row) in self.rows(transaction, &self.columns, "table_name", name)? {
+            let column_type = Self::text(&row, "column_type")?;
+            columns.push((
+                Self::int(&row, "position")?,
+                Column {
+                    name: Self::text(&row, "column_name")?,
+                    column_type: ColumnType::from_name(&column_type)
+                        .ok_or(CatalogError::UnknownColumnType(column_type))?,
+                    nullable: Self::int(&row, "nullable")? != 0,
+                },
+            ));
+        }
+        columns.sort_by_key(|(position, _)| *position);
+        let mut schema = Schema::new();
+        for (_, column) in columns {
+            schema.add_column(column);
+        }
+        Ok(Some(TableInfo {
+            name: name.to_string(),
+            filename,
+            layout: Layout::new(schema),
+        }))
+    }
+
+    pub fn table_names(&self, transaction: &Transaction) -> Result<Vec<String>, CatalogError> {
+        let mut scan = TableScan::new(transaction, &self.tables.filename, &self.tables.layout);
+        let mut names = vec![];
+        while scan.next_row().map_err(CatalogError::TableScanError)? {
+            names.push(Self::text(scan.row().unwrap(), "table_name")?);
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    pub fn create_index(
+        &self,
+        transaction: &Transaction,
+        name: &str,
+        table_name: &str,
+        column_name: &str,
+        index_type: IndexType,
+    ) -> Result<IndexInfo, CatalogError> {
+        self.lock(transaction)?;
+        if self.index(transaction, name)?.is_some() {
+            return Err(CatalogError::AlreadyExists(name.to_string()));
+        }
+        let table = self
+            .table(transaction, table_name)?
+            .ok_or_else(|| CatalogError::TableNotFound(table_name.to_string()))?;
+        if table.layout.schema().column(column_name).is_none() {
+            return Err(CatalogError::ColumnNotFound(
+                table_name.to_string(),
+                column_name.to_string(),
+            ));
+        }
+        let index = IndexInfo {
+            name: name.to_string(),
+            table_name: table_name.to_string(),
+            column_name: column_name.to_string(),
+            index_type,
+            filename: DbFilename::from(format!(
+                "idx_{}_{}.idx",
+                transaction.transaction_number(),
+                name
+            )),
+        };
+        let mut row = self.indexes.layout.row();
+        Self::set_text(&mut row, "index_name", &index.name)?;
+        Self::set_text(&mut row, "table_name", &index.table_name)?;
+        Self::set_text(&mut row, "column_name", &index.column_name)?;
+        Self::set_text(&mut row, "index_type", index.index_type.name())?;
+        Self::set_text(&mut row, "filename", index.filename.as_str())?;
+        self.insert(transaction, &self.indexes, &row)?;
+        Ok(index)
+    }
+
+    pub fn drop_index(&self, transaction: &Transaction, name: &str) -> Result<(), CatalogError> {
+        self.lock(transaction)?;
+        match self.delete_rows(transaction, &self.indexes, "index_name", name)? {
+            0 => Err(CatalogError::IndexNotFound(name.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    pub fn index(
+        &self,
+        transaction: &Transaction,
+        name: &str,
+    ) -> Result<Option<IndexInfo>, CatalogError> {
+        self.rows(transaction, &self.indexes, "index_name", name)?
+            .first()
+            .map(|(_, row)| Self::index_info(row))
+            .transpose()
+    }
+
+    /// Indexes of a table, ordered by name
+    pub fn indexes(
+        &self,
+        transaction: &Transaction,
+        table_name: &str,
+    ) -> Result<Vec<IndexInfo>, CatalogError> {
+        let mut indexes = self
+            .rows(transaction, &self.indexes, "table_name", table_name)?
+            .iter()
+            .map(|(_, row)| Self::index_info(row))
+            .collect::<Result<Vec<_>, _>>()?;
+        indexes.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(indexes)
+    }
+
+    fn index_info(row: &Row) -> Result<IndexInfo, CatalogError> {
+        let index_type = Self::text(row, "index_type")?;
+        Ok(IndexInfo {
+            name: Self::text(row, "index_name")?,
+            table_name: Self::text(row, "table_name")?,
+            column_name: Self::text(row, "column_name")?,
+            index_type: IndexType::from_name(&index_type)
+                .ok_or(CatalogError::UnknownIndexType(index_type))?,
+            filename: DbFilename::from(Self::text(row, "filename")?),
+        })
+    }
+
+    pub fn create_view(
+        &self,
+        transaction: &Transaction,
+        name: &str,
+        definition: &str,
+    ) -> Result<(), CatalogError> {
+        self.lock(transaction)?;
+        if self.table(transaction, name)?.is_some() || self.view(transaction, name)?.is_some() {
+            return Err(CatalogError::AlreadyExists(name.to_string()));
+        }
+        let mut row = self.views.layout.row();
+        Self::set_text(&mut row, "view_name", name)?;
+        Self::set_text(&mut row, "definition", definition)?;
+        self.insert(transaction, &self.views, &row)
+    }
+
+    pub fn drop_view(&self, transaction: &Transaction, name: &str) -> Result<(), CatalogError> {
+        self.lock(transaction)?;
+        match self.delete_rows(transaction, &self.views, "view_name", name)? {
+            0 => Err(CatalogError::ViewNotFound(name.to_string())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Definition of the view
+    pub fn view(
+        &self,
+        transaction: &Transaction,
+        name: &str,
+    ) -> Result<Option<String>, CatalogError> {
+        self.rows(transaction, &self.views, "view_name", name)?
+            .first()
+            .map(|(_, row)| Self::text(row, "definition"))
+            .transpose()
+    }
+
+    /// Stores the statistic, replacing the former value
+    pub fn set_statistic(
+        &self,
+        transaction: &Transaction,
+        statistic: &Statistic,
+    ) -> Result<(), CatalogError> {
+        let mut scan = TableScan::new(
+            transaction,
+            &self.statistics.filename,
+            &self.statistics.layout,
+        );
+        for (record_id, row) in self.rows(
+            transaction,
+            &self.statistics,
+            "table_name",
+            &statistic.table_name,
+        )? {
+            if Self::statistic(&row)?.name == statistic.name
+                && row
+                    .get_string("column_name")
+                    .map_err(CatalogError::LayoutError)?
+                    == statistic.column_name
+            {
+                scan.delete(&record_id)
+                    .map_err(CatalogError::TableScanError)?;
+            }
+        }
+        let mut row = self.statistics.layout.row();
+        Self::set_text(&mut row, "table_name", &statistic.table_name)?;
+        if let Some(column_name) = &statistic.column_name {
+            Self::set_text(&mut row, "column_name", column_name)?;
+        }
+        Self::set_text(&mut row, "statistic", &statistic.name)?;
+        row.set_int("value", statistic.value)
+            .map_err(CatalogError::LayoutError)?;
+        self.insert(transaction, &self.statistics, &row)
+    }
+
+    /// Replaces all statistics of the table by the given ones
+    pub fn replace_statistics(
+        &self,
+        transaction: &Transaction,
+        table_name: &str,
+        statistics: &[Statistic],
+    ) -> Result<(), CatalogError> {
+        self.delete_rows(transaction, &self.statistics, "table_name", table_name)?;
+        for statistic in statistics {
+            let mut row = self.statistics.layout.row();
+            Self::set_text(&mut row, "table_name", table_name)?;
+            if let Some(column_name) = &statistic.column_name {
+                Self::set_text(&mut row, "column_name", column_name)?;
+            }
+            Self::set_text(&mut row, "statistic", &statistic.name)?;
+            row.set_int("value", statistic.value)
+                .map_err(CatalogError::LayoutError)?;
+            self.insert(transaction, &self.statistics, &row)?;
+        }
+        Ok(())
+    }
+
+    /// Statistics of the table and its columns
+    pub fn statistics(
+        &self,
+        transaction: &Transaction,
+        table_name: &str,
+    ) -> Result<Vec<Statistic>, CatalogError> {
+        self.rows(transaction, &self.statistics, "table_name", table_name)?
+            .iter()
+            .map(|(_, row)| Self::statistic(row))
+            .collect()
+    }
+
+    fn statistic(row: &Row) -> Result<Statistic, CatalogError> {
+        Ok(Statistic {
+            table_name: Self::text(row, "table_name")?,
+            column_name: row
+                .get_string("column_name")
+                .map_err(CatalogError::LayoutError)?,
+            name: Self::text(row, "statistic")?,
+            value: Self::int(row, "value")?,
+        })
+    }
+
+    fn register_table(
+        &self,
+        transaction: &Transaction,
+        table: &TableInfo,
+    ) -> Result<(), CatalogError> {
+        let mut row = self.tables.layout.row();
+        Self::set_text(&mut row, "table_name", &table.name)?;
+        Self::set_text(&mut row, "filename", table.filename.as_str())?;
+        self.insert(transaction, &self.tables, &row)?;
+        for (position, column) in table.layout.schema().columns().iter().enumerate() {
+            let mut row = self.columns.layout.row();
+            Self::set_text(&mut row, "table_name", &table.name)?;
+            row.set_int("position", position as i64)
+                .map_err(CatalogError::LayoutError)?;
+            Self::set_text(&mut row, "column_name", &column.name)?;
+            Self::set_text(&mut row, "column_type", column.column_type.name())?;
+            row.set_int("nullable", column.nullable as i64)
+                .map_err(CatalogError::LayoutError)?;
+            self.insert(transaction, &self.columns, &row)?;
+        }
+        Ok(())
+    }
+
+    fn lock(&self, transaction: &Transaction) -> Result<(), CatalogError> {
+        transaction
+            .xlock(&BlockId::new(self.tables.filename.clone(), 0))
+            .map_err(CatalogError::TransactionError)
+    }
+
+    fn insert(
+        &self,
+        transaction: &Transaction,
+        table: &TableInfo,
+        row: &Row,
+    ) -> Result<(), CatalogError> {
+        TableScan::new(transaction, &table.filename, &table.layout)
+            .insert(row)
+            .map(|_| ())
+            .map_err(CatalogError::TableScanError)
+    }
+
+    /// Visible rows of a catalog table having the value in the text column
+    fn rows(
+        &self,
+        transaction: &Transaction,
+        table: &TableInfo,
+        column_name: &str,
+        value: &str,
+    ) -> Result<Vec<(RecordId, Row)>, CatalogError> {
+        let mut scan = TableScan::new(transaction, &table.filename, &table.layout);
+        let mut rows = vec![];
+        while scan.next_row().map_err(CatalogError::TableScanError)? {
+            let row = scan.row().unwrap();
+            if Self::text(row, column_name)? == value {
+                rows.push((scan.record_id().unwrap().clone(), row.clone()));
+            }
+        }
+        Ok(rows)
+    }
+
+    fn delete_rows(
+        &self,
+        transaction: &Transaction,
+        table: &TableInfo,
+        column_name: &str,
+        value: &str,
+    ) -> Result<usize, CatalogError> {
+        let rows = self.rows(transaction, table, column_name, value)?;
+        let mut scan = TableScan::new(transaction, &table.filename, &table.layout);
+        for (record_id, _) in &rows {
+            scan.delete(record_id)
+                .map_err(CatalogError::TableScanError)?;
+        }
+        Ok(rows.len())
+    }
+
+    fn text(row: &Row, column_name: &str) -> Result<String, CatalogError> {
+        row.get_string(column_name)
+            .map_err(CatalogError::LayoutError)?
+            .ok_or_else(|| CatalogError::LayoutError(LayoutError::NotNullable(column_name.into())))
+    }
+
+    fn int(row: &Row, column_name: &str) -> Result<i64, CatalogError> {
+        row.get_int(column_name)
+            .map_err(CatalogError::LayoutError)?
+            .ok_or_else(|| CatalogError::LayoutError(LayoutError::NotNullable(column_name.into())))
+    }
+
+    fn set_text(row: &mut Row, column_name: &str, value: &str) -> Result<(), CatalogError> {
+        row.set_string(column_name, value)
+            .map_err(CatalogError::LayoutError)
+    }
+}
+
+#[derive(Debug)]
+pub enum CatalogError {
+    IoError(IoError),
+    TransactionError(TransactionError),
+    TableScanError(TableScanError),
+    LayoutError(LayoutError),
+    AlreadyExists(String),
+    TableNotFound(String),
+    ColumnNotFound(String, String),
+    IndexNotFound(String),
+    ViewNotFound(String),
+    UnknownColumnType(String),
+    UnknownIndexType(String),
+}
+
+impl Display for CatalogError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CatalogError::IoError(e) => write!(f, "Catalog {}", e),
+            CatalogError::TransactionError(e) => write!(f, "Catalog {}", e),
+            CatalogError::TableScanError(e) => write!(f, "Catalog {}", e),
+            CatalogError::LayoutError(e) => write!(f, "Catalog {}", e),
+            CatalogError::AlreadyExists(name) => write!(f, "Catalog: {} already exists", name),
+            CatalogError::TableNotFound(name) => write!(f, "Catalog: no table {}", name),
+            CatalogError::ColumnNotFound(table_name, column_name) => {
+                write!(f, "Catalog: no column {}.{}", table_name, column_name)
+            }
+            CatalogError::IndexNotFound(name) => write!(f, "Catalog: no index {}", name),
+            CatalogError::ViewNotFound(name) => write!(f, "Catalog: no view {}", name),
+            CatalogError::UnknownColumnType(name) => {
+                write!(f, "Catalog: unknown column type {}", name)
+            }
+            CatalogError::UnknownIndexType(name) => {
+                write!(f, "Catalog: unknown index type {}", name)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::metadata_management::catalog::{Catalog, CatalogError, IndexType, Statistic};
+    use crate::record_management::schema::{ColumnType, Schema};
+    use crate::utils::logging::init_logging;
+
+    #[test]
+    fn test_catalog_create_lookup_and_drop() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("catalog").build().unwrap();
+        let catalog = &hfdb.catalog;
+        let schema = Schema::new()
+            .with_column("id", ColumnType::Integer)
+            .with_nullable_column("name", ColumnType::Varchar);
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let table = catalog
+            .create_table(&tx, "persons", schema.clone())
+            .unwrap();
+        assert!(matches!(
+            catalog.create_table(&tx, "persons", schema.clone()),
+            Err(CatalogError::AlreadyExists(_))
+        ));
+        catalog
+            .create_index(&tx, "persons_id", "persons", "id", IndexType::BTree)
+            .unwrap();
+        assert!(matches!(
+            catalog.create_index(&tx, "persons_x", "persons", "x", IndexType::Hash),
+            Err(CatalogError::ColumnNotFound(_, _))
+        ));
+        catalog
+            .create_view(&tx, "names", "SELECT name FROM persons")
+            .unwrap();
+        let statistic = Statistic {
+            table_name: "persons".to_string(),
+            column_name: None,
+            name: "rows".to_string(),
+            value: 42,
+        };
+        catalog.set_statistic(&tx, &statistic).unwrap();
+        catalog
+            .set_statistic(
+                &tx,
+                &Statistic {
+                    value: 43,
+                    ..statistic.clone()
+                },
+            )
+            .unwrap();
+        tx.commit().unwrap();
+        drop(hfdb);
+
+        let hfdb = HanfriedDbBuilder::unittest("catalog")
+            .file_manager(|fm| fm.fresh_db_directory(false))
+            .build()
+            .unwrap();
+        let catalog = &hfdb.catalog;
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        assert_eq!(catalog.table(&tx, "persons").unwrap(), Some(table));
+        assert_eq!(
+            catalog
+                .table(&tx, Catalog::COLUMNS)
+                .unwrap()
+                .unwrap()
+                .layout
+                .schema()
+                .columns()
+                .len(),
+            5
+        );
+        assert!(catalog
+            .table_names(&tx)
+            .unwrap()
+            .contains(&"persons".to_string()));
+        let indexes = catalog.indexes(&tx, "persons").unwrap();
+        assert_eq!(indexes.len(), 1);
+        assert_eq!(indexes[0].column_name, "id");
+        assert_eq!(indexes[0].index_type, IndexType::BTree);
+        assert_eq!(
+            catalog.view(&tx, "names").unwrap().as_deref(),
+            Some("SELECT name FROM persons")
+        );
+        assert_eq!(
+            catalog.statistics(&tx, "persons").unwrap(),
+            vec![Statistic {
+                value: 43,
+                ..statistic
+            }]
+        );
+
+        catalog.drop_table(&tx, "persons").unwrap();
+        assert_eq!(catalog.table(&tx, "persons").unwrap(), None);
+        assert!(catalog.indexes(&tx, "persons").unwrap().is_empty());
+        assert!(catalog.statistics(&tx, "persons").unwrap().is_empty());
+        assert!(matches!(
+            catalog.drop_table(&tx, "persons"),
+            Err(CatalogError::TableNotFound(_))
+        ));
+        catalog.drop_view(&tx, "names").unwrap();
+        assert_eq!(catalog.view(&tx, "names").unwrap(), None);
+        tx.rollback().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        assert!(catalog.table(&tx, "persons").unwrap().is_some());
+        tx.commit().unwrap();
+    }
+}
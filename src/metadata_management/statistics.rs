@@ -0,0 +1,400 @@
+use crate::metadata_management::catalog::{Catalog, CatalogError, Statistic, TableInfo};
+use crate::record_management::layout::LayoutError;
+use crate::record_management::table_scan::{TableScan, TableScanError};
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use log::info;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::ops::Bound;
+
+/// Equi-depth histogram of the non NULL values of a column: each bucket between two adjacent
+/// bounds holds about the same number of values. Values are mapped to order preserving
+/// integer keys, integers clamped to the i64 range, text and bytes by their first 7 bytes.
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct Histogram {
+    pub bounds: Vec<i64>,
+}
+
+impl Histogram {
+    pub const BUCKETS: usize = 16;
+
+    pub fn key(value: &Value) -> Option<i64> {
+        let prefix = |data: &[u8]| {
+            let mut bytes = [0u8; 8];
+            for (i, byte) in data.iter().take(7).enumerate() {
+                bytes[i + 1] = *byte;
+            }
+            i64::from_be_bytes(bytes)
+        };
+        match value {
+            Value::Null => None,
+            Value::Integer(value) => {
+                Some((*value).clamp(i64::MIN as i128, i64::MAX as i128) as i64)
+            }
+            Value::Text(value) => Some(prefix(value.as_bytes())),
+            Value::Bytes(value) => Some(prefix(value)),
+        }
+    }
+
+    fn from_sorted_keys(keys: &[i64]) -> Self {
+        if keys.is_empty() {
+            return Self::default();
+        }
+        let bounds = (0..=Self::BUCKETS)
+            .map(|bucket| keys[(bucket * (keys.len() - 1)) / Self::BUCKETS])
+            .collect();
+        Self { bounds }
+    }
+
+    /// Estimated fraction of the values within the bounds, None without histogram
+    pub fn fraction(&self, lower: Bound<&Value>, upper: Bound<&Value>) -> Option<f64> {
+        if self.bounds.len() < 2 {
+            return None;
+        }
+        let rank = |bound: Bound<&Value>, unbounded: f64| match bound {
+            Bound::Included(value) | Bound::Excluded(value) => {
+                Histogram::key(value).map_or(unbounded, |key| self.rank(key))
+            }
+            Bound::Unbounded => unbounded,
+        };
+        let buckets = (self.bounds.len() - 1) as f64;
+        let fraction = (rank(upper, buckets) - rank(lower, 0.0)) / buckets;
+        Some(fraction.clamp(0.0, 1.0))
+    }
+
+    /// Position of the key in buckets, interpolated linearly within its bucket
+    fn rank(&self, key: i64) -> f64 {
+        let last = self.bounds.len() - 1;
+        if key < self.bounds[0] {
+            return 0.0;
+        }
+        if key > self.bounds[last] {
+            return last as f64;
+        }
+        let bucket = self.bounds[1..]
+            .iter()
+            .position(|bound| key <= *bound)
+            .unwrap_or(last - 1);
+        let (low, high) = (self.bounds[bucket] as f64, self.bounds[bucket + 1] as f64);
+        let within = if high > low {
+            (key as f64 - low) / (high - low)
+        } else {
+            0.5
+        };
+        bucket as f64 + within
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq, Default)]
+pub struct ColumnStatistics {
+    pub distinct_values: u64,
+    pub null_count: u64,
+    pub histogram: Histogram,
+}
+
+/// Statistics of a table and its columns, collected by ANALYZE and stored in the catalog.
+/// Tables never analyzed get estimates from their block count only, without column statistics.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TableStatistics {
+    pub row_count: u64,
+    pub block_count: u64,
+    /// Average length of an encoded row in bytes
+    pub row_length: u64,
+    pub columns: HashMap<String, ColumnStatistics>,
+}
+
+impl TableStatistics {
+    const ROW_COUNT: &'static str = "row_count";
+    const BLOCK_COUNT: &'static str = "block_count";
+    const ROW_LENGTH: &'static str = "row_length";
+    const DISTINCT_VALUES: &'static str = "distinct_values";
+    const NULL_COUNT: &'static str = "null_count";
+    const HISTOGRAM: &'static str = "histogram_";
+    /// Guessed length of a variable length column of a table never analyzed
+    const VARIABLE_LENGTH_ESTIMATE: usize = 16;
+
+    /// Reads all visible rows of the table
+    pub fn analyze(transaction: &Transaction, table: &TableInfo) -> Result<Self, StatisticsError> {
+        let schema = table.layout.schema();
+        let mut distinct_values = vec![HashSet::new(); schema.columns().len()];
+        let mut null_counts = vec![0u64; schema.columns().len()];
+        let mut keys = vec![vec![]; schema.columns().len()];
+        let mut row_count = 0u64;
+        let mut total_length = 0u64;
+        let mut scan = TableScan::new(transaction, &table.filename, &table.layout);
+        while scan.next_row().map_err(StatisticsError::TableScanError)? {
+            let row = scan.row().unwrap();
+            row_count += 1;
+            total_length += row.to_bytes().map_err(StatisticsError::LayoutError)?.len() as u64;
+            for (i, value) in row.values().iter().enumerate() {
+                match Histogram::key(value) {
+                    Some(key) => {
+                        keys[i].push(key);
+                        distinct_values[i].insert(value.clone());
+                    }
+                    None => null_counts[i] += 1,
+                }
+            }
+        }
+        let mut columns = HashMap::new();
+        for (i, column) in schema.columns().iter().enumerate() {
+            keys[i].sort_unstable();
+            columns.insert(
+                column.name.clone(),
+                ColumnStatistics {
+                    distinct_values: distinct_values[i].len() as u64,
+                    null_count: null_counts[i],
+                    histogram: Histogram::from_sorted_keys(&keys[i]),
+                },
+            );
+        }
+        let statistics = Self {
+            row_count,
+            block_count: Self::block_count(transaction, table)?,
+            row_length: total_length.checked_div(row_count).unwrap_or(0),
+            columns,
+        };
+        info!(
+            "Statistics: analyzed table {}, {} rows in {} blocks",
+            table.name, statistics.row_count, statistics.block_count
+        );
+        Ok(statistics)
+    }
+
+    /// Statistics of the table from the catalog. The row count is scaled to the current block
+    /// count of the table, as the table may have grown or shrunk since it was analyzed.
+    pub fn load(
+        transaction: &Transaction,
+        catalog: &Catalog,
+        table: &TableInfo,
+    ) -> Result<Self, StatisticsError> {
+        let block_count = Self::block_count(transaction, table)?;
+        let stored = catalog
+            .statistics(transaction, &table.name)
+            .map_err(StatisticsError::CatalogError)?;
+        let table_statistic = |name: &str| {
+            stored
+                .iter()
+                .find(|statistic| statistic.column_name.is_none() && statistic.name == name)
+                .map(|statistic| statistic.value.max(0) as u64)
+        };
+        let Some(analyzed_rows) = table_statistic(Self::ROW_COUNT) else {
+            return Ok(Self::estimated(transaction, table, block_count));
+        };
+        let analyzed_blocks = table_statistic(Self::BLOCK_COUNT).unwrap_or(0);
+        let mut columns: HashMap<String, ColumnStatistics> = HashMap::new();
+        let mut histograms: HashMap<&str, Vec<(usize, i64)>> = HashMap::new();
+        for statistic in &stored {
+            let Some(column_name) = &statistic.column_name else {
+                continue;
+            };
+            let column = columns.entry(column_name.clone()).or_default();
+            match statistic.name.as_str() {
+                Self::DISTINCT_VALUES => column.distinct_values = statistic.value.max(0) as u64,
+                Self::NULL_COUNT => column.null_count = statistic.value.max(0) as u64,
+                name => {
+                    if let Some(Ok(bucket)) = name
+                        .strip_prefix(Self::HISTOGRAM)
+                        .map(|bucket| bucket.parse::<usize>())
+                    {
+                        histograms
+                            .entry(column_name)
+                            .or_default()
+                            .push((bucket, statistic.value));
+                    }
+                }
+            }
+        }
+        for (column_name, mut bounds) in histograms {
+            bounds.sort_unstable();
+            columns.get_mut(column_name).unwrap().histogram = Histogram {
+                bounds: bounds.into_iter().map(|(_, bound)| bound).collect(),
+            };
+        }
+        let row_count = match analyzed_blocks {
+            0 => analyzed_rows,
+            blocks => analyzed_rows * block_count.max(1) / blocks,
+        };
+        Ok(Self {
+            row_count,
+            block_count,
+            row_length: table_statistic(Self::ROW_LENGTH).unwrap_or(0),
+            columns,
+        })
+    }
+
+    /// Replaces the statistics of the table in the catalog
+    pub fn store(
+        &self,
+        transaction: &Transaction,
+        catalog: &Catalog,
+        table_name: &str,
+    ) -> Result<(), StatisticsError> {
+        let statistic = |column_name: Option<&String>, name: &str, value: i64| Statistic {
+            table_name: table_name.to_string(),
+            column_name: column_name.cloned(),
+            name: name.to_string(),
+            value,
+        };
+        let mut statistics = vec![
+            statistic(None, Self::ROW_COUNT, self.row_count as i64),
+            statistic(None, Self::BLOCK_COUNT, self.block_count as i64),
+            statistic(None, Self::ROW_LENGTH, self.row_length as i64),
+        ];
+        for (column_name, column) in &self.columns {
+            statistics.push(statistic(
+                Some(column_name),
+                Self::DISTINCT_VALUES,
+                column.distinct_values as i64,
+            ));
+            statistics.push(statistic(
+                Some(column_name),
+                Self::NULL_COUNT,
+                column.null_count as i64,
+            ));
+            for (bucket, bound) in column.histogram.bounds.iter().enumerate() {
+                statistics.push(statistic(
+                    Some(column_name),
+                    &format!("{}{}", Self::HISTOGRAM, bucket),
+                    *bound,
+                ));
+            }
+        }
+        catalog
+            .replace_statistics(transaction, table_name, &statistics)
+            .map_err(StatisticsError::CatalogError)
+    }
+
+    /// Fraction of NULL values of the column, None without column statistics
+    pub fn null_fraction(&self, column_name: &str) -> Option<f64> {
+        let column = self.columns.get(column_name)?;
+        Some(match self.row_count {
+            0 => 0.0,
+            rows => (column.null_count as f64 / rows as f64).min(1.0),
+        })
+    }
+
+    pub fn distinct_values(&self, column_name: &str) -> Option<u64> {
+        self.columns
+            .get(column_name)
+            .map(|column| column.distinct_values)
+    }
+
+    fn estimated(transaction: &Transaction, table: &TableInfo, block_count: u64) -> Self {
+        let schema = table.layout.schema();
+        let variable_columns = schema
+            .columns()
+            .iter()
+            .filter(|column| column.column_type.fixed_length().is_none())
+            .count();
+        let row_length =
+            table.layout.fixed_length() + variable_columns * Self::VARIABLE_LENGTH_ESTIMATE;
+        let rows_per_block = (transaction.block_size().get() / row_length.max(1)).max(1);
+        Self {
+            row_count: block_count * rows_per_block as u64,
+            block_count,
+            row_length: row_length as u64,
+            columns: HashMap::new(),
+        }
+    }
+
+    fn block_count(transaction: &Transaction, table: &TableInfo) -> Result<u64, StatisticsError> {
+        transaction
+            .block_length(&table.filename)
+            .map(|blocks| blocks as u64)
+            .map_err(StatisticsError::TransactionError)
+    }
+}
+
+#[derive(Debug)]
+pub enum StatisticsError {
+    CatalogError(CatalogError),
+    TransactionError(TransactionError),
+    TableScanError(TableScanError),
+    LayoutError(LayoutError),
+}
+
+impl Display for StatisticsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            StatisticsError::CatalogError(e) => write!(f, "Statistics {}", e),
+            StatisticsError::TransactionError(e) => write!(f, "Statistics {}", e),
+            StatisticsError::TableScanError(e) => write!(f, "Statistics {}", e),
+            StatisticsError::LayoutError(e) => write!(f, "Statistics {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::metadata_management::statistics::{Histogram, TableStatistics};
+    use crate::record_management::schema::{ColumnType, Schema};
+    use crate::record_management::table_scan::TableScan;
+    use crate::record_management::value::Value;
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+    use std::ops::Bound;
+
+    #[test]
+    fn test_analyze_store_and_load_statistics() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("statistics")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(200).unwrap()))
+            .build()
+            .unwrap();
+        let catalog = &hfdb.catalog;
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let table = catalog
+            .create_table(
+                &tx,
+                "measurements",
+                Schema::new()
+                    .with_column("id", ColumnType::Integer)
+                    .with_nullable_column("sensor", ColumnType::Varchar),
+            )
+            .unwrap();
+        let estimated = TableStatistics::load(&tx, catalog, &table).unwrap();
+        assert_eq!(estimated.row_count, 0);
+        assert!(estimated.columns.is_empty());
+
+        let mut scan = TableScan::new(&tx, &table.filename, &table.layout);
+        for id in 0..1000 {
+            let mut row = table.layout.row();
+            row.set_int("id", id).unwrap();
+            if id % 4 != 0 {
+                row.set_string("sensor", &format!("sensor{}", id % 10))
+                    .unwrap();
+            }
+            scan.insert(&row).unwrap();
+        }
+        let statistics = TableStatistics::analyze(&tx, &table).unwrap();
+        assert_eq!(statistics.row_count, 1000);
+        assert!(statistics.block_count > 1);
+        assert_eq!(statistics.distinct_values("id"), Some(1000));
+        assert_eq!(statistics.distinct_values("sensor"), Some(10));
+        assert_eq!(statistics.null_fraction("sensor"), Some(0.25));
+        statistics.store(&tx, catalog, "measurements").unwrap();
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let loaded = TableStatistics::load(&tx, catalog, &table).unwrap();
+        assert_eq!(loaded, statistics);
+        let histogram = &loaded.columns["id"].histogram;
+        assert_eq!(histogram.bounds.len(), Histogram::BUCKETS + 1);
+        let fraction = histogram
+            .fraction(Bound::Excluded(&Value::from(749i64)), Bound::Unbounded)
+            .unwrap();
+        assert!((fraction - 0.25).abs() < 0.01, "fraction {}", fraction);
+        assert_eq!(
+            histogram.fraction(Bound::Unbounded, Bound::Excluded(&Value::from(-5i64))),
+            Some(0.0)
+        );
+        assert!(
+            Histogram::key(&Value::from("ab")).unwrap()
+                < Histogram::key(&Value::from("b")).unwrap()
+        );
+        tx.commit().unwrap();
+    }
+}
@@ -0,0 +1,11 @@
+pub mod ast;
+pub mod cost;
+pub mod executor;
+pub mod explain;
+pub mod expression;
+pub mod lexer;
+pub mod parser;
+pub mod plan;
+pub mod planner;
+pub mod session;
+pub mod spill;
@@ -0,0 +1,296 @@
+use crate::datatypes::SortOrder;
+use crate::metadata_management::catalog::IndexType;
+use crate::record_management::schema::ColumnType;
+use crate::record_management::value::Value;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Statement {
+    CreateTable(CreateTable),
+    DropTable(DropTable),
+    CreateIndex(CreateIndex),
+    Insert(Insert),
+    Select(Box<Select>),
+    Update(Update),
+    Delete(Delete),
+    Analyze(Analyze),
+    Explain(Explain),
+    Begin,
+    Commit,
+    Rollback,
+}
+
+/// Refreshes the statistics of the table, of all tables without name
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Analyze {
+    pub table_name: Option<String>,
+}
+
+/// Shows the plan of the query with estimates, EXPLAIN ANALYZE also runs it and reports what
+/// every operator actually did
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Explain {
+    pub analyze: bool,
+    pub select: Box<Select>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CreateTable {
+    pub name: String,
+    pub if_not_exists: bool,
+    pub columns: Vec<ColumnDefinition>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ColumnDefinition {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub nullable: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct DropTable {
+    pub name: String,
+    pub if_exists: bool,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct CreateIndex {
+    pub name: String,
+    pub table_name: String,
+    pub column_name: String,
+    pub index_type: IndexType,
+}
+
+/// Rows to insert, each with one expression per column. Without column names the values are
+/// given for all columns in the order of the table definition.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Insert {
+    pub table_name: String,
+    pub columns: Option<Vec<String>>,
+    pub rows: Vec<Vec<Expression>>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Select {
+    pub distinct: bool,
+    pub projection: Vec<SelectItem>,
+    pub from: Option<TableReference>,
+    pub joins: Vec<Join>,
+    pub where_clause: Option<Expression>,
+    pub group_by: Vec<Expression>,
+    pub having: Option<Expression>,
+    pub order_by: Vec<OrderBy>,
+    pub limit: Option<u64>,
+    pub offset: Option<u64>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum SelectItem {
+    /// `*`
+    Wildcard,
+    /// `table.*`
+    QualifiedWildcard(String),
+    Expression {
+        expression: Expression,
+        alias: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct TableReference {
+    pub name: String,
+    pub alias: Option<String>,
+}
+
+impl TableReference {
+    /// Name the columns of the table are qualified with in the query
+    pub fn reference_name(&self) -> &str {
+        self.alias.as_ref().unwrap_or(&self.name)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum JoinKind {
+    Inner,
+    Left,
+    Cross,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Join {
+    pub kind: JoinKind,
+    pub table: TableReference,
+    /// Join condition, None for cross joins
+    pub on: Option<Expression>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct OrderBy {
+    pub expression: Expression,
+    pub order: SortOrder,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Update {
+    pub table_name: String,
+    pub assignments: Vec<(String, Expression)>,
+    pub where_clause: Option<Expression>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Delete {
+    pub table_name: String,
+    pub where_clause: Option<Expression>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Expression {
+    Literal(Value),
+    Column {
+        table: Option<String>,
+        name: String,
+    },
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<Expression>,
+    },
+    Binary {
+        left: Box<Expression>,
+        operator: BinaryOperator,
+        right: Box<Expression>,
+    },
+    IsNull {
+        expression: Box<Expression>,
+        negated: bool,
+    },
+    /// Function call, `star` is set for `count(*)`
+    Function {
+        name: String,
+        arguments: Vec<Expression>,
+        star: bool,
+    },
+}
+
+impl Expression {
+    pub fn binary(left: Expression, operator: BinaryOperator, right: Expression) -> Self {
+        Expression::Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }
+    }
+
+    pub fn column(name: &str) -> Self {
+        Expression::Column {
+            table: None,
+            name: name.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum UnaryOperator {
+    Not,
+    Minus,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum BinaryOperator {
+    Or,
+    And,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Modulo,
+    Concat,
+}
+
+impl BinaryOperator {
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOperator::Or => "OR",
+            BinaryOperator::And => "AND",
+            BinaryOperator::Equal => "=",
+            BinaryOperator::NotEqual => "<>",
+            BinaryOperator::Less => "<",
+            BinaryOperator::LessEqual => "<=",
+            BinaryOperator::Greater => ">",
+            BinaryOperator::GreaterEqual => ">=",
+            BinaryOperator::Add => "+",
+            BinaryOperator::Subtract => "-",
+            BinaryOperator::Multiply => "*",
+            BinaryOperator::Divide => "/",
+            BinaryOperator::Modulo => "%",
+            BinaryOperator::Concat => "||",
+        }
+    }
+}
+
+/// SQL text of the expression, binary expressions are put in parentheses
+impl Display for Expression {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Expression::Literal(Value::Text(value)) => write!(f, "'{}'", value.replace('\'', "''")),
+            Expression::Literal(Value::Bytes(value)) => {
+                write!(f, "X'")?;
+                for byte in value {
+                    write!(f, "{:02x}", byte)?;
+                }
+                write!(f, "'")
+            }
+            Expression::Literal(value) => write!(f, "{}", value),
+            Expression::Column {
+                table: Some(table),
+                name,
+            } => write!(f, "{}.{}", table, name),
+            Expression::Column { table: None, name } => write!(f, "{}", name),
+            Expression::Unary {
+                operator: UnaryOperator::Not,
+                operand,
+            } => write!(f, "NOT {}", operand),
+            Expression::Unary {
+                operator: UnaryOperator::Minus,
+                operand,
+            } => write!(f, "-{}", operand),
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => write!(f, "({} {} {})", left, operator.symbol(), right),
+            Expression::IsNull {
+                expression,
+                negated,
+            } => write!(
+                f,
+                "{} IS {}NULL",
+                expression,
+                if *negated { "NOT " } else { "" }
+            ),
+            Expression::Function {
+                name,
+                arguments,
+                star,
+            } => {
+                write!(f, "{}(", name)?;
+                if *star {
+                    write!(f, "*")?;
+                }
+                for (i, argument) in arguments.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{}", argument)?;
+                }
+                write!(f, ")")
+            }
+        }
+    }
+}
@@ -0,0 +1,451 @@
+use crate::metadata_management::catalog::{IndexType, TableInfo};
+use crate::metadata_management::statistics::TableStatistics;
+use crate::query_processing::ast::{BinaryOperator, JoinKind, UnaryOperator};
+use crate::query_processing::expression::BoundExpression;
+use crate::query_processing::plan::{IndexLookup, Plan, PlanColumn, PlanNode};
+use crate::record_management::value::Value;
+use std::collections::HashMap;
+use std::ops::Bound;
+
+/// Estimated number of tuples returned by a plan and the cost of executing it, measured in
+/// blocks read or written, processing a tuple costs a fraction of a block.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Estimate {
+    pub rows: f64,
+    pub cost: f64,
+}
+
+/// Estimates plans from the statistics of the tables they read. Inserting a tuple into a hash
+/// table costs twice as much as probing it. Hash tables and sorts are expected to stay in
+/// memory up to the size of the buffer pool, anything larger is charged for writing and reading
+/// it once.
+#[derive(Debug, Clone)]
+pub struct CostModel {
+    statistics: HashMap<String, TableStatistics>,
+    references: HashMap<String, String>,
+    memory: usize,
+    block_size: usize,
+}
+
+impl CostModel {
+    pub const TUPLE_COST: f64 = 0.01;
+    /// Blocks read to find the first entry of a B-tree index, a hash index reads one bucket
+    pub const BTREE_DESCENT_COST: f64 = 2.0;
+    pub const DEFAULT_EQUALITY_SELECTIVITY: f64 = 0.005;
+    pub const DEFAULT_RANGE_SELECTIVITY: f64 = 1.0 / 3.0;
+    pub const DEFAULT_NULL_FRACTION: f64 = 0.1;
+    /// Length of a variable length value in memory, in addition to the size of the value
+    const VARIABLE_LENGTH_ESTIMATE: usize = 16;
+
+    pub fn new(memory: usize, block_size: usize) -> Self {
+        Self {
+            statistics: HashMap::new(),
+            references: HashMap::new(),
+            memory,
+            block_size,
+        }
+    }
+
+    /// Registers the statistics of the table, referenced in the query by the name
+    pub fn add_table(
+        &mut self,
+        reference_name: &str,
+        table: &TableInfo,
+        statistics: TableStatistics,
+    ) {
+        self.references
+            .insert(reference_name.to_string(), table.name.clone());
+        self.statistics.insert(table.name.clone(), statistics);
+    }
+
+    /// Bytes available to a hash table or sort
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    pub fn estimate(&self, plan: &Plan) -> Estimate {
+        match &plan.node {
+            PlanNode::Values(rows) => Estimate {
+                rows: rows.len() as f64,
+                cost: 0.0,
+            },
+            PlanNode::TableScan { table } => {
+                let statistics = &self.statistics[&table.name];
+                let rows = statistics.row_count as f64;
+                Estimate {
+                    rows,
+                    cost: statistics.block_count as f64 + rows * Self::TUPLE_COST,
+                }
+            }
+            PlanNode::IndexScan {
+                table,
+                index,
+                lookup,
+            } => {
+                let statistics = &self.statistics[&table.name];
+                let selectivity = match lookup {
+                    IndexLookup::Equal(value) => {
+                        self.equality_selectivity(statistics, &index.column_name, value)
+                    }
+                    IndexLookup::Range(lower, upper) => self.range_selectivity(
+                        statistics,
+                        &index.column_name,
+                        lower.as_ref(),
+                        upper.as_ref(),
+                    ),
+                };
+                let rows = statistics.row_count as f64 * selectivity;
+                let descent = match index.index_type {
+                    IndexType::BTree => Self::BTREE_DESCENT_COST,
+                    IndexType::Hash => 1.0,
+                };
+                Estimate {
+                    rows,
+                    cost: descent + rows * (1.0 + Self::TUPLE_COST),
+                }
+            }
+            PlanNode::Filter { input, predicate } => {
+                let input_estimate = self.estimate(input);
+                Estimate {
+                    rows: input_estimate.rows * self.selectivity(predicate, &input.columns),
+                    cost: input_estimate.cost + input_estimate.rows * Self::TUPLE_COST,
+                }
+            }
+            PlanNode::Project { input, .. } => self.estimate(input),
+            PlanNode::NestedLoopJoin {
+                left,
+                right,
+                kind,
+                condition,
+            } => {
+                let (left_estimate, right_estimate) = (self.estimate(left), self.estimate(right));
+                let selectivity = condition
+                    .as_ref()
+                    .map_or(1.0, |condition| self.selectivity(condition, &plan.columns));
+                Estimate {
+                    rows: Self::join_rows(*kind, left_estimate, right_estimate, selectivity),
+                    cost: left_estimate.cost
+                        + right_estimate.cost
+                        + left_estimate.rows * right_estimate.rows * Self::TUPLE_COST,
+                }
+            }
+            PlanNode::HashJoin {
+                left,
+                right,
+                kind,
+                left_keys,
+                right_keys,
+                condition,
+            } => {
+                let (left_estimate, right_estimate) = (self.estimate(left), self.estimate(right));
+                let mut selectivity = condition
+                    .as_ref()
+                    .map_or(1.0, |condition| self.selectivity(condition, &plan.columns));
+                for (left_key, right_key) in left_keys.iter().zip(right_keys) {
+                    selectivity *= self.key_selectivity(left_key, left, right_key, right);
+                }
+                Estimate {
+                    rows: Self::join_rows(*kind, left_estimate, right_estimate, selectivity),
+                    cost: left_estimate.cost
+                        + right_estimate.cost
+                        + (left_estimate.rows + 2.0 * right_estimate.rows) * Self::TUPLE_COST
+                        + self.spill_cost(right, right_estimate.rows),
+                }
+            }
+            PlanNode::MergeJoin {
+                left,
+                right,
+                kind,
+                left_key,
+                right_key,
+                condition,
+            } => {
+                let (left_estimate, right_estimate) = (self.estimate(left), self.estimate(right));
+                let selectivity = condition
+                    .as_ref()
+                    .map_or(1.0, |condition| self.selectivity(condition, &plan.columns))
+                    * self.key_selectivity(
+                        &BoundExpression::Column(*left_key),
+                        left,
+                        &BoundExpression::Column(*right_key),
+                        right,
+                    );
+                Estimate {
+                    rows: Self::join_rows(*kind, left_estimate, right_estimate, selectivity),
+                    cost: left_estimate.cost
+                        + right_estimate.cost
+                        + (left_estimate.rows + right_estimate.rows) * Self::TUPLE_COST,
+                }
+            }
+            PlanNode::Sort { input, .. } => {
+                let input_estimate = self.estimate(input);
+                let rows = input_estimate.rows;
+                Estimate {
+                    rows,
+                    cost: input_estimate.cost
+                        + rows * (rows + 1.0).log2() * Self::TUPLE_COST
+                        + self.spill_cost(input, rows),
+                }
+            }
+            PlanNode::Aggregate {
+                input, group_by, ..
+            } => {
+                let input_estimate = self.estimate(input);
+                let rows = if group_by.is_empty() {
+                    1.0
+                } else {
+                    let groups: f64 = group_by
+                        .iter()
+                        .map(|expression| {
+                            self.distinct_values(expression, &input.columns)
+                                .unwrap_or(input_estimate.rows * Self::DEFAULT_RANGE_SELECTIVITY)
+                        })
+                        .product();
+                    groups.clamp(1.0, input_estimate.rows.max(1.0))
+                };
+                Estimate {
+                    rows,
+                    cost: input_estimate.cost + input_estimate.rows * Self::TUPLE_COST,
+                }
+            }
+            PlanNode::Limit {
+                input,
+                limit,
+                offset,
+            } => {
+                let input_estimate = self.estimate(input);
+                let rows = (input_estimate.rows - *offset as f64).max(0.0);
+                Estimate {
+                    rows: limit.map_or(rows, |limit| rows.min(limit as f64)),
+                    cost: input_estimate.cost,
+                }
+            }
+        }
+    }
+
+    /// Estimated bytes of all tuples of the plan kept in memory
+    pub fn memory_usage(&self, plan: &Plan, rows: f64) -> f64 {
+        let tuple_length: usize = plan
+            .columns
+            .iter()
+            .map(|column| {
+                std::mem::size_of::<Value>()
+                    + match column.column_type.fixed_length() {
+                        Some(_) => 0,
+                        None => Self::VARIABLE_LENGTH_ESTIMATE,
+                    }
+            })
+            .sum();
+        rows * tuple_length as f64
+    }
+
+    pub fn fits_in_memory(&self, plan: &Plan) -> bool {
+        self.memory_usage(plan, self.estimate(plan).rows) <= self.memory as f64
+    }
+
+    /// Estimated fraction of the tuples satisfying the condition
+    pub fn selectivity(&self, condition: &BoundExpression, columns: &[PlanColumn]) -> f64 {
+        let column_statistics = |expression: &BoundExpression| match expression {
+            BoundExpression::Column(index) => self.column_statistics(&columns[*index]),
+            _ => None,
+        };
+        match condition {
+            BoundExpression::Literal(value) => match value {
+                Value::Integer(0) | Value::Null => 0.0,
+                _ => 1.0,
+            },
+            BoundExpression::Unary {
+                operator: UnaryOperator::Not,
+                operand,
+            } => 1.0 - self.selectivity(operand, columns),
+            BoundExpression::IsNull {
+                expression,
+                negated,
+            } => {
+                let null_fraction = column_statistics(expression)
+                    .and_then(|(statistics, name)| statistics.null_fraction(name))
+                    .unwrap_or(Self::DEFAULT_NULL_FRACTION);
+                if *negated {
+                    1.0 - null_fraction
+                } else {
+                    null_fraction
+                }
+            }
+            BoundExpression::Binary {
+                left,
+                operator,
+                right,
+            } => match operator {
+                BinaryOperator::And => {
+                    self.selectivity(left, columns) * self.selectivity(right, columns)
+                }
+                BinaryOperator::Or => {
+                    let (left, right) = (
+                        self.selectivity(left, columns),
+                        self.selectivity(right, columns),
+                    );
+                    left + right - left * right
+                }
+                BinaryOperator::Equal | BinaryOperator::NotEqual => {
+                    let equality = match (left.as_ref(), right.as_ref()) {
+                        (column, BoundExpression::Literal(value))
+                        | (BoundExpression::Literal(value), column) => column_statistics(column)
+                            .map_or(Self::DEFAULT_EQUALITY_SELECTIVITY, |(statistics, name)| {
+                                self.equality_selectivity(statistics, name, value)
+                            }),
+                        (left, right) => {
+                            let distinct = |expression| {
+                                column_statistics(expression)
+                                    .and_then(|(statistics, name)| statistics.distinct_values(name))
+                            };
+                            match distinct(left).max(distinct(right)) {
+                                Some(distinct) => 1.0 / distinct.max(1) as f64,
+                                None => Self::DEFAULT_EQUALITY_SELECTIVITY,
+                            }
+                        }
+                    };
+                    if *operator == BinaryOperator::Equal {
+                        equality
+                    } else {
+                        1.0 - equality
+                    }
+                }
+                BinaryOperator::Less
+                | BinaryOperator::LessEqual
+                | BinaryOperator::Greater
+                | BinaryOperator::GreaterEqual => {
+                    let (column, operator, value) = match (left.as_ref(), right.as_ref()) {
+                        (column, BoundExpression::Literal(value)) => (column, *operator, value),
+                        (BoundExpression::Literal(value), column) => {
+                            (column, Self::mirrored(*operator), value)
+                        }
+                        _ => return Self::DEFAULT_RANGE_SELECTIVITY,
+                    };
+                    let (lower, upper) = match operator {
+                        BinaryOperator::Less => (Bound::Unbounded, Bound::Excluded(value)),
+                        BinaryOperator::LessEqual => (Bound::Unbounded, Bound::Included(value)),
+                        BinaryOperator::Greater => (Bound::Excluded(value), Bound::Unbounded),
+                        _ => (Bound::Included(value), Bound::Unbounded),
+                    };
+                    column_statistics(column)
+                        .map_or(Self::DEFAULT_RANGE_SELECTIVITY, |(statistics, name)| {
+                            self.range_selectivity(statistics, name, lower, upper)
+                        })
+                }
+                _ => Self::DEFAULT_RANGE_SELECTIVITY,
+            },
+            _ => Self::DEFAULT_RANGE_SELECTIVITY,
+        }
+    }
+
+    fn equality_selectivity(
+        &self,
+        statistics: &TableStatistics,
+        column_name: &str,
+        value: &Value,
+    ) -> f64 {
+        if value.is_null() {
+            return 0.0;
+        }
+        match (
+            statistics.distinct_values(column_name),
+            statistics.null_fraction(column_name),
+        ) {
+            (Some(0), _) => 0.0,
+            (Some(distinct), Some(null_fraction)) => (1.0 - null_fraction) / distinct as f64,
+            _ => Self::DEFAULT_EQUALITY_SELECTIVITY,
+        }
+    }
+
+    fn range_selectivity(
+        &self,
+        statistics: &TableStatistics,
+        column_name: &str,
+        lower: Bound<&Value>,
+        upper: Bound<&Value>,
+    ) -> f64 {
+        let non_null = 1.0
+            - statistics
+                .null_fraction(column_name)
+                .unwrap_or(Self::DEFAULT_NULL_FRACTION);
+        if matches!((lower, upper), (Bound::Unbounded, Bound::Unbounded)) {
+            return non_null;
+        }
+        match statistics
+            .columns
+            .get(column_name)
+            .and_then(|column| column.histogram.fraction(lower, upper))
+        {
+            Some(fraction) => fraction * non_null,
+            None => Self::DEFAULT_RANGE_SELECTIVITY,
+        }
+    }
+
+    /// Selectivity of the equality of a key of the left with one of the right input
+    fn key_selectivity(
+        &self,
+        left_key: &BoundExpression,
+        left: &Plan,
+        right_key: &BoundExpression,
+        right: &Plan,
+    ) -> f64 {
+        match (
+            self.distinct_values(left_key, &left.columns),
+            self.distinct_values(right_key, &right.columns),
+        ) {
+            (Some(left), Some(right)) => 1.0 / left.max(right).max(1.0),
+            (Some(distinct), None) | (None, Some(distinct)) => 1.0 / distinct.max(1.0),
+            (None, None) => Self::DEFAULT_EQUALITY_SELECTIVITY,
+        }
+    }
+
+    fn distinct_values(&self, expression: &BoundExpression, columns: &[PlanColumn]) -> Option<f64> {
+        match expression {
+            BoundExpression::Column(index) => self
+                .column_statistics(&columns[*index])
+                .and_then(|(statistics, name)| statistics.distinct_values(name))
+                .map(|distinct| distinct as f64),
+            _ => None,
+        }
+    }
+
+    fn column_statistics<'a>(
+        &'a self,
+        column: &'a PlanColumn,
+    ) -> Option<(&'a TableStatistics, &'a str)> {
+        let table_name = self.references.get(column.table.as_ref()?)?;
+        let statistics = self.statistics.get(table_name)?;
+        statistics
+            .columns
+            .contains_key(&column.name)
+            .then_some((statistics, column.name.as_str()))
+    }
+
+    fn join_rows(kind: JoinKind, left: Estimate, right: Estimate, selectivity: f64) -> f64 {
+        let rows = left.rows * right.rows * selectivity;
+        match kind {
+            JoinKind::Left => rows.max(left.rows),
+            _ => rows,
+        }
+    }
+
+    /// Cost of writing and reading the tuples once if they exceed the memory
+    fn spill_cost(&self, plan: &Plan, rows: f64) -> f64 {
+        let bytes = self.memory_usage(plan, rows);
+        if bytes <= self.memory as f64 {
+            0.0
+        } else {
+            2.0 * bytes / self.block_size as f64
+        }
+    }
+
+    fn mirrored(operator: BinaryOperator) -> BinaryOperator {
+        match operator {
+            BinaryOperator::Less => BinaryOperator::Greater,
+            BinaryOperator::LessEqual => BinaryOperator::GreaterEqual,
+            BinaryOperator::Greater => BinaryOperator::Less,
+            BinaryOperator::GreaterEqual => BinaryOperator::LessEqual,
+            operator => operator,
+        }
+    }
+}
@@ -0,0 +1,1468 @@
+use crate::datatypes::SortOrder;
+use crate::file_management::file_manager::IoError;
+use crate::metadata_management::catalog::{Catalog, CatalogError, TableInfo};
+use crate::metadata_management::statistics::{StatisticsError, TableStatistics};
+use crate::query_processing::ast::{
+    Analyze, CreateIndex, CreateTable, Delete, DropTable, Explain, Insert, JoinKind, Statement,
+    Update,
+};
+use crate::query_processing::explain::{explain_plan, InstrumentedOperator, OperatorMetrics};
+use crate::query_processing::expression::{BoundExpression, ExpressionError};
+use crate::query_processing::plan::{
+    Aggregate, AggregateFunction, IndexLookup, Plan, PlanColumn, PlanNode,
+};
+use crate::query_processing::planner::{Planner, PlannerError};
+use crate::query_processing::spill::{
+    partition_of, tuple_length, SpillSpace, TupleFile, MAX_SPILL_DEPTH,
+};
+use crate::record_management::layout::LayoutError;
+use crate::record_management::schema::Schema;
+use crate::record_management::table::{Table, TableError};
+use crate::record_management::table_scan::{RecordId, TableScan, TableScanError};
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::Transaction;
+use log::debug;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+pub type Tuple = Vec<Value>;
+
+/// Iterator over the tuples of a plan node, pulling tuples from its inputs on demand
+pub trait Operator {
+    /// Prepares the operator, and its inputs, to return the first tuple
+    fn open(&mut self) -> Result<(), ExecutionError>;
+
+    /// Next tuple, None when all tuples are returned
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError>;
+
+    /// Record the last tuple was read from, for operators returning table rows unchanged
+    fn record_id(&self) -> Option<RecordId> {
+        None
+    }
+}
+
+/// Operator tree executing the plan within the transaction
+pub fn build_operator(transaction: &Transaction, plan: &Plan) -> Box<dyn Operator> {
+    build(transaction, plan, None)
+}
+
+/// Operator tree measuring every operator, the metrics are collected in pre-order of the plan
+pub fn build_instrumented_operator(
+    transaction: &Transaction,
+    plan: &Plan,
+    metrics: &mut Vec<Rc<RefCell<OperatorMetrics>>>,
+) -> Box<dyn Operator> {
+    build(transaction, plan, Some(metrics))
+}
+
+fn build(
+    transaction: &Transaction,
+    plan: &Plan,
+    mut metrics: Option<&mut Vec<Rc<RefCell<OperatorMetrics>>>>,
+) -> Box<dyn Operator> {
+    let node_metrics = metrics.as_mut().map(|metrics| {
+        let node_metrics = Rc::new(RefCell::new(OperatorMetrics::default()));
+        metrics.push(node_metrics.clone());
+        node_metrics
+    });
+    let operator: Box<dyn Operator> = match &plan.node {
+        PlanNode::Values(rows) => Box::new(ValuesOperator {
+            rows: rows.clone(),
+            position: 0,
+        }),
+        PlanNode::TableScan { table } => Box::new(TableScanOperator {
+            scan: TableScan::new(transaction, &table.filename, &table.layout),
+        }),
+        PlanNode::IndexScan {
+            table,
+            index,
+            lookup,
+        } => Box::new(IndexScanOperator {
+            transaction: transaction.clone(),
+            table: table.clone(),
+            index: index.clone(),
+            lookup: lookup.clone(),
+            rows: VecDeque::new(),
+            record_id: None,
+        }),
+        PlanNode::Filter { input, predicate } => Box::new(FilterOperator {
+            input: build(transaction, input, metrics.as_deref_mut()),
+            predicate: predicate.clone(),
+        }),
+        PlanNode::Project { input, expressions } => Box::new(ProjectOperator {
+            input: build(transaction, input, metrics.as_deref_mut()),
+            expressions: expressions.clone(),
+        }),
+        PlanNode::NestedLoopJoin {
+            left,
+            right,
+            kind,
+            condition,
+        } => Box::new(NestedLoopJoinOperator {
+            left: build(transaction, left, metrics.as_deref_mut()),
+            right: build(transaction, right, metrics.as_deref_mut()),
+            right_width: right.columns.len(),
+            kind: *kind,
+            condition: condition.clone(),
+            right_tuples: vec![],
+            current: None,
+        }),
+        PlanNode::HashJoin {
+            left,
+            right,
+            kind,
+            left_keys,
+            right_keys,
+            condition,
+        } => Box::new(HashJoinOperator {
+            left: build(transaction, left, metrics.as_deref_mut()),
+            right: build(transaction, right, metrics.as_deref_mut()),
+            right_width: right.columns.len(),
+            kind: *kind,
+            left_keys: left_keys.clone(),
+            right_keys: right_keys.clone(),
+            condition: condition.clone(),
+            spill_space: SpillSpace::new(transaction),
+            hash_table: HashMap::new(),
+            output: VecDeque::new(),
+            probe: Probe::Input,
+            partitions: VecDeque::new(),
+        }),
+        PlanNode::MergeJoin {
+            left,
+            right,
+            kind,
+            left_key,
+            right_key,
+            condition,
+        } => Box::new(MergeJoinOperator {
+            left: build(transaction, left, metrics.as_deref_mut()),
+            right: build(transaction, right, metrics.as_deref_mut()),
+            right_width: right.columns.len(),
+            kind: *kind,
+            left_key: *left_key,
+            right_key: *right_key,
+            condition: condition.clone(),
+            next_right: None,
+            group: vec![],
+            output: VecDeque::new(),
+        }),
+        PlanNode::Sort { input, keys } => Box::new(SortOperator {
+            input: build(transaction, input, metrics.as_deref_mut()),
+            keys: keys.clone(),
+            spill_space: SpillSpace::new(transaction),
+            tuples: VecDeque::new(),
+            merge: None,
+        }),
+        PlanNode::Aggregate {
+            input,
+            group_by,
+            aggregates,
+        } => Box::new(AggregateOperator {
+            input: build(transaction, input, metrics.as_deref_mut()),
+            grouping: Grouping {
+                group_by: group_by.clone(),
+                aggregates: aggregates.clone(),
+                spill_space: SpillSpace::new(transaction),
+            },
+            groups: VecDeque::new(),
+            partitions: VecDeque::new(),
+        }),
+        PlanNode::Limit {
+            input,
+            limit,
+            offset,
+        } => Box::new(LimitOperator {
+            input: build(transaction, input, metrics),
+            limit: *limit,
+            offset: *offset,
+            returned: 0,
+        }),
+    };
+    match node_metrics {
+        Some(metrics) => Box::new(InstrumentedOperator::new(transaction, operator, metrics)),
+        None => operator,
+    }
+}
+
+struct ValuesOperator {
+    rows: Vec<Tuple>,
+    position: usize,
+}
+
+impl Operator for ValuesOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.position = 0;
+        Ok(())
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        let tuple = self.rows.get(self.position).cloned();
+        self.position += 1;
+        Ok(tuple)
+    }
+}
+
+struct TableScanOperator {
+    scan: TableScan,
+}
+
+impl Operator for TableScanOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.scan.before_first();
+        Ok(())
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        if self
+            .scan
+            .next_row()
+            .map_err(ExecutionError::TableScanError)?
+        {
+            Ok(Some(self.scan.row().unwrap().values().to_vec()))
+        } else {
+            Ok(None)
+        }
+    }
+
+    fn record_id(&self) -> Option<RecordId> {
+        self.scan.record_id().cloned()
+    }
+}
+
+/// Reads the rows found via the index when opened
+struct IndexScanOperator {
+    transaction: Transaction,
+    table: TableInfo,
+    index: crate::metadata_management::catalog::IndexInfo,
+    lookup: IndexLookup,
+    rows: VecDeque<(RecordId, Tuple)>,
+    record_id: Option<RecordId>,
+}
+
+impl Operator for IndexScanOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        let table = Table::new(
+            &self.transaction,
+            self.table.clone(),
+            vec![self.index.clone()],
+        )
+        .map_err(ExecutionError::TableError)?;
+        let rows = match &self.lookup {
+            IndexLookup::Equal(key) => table.lookup(&self.index.name, key),
+            IndexLookup::Range(lower, upper) => {
+                table.range(&self.index.name, (lower.as_ref(), upper.as_ref()))
+            }
+        }
+        .map_err(ExecutionError::TableError)?;
+        self.rows = rows
+            .into_iter()
+            .map(|(record_id, row)| (record_id, row.values().to_vec()))
+            .collect();
+        Ok(())
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        match self.rows.pop_front() {
+            Some((record_id, tuple)) => {
+                self.record_id = Some(record_id);
+                Ok(Some(tuple))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn record_id(&self) -> Option<RecordId> {
+        self.record_id.clone()
+    }
+}
+
+struct FilterOperator {
+    input: Box<dyn Operator>,
+    predicate: BoundExpression,
+}
+
+impl Operator for FilterOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.input.open()
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        while let Some(tuple) = self.input.next_tuple()? {
+            if self
+                .predicate
+                .is_true(&tuple)
+                .map_err(ExecutionError::ExpressionError)?
+            {
+                return Ok(Some(tuple));
+            }
+        }
+        Ok(None)
+    }
+
+    fn record_id(&self) -> Option<RecordId> {
+        self.input.record_id()
+    }
+}
+
+struct ProjectOperator {
+    input: Box<dyn Operator>,
+    expressions: Vec<BoundExpression>,
+}
+
+impl Operator for ProjectOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.input.open()
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        match self.input.next_tuple()? {
+            Some(tuple) => evaluate_all(&self.expressions, &tuple).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Keeps the right tuples in memory, joins each left tuple with all of them
+struct NestedLoopJoinOperator {
+    left: Box<dyn Operator>,
+    right: Box<dyn Operator>,
+    right_width: usize,
+    kind: JoinKind,
+    condition: Option<BoundExpression>,
+    right_tuples: Vec<Tuple>,
+    current: Option<(Tuple, usize, bool)>,
+}
+
+impl Operator for NestedLoopJoinOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.left.open()?;
+        self.right.open()?;
+        self.right_tuples = collect(self.right.as_mut())?;
+        self.current = None;
+        Ok(())
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        loop {
+            let Some((left, position, matched)) = &mut self.current else {
+                match self.left.next_tuple()? {
+                    Some(left) => self.current = Some((left, 0, false)),
+                    None => return Ok(None),
+                }
+                continue;
+            };
+            while *position < self.right_tuples.len() {
+                let joined = joined(left, &self.right_tuples[*position]);
+                *position += 1;
+                if satisfies(&self.condition, &joined)? {
+                    *matched = true;
+                    return Ok(Some(joined));
+                }
+            }
+            let unmatched =
+                (self.kind == JoinKind::Left && !*matched).then(|| padded(left, self.right_width));
+            self.current = None;
+            if unmatched.is_some() {
+                return Ok(unmatched);
+            }
+        }
+    }
+}
+
+/// Builds a hash table of the right tuples by their keys when opened. Tuples with a NULL key
+/// never match. When the right tuples exceed the memory of the query, both inputs are spilled
+/// to hash partitions by their keys and each pair of partitions is joined on its own, spilling
+/// again if the right partition still exceeds the memory.
+struct HashJoinOperator {
+    left: Box<dyn Operator>,
+    right: Box<dyn Operator>,
+    right_width: usize,
+    kind: JoinKind,
+    left_keys: Vec<BoundExpression>,
+    right_keys: Vec<BoundExpression>,
+    condition: Option<BoundExpression>,
+    spill_space: SpillSpace,
+    hash_table: HashMap<Tuple, Vec<Tuple>>,
+    output: VecDeque<Tuple>,
+    probe: Probe,
+    /// Pairs of right and left partitions not joined yet, with the depth they were spilled at
+    partitions: VecDeque<(TupleFile, TupleFile, usize)>,
+}
+
+/// Where the left tuples probing the hash table come from
+enum Probe {
+    Input,
+    Partition(TupleFile),
+    Exhausted,
+}
+
+impl HashJoinOperator {
+    /// Fills the hash table with the right tuples. If they do not fit into memory, all of them
+    /// are spilled into the returned partitions instead, leaving the hash table empty.
+    fn build(
+        &mut self,
+        right: Option<&mut dyn Operator>,
+        depth: usize,
+    ) -> Result<Option<Vec<TupleFile>>, ExecutionError> {
+        let right = right.unwrap_or(self.right.as_mut());
+        self.hash_table.clear();
+        let mut length = 0;
+        let mut partitions: Option<Vec<TupleFile>> = None;
+        while let Some(tuple) = right.next_tuple()? {
+            let key = evaluate_all(&self.right_keys, &tuple)?;
+            if key.iter().any(Value::is_null) {
+                continue;
+            }
+            if let Some(partitions) = &mut partitions {
+                let partition = partition_of(&key, depth, partitions.len());
+                partitions[partition].push(&tuple)?;
+                continue;
+            }
+            length += tuple_length(&key) + tuple_length(&tuple);
+            self.hash_table.entry(key).or_default().push(tuple);
+            if length > self.spill_space.memory() && depth < MAX_SPILL_DEPTH {
+                debug!("HashJoinOperator: spilling at depth {}", depth);
+                let mut spilled = self.spill_space.partitions();
+                for (key, tuples) in self.hash_table.drain() {
+                    let partition = partition_of(&key, depth, spilled.len());
+                    for tuple in tuples {
+                        spilled[partition].push(&tuple)?;
+                    }
+                }
+                partitions = Some(spilled);
+            }
+        }
+        Ok(partitions)
+    }
+
+    /// Spills the left tuples to partitions matching the right ones and queues the pairs
+    fn partition_left(
+        &mut self,
+        left: Option<&mut dyn Operator>,
+        right_partitions: Vec<TupleFile>,
+        depth: usize,
+    ) -> Result<(), ExecutionError> {
+        let left = left.unwrap_or(self.left.as_mut());
+        let mut left_partitions = self.spill_space.partitions();
+        while let Some(tuple) = left.next_tuple()? {
+            let key = evaluate_all(&self.left_keys, &tuple)?;
+            let partition = partition_of(&key, depth, left_partitions.len());
+            left_partitions[partition].push(&tuple)?;
+        }
+        for (right, left) in right_partitions.into_iter().zip(left_partitions) {
+            if !left.is_empty() && (!right.is_empty() || self.kind == JoinKind::Left) {
+                self.partitions.push_back((right, left, depth + 1));
+            }
+        }
+        Ok(())
+    }
+
+    fn next_left(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        match &mut self.probe {
+            Probe::Input => self.left.next_tuple(),
+            Probe::Partition(partition) => partition.next_tuple(),
+            Probe::Exhausted => Ok(None),
+        }
+    }
+
+    /// Joins the next pair of partitions, false if there is none left
+    fn next_partition(&mut self) -> Result<bool, ExecutionError> {
+        let Some((mut right, mut left, depth)) = self.partitions.pop_front() else {
+            return Ok(false);
+        };
+        right.open()?;
+        left.open()?;
+        match self.build(Some(&mut right), depth)? {
+            None => self.probe = Probe::Partition(left),
+            Some(right_partitions) => {
+                self.probe = Probe::Exhausted;
+                self.partition_left(Some(&mut left), right_partitions, depth)?;
+            }
+        }
+        Ok(true)
+    }
+}
+
+impl Operator for HashJoinOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.left.open()?;
+        self.right.open()?;
+        self.output.clear();
+        self.partitions.clear();
+        self.probe = Probe::Input;
+        if let Some(right_partitions) = self.build(None, 0)? {
+            self.probe = Probe::Exhausted;
+            self.partition_left(None, right_partitions, 0)?;
+        }
+        Ok(())
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        while self.output.is_empty() {
+            let Some(left) = self.next_left()? else {
+                if self.next_partition()? {
+                    continue;
+                }
+                return Ok(None);
+            };
+            let key = evaluate_all(&self.left_keys, &left)?;
+            if let Some(matches) = self.hash_table.get(&key) {
+                for right in matches {
+                    let joined = joined(&left, right);
+                    if satisfies(&self.condition, &joined)? {
+                        self.output.push_back(joined);
+                    }
+                }
+            }
+            if self.output.is_empty() && self.kind == JoinKind::Left {
+                self.output.push_back(padded(&left, self.right_width));
+            }
+        }
+        Ok(self.output.pop_front())
+    }
+}
+
+/// Joins inputs sorted by their keys, keeping the right tuples of the current key in memory
+struct MergeJoinOperator {
+    left: Box<dyn Operator>,
+    right: Box<dyn Operator>,
+    right_width: usize,
+    kind: JoinKind,
+    left_key: usize,
+    right_key: usize,
+    condition: Option<BoundExpression>,
+    next_right: Option<Tuple>,
+    group: Vec<Tuple>,
+    output: VecDeque<Tuple>,
+}
+
+impl Operator for MergeJoinOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.left.open()?;
+        self.right.open()?;
+        self.next_right = self.right.next_tuple()?;
+        self.group.clear();
+        self.output.clear();
+        Ok(())
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        while self.output.is_empty() {
+            let Some(left) = self.left.next_tuple()? else {
+                return Ok(None);
+            };
+            let key = &left[self.left_key];
+            if !key.is_null() {
+                let group_key = self.group.first().map(|tuple| &tuple[self.right_key]);
+                if group_key != Some(key) {
+                    self.group.clear();
+                    while let Some(right) = &self.next_right {
+                        let right_key = &right[self.right_key];
+                        let ordering = if right_key.is_null() {
+                            Ordering::Less
+                        } else {
+                            right_key.cmp(key)
+                        };
+                        if ordering == Ordering::Greater {
+                            break;
+                        }
+                        if ordering == Ordering::Equal {
+                            self.group.push(right.clone());
+                        }
+                        self.next_right = self.right.next_tuple()?;
+                    }
+                }
+                for right in &self.group {
+                    let joined = joined(&left, right);
+                    if satisfies(&self.condition, &joined)? {
+                        self.output.push_back(joined);
+                    }
+                }
+            }
+            if self.output.is_empty() && self.kind == JoinKind::Left {
+                self.output.push_back(padded(&left, self.right_width));
+            }
+        }
+        Ok(self.output.pop_front())
+    }
+}
+
+/// Sorts all input tuples in memory when opened, NULL is smaller than any other value
+/// Sorts in memory as long as the tuples fit into the memory of the query. Otherwise sorted runs
+/// are written to temporary files whenever memory is full, and merged as many at a time as the
+/// memory allows until they can be merged while returning the tuples. The sort is stable.
+struct SortOperator {
+    input: Box<dyn Operator>,
+    keys: Vec<(BoundExpression, SortOrder)>,
+    spill_space: SpillSpace,
+    tuples: VecDeque<Tuple>,
+    merge: Option<RunMerge>,
+}
+
+impl SortOperator {
+    fn sort_key(&self, tuple: &[Value]) -> Result<Tuple, ExecutionError> {
+        let expressions: Vec<BoundExpression> =
+            self.keys.iter().map(|(key, _)| key.clone()).collect();
+        evaluate_all(&expressions, tuple)
+    }
+
+    fn write_run(&self, keyed: &mut Vec<(Tuple, Tuple)>) -> Result<TupleFile, ExecutionError> {
+        keyed.sort_by(|(a, _), (b, _)| compare_keys(a, b, &self.keys));
+        let mut run = self.spill_space.tuple_file();
+        for (_, tuple) in keyed.drain(..) {
+            run.push(&tuple)?;
+        }
+        Ok(run)
+    }
+}
+
+impl Operator for SortOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.input.open()?;
+        self.tuples.clear();
+        self.merge = None;
+        let mut keyed = vec![];
+        let mut length = 0;
+        let mut runs = vec![];
+        while let Some(tuple) = self.input.next_tuple()? {
+            length += tuple_length(&tuple);
+            keyed.push((self.sort_key(&tuple)?, tuple));
+            if length > self.spill_space.memory() {
+                runs.push(self.write_run(&mut keyed)?);
+                length = 0;
+            }
+        }
+        if runs.is_empty() {
+            keyed.sort_by(|(a, _), (b, _)| compare_keys(a, b, &self.keys));
+            self.tuples = keyed.into_iter().map(|(_, tuple)| tuple).collect();
+            return Ok(());
+        }
+        if !keyed.is_empty() {
+            runs.push(self.write_run(&mut keyed)?);
+        }
+        let fan_in = self.spill_space.fan_out();
+        while runs.len() > fan_in {
+            debug!(
+                "SortOperator: merging {} runs {} at a time",
+                runs.len(),
+                fan_in
+            );
+            let mut runs_to_merge = runs.into_iter();
+            runs = vec![];
+            loop {
+                let group: Vec<TupleFile> = runs_to_merge.by_ref().take(fan_in).collect();
+                if group.is_empty() {
+                    break;
+                }
+                let mut merge = RunMerge::new(group, &self.keys)?;
+                let mut run = self.spill_space.tuple_file();
+                while let Some(tuple) = merge.next_tuple()? {
+                    run.push(&tuple)?;
+                }
+                runs.push(run);
+            }
+        }
+        self.merge = Some(RunMerge::new(runs, &self.keys)?);
+        Ok(())
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        match &mut self.merge {
+            Some(merge) => merge.next_tuple(),
+            None => Ok(self.tuples.pop_front()),
+        }
+    }
+}
+
+/// Merges sorted runs, of equal keys the tuple of the earliest run comes first
+struct RunMerge {
+    keys: Vec<(BoundExpression, SortOrder)>,
+    runs: Vec<TupleFile>,
+    /// Next tuple of each run with its sort key, None when the run is exhausted
+    heads: Vec<Option<(Tuple, Tuple)>>,
+}
+
+impl RunMerge {
+    fn new(
+        mut runs: Vec<TupleFile>,
+        keys: &[(BoundExpression, SortOrder)],
+    ) -> Result<Self, ExecutionError> {
+        let mut heads = vec![];
+        for run in &mut runs {
+            run.open()?;
+            heads.push(Self::keyed(run.next_tuple()?, keys)?);
+        }
+        Ok(Self {
+            keys: keys.to_vec(),
+            runs,
+            heads,
+        })
+    }
+
+    fn keyed(
+        tuple: Option<Tuple>,
+        keys: &[(BoundExpression, SortOrder)],
+    ) -> Result<Option<(Tuple, Tuple)>, ExecutionError> {
+        let expressions: Vec<BoundExpression> = keys.iter().map(|(key, _)| key.clone()).collect();
+        tuple
+            .map(|tuple| Ok((evaluate_all(&expressions, &tuple)?, tuple)))
+            .transpose()
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        let mut smallest: Option<usize> = None;
+        for (index, head) in self.heads.iter().enumerate() {
+            if let Some((key, _)) = head {
+                let is_smaller = match smallest.and_then(|smallest| self.heads[smallest].as_ref()) {
+                    Some((smallest_key, _)) => {
+                        compare_keys(key, smallest_key, &self.keys) == Ordering::Less
+                    }
+                    None => true,
+                };
+                if is_smaller {
+                    smallest = Some(index);
+                }
+            }
+        }
+        let Some(index) = smallest else {
+            return Ok(None);
+        };
+        let next = Self::keyed(self.runs[index].next_tuple()?, &self.keys)?;
+        Ok(std::mem::replace(&mut self.heads[index], next).map(|(_, tuple)| tuple))
+    }
+}
+
+pub(crate) fn compare_keys(
+    a: &[Value],
+    b: &[Value],
+    keys: &[(BoundExpression, SortOrder)],
+) -> Ordering {
+    for ((a, b), (_, order)) in a.iter().zip(b).zip(keys) {
+        let ordering = match order {
+            SortOrder::Ascending => a.cmp(b),
+            SortOrder::Descending => b.cmp(a),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+#[derive(Debug, Clone)]
+enum Accumulator {
+    Count(i128),
+    Sum(Option<i128>),
+    Min(Value),
+    Max(Value),
+    Avg(i128, i128),
+}
+
+impl Accumulator {
+    fn new(function: AggregateFunction) -> Self {
+        match function {
+            AggregateFunction::Count => Accumulator::Count(0),
+            AggregateFunction::Sum => Accumulator::Sum(None),
+            AggregateFunction::Min => Accumulator::Min(Value::Null),
+            AggregateFunction::Max => Accumulator::Max(Value::Null),
+            AggregateFunction::Avg => Accumulator::Avg(0, 0),
+        }
+    }
+
+    /// Adds the value, NULL values are ignored (`count(*)` gets a non NULL value for each row)
+    fn add(&mut self, value: Value) -> Result<(), ExecutionError> {
+        if value.is_null() {
+            return Ok(());
+        }
+        match (self, value) {
+            (Accumulator::Count(count), _) => *count += 1,
+            (Accumulator::Sum(sum), Value::Integer(value)) => {
+                *sum = Some(
+                    sum.unwrap_or(0)
+                        .checked_add(value)
+                        .ok_or(ExecutionError::ExpressionError(ExpressionError::Overflow))?,
+                )
+            }
+            (Accumulator::Avg(sum, count), Value::Integer(value)) => {
+                *sum = sum
+                    .checked_add(value)
+                    .ok_or(ExecutionError::ExpressionError(ExpressionError::Overflow))?;
+                *count += 1;
+            }
+            (Accumulator::Min(min), value) => {
+                if min.is_null() || value < *min {
+                    *min = value
+                }
+            }
+            (Accumulator::Max(max), value) => {
+                if max.is_null() || value > *max {
+                    *max = value
+                }
+            }
+            (_, value) => {
+                return Err(ExecutionError::ExpressionError(
+                    ExpressionError::InvalidOperand("sum", value),
+                ))
+            }
+        }
+        Ok(())
+    }
+
+    /// Result of the aggregate, the average is rounded towards zero
+    fn result(&self) -> Value {
+        match self {
+            Accumulator::Count(count) => Value::Integer(*count),
+            Accumulator::Sum(sum) => Value::from(*sum),
+            Accumulator::Min(value) | Accumulator::Max(value) => value.clone(),
+            Accumulator::Avg(_, 0) => Value::Null,
+            Accumulator::Avg(sum, count) => Value::Integer(sum / count),
+        }
+    }
+}
+
+/// Groups the input tuples when opened, returning the groups in the order of their first tuple.
+/// Once the groups fill the memory of the query, the tuples of further groups are spilled to
+/// hash partitions, which are grouped the same way after the groups in memory are returned.
+/// Without group expressions there is exactly one group, even without input.
+struct AggregateOperator {
+    input: Box<dyn Operator>,
+    grouping: Grouping,
+    groups: VecDeque<Tuple>,
+    /// Spilled tuples with the depth they are grouped at
+    partitions: VecDeque<(TupleFile, usize)>,
+}
+
+struct Grouping {
+    group_by: Vec<BoundExpression>,
+    aggregates: Vec<Aggregate>,
+    spill_space: SpillSpace,
+}
+
+impl Grouping {
+    /// Memory an accumulator is assumed to take
+    const ACCUMULATOR_LENGTH: usize = 32;
+
+    /// Groups of the tuples kept in memory and the non empty partitions of the spilled ones
+    fn aggregate(
+        &self,
+        source: &mut dyn Operator,
+        depth: usize,
+    ) -> Result<(VecDeque<Tuple>, Vec<TupleFile>), ExecutionError> {
+        let mut positions: HashMap<Tuple, usize> = HashMap::new();
+        let mut groups: Vec<(Tuple, Vec<Accumulator>)> = vec![];
+        let mut partitions: Vec<TupleFile> = vec![];
+        let mut length = 0;
+        let new_accumulators = || {
+            self.aggregates
+                .iter()
+                .map(|aggregate| Accumulator::new(aggregate.function))
+                .collect::<Vec<_>>()
+        };
+        if self.group_by.is_empty() {
+            groups.push((vec![], new_accumulators()));
+            positions.insert(vec![], 0);
+        }
+        while let Some(tuple) = source.next_tuple()? {
+            let key = evaluate_all(&self.group_by, &tuple)?;
+            let position = match positions.get(&key) {
+                Some(position) => *position,
+                None if length > self.spill_space.memory() && depth < MAX_SPILL_DEPTH => {
+                    if partitions.is_empty() {
+                        debug!("AggregateOperator: spilling at depth {}", depth);
+                        partitions = self.spill_space.partitions();
+                    }
+                    let partition = partition_of(&key, depth, partitions.len());
+                    partitions[partition].push(&tuple)?;
+                    continue;
+                }
+                None => {
+                    length +=
+                        2 * tuple_length(&key) + Self::ACCUMULATOR_LENGTH * self.aggregates.len();
+                    positions.insert(key.clone(), groups.len());
+                    groups.push((key, new_accumulators()));
+                    groups.len() - 1
+                }
+            };
+            for (aggregate, accumulator) in self.aggregates.iter().zip(&mut groups[position].1) {
+                let value = match &aggregate.argument {
+                    Some(argument) => argument
+                        .evaluate(&tuple)
+                        .map_err(ExecutionError::ExpressionError)?,
+                    None => Value::Integer(1),
+                };
+                accumulator.add(value)?;
+            }
+        }
+        let groups = groups
+            .into_iter()
+            .map(|(mut key, accumulators)| {
+                key.extend(accumulators.iter().map(Accumulator::result));
+                key
+            })
+            .collect();
+        let partitions = partitions
+            .into_iter()
+            .filter(|partition| !partition.is_empty())
+            .collect();
+        Ok((groups, partitions))
+    }
+}
+
+impl Operator for AggregateOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.input.open()?;
+        let (groups, partitions) = self.grouping.aggregate(self.input.as_mut(), 0)?;
+        self.groups = groups;
+        self.partitions = partitions
+            .into_iter()
+            .map(|partition| (partition, 1))
+            .collect();
+        Ok(())
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        loop {
+            if let Some(group) = self.groups.pop_front() {
+                return Ok(Some(group));
+            }
+            let Some((mut partition, depth)) = self.partitions.pop_front() else {
+                return Ok(None);
+            };
+            partition.open()?;
+            let (groups, partitions) = self.grouping.aggregate(&mut partition, depth)?;
+            self.groups = groups;
+            self.partitions.extend(
+                partitions
+                    .into_iter()
+                    .map(|partition| (partition, depth + 1)),
+            );
+        }
+    }
+}
+
+struct LimitOperator {
+    input: Box<dyn Operator>,
+    limit: Option<u64>,
+    offset: u64,
+    returned: u64,
+}
+
+impl Operator for LimitOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.returned = 0;
+        self.input.open()?;
+        for _ in 0..self.offset {
+            if self.input.next_tuple()?.is_none() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        if self.limit.is_some_and(|limit| self.returned >= limit) {
+            return Ok(None);
+        }
+        self.returned += 1;
+        self.input.next_tuple()
+    }
+}
+
+fn evaluate_all(expressions: &[BoundExpression], tuple: &[Value]) -> Result<Tuple, ExecutionError> {
+    expressions
+        .iter()
+        .map(|expression| expression.evaluate(tuple))
+        .collect::<Result<Tuple, _>>()
+        .map_err(ExecutionError::ExpressionError)
+}
+
+fn satisfies(condition: &Option<BoundExpression>, tuple: &[Value]) -> Result<bool, ExecutionError> {
+    match condition {
+        Some(condition) => condition
+            .is_true(tuple)
+            .map_err(ExecutionError::ExpressionError),
+        None => Ok(true),
+    }
+}
+
+fn joined(left: &[Value], right: &[Value]) -> Tuple {
+    let mut tuple = left.to_vec();
+    tuple.extend_from_slice(right);
+    tuple
+}
+
+/// Left tuple without a matching right one, its right columns are NULL
+fn padded(left: &[Value], right_width: usize) -> Tuple {
+    let mut tuple = left.to_vec();
+    tuple.resize(left.len() + right_width, Value::Null);
+    tuple
+}
+
+fn collect(operator: &mut dyn Operator) -> Result<Vec<Tuple>, ExecutionError> {
+    let mut tuples = vec![];
+    while let Some(tuple) = operator.next_tuple()? {
+        tuples.push(tuple);
+    }
+    Ok(tuples)
+}
+
+/// Rows returned by a query with the names and types of their columns
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ResultSet {
+    pub columns: Vec<PlanColumn>,
+    pub rows: Vec<Tuple>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum QueryResult {
+    Rows(ResultSet),
+    Inserted(usize),
+    Updated(usize),
+    Deleted(usize),
+    /// Number of tables analyzed
+    Analyzed(usize),
+    TableCreated,
+    TableDropped,
+    IndexCreated,
+    Begin,
+    Commit,
+    Rollback,
+}
+
+/// Executes statements, except transaction control, within a transaction
+pub struct Executor {
+    transaction: Transaction,
+    catalog: Catalog,
+}
+
+impl Executor {
+    pub fn new(transaction: &Transaction, catalog: &Catalog) -> Self {
+        Self {
+            transaction: transaction.clone(),
+            catalog: catalog.clone(),
+        }
+    }
+
+    pub fn execute(&self, statement: &Statement) -> Result<QueryResult, ExecutionError> {
+        debug!("Executor: executing {:?}", statement);
+        match statement {
+            Statement::CreateTable(create_table) => self.create_table(create_table),
+            Statement::DropTable(drop_table) => self.drop_table(drop_table),
+            Statement::CreateIndex(create_index) => self.create_index(create_index),
+            Statement::Insert(insert) => self.insert(insert),
+            Statement::Select(select) => {
+                let plan = self
+                    .planner()
+                    .plan_select(select)
+                    .map_err(ExecutionError::PlannerError)?;
+                self.query(&plan).map(QueryResult::Rows)
+            }
+            Statement::Update(update) => self.update(update),
+            Statement::Delete(delete) => self.delete(delete),
+            Statement::Analyze(analyze) => self.analyze(analyze),
+            Statement::Explain(explain) => self.explain(explain).map(QueryResult::Rows),
+            Statement::Begin | Statement::Commit | Statement::Rollback => {
+                Err(ExecutionError::TransactionControl)
+            }
+        }
+    }
+
+    /// Executes the plan, returning all its rows
+    pub fn query(&self, plan: &Plan) -> Result<ResultSet, ExecutionError> {
+        let mut operator = build_operator(&self.transaction, plan);
+        operator.open()?;
+        Ok(ResultSet {
+            columns: plan.columns.clone(),
+            rows: collect(operator.as_mut())?,
+        })
+    }
+
+    pub fn planner(&self) -> Planner {
+        Planner::new(&self.transaction, &self.catalog)
+    }
+
+    /// Plan of the query as lines of text, running it for EXPLAIN ANALYZE
+    fn explain(&self, explain: &Explain) -> Result<ResultSet, ExecutionError> {
+        let mut planner = self.planner();
+        let plan = planner
+            .plan_select(&explain.select)
+            .map_err(ExecutionError::PlannerError)?;
+        explain_plan(
+            &self.transaction,
+            &plan,
+            planner.cost_model(),
+            explain.analyze,
+        )
+    }
+
+    fn create_table(&self, create_table: &CreateTable) -> Result<QueryResult, ExecutionError> {
+        let mut schema = Schema::new();
+        for column in &create_table.columns {
+            schema = if column.nullable {
+                schema.with_nullable_column(&column.name, column.column_type)
+            } else {
+                schema.with_column(&column.name, column.column_type)
+            };
+        }
+        match self
+            .catalog
+            .create_table(&self.transaction, &create_table.name, schema)
+        {
+            Err(CatalogError::AlreadyExists(_)) if create_table.if_not_exists => {}
+            result => {
+                result.map_err(ExecutionError::CatalogError)?;
+            }
+        }
+        Ok(QueryResult::TableCreated)
+    }
+
+    fn drop_table(&self, drop_table: &DropTable) -> Result<QueryResult, ExecutionError> {
+        match self.catalog.drop_table(&self.transaction, &drop_table.name) {
+            Err(CatalogError::TableNotFound(_)) if drop_table.if_exists => {}
+            result => result.map_err(ExecutionError::CatalogError)?,
+        }
+        Ok(QueryResult::TableDropped)
+    }
+
+    fn create_index(&self, create_index: &CreateIndex) -> Result<QueryResult, ExecutionError> {
+        self.open_table(&create_index.table_name)?
+            .create_index(
+                &self.catalog,
+                &create_index.name,
+                &create_index.column_name,
+                create_index.index_type,
+            )
+            .map_err(ExecutionError::TableError)?;
+        Ok(QueryResult::IndexCreated)
+    }
+
+    /// Inserts the rows, columns not given are NULL
+    fn insert(&self, insert: &Insert) -> Result<QueryResult, ExecutionError> {
+        let mut table = self.open_table(&insert.table_name)?;
+        let column_names: Vec<String> = match &insert.columns {
+            Some(columns) => columns.clone(),
+            None => table
+                .layout()
+                .schema()
+                .columns()
+                .iter()
+                .map(|column| column.name.clone())
+                .collect(),
+        };
+        for values in &insert.rows {
+            if values.len() != column_names.len() {
+                return Err(ExecutionError::ValueCount(column_names.len(), values.len()));
+            }
+            let mut row = table.layout().row();
+            for (name, expression) in column_names.iter().zip(values) {
+                let value = Planner::bind_constant(expression)
+                    .map_err(ExecutionError::PlannerError)?
+                    .evaluate(&[])
+                    .map_err(ExecutionError::ExpressionError)?;
+                row.set_value(name, value)
+                    .map_err(ExecutionError::LayoutError)?;
+            }
+            for column in table.layout().schema().columns() {
+                if !column.nullable && row.is_null(&column.name).unwrap() {
+                    return Err(ExecutionError::LayoutError(LayoutError::NotNullable(
+                        column.name.clone(),
+                    )));
+                }
+            }
+            table.insert(&row).map_err(ExecutionError::TableError)?;
+        }
+        Ok(QueryResult::Inserted(insert.rows.len()))
+    }
+
+    fn update(&self, update: &Update) -> Result<QueryResult, ExecutionError> {
+        let plan = self
+            .planner()
+            .plan_table_access(&update.table_name, update.where_clause.as_ref())
+            .map_err(ExecutionError::PlannerError)?;
+        let assignments = update
+            .assignments
+            .iter()
+            .map(|(name, expression)| {
+                Planner::bind_expression(expression, &plan.columns)
+                    .map(|bound| (name, bound))
+                    .map_err(ExecutionError::PlannerError)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut table = self.open_table(&update.table_name)?;
+        let matching = self.matching_records(&plan)?;
+        for (record_id, tuple) in &matching {
+            let mut row = table.layout().row();
+            for (column, value) in table.layout().schema().columns().iter().zip(tuple) {
+                row.set_value(&column.name, value.clone())
+                    .map_err(ExecutionError::LayoutError)?;
+            }
+            for (name, expression) in &assignments {
+                let value = expression
+                    .evaluate(tuple)
+                    .map_err(ExecutionError::ExpressionError)?;
+                row.set_value(name, value)
+                    .map_err(ExecutionError::LayoutError)?;
+            }
+            table
+                .update(record_id, &row)
+                .map_err(ExecutionError::TableError)?;
+        }
+        Ok(QueryResult::Updated(matching.len()))
+    }
+
+    fn delete(&self, delete: &Delete) -> Result<QueryResult, ExecutionError> {
+        let plan = self
+            .planner()
+            .plan_table_access(&delete.table_name, delete.where_clause.as_ref())
+            .map_err(ExecutionError::PlannerError)?;
+        let mut table = self.open_table(&delete.table_name)?;
+        let matching = self.matching_records(&plan)?;
+        for (record_id, _) in &matching {
+            table
+                .delete(record_id)
+                .map_err(ExecutionError::TableError)?;
+        }
+        Ok(QueryResult::Deleted(matching.len()))
+    }
+
+    /// Collects and stores the statistics of the table, of all tables without name
+    fn analyze(&self, analyze: &Analyze) -> Result<QueryResult, ExecutionError> {
+        let table_names = match &analyze.table_name {
+            Some(table_name) => vec![table_name.clone()],
+            None => self
+                .catalog
+                .table_names(&self.transaction)
+                .map_err(ExecutionError::CatalogError)?,
+        };
+        for table_name in &table_names {
+            let table = self.open_table(table_name)?;
+            TableStatistics::analyze(&self.transaction, table.info())
+                .and_then(|statistics| {
+                    statistics.store(&self.transaction, &self.catalog, table_name)
+                })
+                .map_err(ExecutionError::StatisticsError)?;
+        }
+        Ok(QueryResult::Analyzed(table_names.len()))
+    }
+
+    /// Records of all rows of the table access plan, read completely before any of them is
+    /// changed so that changed rows are not found again
+    fn matching_records(&self, plan: &Plan) -> Result<Vec<(RecordId, Tuple)>, ExecutionError> {
+        let mut operator = build_operator(&self.transaction, plan);
+        operator.open()?;
+        let mut records = vec![];
+        while let Some(tuple) = operator.next_tuple()? {
+            records.push((operator.record_id().unwrap(), tuple));
+        }
+        Ok(records)
+    }
+
+    fn open_table(&self, name: &str) -> Result<Table, ExecutionError> {
+        Table::open(&self.transaction, &self.catalog, name).map_err(|e| match e {
+            TableError::TableNotFound(name) => {
+                ExecutionError::PlannerError(PlannerError::TableNotFound(name))
+            }
+            e => ExecutionError::TableError(e),
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum ExecutionError {
+    PlannerError(PlannerError),
+    CatalogError(CatalogError),
+    StatisticsError(StatisticsError),
+    TableError(TableError),
+    TableScanError(TableScanError),
+    LayoutError(LayoutError),
+    ExpressionError(ExpressionError),
+    IoError(IoError),
+    ValueCount(usize, usize),
+    TransactionControl,
+}
+
+impl Display for ExecutionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecutionError::PlannerError(e) => write!(f, "Executor {}", e),
+            ExecutionError::CatalogError(e) => write!(f, "Executor {}", e),
+            ExecutionError::StatisticsError(e) => write!(f, "Executor {}", e),
+            ExecutionError::TableError(e) => write!(f, "Executor {}", e),
+            ExecutionError::TableScanError(e) => write!(f, "Executor {}", e),
+            ExecutionError::LayoutError(e) => write!(f, "Executor {}", e),
+            ExecutionError::ExpressionError(e) => write!(f, "Executor {}", e),
+            ExecutionError::IoError(e) => write!(f, "Executor: temporary file {}", e),
+            ExecutionError::ValueCount(expected, found) => {
+                write!(f, "Executor: expected {} values, found {}", expected, found)
+            }
+            ExecutionError::TransactionControl => {
+                write!(f, "Executor: transaction control belongs to the session")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datatypes::SortOrder;
+    use crate::db_management_system::hfdb::{HanfriedDb, HanfriedDbBuilder};
+    use crate::query_processing::ast::JoinKind;
+    use crate::query_processing::executor::{Executor, Tuple};
+    use crate::query_processing::expression::BoundExpression;
+    use crate::query_processing::plan::{Aggregate, AggregateFunction, Plan, PlanColumn, PlanNode};
+    use crate::record_management::schema::ColumnType;
+    use crate::record_management::value::Value;
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+
+    fn values(rows: Vec<Tuple>, width: usize) -> Box<Plan> {
+        let columns = (0..width)
+            .map(|index| PlanColumn {
+                table: None,
+                name: format!("c{}", index),
+                column_type: ColumnType::HugeInteger,
+            })
+            .collect();
+        Box::new(Plan::new(PlanNode::Values(rows), columns))
+    }
+
+    fn query(hfdb: &HanfriedDb, node: PlanNode, width: usize) -> Vec<Tuple> {
+        let plan = *values(vec![], width);
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let executor = Executor::new(&tx, &hfdb.catalog);
+        let rows = executor.query(&Plan::new(node, plan.columns)).unwrap().rows;
+        tx.commit().unwrap();
+        rows
+    }
+
+    fn temp_files(hfdb: &HanfriedDb) -> usize {
+        let name = hfdb.file_manager.temp_filename();
+        name.as_str()["temp".len()..].parse().unwrap()
+    }
+
+    #[test]
+    fn test_sort_aggregate_and_hash_join_spill_to_temp_files() {
+        init_logging();
+        // 4 buffers of 400 bytes give each operator 1600 bytes of memory
+        let hfdb = HanfriedDbBuilder::unittest("executor_spill")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(400).unwrap()))
+            .buffer_manager(|bm| bm.pool_size(4))
+            .build()
+            .unwrap();
+
+        let rows: Vec<Tuple> = (0..1000i64)
+            .map(|i| vec![Value::from((i * 7919) % 10), Value::from(i)])
+            .collect();
+        let sorted = query(
+            &hfdb,
+            PlanNode::Sort {
+                input: values(rows.clone(), 2),
+                keys: vec![(BoundExpression::Column(0), SortOrder::Descending)],
+            },
+            2,
+        );
+        let mut expected = rows.clone();
+        expected.sort_by(|a, b| b[0].cmp(&a[0]));
+        assert_eq!(sorted, expected);
+        let after_sort = temp_files(&hfdb);
+        assert!(after_sort > 10, "{} temp files", after_sort);
+
+        let aggregated = query(
+            &hfdb,
+            PlanNode::Aggregate {
+                input: values(
+                    (0..1200i64)
+                        .map(|i| vec![Value::from(i % 300), Value::from(i)])
+                        .collect(),
+                    2,
+                ),
+                group_by: vec![BoundExpression::Column(0)],
+                aggregates: vec![
+                    Aggregate {
+                        function: AggregateFunction::Count,
+                        argument: None,
+                    },
+                    Aggregate {
+                        function: AggregateFunction::Sum,
+                        argument: Some(BoundExpression::Column(1)),
+                    },
+                ],
+            },
+            3,
+        );
+        assert!(temp_files(&hfdb) > after_sort + 1);
+        assert_eq!(aggregated[0][0], Value::from(0i64));
+        let mut aggregated = aggregated;
+        aggregated.sort();
+        let expected: Vec<Tuple> = (0..300i64)
+            .map(|group| {
+                let sum = (0..4).map(|n| group + n * 300).sum::<i64>();
+                vec![Value::from(group), Value::from(4i64), Value::from(sum)]
+            })
+            .collect();
+        assert_eq!(aggregated, expected);
+
+        let before_join = temp_files(&hfdb);
+        let mut left: Vec<Tuple> = (0..400i64)
+            .map(|i| vec![Value::from(i), Value::from(i % 100)])
+            .collect();
+        left.push(vec![Value::from(400i64), Value::Null]);
+        let mut right: Vec<Tuple> = (0..300i64)
+            .map(|i| vec![Value::from(i % 150), Value::from(format!("right {}", i))])
+            .collect();
+        right.push(vec![Value::Null, Value::from("null key")]);
+        for kind in [JoinKind::Inner, JoinKind::Left] {
+            let mut joined = query(
+                &hfdb,
+                PlanNode::HashJoin {
+                    left: values(left.clone(), 2),
+                    right: values(right.clone(), 2),
+                    kind,
+                    left_keys: vec![BoundExpression::Column(1)],
+                    right_keys: vec![BoundExpression::Column(0)],
+                    condition: None,
+                },
+                4,
+            );
+            joined.sort();
+            let mut expected: Vec<Tuple> = left
+                .iter()
+                .flat_map(|l| {
+                    right
+                        .iter()
+                        .filter(|r| !l[1].is_null() && l[1] == r[0])
+                        .map(|r| [l.clone(), r.clone()].concat())
+                })
+                .collect();
+            if kind == JoinKind::Left {
+                expected.push(vec![
+                    Value::from(400i64),
+                    Value::Null,
+                    Value::Null,
+                    Value::Null,
+                ]);
+            }
+            expected.sort();
+            assert_eq!(joined, expected);
+        }
+        assert!(temp_files(&hfdb) > before_join + 2);
+
+        let leftovers = std::fs::read_dir("/data/hanfried-db-unittest/executor_spill")
+            .unwrap()
+            .filter(|entry| {
+                entry
+                    .as_ref()
+                    .unwrap()
+                    .file_name()
+                    .to_str()
+                    .unwrap()
+                    .starts_with("temp")
+            })
+            .count();
+        assert_eq!(leftovers, 0);
+    }
+}
@@ -0,0 +1,514 @@
+use crate::datatypes::SortOrder;
+use crate::query_processing::ast::{Expression, JoinKind};
+use crate::query_processing::cost::CostModel;
+use crate::query_processing::executor::{
+    build_instrumented_operator, ExecutionError, Operator, ResultSet, Tuple,
+};
+use crate::query_processing::expression::BoundExpression;
+use crate::query_processing::plan::{IndexLookup, Plan, PlanColumn, PlanNode};
+use crate::record_management::schema::ColumnType;
+use crate::record_management::table_scan::RecordId;
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::{IoStatistics, Transaction};
+use std::cell::RefCell;
+use std::ops::Bound;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+/// What an operator did while the query ran. Like the elapsed time, the buffer and log work
+/// includes the work done by the inputs of the operator.
+#[derive(Debug, Default, Clone, Copy, Eq, PartialEq)]
+pub struct OperatorMetrics {
+    pub rows: usize,
+    pub elapsed: Duration,
+    pub io: IoStatistics,
+}
+
+/// Passes the tuples of the operator on, measuring the time and I/O spent in it
+pub struct InstrumentedOperator {
+    transaction: Transaction,
+    operator: Box<dyn Operator>,
+    metrics: Rc<RefCell<OperatorMetrics>>,
+}
+
+impl InstrumentedOperator {
+    pub fn new(
+        transaction: &Transaction,
+        operator: Box<dyn Operator>,
+        metrics: Rc<RefCell<OperatorMetrics>>,
+    ) -> Self {
+        Self {
+            transaction: transaction.clone(),
+            operator,
+            metrics,
+        }
+    }
+
+    fn measure<T>(
+        &mut self,
+        f: impl FnOnce(&mut dyn Operator) -> Result<T, ExecutionError>,
+    ) -> Result<T, ExecutionError> {
+        let io_before = self.transaction.io_statistics();
+        let start_time = Instant::now();
+        let result = f(self.operator.as_mut());
+        let elapsed = start_time.elapsed();
+        let io = self.transaction.io_statistics().since(&io_before);
+        let mut metrics = self.metrics.borrow_mut();
+        metrics.elapsed += elapsed;
+        metrics.io.blocks_pinned += io.blocks_pinned;
+        metrics.io.buffer_hits += io.buffer_hits;
+        metrics.io.disk_reads += io.disk_reads;
+        metrics.io.log_bytes += io.log_bytes;
+        result
+    }
+}
+
+impl Operator for InstrumentedOperator {
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.measure(|operator| operator.open())
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        let tuple = self.measure(|operator| operator.next_tuple())?;
+        if tuple.is_some() {
+            self.metrics.borrow_mut().rows += 1;
+        }
+        Ok(tuple)
+    }
+
+    fn record_id(&self) -> Option<RecordId> {
+        self.operator.record_id()
+    }
+}
+
+/// One line per plan node, indented below its parent, with the estimated rows and cost. With
+/// analyze the query is run and every line also shows what the operator actually did, followed
+/// by the total execution time.
+pub fn explain_plan(
+    transaction: &Transaction,
+    plan: &Plan,
+    cost_model: &CostModel,
+    analyze: bool,
+) -> Result<ResultSet, ExecutionError> {
+    let mut metrics = vec![];
+    let mut execution_time = None;
+    if analyze {
+        let start_time = Instant::now();
+        let mut operator = build_instrumented_operator(transaction, plan, &mut metrics);
+        operator.open()?;
+        while operator.next_tuple()?.is_some() {}
+        execution_time = Some(start_time.elapsed());
+    }
+    let mut lines = vec![];
+    let mut metrics = metrics.iter().map(|metrics| *metrics.borrow());
+    explain_node(plan, cost_model, &mut metrics, 0, &mut lines);
+    if let Some(execution_time) = execution_time {
+        lines.push(format!("Execution Time: {}", milliseconds(execution_time)));
+    }
+    Ok(ResultSet {
+        columns: explain_columns(),
+        rows: lines
+            .into_iter()
+            .map(|line| vec![Value::Text(line)])
+            .collect(),
+    })
+}
+
+/// The single column of the lines of the plan
+pub fn explain_columns() -> Vec<PlanColumn> {
+    vec![PlanColumn {
+        table: None,
+        name: "QUERY PLAN".to_string(),
+        column_type: ColumnType::Varchar,
+    }]
+}
+
+fn explain_node(
+    plan: &Plan,
+    cost_model: &CostModel,
+    metrics: &mut impl Iterator<Item = OperatorMetrics>,
+    depth: usize,
+    lines: &mut Vec<String>,
+) {
+    let estimate = cost_model.estimate(plan);
+    let mut line = format!(
+        "{}{}  (rows={:.0} cost={:.2})",
+        if depth == 0 {
+            String::new()
+        } else {
+            format!("{}->  ", "   ".repeat(depth - 1))
+        },
+        describe(plan),
+        estimate.rows,
+        estimate.cost
+    );
+    if let Some(metrics) = metrics.next() {
+        line.push_str(&format!(
+            " (actual rows={} time={} pinned={} hits={} reads={} log={}B)",
+            metrics.rows,
+            milliseconds(metrics.elapsed),
+            metrics.io.blocks_pinned,
+            metrics.io.buffer_hits,
+            metrics.io.disk_reads,
+            metrics.io.log_bytes
+        ));
+    }
+    lines.push(line);
+    for input in inputs(plan) {
+        explain_node(input, cost_model, metrics, depth + 1, lines);
+    }
+}
+
+fn inputs(plan: &Plan) -> Vec<&Plan> {
+    match &plan.node {
+        PlanNode::Values(_) | PlanNode::TableScan { .. } | PlanNode::IndexScan { .. } => vec![],
+        PlanNode::Filter { input, .. }
+        | PlanNode::Project { input, .. }
+        | PlanNode::Sort { input, .. }
+        | PlanNode::Aggregate { input, .. }
+        | PlanNode::Limit { input, .. } => vec![input],
+        PlanNode::NestedLoopJoin { left, right, .. }
+        | PlanNode::HashJoin { left, right, .. }
+        | PlanNode::MergeJoin { left, right, .. } => vec![left, right],
+    }
+}
+
+fn describe(plan: &Plan) -> String {
+    match &plan.node {
+        PlanNode::Values(rows) => format!("Values ({} rows)", rows.len()),
+        PlanNode::TableScan { table } => {
+            format!("Table Scan on {}", table_reference(&table.name, plan))
+        }
+        PlanNode::IndexScan {
+            table,
+            index,
+            lookup,
+        } => {
+            let column = Expression::column(&index.column_name);
+            let condition = match lookup {
+                IndexLookup::Equal(value) => format!("{} = {}", column, literal(value)),
+                IndexLookup::Range(lower, upper) => {
+                    let bounds: Vec<String> = [(lower, ">", ">="), (upper, "<", "<=")]
+                        .into_iter()
+                        .filter_map(|(bound, exclusive, inclusive)| match bound {
+                            Bound::Included(value) => {
+                                Some(format!("{} {} {}", column, inclusive, literal(value)))
+                            }
+                            Bound::Excluded(value) => {
+                                Some(format!("{} {} {}", column, exclusive, literal(value)))
+                            }
+                            Bound::Unbounded => None,
+                        })
+                        .collect();
+                    bounds.join(" AND ")
+                }
+            };
+            format!(
+                "{} Index Scan using {} on {} where {}",
+                index.index_type.name(),
+                index.name,
+                table_reference(&table.name, plan),
+                condition
+            )
+        }
+        PlanNode::Filter { input, predicate } => {
+            format!("Filter {}", unbound(predicate, &input.columns))
+        }
+        PlanNode::Project { input, expressions } => {
+            format!("Project {}", expression_list(expressions, &input.columns))
+        }
+        PlanNode::NestedLoopJoin {
+            left,
+            right,
+            kind,
+            condition,
+        } => {
+            let mut description = format!("Nested Loop {} Join", join_kind(*kind));
+            if let Some(condition) = condition {
+                let columns = [left.columns.as_slice(), right.columns.as_slice()].concat();
+                description.push_str(&format!(" on {}", unbound(condition, &columns)));
+            }
+            description
+        }
+        PlanNode::HashJoin {
+            left,
+            right,
+            kind,
+            left_keys,
+            right_keys,
+            condition,
+        } => {
+            let mut conditions: Vec<String> = left_keys
+                .iter()
+                .zip(right_keys)
+                .map(|(left_key, right_key)| {
+                    format!(
+                        "{} = {}",
+                        unbound(left_key, &left.columns),
+                        unbound(right_key, &right.columns)
+                    )
+                })
+                .collect();
+            if let Some(condition) = condition {
+                let columns = [left.columns.as_slice(), right.columns.as_slice()].concat();
+                conditions.push(unbound(condition, &columns).to_string());
+            }
+            format!(
+                "Hash {} Join on {}",
+                join_kind(*kind),
+                conditions.join(" AND ")
+            )
+        }
+        PlanNode::MergeJoin {
+            left,
+            right,
+            kind,
+            left_key,
+            right_key,
+            condition,
+        } => {
+            let mut description = format!(
+                "Merge {} Join on {} = {}",
+                join_kind(*kind),
+                column_expression(&left.columns[*left_key]),
+                column_expression(&right.columns[*right_key])
+            );
+            if let Some(condition) = condition {
+                let columns = [left.columns.as_slice(), right.columns.as_slice()].concat();
+                description.push_str(&format!(" AND {}", unbound(condition, &columns)));
+            }
+            description
+        }
+        PlanNode::Sort { input, keys } => {
+            let keys: Vec<String> = keys
+                .iter()
+                .map(|(key, order)| match order {
+                    SortOrder::Ascending => unbound(key, &input.columns).to_string(),
+                    SortOrder::Descending => format!("{} DESC", unbound(key, &input.columns)),
+                })
+                .collect();
+            format!("Sort by {}", keys.join(", "))
+        }
+        PlanNode::Aggregate {
+            input,
+            group_by,
+            aggregates,
+        } => {
+            let aggregates: Vec<String> = aggregates
+                .iter()
+                .map(|aggregate| {
+                    let name = format!("{:?}", aggregate.function).to_lowercase();
+                    match &aggregate.argument {
+                        Some(argument) => {
+                            format!("{}({})", name, unbound(argument, &input.columns))
+                        }
+                        None => format!("{}(*)", name),
+                    }
+                })
+                .collect();
+            let mut description = format!("Aggregate {}", aggregates.join(", "));
+            if !group_by.is_empty() {
+                description.push_str(&format!(
+                    " group by {}",
+                    expression_list(group_by, &input.columns)
+                ));
+            }
+            description
+        }
+        PlanNode::Limit { limit, offset, .. } => {
+            let mut description = "Limit".to_string();
+            if let Some(limit) = limit {
+                description.push_str(&format!(" {}", limit));
+            }
+            if *offset > 0 {
+                description.push_str(&format!(" offset {}", offset));
+            }
+            description
+        }
+    }
+}
+
+/// Table name followed by the alias it is referenced with in the query, if any
+fn table_reference(table_name: &str, plan: &Plan) -> String {
+    match plan
+        .columns
+        .first()
+        .and_then(|column| column.table.as_ref())
+    {
+        Some(alias) if alias != table_name => format!("{} {}", table_name, alias),
+        _ => table_name.to_string(),
+    }
+}
+
+fn join_kind(kind: JoinKind) -> &'static str {
+    match kind {
+        JoinKind::Inner => "Inner",
+        JoinKind::Left => "Left",
+        JoinKind::Cross => "Cross",
+    }
+}
+
+fn literal(value: &Value) -> Expression {
+    Expression::Literal(value.clone())
+}
+
+fn column_expression(column: &PlanColumn) -> Expression {
+    Expression::Column {
+        table: column.table.clone(),
+        name: column.name.clone(),
+    }
+}
+
+fn expression_list(expressions: &[BoundExpression], columns: &[PlanColumn]) -> String {
+    expressions
+        .iter()
+        .map(|expression| unbound(expression, columns).to_string())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Expression referring to the columns by name again, to show it as SQL text
+fn unbound(expression: &BoundExpression, columns: &[PlanColumn]) -> Expression {
+    match expression {
+        BoundExpression::Literal(value) => literal(value),
+        BoundExpression::Column(index) => column_expression(&columns[*index]),
+        BoundExpression::Unary { operator, operand } => Expression::Unary {
+            operator: *operator,
+            operand: Box::new(unbound(operand, columns)),
+        },
+        BoundExpression::Binary {
+            left,
+            operator,
+            right,
+        } => Expression::binary(unbound(left, columns), *operator, unbound(right, columns)),
+        BoundExpression::IsNull {
+            expression,
+            negated,
+        } => Expression::IsNull {
+            expression: Box::new(unbound(expression, columns)),
+            negated: *negated,
+        },
+    }
+}
+
+fn milliseconds(duration: Duration) -> String {
+    format!("{:.3}ms", duration.as_secs_f64() * 1000.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::query_processing::executor::QueryResult;
+    use crate::query_processing::session::Session;
+    use crate::record_management::value::Value;
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+
+    fn lines(session: &mut Session, sql: &str) -> Vec<String> {
+        match session.execute(sql).unwrap().pop() {
+            Some(QueryResult::Rows(result_set)) => {
+                assert_eq!(result_set.columns[0].name, "QUERY PLAN");
+                result_set
+                    .rows
+                    .into_iter()
+                    .map(|row| match &row[..] {
+                        [Value::Text(line)] => line.clone(),
+                        row => panic!("{:?} is no plan line", row),
+                    })
+                    .collect()
+            }
+            result => panic!("{:?} returned no rows", result),
+        }
+    }
+
+    /// Value of `name=` in the line, e.g. the actual rows
+    fn metric(line: &str, name: &str) -> usize {
+        let (_, actual) = line.split_once("(actual ").unwrap();
+        let (_, value) = actual.split_once(&format!("{}=", name)).unwrap();
+        value
+            .split(|c: char| !c.is_ascii_digit())
+            .next()
+            .unwrap()
+            .parse()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_explain_and_explain_analyze() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("explain")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(400).unwrap()))
+            .build()
+            .unwrap();
+        let mut session = Session::new(&hfdb.transaction_manager, &hfdb.catalog);
+        session
+            .execute(
+                "CREATE TABLE cities (id INT NOT NULL, name VARCHAR NOT NULL);
+                 CREATE TABLE persons (id INT NOT NULL, name TEXT, city_id INT, age INT);
+                 CREATE INDEX cities_id ON cities (id);
+                 INSERT INTO cities VALUES (1, 'Berlin'), (2, 'Hamburg');
+                 INSERT INTO persons VALUES
+                    (1, 'Ada', 1, 36), (2, 'Bob', 2, 25), (3, 'Cid', 1, 51), (4, 'Dan', 2, 17);",
+            )
+            .unwrap();
+
+        let explained = lines(
+            &mut session,
+            "EXPLAIN SELECT name FROM persons WHERE age > 20",
+        );
+        assert_eq!(explained.len(), 3, "{:?}", explained);
+        assert!(explained[0].starts_with("Project persons.name  (rows="));
+        assert!(explained[1].starts_with("->  Filter (persons.age > 20)  (rows="));
+        assert!(explained[2].starts_with("   ->  Table Scan on persons  (rows="));
+        assert!(explained.iter().all(|line| !line.contains("actual")));
+
+        let cities: Vec<String> = (3..200)
+            .map(|id| format!("({}, 'City{}')", id, id))
+            .collect();
+        session
+            .execute(&format!(
+                "INSERT INTO cities VALUES {}; ANALYZE",
+                cities.join(", ")
+            ))
+            .unwrap();
+        let explained = lines(
+            &mut session,
+            "EXPLAIN SELECT name FROM cities c WHERE c.id = 2",
+        );
+        assert!(
+            explained
+                .iter()
+                .any(|line| line
+                    .contains("BTree Index Scan using cities_id on cities c where id = 2")),
+            "{:?}",
+            explained
+        );
+
+        let explained = lines(
+            &mut session,
+            "EXPLAIN ANALYZE SELECT p.name, c.name FROM persons p JOIN cities c ON p.city_id = c.id \
+             WHERE p.age > 20",
+        );
+        let execution_time = explained.last().unwrap();
+        assert!(execution_time.starts_with("Execution Time: "));
+        let operators = &explained[..explained.len() - 1];
+        assert_eq!(metric(&operators[0], "rows"), 3, "{:?}", explained);
+        let persons_scan = operators
+            .iter()
+            .find(|line| line.contains("Table Scan on persons p"))
+            .unwrap();
+        assert_eq!(metric(persons_scan, "rows"), 4);
+        for line in operators {
+            assert!(line.contains("(rows="), "{}", line);
+            assert_eq!(
+                metric(line, "pinned"),
+                metric(line, "hits") + metric(line, "reads"),
+                "{}",
+                line
+            );
+            assert_eq!(metric(line, "log"), 0, "{}", line);
+        }
+        assert!(metric(persons_scan, "pinned") > 0);
+        assert!(metric(&operators[0], "pinned") >= metric(persons_scan, "pinned"));
+    }
+}
@@ -0,0 +1,334 @@
+use crate::query_processing::ast::{BinaryOperator, UnaryOperator};
+use crate::record_management::value::Value;
+use std::cmp::Ordering;
+use std::fmt::{Display, Formatter};
+
+/// Expression with its column references resolved to positions within the input tuple.
+///
+/// There is no boolean type: conditions evaluate to the integers 1 (true) and 0 (false), or to
+/// NULL (unknown) following the three-valued logic of SQL.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum BoundExpression {
+    Literal(Value),
+    Column(usize),
+    Unary {
+        operator: UnaryOperator,
+        operand: Box<BoundExpression>,
+    },
+    Binary {
+        left: Box<BoundExpression>,
+        operator: BinaryOperator,
+        right: Box<BoundExpression>,
+    },
+    IsNull {
+        expression: Box<BoundExpression>,
+        negated: bool,
+    },
+}
+
+impl BoundExpression {
+    pub fn binary(left: BoundExpression, operator: BinaryOperator, right: BoundExpression) -> Self {
+        BoundExpression::Binary {
+            left: Box::new(left),
+            operator,
+            right: Box::new(right),
+        }
+    }
+
+    pub fn evaluate(&self, tuple: &[Value]) -> Result<Value, ExpressionError> {
+        match self {
+            BoundExpression::Literal(value) => Ok(value.clone()),
+            BoundExpression::Column(index) => Ok(tuple[*index].clone()),
+            BoundExpression::Unary { operator, operand } => {
+                match (operator, operand.evaluate(tuple)?) {
+                    (_, Value::Null) => Ok(Value::Null),
+                    (UnaryOperator::Not, Value::Integer(value)) => Ok(Self::boolean(value == 0)),
+                    (UnaryOperator::Minus, Value::Integer(value)) => value
+                        .checked_neg()
+                        .map(Value::Integer)
+                        .ok_or(ExpressionError::Overflow),
+                    (_, value) => Err(ExpressionError::InvalidOperand(
+                        Self::unary_symbol(*operator),
+                        value,
+                    )),
+                }
+            }
+            BoundExpression::Binary {
+                left,
+                operator: BinaryOperator::And,
+                right,
+            } => match Self::truth(left.evaluate(tuple)?)? {
+                Some(false) => Ok(Self::boolean(false)),
+                left => match (left, Self::truth(right.evaluate(tuple)?)?) {
+                    (_, Some(false)) => Ok(Self::boolean(false)),
+                    (Some(true), Some(true)) => Ok(Self::boolean(true)),
+                    _ => Ok(Value::Null),
+                },
+            },
+            BoundExpression::Binary {
+                left,
+                operator: BinaryOperator::Or,
+                right,
+            } => match Self::truth(left.evaluate(tuple)?)? {
+                Some(true) => Ok(Self::boolean(true)),
+                left => match (left, Self::truth(right.evaluate(tuple)?)?) {
+                    (_, Some(true)) => Ok(Self::boolean(true)),
+                    (Some(false), Some(false)) => Ok(Self::boolean(false)),
+                    _ => Ok(Value::Null),
+                },
+            },
+            BoundExpression::Binary {
+                left,
+                operator,
+                right,
+            } => Self::apply(*operator, left.evaluate(tuple)?, right.evaluate(tuple)?),
+            BoundExpression::IsNull {
+                expression,
+                negated,
+            } => Ok(Self::boolean(
+                expression.evaluate(tuple)?.is_null() != *negated,
+            )),
+        }
+    }
+
+    /// Whether the condition holds, unknown counts as false
+    pub fn is_true(&self, tuple: &[Value]) -> Result<bool, ExpressionError> {
+        Ok(Self::truth(self.evaluate(tuple)?)? == Some(true))
+    }
+
+    /// Column positions the expression refers to
+    pub fn columns(&self) -> Vec<usize> {
+        match self {
+            BoundExpression::Literal(_) => vec![],
+            BoundExpression::Column(index) => vec![*index],
+            BoundExpression::Unary { operand, .. } => operand.columns(),
+            BoundExpression::Binary { left, right, .. } => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+            BoundExpression::IsNull { expression, .. } => expression.columns(),
+        }
+    }
+
+    /// The expression with all column positions moved by the offset, e.g. to apply it to the
+    /// right part of a joined tuple
+    pub fn shifted(&self, offset: isize) -> BoundExpression {
+        self.mapped(&|index| (index as isize + offset) as usize)
+    }
+
+    /// The expression with all column positions replaced by the mapping
+    pub fn mapped(&self, mapping: &dyn Fn(usize) -> usize) -> BoundExpression {
+        match self {
+            BoundExpression::Literal(value) => BoundExpression::Literal(value.clone()),
+            BoundExpression::Column(index) => BoundExpression::Column(mapping(*index)),
+            BoundExpression::Unary { operator, operand } => BoundExpression::Unary {
+                operator: *operator,
+                operand: Box::new(operand.mapped(mapping)),
+            },
+            BoundExpression::Binary {
+                left,
+                operator,
+                right,
+            } => BoundExpression::binary(left.mapped(mapping), *operator, right.mapped(mapping)),
+            BoundExpression::IsNull {
+                expression,
+                negated,
+            } => BoundExpression::IsNull {
+                expression: Box::new(expression.mapped(mapping)),
+                negated: *negated,
+            },
+        }
+    }
+
+    pub fn boolean(value: bool) -> Value {
+        Value::Integer(value as i128)
+    }
+
+    fn truth(value: Value) -> Result<Option<bool>, ExpressionError> {
+        match value {
+            Value::Null => Ok(None),
+            Value::Integer(value) => Ok(Some(value != 0)),
+            value => Err(ExpressionError::NotABoolean(value)),
+        }
+    }
+
+    fn apply(
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+    ) -> Result<Value, ExpressionError> {
+        if left.is_null() || right.is_null() {
+            return Ok(Value::Null);
+        }
+        let ordering = || Self::compare(operator, &left, &right);
+        match operator {
+            BinaryOperator::Equal => Ok(Self::boolean(ordering()? == Ordering::Equal)),
+            BinaryOperator::NotEqual => Ok(Self::boolean(ordering()? != Ordering::Equal)),
+            BinaryOperator::Less => Ok(Self::boolean(ordering()? == Ordering::Less)),
+            BinaryOperator::LessEqual => Ok(Self::boolean(ordering()? != Ordering::Greater)),
+            BinaryOperator::Greater => Ok(Self::boolean(ordering()? == Ordering::Greater)),
+            BinaryOperator::GreaterEqual => Ok(Self::boolean(ordering()? != Ordering::Less)),
+            BinaryOperator::Concat => match (left, right) {
+                (Value::Text(left), Value::Text(right)) => Ok(Value::Text(left + &right)),
+                (Value::Bytes(mut left), Value::Bytes(right)) => {
+                    left.extend(right);
+                    Ok(Value::Bytes(left))
+                }
+                (left, right) => Err(ExpressionError::TypeMismatch(operator, left, right)),
+            },
+            BinaryOperator::Add
+            | BinaryOperator::Subtract
+            | BinaryOperator::Multiply
+            | BinaryOperator::Divide
+            | BinaryOperator::Modulo => match (left, right) {
+                (Value::Integer(left), Value::Integer(right)) => {
+                    Self::arithmetic(operator, left, right).map(Value::Integer)
+                }
+                (left, right) => Err(ExpressionError::TypeMismatch(operator, left, right)),
+            },
+            BinaryOperator::And | BinaryOperator::Or => {
+                unreachable!("evaluated with three-valued logic")
+            }
+        }
+    }
+
+    fn compare(
+        operator: BinaryOperator,
+        left: &Value,
+        right: &Value,
+    ) -> Result<Ordering, ExpressionError> {
+        match (left, right) {
+            (Value::Integer(_), Value::Integer(_))
+            | (Value::Text(_), Value::Text(_))
+            | (Value::Bytes(_), Value::Bytes(_)) => Ok(left.cmp(right)),
+            _ => Err(ExpressionError::TypeMismatch(
+                operator,
+                left.clone(),
+                right.clone(),
+            )),
+        }
+    }
+
+    fn arithmetic(
+        operator: BinaryOperator,
+        left: i128,
+        right: i128,
+    ) -> Result<i128, ExpressionError> {
+        let result = match operator {
+            BinaryOperator::Add => left.checked_add(right),
+            BinaryOperator::Subtract => left.checked_sub(right),
+            BinaryOperator::Multiply => left.checked_mul(right),
+            BinaryOperator::Divide | BinaryOperator::Modulo if right == 0 => {
+                return Err(ExpressionError::DivisionByZero)
+            }
+            BinaryOperator::Divide => left.checked_div(right),
+            _ => left.checked_rem(right),
+        };
+        result.ok_or(ExpressionError::Overflow)
+    }
+
+    fn unary_symbol(operator: UnaryOperator) -> &'static str {
+        match operator {
+            UnaryOperator::Not => "NOT",
+            UnaryOperator::Minus => "-",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ExpressionError {
+    TypeMismatch(BinaryOperator, Value, Value),
+    InvalidOperand(&'static str, Value),
+    NotABoolean(Value),
+    DivisionByZero,
+    Overflow,
+}
+
+impl Display for ExpressionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExpressionError::TypeMismatch(operator, left, right) => write!(
+                f,
+                "Expression: operator {} can not be applied to {:?} and {:?}",
+                operator.symbol(),
+                left,
+                right
+            ),
+            ExpressionError::InvalidOperand(operator, value) => write!(
+                f,
+                "Expression: operator {} can not be applied to {:?}",
+                operator, value
+            ),
+            ExpressionError::NotABoolean(value) => {
+                write!(f, "Expression: {:?} is no condition", value)
+            }
+            ExpressionError::DivisionByZero => write!(f, "Expression: division by zero"),
+            ExpressionError::Overflow => write!(f, "Expression: integer overflow"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_processing::ast::BinaryOperator;
+    use crate::query_processing::expression::{BoundExpression, ExpressionError};
+    use crate::record_management::value::Value;
+
+    #[test]
+    fn test_evaluate_three_valued_logic_and_arithmetic() {
+        let tuple = [Value::Integer(7), Value::Null, Value::from("a")];
+        let seven = || BoundExpression::Column(0);
+        let null = || BoundExpression::Column(1);
+        let less = BoundExpression::binary(
+            seven(),
+            BinaryOperator::Less,
+            BoundExpression::Literal(Value::Integer(10)),
+        );
+        let unknown = BoundExpression::binary(null(), BinaryOperator::Equal, seven());
+        assert_eq!(less.evaluate(&tuple), Ok(Value::Integer(1)));
+        assert_eq!(unknown.evaluate(&tuple), Ok(Value::Null));
+        assert_eq!(
+            BoundExpression::binary(less.clone(), BinaryOperator::Or, unknown.clone())
+                .evaluate(&tuple),
+            Ok(Value::Integer(1))
+        );
+        assert_eq!(
+            BoundExpression::binary(less, BinaryOperator::And, unknown.clone()).evaluate(&tuple),
+            Ok(Value::Null)
+        );
+        assert!(!unknown.is_true(&tuple).unwrap());
+        assert_eq!(
+            BoundExpression::binary(
+                BoundExpression::binary(seven(), BinaryOperator::Multiply, seven()),
+                BinaryOperator::Modulo,
+                BoundExpression::Literal(Value::Integer(5))
+            )
+            .evaluate(&tuple),
+            Ok(Value::Integer(4))
+        );
+        assert_eq!(
+            BoundExpression::binary(
+                BoundExpression::Column(2),
+                BinaryOperator::Concat,
+                BoundExpression::Literal(Value::from("b"))
+            )
+            .evaluate(&tuple),
+            Ok(Value::from("ab"))
+        );
+        assert_eq!(
+            BoundExpression::binary(
+                seven(),
+                BinaryOperator::Divide,
+                BoundExpression::Literal(Value::Integer(0))
+            )
+            .evaluate(&tuple),
+            Err(ExpressionError::DivisionByZero)
+        );
+        assert!(matches!(
+            BoundExpression::binary(seven(), BinaryOperator::Equal, BoundExpression::Column(2))
+                .evaluate(&tuple),
+            Err(ExpressionError::TypeMismatch(BinaryOperator::Equal, _, _))
+        ));
+    }
+}
@@ -0,0 +1,531 @@
+use std::fmt::{Display, Formatter};
+
+/// Line and column (both starting at 1) of a character in the SQL text
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct Position {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Display for Position {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Keyword {
+    Analyze,
+    And,
+    As,
+    Asc,
+    Begin,
+    By,
+    Commit,
+    Create,
+    Cross,
+    Delete,
+    Desc,
+    Distinct,
+    Drop,
+    Exists,
+    Explain,
+    From,
+    Group,
+    Having,
+    If,
+    Index,
+    Inner,
+    Insert,
+    Into,
+    Is,
+    Join,
+    Left,
+    Limit,
+    Not,
+    Null,
+    Offset,
+    On,
+    Or,
+    Order,
+    Outer,
+    Rollback,
+    Select,
+    Set,
+    Table,
+    Transaction,
+    Update,
+    Using,
+    Values,
+    Where,
+}
+
+impl Keyword {
+    const ALL: [Keyword; 43] = [
+        Keyword::Analyze,
+        Keyword::And,
+        Keyword::As,
+        Keyword::Asc,
+        Keyword::Begin,
+        Keyword::By,
+        Keyword::Commit,
+        Keyword::Create,
+        Keyword::Cross,
+        Keyword::Delete,
+        Keyword::Desc,
+        Keyword::Distinct,
+        Keyword::Drop,
+        Keyword::Exists,
+        Keyword::Explain,
+        Keyword::From,
+        Keyword::Group,
+        Keyword::Having,
+        Keyword::If,
+        Keyword::Index,
+        Keyword::Inner,
+        Keyword::Insert,
+        Keyword::Into,
+        Keyword::Is,
+        Keyword::Join,
+        Keyword::Left,
+        Keyword::Limit,
+        Keyword::Not,
+        Keyword::Null,
+        Keyword::Offset,
+        Keyword::On,
+        Keyword::Or,
+        Keyword::Order,
+        Keyword::Outer,
+        Keyword::Rollback,
+        Keyword::Select,
+        Keyword::Set,
+        Keyword::Table,
+        Keyword::Transaction,
+        Keyword::Update,
+        Keyword::Using,
+        Keyword::Values,
+        Keyword::Where,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Keyword::Analyze => "ANALYZE",
+            Keyword::And => "AND",
+            Keyword::As => "AS",
+            Keyword::Asc => "ASC",
+            Keyword::Begin => "BEGIN",
+            Keyword::By => "BY",
+            Keyword::Commit => "COMMIT",
+            Keyword::Create => "CREATE",
+            Keyword::Cross => "CROSS",
+            Keyword::Delete => "DELETE",
+            Keyword::Desc => "DESC",
+            Keyword::Distinct => "DISTINCT",
+            Keyword::Drop => "DROP",
+            Keyword::Exists => "EXISTS",
+            Keyword::Explain => "EXPLAIN",
+            Keyword::From => "FROM",
+            Keyword::Group => "GROUP",
+            Keyword::Having => "HAVING",
+            Keyword::If => "IF",
+            Keyword::Index => "INDEX",
+            Keyword::Inner => "INNER",
+            Keyword::Insert => "INSERT",
+            Keyword::Into => "INTO",
+            Keyword::Is => "IS",
+            Keyword::Join => "JOIN",
+            Keyword::Left => "LEFT",
+            Keyword::Limit => "LIMIT",
+            Keyword::Not => "NOT",
+            Keyword::Null => "NULL",
+            Keyword::Offset => "OFFSET",
+            Keyword::On => "ON",
+            Keyword::Or => "OR",
+            Keyword::Order => "ORDER",
+            Keyword::Outer => "OUTER",
+            Keyword::Rollback => "ROLLBACK",
+            Keyword::Select => "SELECT",
+            Keyword::Set => "SET",
+            Keyword::Table => "TABLE",
+            Keyword::Transaction => "TRANSACTION",
+            Keyword::Update => "UPDATE",
+            Keyword::Using => "USING",
+            Keyword::Values => "VALUES",
+            Keyword::Where => "WHERE",
+        }
+    }
+
+    /// Keyword with the name, case insensitive
+    pub fn from_name(name: &str) -> Option<Keyword> {
+        Self::ALL
+            .into_iter()
+            .find(|keyword| keyword.name().eq_ignore_ascii_case(name))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum TokenKind {
+    Keyword(Keyword),
+    Identifier(String),
+    Integer(i128),
+    String(String),
+    Bytes(Vec<u8>),
+    Comma,
+    Dot,
+    Semicolon,
+    LeftParenthesis,
+    RightParenthesis,
+    Star,
+    Plus,
+    Minus,
+    Slash,
+    Percent,
+    Concat,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    EndOfInput,
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Keyword(keyword) => write!(f, "{}", keyword.name()),
+            TokenKind::Identifier(name) => write!(f, "identifier {}", name),
+            TokenKind::Integer(value) => write!(f, "integer {}", value),
+            TokenKind::String(value) => write!(f, "string '{}'", value),
+            TokenKind::Bytes(_) => write!(f, "byte string"),
+            TokenKind::Comma => write!(f, "','"),
+            TokenKind::Dot => write!(f, "'.'"),
+            TokenKind::Semicolon => write!(f, "';'"),
+            TokenKind::LeftParenthesis => write!(f, "'('"),
+            TokenKind::RightParenthesis => write!(f, "')'"),
+            TokenKind::Star => write!(f, "'*'"),
+            TokenKind::Plus => write!(f, "'+'"),
+            TokenKind::Minus => write!(f, "'-'"),
+            TokenKind::Slash => write!(f, "'/'"),
+            TokenKind::Percent => write!(f, "'%'"),
+            TokenKind::Concat => write!(f, "'||'"),
+            TokenKind::Equal => write!(f, "'='"),
+            TokenKind::NotEqual => write!(f, "'<>'"),
+            TokenKind::Less => write!(f, "'<'"),
+            TokenKind::LessEqual => write!(f, "'<='"),
+            TokenKind::Greater => write!(f, "'>'"),
+            TokenKind::GreaterEqual => write!(f, "'>='"),
+            TokenKind::EndOfInput => write!(f, "end of input"),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub position: Position,
+}
+
+/// Splits SQL text into tokens, skipping whitespace and comments (`-- ...` up to the end of the
+/// line and `/* ... */`).
+///
+/// Identifiers may be quoted with double quotes to keep their case and to use keywords as names,
+/// unquoted ones are converted to lower case. Strings are quoted with single quotes (two single
+/// quotes for a quote within the string), byte strings are written as `X'0aff'`.
+pub struct Lexer<'a> {
+    chars: std::iter::Peekable<std::str::Chars<'a>>,
+    position: Position,
+}
+
+impl<'a> Lexer<'a> {
+    pub fn new(sql: &'a str) -> Self {
+        Self {
+            chars: sql.chars().peekable(),
+            position: Position { line: 1, column: 1 },
+        }
+    }
+
+    /// All tokens, the last one is always [TokenKind::EndOfInput]
+    pub fn tokens(mut self) -> Result<Vec<Token>, LexerError> {
+        let mut tokens = vec![];
+        loop {
+            let token = self.next_token()?;
+            let end = token.kind == TokenKind::EndOfInput;
+            tokens.push(token);
+            if end {
+                return Ok(tokens);
+            }
+        }
+    }
+
+    fn next_token(&mut self) -> Result<Token, LexerError> {
+        self.skip_whitespace_and_comments()?;
+        let position = self.position;
+        let c = match self.bump() {
+            Some(c) => c,
+            None => {
+                return Ok(Token {
+                    kind: TokenKind::EndOfInput,
+                    position,
+                })
+            }
+        };
+        let kind = match c {
+            ',' => TokenKind::Comma,
+            '.' => TokenKind::Dot,
+            ';' => TokenKind::Semicolon,
+            '(' => TokenKind::LeftParenthesis,
+            ')' => TokenKind::RightParenthesis,
+            '*' => TokenKind::Star,
+            '+' => TokenKind::Plus,
+            '-' => TokenKind::Minus,
+            '/' => TokenKind::Slash,
+            '%' => TokenKind::Percent,
+            '=' => TokenKind::Equal,
+            '|' if self.bump_if('|') => TokenKind::Concat,
+            '!' if self.bump_if('=') => TokenKind::NotEqual,
+            '<' if self.bump_if('=') => TokenKind::LessEqual,
+            '<' if self.bump_if('>') => TokenKind::NotEqual,
+            '<' => TokenKind::Less,
+            '>' if self.bump_if('=') => TokenKind::GreaterEqual,
+            '>' => TokenKind::Greater,
+            '\'' => TokenKind::String(self.quoted('\'', position)?),
+            '"' => TokenKind::Identifier(self.quoted('"', position)?),
+            'x' | 'X' if self.chars.peek() == Some(&'\'') => {
+                self.bump();
+                TokenKind::Bytes(Self::hex(&self.quoted('\'', position)?, position)?)
+            }
+            c if c.is_ascii_digit() => {
+                let digits = self.take_while(c, |c| c.is_ascii_alphanumeric());
+                TokenKind::Integer(
+                    digits
+                        .parse()
+                        .map_err(|_| LexerError::InvalidNumber(digits, position))?,
+                )
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let word = self.take_while(c, |c| c.is_alphanumeric() || c == '_');
+                match Keyword::from_name(&word) {
+                    Some(keyword) => TokenKind::Keyword(keyword),
+                    None => TokenKind::Identifier(word.to_lowercase()),
+                }
+            }
+            c => return Err(LexerError::UnexpectedCharacter(c, position)),
+        };
+        Ok(Token { kind, position })
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        if c == '\n' {
+            self.position.line += 1;
+            self.position.column = 1;
+        } else {
+            self.position.column += 1;
+        }
+        Some(c)
+    }
+
+    fn bump_if(&mut self, expected: char) -> bool {
+        if self.chars.peek() == Some(&expected) {
+            self.bump();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn take_while(&mut self, first: char, predicate: impl Fn(char) -> bool) -> String {
+        let mut word = String::from(first);
+        while let Some(&c) = self.chars.peek() {
+            if !predicate(c) {
+                break;
+            }
+            word.push(c);
+            self.bump();
+        }
+        word
+    }
+
+    /// Content up to the closing quote, a doubled quote stands for the quote itself
+    fn quoted(&mut self, quote: char, start: Position) -> Result<String, LexerError> {
+        let mut content = String::new();
+        loop {
+            match self.bump() {
+                Some(c) if c == quote => {
+                    if !self.bump_if(quote) {
+                        return Ok(content);
+                    }
+                    content.push(quote);
+                }
+                Some(c) => content.push(c),
+                None => return Err(LexerError::UnterminatedQuote(quote, start)),
+            }
+        }
+    }
+
+    fn hex(digits: &str, position: Position) -> Result<Vec<u8>, LexerError> {
+        if !digits.len().is_multiple_of(2) || !digits.is_ascii() {
+            return Err(LexerError::InvalidBytes(digits.to_string(), position));
+        }
+        (0..digits.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&digits[i..i + 2], 16))
+            .collect::<Result<Vec<u8>, _>>()
+            .map_err(|_| LexerError::InvalidBytes(digits.to_string(), position))
+    }
+
+    fn skip_whitespace_and_comments(&mut self) -> Result<(), LexerError> {
+        loop {
+            match self.chars.peek() {
+                Some(c) if c.is_whitespace() => {
+                    self.bump();
+                }
+                Some('-') => {
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    if lookahead.peek() != Some(&'-') {
+                        return Ok(());
+                    }
+                    while !matches!(self.bump(), Some('\n') | None) {}
+                }
+                Some('/') => {
+                    let mut lookahead = self.chars.clone();
+                    lookahead.next();
+                    if lookahead.peek() != Some(&'*') {
+                        return Ok(());
+                    }
+                    let start = self.position;
+                    self.bump();
+                    self.bump();
+                    loop {
+                        match self.bump() {
+                            Some('*') if self.bump_if('/') => break,
+                            Some(_) => {}
+                            None => return Err(LexerError::UnterminatedComment(start)),
+                        }
+                    }
+                }
+                _ => return Ok(()),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LexerError {
+    UnexpectedCharacter(char, Position),
+    UnterminatedQuote(char, Position),
+    UnterminatedComment(Position),
+    InvalidNumber(String, Position),
+    InvalidBytes(String, Position),
+}
+
+impl LexerError {
+    pub fn position(&self) -> Position {
+        match self {
+            LexerError::UnexpectedCharacter(_, position)
+            | LexerError::UnterminatedQuote(_, position)
+            | LexerError::UnterminatedComment(position)
+            | LexerError::InvalidNumber(_, position)
+            | LexerError::InvalidBytes(_, position) => *position,
+        }
+    }
+}
+
+impl Display for LexerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexerError::UnexpectedCharacter(c, position) => {
+                write!(f, "Lexer: unexpected character {:?} at {}", c, position)
+            }
+            LexerError::UnterminatedQuote(quote, position) => {
+                write!(
+                    f,
+                    "Lexer: missing closing {} for {} at {}",
+                    quote, quote, position
+                )
+            }
+            LexerError::UnterminatedComment(position) => {
+                write!(f, "Lexer: unterminated comment at {}", position)
+            }
+            LexerError::InvalidNumber(number, position) => {
+                write!(f, "Lexer: invalid number {} at {}", number, position)
+            }
+            LexerError::InvalidBytes(bytes, position) => {
+                write!(f, "Lexer: invalid hex bytes '{}' at {}", bytes, position)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_processing::lexer::{Keyword, Lexer, LexerError, Position, TokenKind};
+
+    fn kinds(sql: &str) -> Vec<TokenKind> {
+        Lexer::new(sql)
+            .tokens()
+            .unwrap()
+            .into_iter()
+            .map(|token| token.kind)
+            .collect()
+    }
+
+    #[test]
+    fn test_lexer_tokens() {
+        assert_eq!(
+            kinds("select Name, \"Order\" from t -- comment\n where /* a\n b */ x <> 'it''s' || X'0aFF' >= -42;"),
+            vec![
+                TokenKind::Keyword(Keyword::Select),
+                TokenKind::Identifier("name".to_string()),
+                TokenKind::Comma,
+                TokenKind::Identifier("Order".to_string()),
+                TokenKind::Keyword(Keyword::From),
+                TokenKind::Identifier("t".to_string()),
+                TokenKind::Keyword(Keyword::Where),
+                TokenKind::Identifier("x".to_string()),
+                TokenKind::NotEqual,
+                TokenKind::String("it's".to_string()),
+                TokenKind::Concat,
+                TokenKind::Bytes(vec![0x0a, 0xff]),
+                TokenKind::GreaterEqual,
+                TokenKind::Minus,
+                TokenKind::Integer(42),
+                TokenKind::Semicolon,
+                TokenKind::EndOfInput,
+            ]
+        );
+        let tokens = Lexer::new("select\n  x").tokens().unwrap();
+        assert_eq!(tokens[1].position, Position { line: 2, column: 3 });
+    }
+
+    #[test]
+    fn test_lexer_errors() {
+        assert_eq!(
+            Lexer::new("select 'abc").tokens(),
+            Err(LexerError::UnterminatedQuote(
+                '\'',
+                Position { line: 1, column: 8 }
+            ))
+        );
+        assert_eq!(
+            Lexer::new("select\n a # b").tokens(),
+            Err(LexerError::UnexpectedCharacter(
+                '#',
+                Position { line: 2, column: 4 }
+            ))
+        );
+        assert_eq!(
+            Lexer::new("select 12ab").tokens(),
+            Err(LexerError::InvalidNumber(
+                "12ab".to_string(),
+                Position { line: 1, column: 8 }
+            ))
+        );
+        assert!(matches!(
+            Lexer::new("select X'abc'").tokens(),
+            Err(LexerError::InvalidBytes(_, _))
+        ));
+    }
+}
@@ -0,0 +1,972 @@
+use crate::datatypes::SortOrder;
+use crate::metadata_management::catalog::IndexType;
+use crate::query_processing::ast::{
+    Analyze, BinaryOperator, ColumnDefinition, CreateIndex, CreateTable, Delete, DropTable,
+    Explain, Expression, Insert, Join, JoinKind, OrderBy, Select, SelectItem, Statement,
+    TableReference, UnaryOperator, Update,
+};
+use crate::query_processing::lexer::{Keyword, Lexer, LexerError, Position, Token, TokenKind};
+use crate::record_management::schema::ColumnType;
+use crate::record_management::value::Value;
+use std::fmt::{Display, Formatter};
+
+/// Parses the statements of the SQL text, separated by semicolons
+pub fn parse(sql: &str) -> Result<Vec<Statement>, ParserError> {
+    Parser::new(sql)?.statements()
+}
+
+/// Parses SQL text consisting of exactly one statement, optionally ended by a semicolon
+pub fn parse_statement(sql: &str) -> Result<Statement, ParserError> {
+    let mut parser = Parser::new(sql)?;
+    let statement = parser.statement()?;
+    parser.bump_if(&TokenKind::Semicolon);
+    parser.expect(&TokenKind::EndOfInput)?;
+    Ok(statement)
+}
+
+/// Recursive descent parser for the supported SQL subset. Operator precedence from lowest to
+/// highest: OR, AND, NOT, comparisons and IS [NOT] NULL, + - ||, * / %, unary minus.
+pub struct Parser {
+    tokens: Vec<Token>,
+    index: usize,
+}
+
+impl Parser {
+    pub fn new(sql: &str) -> Result<Self, ParserError> {
+        Ok(Self {
+            tokens: Lexer::new(sql).tokens().map_err(ParserError::LexerError)?,
+            index: 0,
+        })
+    }
+
+    pub fn statements(&mut self) -> Result<Vec<Statement>, ParserError> {
+        let mut statements = vec![];
+        loop {
+            while self.bump_if(&TokenKind::Semicolon) {}
+            if self.peek().kind == TokenKind::EndOfInput {
+                return Ok(statements);
+            }
+            statements.push(self.statement()?);
+            if self.peek().kind != TokenKind::EndOfInput {
+                self.expect(&TokenKind::Semicolon)?;
+            }
+        }
+    }
+
+    pub fn statement(&mut self) -> Result<Statement, ParserError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Keyword(Keyword::Create) => {
+                if self.bump_if_keyword(Keyword::Index) {
+                    self.create_index().map(Statement::CreateIndex)
+                } else {
+                    self.expect_keyword(Keyword::Table)?;
+                    self.create_table().map(Statement::CreateTable)
+                }
+            }
+            TokenKind::Keyword(Keyword::Drop) => {
+                self.expect_keyword(Keyword::Table)?;
+                let if_exists = self.bump_if_keyword(Keyword::If);
+                if if_exists {
+                    self.expect_keyword(Keyword::Exists)?;
+                }
+                Ok(Statement::DropTable(DropTable {
+                    name: self.identifier()?,
+                    if_exists,
+                }))
+            }
+            TokenKind::Keyword(Keyword::Insert) => self.insert().map(Statement::Insert),
+            TokenKind::Keyword(Keyword::Select) => self
+                .select()
+                .map(|select| Statement::Select(Box::new(select))),
+            TokenKind::Keyword(Keyword::Update) => self.update().map(Statement::Update),
+            TokenKind::Keyword(Keyword::Delete) => self.delete().map(Statement::Delete),
+            TokenKind::Keyword(Keyword::Analyze) => {
+                let table_name = match self.peek().kind {
+                    TokenKind::Identifier(_) => Some(self.identifier()?),
+                    _ => None,
+                };
+                Ok(Statement::Analyze(Analyze { table_name }))
+            }
+            TokenKind::Keyword(Keyword::Explain) => {
+                let analyze = self.bump_if_keyword(Keyword::Analyze);
+                self.expect_keyword(Keyword::Select)?;
+                Ok(Statement::Explain(Explain {
+                    analyze,
+                    select: Box::new(self.select()?),
+                }))
+            }
+            TokenKind::Keyword(Keyword::Begin) => {
+                self.bump_if_keyword(Keyword::Transaction);
+                Ok(Statement::Begin)
+            }
+            TokenKind::Keyword(Keyword::Commit) => {
+                self.bump_if_keyword(Keyword::Transaction);
+                Ok(Statement::Commit)
+            }
+            TokenKind::Keyword(Keyword::Rollback) => {
+                self.bump_if_keyword(Keyword::Transaction);
+                Ok(Statement::Rollback)
+            }
+            kind => Err(ParserError::UnexpectedToken {
+                expected: "statement".to_string(),
+                found: kind,
+                position: token.position,
+            }),
+        }
+    }
+
+    fn create_table(&mut self) -> Result<CreateTable, ParserError> {
+        let if_not_exists = self.bump_if_keyword(Keyword::If);
+        if if_not_exists {
+            self.expect_keyword(Keyword::Not)?;
+            self.expect_keyword(Keyword::Exists)?;
+        }
+        let name = self.identifier()?;
+        self.expect(&TokenKind::LeftParenthesis)?;
+        let columns = self.comma_separated(|parser| {
+            let name = parser.identifier()?;
+            let column_type = parser.column_type()?;
+            let nullable = if parser.bump_if_keyword(Keyword::Not) {
+                parser.expect_keyword(Keyword::Null)?;
+                false
+            } else {
+                parser.bump_if_keyword(Keyword::Null);
+                true
+            };
+            Ok(ColumnDefinition {
+                name,
+                column_type,
+                nullable,
+            })
+        })?;
+        self.expect(&TokenKind::RightParenthesis)?;
+        Ok(CreateTable {
+            name,
+            if_not_exists,
+            columns,
+        })
+    }
+
+    /// Type name, either one of the [ColumnType] names or a common SQL alias, optionally followed
+    /// by a length in parentheses which is ignored
+    fn column_type(&mut self) -> Result<ColumnType, ParserError> {
+        let position = self.peek().position;
+        let name = self.identifier()?;
+        let column_type = match name.to_lowercase().as_str() {
+            "tinyint" => Some(ColumnType::TinyInteger),
+            "smallint" | "int2" => Some(ColumnType::SmallInteger),
+            "int" | "int4" => Some(ColumnType::Integer),
+            "bigint" | "int8" => Some(ColumnType::BigInteger),
+            "text" | "char" | "string" => Some(ColumnType::Varchar),
+            "blob" | "bytea" => Some(ColumnType::Varbinary),
+            _ => ColumnType::ALL
+                .into_iter()
+                .find(|column_type| column_type.name().eq_ignore_ascii_case(&name)),
+        }
+        .ok_or(ParserError::UnknownColumnType(name, position))?;
+        if self.bump_if(&TokenKind::LeftParenthesis) {
+            self.unsigned_integer()?;
+            self.expect(&TokenKind::RightParenthesis)?;
+        }
+        Ok(column_type)
+    }
+
+    fn create_index(&mut self) -> Result<CreateIndex, ParserError> {
+        let name = self.identifier()?;
+        self.expect_keyword(Keyword::On)?;
+        let table_name = self.identifier()?;
+        self.expect(&TokenKind::LeftParenthesis)?;
+        let column_name = self.identifier()?;
+        self.expect(&TokenKind::RightParenthesis)?;
+        let index_type = if self.bump_if_keyword(Keyword::Using) {
+            let position = self.peek().position;
+            let type_name = self.identifier()?;
+            [IndexType::BTree, IndexType::Hash]
+                .into_iter()
+                .find(|index_type| index_type.name().eq_ignore_ascii_case(&type_name))
+                .ok_or(ParserError::UnknownIndexType(type_name, position))?
+        } else {
+            IndexType::BTree
+        };
+        Ok(CreateIndex {
+            name,
+            table_name,
+            column_name,
+            index_type,
+        })
+    }
+
+    fn insert(&mut self) -> Result<Insert, ParserError> {
+        self.expect_keyword(Keyword::Into)?;
+        let table_name = self.identifier()?;
+        let columns = if self.bump_if(&TokenKind::LeftParenthesis) {
+            let columns = self.comma_separated(Self::identifier)?;
+            self.expect(&TokenKind::RightParenthesis)?;
+            Some(columns)
+        } else {
+            None
+        };
+        self.expect_keyword(Keyword::Values)?;
+        let rows = self.comma_separated(|parser| {
+            parser.expect(&TokenKind::LeftParenthesis)?;
+            let values = parser.comma_separated(Self::expression)?;
+            parser.expect(&TokenKind::RightParenthesis)?;
+            Ok(values)
+        })?;
+        Ok(Insert {
+            table_name,
+            columns,
+            rows,
+        })
+    }
+
+    fn select(&mut self) -> Result<Select, ParserError> {
+        let distinct = self.bump_if_keyword(Keyword::Distinct);
+        let projection = self.comma_separated(Self::select_item)?;
+        let mut from = None;
+        let mut joins = vec![];
+        if self.bump_if_keyword(Keyword::From) {
+            from = Some(self.table_reference()?);
+            while let Some(join) = self.join()? {
+                joins.push(join);
+            }
+        }
+        let where_clause = self.optional_where()?;
+        let mut group_by = vec![];
+        if self.bump_if_keyword(Keyword::Group) {
+            self.expect_keyword(Keyword::By)?;
+            group_by = self.comma_separated(Self::expression)?;
+        }
+        let having = if self.bump_if_keyword(Keyword::Having) {
+            Some(self.expression()?)
+        } else {
+            None
+        };
+        let mut order_by = vec![];
+        if self.bump_if_keyword(Keyword::Order) {
+            self.expect_keyword(Keyword::By)?;
+            order_by = self.comma_separated(|parser| {
+                let expression = parser.expression()?;
+                let order = if parser.bump_if_keyword(Keyword::Desc) {
+                    SortOrder::Descending
+                } else {
+                    parser.bump_if_keyword(Keyword::Asc);
+                    SortOrder::Ascending
+                };
+                Ok(OrderBy { expression, order })
+            })?;
+        }
+        let limit = if self.bump_if_keyword(Keyword::Limit) {
+            Some(self.unsigned_integer()?)
+        } else {
+            None
+        };
+        let offset = if self.bump_if_keyword(Keyword::Offset) {
+            Some(self.unsigned_integer()?)
+        } else {
+            None
+        };
+        Ok(Select {
+            distinct,
+            projection,
+            from,
+            joins,
+            where_clause,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        })
+    }
+
+    fn select_item(&mut self) -> Result<SelectItem, ParserError> {
+        if self.bump_if(&TokenKind::Star) {
+            return Ok(SelectItem::Wildcard);
+        }
+        if let (TokenKind::Identifier(table), TokenKind::Dot, TokenKind::Star) = (
+            &self.peek().kind,
+            &self.peek_at(1).kind,
+            &self.peek_at(2).kind,
+        ) {
+            let table = table.clone();
+            self.index += 3;
+            return Ok(SelectItem::QualifiedWildcard(table));
+        }
+        let expression = self.expression()?;
+        let alias = self.optional_alias()?;
+        Ok(SelectItem::Expression { expression, alias })
+    }
+
+    fn optional_alias(&mut self) -> Result<Option<String>, ParserError> {
+        if self.bump_if_keyword(Keyword::As) {
+            return self.identifier().map(Some);
+        }
+        if let TokenKind::Identifier(alias) = &self.peek().kind {
+            let alias = alias.clone();
+            self.index += 1;
+            return Ok(Some(alias));
+        }
+        Ok(None)
+    }
+
+    fn table_reference(&mut self) -> Result<TableReference, ParserError> {
+        Ok(TableReference {
+            name: self.identifier()?,
+            alias: self.optional_alias()?,
+        })
+    }
+
+    fn join(&mut self) -> Result<Option<Join>, ParserError> {
+        let kind = if self.bump_if(&TokenKind::Comma) {
+            return Ok(Some(Join {
+                kind: JoinKind::Cross,
+                table: self.table_reference()?,
+                on: None,
+            }));
+        } else if self.bump_if_keyword(Keyword::Cross) {
+            JoinKind::Cross
+        } else if self.bump_if_keyword(Keyword::Left) {
+            self.bump_if_keyword(Keyword::Outer);
+            JoinKind::Left
+        } else if self.bump_if_keyword(Keyword::Inner) || self.peek_keyword(Keyword::Join) {
+            JoinKind::Inner
+        } else {
+            return Ok(None);
+        };
+        self.expect_keyword(Keyword::Join)?;
+        let table = self.table_reference()?;
+        let on = if kind == JoinKind::Cross {
+            None
+        } else {
+            self.expect_keyword(Keyword::On)?;
+            Some(self.expression()?)
+        };
+        Ok(Some(Join { kind, table, on }))
+    }
+
+    fn update(&mut self) -> Result<Update, ParserError> {
+        let table_name = self.identifier()?;
+        self.expect_keyword(Keyword::Set)?;
+        let assignments = self.comma_separated(|parser| {
+            let column = parser.identifier()?;
+            parser.expect(&TokenKind::Equal)?;
+            Ok((column, parser.expression()?))
+        })?;
+        Ok(Update {
+            table_name,
+            assignments,
+            where_clause: self.optional_where()?,
+        })
+    }
+
+    fn delete(&mut self) -> Result<Delete, ParserError> {
+        self.expect_keyword(Keyword::From)?;
+        Ok(Delete {
+            table_name: self.identifier()?,
+            where_clause: self.optional_where()?,
+        })
+    }
+
+    fn optional_where(&mut self) -> Result<Option<Expression>, ParserError> {
+        if self.bump_if_keyword(Keyword::Where) {
+            self.expression().map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+
+    pub fn expression(&mut self) -> Result<Expression, ParserError> {
+        let mut left = self.and()?;
+        while self.bump_if_keyword(Keyword::Or) {
+            left = Expression::binary(left, BinaryOperator::Or, self.and()?);
+        }
+        Ok(left)
+    }
+
+    fn and(&mut self) -> Result<Expression, ParserError> {
+        let mut left = self.not()?;
+        while self.bump_if_keyword(Keyword::And) {
+            left = Expression::binary(left, BinaryOperator::And, self.not()?);
+        }
+        Ok(left)
+    }
+
+    fn not(&mut self) -> Result<Expression, ParserError> {
+        if self.bump_if_keyword(Keyword::Not) {
+            return Ok(Expression::Unary {
+                operator: UnaryOperator::Not,
+                operand: Box::new(self.not()?),
+            });
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Expression, ParserError> {
+        let left = self.additive()?;
+        if self.bump_if_keyword(Keyword::Is) {
+            let negated = self.bump_if_keyword(Keyword::Not);
+            self.expect_keyword(Keyword::Null)?;
+            return Ok(Expression::IsNull {
+                expression: Box::new(left),
+                negated,
+            });
+        }
+        let operator = match self.peek().kind {
+            TokenKind::Equal => BinaryOperator::Equal,
+            TokenKind::NotEqual => BinaryOperator::NotEqual,
+            TokenKind::Less => BinaryOperator::Less,
+            TokenKind::LessEqual => BinaryOperator::LessEqual,
+            TokenKind::Greater => BinaryOperator::Greater,
+            TokenKind::GreaterEqual => BinaryOperator::GreaterEqual,
+            _ => return Ok(left),
+        };
+        self.index += 1;
+        Ok(Expression::binary(left, operator, self.additive()?))
+    }
+
+    fn additive(&mut self) -> Result<Expression, ParserError> {
+        let mut left = self.multiplicative()?;
+        loop {
+            let operator = match self.peek().kind {
+                TokenKind::Plus => BinaryOperator::Add,
+                TokenKind::Minus => BinaryOperator::Subtract,
+                TokenKind::Concat => BinaryOperator::Concat,
+                _ => return Ok(left),
+            };
+            self.index += 1;
+            left = Expression::binary(left, operator, self.multiplicative()?);
+        }
+    }
+
+    fn multiplicative(&mut self) -> Result<Expression, ParserError> {
+        let mut left = self.unary()?;
+        loop {
+            let operator = match self.peek().kind {
+                TokenKind::Star => BinaryOperator::Multiply,
+                TokenKind::Slash => BinaryOperator::Divide,
+                TokenKind::Percent => BinaryOperator::Modulo,
+                _ => return Ok(left),
+            };
+            self.index += 1;
+            left = Expression::binary(left, operator, self.unary()?);
+        }
+    }
+
+    /// Unary minus, applied directly to integer literals
+    fn unary(&mut self) -> Result<Expression, ParserError> {
+        if self.bump_if(&TokenKind::Minus) {
+            return Ok(match self.unary()? {
+                Expression::Literal(Value::Integer(value)) => {
+                    Expression::Literal(Value::Integer(-value))
+                }
+                operand => Expression::Unary {
+                    operator: UnaryOperator::Minus,
+                    operand: Box::new(operand),
+                },
+            });
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expression, ParserError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Integer(value) => Ok(Expression::Literal(Value::Integer(value))),
+            TokenKind::String(value) => Ok(Expression::Literal(Value::Text(value))),
+            TokenKind::Bytes(value) => Ok(Expression::Literal(Value::Bytes(value))),
+            TokenKind::Keyword(Keyword::Null) => Ok(Expression::Literal(Value::Null)),
+            TokenKind::LeftParenthesis => {
+                let expression = self.expression()?;
+                self.expect(&TokenKind::RightParenthesis)?;
+                Ok(expression)
+            }
+            TokenKind::Identifier(name) => {
+                if self.bump_if(&TokenKind::LeftParenthesis) {
+                    self.function(name)
+                } else if self.bump_if(&TokenKind::Dot) {
+                    Ok(Expression::Column {
+                        table: Some(name),
+                        name: self.identifier()?,
+                    })
+                } else {
+                    Ok(Expression::Column { table: None, name })
+                }
+            }
+            kind => Err(ParserError::UnexpectedToken {
+                expected: "expression".to_string(),
+                found: kind,
+                position: token.position,
+            }),
+        }
+    }
+
+    fn function(&mut self, name: String) -> Result<Expression, ParserError> {
+        let star = self.bump_if(&TokenKind::Star);
+        let arguments = if star || self.peek().kind == TokenKind::RightParenthesis {
+            vec![]
+        } else {
+            self.comma_separated(Self::expression)?
+        };
+        self.expect(&TokenKind::RightParenthesis)?;
+        Ok(Expression::Function {
+            name,
+            arguments,
+            star,
+        })
+    }
+
+    fn comma_separated<T>(
+        &mut self,
+        mut item: impl FnMut(&mut Self) -> Result<T, ParserError>,
+    ) -> Result<Vec<T>, ParserError> {
+        let mut items = vec![item(self)?];
+        while self.bump_if(&TokenKind::Comma) {
+            items.push(item(self)?);
+        }
+        Ok(items)
+    }
+
+    fn identifier(&mut self) -> Result<String, ParserError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Identifier(name) => Ok(name),
+            kind => Err(ParserError::UnexpectedToken {
+                expected: "identifier".to_string(),
+                found: kind,
+                position: token.position,
+            }),
+        }
+    }
+
+    fn unsigned_integer(&mut self) -> Result<u64, ParserError> {
+        let token = self.next();
+        match token.kind {
+            TokenKind::Integer(value) => u64::try_from(value)
+                .map_err(|_| ParserError::NumberOutOfRange(value, token.position)),
+            kind => Err(ParserError::UnexpectedToken {
+                expected: "integer".to_string(),
+                found: kind,
+                position: token.position,
+            }),
+        }
+    }
+
+    fn peek(&self) -> &Token {
+        self.peek_at(0)
+    }
+
+    /// The last token is always the end of input, so peeking beyond it returns that
+    fn peek_at(&self, offset: usize) -> &Token {
+        &self.tokens[(self.index + offset).min(self.tokens.len() - 1)]
+    }
+
+    fn next(&mut self) -> Token {
+        let token = self.peek().clone();
+        if self.index < self.tokens.len() - 1 {
+            self.index += 1;
+        }
+        token
+    }
+
+    fn peek_keyword(&self, keyword: Keyword) -> bool {
+        self.peek().kind == TokenKind::Keyword(keyword)
+    }
+
+    fn bump_if(&mut self, kind: &TokenKind) -> bool {
+        if self.peek().kind == *kind {
+            self.next();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn bump_if_keyword(&mut self, keyword: Keyword) -> bool {
+        self.bump_if(&TokenKind::Keyword(keyword))
+    }
+
+    fn expect(&mut self, kind: &TokenKind) -> Result<(), ParserError> {
+        let token = self.next();
+        if token.kind == *kind {
+            Ok(())
+        } else {
+            Err(ParserError::UnexpectedToken {
+                expected: kind.to_string(),
+                found: token.kind,
+                position: token.position,
+            })
+        }
+    }
+
+    fn expect_keyword(&mut self, keyword: Keyword) -> Result<(), ParserError> {
+        self.expect(&TokenKind::Keyword(keyword))
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ParserError {
+    LexerError(LexerError),
+    UnexpectedToken {
+        expected: String,
+        found: TokenKind,
+        position: Position,
+    },
+    UnknownColumnType(String, Position),
+    UnknownIndexType(String, Position),
+    NumberOutOfRange(i128, Position),
+}
+
+impl ParserError {
+    pub fn position(&self) -> Position {
+        match self {
+            ParserError::LexerError(e) => e.position(),
+            ParserError::UnexpectedToken { position, .. }
+            | ParserError::UnknownColumnType(_, position)
+            | ParserError::UnknownIndexType(_, position)
+            | ParserError::NumberOutOfRange(_, position) => *position,
+        }
+    }
+}
+
+impl Display for ParserError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParserError::LexerError(e) => write!(f, "Parser {}", e),
+            ParserError::UnexpectedToken {
+                expected,
+                found,
+                position,
+            } => write!(
+                f,
+                "Parser: expected {}, found {} at {}",
+                expected, found, position
+            ),
+            ParserError::UnknownColumnType(name, position) => {
+                write!(f, "Parser: unknown column type {} at {}", name, position)
+            }
+            ParserError::UnknownIndexType(name, position) => {
+                write!(f, "Parser: unknown index type {} at {}", name, position)
+            }
+            ParserError::NumberOutOfRange(value, position) => {
+                write!(f, "Parser: number {} out of range at {}", value, position)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datatypes::SortOrder;
+    use crate::metadata_management::catalog::IndexType;
+    use crate::query_processing::ast::{
+        Analyze, BinaryOperator, ColumnDefinition, CreateIndex, CreateTable, Delete, DropTable,
+        Explain, Expression, Insert, Join, JoinKind, OrderBy, Select, SelectItem, Statement,
+        TableReference, Update,
+    };
+    use crate::query_processing::lexer::{Keyword, Position, TokenKind};
+    use crate::query_processing::parser::{parse, parse_statement, ParserError};
+    use crate::record_management::schema::ColumnType;
+    use crate::record_management::value::Value;
+
+    fn literal(value: impl Into<Value>) -> Expression {
+        Expression::Literal(value.into())
+    }
+
+    #[test]
+    fn test_parse_ddl_and_dml() {
+        assert_eq!(
+            parse(
+                "CREATE TABLE IF NOT EXISTS persons (id int NOT NULL, name VARCHAR(40), data Varbinary);
+                 CREATE INDEX persons_name ON persons (name) USING hash;
+                 INSERT INTO persons (id, name) VALUES (1, 'Ada'), (-2, NULL);
+                 UPDATE persons SET name = name || '!', id = id + 1 WHERE id = 1;
+                 DELETE FROM persons;
+                 DROP TABLE IF EXISTS persons;
+                 ANALYZE persons; ANALYZE;
+                 BEGIN TRANSACTION; COMMIT; ROLLBACK;"
+            )
+            .unwrap(),
+            vec![
+                Statement::CreateTable(CreateTable {
+                    name: "persons".to_string(),
+                    if_not_exists: true,
+                    columns: vec![
+                        ColumnDefinition {
+                            name: "id".to_string(),
+                            column_type: ColumnType::Integer,
+                            nullable: false,
+                        },
+                        ColumnDefinition {
+                            name: "name".to_string(),
+                            column_type: ColumnType::Varchar,
+                            nullable: true,
+                        },
+                        ColumnDefinition {
+                            name: "data".to_string(),
+                            column_type: ColumnType::Varbinary,
+                            nullable: true,
+                        },
+                    ],
+                }),
+                Statement::CreateIndex(CreateIndex {
+                    name: "persons_name".to_string(),
+                    table_name: "persons".to_string(),
+                    column_name: "name".to_string(),
+                    index_type: IndexType::Hash,
+                }),
+                Statement::Insert(Insert {
+                    table_name: "persons".to_string(),
+                    columns: Some(vec!["id".to_string(), "name".to_string()]),
+                    rows: vec![
+                        vec![literal(1i64), literal("Ada")],
+                        vec![literal(-2i64), Expression::Literal(Value::Null)],
+                    ],
+                }),
+                Statement::Update(Update {
+                    table_name: "persons".to_string(),
+                    assignments: vec![
+                        (
+                            "name".to_string(),
+                            Expression::binary(
+                                Expression::column("name"),
+                                BinaryOperator::Concat,
+                                literal("!")
+                            )
+                        ),
+                        (
+                            "id".to_string(),
+                            Expression::binary(
+                                Expression::column("id"),
+                                BinaryOperator::Add,
+                                literal(1i64)
+                            )
+                        ),
+                    ],
+                    where_clause: Some(Expression::binary(
+                        Expression::column("id"),
+                        BinaryOperator::Equal,
+                        literal(1i64)
+                    )),
+                }),
+                Statement::Delete(Delete {
+                    table_name: "persons".to_string(),
+                    where_clause: None,
+                }),
+                Statement::DropTable(DropTable {
+                    name: "persons".to_string(),
+                    if_exists: true,
+                }),
+                Statement::Analyze(Analyze {
+                    table_name: Some("persons".to_string())
+                }),
+                Statement::Analyze(Analyze { table_name: None }),
+                Statement::Begin,
+                Statement::Commit,
+                Statement::Rollback,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_select() {
+        assert_eq!(
+            parse_statement(
+                "SELECT p.city, count(*) AS n FROM persons p JOIN cities c ON p.city = c.name \
+                 LEFT OUTER JOIN countries ON c.country = countries.name, planets \
+                 WHERE p.age >= 18 GROUP BY p.city HAVING count(*) > 1 \
+                 ORDER BY n DESC, p.city LIMIT 10 OFFSET 5;"
+            )
+            .unwrap(),
+            Statement::Select(Box::new(Select {
+                distinct: false,
+                projection: vec![
+                    SelectItem::Expression {
+                        expression: Expression::Column {
+                            table: Some("p".to_string()),
+                            name: "city".to_string()
+                        },
+                        alias: None,
+                    },
+                    SelectItem::Expression {
+                        expression: Expression::Function {
+                            name: "count".to_string(),
+                            arguments: vec![],
+                            star: true,
+                        },
+                        alias: Some("n".to_string()),
+                    },
+                ],
+                from: Some(TableReference {
+                    name: "persons".to_string(),
+                    alias: Some("p".to_string()),
+                }),
+                joins: vec![
+                    Join {
+                        kind: JoinKind::Inner,
+                        table: TableReference {
+                            name: "cities".to_string(),
+                            alias: Some("c".to_string()),
+                        },
+                        on: Some(Expression::binary(
+                            Expression::Column {
+                                table: Some("p".to_string()),
+                                name: "city".to_string()
+                            },
+                            BinaryOperator::Equal,
+                            Expression::Column {
+                                table: Some("c".to_string()),
+                                name: "name".to_string()
+                            },
+                        )),
+                    },
+                    Join {
+                        kind: JoinKind::Left,
+                        table: TableReference {
+                            name: "countries".to_string(),
+                            alias: None,
+                        },
+                        on: Some(Expression::binary(
+                            Expression::Column {
+                                table: Some("c".to_string()),
+                                name: "country".to_string()
+                            },
+                            BinaryOperator::Equal,
+                            Expression::Column {
+                                table: Some("countries".to_string()),
+                                name: "name".to_string()
+                            },
+                        )),
+                    },
+                    Join {
+                        kind: JoinKind::Cross,
+                        table: TableReference {
+                            name: "planets".to_string(),
+                            alias: None,
+                        },
+                        on: None,
+                    },
+                ],
+                where_clause: Some(Expression::binary(
+                    Expression::Column {
+                        table: Some("p".to_string()),
+                        name: "age".to_string()
+                    },
+                    BinaryOperator::GreaterEqual,
+                    literal(18i64)
+                )),
+                group_by: vec![Expression::Column {
+                    table: Some("p".to_string()),
+                    name: "city".to_string()
+                }],
+                having: Some(Expression::binary(
+                    Expression::Function {
+                        name: "count".to_string(),
+                        arguments: vec![],
+                        star: true,
+                    },
+                    BinaryOperator::Greater,
+                    literal(1i64)
+                )),
+                order_by: vec![
+                    OrderBy {
+                        expression: Expression::column("n"),
+                        order: SortOrder::Descending,
+                    },
+                    OrderBy {
+                        expression: Expression::Column {
+                            table: Some("p".to_string()),
+                            name: "city".to_string()
+                        },
+                        order: SortOrder::Ascending,
+                    },
+                ],
+                limit: Some(10),
+                offset: Some(5),
+            }))
+        );
+    }
+
+    #[test]
+    fn test_parse_explain() {
+        let Statement::Select(select) = parse_statement("select a from t where a > 1").unwrap()
+        else {
+            panic!("select expected")
+        };
+        assert_eq!(
+            parse(
+                "EXPLAIN SELECT a FROM t WHERE a > 1; explain analyze select a from t where a > 1"
+            )
+            .unwrap(),
+            vec![
+                Statement::Explain(Explain {
+                    analyze: false,
+                    select: select.clone(),
+                }),
+                Statement::Explain(Explain {
+                    analyze: true,
+                    select,
+                }),
+            ]
+        );
+        assert!(matches!(
+            parse_statement("EXPLAIN DELETE FROM t"),
+            Err(ParserError::UnexpectedToken { .. })
+        ));
+    }
+
+    #[test]
+    fn test_parse_expression_precedence() {
+        let Statement::Select(select) =
+            parse_statement("select * from t where not a = 1 or b + 2 * -c < 3 and d is not null")
+                .unwrap()
+        else {
+            panic!("select expected")
+        };
+        assert_eq!(select.projection, vec![SelectItem::Wildcard]);
+        assert_eq!(
+            select.where_clause.unwrap().to_string(),
+            "(NOT (a = 1) OR (((b + (2 * -c)) < 3) AND d IS NOT NULL))"
+        );
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        assert_eq!(
+            parse_statement("SELECT a FROM\n  WHERE a = 1"),
+            Err(ParserError::UnexpectedToken {
+                expected: "identifier".to_string(),
+                found: TokenKind::Keyword(Keyword::Where),
+                position: Position { line: 2, column: 3 },
+            })
+        );
+        assert_eq!(
+            parse_statement("CREATE TABLE t (a float)"),
+            Err(ParserError::UnknownColumnType(
+                "float".to_string(),
+                Position {
+                    line: 1,
+                    column: 19
+                }
+            ))
+        );
+        assert_eq!(
+            parse_statement("INSERT INTO t VALUES (1, 2")
+                .unwrap_err()
+                .position(),
+            Position {
+                line: 1,
+                column: 27
+            }
+        );
+        assert_eq!(
+            parse("SELECT 1 SELECT 2").unwrap_err().to_string(),
+            "Parser: expected ';', found SELECT at line 1, column 10"
+        );
+        assert!(matches!(
+            parse_statement("SELECT 'abc"),
+            Err(ParserError::LexerError(_))
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::datatypes::SortOrder;
+use crate::metadata_management::catalog::{IndexInfo, IndexType, TableInfo};
+use crate::query_processing::ast::JoinKind;
+use crate::query_processing::expression::BoundExpression;
+use crate::record_management::schema::ColumnType;
+use crate::record_management::value::Value;
+use std::ops::Bound;
+
+/// Output column of a plan node. Columns of tables keep the name the table is referenced with in
+/// the query, computed columns have no table.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct PlanColumn {
+    pub table: Option<String>,
+    pub name: String,
+    pub column_type: ColumnType,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum IndexLookup {
+    Equal(Value),
+    Range(Bound<Value>, Bound<Value>),
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum AggregateFunction {
+    Count,
+    Sum,
+    Min,
+    Max,
+    Avg,
+}
+
+impl AggregateFunction {
+    pub fn from_name(name: &str) -> Option<AggregateFunction> {
+        match name.to_lowercase().as_str() {
+            "count" => Some(AggregateFunction::Count),
+            "sum" => Some(AggregateFunction::Sum),
+            "min" => Some(AggregateFunction::Min),
+            "max" => Some(AggregateFunction::Max),
+            "avg" => Some(AggregateFunction::Avg),
+            _ => None,
+        }
+    }
+}
+
+/// Aggregate function applied to the argument, None for `count(*)`
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Aggregate {
+    pub function: AggregateFunction,
+    pub argument: Option<BoundExpression>,
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum PlanNode {
+    /// Constant rows, e.g. the single empty row of a select without FROM
+    Values(Vec<Vec<Value>>),
+    TableScan {
+        table: TableInfo,
+    },
+    IndexScan {
+        table: TableInfo,
+        index: IndexInfo,
+        lookup: IndexLookup,
+    },
+    Filter {
+        input: Box<Plan>,
+        predicate: BoundExpression,
+    },
+    Project {
+        input: Box<Plan>,
+        expressions: Vec<BoundExpression>,
+    },
+    /// Compares every left with every right tuple, the condition refers to the joined tuple
+    NestedLoopJoin {
+        left: Box<Plan>,
+        right: Box<Plan>,
+        kind: JoinKind,
+        condition: Option<BoundExpression>,
+    },
+    /// Builds a hash table of the right input by its keys and probes it with the left tuples.
+    /// The keys refer to the tuples of their input, the residual condition to the joined tuple.
+    HashJoin {
+        left: Box<Plan>,
+        right: Box<Plan>,
+        kind: JoinKind,
+        left_keys: Vec<BoundExpression>,
+        right_keys: Vec<BoundExpression>,
+        condition: Option<BoundExpression>,
+    },
+    /// Joins inputs both sorted ascending by their key columns
+    MergeJoin {
+        left: Box<Plan>,
+        right: Box<Plan>,
+        kind: JoinKind,
+        left_key: usize,
+        right_key: usize,
+        condition: Option<BoundExpression>,
+    },
+    Sort {
+        input: Box<Plan>,
+        keys: Vec<(BoundExpression, SortOrder)>,
+    },
+    /// One tuple per group: the values of the group expressions followed by the aggregates
+    Aggregate {
+        input: Box<Plan>,
+        group_by: Vec<BoundExpression>,
+        aggregates: Vec<Aggregate>,
+    },
+    Limit {
+        input: Box<Plan>,
+        limit: Option<u64>,
+        offset: u64,
+    },
+}
+
+/// Tree of operators executing a query, each producing tuples of its columns
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Plan {
+    pub node: PlanNode,
+    pub columns: Vec<PlanColumn>,
+}
+
+impl Plan {
+    pub fn new(node: PlanNode, columns: Vec<PlanColumn>) -> Self {
+        Self { node, columns }
+    }
+
+    /// Column the tuples are sorted by ascending, if any
+    pub fn ordered_by(&self) -> Option<usize> {
+        match &self.node {
+            PlanNode::IndexScan { table, index, .. } if index.index_type == IndexType::BTree => {
+                table.layout.schema().index_of(&index.column_name)
+            }
+            PlanNode::Filter { input, .. } | PlanNode::Limit { input, .. } => input.ordered_by(),
+            PlanNode::MergeJoin { left_key, .. } => Some(*left_key),
+            PlanNode::Sort { keys, .. } => match keys.first() {
+                Some((BoundExpression::Column(index), SortOrder::Ascending)) => Some(*index),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
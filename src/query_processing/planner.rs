@@ -0,0 +1,1219 @@
+use crate::datatypes::SortOrder;
+use crate::metadata_management::catalog::{Catalog, CatalogError, IndexInfo, IndexType, TableInfo};
+use crate::metadata_management::statistics::{StatisticsError, TableStatistics};
+use crate::query_processing::ast::{
+    BinaryOperator, Expression, Join, JoinKind, Select, SelectItem, TableReference, UnaryOperator,
+};
+use crate::query_processing::cost::CostModel;
+use crate::query_processing::expression::BoundExpression;
+use crate::query_processing::plan::{
+    Aggregate, AggregateFunction, IndexLookup, Plan, PlanColumn, PlanNode,
+};
+use crate::query_processing::spill::query_memory;
+use crate::record_management::schema::ColumnType;
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::Transaction;
+use std::fmt::{Display, Formatter};
+use std::ops::Bound;
+
+/// Table of the FROM clause with the position of its first column within the joined tuple
+struct FromTable {
+    reference_name: String,
+    info: TableInfo,
+    indexes: Vec<IndexInfo>,
+    offset: usize,
+}
+
+impl FromTable {
+    fn columns(&self) -> Vec<PlanColumn> {
+        self.info
+            .layout
+            .schema()
+            .columns()
+            .iter()
+            .map(|column| PlanColumn {
+                table: Some(self.reference_name.clone()),
+                name: column.name.clone(),
+                column_type: column.column_type,
+            })
+            .collect()
+    }
+
+    fn width(&self) -> usize {
+        self.info.layout.schema().columns().len()
+    }
+}
+
+/// Select list entry, wildcards expanded to one item per column
+struct ProjectionItem {
+    expression: Option<Expression>,
+    alias: Option<String>,
+    bound: BoundExpression,
+    column: PlanColumn,
+}
+
+/// Turns statements into plans, resolving table and column names against the catalog as seen
+/// by the transaction. Choices between plans are made by the costs estimated from the table
+/// statistics.
+///
+/// Conditions of the WHERE clause are split at AND: conditions on a single table are applied
+/// when scanning it, using an index for a comparison of an indexed column with a constant if
+/// that is cheaper than a full scan. The others become join conditions as soon as all their
+/// tables are joined. Without LEFT JOINs the tables are joined in the order estimated to keep
+/// the intermediate results small, otherwise in the order of the FROM clause, with conditions
+/// on the right table of a LEFT JOIN applied after the join. Joins with equality conditions are
+/// executed as hash or merge joins, other joins compare all pairs of tuples.
+pub struct Planner {
+    transaction: Transaction,
+    catalog: Catalog,
+    cost_model: CostModel,
+}
+
+impl Planner {
+    pub fn new(transaction: &Transaction, catalog: &Catalog) -> Self {
+        Self {
+            transaction: transaction.clone(),
+            catalog: catalog.clone(),
+            cost_model: CostModel::new(query_memory(transaction), transaction.block_size().get()),
+        }
+    }
+
+    /// Cost model with the statistics of all tables planned so far
+    pub fn cost_model(&self) -> &CostModel {
+        &self.cost_model
+    }
+
+    pub fn plan_select(&mut self, select: &Select) -> Result<Plan, PlannerError> {
+        let mut plan = self.plan_from(
+            select.from.as_ref(),
+            &select.joins,
+            select.where_clause.as_ref(),
+        )?;
+        let mut aggregate_calls = vec![];
+        for item in &select.projection {
+            if let SelectItem::Expression { expression, .. } = item {
+                Self::collect_aggregate_calls(expression, &mut aggregate_calls);
+            }
+        }
+        if let Some(having) = &select.having {
+            Self::collect_aggregate_calls(having, &mut aggregate_calls);
+        }
+        for order_by in &select.order_by {
+            Self::collect_aggregate_calls(&order_by.expression, &mut aggregate_calls);
+        }
+        let mut substitutions = vec![];
+        if !select.group_by.is_empty() || !aggregate_calls.is_empty() {
+            (plan, substitutions) = Self::plan_aggregate(plan, &select.group_by, &aggregate_calls)?;
+        }
+        if let Some(having) = &select.having {
+            let predicate = Self::bind(having, &plan.columns, &substitutions)?;
+            plan = Self::filter(plan, vec![predicate]);
+        }
+        let items = Self::projection_items(&select.projection, &plan.columns, &substitutions)?;
+        let mut keys = vec![];
+        if select.distinct {
+            plan = Self::project(plan, &items);
+            plan = Plan::new(
+                PlanNode::Aggregate {
+                    group_by: (0..items.len()).map(BoundExpression::Column).collect(),
+                    aggregates: vec![],
+                    input: Box::new(plan.clone()),
+                },
+                plan.columns,
+            );
+            let projected = items
+                .iter()
+                .enumerate()
+                .filter_map(|(i, item)| item.expression.clone().map(|e| (e, i)))
+                .collect::<Vec<_>>();
+            for order_by in &select.order_by {
+                let key = match Self::alias(&order_by.expression, &items) {
+                    Some(i) => BoundExpression::Column(i),
+                    None => Self::bind(&order_by.expression, &plan.columns, &projected)?,
+                };
+                keys.push((key, order_by.order));
+            }
+            plan = Self::sort(plan, keys);
+        } else {
+            for order_by in &select.order_by {
+                let key = match Self::alias(&order_by.expression, &items) {
+                    Some(i) => items[i].bound.clone(),
+                    None => Self::bind(&order_by.expression, &plan.columns, &substitutions)?,
+                };
+                keys.push((key, order_by.order));
+            }
+            plan = Self::project(Self::sort(plan, keys), &items);
+        }
+        if select.limit.is_some() || select.offset.is_some() {
+            plan = Plan::new(
+                PlanNode::Limit {
+                    input: Box::new(plan.clone()),
+                    limit: select.limit,
+                    offset: select.offset.unwrap_or(0),
+                },
+                plan.columns,
+            );
+        }
+        Ok(plan)
+    }
+
+    /// Plan reading the rows of the table matching the condition, for updates and deletes
+    pub fn plan_table_access(
+        &mut self,
+        table_name: &str,
+        where_clause: Option<&Expression>,
+    ) -> Result<Plan, PlannerError> {
+        let reference = TableReference {
+            name: table_name.to_string(),
+            alias: None,
+        };
+        self.plan_from(Some(&reference), &[], where_clause)
+    }
+
+    /// Binds an expression without column references, e.g. a value to insert
+    pub fn bind_constant(expression: &Expression) -> Result<BoundExpression, PlannerError> {
+        Self::bind(expression, &[], &[])
+    }
+
+    /// Binds an expression over the columns
+    pub fn bind_expression(
+        expression: &Expression,
+        columns: &[PlanColumn],
+    ) -> Result<BoundExpression, PlannerError> {
+        Self::bind(expression, columns, &[])
+    }
+
+    fn plan_from(
+        &mut self,
+        from: Option<&TableReference>,
+        joins: &[Join],
+        where_clause: Option<&Expression>,
+    ) -> Result<Plan, PlannerError> {
+        let Some(first) = from else {
+            let plan = Plan::new(PlanNode::Values(vec![vec![]]), vec![]);
+            return match where_clause {
+                Some(condition) => Ok(Self::filter(plan, vec![Self::bind(condition, &[], &[])?])),
+                None => Ok(plan),
+            };
+        };
+        let mut tables: Vec<FromTable> = vec![];
+        let mut columns = vec![];
+        for reference in std::iter::once(first).chain(joins.iter().map(|join| &join.table)) {
+            let reference_name = reference.reference_name().to_string();
+            if tables
+                .iter()
+                .any(|table| table.reference_name == reference_name)
+            {
+                return Err(PlannerError::DuplicateTableReference(reference_name));
+            }
+            let info = self
+                .catalog
+                .table(&self.transaction, &reference.name)
+                .map_err(PlannerError::CatalogError)?
+                .ok_or_else(|| PlannerError::TableNotFound(reference.name.clone()))?;
+            let indexes = self
+                .catalog
+                .indexes(&self.transaction, &reference.name)
+                .map_err(PlannerError::CatalogError)?;
+            let statistics = TableStatistics::load(&self.transaction, &self.catalog, &info)
+                .map_err(PlannerError::StatisticsError)?;
+            self.cost_model
+                .add_table(&reference_name, &info, statistics);
+            let table = FromTable {
+                reference_name,
+                info,
+                indexes,
+                offset: columns.len(),
+            };
+            columns.extend(table.columns());
+            tables.push(table);
+        }
+        let table_of = |column: usize| {
+            tables
+                .iter()
+                .rposition(|table| table.offset <= column)
+                .unwrap()
+        };
+        let reorder = joins.iter().all(|join| join.kind != JoinKind::Left);
+        let mut conditions = vec![];
+        if let Some(condition) = where_clause {
+            conditions.extend(Self::conjuncts(Self::bind(condition, &columns, &[])?));
+        }
+        let mut on_conditions = vec![vec![]; tables.len()];
+        for (i, join) in joins.iter().enumerate() {
+            if let Some(on) = &join.on {
+                let table = &tables[i + 1];
+                let bound = Self::conjuncts(Self::bind(
+                    on,
+                    &columns[..table.offset + table.width()],
+                    &[],
+                )?);
+                if reorder {
+                    conditions.extend(bound);
+                } else {
+                    on_conditions[i + 1] = bound;
+                }
+            }
+        }
+        let mut local = vec![vec![]; tables.len()];
+        let mut pending = vec![];
+        for conjunct in conditions {
+            let mut referenced: Vec<usize> = conjunct.columns().into_iter().map(table_of).collect();
+            referenced.sort();
+            referenced.dedup();
+            match referenced.as_slice() {
+                [] => local[0].push(conjunct),
+                [i] if *i == 0 || joins[i - 1].kind != JoinKind::Left => {
+                    local[*i].push(conjunct.shifted(-(tables[*i].offset as isize)))
+                }
+                _ => pending.push((referenced, conjunct)),
+            }
+        }
+        let mut accesses: Vec<Option<Plan>> = tables
+            .iter()
+            .zip(local)
+            .map(|(table, conjuncts)| Some(self.plan_access(table, conjuncts)))
+            .collect();
+        let order = if reorder {
+            self.join_order(&accesses, &pending, &columns)
+        } else {
+            (0..tables.len()).collect()
+        };
+        let mut positions = vec![0; tables.len()];
+        let mut plan = accesses[order[0]].take().unwrap();
+        let mut joined = vec![order[0]];
+        for &i in &order[1..] {
+            let right = accesses[i].take().unwrap();
+            positions[i] = plan.columns.len();
+            joined.push(i);
+            let position = |column: usize| {
+                let table = table_of(column);
+                positions[table] + column - tables[table].offset
+            };
+            let mut conditions: Vec<BoundExpression> = on_conditions[i]
+                .iter()
+                .map(|condition| condition.mapped(&position))
+                .collect();
+            let (ready, rest): (Vec<_>, Vec<_>) = pending
+                .into_iter()
+                .partition(|(referenced, _)| referenced.iter().all(|table| joined.contains(table)));
+            pending = rest;
+            let ready = ready
+                .into_iter()
+                .map(|(_, conjunct)| conjunct.mapped(&position))
+                .collect();
+            let mut post_filter = vec![];
+            let kind = match (reorder, i) {
+                (false, i) if joins[i - 1].kind == JoinKind::Left => {
+                    post_filter = ready;
+                    JoinKind::Left
+                }
+                (reorder, i) => {
+                    conditions.extend(ready);
+                    match (reorder, conditions.is_empty()) {
+                        (_, false) => JoinKind::Inner,
+                        (false, true) => joins[i - 1].kind,
+                        (true, true) => JoinKind::Cross,
+                    }
+                }
+            };
+            plan = self.plan_join(plan, right, kind, conditions, &tables);
+            plan = Self::filter(plan, post_filter);
+        }
+        if order.iter().enumerate().any(|(position, i)| position != *i) {
+            let expressions = (0..columns.len())
+                .map(|column| {
+                    let table = table_of(column);
+                    BoundExpression::Column(positions[table] + column - tables[table].offset)
+                })
+                .collect();
+            plan = Plan::new(
+                PlanNode::Project {
+                    input: Box::new(plan),
+                    expressions,
+                },
+                columns,
+            );
+        }
+        Ok(plan)
+    }
+
+    /// Greedy join order: starts with the table returning the fewest rows, then adds the table
+    /// giving the smallest join result, preferring tables with a join condition over cross
+    /// products
+    fn join_order(
+        &self,
+        accesses: &[Option<Plan>],
+        conditions: &[(Vec<usize>, BoundExpression)],
+        columns: &[PlanColumn],
+    ) -> Vec<usize> {
+        let rows: Vec<f64> = accesses
+            .iter()
+            .map(|access| self.cost_model.estimate(access.as_ref().unwrap()).rows)
+            .collect();
+        let mut order = vec![(0..rows.len())
+            .min_by(|a, b| rows[*a].total_cmp(&rows[*b]))
+            .unwrap()];
+        let mut joined_rows = rows[order[0]];
+        while order.len() < rows.len() {
+            let (next, _, next_rows) = (0..rows.len())
+                .filter(|table| !order.contains(table))
+                .map(|table| {
+                    let mut connected = false;
+                    let mut selectivity = 1.0;
+                    for (referenced, condition) in conditions {
+                        if referenced.contains(&table)
+                            && referenced
+                                .iter()
+                                .all(|other| *other == table || order.contains(other))
+                        {
+                            connected = true;
+                            selectivity *= self.cost_model.selectivity(condition, columns);
+                        }
+                    }
+                    (table, connected, joined_rows * rows[table] * selectivity)
+                })
+                .min_by(|(_, a_connected, a_rows), (_, b_connected, b_rows)| {
+                    b_connected.cmp(a_connected).then(a_rows.total_cmp(b_rows))
+                })
+                .unwrap();
+            order.push(next);
+            joined_rows = next_rows;
+        }
+        order
+    }
+
+    /// Cheapest of a full table scan and the index scans usable for a comparison of an indexed
+    /// column with a constant
+    fn plan_access(&self, table: &FromTable, conjuncts: Vec<BoundExpression>) -> Plan {
+        let schema = table.info.layout.schema();
+        let mut best = Self::filter(
+            Plan::new(
+                PlanNode::TableScan {
+                    table: table.info.clone(),
+                },
+                table.columns(),
+            ),
+            conjuncts.clone(),
+        );
+        let mut best_cost = self.cost_model.estimate(&best).cost;
+        for (i, conjunct) in conjuncts.iter().enumerate() {
+            let Some((column, operator, value)) = Self::column_comparison(conjunct) else {
+                continue;
+            };
+            let column = &schema.columns()[column];
+            if !Self::fits(column.column_type, &value) {
+                continue;
+            }
+            for index in table
+                .indexes
+                .iter()
+                .filter(|index| index.column_name == column.name)
+            {
+                let lookup = match (operator, index.index_type) {
+                    (BinaryOperator::Equal, _) => IndexLookup::Equal(value.clone()),
+                    (_, IndexType::Hash) => continue,
+                    (BinaryOperator::Less, _) => {
+                        IndexLookup::Range(Bound::Unbounded, Bound::Excluded(value.clone()))
+                    }
+                    (BinaryOperator::LessEqual, _) => {
+                        IndexLookup::Range(Bound::Unbounded, Bound::Included(value.clone()))
+                    }
+                    (BinaryOperator::Greater, _) => {
+                        IndexLookup::Range(Bound::Excluded(value.clone()), Bound::Unbounded)
+                    }
+                    (BinaryOperator::GreaterEqual, _) => {
+                        IndexLookup::Range(Bound::Included(value.clone()), Bound::Unbounded)
+                    }
+                    _ => continue,
+                };
+                let mut rest = conjuncts.clone();
+                rest.remove(i);
+                let candidate = Self::filter(
+                    Plan::new(
+                        PlanNode::IndexScan {
+                            table: table.info.clone(),
+                            index: index.clone(),
+                            lookup,
+                        },
+                        table.columns(),
+                    ),
+                    rest,
+                );
+                let cost = self.cost_model.estimate(&candidate).cost;
+                if cost < best_cost {
+                    (best, best_cost) = (candidate, cost);
+                }
+            }
+        }
+        best
+    }
+
+    /// Column, operator and constant of a comparison, the operator mirrored if the constant
+    /// comes first
+    fn column_comparison(expression: &BoundExpression) -> Option<(usize, BinaryOperator, Value)> {
+        let BoundExpression::Binary {
+            left,
+            operator,
+            right,
+        } = expression
+        else {
+            return None;
+        };
+        let mirrored = match operator {
+            BinaryOperator::Equal => BinaryOperator::Equal,
+            BinaryOperator::Less => BinaryOperator::Greater,
+            BinaryOperator::LessEqual => BinaryOperator::GreaterEqual,
+            BinaryOperator::Greater => BinaryOperator::Less,
+            BinaryOperator::GreaterEqual => BinaryOperator::LessEqual,
+            _ => return None,
+        };
+        match (left.as_ref(), right.as_ref()) {
+            (BoundExpression::Column(column), BoundExpression::Literal(value))
+                if !value.is_null() =>
+            {
+                Some((*column, *operator, value.clone()))
+            }
+            (BoundExpression::Literal(value), BoundExpression::Column(column))
+                if !value.is_null() =>
+            {
+                Some((*column, mirrored, value.clone()))
+            }
+            _ => None,
+        }
+    }
+
+    /// Whether the value can be stored in a column of the type, only then it can be looked up
+    /// in an index
+    fn fits(column_type: ColumnType, value: &Value) -> bool {
+        match (column_type.integer_range(), value) {
+            (Some((min, max)), Value::Integer(value)) => min <= *value && *value <= max,
+            (None, Value::Text(_)) => column_type == ColumnType::Varchar,
+            (None, Value::Bytes(_)) => column_type == ColumnType::Varbinary,
+            _ => false,
+        }
+    }
+
+    /// Cheapest join of the inputs: a hash join building its hash table from either input (the
+    /// left one only for inner joins), or a merge join of the inputs read in key order via a
+    /// B-tree index or sorted. Merge joins need no memory, they are preferred at equal costs.
+    /// Joins without equality conditions compare all pairs of tuples.
+    fn plan_join(
+        &self,
+        left: Plan,
+        right: Plan,
+        kind: JoinKind,
+        conditions: Vec<BoundExpression>,
+        tables: &[FromTable],
+    ) -> Plan {
+        let offset = left.columns.len();
+        let width = offset + right.columns.len();
+        let mut columns = left.columns.clone();
+        columns.extend(right.columns.iter().cloned());
+        let mut keys = vec![];
+        let mut residual = vec![];
+        for condition in &conditions {
+            match Self::equi_join_keys(condition, offset) {
+                Some((left_key, right_key)) => {
+                    keys.push((left_key, right_key.shifted(-(offset as isize))))
+                }
+                None => residual.push(condition.clone()),
+            }
+        }
+        if keys.is_empty() {
+            return Plan::new(
+                PlanNode::NestedLoopJoin {
+                    left: Box::new(left),
+                    right: Box::new(right),
+                    kind,
+                    condition: Self::conjunction(residual),
+                },
+                columns,
+            );
+        }
+        let mut candidates = vec![];
+        if let Some(position) = keys.iter().position(|keys| {
+            matches!(
+                keys,
+                (BoundExpression::Column(_), BoundExpression::Column(_))
+            )
+        }) {
+            let (BoundExpression::Column(left_key), BoundExpression::Column(right_key)) =
+                keys[position]
+            else {
+                unreachable!("merge keys are columns")
+            };
+            let merge_residual = conditions
+                .iter()
+                .filter(|condition| {
+                    Self::equi_join_keys(condition, offset).is_none_or(|(l, r)| {
+                        l != BoundExpression::Column(left_key)
+                            || r != BoundExpression::Column(right_key + offset)
+                    })
+                })
+                .cloned()
+                .collect::<Vec<_>>();
+            let merge_join = |left: Plan, right: Plan| {
+                Plan::new(
+                    PlanNode::MergeJoin {
+                        left: Box::new(left),
+                        right: Box::new(right),
+                        kind,
+                        left_key,
+                        right_key,
+                        condition: Self::conjunction(merge_residual.clone()),
+                    },
+                    columns.clone(),
+                )
+            };
+            if let (Some(ordered_left), Some(ordered_right)) = (
+                Self::ordered(&left, left_key, tables),
+                Self::ordered(&right, right_key, tables),
+            ) {
+                candidates.push(merge_join(ordered_left, ordered_right));
+            }
+            let sorted = |plan: &Plan, key: usize| match plan.ordered_by() == Some(key) {
+                true => plan.clone(),
+                false => Self::sort(
+                    plan.clone(),
+                    vec![(BoundExpression::Column(key), SortOrder::Ascending)],
+                ),
+            };
+            candidates.push(merge_join(
+                sorted(&left, left_key),
+                sorted(&right, right_key),
+            ));
+        }
+        let (left_keys, right_keys): (Vec<_>, Vec<_>) = keys.into_iter().unzip();
+        let residual = Self::conjunction(residual);
+        if kind == JoinKind::Inner {
+            // the joined tuple of the swapped inputs starts with the columns of the right input
+            let swapped = |column: usize| match column < offset {
+                true => column + width - offset,
+                false => column - offset,
+            };
+            let mut swapped_columns = right.columns.clone();
+            swapped_columns.extend(left.columns.iter().cloned());
+            let join = Plan::new(
+                PlanNode::HashJoin {
+                    left: Box::new(right.clone()),
+                    right: Box::new(left.clone()),
+                    kind,
+                    left_keys: right_keys.clone(),
+                    right_keys: left_keys.clone(),
+                    condition: residual.as_ref().map(|residual| residual.mapped(&swapped)),
+                },
+                swapped_columns,
+            );
+            candidates.push(Plan::new(
+                PlanNode::Project {
+                    input: Box::new(join),
+                    expressions: (0..width)
+                        .map(|column| BoundExpression::Column(swapped(column)))
+                        .collect(),
+                },
+                columns.clone(),
+            ));
+        }
+        candidates.push(Plan::new(
+            PlanNode::HashJoin {
+                left: Box::new(left),
+                right: Box::new(right),
+                kind,
+                left_keys,
+                right_keys,
+                condition: residual,
+            },
+            columns,
+        ));
+        candidates
+            .into_iter()
+            .map(|candidate| (self.cost_model.estimate(&candidate).cost, candidate))
+            .reduce(|best, candidate| {
+                if candidate.0 < best.0 {
+                    candidate
+                } else {
+                    best
+                }
+            })
+            .unwrap()
+            .1
+    }
+
+    /// Sides of an equality between an expression on the left and one on the right input
+    fn equi_join_keys(
+        condition: &BoundExpression,
+        offset: usize,
+    ) -> Option<(BoundExpression, BoundExpression)> {
+        let BoundExpression::Binary {
+            left,
+            operator: BinaryOperator::Equal,
+            right,
+        } = condition
+        else {
+            return None;
+        };
+        let only_left = |expression: &BoundExpression| {
+            let columns = expression.columns();
+            !columns.is_empty() && columns.iter().all(|column| *column < offset)
+        };
+        let only_right = |expression: &BoundExpression| {
+            let columns = expression.columns();
+            !columns.is_empty() && columns.iter().all(|column| *column >= offset)
+        };
+        if only_left(left) && only_right(right) {
+            Some((*left.clone(), *right.clone()))
+        } else if only_right(left) && only_left(right) {
+            Some((*right.clone(), *left.clone()))
+        } else {
+            None
+        }
+    }
+
+    /// The plan producing its tuples in ascending order of the column: the plan itself if it
+    /// does already, a table scan replaced by a scan of a B-tree index on the column
+    fn ordered(plan: &Plan, column: usize, tables: &[FromTable]) -> Option<Plan> {
+        if plan.ordered_by() == Some(column) {
+            return Some(plan.clone());
+        }
+        match &plan.node {
+            PlanNode::TableScan { table } => {
+                let column_name = &table.layout.schema().columns()[column].name;
+                let index = tables
+                    .iter()
+                    .find(|from_table| from_table.info == *table)?
+                    .indexes
+                    .iter()
+                    .find(|index| {
+                        index.index_type == IndexType::BTree && index.column_name == *column_name
+                    })?;
+                Some(Plan::new(
+                    PlanNode::IndexScan {
+                        table: table.clone(),
+                        index: index.clone(),
+                        lookup: IndexLookup::Range(Bound::Unbounded, Bound::Unbounded),
+                    },
+                    plan.columns.clone(),
+                ))
+            }
+            PlanNode::Filter { input, predicate } => Some(Plan::new(
+                PlanNode::Filter {
+                    input: Box::new(Self::ordered(input, column, tables)?),
+                    predicate: predicate.clone(),
+                },
+                plan.columns.clone(),
+            )),
+            _ => None,
+        }
+    }
+
+    fn plan_aggregate(
+        input: Plan,
+        group_by: &[Expression],
+        calls: &[Expression],
+    ) -> Result<(Plan, Vec<(Expression, usize)>), PlannerError> {
+        let mut bound_group_by = vec![];
+        let mut columns = vec![];
+        let mut substitutions = vec![];
+        for expression in group_by {
+            let bound = Self::bind(expression, &input.columns, &[])?;
+            columns.push(match &bound {
+                BoundExpression::Column(index) => input.columns[*index].clone(),
+                _ => PlanColumn {
+                    table: None,
+                    name: expression.to_string(),
+                    column_type: Self::expression_type(&bound, &input.columns),
+                },
+            });
+            substitutions.push((expression.clone(), bound_group_by.len()));
+            bound_group_by.push(bound);
+        }
+        let mut aggregates = vec![];
+        for call in calls {
+            let Expression::Function {
+                name,
+                arguments,
+                star,
+            } = call
+            else {
+                unreachable!("aggregate calls are functions")
+            };
+            let function = AggregateFunction::from_name(name).unwrap();
+            let argument = match (function, *star, arguments.as_slice()) {
+                (AggregateFunction::Count, true, []) => None,
+                (_, false, [argument]) => Some(Self::bind(argument, &input.columns, &[])?),
+                _ => return Err(PlannerError::InvalidAggregate(call.to_string())),
+            };
+            let column_type = match (function, &argument) {
+                (AggregateFunction::Count, _) => ColumnType::BigCount,
+                (AggregateFunction::Min | AggregateFunction::Max, Some(argument)) => {
+                    Self::expression_type(argument, &input.columns)
+                }
+                _ => ColumnType::HugeInteger,
+            };
+            columns.push(PlanColumn {
+                table: None,
+                name: call.to_string(),
+                column_type,
+            });
+            substitutions.push((call.clone(), bound_group_by.len() + aggregates.len()));
+            aggregates.push(Aggregate { function, argument });
+        }
+        let plan = Plan::new(
+            PlanNode::Aggregate {
+                input: Box::new(input),
+                group_by: bound_group_by,
+                aggregates,
+            },
+            columns,
+        );
+        Ok((plan, substitutions))
+    }
+
+    fn collect_aggregate_calls(expression: &Expression, calls: &mut Vec<Expression>) {
+        match expression {
+            Expression::Function { name, .. } if AggregateFunction::from_name(name).is_some() => {
+                if !calls.contains(expression) {
+                    calls.push(expression.clone());
+                }
+            }
+            Expression::Function { arguments, .. } => {
+                for argument in arguments {
+                    Self::collect_aggregate_calls(argument, calls);
+                }
+            }
+            Expression::Unary { operand, .. } => Self::collect_aggregate_calls(operand, calls),
+            Expression::Binary { left, right, .. } => {
+                Self::collect_aggregate_calls(left, calls);
+                Self::collect_aggregate_calls(right, calls);
+            }
+            Expression::IsNull { expression, .. } => {
+                Self::collect_aggregate_calls(expression, calls)
+            }
+            Expression::Literal(_) | Expression::Column { .. } => {}
+        }
+    }
+
+    fn projection_items(
+        projection: &[SelectItem],
+        columns: &[PlanColumn],
+        substitutions: &[(Expression, usize)],
+    ) -> Result<Vec<ProjectionItem>, PlannerError> {
+        let mut items = vec![];
+        for item in projection {
+            match item {
+                SelectItem::Wildcard => items.extend(columns.iter().enumerate().map(
+                    |(i, column)| ProjectionItem {
+                        expression: None,
+                        alias: None,
+                        bound: BoundExpression::Column(i),
+                        column: column.clone(),
+                    },
+                )),
+                SelectItem::QualifiedWildcard(table) => {
+                    let length = items.len();
+                    items.extend(
+                        columns
+                            .iter()
+                            .enumerate()
+                            .filter(|(_, column)| column.table.as_ref() == Some(table))
+                            .map(|(i, column)| ProjectionItem {
+                                expression: None,
+                                alias: None,
+                                bound: BoundExpression::Column(i),
+                                column: column.clone(),
+                            }),
+                    );
+                    if items.len() == length {
+                        return Err(PlannerError::TableNotFound(table.clone()));
+                    }
+                }
+                SelectItem::Expression { expression, alias } => {
+                    let bound = Self::bind(expression, columns, substitutions)?;
+                    let column = match (alias, &bound) {
+                        (None, BoundExpression::Column(i))
+                            if matches!(expression, Expression::Column { .. }) =>
+                        {
+                            columns[*i].clone()
+                        }
+                        _ => PlanColumn {
+                            table: None,
+                            name: alias.clone().unwrap_or_else(|| expression.to_string()),
+                            column_type: Self::expression_type(&bound, columns),
+                        },
+                    };
+                    items.push(ProjectionItem {
+                        expression: Some(expression.clone()),
+                        alias: alias.clone(),
+                        bound,
+                        column,
+                    });
+                }
+            }
+        }
+        Ok(items)
+    }
+
+    /// Position of the select list item the expression refers to by its alias
+    fn alias(expression: &Expression, items: &[ProjectionItem]) -> Option<usize> {
+        match expression {
+            Expression::Column { table: None, name } => items
+                .iter()
+                .position(|item| item.alias.as_ref() == Some(name)),
+            _ => None,
+        }
+    }
+
+    fn project(input: Plan, items: &[ProjectionItem]) -> Plan {
+        Plan::new(
+            PlanNode::Project {
+                input: Box::new(input),
+                expressions: items.iter().map(|item| item.bound.clone()).collect(),
+            },
+            items.iter().map(|item| item.column.clone()).collect(),
+        )
+    }
+
+    fn sort(input: Plan, keys: Vec<(BoundExpression, SortOrder)>) -> Plan {
+        if keys.is_empty() {
+            return input;
+        }
+        let columns = input.columns.clone();
+        Plan::new(
+            PlanNode::Sort {
+                input: Box::new(input),
+                keys,
+            },
+            columns,
+        )
+    }
+
+    fn filter(input: Plan, conjuncts: Vec<BoundExpression>) -> Plan {
+        match Self::conjunction(conjuncts) {
+            Some(predicate) => {
+                let columns = input.columns.clone();
+                Plan::new(
+                    PlanNode::Filter {
+                        input: Box::new(input),
+                        predicate,
+                    },
+                    columns,
+                )
+            }
+            None => input,
+        }
+    }
+
+    fn conjuncts(expression: BoundExpression) -> Vec<BoundExpression> {
+        match expression {
+            BoundExpression::Binary {
+                left,
+                operator: BinaryOperator::And,
+                right,
+            } => {
+                let mut conjuncts = Self::conjuncts(*left);
+                conjuncts.extend(Self::conjuncts(*right));
+                conjuncts
+            }
+            expression => vec![expression],
+        }
+    }
+
+    fn conjunction(conjuncts: Vec<BoundExpression>) -> Option<BoundExpression> {
+        conjuncts
+            .into_iter()
+            .reduce(|left, right| BoundExpression::binary(left, BinaryOperator::And, right))
+    }
+
+    /// Resolves the columns of the expression. Subexpressions equal to one of the substitutions
+    /// (group expressions and aggregates after grouping) refer to its column.
+    fn bind(
+        expression: &Expression,
+        columns: &[PlanColumn],
+        substitutions: &[(Expression, usize)],
+    ) -> Result<BoundExpression, PlannerError> {
+        if let Some((_, index)) = substitutions
+            .iter()
+            .find(|(substituted, _)| substituted == expression)
+        {
+            return Ok(BoundExpression::Column(*index));
+        }
+        match expression {
+            Expression::Literal(value) => Ok(BoundExpression::Literal(value.clone())),
+            Expression::Column { table, name } => {
+                let mut matching = columns.iter().enumerate().filter(|(_, column)| {
+                    column.name == *name && (table.is_none() || column.table == *table)
+                });
+                match (matching.next(), matching.next()) {
+                    (Some((index, _)), None) => Ok(BoundExpression::Column(index)),
+                    (Some(_), Some(_)) => {
+                        Err(PlannerError::AmbiguousColumn(expression.to_string()))
+                    }
+                    (None, _) if substitutions.is_empty() => {
+                        Err(PlannerError::UnknownColumn(expression.to_string()))
+                    }
+                    (None, _) => Err(PlannerError::NotGrouped(expression.to_string())),
+                }
+            }
+            Expression::Unary { operator, operand } => Ok(BoundExpression::Unary {
+                operator: *operator,
+                operand: Box::new(Self::bind(operand, columns, substitutions)?),
+            }),
+            Expression::Binary {
+                left,
+                operator,
+                right,
+            } => Ok(BoundExpression::binary(
+                Self::bind(left, columns, substitutions)?,
+                *operator,
+                Self::bind(right, columns, substitutions)?,
+            )),
+            Expression::IsNull {
+                expression,
+                negated,
+            } => Ok(BoundExpression::IsNull {
+                expression: Box::new(Self::bind(expression, columns, substitutions)?),
+                negated: *negated,
+            }),
+            Expression::Function { name, .. } => match AggregateFunction::from_name(name) {
+                Some(_) => Err(PlannerError::AggregateNotAllowed(expression.to_string())),
+                None => Err(PlannerError::UnknownFunction(name.clone())),
+            },
+        }
+    }
+
+    /// Type of the values of the expression: conditions are small integers (0 or 1), results
+    /// of arithmetic huge integers
+    fn expression_type(expression: &BoundExpression, columns: &[PlanColumn]) -> ColumnType {
+        match expression {
+            BoundExpression::Literal(Value::Integer(value)) if i64::try_from(*value).is_ok() => {
+                ColumnType::BigInteger
+            }
+            BoundExpression::Literal(Value::Integer(_)) => ColumnType::HugeInteger,
+            BoundExpression::Literal(Value::Bytes(_)) => ColumnType::Varbinary,
+            BoundExpression::Literal(_) => ColumnType::Varchar,
+            BoundExpression::Column(index) => columns[*index].column_type,
+            BoundExpression::Unary {
+                operator: UnaryOperator::Minus,
+                ..
+            } => ColumnType::HugeInteger,
+            BoundExpression::Binary {
+                left,
+                operator: BinaryOperator::Concat,
+                ..
+            } => Self::expression_type(left, columns),
+            BoundExpression::Binary {
+                operator:
+                    BinaryOperator::Add
+                    | BinaryOperator::Subtract
+                    | BinaryOperator::Multiply
+                    | BinaryOperator::Divide
+                    | BinaryOperator::Modulo,
+                ..
+            } => ColumnType::HugeInteger,
+            BoundExpression::Unary { .. }
+            | BoundExpression::Binary { .. }
+            | BoundExpression::IsNull { .. } => ColumnType::TinyInteger,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum PlannerError {
+    CatalogError(CatalogError),
+    StatisticsError(StatisticsError),
+    TableNotFound(String),
+    DuplicateTableReference(String),
+    UnknownColumn(String),
+    AmbiguousColumn(String),
+    NotGrouped(String),
+    UnknownFunction(String),
+    AggregateNotAllowed(String),
+    InvalidAggregate(String),
+}
+
+impl Display for PlannerError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PlannerError::CatalogError(e) => write!(f, "Planner {}", e),
+            PlannerError::StatisticsError(e) => write!(f, "Planner {}", e),
+            PlannerError::TableNotFound(name) => write!(f, "Planner: no table {}", name),
+            PlannerError::DuplicateTableReference(name) => {
+                write!(f, "Planner: table name {} used more than once", name)
+            }
+            PlannerError::UnknownColumn(name) => write!(f, "Planner: no column {}", name),
+            PlannerError::AmbiguousColumn(name) => {
+                write!(f, "Planner: column {} is ambiguous", name)
+            }
+            PlannerError::NotGrouped(name) => write!(
+                f,
+                "Planner: column {} must be grouped by or used in an aggregate",
+                name
+            ),
+            PlannerError::UnknownFunction(name) => write!(f, "Planner: no function {}", name),
+            PlannerError::AggregateNotAllowed(call) => {
+                write!(f, "Planner: aggregate {} is not allowed here", call)
+            }
+            PlannerError::InvalidAggregate(call) => {
+                write!(f, "Planner: invalid aggregate {}", call)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::query_processing::ast::Statement;
+    use crate::query_processing::executor::{QueryResult, ResultSet};
+    use crate::query_processing::parser::parse_statement;
+    use crate::query_processing::plan::{Plan, PlanColumn, PlanNode};
+    use crate::query_processing::planner::Planner;
+    use crate::query_processing::session::Session;
+    use crate::record_management::schema::ColumnType;
+    use crate::record_management::value::Value;
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+
+    /// Nodes of the plan in pre-order, left input first
+    fn nodes(plan: &Plan) -> Vec<&PlanNode> {
+        let mut nodes = vec![&plan.node];
+        match &plan.node {
+            PlanNode::Values(_) | PlanNode::TableScan { .. } | PlanNode::IndexScan { .. } => {}
+            PlanNode::Filter { input, .. }
+            | PlanNode::Project { input, .. }
+            | PlanNode::Sort { input, .. }
+            | PlanNode::Aggregate { input, .. }
+            | PlanNode::Limit { input, .. } => nodes.extend(self::nodes(input)),
+            PlanNode::NestedLoopJoin { left, right, .. }
+            | PlanNode::HashJoin { left, right, .. }
+            | PlanNode::MergeJoin { left, right, .. } => {
+                nodes.extend(self::nodes(left));
+                nodes.extend(self::nodes(right));
+            }
+        }
+        nodes
+    }
+
+    fn plan(planner: &mut Planner, sql: &str) -> Plan {
+        let Statement::Select(select) = parse_statement(sql).unwrap() else {
+            panic!("{} is no select", sql)
+        };
+        planner.plan_select(&select).unwrap()
+    }
+
+    /// Name of the first table read by the plan
+    fn first_table(plan: &Plan) -> String {
+        nodes(plan)
+            .into_iter()
+            .find_map(|node| match node {
+                PlanNode::TableScan { table } | PlanNode::IndexScan { table, .. } => {
+                    Some(table.name.clone())
+                }
+                _ => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn test_cost_based_plan_choices() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("planner_costs")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(400).unwrap()))
+            .build()
+            .unwrap();
+        let mut session = Session::new(&hfdb.transaction_manager, &hfdb.catalog);
+        session
+            .execute(
+                "CREATE TABLE customers (id INT NOT NULL, region INT);
+                 CREATE TABLE orders (id INT NOT NULL, customer_id INT, amount INT);
+                 CREATE INDEX customers_id ON customers (id);
+                 CREATE INDEX orders_id ON orders (id);
+                 CREATE INDEX orders_customer ON orders (customer_id);",
+            )
+            .unwrap();
+        let customers: Vec<String> = (0..20).map(|id| format!("({}, {})", id, id % 5)).collect();
+        let orders: Vec<String> = (0..1000)
+            .map(|id| format!("({}, {}, {})", id, id % 20, id % 7))
+            .collect();
+        session
+            .execute(&format!(
+                "INSERT INTO customers VALUES {}; INSERT INTO orders VALUES {}; ANALYZE",
+                customers.join(", "),
+                orders.join(", ")
+            ))
+            .unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let mut planner = Planner::new(&tx, &hfdb.catalog);
+        let index_scan = |plan: &Plan, index_name: &str| {
+            nodes(plan).into_iter().any(|node| {
+                matches!(node, PlanNode::IndexScan { index, .. } if index.name == index_name)
+            })
+        };
+
+        assert!(index_scan(
+            &plan(&mut planner, "SELECT * FROM orders WHERE id = 5"),
+            "orders_id"
+        ));
+        assert!(index_scan(
+            &plan(&mut planner, "SELECT * FROM orders WHERE id < 20"),
+            "orders_id"
+        ));
+        let wide_range = plan(&mut planner, "SELECT * FROM orders WHERE id > 10");
+        assert!(nodes(&wide_range)
+            .iter()
+            .any(|node| matches!(node, PlanNode::TableScan { .. })));
+        assert!(!index_scan(&wide_range, "orders_id"));
+
+        let join = plan(
+            &mut planner,
+            "SELECT * FROM orders o, customers c WHERE o.customer_id = c.id AND c.region = 3",
+        );
+        assert_eq!(join.columns[0].table.as_deref(), Some("o"));
+        assert_eq!(join.columns[3].table.as_deref(), Some("c"));
+        let hash_join = nodes(&join)
+            .into_iter()
+            .find_map(|node| match node {
+                PlanNode::HashJoin { right, .. } => Some(first_table(right)),
+                _ => None,
+            })
+            .expect("hash join");
+        assert_eq!(hash_join, "customers");
+
+        let merge = plan(
+            &mut planner,
+            "SELECT * FROM orders a JOIN orders b ON a.id = b.id WHERE a.id < 20 AND b.id < 20",
+        );
+        assert!(nodes(&merge).into_iter().any(|node| matches!(
+            node,
+            PlanNode::MergeJoin { left, right, .. }
+                if left.ordered_by().is_some() && right.ordered_by().is_some()
+        )));
+
+        let filtered = plan(&mut planner, "SELECT * FROM orders WHERE customer_id = 7");
+        let estimate = planner.cost_model().estimate(&filtered);
+        assert!(
+            (45.0..55.0).contains(&estimate.rows),
+            "estimated {} rows",
+            estimate.rows
+        );
+        tx.commit().unwrap();
+        assert_eq!(
+            session
+                .execute(
+                    "SELECT count(*) FROM orders o, customers c \
+                     WHERE o.customer_id = c.id AND c.region = 3"
+                )
+                .unwrap(),
+            vec![QueryResult::Rows(ResultSet {
+                columns: vec![PlanColumn {
+                    table: None,
+                    name: "count(*)".to_string(),
+                    column_type: ColumnType::BigCount,
+                }],
+                rows: vec![vec![Value::from(200i64)]],
+            })]
+        );
+    }
+}
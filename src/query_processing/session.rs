@@ -0,0 +1,491 @@
+use crate::metadata_management::catalog::Catalog;
+use crate::query_processing::ast::Statement;
+use crate::query_processing::executor::{ExecutionError, Executor, QueryResult};
+use crate::query_processing::explain::explain_columns;
+use crate::query_processing::parser::{parse, ParserError};
+use crate::query_processing::plan::PlanColumn;
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use crate::transaction_management::transaction_manager::TransactionManager;
+use std::fmt::{Display, Formatter};
+
+/// Executes SQL for one client. Statements outside of BEGIN and COMMIT or ROLLBACK run in
+/// their own transaction, committed when the statement succeeded. A failing statement within
+/// an explicit transaction rolls back the whole transaction, the session then rejects all
+/// statements until the transaction is ended by COMMIT or ROLLBACK (both rolling back, as
+/// PostgreSQL does). An open transaction is rolled back when the session is dropped.
+pub struct Session {
+    transaction_manager: TransactionManager,
+    catalog: Catalog,
+    transaction: Option<Transaction>,
+    /// The explicit transaction failed and was rolled back, but is not ended yet
+    failed: bool,
+}
+
+impl Session {
+    pub fn new(transaction_manager: &TransactionManager, catalog: &Catalog) -> Self {
+        Self {
+            transaction_manager: transaction_manager.clone(),
+            catalog: catalog.clone(),
+            transaction: None,
+            failed: false,
+        }
+    }
+
+    /// Within an explicit transaction, including a failed one
+    pub fn in_transaction(&self) -> bool {
+        self.transaction.is_some() || self.failed
+    }
+
+    /// Within an explicit transaction that failed, statements are rejected until it is ended
+    pub fn in_failed_transaction(&self) -> bool {
+        self.failed
+    }
+
+    /// Executes all statements of the SQL text, stopping at the first failing one
+    pub fn execute(&mut self, sql: &str) -> Result<Vec<QueryResult>, SessionError> {
+        let statements = match parse(sql) {
+            Ok(statements) => statements,
+            Err(e) => {
+                self.fail_transaction()?;
+                return Err(SessionError::ParserError(e));
+            }
+        };
+        statements
+            .iter()
+            .map(|statement| self.execute_statement(statement))
+            .collect()
+    }
+
+    /// Rolls back the explicit transaction after an error outside of execute_statement, e.g. SQL
+    /// not parsing, and rejects statements until the transaction is ended
+    pub fn fail_transaction(&mut self) -> Result<(), SessionError> {
+        if let Some(transaction) = self.transaction.take() {
+            self.failed = true;
+            transaction
+                .rollback()
+                .map_err(SessionError::TransactionError)?;
+        }
+        Ok(())
+    }
+
+    pub fn execute_statement(
+        &mut self,
+        statement: &Statement,
+    ) -> Result<QueryResult, SessionError> {
+        if self.failed {
+            return match statement {
+                Statement::Commit | Statement::Rollback => {
+                    self.failed = false;
+                    Ok(QueryResult::Rollback)
+                }
+                _ => Err(SessionError::FailedTransaction),
+            };
+        }
+        match statement {
+            Statement::Begin => {
+                if self.transaction.is_some() {
+                    return Err(SessionError::AlreadyInTransaction);
+                }
+                self.transaction = Some(self.begin()?);
+                Ok(QueryResult::Begin)
+            }
+            Statement::Commit => {
+                let transaction = self.transaction.take().ok_or(SessionError::NoTransaction)?;
+                transaction
+                    .commit()
+                    .map_err(SessionError::TransactionError)?;
+                Ok(QueryResult::Commit)
+            }
+            Statement::Rollback => {
+                let transaction = self.transaction.take().ok_or(SessionError::NoTransaction)?;
+                transaction
+                    .rollback()
+                    .map_err(SessionError::TransactionError)?;
+                Ok(QueryResult::Rollback)
+            }
+            statement => {
+                let autocommit = self.transaction.is_none();
+                let transaction = match &self.transaction {
+                    Some(transaction) => transaction.clone(),
+                    None => self.begin()?,
+                };
+                match Executor::new(&transaction, &self.catalog).execute(statement) {
+                    Ok(result) => {
+                        if autocommit {
+                            transaction
+                                .commit()
+                                .map_err(SessionError::TransactionError)?;
+                        }
+                        Ok(result)
+                    }
+                    Err(e) => {
+                        if autocommit {
+                            transaction
+                                .rollback()
+                                .map_err(SessionError::TransactionError)?;
+                        } else {
+                            self.fail_transaction()?;
+                        }
+                        Err(SessionError::ExecutionError(e))
+                    }
+                }
+            }
+        }
+    }
+
+    /// Columns of the rows the statement returns, None for statements returning no rows. The
+    /// statement is only planned, not executed.
+    pub fn describe(&self, statement: &Statement) -> Result<Option<Vec<PlanColumn>>, SessionError> {
+        if self.failed {
+            return Err(SessionError::FailedTransaction);
+        }
+        match statement {
+            Statement::Select(select) => {
+                let transaction = match &self.transaction {
+                    Some(transaction) => transaction.clone(),
+                    None => self.begin()?,
+                };
+                let plan = Executor::new(&transaction, &self.catalog)
+                    .planner()
+                    .plan_select(select);
+                if self.transaction.is_none() {
+                    transaction
+                        .commit()
+                        .map_err(SessionError::TransactionError)?;
+                }
+                plan.map(|plan| Some(plan.columns))
+                    .map_err(|e| SessionError::ExecutionError(ExecutionError::PlannerError(e)))
+            }
+            Statement::Explain(_) => Ok(Some(explain_columns())),
+            _ => Ok(None),
+        }
+    }
+
+    fn begin(&self) -> Result<Transaction, SessionError> {
+        self.transaction_manager
+            .begin()
+            .map_err(SessionError::TransactionError)
+    }
+}
+
+#[derive(Debug)]
+pub enum SessionError {
+    ParserError(ParserError),
+    ExecutionError(ExecutionError),
+    TransactionError(TransactionError),
+    AlreadyInTransaction,
+    NoTransaction,
+    FailedTransaction,
+}
+
+impl Display for SessionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SessionError::ParserError(e) => write!(f, "Session {}", e),
+            SessionError::ExecutionError(e) => write!(f, "Session {}", e),
+            SessionError::TransactionError(e) => write!(f, "Session {}", e),
+            SessionError::AlreadyInTransaction => {
+                write!(f, "Session: a transaction is already in progress")
+            }
+            SessionError::NoTransaction => write!(f, "Session: no transaction in progress"),
+            SessionError::FailedTransaction => write!(
+                f,
+                "Session: current transaction is aborted, commands ignored until end of \
+                 transaction block"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::query_processing::ast::Statement;
+    use crate::query_processing::executor::{ExecutionError, Executor, QueryResult};
+    use crate::query_processing::parser::parse_statement;
+    use crate::query_processing::plan::{Plan, PlanNode};
+    use crate::query_processing::session::{Session, SessionError};
+    use crate::record_management::value::Value;
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+
+    fn rows(session: &mut Session, sql: &str) -> Vec<Vec<Value>> {
+        match session.execute(sql).unwrap().pop() {
+            Some(QueryResult::Rows(result_set)) => result_set.rows,
+            result => panic!("{:?} returned no rows", result),
+        }
+    }
+
+    fn plan(session: &mut Session, sql: &str) -> Plan {
+        let Statement::Select(select) = parse_statement(sql).unwrap() else {
+            panic!("{} is no select", sql)
+        };
+        let tx = session.transaction_manager.begin().unwrap();
+        let executor = Executor::new(&tx, &session.catalog);
+        executor.planner().plan_select(&select).unwrap()
+    }
+
+    fn contains(plan: &Plan, matches: &dyn Fn(&PlanNode) -> bool) -> bool {
+        matches(&plan.node)
+            || match &plan.node {
+                PlanNode::Values(_) | PlanNode::TableScan { .. } | PlanNode::IndexScan { .. } => {
+                    false
+                }
+                PlanNode::Filter { input, .. }
+                | PlanNode::Project { input, .. }
+                | PlanNode::Sort { input, .. }
+                | PlanNode::Aggregate { input, .. }
+                | PlanNode::Limit { input, .. } => contains(input, matches),
+                PlanNode::NestedLoopJoin { left, right, .. }
+                | PlanNode::HashJoin { left, right, .. }
+                | PlanNode::MergeJoin { left, right, .. } => {
+                    contains(left, matches) || contains(right, matches)
+                }
+            }
+    }
+
+    fn integers(values: &[i64]) -> Vec<Value> {
+        values.iter().map(|value| Value::from(*value)).collect()
+    }
+
+    #[test]
+    fn test_select_with_joins_aggregates_and_index_scans() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("session_select")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(400).unwrap()))
+            .build()
+            .unwrap();
+        let mut session = Session::new(&hfdb.transaction_manager, &hfdb.catalog);
+        session
+            .execute(
+                "CREATE TABLE cities (id INT NOT NULL, name VARCHAR NOT NULL);
+                 CREATE TABLE persons (id INT NOT NULL, name TEXT, city_id INT, age INT);
+                 CREATE INDEX cities_id ON cities (id);
+                 CREATE INDEX persons_city ON persons (city_id);
+                 CREATE INDEX persons_id ON persons (id) USING hash;
+                 INSERT INTO cities VALUES (1, 'Berlin'), (2, 'Hamburg'), (3, 'Munich');
+                 INSERT INTO persons VALUES
+                    (1, 'Ada', 1, 36), (2, 'Bob', 2, 25), (3, 'Cid', 1, 51),
+                    (4, 'Dan', NULL, 40), (5, 'Eve', 2, 30), (6, 'Fay', 1, 19);",
+            )
+            .unwrap();
+
+        let sql = "SELECT p.name, c.name FROM persons p JOIN cities c ON p.city_id = c.id \
+                   WHERE p.age > 20 ORDER BY p.name";
+        assert_eq!(
+            rows(&mut session, sql),
+            vec![
+                vec![Value::from("Ada"), Value::from("Berlin")],
+                vec![Value::from("Bob"), Value::from("Hamburg")],
+                vec![Value::from("Cid"), Value::from("Berlin")],
+                vec![Value::from("Eve"), Value::from("Hamburg")],
+            ]
+        );
+
+        let sql = "SELECT p.id, c.name FROM persons p, cities c \
+                   WHERE p.city_id + 0 = c.id AND p.age < 30 ORDER BY p.id";
+        assert!(contains(&plan(&mut session, sql), &|node| matches!(
+            node,
+            PlanNode::HashJoin { .. }
+        )));
+        assert_eq!(
+            rows(&mut session, sql),
+            vec![
+                vec![Value::from(2i64), Value::from("Hamburg")],
+                vec![Value::from(6i64), Value::from("Berlin")],
+            ]
+        );
+
+        let sql = "SELECT c.name, p.name FROM cities c LEFT JOIN persons p \
+                   ON p.city_id = c.id AND p.age > 45 ORDER BY c.id";
+        assert_eq!(
+            rows(&mut session, sql),
+            vec![
+                vec![Value::from("Berlin"), Value::from("Cid")],
+                vec![Value::from("Hamburg"), Value::Null],
+                vec![Value::from("Munich"), Value::Null],
+            ]
+        );
+
+        let sql = "SELECT p.name FROM persons p JOIN cities c ON p.city_id < c.id \
+                   WHERE c.name = 'Hamburg' ORDER BY 1";
+        assert!(contains(&plan(&mut session, sql), &|node| matches!(
+            node,
+            PlanNode::NestedLoopJoin { .. }
+        )));
+        assert_eq!(
+            rows(&mut session, sql),
+            vec![
+                vec![Value::from("Ada")],
+                vec![Value::from("Cid")],
+                vec![Value::from("Fay")]
+            ]
+        );
+
+        let sql = "SELECT name FROM persons WHERE id = 4";
+        assert!(contains(&plan(&mut session, sql), &|node| matches!(
+            node,
+            PlanNode::IndexScan { index, .. } if index.name == "persons_id"
+        )));
+        assert_eq!(rows(&mut session, sql), vec![vec![Value::from("Dan")]]);
+
+        assert_eq!(
+            rows(
+                &mut session,
+                "SELECT city_id, count(*), sum(age), min(name), avg(age) FROM persons \
+                 GROUP BY city_id HAVING count(*) > 1 ORDER BY city_id DESC"
+            ),
+            vec![
+                vec![
+                    Value::from(2i64),
+                    Value::from(2i64),
+                    Value::from(55i64),
+                    Value::from("Bob"),
+                    Value::from(27i64)
+                ],
+                vec![
+                    Value::from(1i64),
+                    Value::from(3i64),
+                    Value::from(106i64),
+                    Value::from("Ada"),
+                    Value::from(35i64)
+                ],
+            ]
+        );
+        assert_eq!(
+            rows(&mut session, "SELECT count(city_id), max(age) FROM persons"),
+            vec![integers(&[5, 51])]
+        );
+        assert_eq!(
+            rows(
+                &mut session,
+                "SELECT DISTINCT city_id FROM persons WHERE city_id IS NOT NULL"
+            ),
+            vec![integers(&[1]), integers(&[2])]
+        );
+        assert_eq!(
+            rows(
+                &mut session,
+                "SELECT id * 10 AS x FROM persons ORDER BY x DESC LIMIT 2 OFFSET 1"
+            ),
+            vec![integers(&[50]), integers(&[40])]
+        );
+        assert_eq!(
+            rows(&mut session, "SELECT 1 + 2, 'a' || 'b'"),
+            vec![vec![Value::from(3i64), Value::from("ab")]]
+        );
+        assert!(matches!(
+            session.execute("SELECT unknown FROM persons"),
+            Err(SessionError::ExecutionError(ExecutionError::PlannerError(
+                _
+            )))
+        ));
+    }
+
+    #[test]
+    fn test_update_delete_and_transactions() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("session_transactions")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(400).unwrap()))
+            .build()
+            .unwrap();
+        let mut session = Session::new(&hfdb.transaction_manager, &hfdb.catalog);
+        session
+            .execute(
+                "CREATE TABLE counters (id INT NOT NULL, value INT);
+                 CREATE INDEX counters_id ON counters (id);
+                 INSERT INTO counters (id, value) VALUES (1, 10), (2, 20), (3, 30);",
+            )
+            .unwrap();
+
+        assert_eq!(
+            session
+                .execute("UPDATE counters SET value = value + id WHERE id >= 2")
+                .unwrap(),
+            vec![QueryResult::Updated(2)]
+        );
+        assert_eq!(
+            session
+                .execute("DELETE FROM counters WHERE id = 1")
+                .unwrap(),
+            vec![QueryResult::Deleted(1)]
+        );
+        assert_eq!(
+            rows(&mut session, "SELECT * FROM counters ORDER BY id"),
+            vec![integers(&[2, 22]), integers(&[3, 33])]
+        );
+
+        session
+            .execute("BEGIN; INSERT INTO counters VALUES (4, 40); DELETE FROM counters")
+            .unwrap();
+        assert!(session.in_transaction());
+        assert_eq!(
+            rows(&mut session, "SELECT count(*) FROM counters"),
+            vec![integers(&[0])]
+        );
+        session.execute("ROLLBACK").unwrap();
+        assert_eq!(
+            rows(&mut session, "SELECT count(*) FROM counters"),
+            vec![integers(&[2])]
+        );
+
+        session
+            .execute("BEGIN; INSERT INTO counters VALUES (4, 40); COMMIT")
+            .unwrap();
+        assert_eq!(
+            rows(&mut session, "SELECT count(*) FROM counters"),
+            vec![integers(&[3])]
+        );
+
+        session.execute("BEGIN").unwrap();
+        assert!(matches!(
+            session.execute("BEGIN"),
+            Err(SessionError::AlreadyInTransaction)
+        ));
+        session
+            .execute("INSERT INTO counters VALUES (5, 50)")
+            .unwrap();
+        assert!(session
+            .execute("INSERT INTO counters VALUES (NULL, 60)")
+            .is_err());
+        assert!(session.in_transaction());
+        assert!(session.in_failed_transaction());
+        assert!(matches!(
+            session.execute("SELECT count(*) FROM counters"),
+            Err(SessionError::FailedTransaction)
+        ));
+        assert!(matches!(
+            session.execute("BEGIN"),
+            Err(SessionError::FailedTransaction)
+        ));
+        assert_eq!(
+            session.execute("COMMIT").unwrap(),
+            vec![QueryResult::Rollback]
+        );
+        assert!(!session.in_transaction());
+        assert!(matches!(
+            session.execute("COMMIT"),
+            Err(SessionError::NoTransaction)
+        ));
+        assert_eq!(
+            rows(&mut session, "SELECT count(*) FROM counters"),
+            vec![integers(&[3])]
+        );
+        assert!(session
+            .execute("INSERT INTO counters (id) VALUES (6, 7)")
+            .is_err());
+        assert!(!session.in_transaction());
+
+        session.execute("BEGIN").unwrap();
+        assert!(session.execute("SELEC 1").is_err());
+        assert!(session.in_failed_transaction());
+        assert_eq!(
+            session.execute("ROLLBACK").unwrap(),
+            vec![QueryResult::Rollback]
+        );
+        assert_eq!(
+            rows(&mut session, "SELECT count(*) FROM counters"),
+            vec![integers(&[3])]
+        );
+    }
+}
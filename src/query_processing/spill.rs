@@ -0,0 +1,253 @@
+use crate::datatypes::fixed_length_counts::Count;
+use crate::datatypes::fixed_length_integers::HugeInteger;
+use crate::datatypes::varbinary::Varbinary;
+use crate::datatypes::varchar::Varchar;
+use crate::datatypes::HfdbSerializableDatatype;
+use crate::file_management::file_manager::FileManager;
+use crate::file_management::temp_file::TempFile;
+use crate::query_processing::executor::{ExecutionError, Operator, Tuple};
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::Transaction;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+
+/// Partitions of partitions are spilled again up to this depth, deeper ones are kept in memory
+/// however large they are: a single key with more tuples than fit into memory cannot be split.
+pub const MAX_SPILL_DEPTH: usize = 8;
+
+const NULL_TAG: u8 = 0;
+const INTEGER_TAG: u8 = 1;
+const TEXT_TAG: u8 = 2;
+const BYTES_TAG: u8 = 3;
+
+/// Memory a sort, an aggregate or a hash join may use before spilling to temporary files. Like
+/// the cost model, queries get as much memory as the buffer pool has.
+pub fn query_memory(transaction: &Transaction) -> usize {
+    transaction.buffer_pool_size() * transaction.block_size().get()
+}
+
+/// Bytes of the encoded tuple, also used as the memory the tuple takes
+pub fn tuple_length(tuple: &[Value]) -> usize {
+    tuple
+        .iter()
+        .map(|value| {
+            1 + match value {
+                Value::Null => 0,
+                Value::Integer(value) => HugeInteger::from(*value).serialized_length(),
+                Value::Text(value) => Varchar::from(value.as_str()).serialized_length(),
+                Value::Bytes(value) => Varbinary::from(value.as_slice()).serialized_length(),
+            }
+        })
+        .sum()
+}
+
+/// Each value is a tag byte followed by the value, if not NULL
+fn encode(tuple: &[Value]) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(tuple_length(tuple));
+    for value in tuple {
+        match value {
+            Value::Null => bytes.push(NULL_TAG),
+            Value::Integer(value) => {
+                bytes.push(INTEGER_TAG);
+                append(&mut bytes, &HugeInteger::from(*value));
+            }
+            Value::Text(value) => {
+                bytes.push(TEXT_TAG);
+                append(&mut bytes, &Varchar::from(value.as_str()));
+            }
+            Value::Bytes(value) => {
+                bytes.push(BYTES_TAG);
+                append(&mut bytes, &Varbinary::from(value.as_slice()));
+            }
+        }
+    }
+    bytes
+}
+
+fn append<T: HfdbSerializableDatatype>(bytes: &mut Vec<u8>, value: &T) {
+    let offset = bytes.len();
+    bytes.resize(offset + value.serialized_length(), 0);
+    value.serialize(&mut bytes[offset..]);
+}
+
+fn decode(bytes: &[u8]) -> Tuple {
+    let mut tuple = vec![];
+    let mut offset = 0;
+    while offset < bytes.len() {
+        let tag = bytes[offset];
+        offset += 1;
+        let (value, length) = match tag {
+            NULL_TAG => (Value::Null, 0),
+            INTEGER_TAG => {
+                let value = HugeInteger::deserialize(&bytes[offset..]);
+                let length = value.serialized_length();
+                (Value::Integer(i128::from(value)), length)
+            }
+            TEXT_TAG => {
+                let value = Varchar::deserialize(&bytes[offset..]);
+                (Value::Text(String::from(&value)), value.serialized_length())
+            }
+            _ => {
+                let value = Varbinary::deserialize(&bytes[offset..]);
+                (Value::Bytes(Vec::from(&value)), value.serialized_length())
+            }
+        };
+        tuple.push(value);
+        offset += length;
+    }
+    tuple
+}
+
+/// Tuples written to a temporary file, each prefixed by its length, and read back in the same
+/// order once opened
+#[derive(Debug)]
+pub struct TupleFile {
+    file: TempFile,
+    tuple_count: usize,
+}
+
+impl TupleFile {
+    pub fn new(file_manager: &FileManager) -> Self {
+        Self {
+            file: TempFile::new(file_manager),
+            tuple_count: 0,
+        }
+    }
+
+    pub fn push(&mut self, tuple: &[Value]) -> Result<(), ExecutionError> {
+        let bytes = encode(tuple);
+        let mut length = vec![0u8; Count::from(bytes.len()).serialized_length()];
+        Count::from(bytes.len()).serialize(&mut length);
+        self.file
+            .write(&length)
+            .and_then(|_| self.file.write(&bytes))
+            .map_err(ExecutionError::IoError)?;
+        self.tuple_count += 1;
+        Ok(())
+    }
+
+    /// Number of tuples written
+    pub fn len(&self) -> usize {
+        self.tuple_count
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tuple_count == 0
+    }
+}
+
+impl Operator for TupleFile {
+    /// Starts reading at the first tuple, nothing can be written afterwards
+    fn open(&mut self) -> Result<(), ExecutionError> {
+        self.file.rewind().map_err(ExecutionError::IoError)
+    }
+
+    fn next_tuple(&mut self) -> Result<Option<Tuple>, ExecutionError> {
+        if self.file.remaining() == 0 {
+            return Ok(None);
+        }
+        let length_field = self
+            .file
+            .read(Count::from(0usize).serialized_length())
+            .map_err(ExecutionError::IoError)?;
+        let length = usize::from(&Count::deserialize(&length_field));
+        let bytes = self.file.read(length).map_err(ExecutionError::IoError)?;
+        Ok(Some(decode(&bytes)))
+    }
+}
+
+/// Memory budget of an operator and the temporary files it spills to when exceeding it
+#[derive(Debug, Clone)]
+pub struct SpillSpace {
+    file_manager: FileManager,
+    memory: usize,
+}
+
+impl SpillSpace {
+    pub fn new(transaction: &Transaction) -> Self {
+        Self::with_memory(transaction.file_manager(), query_memory(transaction))
+    }
+
+    pub fn with_memory(file_manager: &FileManager, memory: usize) -> Self {
+        Self {
+            file_manager: file_manager.clone(),
+            memory,
+        }
+    }
+
+    pub fn memory(&self) -> usize {
+        self.memory
+    }
+
+    /// Number of files read (merged runs) or written (partitions) at once: one page per file has
+    /// to fit into memory, but at least two are needed to make progress
+    pub fn fan_out(&self) -> usize {
+        (self.memory / self.file_manager.block_size.get())
+            .saturating_sub(1)
+            .max(2)
+    }
+
+    pub fn tuple_file(&self) -> TupleFile {
+        TupleFile::new(&self.file_manager)
+    }
+
+    pub fn partitions(&self) -> Vec<TupleFile> {
+        (0..self.fan_out()).map(|_| self.tuple_file()).collect()
+    }
+}
+
+/// Partition of the key, every depth distributes the keys differently, so a partition spilled
+/// again is split among all its partitions
+pub fn partition_of(key: &[Value], depth: usize, partitions: usize) -> usize {
+    let mut hasher = DefaultHasher::new();
+    depth.hash(&mut hasher);
+    key.hash(&mut hasher);
+    (hasher.finish() % partitions as u64) as usize
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_management::file_manager::FileManagerBuilder;
+    use crate::query_processing::executor::Operator;
+    use crate::query_processing::spill::{partition_of, tuple_length, SpillSpace, TupleFile};
+    use crate::record_management::value::Value;
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn test_tuple_file_round_trip_and_partitions() {
+        let file_manager = FileManagerBuilder::unittest("spill")
+            .block_size(NonZeroUsize::new(64).unwrap())
+            .build()
+            .unwrap();
+        let tuples = vec![
+            vec![Value::Null, Value::from(-42i64), Value::from("")],
+            vec![Value::Integer(i128::MIN), Value::from("x".repeat(150))],
+            vec![],
+            vec![Value::Bytes(vec![0, 255, 7]), Value::Null],
+        ];
+        let mut file = TupleFile::new(&file_manager);
+        for tuple in &tuples {
+            file.push(tuple).unwrap();
+        }
+        assert_eq!(file.len(), 4);
+        for _ in 0..2 {
+            file.open().unwrap();
+            let mut read = vec![];
+            while let Some(tuple) = file.next_tuple().unwrap() {
+                read.push(tuple);
+            }
+            assert_eq!(read, tuples);
+        }
+        assert_eq!(tuple_length(&tuples[0]), 1 + 1 + 16 + 1 + 1);
+
+        let spill_space = SpillSpace::with_memory(&file_manager, 64 * 5);
+        assert_eq!(spill_space.fan_out(), 4);
+        assert_eq!(SpillSpace::with_memory(&file_manager, 10).fan_out(), 2);
+        let key = vec![Value::from(7i64)];
+        assert_eq!(partition_of(&key, 0, 4), partition_of(&key, 0, 4));
+        let partitions_used = (0..100i64)
+            .map(|key| partition_of(&[Value::from(key)], 1, 4))
+            .collect::<std::collections::HashSet<_>>();
+        assert_eq!(partitions_used.len(), 4);
+    }
+}
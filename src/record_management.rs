@@ -0,0 +1,6 @@
+pub mod layout;
+pub mod record_page;
+pub mod schema;
+pub mod table;
+pub mod table_scan;
+pub mod value;
@@ -0,0 +1,576 @@
+use crate::datatypes::fixed_length_counts::{BigCount, Count, HugeCount, SmallCount, TinyCount};
+use crate::datatypes::fixed_length_integers::{
+    BigInteger, HugeInteger, Integer, SmallInteger, TinyInteger,
+};
+use crate::datatypes::varbinary::Varbinary;
+use crate::datatypes::varchar::Varchar;
+use crate::datatypes::varcount::Varcount;
+use crate::datatypes::varint::Varint;
+use crate::datatypes::{HfdbKeyDatatype, HfdbSerializableDatatype};
+use crate::record_management::schema::{Column, ColumnType, Schema};
+use crate::record_management::value::Value;
+use std::fmt::{Display, Formatter};
+use std::sync::Arc;
+
+/// Encoding of the rows of a schema within a record:
+/// a null bitmap (one bit per column, set for null), followed by the fixed length columns at
+/// fixed offsets (zeroed when null) and the variable length columns in schema order (left out
+/// when null).
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Layout {
+    schema: Arc<Schema>,
+    offsets: Arc<Vec<Option<usize>>>,
+    fixed_length: usize,
+}
+
+impl Layout {
+    pub fn new(schema: Schema) -> Self {
+        let null_bitmap_length = schema.columns().len().div_ceil(8);
+        let mut fixed_length = null_bitmap_length;
+        let offsets = schema
+            .columns()
+            .iter()
+            .map(|column| {
+                column.column_type.fixed_length().map(|length| {
+                    fixed_length += length;
+                    fixed_length - length
+                })
+            })
+            .collect();
+        Self {
+            schema: Arc::new(schema),
+            offsets: Arc::new(offsets),
+            fixed_length,
+        }
+    }
+
+    pub fn schema(&self) -> &Schema {
+        &self.schema
+    }
+
+    pub fn null_bitmap_length(&self) -> usize {
+        self.schema.columns().len().div_ceil(8)
+    }
+
+    /// Offset of a fixed length column within the record, None for variable length ones
+    pub fn offset(&self, name: &str) -> Option<usize> {
+        self.schema
+            .index_of(name)
+            .and_then(|index| self.offsets[index])
+    }
+
+    /// Length of the null bitmap and all fixed length columns, the minimal length of a record
+    pub fn fixed_length(&self) -> usize {
+        self.fixed_length
+    }
+
+    /// Row with all columns null
+    pub fn row(&self) -> Row {
+        Row {
+            layout: self.clone(),
+            values: vec![Value::Null; self.schema.columns().len()],
+        }
+    }
+
+    pub fn encode(&self, row: &Row) -> Result<Vec<u8>, LayoutError> {
+        let mut record = vec![0u8; self.fixed_length];
+        for (index, (column, value)) in self.schema.columns().iter().zip(&row.values).enumerate() {
+            if value.is_null() {
+                if !column.nullable {
+                    return Err(LayoutError::NotNullable(column.name.clone()));
+                }
+                record[index / 8] |= 1 << (index % 8);
+                continue;
+            }
+            let bytes = Self::serialize_value(column, value)?;
+            match self.offsets[index] {
+                Some(offset) => record[offset..offset + bytes.len()].copy_from_slice(&bytes),
+                None => record.extend(bytes),
+            }
+        }
+        Ok(record)
+    }
+
+    pub fn decode(&self, record: &[u8]) -> Row {
+        let mut variable_offset = self.fixed_length;
+        let values = self
+            .schema
+            .columns()
+            .iter()
+            .enumerate()
+            .map(|(index, column)| {
+                if record[index / 8] & (1 << (index % 8)) != 0 {
+                    return Value::Null;
+                }
+                let offset = self.offsets[index].unwrap_or(variable_offset);
+                let (value, length) =
+                    Self::deserialize_value(column.column_type, &record[offset..]);
+                if self.offsets[index].is_none() {
+                    variable_offset += length;
+                }
+                value
+            })
+            .collect();
+        Row {
+            layout: self.clone(),
+            values,
+        }
+    }
+
+    /// Order preserving (memcomparable) encoding of the row, see HfdbKeyDatatype: the columns in
+    /// schema order, each a marker byte (0 for null, so that nulls sort first) followed by the
+    /// ascending key encoding of the value. Comparing encoded rows bytewise compares them column
+    /// by column.
+    pub fn encode_key(&self, row: &Row) -> Result<Vec<u8>, LayoutError> {
+        let mut key = vec![];
+        for (column, value) in self.schema.columns().iter().zip(&row.values) {
+            let value = Self::check_value(column, value.clone())?;
+            if value.is_null() {
+                key.push(0);
+                continue;
+            }
+            key.push(1);
+            key.extend(match (column.column_type, value) {
+                (ColumnType::TinyInteger, Value::Integer(v)) => {
+                    serialize_key(TinyInteger::from(v as i8))
+                }
+                (ColumnType::SmallInteger, Value::Integer(v)) => {
+                    serialize_key(SmallInteger::from(v as i16))
+                }
+                (ColumnType::Integer, Value::Integer(v)) => serialize_key(Integer::from(v as i32)),
+                (ColumnType::BigInteger, Value::Integer(v)) => {
+                    serialize_key(BigInteger::from(v as i64))
+                }
+                (ColumnType::HugeInteger, Value::Integer(v)) => serialize_key(HugeInteger::from(v)),
+                (ColumnType::TinyCount, Value::Integer(v)) => {
+                    serialize_key(TinyCount::from(v as u8))
+                }
+                (ColumnType::SmallCount, Value::Integer(v)) => {
+                    serialize_key(SmallCount::from(v as u16))
+                }
+                (ColumnType::Count, Value::Integer(v)) => serialize_key(Count::from(v as u32)),
+                (ColumnType::BigCount, Value::Integer(v)) => {
+                    serialize_key(BigCount::from(v as u64))
+                }
+                (ColumnType::HugeCount, Value::Integer(v)) => {
+                    serialize_key(HugeCount::from(v as u128))
+                }
+                (ColumnType::Varint, Value::Integer(v)) => serialize_key(Varint::from(v as i64)),
+                (ColumnType::Varcount, Value::Integer(v)) => {
+                    serialize_key(Varcount::from(v as u64))
+                }
+                (ColumnType::Varchar, Value::Text(v)) => serialize_key(Varchar::from(v)),
+                (ColumnType::Varbinary, Value::Bytes(v)) => serialize_key(Varbinary::from(v)),
+                _ => unreachable!("values are checked against their column type"),
+            });
+        }
+        Ok(key)
+    }
+
+    pub fn decode_key(&self, key: &[u8]) -> Row {
+        let mut offset = 0;
+        let values = self
+            .schema
+            .columns()
+            .iter()
+            .map(|column| {
+                offset += 1;
+                if key[offset - 1] == 0 {
+                    return Value::Null;
+                }
+                let (value, length) =
+                    Self::deserialize_key_value(column.column_type, &key[offset..]);
+                offset += length;
+                value
+            })
+            .collect();
+        Row {
+            layout: self.clone(),
+            values,
+        }
+    }
+
+    fn serialize_value(column: &Column, value: &Value) -> Result<Vec<u8>, LayoutError> {
+        let value = Self::check_value(column, value.clone())?;
+        Ok(match (column.column_type, value) {
+            (ColumnType::TinyInteger, Value::Integer(v)) => serialize(TinyInteger::from(v as i8)),
+            (ColumnType::SmallInteger, Value::Integer(v)) => {
+                serialize(SmallInteger::from(v as i16))
+            }
+            (ColumnType::Integer, Value::Integer(v)) => serialize(Integer::from(v as i32)),
+            (ColumnType::BigInteger, Value::Integer(v)) => serialize(BigInteger::from(v as i64)),
+            (ColumnType::HugeInteger, Value::Integer(v)) => serialize(HugeInteger::from(v)),
+            (ColumnType::TinyCount, Value::Integer(v)) => serialize(TinyCount::from(v as u8)),
+            (ColumnType::SmallCount, Value::Integer(v)) => serialize(SmallCount::from(v as u16)),
+            (ColumnType::Count, Value::Integer(v)) => serialize(Count::from(v as u32)),
+            (ColumnType::BigCount, Value::Integer(v)) => serialize(BigCount::from(v as u64)),
+            (ColumnType::HugeCount, Value::Integer(v)) => serialize(HugeCount::from(v as u128)),
+            (ColumnType::Varint, Value::Integer(v)) => serialize(Varint::from(v as i64)),
+            (ColumnType::Varcount, Value::Integer(v)) => serialize(Varcount::from(v as u64)),
+            (ColumnType::Varchar, Value::Text(v)) => serialize(Varchar::from(v)),
+            (ColumnType::Varbinary, Value::Bytes(v)) => serialize(Varbinary::from(v)),
+            _ => unreachable!("values are checked against their column type"),
+        })
+    }
+
+    /// Value matching the column type and within its range
+    fn check_value(column: &Column, value: Value) -> Result<Value, LayoutError> {
+        match (&value, column.column_type.integer_range()) {
+            (Value::Null, _) if column.nullable => Ok(value),
+            (Value::Null, _) => Err(LayoutError::NotNullable(column.name.clone())),
+            (Value::Integer(v), Some((min, max))) if *v < min || *v > max => {
+                Err(LayoutError::ValueOutOfRange(column.name.clone(), value))
+            }
+            (Value::Integer(_), Some(_)) => Ok(value),
+            (Value::Text(_), None) if column.column_type == ColumnType::Varchar => Ok(value),
+            (Value::Bytes(_), None) if column.column_type == ColumnType::Varbinary => Ok(value),
+            _ => Err(LayoutError::TypeMismatch(
+                column.name.clone(),
+                column.column_type,
+                value,
+            )),
+        }
+    }
+
+    fn deserialize_value(column_type: ColumnType, buffer: &[u8]) -> (Value, usize) {
+        fn integer<T: HfdbSerializableDatatype>(
+            buffer: &[u8],
+            into: impl FnOnce(T) -> i128,
+        ) -> (Value, usize) {
+            let value = T::deserialize(buffer);
+            let length = value.serialized_length();
+            (Value::Integer(into(value)), length)
+        }
+        match column_type {
+            ColumnType::TinyInteger => integer::<TinyInteger>(buffer, |v| i8::from(v) as i128),
+            ColumnType::SmallInteger => integer::<SmallInteger>(buffer, |v| i16::from(v) as i128),
+            ColumnType::Integer => integer::<Integer>(buffer, |v| i32::from(v) as i128),
+            ColumnType::BigInteger => integer::<BigInteger>(buffer, |v| i64::from(v) as i128),
+            ColumnType::HugeInteger => integer::<HugeInteger>(buffer, i128::from),
+            ColumnType::TinyCount => integer::<TinyCount>(buffer, |v| u8::from(&v) as i128),
+            ColumnType::SmallCount => integer::<SmallCount>(buffer, |v| u16::from(&v) as i128),
+            ColumnType::Count => integer::<Count>(buffer, |v| u32::from(&v) as i128),
+            ColumnType::BigCount => integer::<BigCount>(buffer, |v| u64::from(&v) as i128),
+            ColumnType::HugeCount => integer::<HugeCount>(buffer, |v| u128::from(&v) as i128),
+            ColumnType::Varint => integer::<Varint>(buffer, |v| i64::from(&v) as i128),
+            ColumnType::Varcount => integer::<Varcount>(buffer, |v| u64::from(&v) as i128),
+            ColumnType::Varchar => {
+                let value = Varchar::deserialize(buffer);
+                (Value::Text(String::from(&value)), value.serialized_length())
+            }
+            ColumnType::Varbinary => {
+                let value = Varbinary::deserialize(buffer);
+                (Value::Bytes(Vec::from(&value)), value.serialized_length())
+            }
+        }
+    }
+
+    fn deserialize_key_value(column_type: ColumnType, buffer: &[u8]) -> (Value, usize) {
+        fn integer<T: HfdbKeyDatatype>(
+            buffer: &[u8],
+            into: impl FnOnce(T) -> i128,
+        ) -> (Value, usize) {
+            let (value, length) = T::deserialize_key_ascending(buffer);
+            (Value::Integer(into(value)), length)
+        }
+        match column_type {
+            ColumnType::TinyInteger => integer::<TinyInteger>(buffer, |v| i8::from(v) as i128),
+            ColumnType::SmallInteger => integer::<SmallInteger>(buffer, |v| i16::from(v) as i128),
+            ColumnType::Integer => integer::<Integer>(buffer, |v| i32::from(v) as i128),
+            ColumnType::BigInteger => integer::<BigInteger>(buffer, |v| i64::from(v) as i128),
+            ColumnType::HugeInteger => integer::<HugeInteger>(buffer, i128::from),
+            ColumnType::TinyCount => integer::<TinyCount>(buffer, |v| u8::from(&v) as i128),
+            ColumnType::SmallCount => integer::<SmallCount>(buffer, |v| u16::from(&v) as i128),
+            ColumnType::Count => integer::<Count>(buffer, |v| u32::from(&v) as i128),
+            ColumnType::BigCount => integer::<BigCount>(buffer, |v| u64::from(&v) as i128),
+            ColumnType::HugeCount => integer::<HugeCount>(buffer, |v| u128::from(&v) as i128),
+            ColumnType::Varint => integer::<Varint>(buffer, |v| i64::from(&v) as i128),
+            ColumnType::Varcount => integer::<Varcount>(buffer, |v| u64::from(&v) as i128),
+            ColumnType::Varchar => {
+                let (value, length) = Varchar::deserialize_key_ascending(buffer);
+                (Value::Text(String::from(&value)), length)
+            }
+            ColumnType::Varbinary => {
+                let (value, length) = Varbinary::deserialize_key_ascending(buffer);
+                (Value::Bytes(Vec::from(&value)), length)
+            }
+        }
+    }
+}
+
+fn serialize<T: HfdbSerializableDatatype>(value: T) -> Vec<u8> {
+    let mut bytes = vec![0u8; value.serialized_length()];
+    value.serialize(&mut bytes);
+    bytes
+}
+
+fn serialize_key<T: HfdbKeyDatatype>(value: T) -> Vec<u8> {
+    let mut bytes = vec![0u8; value.key_length()];
+    value.serialize_key_ascending(&mut bytes);
+    bytes
+}
+
+/// Values of one row, accessed by column name and checked against the column types on change
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Row {
+    layout: Layout,
+    values: Vec<Value>,
+}
+
+impl Row {
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn values(&self) -> &[Value] {
+        &self.values
+    }
+
+    pub fn to_bytes(&self) -> Result<Vec<u8>, LayoutError> {
+        self.layout.encode(self)
+    }
+
+    pub fn get_value(&self, name: &str) -> Result<&Value, LayoutError> {
+        Ok(&self.values[self.index_of(name)?])
+    }
+
+    pub fn is_null(&self, name: &str) -> Result<bool, LayoutError> {
+        Ok(self.get_value(name)?.is_null())
+    }
+
+    pub fn get_int(&self, name: &str) -> Result<Option<i64>, LayoutError> {
+        match self.get_value(name)? {
+            Value::Null => Ok(None),
+            Value::Integer(value) => i64::try_from(*value).map(Some).map_err(|_| {
+                LayoutError::ValueOutOfRange(name.to_string(), Value::Integer(*value))
+            }),
+            value => Err(self.type_mismatch(name, value)),
+        }
+    }
+
+    pub fn get_huge_int(&self, name: &str) -> Result<Option<i128>, LayoutError> {
+        match self.get_value(name)? {
+            Value::Null => Ok(None),
+            Value::Integer(value) => Ok(Some(*value)),
+            value => Err(self.type_mismatch(name, value)),
+        }
+    }
+
+    pub fn get_string(&self, name: &str) -> Result<Option<String>, LayoutError> {
+        match self.get_value(name)? {
+            Value::Null => Ok(None),
+            Value::Text(value) => Ok(Some(value.clone())),
+            value => Err(self.type_mismatch(name, value)),
+        }
+    }
+
+    pub fn get_bytes(&self, name: &str) -> Result<Option<Vec<u8>>, LayoutError> {
+        match self.get_value(name)? {
+            Value::Null => Ok(None),
+            Value::Bytes(value) => Ok(Some(value.clone())),
+            value => Err(self.type_mismatch(name, value)),
+        }
+    }
+
+    pub fn set_value(&mut self, name: &str, value: Value) -> Result<(), LayoutError> {
+        let index = self.index_of(name)?;
+        let column = &self.layout.schema.columns()[index];
+        self.values[index] = Layout::check_value(column, value)?;
+        Ok(())
+    }
+
+    pub fn set_null(&mut self, name: &str) -> Result<(), LayoutError> {
+        self.set_value(name, Value::Null)
+    }
+
+    pub fn set_int(&mut self, name: &str, value: i64) -> Result<(), LayoutError> {
+        self.set_value(name, Value::from(value))
+    }
+
+    pub fn set_huge_int(&mut self, name: &str, value: i128) -> Result<(), LayoutError> {
+        self.set_value(name, Value::from(value))
+    }
+
+    pub fn set_string(&mut self, name: &str, value: &str) -> Result<(), LayoutError> {
+        self.set_value(name, Value::from(value))
+    }
+
+    pub fn set_bytes(&mut self, name: &str, value: &[u8]) -> Result<(), LayoutError> {
+        self.set_value(name, Value::from(value.to_vec()))
+    }
+
+    fn index_of(&self, name: &str) -> Result<usize, LayoutError> {
+        self.layout
+            .schema
+            .index_of(name)
+            .ok_or_else(|| LayoutError::UnknownColumn(name.to_string()))
+    }
+
+    fn type_mismatch(&self, name: &str, value: &Value) -> LayoutError {
+        let column_type = self.layout.schema.columns()[self.index_of(name).unwrap()].column_type;
+        LayoutError::TypeMismatch(name.to_string(), column_type, value.clone())
+    }
+}
+
+#[derive(Debug, Eq, PartialEq)]
+pub enum LayoutError {
+    UnknownColumn(String),
+    NotNullable(String),
+    TypeMismatch(String, ColumnType, Value),
+    ValueOutOfRange(String, Value),
+}
+
+impl Display for LayoutError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::UnknownColumn(name) => write!(f, "Layout: unknown column {}", name),
+            Self::NotNullable(name) => write!(f, "Layout: column {} must not be null", name),
+            Self::TypeMismatch(name, column_type, value) => write!(
+                f,
+                "Layout: value {} does not match type {:?} of column {}",
+                value, column_type, name
+            ),
+            Self::ValueOutOfRange(name, value) => {
+                write!(
+                    f,
+                    "Layout: value {} out of range for column {}",
+                    value, name
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::record_management::layout::{Layout, LayoutError};
+    use crate::record_management::schema::{ColumnType, Schema};
+    use crate::record_management::value::Value;
+
+    fn layout() -> Layout {
+        Layout::new(
+            Schema::new()
+                .with_column("id", ColumnType::Integer)
+                .with_nullable_column("name", ColumnType::Varchar)
+                .with_column("counter", ColumnType::BigCount)
+                .with_nullable_column("payload", ColumnType::Varbinary)
+                .with_column("delta", ColumnType::Varint)
+                .with_nullable_column("small", ColumnType::TinyInteger),
+        )
+    }
+
+    #[test]
+    fn test_layout_offsets() {
+        let layout = layout();
+        assert_eq!(layout.null_bitmap_length(), 1);
+        assert_eq!(layout.offset("id"), Some(1));
+        assert_eq!(layout.offset("name"), None);
+        assert_eq!(layout.offset("counter"), Some(5));
+        assert_eq!(layout.offset("small"), Some(13));
+        assert_eq!(layout.fixed_length(), 14);
+    }
+
+    #[test]
+    fn test_layout_encode_decode_rows() {
+        let layout = layout();
+        let mut row = layout.row();
+        row.set_int("id", 42).unwrap();
+        row.set_string("name", "Hanfried").unwrap();
+        row.set_int("counter", 4711).unwrap();
+        row.set_int("delta", i64::MIN).unwrap();
+        let bytes = row.to_bytes().unwrap();
+        let decoded = layout.decode(&bytes);
+        assert_eq!(decoded, row);
+        assert_eq!(decoded.get_int("id").unwrap(), Some(42));
+        assert_eq!(
+            decoded.get_string("name").unwrap(),
+            Some("Hanfried".to_string())
+        );
+        assert_eq!(decoded.get_bytes("payload").unwrap(), None);
+        assert_eq!(decoded.get_int("delta").unwrap(), Some(i64::MIN));
+        assert!(decoded.is_null("small").unwrap());
+
+        let mut row = decoded;
+        row.set_null("name").unwrap();
+        row.set_bytes("payload", &[1, 2, 3]).unwrap();
+        row.set_int("small", -7).unwrap();
+        let decoded = layout.decode(&row.to_bytes().unwrap());
+        assert_eq!(decoded.get_string("name").unwrap(), None);
+        assert_eq!(decoded.get_bytes("payload").unwrap(), Some(vec![1, 2, 3]));
+        assert_eq!(decoded.get_int("small").unwrap(), Some(-7));
+        assert_eq!(decoded.get_value("delta").unwrap(), &Value::from(i64::MIN));
+    }
+
+    #[test]
+    fn test_layout_encode_decode_keys() {
+        let layout = Layout::new(
+            Schema::new()
+                .with_nullable_column("name", ColumnType::Varchar)
+                .with_column("delta", ColumnType::Varint),
+        );
+        let mut rows = vec![];
+        for (name, delta) in [
+            (None, 0),
+            (Some(""), i64::MIN),
+            (Some(""), -1),
+            (Some(""), 1),
+            (Some("a"), -300),
+            (Some("a\0"), -300),
+            (Some("b"), i64::MIN),
+        ] {
+            let mut row = layout.row();
+            if let Some(name) = name {
+                row.set_string("name", name).unwrap();
+            }
+            row.set_int("delta", delta).unwrap();
+            rows.push(row);
+        }
+        let keys: Vec<Vec<u8>> = rows
+            .iter()
+            .map(|row| layout.encode_key(row).unwrap())
+            .collect();
+        for (row, key) in rows.iter().zip(&keys) {
+            assert_eq!(&layout.decode_key(key), row);
+        }
+        assert!(keys.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(
+            layout.encode_key(&layout.row()),
+            Err(LayoutError::NotNullable("delta".to_string()))
+        );
+    }
+
+    #[test]
+    fn test_layout_checks_values() {
+        let layout = layout();
+        let mut row = layout.row();
+        assert_eq!(
+            row.to_bytes(),
+            Err(LayoutError::NotNullable("id".to_string()))
+        );
+        assert_eq!(
+            row.set_null("id"),
+            Err(LayoutError::NotNullable("id".to_string()))
+        );
+        assert!(matches!(
+            row.set_string("id", "42"),
+            Err(LayoutError::TypeMismatch(_, ColumnType::Integer, _))
+        ));
+        assert!(matches!(
+            row.set_int("counter", -1),
+            Err(LayoutError::ValueOutOfRange(_, _))
+        ));
+        assert!(matches!(
+            row.set_int("small", 128),
+            Err(LayoutError::ValueOutOfRange(_, _))
+        ));
+        assert_eq!(
+            row.get_int("unknown"),
+            Err(LayoutError::UnknownColumn("unknown".to_string()))
+        );
+        row.set_int("id", 1).unwrap();
+        assert!(matches!(
+            row.get_string("id"),
+            Err(LayoutError::TypeMismatch(_, _, _))
+        ));
+    }
+}
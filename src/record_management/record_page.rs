@@ -0,0 +1,496 @@
+use crate::datatypes::fixed_length_counts::Count;
+use crate::file_management::block_id::BlockId;
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use log::debug;
+use std::fmt::{Display, Formatter};
+use std::num::NonZeroUsize;
+
+const SLOT_COUNT_OFFSET: usize = 0;
+const RECORDS_LENGTH_OFFSET: usize = 4;
+const HEADER_LENGTH: usize = 8;
+const SLOT_LENGTH: usize = 8;
+
+/// Position of a record in the slot directory of its page, stays the same when the record is
+/// moved within the page
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash, Ord, PartialOrd)]
+pub struct SlotId(usize);
+
+impl From<usize> for SlotId {
+    fn from(value: usize) -> Self {
+        Self(value)
+    }
+}
+
+impl From<SlotId> for usize {
+    fn from(value: SlotId) -> Self {
+        value.0
+    }
+}
+
+impl Display for SlotId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+struct Slot {
+    offset: usize,
+    length: usize,
+}
+
+impl Slot {
+    const DELETED: Slot = Slot {
+        offset: 0,
+        length: 0,
+    };
+
+    fn is_deleted(&self) -> bool {
+        self.offset == 0
+    }
+}
+
+/// Slotted page of variable length records within one block, read and changed through a
+/// transaction.
+///
+/// Layout: a header with the number of slots and the length of the record area, followed by the
+/// slot directory growing towards the end of the block, each slot holding offset and length of
+/// its record (offset 0 for a deleted slot). The records are stored at the end of the block,
+/// growing towards the beginning. A block never written before (all zeros) is an empty page.
+///
+/// Changes take the exclusive lock and latch of the block. Reads only take the shared latch, so
+/// readers never block writers, which are visible to readers immediately. Readers decide with
+/// the help of record version headers which records they see (see mvcc).
+#[derive(Debug)]
+pub struct RecordPage {
+    transaction: Transaction,
+    block: BlockId,
+    block_size: usize,
+}
+
+impl RecordPage {
+    /// Pins the block until the record page is dropped
+    pub fn new(transaction: &Transaction, block: &BlockId) -> Result<Self, RecordPageError> {
+        transaction
+            .pin(block)
+            .map_err(RecordPageError::TransactionError)?;
+        Ok(Self {
+            transaction: transaction.clone(),
+            block: block.clone(),
+            block_size: usize::from(transaction.block_size()),
+        })
+    }
+
+    pub fn block(&self) -> &BlockId {
+        &self.block
+    }
+
+    /// Length of the largest record fitting into an empty page
+    pub fn max_record_length(block_size: NonZeroUsize) -> usize {
+        usize::from(block_size) - HEADER_LENGTH - SLOT_LENGTH
+    }
+
+    pub fn get(&self, slot_id: SlotId) -> Result<Vec<u8>, RecordPageError> {
+        self.latched_read(|| {
+            let slot = self.live_slot(slot_id)?;
+            self.transaction
+                .read_raw_bytes(&self.block, slot.offset, slot.length)
+                .map_err(RecordPageError::TransactionError)
+        })
+    }
+
+    /// Ids of all slots holding a record, in ascending order
+    pub fn slots(&self) -> Result<Vec<SlotId>, RecordPageError> {
+        self.latched_read(|| {
+            let mut slot_ids = vec![];
+            for slot_nr in 0..self.slot_count()? {
+                if !self.slot(slot_nr)?.is_deleted() {
+                    slot_ids.push(SlotId(slot_nr));
+                }
+            }
+            Ok(slot_ids)
+        })
+    }
+
+    /// Bytes available for records and their slots, including the space compaction would regain
+    pub fn free_space(&self) -> Result<usize, RecordPageError> {
+        self.latched_read(|| {
+            let slot_count = self.slot_count()?;
+            Ok(self.block_size - Self::directory_end(slot_count) - self.live_length()?)
+        })
+    }
+
+    /// Stores the record in the first deleted slot or a new one, compacting the page if its free
+    /// space is fragmented
+    pub fn insert(&self, record: &[u8]) -> Result<SlotId, RecordPageError> {
+        self.latched_write(|| {
+            let slot_count = self.slot_count()?;
+            let mut slot_nr = slot_count;
+            for nr in 0..slot_count {
+                if self.slot(nr)?.is_deleted() {
+                    slot_nr = nr;
+                    break;
+                }
+            }
+            let slot_count = slot_count.max(slot_nr + 1);
+            let offset = self.allocate(record.len(), slot_count)?;
+            self.set_raw_bytes(offset, record)?;
+            self.set_slot(
+                slot_nr,
+                Slot {
+                    offset,
+                    length: record.len(),
+                },
+            )?;
+            self.set_count(SLOT_COUNT_OFFSET, slot_count)?;
+            debug!(
+                "RecordPage {:?}: inserted {} bytes into slot {}",
+                self.block,
+                record.len(),
+                slot_nr
+            );
+            Ok(SlotId(slot_nr))
+        })
+    }
+
+    /// Overwrites the record in place if the new one is not longer, otherwise the record is moved
+    /// within the page
+    pub fn update(&self, slot_id: SlotId, record: &[u8]) -> Result<(), RecordPageError> {
+        self.latched_write(|| {
+            let slot = self.live_slot(slot_id)?;
+            if record.len() <= slot.length {
+                self.set_raw_bytes(slot.offset, record)?;
+                return self.set_slot(
+                    slot_id.0,
+                    Slot {
+                        offset: slot.offset,
+                        length: record.len(),
+                    },
+                );
+            }
+            let slot_count = self.slot_count()?;
+            let live_length = self.live_length()? - slot.length;
+            if Self::directory_end(slot_count) + live_length + record.len() > self.block_size {
+                return Err(RecordPageError::PageFull(self.block.clone()));
+            }
+            self.set_slot(slot_id.0, Slot::DELETED)?;
+            let offset = self.allocate(record.len(), slot_count)?;
+            self.set_raw_bytes(offset, record)?;
+            self.set_slot(
+                slot_id.0,
+                Slot {
+                    offset,
+                    length: record.len(),
+                },
+            )
+        })
+    }
+
+    pub fn delete(&self, slot_id: SlotId) -> Result<(), RecordPageError> {
+        self.latched_write(|| {
+            self.live_slot(slot_id)?;
+            self.set_slot(slot_id.0, Slot::DELETED)
+        })
+    }
+
+    /// Moves all records to the end of the block, so the free space is contiguous again
+    pub fn compact(&self) -> Result<(), RecordPageError> {
+        self.latched_write(|| self.compact_records())
+    }
+
+    fn latched_read<R>(
+        &self,
+        reader: impl FnOnce() -> Result<R, RecordPageError>,
+    ) -> Result<R, RecordPageError> {
+        self.transaction
+            .read_latched(&self.block, reader)
+            .map_err(RecordPageError::TransactionError)?
+    }
+
+    fn latched_write<R>(
+        &self,
+        writer: impl FnOnce() -> Result<R, RecordPageError>,
+    ) -> Result<R, RecordPageError> {
+        self.transaction
+            .write_latched(&self.block, writer)
+            .map_err(RecordPageError::TransactionError)?
+    }
+
+    fn directory_end(slot_count: usize) -> usize {
+        HEADER_LENGTH + slot_count * SLOT_LENGTH
+    }
+
+    /// Reserves length bytes at the beginning of the record area, returns their offset
+    fn allocate(&self, length: usize, slot_count: usize) -> Result<usize, RecordPageError> {
+        let mut records_length = self.count(RECORDS_LENGTH_OFFSET)?;
+        let directory_end = Self::directory_end(slot_count);
+        if directory_end + records_length + length > self.block_size {
+            let live_length = self.live_length()?;
+            if directory_end + live_length + length > self.block_size {
+                return Err(RecordPageError::PageFull(self.block.clone()));
+            }
+            self.compact_records()?;
+            records_length = live_length;
+        }
+        records_length += length;
+        self.set_count(RECORDS_LENGTH_OFFSET, records_length)?;
+        Ok(self.block_size - records_length)
+    }
+
+    fn compact_records(&self) -> Result<(), RecordPageError> {
+        let records_length = self.count(RECORDS_LENGTH_OFFSET)?;
+        let live_length = self.live_length()?;
+        if live_length == records_length {
+            return Ok(());
+        }
+        let mut records = Vec::with_capacity(live_length);
+        let mut slots = vec![];
+        for slot_nr in 0..self.slot_count()? {
+            let slot = self.slot(slot_nr)?;
+            if !slot.is_deleted() {
+                let offset = self.block_size - live_length + records.len();
+                records.extend(self.read_raw_bytes(slot.offset, slot.length)?);
+                slots.push((
+                    slot_nr,
+                    Slot {
+                        offset,
+                        length: slot.length,
+                    },
+                ));
+            }
+        }
+        self.set_raw_bytes(self.block_size - live_length, &records)?;
+        for (slot_nr, slot) in slots {
+            self.set_slot(slot_nr, slot)?;
+        }
+        self.set_count(RECORDS_LENGTH_OFFSET, live_length)?;
+        debug!(
+            "RecordPage {:?}: compacted records from {} to {} bytes",
+            self.block, records_length, live_length
+        );
+        Ok(())
+    }
+
+    fn live_length(&self) -> Result<usize, RecordPageError> {
+        let mut live_length = 0;
+        for slot_nr in 0..self.slot_count()? {
+            live_length += self.slot(slot_nr)?.length;
+        }
+        Ok(live_length)
+    }
+
+    fn slot_count(&self) -> Result<usize, RecordPageError> {
+        self.count(SLOT_COUNT_OFFSET)
+    }
+
+    fn live_slot(&self, slot_id: SlotId) -> Result<Slot, RecordPageError> {
+        if slot_id.0 < self.slot_count()? {
+            let slot = self.slot(slot_id.0)?;
+            if !slot.is_deleted() {
+                return Ok(slot);
+            }
+        }
+        Err(RecordPageError::SlotNotFound(self.block.clone(), slot_id))
+    }
+
+    fn slot(&self, slot_nr: usize) -> Result<Slot, RecordPageError> {
+        let slot_offset = Self::directory_end(slot_nr);
+        Ok(Slot {
+            offset: self.count(slot_offset)?,
+            length: self.count(slot_offset + 4)?,
+        })
+    }
+
+    fn set_slot(&self, slot_nr: usize, slot: Slot) -> Result<(), RecordPageError> {
+        let slot_offset = Self::directory_end(slot_nr);
+        self.set_count(slot_offset, slot.offset)?;
+        self.set_count(slot_offset + 4, slot.length)
+    }
+
+    fn count(&self, offset: usize) -> Result<usize, RecordPageError> {
+        self.transaction
+            .read::<Count>(&self.block, offset)
+            .map(|count| usize::from(&count))
+            .map_err(RecordPageError::TransactionError)
+    }
+
+    fn set_count(&self, offset: usize, count: usize) -> Result<(), RecordPageError> {
+        self.transaction
+            .set(&self.block, offset, &Count::from(count))
+            .map_err(RecordPageError::TransactionError)
+    }
+
+    fn read_raw_bytes(&self, offset: usize, length: usize) -> Result<Vec<u8>, RecordPageError> {
+        self.transaction
+            .read_raw_bytes(&self.block, offset, length)
+            .map_err(RecordPageError::TransactionError)
+    }
+
+    fn set_raw_bytes(&self, offset: usize, value: &[u8]) -> Result<(), RecordPageError> {
+        self.transaction
+            .set_raw_bytes(&self.block, offset, value)
+            .map_err(RecordPageError::TransactionError)
+    }
+}
+
+impl Drop for RecordPage {
+    fn drop(&mut self) {
+        // After commit or rollback the transaction has already unpinned all its blocks
+        let _ = self.transaction.unpin(&self.block);
+    }
+}
+
+#[derive(Debug)]
+pub enum RecordPageError {
+    TransactionError(TransactionError),
+    PageFull(BlockId),
+    SlotNotFound(BlockId, SlotId),
+}
+
+impl Display for RecordPageError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TransactionError(e) => write!(f, "RecordPage {}", e),
+            Self::PageFull(block) => write!(f, "RecordPage: no space left in {:?}", block),
+            Self::SlotNotFound(block, slot_id) => {
+                write!(
+                    f,
+                    "RecordPage: no record in slot {} of {:?}",
+                    slot_id, block
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datatypes::varchar::Varchar;
+    use crate::datatypes::HfdbSerializableDatatype;
+    use crate::db_management_system::hfdb::{HanfriedDb, HanfriedDbBuilder};
+    use crate::file_management::block_id::DbFilename;
+    use crate::record_management::record_page::{RecordPage, RecordPageError, SlotId};
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+
+    fn hfdb(sub_directory_name: &str) -> HanfriedDb {
+        HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .buffer_manager(|bm| bm.pool_size(3))
+            .build()
+            .unwrap()
+    }
+
+    fn varchar(value: &str) -> Vec<u8> {
+        let varchar = Varchar::from(value);
+        let mut bytes = vec![0u8; varchar.serialized_length()];
+        varchar.serialize(&mut bytes);
+        bytes
+    }
+
+    fn string(bytes: Vec<u8>) -> String {
+        String::from(&Varchar::deserialize(&bytes))
+    }
+
+    #[test]
+    fn test_record_page_insert_update_delete() {
+        init_logging();
+        let hfdb = hfdb("record_page");
+        let block = hfdb
+            .file_manager
+            .append(&DbFilename::from("record_page.tbl"))
+            .unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let page = RecordPage::new(&tx, &block).unwrap();
+        assert!(page.slots().unwrap().is_empty());
+        assert_eq!(page.free_space().unwrap(), 92);
+
+        let first = page.insert(&varchar("first")).unwrap();
+        let second = page.insert(&varchar("second")).unwrap();
+        let third = page.insert(&varchar("third")).unwrap();
+        assert_eq!(
+            (first, second, third),
+            (SlotId::from(0), SlotId::from(1), SlotId::from(2))
+        );
+        assert_eq!(string(page.get(second).unwrap()), "second");
+
+        page.update(second, &varchar("2nd")).unwrap();
+        assert_eq!(string(page.get(second).unwrap()), "2nd");
+        page.update(first, &varchar("the relocated first")).unwrap();
+        assert_eq!(string(page.get(first).unwrap()), "the relocated first");
+
+        page.delete(second).unwrap();
+        assert!(matches!(
+            page.get(second),
+            Err(RecordPageError::SlotNotFound(_, _))
+        ));
+        assert_eq!(page.slots().unwrap(), vec![first, third]);
+        assert_eq!(page.insert(&varchar("reused")).unwrap(), second);
+        drop(page);
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let page = RecordPage::new(&tx, &block).unwrap();
+        page.delete(first).unwrap();
+        page.insert(&varchar("rolled back")).unwrap();
+        tx.rollback().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let page = RecordPage::new(&tx, &block).unwrap();
+        assert_eq!(page.slots().unwrap(), vec![first, second, third]);
+        assert_eq!(string(page.get(first).unwrap()), "the relocated first");
+        assert_eq!(string(page.get(second).unwrap()), "reused");
+        assert_eq!(string(page.get(third).unwrap()), "third");
+        drop(page);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_record_page_compaction_and_page_full() {
+        init_logging();
+        let hfdb = hfdb("record_page_compaction");
+        let block = hfdb
+            .file_manager
+            .append(&DbFilename::from("record_page.tbl"))
+            .unwrap();
+        let block_size = NonZeroUsize::new(100).unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let page = RecordPage::new(&tx, &block).unwrap();
+        let too_large = vec![1u8; RecordPage::max_record_length(block_size) + 1];
+        assert!(matches!(
+            page.insert(&too_large),
+            Err(RecordPageError::PageFull(_))
+        ));
+
+        let slots: Vec<SlotId> = (0..3u8).map(|nr| page.insert(&[nr; 20]).unwrap()).collect();
+        assert_eq!(page.free_space().unwrap(), 8);
+        assert!(matches!(
+            page.insert(&[9; 20]),
+            Err(RecordPageError::PageFull(_))
+        ));
+
+        // Only fits when the space of the deleted records in front and behind is reused
+        page.delete(slots[0]).unwrap();
+        page.delete(slots[2]).unwrap();
+        let large = page.insert(&[7; 40]).unwrap();
+        assert_eq!(large, slots[0]);
+        assert_eq!(page.get(large).unwrap(), vec![7; 40]);
+        assert_eq!(page.get(slots[1]).unwrap(), vec![1; 20]);
+        assert!(matches!(
+            page.update(slots[1], &[1; 45]),
+            Err(RecordPageError::PageFull(_))
+        ));
+        page.update(slots[1], &[1; 28]).unwrap();
+        assert_eq!(page.get(slots[1]).unwrap(), vec![1; 28]);
+        assert_eq!(page.free_space().unwrap(), 0);
+
+        page.delete(large).unwrap();
+        page.compact().unwrap();
+        assert_eq!(page.slots().unwrap(), vec![slots[1]]);
+        assert_eq!(page.get(slots[1]).unwrap(), vec![1; 28]);
+        drop(page);
+        tx.commit().unwrap();
+    }
+}
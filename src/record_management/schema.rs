@@ -0,0 +1,173 @@
+/// Column types, each stored as the [crate::datatypes] type of the same name
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+pub enum ColumnType {
+    TinyInteger,
+    SmallInteger,
+    Integer,
+    BigInteger,
+    HugeInteger,
+    TinyCount,
+    SmallCount,
+    Count,
+    BigCount,
+    HugeCount,
+    Varint,
+    Varcount,
+    Varchar,
+    Varbinary,
+}
+
+impl ColumnType {
+    /// Serialized length of fixed length types, None for variable length ones
+    pub fn fixed_length(&self) -> Option<usize> {
+        match self {
+            ColumnType::TinyInteger | ColumnType::TinyCount => Some(1),
+            ColumnType::SmallInteger | ColumnType::SmallCount => Some(2),
+            ColumnType::Integer | ColumnType::Count => Some(4),
+            ColumnType::BigInteger | ColumnType::BigCount => Some(8),
+            ColumnType::HugeInteger | ColumnType::HugeCount => Some(16),
+            ColumnType::Varint
+            | ColumnType::Varcount
+            | ColumnType::Varchar
+            | ColumnType::Varbinary => None,
+        }
+    }
+
+    pub const ALL: [ColumnType; 14] = [
+        ColumnType::TinyInteger,
+        ColumnType::SmallInteger,
+        ColumnType::Integer,
+        ColumnType::BigInteger,
+        ColumnType::HugeInteger,
+        ColumnType::TinyCount,
+        ColumnType::SmallCount,
+        ColumnType::Count,
+        ColumnType::BigCount,
+        ColumnType::HugeCount,
+        ColumnType::Varint,
+        ColumnType::Varcount,
+        ColumnType::Varchar,
+        ColumnType::Varbinary,
+    ];
+
+    /// Name the type is stored with in the catalog
+    pub fn name(&self) -> &'static str {
+        match self {
+            ColumnType::TinyInteger => "TinyInteger",
+            ColumnType::SmallInteger => "SmallInteger",
+            ColumnType::Integer => "Integer",
+            ColumnType::BigInteger => "BigInteger",
+            ColumnType::HugeInteger => "HugeInteger",
+            ColumnType::TinyCount => "TinyCount",
+            ColumnType::SmallCount => "SmallCount",
+            ColumnType::Count => "Count",
+            ColumnType::BigCount => "BigCount",
+            ColumnType::HugeCount => "HugeCount",
+            ColumnType::Varint => "Varint",
+            ColumnType::Varcount => "Varcount",
+            ColumnType::Varchar => "Varchar",
+            ColumnType::Varbinary => "Varbinary",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<ColumnType> {
+        Self::ALL
+            .into_iter()
+            .find(|column_type| column_type.name() == name)
+    }
+
+    /// Range of integer values the type can hold, None for non integer types
+    pub fn integer_range(&self) -> Option<(i128, i128)> {
+        match self {
+            ColumnType::TinyInteger => Some((i8::MIN as i128, i8::MAX as i128)),
+            ColumnType::SmallInteger => Some((i16::MIN as i128, i16::MAX as i128)),
+            ColumnType::Integer => Some((i32::MIN as i128, i32::MAX as i128)),
+            ColumnType::BigInteger | ColumnType::Varint => {
+                Some((i64::MIN as i128, i64::MAX as i128))
+            }
+            ColumnType::HugeInteger => Some((i128::MIN, i128::MAX)),
+            ColumnType::TinyCount => Some((0, u8::MAX as i128)),
+            ColumnType::SmallCount => Some((0, u16::MAX as i128)),
+            ColumnType::Count => Some((0, u32::MAX as i128)),
+            ColumnType::BigCount | ColumnType::Varcount => Some((0, u64::MAX as i128)),
+            ColumnType::HugeCount => Some((0, i128::MAX)),
+            ColumnType::Varchar | ColumnType::Varbinary => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Column {
+    pub name: String,
+    pub column_type: ColumnType,
+    pub nullable: bool,
+}
+
+/// Named and typed columns of a table, in the order of their definition
+#[derive(Debug, Clone, Default, Eq, PartialEq)]
+pub struct Schema {
+    columns: Vec<Column>,
+}
+
+impl Schema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_column(self, name: &str, column_type: ColumnType) -> Self {
+        self.with(name, column_type, false)
+    }
+
+    pub fn with_nullable_column(self, name: &str, column_type: ColumnType) -> Self {
+        self.with(name, column_type, true)
+    }
+
+    fn with(mut self, name: &str, column_type: ColumnType, nullable: bool) -> Self {
+        self.add_column(Column {
+            name: name.to_string(),
+            column_type,
+            nullable,
+        });
+        self
+    }
+
+    pub fn add_column(&mut self, column: Column) {
+        self.columns.push(column);
+    }
+
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    pub fn index_of(&self, name: &str) -> Option<usize> {
+        self.columns.iter().position(|column| column.name == name)
+    }
+
+    pub fn column(&self, name: &str) -> Option<&Column> {
+        self.index_of(name).map(|index| &self.columns[index])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::record_management::schema::{ColumnType, Schema};
+
+    #[test]
+    fn test_schema_columns() {
+        let schema = Schema::new()
+            .with_column("id", ColumnType::Integer)
+            .with_nullable_column("name", ColumnType::Varchar);
+        assert_eq!(schema.columns().len(), 2);
+        assert_eq!(schema.index_of("name"), Some(1));
+        assert!(schema.column("name").unwrap().nullable);
+        assert!(!schema.column("id").unwrap().nullable);
+        assert_eq!(schema.column("unknown"), None);
+        assert_eq!(ColumnType::Integer.fixed_length(), Some(4));
+        assert_eq!(ColumnType::Varchar.fixed_length(), None);
+        assert_eq!(ColumnType::TinyCount.integer_range(), Some((0, 255)));
+        for column_type in ColumnType::ALL {
+            assert_eq!(ColumnType::from_name(column_type.name()), Some(column_type));
+        }
+        assert_eq!(ColumnType::from_name("Unknown"), None);
+    }
+}
@@ -0,0 +1,523 @@
+use crate::index_management::index::{Index, IndexError};
+use crate::metadata_management::catalog::{Catalog, CatalogError, IndexInfo, IndexType, TableInfo};
+use crate::record_management::layout::{Layout, LayoutError, Row};
+use crate::record_management::table_scan::{RecordId, TableScan, TableScanError};
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::Transaction;
+use log::debug;
+use std::collections::HashSet;
+use std::fmt::{Display, Formatter};
+use std::ops::{Bound, RangeBounds};
+
+/// Table registered in the catalog, keeping its indexes up to date when rows are inserted or
+/// updated.
+///
+/// Indexes hold entries for all record versions: deleting a row leaves its entries, as older
+/// snapshots may still need them. The entries of a version are removed together with it, by
+/// vacuum or when its page gets full. Index lookups check the key of every record found, as
+/// entries of versions removed by other transactions may still be seen.
+#[derive(Debug)]
+pub struct Table {
+    transaction: Transaction,
+    info: TableInfo,
+    heap: TableScan,
+    indexes: Vec<(IndexInfo, Index)>,
+}
+
+impl Table {
+    pub fn open(
+        transaction: &Transaction,
+        catalog: &Catalog,
+        name: &str,
+    ) -> Result<Self, TableError> {
+        let info = catalog
+            .table(transaction, name)
+            .map_err(TableError::CatalogError)?
+            .ok_or_else(|| TableError::TableNotFound(name.to_string()))?;
+        let index_infos = catalog
+            .indexes(transaction, name)
+            .map_err(TableError::CatalogError)?;
+        Self::new(transaction, info, index_infos)
+    }
+
+    /// Table with the given indexes, which have to be registered in the catalog for it
+    pub fn new(
+        transaction: &Transaction,
+        info: TableInfo,
+        index_infos: Vec<IndexInfo>,
+    ) -> Result<Self, TableError> {
+        let mut indexes = vec![];
+        for index_info in index_infos {
+            let index =
+                Index::open(transaction, &index_info, &info).map_err(TableError::IndexError)?;
+            indexes.push((index_info, index));
+        }
+        Ok(Self {
+            transaction: transaction.clone(),
+            heap: TableScan::new(transaction, &info.filename, &info.layout).collecting_removed(),
+            info,
+            indexes,
+        })
+    }
+
+    pub fn info(&self) -> &TableInfo {
+        &self.info
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.info.layout
+    }
+
+    pub fn indexes(&self) -> impl Iterator<Item = &IndexInfo> {
+        self.indexes.iter().map(|(index_info, _)| index_info)
+    }
+
+    /// Sequential scan of the rows visible for the transaction
+    pub fn scan(&self) -> TableScan {
+        TableScan::new(&self.transaction, &self.info.filename, &self.info.layout)
+    }
+
+    pub fn get(&self, record_id: &RecordId) -> Result<Option<Row>, TableError> {
+        self.heap.get(record_id).map_err(TableError::TableScanError)
+    }
+
+    pub fn insert(&mut self, row: &Row) -> Result<RecordId, TableError> {
+        let record_id = self.heap.insert(row).map_err(TableError::TableScanError)?;
+        self.unindex_removed()?;
+        self.index_row(&record_id, row)?;
+        Ok(record_id)
+    }
+
+    /// Replaces the row by a new version, returns its record id
+    pub fn update(&mut self, record_id: &RecordId, row: &Row) -> Result<RecordId, TableError> {
+        let record_id = self
+            .heap
+            .update(record_id, row)
+            .map_err(TableError::TableScanError)?;
+        self.unindex_removed()?;
+        self.index_row(&record_id, row)?;
+        Ok(record_id)
+    }
+
+    pub fn delete(&mut self, record_id: &RecordId) -> Result<(), TableError> {
+        self.heap
+            .delete(record_id)
+            .map_err(TableError::TableScanError)
+    }
+
+    /// Creates the index in the catalog and adds the entries of all existing record versions
+    pub fn create_index(
+        &mut self,
+        catalog: &Catalog,
+        name: &str,
+        column_name: &str,
+        index_type: IndexType,
+    ) -> Result<IndexInfo, TableError> {
+        let index_info = catalog
+            .create_index(
+                &self.transaction,
+                name,
+                &self.info.name,
+                column_name,
+                index_type,
+            )
+            .map_err(TableError::CatalogError)?;
+        let index = Index::open(&self.transaction, &index_info, &self.info)
+            .map_err(TableError::IndexError)?;
+        let mut scan = self.scan().all_versions();
+        while scan.next_row().map_err(TableError::TableScanError)? {
+            let key = Self::key(scan.row().unwrap(), column_name)?;
+            index
+                .insert(&key, scan.record_id().unwrap())
+                .map_err(TableError::IndexError)?;
+        }
+        self.indexes.push((index_info.clone(), index));
+        Ok(index_info)
+    }
+
+    /// Visible rows with the key, found via the index
+    pub fn lookup(
+        &self,
+        index_name: &str,
+        key: &Value,
+    ) -> Result<Vec<(RecordId, Row)>, TableError> {
+        let (index_info, index) = self.index(index_name)?;
+        let entries = index.lookup(key).map_err(TableError::IndexError)?;
+        self.visible_rows(index_info, entries)
+    }
+
+    /// Visible rows with keys within the bounds, found via the index, in key order
+    pub fn range<'a>(
+        &self,
+        index_name: &str,
+        bounds: impl RangeBounds<&'a Value>,
+    ) -> Result<Vec<(RecordId, Row)>, TableError> {
+        let (index_info, index) = self.index(index_name)?;
+        let entries = index
+            .range(
+                bounds.start_bound().map(|key| *key),
+                bounds.end_bound().map(|key| *key),
+            )
+            .map_err(TableError::IndexError)?;
+        self.visible_rows(index_info, entries)
+    }
+
+    /// Visible rows with keys within the bounds, found via the index, in key order: the first
+    /// limit ones and the others with the key of the last one, so that a scan can go on after it
+    pub fn range_limited<'a>(
+        &self,
+        index_name: &str,
+        bounds: impl RangeBounds<&'a Value>,
+        limit: usize,
+    ) -> Result<Vec<(RecordId, Row)>, TableError> {
+        let (index_info, index) = self.index(index_name)?;
+        let mut lower = bounds.start_bound().map(|key| (*key).clone());
+        let upper = bounds.end_bound().map(|key| *key);
+        let mut rows = vec![];
+        while rows.len() < limit {
+            let wanted = limit - rows.len();
+            let entries = index
+                .range_limited(lower.as_ref(), upper, wanted)
+                .map_err(TableError::IndexError)?;
+            let exhausted = entries.len() < wanted;
+            if let Some((key, _)) = entries.last() {
+                lower = Bound::Excluded(key.clone());
+            }
+            rows.extend(self.visible_rows(index_info, entries)?);
+            if exhausted {
+                break;
+            }
+        }
+        Ok(rows)
+    }
+
+    fn visible_rows(
+        &self,
+        index_info: &IndexInfo,
+        entries: Vec<(Value, RecordId)>,
+    ) -> Result<Vec<(RecordId, Row)>, TableError> {
+        let mut found = HashSet::new();
+        let mut rows = vec![];
+        for (key, record_id) in entries {
+            if let Some(row) = self.get(&record_id)? {
+                if Self::key(&row, &index_info.column_name)? == key
+                    && found.insert(record_id.clone())
+                {
+                    rows.push((record_id, row));
+                }
+            }
+        }
+        Ok(rows)
+    }
+
+    /// Removes the record versions no snapshot can see anymore and their index entries, returns
+    /// the number of removed versions
+    pub fn vacuum(&mut self) -> Result<usize, TableError> {
+        let removed = self.heap.vacuum().map_err(TableError::TableScanError)?;
+        self.unindex_removed()?;
+        Ok(removed)
+    }
+
+    fn index(&self, name: &str) -> Result<&(IndexInfo, Index), TableError> {
+        self.indexes
+            .iter()
+            .find(|(index_info, _)| index_info.name == name)
+            .ok_or_else(|| TableError::IndexNotFound(name.to_string()))
+    }
+
+    fn index_row(&self, record_id: &RecordId, row: &Row) -> Result<(), TableError> {
+        for (index_info, index) in &self.indexes {
+            index
+                .insert(&Self::key(row, &index_info.column_name)?, record_id)
+                .map_err(TableError::IndexError)?;
+        }
+        Ok(())
+    }
+
+    /// Removes the index entries of the versions the heap removed as garbage. Has to be done
+    /// before indexing a new version, as it may reuse the slot of a removed one.
+    fn unindex_removed(&mut self) -> Result<(), TableError> {
+        let removed = self.heap.take_removed();
+        if removed.is_empty() {
+            return Ok(());
+        }
+        for (index_info, index) in &self.indexes {
+            for (record_id, row) in &removed {
+                index
+                    .delete(&Self::key(row, &index_info.column_name)?, record_id)
+                    .map_err(TableError::IndexError)?;
+            }
+        }
+        debug!(
+            "Table {}: removed the index entries of {} versions",
+            self.info.name,
+            removed.len()
+        );
+        Ok(())
+    }
+
+    fn key(row: &Row, column_name: &str) -> Result<Value, TableError> {
+        row.get_value(column_name)
+            .cloned()
+            .map_err(TableError::LayoutError)
+    }
+}
+
+#[derive(Debug)]
+pub enum TableError {
+    CatalogError(CatalogError),
+    TableScanError(TableScanError),
+    IndexError(IndexError),
+    LayoutError(LayoutError),
+    TableNotFound(String),
+    IndexNotFound(String),
+}
+
+impl Display for TableError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TableError::CatalogError(e) => write!(f, "Table {}", e),
+            TableError::TableScanError(e) => write!(f, "Table {}", e),
+            TableError::IndexError(e) => write!(f, "Table {}", e),
+            TableError::LayoutError(e) => write!(f, "Table {}", e),
+            TableError::TableNotFound(name) => write!(f, "Table: no table {}", name),
+            TableError::IndexNotFound(name) => write!(f, "Table: no index {}", name),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::index_management::index::IndexError;
+    use crate::metadata_management::catalog::IndexType;
+    use crate::record_management::schema::{ColumnType, Schema};
+    use crate::record_management::table::{Table, TableError};
+    use crate::record_management::value::Value;
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn test_table_maintains_indexes() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("table")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(200).unwrap()))
+            .build()
+            .unwrap();
+        let catalog = &hfdb.catalog;
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        catalog
+            .create_table(
+                &tx,
+                "persons",
+                Schema::new()
+                    .with_column("id", ColumnType::Integer)
+                    .with_column("city", ColumnType::Varchar),
+            )
+            .unwrap();
+        let mut table = Table::open(&tx, catalog, "persons").unwrap();
+        let cities = ["Berlin", "Hamburg", "Munich"];
+        let mut record_ids = vec![];
+        for id in 0..30 {
+            let mut row = table.layout().row();
+            row.set_int("id", id).unwrap();
+            row.set_string("city", cities[id as usize % 3]).unwrap();
+            record_ids.push(table.insert(&row).unwrap());
+        }
+        table
+            .create_index(catalog, "persons_id", "id", IndexType::BTree)
+            .unwrap();
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let mut table = Table::open(&tx, catalog, "persons").unwrap();
+        table
+            .create_index(catalog, "persons_city", "city", IndexType::BTree)
+            .unwrap();
+        assert_eq!(
+            table
+                .lookup("persons_city", &Value::from("Hamburg"))
+                .unwrap()
+                .len(),
+            10
+        );
+        let berlin = Value::from("Berlin");
+        let hamburg = Value::from("Hamburg");
+        assert_eq!(
+            table
+                .range("persons_city", &berlin..&hamburg)
+                .unwrap()
+                .len(),
+            10
+        );
+        assert_eq!(table.range("persons_id", ..).unwrap().len(), 30);
+
+        let (record_id, mut row) = table
+            .lookup("persons_id", &Value::from(4i64))
+            .unwrap()
+            .remove(0);
+        row.set_string("city", "Munich").unwrap();
+        table.update(&record_id, &row).unwrap();
+        table.delete(&record_ids[5]).unwrap();
+        assert_eq!(
+            table
+                .lookup("persons_city", &Value::from("Munich"))
+                .unwrap()
+                .len(),
+            10
+        );
+        assert!(table
+            .lookup("persons_id", &Value::from(5i64))
+            .unwrap()
+            .is_empty());
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let mut table = Table::open(&tx, catalog, "persons").unwrap();
+        assert_eq!(table.vacuum().unwrap(), 2);
+        assert_eq!(table.range("persons_id", ..).unwrap().len(), 29);
+        assert_eq!(
+            table
+                .lookup("persons_city", &Value::from("Hamburg"))
+                .unwrap()
+                .len(),
+            9
+        );
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_table_removes_index_entries_of_reused_slots() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("table_reused_slots")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(200).unwrap()))
+            .build()
+            .unwrap();
+        let catalog = &hfdb.catalog;
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        catalog
+            .create_table(
+                &tx,
+                "persons",
+                Schema::new().with_column("id", ColumnType::Integer),
+            )
+            .unwrap();
+        let mut table = Table::open(&tx, catalog, "persons").unwrap();
+        table
+            .create_index(catalog, "persons_id", "id", IndexType::BTree)
+            .unwrap();
+        let mut record_ids = vec![];
+        for id in 0..5 {
+            let mut row = table.layout().row();
+            row.set_int("id", id).unwrap();
+            record_ids.push(table.insert(&row).unwrap());
+        }
+        tx.commit().unwrap();
+        let block_length = hfdb
+            .file_manager
+            .block_length(&table.info().filename)
+            .unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let mut table = Table::open(&tx, catalog, "persons").unwrap();
+        for record_id in &record_ids {
+            table.delete(record_id).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let mut table = Table::open(&tx, catalog, "persons").unwrap();
+        for id in 5..10 {
+            let mut row = table.layout().row();
+            row.set_int("id", id).unwrap();
+            table.insert(&row).unwrap();
+        }
+        assert_eq!(
+            hfdb.file_manager
+                .block_length(&table.info().filename)
+                .unwrap(),
+            block_length
+        );
+        let (_, index) = table.index("persons_id").unwrap();
+        assert_eq!(index.entries().unwrap().len(), 5);
+        assert!(table
+            .lookup("persons_id", &Value::from(3i64))
+            .unwrap()
+            .is_empty());
+        assert_eq!(table.range("persons_id", ..).unwrap().len(), 5);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_table_with_hash_index() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("table_hash_index")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(200).unwrap()))
+            .build()
+            .unwrap();
+        let catalog = &hfdb.catalog;
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        catalog
+            .create_table(
+                &tx,
+                "persons",
+                Schema::new()
+                    .with_column("id", ColumnType::Integer)
+                    .with_column("city", ColumnType::Varchar),
+            )
+            .unwrap();
+        let mut table = Table::open(&tx, catalog, "persons").unwrap();
+        table
+            .create_index(catalog, "persons_city", "city", IndexType::Hash)
+            .unwrap();
+        let cities = ["Berlin", "Hamburg", "Munich", "Cologne"];
+        for id in 0..100 {
+            let mut row = table.layout().row();
+            row.set_int("id", id).unwrap();
+            row.set_string("city", cities[id as usize % 4]).unwrap();
+            table.insert(&row).unwrap();
+        }
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let mut table = Table::open(&tx, catalog, "persons").unwrap();
+        let (record_id, mut row) = table
+            .lookup("persons_city", &Value::from("Cologne"))
+            .unwrap()
+            .remove(0);
+        assert_eq!(row.get_string("city").unwrap().unwrap(), "Cologne");
+        row.set_string("city", "Bremen").unwrap();
+        table.update(&record_id, &row).unwrap();
+        assert_eq!(
+            table
+                .lookup("persons_city", &Value::from("Cologne"))
+                .unwrap()
+                .len(),
+            24
+        );
+        assert_eq!(
+            table
+                .lookup("persons_city", &Value::from("Bremen"))
+                .unwrap()
+                .len(),
+            1
+        );
+        assert!(matches!(
+            table.range("persons_city", ..),
+            Err(TableError::IndexError(IndexError::RangeNotSupported(_)))
+        ));
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let mut table = Table::open(&tx, catalog, "persons").unwrap();
+        assert_eq!(table.vacuum().unwrap(), 1);
+        assert_eq!(
+            table
+                .lookup("persons_city", &Value::from("Bremen"))
+                .unwrap()
+                .len(),
+            1
+        );
+        tx.commit().unwrap();
+    }
+}
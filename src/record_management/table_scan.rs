@@ -0,0 +1,536 @@
+use crate::datatypes::HfdbSerializableDatatype;
+use crate::file_management::block_id::{BlockId, DbFilename};
+use crate::memory_management::buffer::TransactionNumber;
+use crate::record_management::layout::{Layout, LayoutError, Row};
+use crate::record_management::record_page::{RecordPage, RecordPageError, SlotId};
+use crate::transaction_management::mvcc::RecordVersion;
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use log::debug;
+use std::fmt::{Display, Formatter};
+
+/// Location of a record version, stays the same while the version exists. Updating a row
+/// creates a new version with its own record id.
+#[derive(Debug, Clone, Eq, PartialEq, Hash)]
+pub struct RecordId {
+    pub block: BlockId,
+    pub slot: SlotId,
+}
+
+impl RecordId {
+    pub fn new(block: BlockId, slot: SlotId) -> Self {
+        Self { block, slot }
+    }
+}
+
+impl Display for RecordId {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}:{}:{}",
+            self.block.filename(),
+            self.block.block_number(),
+            self.slot
+        )
+    }
+}
+
+/// Heap file of a table: the rows are stored as record versions (a RecordVersion header followed
+/// by the row encoded with the layout) in record pages, appending a new block when the last one
+/// is full. Scans return the rows visible in the snapshot of the transaction, including its own
+/// changes made during the scan.
+///
+/// Versions no snapshot can see anymore are removed when their page gets full or by vacuum. A
+/// scan collecting the removed versions keeps them until they are taken, e.g. to remove their
+/// index entries.
+#[derive(Debug)]
+pub struct TableScan {
+    transaction: Transaction,
+    filename: DbFilename,
+    layout: Layout,
+    record_page: Option<RecordPage>,
+    slots: Vec<SlotId>,
+    position: usize,
+    current: Option<(RecordId, Row)>,
+    all_versions: bool,
+    removed: Option<Vec<(RecordId, Row)>>,
+}
+
+impl TableScan {
+    pub fn new(transaction: &Transaction, filename: &DbFilename, layout: &Layout) -> Self {
+        Self {
+            transaction: transaction.clone(),
+            filename: filename.clone(),
+            layout: layout.clone(),
+            record_page: None,
+            slots: vec![],
+            position: 0,
+            current: None,
+            all_versions: false,
+            removed: None,
+        }
+    }
+
+    /// Scans (and gets) all record versions, whether visible or not, e.g. to build an index
+    pub fn all_versions(mut self) -> Self {
+        self.all_versions = true;
+        self
+    }
+
+    /// Keeps the record ids and rows of the versions removed as garbage, see take_removed
+    pub fn collecting_removed(mut self) -> Self {
+        self.removed = Some(vec![]);
+        self
+    }
+
+    /// Record ids and rows of the versions removed since the last call, if collecting them
+    pub fn take_removed(&mut self) -> Vec<(RecordId, Row)> {
+        self.removed
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    pub fn layout(&self) -> &Layout {
+        &self.layout
+    }
+
+    pub fn filename(&self) -> &DbFilename {
+        &self.filename
+    }
+
+    /// Restarts the scan at the first block
+    pub fn before_first(&mut self) {
+        self.record_page = None;
+        self.slots = vec![];
+        self.position = 0;
+        self.current = None;
+    }
+
+    /// Moves to the next visible row, false at the end of the table
+    pub fn next_row(&mut self) -> Result<bool, TableScanError> {
+        loop {
+            while self.position < self.slots.len() {
+                let slot = self.slots[self.position];
+                self.position += 1;
+                let record_page = self.record_page.as_ref().unwrap();
+                match Self::visible_row(
+                    &self.transaction,
+                    &self.layout,
+                    record_page,
+                    slot,
+                    self.all_versions,
+                )? {
+                    Some(row) => {
+                        let record_id = RecordId::new(record_page.block().clone(), slot);
+                        self.current = Some((record_id, row));
+                        return Ok(true);
+                    }
+                    None => continue,
+                }
+            }
+            let next_block_number = match &self.record_page {
+                Some(record_page) => record_page.block().block_number() + 1,
+                None => 0,
+            };
+            self.record_page = None;
+            if next_block_number >= self.block_length()? {
+                self.current = None;
+                return Ok(false);
+            }
+            let block = BlockId::new(self.filename.clone(), next_block_number);
+            let record_page = self.record_page(&block)?;
+            self.slots = record_page
+                .slots()
+                .map_err(TableScanError::RecordPageError)?;
+            self.position = 0;
+            self.record_page = Some(record_page);
+        }
+    }
+
+    pub fn record_id(&self) -> Option<&RecordId> {
+        self.current.as_ref().map(|(record_id, _)| record_id)
+    }
+
+    /// Row the scan is positioned on
+    pub fn row(&self) -> Option<&Row> {
+        self.current.as_ref().map(|(_, row)| row)
+    }
+
+    /// Row of the record version if it is visible in the snapshot of the transaction
+    pub fn get(&self, record_id: &RecordId) -> Result<Option<Row>, TableScanError> {
+        let record_page = self.record_page(&record_id.block)?;
+        Self::visible_row(
+            &self.transaction,
+            &self.layout,
+            &record_page,
+            record_id.slot,
+            self.all_versions,
+        )
+    }
+
+    /// Inserts the row into the last block, a new one is appended if it does not fit
+    pub fn insert(&mut self, row: &Row) -> Result<RecordId, TableScanError> {
+        let record = self.record(RecordVersion::new(self.transaction_number()), row)?;
+        let block_length = self.block_length()?;
+        if block_length > 0 {
+            let block = BlockId::new(self.filename.clone(), block_length - 1);
+            if let Some(record_id) = self.insert_into(&block, &record)? {
+                return Ok(record_id);
+            }
+        }
+        let block = self
+            .transaction
+            .append(&self.filename)
+            .map_err(TableScanError::TransactionError)?;
+        debug!("TableScan {}: appended {:?}", self.filename, block);
+        self.insert_into(&block, &record)?
+            .ok_or(TableScanError::RecordTooLarge(record.len()))
+    }
+
+    /// Deletes the visible record version. Fails with a WriteConflict if another transaction
+    /// deleted it after the snapshot was taken.
+    pub fn delete(&mut self, record_id: &RecordId) -> Result<(), TableScanError> {
+        let record_page = self.record_page(&record_id.block)?;
+        self.transaction
+            .xlock(&record_id.block)
+            .map_err(TableScanError::TransactionError)?;
+        let mut record = record_page
+            .get(record_id.slot)
+            .map_err(|e| Self::not_found_or(e, record_id))?;
+        let mut version = RecordVersion::deserialize(&record);
+        if !self.transaction.snapshot().can_delete(&version) {
+            return Err(
+                if self.transaction.snapshot().sees(version.created_by)
+                    && version.deleted_by.is_some()
+                {
+                    TableScanError::WriteConflict(record_id.clone())
+                } else {
+                    TableScanError::RecordNotFound(record_id.clone())
+                },
+            );
+        }
+        version.deleted_by = Some(self.transaction_number());
+        version.serialize(&mut record[..RecordVersion::SERIALIZED_LENGTH]);
+        record_page
+            .update(record_id.slot, &record)
+            .map_err(TableScanError::RecordPageError)
+    }
+
+    /// Replaces the visible record version by a new one, preferably in the same block.
+    /// Returns the record id of the new version.
+    pub fn update(&mut self, record_id: &RecordId, row: &Row) -> Result<RecordId, TableScanError> {
+        self.delete(record_id)?;
+        let record = self.record(RecordVersion::new(self.transaction_number()), row)?;
+        match self.insert_into(&record_id.block, &record)? {
+            Some(new_record_id) => Ok(new_record_id),
+            None => self.insert(row),
+        }
+    }
+
+    /// Removes all record versions no snapshot can see anymore, returns their number
+    pub fn vacuum(&mut self) -> Result<usize, TableScanError> {
+        let mut removed = 0;
+        for block_number in 0..self.block_length()? {
+            let block = BlockId::new(self.filename.clone(), block_number);
+            let record_page = self.record_page(&block)?;
+            removed += self.vacuum_page(&record_page)?;
+        }
+        self.before_first();
+        Ok(removed)
+    }
+
+    fn insert_into(
+        &mut self,
+        block: &BlockId,
+        record: &[u8],
+    ) -> Result<Option<RecordId>, TableScanError> {
+        let record_page = self.record_page(block)?;
+        for attempt in 0..2 {
+            match record_page.insert(record) {
+                Ok(slot) => return Ok(Some(RecordId::new(block.clone(), slot))),
+                Err(RecordPageError::PageFull(_)) if attempt == 0 => {
+                    if self.vacuum_page(&record_page)? == 0 {
+                        return Ok(None);
+                    }
+                }
+                Err(RecordPageError::PageFull(_)) => return Ok(None),
+                Err(e) => return Err(TableScanError::RecordPageError(e)),
+            }
+        }
+        Ok(None)
+    }
+
+    fn vacuum_page(&mut self, record_page: &RecordPage) -> Result<usize, TableScanError> {
+        let horizon = self.transaction.gc_horizon();
+        let mut removed = 0;
+        for slot in record_page
+            .slots()
+            .map_err(TableScanError::RecordPageError)?
+        {
+            let record = match record_page.get(slot) {
+                Ok(record) => record,
+                Err(RecordPageError::SlotNotFound(_, _)) => continue,
+                Err(e) => return Err(TableScanError::RecordPageError(e)),
+            };
+            if RecordVersion::deserialize(&record).is_garbage(horizon) {
+                if let Some(versions) = self.removed.as_mut() {
+                    versions.push((
+                        RecordId::new(record_page.block().clone(), slot),
+                        self.layout
+                            .decode(&record[RecordVersion::SERIALIZED_LENGTH..]),
+                    ));
+                }
+                record_page
+                    .delete(slot)
+                    .map_err(TableScanError::RecordPageError)?;
+                removed += 1;
+            }
+        }
+        if removed > 0 {
+            debug!(
+                "TableScan {}: removed {} garbage versions from {:?}",
+                self.filename,
+                removed,
+                record_page.block()
+            );
+        }
+        Ok(removed)
+    }
+
+    fn visible_row(
+        transaction: &Transaction,
+        layout: &Layout,
+        record_page: &RecordPage,
+        slot: SlotId,
+        all_versions: bool,
+    ) -> Result<Option<Row>, TableScanError> {
+        let record = match record_page.get(slot) {
+            Ok(record) => record,
+            Err(RecordPageError::SlotNotFound(_, _)) => return Ok(None),
+            Err(e) => return Err(TableScanError::RecordPageError(e)),
+        };
+        let version = RecordVersion::deserialize(&record);
+        Ok(
+            (all_versions || transaction.snapshot().is_visible(&version))
+                .then(|| layout.decode(&record[RecordVersion::SERIALIZED_LENGTH..])),
+        )
+    }
+
+    fn record(&self, version: RecordVersion, row: &Row) -> Result<Vec<u8>, TableScanError> {
+        let mut record = vec![0u8; RecordVersion::SERIALIZED_LENGTH];
+        version.serialize(&mut record);
+        record.extend(
+            self.layout
+                .encode(row)
+                .map_err(TableScanError::LayoutError)?,
+        );
+        let max_record_length = RecordPage::max_record_length(self.transaction.block_size());
+        if record.len() > max_record_length {
+            return Err(TableScanError::RecordTooLarge(record.len()));
+        }
+        Ok(record)
+    }
+
+    fn record_page(&self, block: &BlockId) -> Result<RecordPage, TableScanError> {
+        RecordPage::new(&self.transaction, block).map_err(TableScanError::RecordPageError)
+    }
+
+    fn block_length(&self) -> Result<usize, TableScanError> {
+        self.transaction
+            .block_length(&self.filename)
+            .map_err(TableScanError::TransactionError)
+    }
+
+    fn transaction_number(&self) -> TransactionNumber {
+        self.transaction.transaction_number()
+    }
+
+    fn not_found_or(error: RecordPageError, record_id: &RecordId) -> TableScanError {
+        match error {
+            RecordPageError::SlotNotFound(_, _) => {
+                TableScanError::RecordNotFound(record_id.clone())
+            }
+            e => TableScanError::RecordPageError(e),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TableScanError {
+    TransactionError(TransactionError),
+    RecordPageError(RecordPageError),
+    LayoutError(LayoutError),
+    RecordTooLarge(usize),
+    RecordNotFound(RecordId),
+    WriteConflict(RecordId),
+}
+
+impl Display for TableScanError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::TransactionError(e) => write!(f, "TableScan {}", e),
+            Self::RecordPageError(e) => write!(f, "TableScan {}", e),
+            Self::LayoutError(e) => write!(f, "TableScan {}", e),
+            Self::RecordTooLarge(length) => {
+                write!(
+                    f,
+                    "TableScan: record of {} bytes does not fit into a block",
+                    length
+                )
+            }
+            Self::RecordNotFound(record_id) => {
+                write!(f, "TableScan: no visible record {}", record_id)
+            }
+            Self::WriteConflict(record_id) => {
+                write!(
+                    f,
+                    "TableScan: record {} was changed concurrently",
+                    record_id
+                )
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::{HanfriedDb, HanfriedDbBuilder};
+    use crate::file_management::block_id::DbFilename;
+    use crate::record_management::layout::Layout;
+    use crate::record_management::schema::{ColumnType, Schema};
+    use crate::record_management::table_scan::{TableScan, TableScanError};
+    use crate::transaction_management::transaction::Transaction;
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+
+    fn hfdb(sub_directory_name: &str) -> HanfriedDb {
+        HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(200).unwrap()))
+            .buffer_manager(|bm| bm.pool_size(10))
+            .build()
+            .unwrap()
+    }
+
+    fn layout() -> Layout {
+        Layout::new(
+            Schema::new()
+                .with_column("id", ColumnType::Integer)
+                .with_nullable_column("name", ColumnType::Varchar),
+        )
+    }
+
+    fn names(transaction: &Transaction, filename: &DbFilename) -> Vec<(i64, Option<String>)> {
+        let mut scan = TableScan::new(transaction, filename, &layout());
+        let mut names = vec![];
+        while scan.next_row().unwrap() {
+            let row = scan.row().unwrap();
+            names.push((
+                row.get_int("id").unwrap().unwrap(),
+                row.get_string("name").unwrap(),
+            ));
+        }
+        names
+    }
+
+    #[test]
+    fn test_table_scan_insert_update_delete() {
+        init_logging();
+        let hfdb = hfdb("table_scan");
+        let filename = DbFilename::from("table_scan.tbl");
+        let layout = layout();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let mut scan = TableScan::new(&tx, &filename, &layout);
+        let record_ids: Vec<_> = (0..20)
+            .map(|id| {
+                let mut row = layout.row();
+                row.set_int("id", id).unwrap();
+                if id % 2 == 0 {
+                    row.set_string("name", &format!("name {}", id)).unwrap();
+                }
+                scan.insert(&row).unwrap()
+            })
+            .collect();
+        assert!(
+            tx.block_length(&filename).unwrap() > 1,
+            "Inserting has to append new blocks"
+        );
+        let rows = names(&tx, &filename);
+        assert_eq!(rows.len(), 20);
+        assert_eq!(rows[2], (2, Some("name 2".to_string())));
+        assert_eq!(rows[3], (3, None));
+        tx.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let reader = hfdb.transaction_manager.begin().unwrap();
+        let mut scan = TableScan::new(&tx, &filename, &layout);
+        scan.delete(&record_ids[0]).unwrap();
+        let mut row = scan.get(&record_ids[1]).unwrap().unwrap();
+        row.set_string("name", "updated").unwrap();
+        let updated = scan.update(&record_ids[1], &row).unwrap();
+        assert_ne!(updated, record_ids[1]);
+        assert!(scan.get(&record_ids[1]).unwrap().is_none());
+        assert!(matches!(
+            scan.delete(&record_ids[0]),
+            Err(TableScanError::WriteConflict(_))
+        ));
+
+        let rows = names(&tx, &filename);
+        assert_eq!(rows.len(), 19);
+        assert!(rows.contains(&(1, Some("updated".to_string()))));
+        let rows = names(&reader, &filename);
+        assert_eq!(rows.len(), 20);
+        assert!(rows.contains(&(1, None)));
+        tx.commit().unwrap();
+
+        let mut scan = TableScan::new(&reader, &filename, &layout);
+        assert!(matches!(
+            scan.delete(&record_ids[1]),
+            Err(TableScanError::WriteConflict(_))
+        ));
+        reader.rollback().unwrap();
+    }
+
+    #[test]
+    fn test_table_scan_vacuum() {
+        init_logging();
+        let hfdb = hfdb("table_scan_vacuum");
+        let filename = DbFilename::from("table_scan.tbl");
+        let layout = layout();
+        let mut row = layout.row();
+        row.set_int("id", 1).unwrap();
+        row.set_string("name", "a rather long name, so blocks fill up fast")
+            .unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let mut record_id = TableScan::new(&tx, &filename, &layout)
+            .insert(&row)
+            .unwrap();
+        tx.commit().unwrap();
+
+        let reader = hfdb.transaction_manager.begin().unwrap();
+        for _ in 0..5 {
+            let tx = hfdb.transaction_manager.begin().unwrap();
+            record_id = TableScan::new(&tx, &filename, &layout)
+                .update(&record_id, &row)
+                .unwrap();
+            tx.commit().unwrap();
+        }
+        assert_eq!(names(&reader, &filename).len(), 1);
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        assert_eq!(
+            TableScan::new(&tx, &filename, &layout).vacuum().unwrap(),
+            0,
+            "Versions deleted after the reader started have to be kept"
+        );
+        tx.commit().unwrap();
+        assert_eq!(names(&reader, &filename).len(), 1);
+        reader.commit().unwrap();
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        let mut scan = TableScan::new(&tx, &filename, &layout);
+        assert_eq!(scan.vacuum().unwrap(), 5);
+        assert_eq!(names(&tx, &filename).len(), 1);
+        tx.commit().unwrap();
+    }
+}
@@ -0,0 +1,6 @@
+pub mod documents;
+pub mod http;
+pub mod key_value;
+pub mod options;
+pub mod postgres;
+pub mod redis;
@@ -0,0 +1,1040 @@
+use crate::file_management::block_id::DbFilename;
+use crate::index_management::inverted_index::{
+    CollectionStatistics, InvertedIndex, InvertedIndexError, Posting,
+};
+use crate::metadata_management::catalog::{Catalog, CatalogError, IndexType};
+use crate::record_management::layout::{LayoutError, Row};
+use crate::record_management::schema::{ColumnType, Schema};
+use crate::record_management::table::{Table, TableError};
+use crate::record_management::value::Value;
+use crate::server::key_value::now_milliseconds;
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use crate::transaction_management::transaction_manager::TransactionManager;
+use crate::utils::json::Json;
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
+
+/// BM25 term frequency saturation
+const K1: f64 = 1.2;
+/// BM25 document length normalization
+const B: f64 = 0.75;
+/// Longer collection names are rejected
+const MAX_COLLECTION_NAME_LENGTH: usize = 48;
+
+/// Query on the documents of a collection, in the syntax of the Elasticsearch query DSL. The
+/// field `_all` stands for all fields of a document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Query {
+    MatchAll,
+    /// Documents containing any (or all) tokens of the text
+    Match {
+        field: String,
+        text: String,
+        all_tokens: bool,
+    },
+    /// Documents containing the tokens of the text one after the other
+    MatchPhrase {
+        field: String,
+        text: String,
+    },
+    /// Documents with the exact value in the field, or in an array in the field
+    Term {
+        field: String,
+        value: Json,
+    },
+    /// Documents with a number or string within the bounds in the field
+    Range {
+        field: String,
+        low: Bound<Json>,
+        high: Bound<Json>,
+    },
+    /// Documents matching all `must` and `filter` queries and none of the `must_not` queries. If
+    /// there are neither `must` nor `filter` queries at least one of the `should` queries has to
+    /// match. Only `must` and `should` queries contribute to the score.
+    Bool {
+        must: Vec<Query>,
+        should: Vec<Query>,
+        must_not: Vec<Query>,
+        filter: Vec<Query>,
+    },
+}
+
+impl Query {
+    pub const ALL_FIELDS: &'static str = "_all";
+
+    pub fn from_json(json: &Json) -> Result<Query, DocumentError> {
+        let (kind, body) = single_member(json, "query")?;
+        match kind {
+            "match_all" => Ok(Query::MatchAll),
+            "match" => {
+                let (field, body) = single_member(body, "match")?;
+                let (text, options) = Self::text(body, "match")?;
+                let all_tokens = match options.and_then(|options| options.get("operator")) {
+                    None => false,
+                    Some(operator) => match operator.as_str().map(str::to_lowercase).as_deref() {
+                        Some("or") => false,
+                        Some("and") => true,
+                        _ => return Err(invalid_query("operator has to be \"and\" or \"or\"")),
+                    },
+                };
+                Ok(Query::Match {
+                    field: field.to_string(),
+                    text,
+                    all_tokens,
+                })
+            }
+            "match_phrase" => {
+                let (field, body) = single_member(body, "match_phrase")?;
+                Ok(Query::MatchPhrase {
+                    field: field.to_string(),
+                    text: Self::text(body, "match_phrase")?.0,
+                })
+            }
+            "term" => {
+                let (field, body) = single_member(body, "term")?;
+                let value = match body.get("value") {
+                    Some(value) => value,
+                    None => body,
+                };
+                Ok(Query::Term {
+                    field: field.to_string(),
+                    value: value.clone(),
+                })
+            }
+            "range" => {
+                let (field, body) = single_member(body, "range")?;
+                let bounds = body
+                    .as_object()
+                    .ok_or_else(|| invalid_query("range needs an object of bounds"))?;
+                let (mut low, mut high) = (Bound::Unbounded, Bound::Unbounded);
+                for (name, value) in bounds {
+                    match name.as_str() {
+                        "gt" => low = Bound::Excluded(value.clone()),
+                        "gte" => low = Bound::Included(value.clone()),
+                        "lt" => high = Bound::Excluded(value.clone()),
+                        "lte" => high = Bound::Included(value.clone()),
+                        _ => return Err(invalid_query(&format!("unknown range bound {}", name))),
+                    }
+                }
+                Ok(Query::Range {
+                    field: field.to_string(),
+                    low,
+                    high,
+                })
+            }
+            "bool" => {
+                let clauses = |name: &str| -> Result<Vec<Query>, DocumentError> {
+                    match body.get(name) {
+                        None => Ok(vec![]),
+                        Some(Json::Array(queries)) => {
+                            queries.iter().map(Query::from_json).collect()
+                        }
+                        Some(query) => Ok(vec![Query::from_json(query)?]),
+                    }
+                };
+                if let Some((name, _)) = body.as_object().into_iter().flatten().find(|(name, _)| {
+                    !matches!(name.as_str(), "must" | "should" | "must_not" | "filter")
+                }) {
+                    return Err(invalid_query(&format!("unknown bool clause {}", name)));
+                }
+                Ok(Query::Bool {
+                    must: clauses("must")?,
+                    should: clauses("should")?,
+                    must_not: clauses("must_not")?,
+                    filter: clauses("filter")?,
+                })
+            }
+            _ => Err(invalid_query(&format!("unknown query {}", kind))),
+        }
+    }
+
+    /// Query of the `q` URL parameter: `field:text` or just text to search all fields
+    pub fn from_query_string(query_string: &str) -> Query {
+        let (field, text) = match query_string.split_once(':') {
+            Some((field, text)) if !field.is_empty() && !field.contains(' ') => (field, text),
+            _ => (Self::ALL_FIELDS, query_string),
+        };
+        Query::Match {
+            field: field.to_string(),
+            text: text.to_string(),
+            all_tokens: false,
+        }
+    }
+
+    /// Text of a match query, either the value itself or its `query` member with the options
+    fn text<'a>(body: &'a Json, kind: &str) -> Result<(String, Option<&'a Json>), DocumentError> {
+        let (text, options) = match body.get("query") {
+            Some(text) => (text, Some(body)),
+            None => (body, None),
+        };
+        match text {
+            Json::String(text) => Ok((text.clone(), options)),
+            Json::Number(_) | Json::Bool(_) => Ok((text.to_string(), options)),
+            _ => Err(invalid_query(&format!("{} needs a text", kind))),
+        }
+    }
+}
+
+fn single_member<'a>(json: &'a Json, kind: &str) -> Result<(&'a str, &'a Json), DocumentError> {
+    match json.as_object() {
+        Some([(name, value)]) => Ok((name, value)),
+        _ => Err(invalid_query(&format!(
+            "{} needs an object with exactly one member",
+            kind
+        ))),
+    }
+}
+
+fn invalid_query(message: &str) -> DocumentError {
+    DocumentError::InvalidQuery(message.to_string())
+}
+
+/// Lowercased runs of letters and digits
+pub fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|token| !token.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Term of the token in the field, an empty field stands for all fields
+fn term(field: &str, token: &str) -> Vec<u8> {
+    let field = match field {
+        Query::ALL_FIELDS => "",
+        field => field,
+    };
+    [field.as_bytes(), &[0], token.as_bytes()].concat()
+}
+
+/// Texts of the scalar values in the document with their field paths, members of nested objects
+/// have dotted paths and array elements the path of the array
+fn field_texts(path: &str, value: &Json, texts: &mut Vec<(String, String)>) {
+    match value {
+        Json::Null => {}
+        Json::String(text) => texts.push((path.to_string(), text.clone())),
+        Json::Bool(_) | Json::Number(_) => texts.push((path.to_string(), value.to_string())),
+        Json::Array(values) => values
+            .iter()
+            .for_each(|value| field_texts(path, value, texts)),
+        Json::Object(members) => {
+            for (name, value) in members {
+                match path {
+                    "" => field_texts(name, value, texts),
+                    _ => field_texts(&format!("{}.{}", path, name), value, texts),
+                }
+            }
+        }
+    }
+}
+
+/// Values at the dotted path, the elements of arrays on the way are all followed
+fn field_values<'a>(value: &'a Json, path: &str, values: &mut Vec<&'a Json>) {
+    match value {
+        Json::Array(elements) => elements
+            .iter()
+            .for_each(|element| field_values(element, path, values)),
+        _ if path.is_empty() => values.push(value),
+        _ => {
+            let (name, rest) = path.split_once('.').unwrap_or((path, ""));
+            if let Some(member) = value.get(name) {
+                field_values(member, rest, values);
+            }
+        }
+    }
+}
+
+fn compare_json(left: &Json, right: &Json) -> Option<Ordering> {
+    match (left, right) {
+        (Json::Number(left), Json::Number(right)) => left.partial_cmp(right),
+        (Json::String(left), Json::String(right)) => Some(left.cmp(right)),
+        _ => None,
+    }
+}
+
+/// Document found by a search, with its score
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hit {
+    pub id: String,
+    pub score: f64,
+    pub source: Json,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SearchResult {
+    /// Number of matching documents, also those not returned
+    pub total: usize,
+    pub max_score: Option<f64>,
+    pub hits: Vec<Hit>,
+}
+
+/// Stored document of a collection
+#[derive(Debug, Clone)]
+struct Document {
+    id: String,
+    length: usize,
+    source: Json,
+}
+
+/// Collections of JSON documents with full-text search.
+///
+/// Each collection is a table `fts_<name>` holding the documents with their id, their number in
+/// the inverted index and their length in tokens, indexed on id and number. The inverted index
+/// files are named after the table file. Every token of a string (or the text of a number or
+/// boolean) is indexed twice, as term of its field and as term of all fields. Positions count
+/// the tokens over the whole document, with a gap between values, so phrases do not match across
+/// values.
+///
+/// Replacing a document removes the old version and adds the new one with a new number. Postings
+/// of removed documents stay in the index and are skipped by searches, they still count for the
+/// document frequencies. Scoring is BM25 with document lengths counted over all fields.
+///
+/// Operations run one after the other in their own transaction, so they never conflict.
+#[derive(Debug, Clone)]
+pub struct DocumentStore {
+    transaction_manager: TransactionManager,
+    catalog: Catalog,
+    serializer: Arc<Mutex<()>>,
+}
+
+impl DocumentStore {
+    pub fn new(transaction_manager: &TransactionManager, catalog: &Catalog) -> Self {
+        Self {
+            transaction_manager: transaction_manager.clone(),
+            catalog: catalog.clone(),
+            serializer: Arc::new(Mutex::new(())),
+        }
+    }
+
+    /// Names of the collections with their numbers of documents
+    pub fn collections(&self) -> Result<Vec<(String, u64)>, DocumentError> {
+        self.run(|transaction| {
+            let mut collections = vec![];
+            for table_name in self
+                .catalog
+                .table_names(transaction)
+                .map_err(DocumentError::CatalogError)?
+            {
+                if let Some(name) = table_name.strip_prefix("fts_") {
+                    if let Some(collection) = self.collection(transaction, name, false)? {
+                        let statistics = collection
+                            .index
+                            .statistics()
+                            .map_err(DocumentError::InvertedIndexError)?;
+                        collections.push((name.to_string(), statistics.document_count));
+                    }
+                }
+            }
+            Ok(collections)
+        })
+    }
+
+    /// Stores the document under the id, a generated one if None, replacing a document with the
+    /// same id. Creates the collection if needed. Returns the id and whether the document is new.
+    pub fn index(
+        &self,
+        collection: &str,
+        id: Option<&str>,
+        source: &Json,
+    ) -> Result<(String, bool), DocumentError> {
+        if source.as_object().is_none() {
+            return Err(DocumentError::InvalidDocument(
+                "a document has to be an object".to_string(),
+            ));
+        }
+        self.run(|transaction| {
+            let mut collection = self.collection(transaction, collection, true)?.unwrap();
+            let id = match id {
+                Some(id) => id.to_string(),
+                None => {
+                    let statistics = collection
+                        .index
+                        .statistics()
+                        .map_err(DocumentError::InvertedIndexError)?;
+                    format!("{:x}{:x}", now_milliseconds(), statistics.next_document)
+                }
+            };
+            let replaced = collection.remove(&id)?;
+            let index = &collection.index;
+
+            let mut texts = vec![];
+            field_texts("", source, &mut texts);
+            let max_term_length = index.max_term_length();
+            let mut terms: BTreeMap<Vec<u8>, Vec<usize>> = BTreeMap::new();
+            let mut position = 0;
+            for (field, text) in texts {
+                for token in tokenize(&text) {
+                    for term in [term(&field, &token), term(Query::ALL_FIELDS, &token)] {
+                        // Tokens too long for the dictionary are not searchable
+                        if term.len() <= max_term_length {
+                            terms.entry(term).or_default().push(position);
+                        }
+                    }
+                    position += 1;
+                }
+                position += 1;
+            }
+            let length = terms
+                .iter()
+                .filter(|(term, _)| term[0] == 0)
+                .map(|(_, positions)| positions.len())
+                .sum();
+            let number = index
+                .add_document(&terms, length)
+                .map_err(DocumentError::InvertedIndexError)?;
+
+            let mut row = collection.table.layout().row();
+            row.set_int("number", number as i64)
+                .and_then(|_| row.set_string("id", &id))
+                .and_then(|_| row.set_int("length", length as i64))
+                .and_then(|_| row.set_string("source", &source.to_string()))
+                .map_err(layout_error)?;
+            collection
+                .table
+                .insert(&row)
+                .map_err(DocumentError::TableError)?;
+            Ok((id, !replaced))
+        })
+    }
+
+    /// The document with the id, None if there is none
+    pub fn get(&self, collection: &str, id: &str) -> Result<Option<Json>, DocumentError> {
+        self.run(|transaction| {
+            let collection = self.existing_collection(transaction, collection)?;
+            Ok(collection.by_id(id)?.map(|document| document.source))
+        })
+    }
+
+    /// Removes the document with the id, false if there is none
+    pub fn delete(&self, collection: &str, id: &str) -> Result<bool, DocumentError> {
+        self.run(|transaction| {
+            self.existing_collection(transaction, collection)?
+                .remove(id)
+        })
+    }
+
+    /// Documents matching the query, by descending score, skipping the first `from` ones
+    pub fn search(
+        &self,
+        collection: &str,
+        query: &Query,
+        from: usize,
+        size: usize,
+    ) -> Result<SearchResult, DocumentError> {
+        self.run(|transaction| {
+            let collection = self.existing_collection(transaction, collection)?;
+            let statistics = collection
+                .index
+                .statistics()
+                .map_err(DocumentError::InvertedIndexError)?;
+            let mut search = Search {
+                collection: &collection,
+                statistics,
+                documents: HashMap::new(),
+                all_documents: None,
+            };
+            let mut scores: Vec<(u64, f64)> = search.evaluate(query)?.into_iter().collect();
+            scores.sort_by(|(left_number, left_score), (right_number, right_score)| {
+                right_score
+                    .total_cmp(left_score)
+                    .then(left_number.cmp(right_number))
+            });
+            let mut hits = vec![];
+            for (number, score) in scores.iter().skip(from).take(size) {
+                let document = search.document(*number)?.unwrap();
+                hits.push(Hit {
+                    id: document.id,
+                    score: *score,
+                    source: document.source,
+                });
+            }
+            Ok(SearchResult {
+                total: scores.len(),
+                max_score: scores.first().map(|(_, score)| *score),
+                hits,
+            })
+        })
+    }
+
+    fn run<R>(
+        &self,
+        operation: impl FnOnce(&Transaction) -> Result<R, DocumentError>,
+    ) -> Result<R, DocumentError> {
+        let _serialized = self.serializer.lock().unwrap();
+        let transaction = self
+            .transaction_manager
+            .begin()
+            .map_err(DocumentError::TransactionError)?;
+        match operation(&transaction) {
+            Ok(result) => {
+                transaction
+                    .commit()
+                    .map_err(DocumentError::TransactionError)?;
+                Ok(result)
+            }
+            Err(e) => {
+                transaction
+                    .rollback()
+                    .map_err(DocumentError::TransactionError)?;
+                Err(e)
+            }
+        }
+    }
+
+    fn existing_collection(
+        &self,
+        transaction: &Transaction,
+        name: &str,
+    ) -> Result<Collection, DocumentError> {
+        self.collection(transaction, name, false)?
+            .ok_or_else(|| DocumentError::CollectionNotFound(name.to_string()))
+    }
+
+    /// Opens the collection, None if it does not exist and is not to be created
+    fn collection(
+        &self,
+        transaction: &Transaction,
+        name: &str,
+        create: bool,
+    ) -> Result<Option<Collection>, DocumentError> {
+        if name.is_empty()
+            || name.len() > MAX_COLLECTION_NAME_LENGTH
+            || !name
+                .chars()
+                .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        {
+            return Err(DocumentError::InvalidCollectionName(name.to_string()));
+        }
+        let table_name = format!("fts_{}", name);
+        let id_index = format!("{}_id", table_name);
+        let number_index = format!("{}_number", table_name);
+        let table_info = match self
+            .catalog
+            .table(transaction, &table_name)
+            .map_err(DocumentError::CatalogError)?
+        {
+            Some(table_info) => table_info,
+            None if create => {
+                self.catalog
+                    .create_table(
+                        transaction,
+                        &table_name,
+                        Schema::new()
+                            .with_column("number", ColumnType::BigInteger)
+                            .with_column("id", ColumnType::Varchar)
+                            .with_column("length", ColumnType::Integer)
+                            .with_column("source", ColumnType::Varchar),
+                    )
+                    .map_err(DocumentError::CatalogError)?;
+                let mut table = Table::open(transaction, &self.catalog, &table_name)
+                    .map_err(DocumentError::TableError)?;
+                for (index_name, column) in [(&id_index, "id"), (&number_index, "number")] {
+                    table
+                        .create_index(&self.catalog, index_name, column, IndexType::BTree)
+                        .map_err(DocumentError::TableError)?;
+                }
+                table.info().clone()
+            }
+            None => return Ok(None),
+        };
+        let base = table_info.filename.as_str().trim_end_matches(".tbl");
+        let index = InvertedIndex::new(
+            transaction,
+            &DbFilename::from(format!("{}.dict", base)),
+            &DbFilename::from(format!("{}.post", base)),
+        )
+        .map_err(DocumentError::InvertedIndexError)?;
+        Ok(Some(Collection {
+            table: Table::open(transaction, &self.catalog, &table_name)
+                .map_err(DocumentError::TableError)?,
+            index,
+            id_index,
+            number_index,
+        }))
+    }
+}
+
+/// Table and inverted index of a collection, opened in a transaction
+struct Collection {
+    table: Table,
+    index: InvertedIndex,
+    id_index: String,
+    number_index: String,
+}
+
+impl Collection {
+    fn by_id(&self, id: &str) -> Result<Option<Document>, DocumentError> {
+        match self
+            .table
+            .lookup(&self.id_index, &Value::from(id))
+            .map_err(DocumentError::TableError)?
+            .first()
+        {
+            Some((_, row)) => Ok(Some(Self::document(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    fn by_number(&self, number: u64) -> Result<Option<Document>, DocumentError> {
+        match self
+            .table
+            .lookup(&self.number_index, &Value::from(number as i64))
+            .map_err(DocumentError::TableError)?
+            .first()
+        {
+            Some((_, row)) => Ok(Some(Self::document(row)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Removes the document with the id from the table and the index statistics
+    fn remove(&mut self, id: &str) -> Result<bool, DocumentError> {
+        let Some((record_id, row)) = self
+            .table
+            .lookup(&self.id_index, &Value::from(id))
+            .map_err(DocumentError::TableError)?
+            .pop()
+        else {
+            return Ok(false);
+        };
+        self.table
+            .delete(&record_id)
+            .map_err(DocumentError::TableError)?;
+        self.index
+            .remove_document(Self::document(&row)?.length)
+            .map_err(DocumentError::InvertedIndexError)?;
+        Ok(true)
+    }
+
+    fn document(row: &Row) -> Result<Document, DocumentError> {
+        let source = row
+            .get_string("source")
+            .map_err(layout_error)?
+            .unwrap_or_default();
+        Ok(Document {
+            id: row
+                .get_string("id")
+                .map_err(layout_error)?
+                .unwrap_or_default(),
+            length: row
+                .get_int("length")
+                .map_err(layout_error)?
+                .unwrap_or_default() as usize,
+            source: Json::parse(&source)
+                .map_err(|e| DocumentError::InvalidDocument(e.to_string()))?,
+        })
+    }
+}
+
+/// Evaluation of a query, caching the documents looked up
+struct Search<'a> {
+    collection: &'a Collection,
+    statistics: CollectionStatistics,
+    documents: HashMap<u64, Option<Document>>,
+    all_documents: Option<Vec<u64>>,
+}
+
+impl Search<'_> {
+    /// Scores of the existing documents matching the query
+    fn evaluate(&mut self, query: &Query) -> Result<HashMap<u64, f64>, DocumentError> {
+        match query {
+            Query::MatchAll => Ok(self
+                .all_documents()?
+                .into_iter()
+                .map(|number| (number, 1.0))
+                .collect()),
+            Query::Match {
+                field,
+                text,
+                all_tokens,
+            } => {
+                let tokens: Vec<String> = tokenize(text)
+                    .into_iter()
+                    .collect::<HashSet<_>>()
+                    .into_iter()
+                    .collect();
+                let mut scores = HashMap::new();
+                let mut matched_tokens: HashMap<u64, usize> = HashMap::new();
+                for token in &tokens {
+                    let postings = self.postings(field, token)?;
+                    let document_frequency = postings.len();
+                    for posting in postings {
+                        if let Some(score) =
+                            self.score(&posting, posting.term_frequency(), document_frequency)?
+                        {
+                            *scores.entry(posting.document).or_default() += score;
+                            *matched_tokens.entry(posting.document).or_default() += 1;
+                        }
+                    }
+                }
+                if *all_tokens {
+                    scores.retain(|number, _| matched_tokens[number] == tokens.len());
+                }
+                Ok(scores)
+            }
+            Query::MatchPhrase { field, text } => {
+                let tokens = tokenize(text);
+                let mut lists = vec![];
+                for token in &tokens {
+                    lists.push(self.postings(field, token)?);
+                }
+                let Some((first, rest)) = lists.split_first() else {
+                    return Ok(HashMap::new());
+                };
+                let by_document: Vec<HashMap<u64, &Posting>> = rest
+                    .iter()
+                    .map(|list| {
+                        list.iter()
+                            .map(|posting| (posting.document, posting))
+                            .collect()
+                    })
+                    .collect();
+                let mut scores = HashMap::new();
+                for posting in first {
+                    let Some(others) = by_document
+                        .iter()
+                        .map(|postings| postings.get(&posting.document))
+                        .collect::<Option<Vec<_>>>()
+                    else {
+                        continue;
+                    };
+                    let frequency = posting
+                        .positions
+                        .iter()
+                        .filter(|&&position| {
+                            others.iter().enumerate().all(|(i, other)| {
+                                other.positions.binary_search(&(position + i + 1)).is_ok()
+                            })
+                        })
+                        .count();
+                    if frequency == 0 {
+                        continue;
+                    }
+                    let mut score = Some(0.0);
+                    for list in &lists {
+                        let term_score = self.score(posting, frequency, list.len())?;
+                        score = score.zip(term_score).map(|(score, term)| score + term);
+                    }
+                    if let Some(score) = score {
+                        scores.insert(posting.document, score);
+                    }
+                }
+                Ok(scores)
+            }
+            Query::Term { field, value } => self.filter(|source| {
+                let mut values = vec![];
+                field_values(source, field, &mut values);
+                values.contains(&value)
+            }),
+            Query::Range { field, low, high } => self.filter(|source| {
+                let mut values = vec![];
+                field_values(source, field, &mut values);
+                values.into_iter().any(|value| {
+                    let above = match low {
+                        Bound::Included(low) => compare_json(value, low).is_some_and(|o| o.is_ge()),
+                        Bound::Excluded(low) => compare_json(value, low).is_some_and(|o| o.is_gt()),
+                        Bound::Unbounded => matches!(value, Json::Number(_) | Json::String(_)),
+                    };
+                    let below = match high {
+                        Bound::Included(high) => {
+                            compare_json(value, high).is_some_and(|o| o.is_le())
+                        }
+                        Bound::Excluded(high) => {
+                            compare_json(value, high).is_some_and(|o| o.is_lt())
+                        }
+                        Bound::Unbounded => true,
+                    };
+                    above && below
+                })
+            }),
+            Query::Bool {
+                must,
+                should,
+                must_not,
+                filter,
+            } => {
+                let mut scores: Option<HashMap<u64, f64>> = None;
+                for (queries, scoring) in [(must, true), (filter, false)] {
+                    for query in queries {
+                        let matches = self.evaluate(query)?;
+                        scores = Some(match scores {
+                            None => matches
+                                .into_iter()
+                                .map(|(number, score)| (number, if scoring { score } else { 0.0 }))
+                                .collect(),
+                            Some(mut scores) => {
+                                scores.retain(|number, _| matches.contains_key(number));
+                                if scoring {
+                                    for (number, score) in scores.iter_mut() {
+                                        *score += matches[number];
+                                    }
+                                }
+                                scores
+                            }
+                        });
+                    }
+                }
+                let required = scores.is_some();
+                let mut scores = match scores {
+                    Some(scores) => scores,
+                    None if should.is_empty() => self.evaluate(&Query::MatchAll)?,
+                    None => HashMap::new(),
+                };
+                for query in should {
+                    for (number, score) in self.evaluate(query)? {
+                        match scores.get_mut(&number) {
+                            Some(total) => *total += score,
+                            None if !required => {
+                                scores.insert(number, score);
+                            }
+                            None => {}
+                        }
+                    }
+                }
+                for query in must_not {
+                    for number in self.evaluate(query)?.keys() {
+                        scores.remove(number);
+                    }
+                }
+                Ok(scores)
+            }
+        }
+    }
+
+    fn postings(&self, field: &str, token: &str) -> Result<Vec<Posting>, DocumentError> {
+        self.collection
+            .index
+            .postings(&term(field, token))
+            .map_err(DocumentError::InvertedIndexError)
+    }
+
+    /// BM25 score of a term for the document of the posting, None if the document was removed
+    fn score(
+        &mut self,
+        posting: &Posting,
+        term_frequency: usize,
+        document_frequency: usize,
+    ) -> Result<Option<f64>, DocumentError> {
+        let average_length = self.statistics.average_length();
+        let document_count = self.statistics.document_count as f64;
+        let Some(document) = self.document(posting.document)? else {
+            return Ok(None);
+        };
+        let document_frequency = document_frequency as f64;
+        let inverse_document_frequency =
+            (1.0 + (document_count - document_frequency + 0.5) / (document_frequency + 0.5)).ln();
+        let term_frequency = term_frequency as f64;
+        let length_ratio = match average_length {
+            0.0 => 1.0,
+            average_length => document.length as f64 / average_length,
+        };
+        Ok(Some(
+            inverse_document_frequency * term_frequency * (K1 + 1.0)
+                / (term_frequency + K1 * (1.0 - B + B * length_ratio)),
+        ))
+    }
+
+    /// All existing documents matching the predicate on their source, scored 1
+    fn filter(
+        &mut self,
+        predicate: impl Fn(&Json) -> bool,
+    ) -> Result<HashMap<u64, f64>, DocumentError> {
+        let mut scores = HashMap::new();
+        for number in self.all_documents()? {
+            if let Some(document) = self.document(number)? {
+                if predicate(&document.source) {
+                    scores.insert(number, 1.0);
+                }
+            }
+        }
+        Ok(scores)
+    }
+
+    fn all_documents(&mut self) -> Result<Vec<u64>, DocumentError> {
+        if self.all_documents.is_none() {
+            let mut numbers = vec![];
+            for (_, row) in self
+                .collection
+                .table
+                .range(&self.collection.number_index, ..)
+                .map_err(DocumentError::TableError)?
+            {
+                let number = row
+                    .get_int("number")
+                    .map_err(layout_error)?
+                    .unwrap_or_default() as u64;
+                self.documents
+                    .insert(number, Some(Collection::document(&row)?));
+                numbers.push(number);
+            }
+            self.all_documents = Some(numbers);
+        }
+        Ok(self.all_documents.clone().unwrap())
+    }
+
+    fn document(&mut self, number: u64) -> Result<Option<Document>, DocumentError> {
+        if let Some(document) = self.documents.get(&number) {
+            return Ok(document.clone());
+        }
+        let document = self.collection.by_number(number)?;
+        self.documents.insert(number, document.clone());
+        Ok(document)
+    }
+}
+
+fn layout_error(error: LayoutError) -> DocumentError {
+    DocumentError::TableError(TableError::LayoutError(error))
+}
+
+#[derive(Debug)]
+pub enum DocumentError {
+    TransactionError(TransactionError),
+    CatalogError(CatalogError),
+    TableError(TableError),
+    InvertedIndexError(InvertedIndexError),
+    InvalidCollectionName(String),
+    CollectionNotFound(String),
+    InvalidDocument(String),
+    InvalidQuery(String),
+}
+
+impl Display for DocumentError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DocumentError::TransactionError(e) => write!(f, "Documents {}", e),
+            DocumentError::CatalogError(e) => write!(f, "Documents {}", e),
+            DocumentError::TableError(e) => write!(f, "Documents {}", e),
+            DocumentError::InvertedIndexError(e) => write!(f, "Documents {}", e),
+            DocumentError::InvalidCollectionName(name) => write!(
+                f,
+                "Documents: invalid collection name {}, only lowercase letters, digits and \
+                 underscores are allowed",
+                name
+            ),
+            DocumentError::CollectionNotFound(name) => {
+                write!(f, "Documents: collection {} not found", name)
+            }
+            DocumentError::InvalidDocument(message) => {
+                write!(f, "Documents: invalid document, {}", message)
+            }
+            DocumentError::InvalidQuery(message) => {
+                write!(f, "Documents: invalid query, {}", message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::server::documents::{tokenize, DocumentError, DocumentStore, Query};
+    use crate::utils::json::Json;
+    use crate::utils::logging::init_logging;
+
+    fn ids(store: &DocumentStore, query: &str) -> Vec<String> {
+        let query = Query::from_json(&Json::parse(query).unwrap()).unwrap();
+        store
+            .search("articles", &query, 0, 10)
+            .unwrap()
+            .hits
+            .into_iter()
+            .map(|hit| hit.id)
+            .collect()
+    }
+
+    #[test]
+    fn test_document_store() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("document_store")
+            .build()
+            .unwrap();
+        let store = DocumentStore::new(&hfdb.transaction_manager, &hfdb.catalog);
+        assert_eq!(tokenize("Héllo, WORLD-42!"), vec!["héllo", "world", "42"]);
+
+        for (id, source) in [
+            (
+                "a",
+                r#"{"title": "Rust storage engines", "body": "pages, buffers and logs"}"#,
+            ),
+            (
+                "b",
+                r#"{"title": "Logs", "body": "write ahead logs make storage durable"}"#,
+            ),
+            (
+                "c",
+                r#"{"title": "Search", "tags": ["storage", "search"], "views": 7}"#,
+            ),
+        ] {
+            let source = Json::parse(source).unwrap();
+            assert_eq!(
+                store.index("articles", Some(id), &source).unwrap(),
+                (id.to_string(), true)
+            );
+        }
+        let (generated, created) = store
+            .index(
+                "articles",
+                None,
+                &Json::parse(r#"{"title": "Ahead"}"#).unwrap(),
+            )
+            .unwrap();
+        assert!(created);
+        assert!(store.delete("articles", &generated).unwrap());
+
+        assert_eq!(ids(&store, r#"{"match": {"title": "logs"}}"#), vec!["b"]);
+        // Shorter documents score higher for the same term frequency
+        assert_eq!(
+            ids(&store, r#"{"match": {"_all": "storage"}}"#),
+            vec!["c", "a", "b"]
+        );
+        assert_eq!(
+            ids(
+                &store,
+                r#"{"match": {"_all": {"query": "logs storage", "operator": "and"}}}"#
+            ),
+            vec!["b", "a"]
+        );
+        assert!(ids(&store, r#"{"match_phrase": {"body": "ahead write"}}"#).is_empty());
+        assert_eq!(
+            ids(&store, r#"{"match_phrase": {"_all": "ahead logs"}}"#),
+            vec!["b"]
+        );
+        // Phrases do not match across values
+        assert!(ids(&store, r#"{"match_phrase": {"tags": "storage search"}}"#).is_empty());
+        assert_eq!(
+            ids(
+                &store,
+                r#"{"bool": {"should": [{"term": {"tags": "search"}}, {"range": {"views": {"lt": 7}}}]}}"#
+            ),
+            vec!["c"]
+        );
+        assert_eq!(
+            ids(
+                &store,
+                r#"{"bool": {"must_not": {"match": {"_all": "search"}}}}"#
+            ),
+            vec!["a", "b"]
+        );
+
+        // Survives a restart, replacing keeps the count
+        drop(store);
+        let store = DocumentStore::new(&hfdb.transaction_manager, &hfdb.catalog);
+        let source = Json::parse(r#"{"title": "Search again"}"#).unwrap();
+        assert_eq!(
+            store.index("articles", Some("c"), &source).unwrap(),
+            ("c".to_string(), false)
+        );
+        assert_eq!(store.get("articles", "c").unwrap(), Some(source));
+        assert_eq!(
+            store.collections().unwrap(),
+            vec![("articles".to_string(), 3)]
+        );
+        assert!(ids(&store, r#"{"term": {"views": 7}}"#).is_empty());
+        assert!(matches!(
+            store.get("unknown", "a"),
+            Err(DocumentError::CollectionNotFound(_))
+        ));
+    }
+}
@@ -0,0 +1,633 @@
+use crate::db_management_system::hfdb::HanfriedDb;
+use crate::server::documents::{DocumentError, DocumentStore, Query, SearchResult};
+use crate::utils::json::Json;
+use log::{debug, info, warn};
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::thread;
+
+/// Request bodies larger than this are rejected instead of allocating their length
+const MAX_BODY_LENGTH: usize = 16 * 1024 * 1024;
+const MAX_HEADER_LINES: usize = 100;
+const DEFAULT_SEARCH_SIZE: usize = 10;
+
+/// HTTP/1.1 server with a JSON document API modelled on Elasticsearch, on the [DocumentStore]:
+/// * `PUT /{collection}/_doc/{id}` stores a document, `POST /{collection}/_doc` stores one
+///   under a generated id, collections are created by their first document
+/// * `GET /{collection}/_doc/{id}` and `DELETE /{collection}/_doc/{id}`
+/// * `GET|POST /{collection}/_search` with a body `{"query": ..., "from": 0, "size": 10}` in
+///   the query DSL, or with the URL parameters `q`, `from` and `size`
+/// * `GET /_collections` lists the collections
+///
+/// Every connection gets its own thread and may send several requests. Bodies need a
+/// Content-Length, chunked transfer encoding is not supported.
+#[derive(Debug, Clone)]
+pub struct HttpServer {
+    store: DocumentStore,
+}
+
+impl HttpServer {
+    pub const DEFAULT_LISTEN: &'static str = "127.0.0.1:9200";
+
+    pub fn new(hfdb: &HanfriedDb) -> Self {
+        Self {
+            store: DocumentStore::new(&hfdb.transaction_manager, &hfdb.catalog),
+        }
+    }
+
+    /// Accepts connections until accepting fails
+    pub fn serve(&self, listener: TcpListener) -> std::io::Result<()> {
+        info!("HTTP server listening on {}", listener.local_addr()?);
+        loop {
+            let (stream, address) = listener.accept()?;
+            let server = self.clone();
+            thread::spawn(move || {
+                debug!("HTTP connection from {}", address);
+                match server.serve_stream(stream) {
+                    Ok(()) => debug!("HTTP connection from {} closed", address),
+                    Err(e) => warn!("HTTP connection from {} failed: {}", address, e),
+                }
+            });
+        }
+    }
+
+    pub fn serve_stream(&self, stream: TcpStream) -> Result<(), HttpError> {
+        stream.set_nodelay(true).map_err(HttpError::IoError)?;
+        let reader = stream.try_clone().map_err(HttpError::IoError)?;
+        self.serve_connection(reader, stream)
+    }
+
+    /// Serves the requests of one client until it closes the connection or asks to
+    pub fn serve_connection<R: Read, W: Write>(
+        &self,
+        reader: R,
+        mut writer: W,
+    ) -> Result<(), HttpError> {
+        let mut reader = BufReader::new(reader);
+        loop {
+            let request = match Request::read(&mut reader) {
+                Ok(Some(request)) => request,
+                Ok(None) => return Ok(()),
+                Err(HttpError::Malformed(message)) => {
+                    let response = Response::error(400, "parse_exception", &message);
+                    response.write(&mut writer, false)?;
+                    return Err(HttpError::Malformed(message));
+                }
+                Err(e) => return Err(e),
+            };
+            let response = self.handle(&request);
+            debug!(
+                "HTTP {} {} -> {}",
+                request.method, request.path, response.status
+            );
+            response.write(&mut writer, request.keep_alive)?;
+            if !request.keep_alive {
+                return Ok(());
+            }
+        }
+    }
+
+    fn handle(&self, request: &Request) -> Response {
+        let segments: Vec<String> = request
+            .path
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(percent_decode)
+            .collect();
+        let segments: Vec<&str> = segments.iter().map(String::as_str).collect();
+        let method = request.method.as_str();
+        let result = match (method, segments.as_slice()) {
+            ("GET", []) => Ok(Response::ok(Json::object([
+                ("name", Json::from("hanfried-db")),
+                (
+                    "version",
+                    Json::object([("number", Json::from(env!("CARGO_PKG_VERSION")))]),
+                ),
+            ]))),
+            ("GET", ["_collections"]) => self.collections(),
+            ("PUT" | "POST", [collection, "_doc", id]) => {
+                self.index(collection, Some(id), &request.body)
+            }
+            ("POST", [collection, "_doc"]) => self.index(collection, None, &request.body),
+            ("GET", [collection, "_doc", id]) => self.get(collection, id),
+            ("DELETE", [collection, "_doc", id]) => self.delete(collection, id),
+            ("GET" | "POST", [collection, "_search"]) => self.search(collection, request),
+            (_, [] | ["_collections"] | [_, "_doc"] | [_, "_doc", _] | [_, "_search"]) => {
+                return Response::error(
+                    405,
+                    "method_not_allowed",
+                    &format!("{} is not allowed for {}", method, request.path),
+                )
+            }
+            _ => {
+                return Response::error(
+                    404,
+                    "not_found",
+                    &format!("no handler for {} {}", method, request.path),
+                )
+            }
+        };
+        result.unwrap_or_else(|e| {
+            let (status, kind) = match &e {
+                DocumentError::InvalidCollectionName(_) => (400, "invalid_index_name_exception"),
+                DocumentError::InvalidDocument(_) => (400, "mapper_parsing_exception"),
+                DocumentError::InvalidQuery(_) => (400, "parsing_exception"),
+                DocumentError::CollectionNotFound(_) => (404, "index_not_found_exception"),
+                _ => (500, "internal_server_error"),
+            };
+            if status == 500 {
+                warn!("HTTP {} {} failed: {}", method, request.path, e);
+            }
+            Response::error(status, kind, &e.to_string())
+        })
+    }
+
+    fn collections(&self) -> Result<Response, DocumentError> {
+        let collections = self
+            .store
+            .collections()?
+            .into_iter()
+            .map(|(name, documents)| {
+                Json::object([
+                    ("name", Json::from(name)),
+                    ("documents", Json::Number(documents as f64)),
+                ])
+            })
+            .collect();
+        Ok(Response::ok(Json::object([(
+            "collections",
+            Json::Array(collections),
+        )])))
+    }
+
+    fn index(
+        &self,
+        collection: &str,
+        id: Option<&str>,
+        body: &[u8],
+    ) -> Result<Response, DocumentError> {
+        let source = parse_body(body)?.ok_or_else(|| {
+            DocumentError::InvalidDocument("the request body is empty".to_string())
+        })?;
+        let (id, created) = self.store.index(collection, id, &source)?;
+        Ok(Response {
+            status: if created { 201 } else { 200 },
+            body: Json::object([
+                ("_index", Json::from(collection)),
+                ("_id", Json::from(id)),
+                (
+                    "result",
+                    Json::from(if created { "created" } else { "updated" }),
+                ),
+            ]),
+        })
+    }
+
+    fn get(&self, collection: &str, id: &str) -> Result<Response, DocumentError> {
+        let source = self.store.get(collection, id)?;
+        let mut body = Json::object([
+            ("_index", Json::from(collection)),
+            ("_id", Json::from(id)),
+            ("found", Json::from(source.is_some())),
+        ]);
+        let status = match source {
+            Some(source) => {
+                if let Json::Object(members) = &mut body {
+                    members.push(("_source".to_string(), source));
+                }
+                200
+            }
+            None => 404,
+        };
+        Ok(Response { status, body })
+    }
+
+    fn delete(&self, collection: &str, id: &str) -> Result<Response, DocumentError> {
+        let deleted = self.store.delete(collection, id)?;
+        Ok(Response {
+            status: if deleted { 200 } else { 404 },
+            body: Json::object([
+                ("_index", Json::from(collection)),
+                ("_id", Json::from(id)),
+                (
+                    "result",
+                    Json::from(if deleted { "deleted" } else { "not_found" }),
+                ),
+            ]),
+        })
+    }
+
+    fn search(&self, collection: &str, request: &Request) -> Result<Response, DocumentError> {
+        let body = parse_body(&request.body)?.unwrap_or(Json::Object(vec![]));
+        let number = |name: &str| -> Result<Option<usize>, DocumentError> {
+            match (body.get(name), request.parameter(name)) {
+                (Some(value), _) => value.as_usize().map(Some),
+                (None, Some(value)) => value.parse().ok().map(Some),
+                (None, None) => Some(None),
+            }
+            .ok_or_else(|| DocumentError::InvalidQuery(format!("{} has to be a number", name)))
+        };
+        let from = number("from")?.unwrap_or(0);
+        let size = number("size")?.unwrap_or(DEFAULT_SEARCH_SIZE);
+        let query = match (body.get("query"), request.parameter("q")) {
+            (Some(query), _) => Query::from_json(query)?,
+            (None, Some(query_string)) => Query::from_query_string(&query_string),
+            (None, None) => Query::MatchAll,
+        };
+        let result = self.store.search(collection, &query, from, size)?;
+        Ok(Response::ok(search_response(collection, result)))
+    }
+}
+
+fn parse_body(body: &[u8]) -> Result<Option<Json>, DocumentError> {
+    let text = std::str::from_utf8(body)
+        .map_err(|_| DocumentError::InvalidDocument("the body is not UTF-8".to_string()))?;
+    if text.trim().is_empty() {
+        return Ok(None);
+    }
+    Json::parse(text)
+        .map(Some)
+        .map_err(|e| DocumentError::InvalidDocument(e.to_string()))
+}
+
+fn search_response(collection: &str, result: SearchResult) -> Json {
+    let hits = result
+        .hits
+        .into_iter()
+        .map(|hit| {
+            Json::object([
+                ("_index", Json::from(collection)),
+                ("_id", Json::from(hit.id)),
+                ("_score", Json::from(hit.score)),
+                ("_source", hit.source),
+            ])
+        })
+        .collect();
+    Json::object([(
+        "hits",
+        Json::object([
+            (
+                "total",
+                Json::object([
+                    ("value", Json::from(result.total)),
+                    ("relation", Json::from("eq")),
+                ]),
+            ),
+            (
+                "max_score",
+                result.max_score.map(Json::from).unwrap_or(Json::Null),
+            ),
+            ("hits", Json::Array(hits)),
+        ]),
+    )])
+}
+
+/// Decodes `%XX` escapes, invalid ones are kept as they are
+fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut index = 0;
+    while index < bytes.len() {
+        let escaped = (bytes[index] == b'%')
+            .then(|| text.get(index + 1..index + 3))
+            .flatten()
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match escaped {
+            Some(byte) => {
+                decoded.push(byte);
+                index += 3;
+            }
+            None => {
+                decoded.push(bytes[index]);
+                index += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[derive(Debug)]
+struct Request {
+    method: String,
+    path: String,
+    /// URL parameters, `+` already decoded to a space
+    parameters: Vec<(String, String)>,
+    body: Vec<u8>,
+    keep_alive: bool,
+}
+
+impl Request {
+    /// Next request, None at the end of the input
+    fn read(reader: &mut impl BufRead) -> Result<Option<Request>, HttpError> {
+        let Some(request_line) = Self::read_line(reader)? else {
+            return Ok(None);
+        };
+        let mut parts = request_line.split_whitespace();
+        let (Some(method), Some(target), Some(version), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            return Err(HttpError::Malformed(format!(
+                "invalid request line {}",
+                request_line
+            )));
+        };
+        if !version.starts_with("HTTP/1.") {
+            return Err(HttpError::Malformed(format!(
+                "unsupported version {}",
+                version
+            )));
+        }
+        let mut keep_alive = version != "HTTP/1.0";
+        let mut content_length = 0;
+        for _ in 0..=MAX_HEADER_LINES {
+            let line = Self::read_line(reader)?
+                .ok_or_else(|| HttpError::Malformed("unexpected end of headers".to_string()))?;
+            if line.is_empty() {
+                let mut body = vec![0u8; content_length];
+                reader.read_exact(&mut body).map_err(HttpError::IoError)?;
+                let (path, query) = target.split_once('?').unwrap_or((target, ""));
+                let parameters = query
+                    .split('&')
+                    .filter(|parameter| !parameter.is_empty())
+                    .map(|parameter| {
+                        let (name, value) = parameter.split_once('=').unwrap_or((parameter, ""));
+                        (
+                            percent_decode(&name.replace('+', " ")),
+                            percent_decode(&value.replace('+', " ")),
+                        )
+                    })
+                    .collect();
+                return Ok(Some(Request {
+                    method: method.to_string(),
+                    path: path.to_string(),
+                    parameters,
+                    body,
+                    keep_alive,
+                }));
+            }
+            let (name, value) = line
+                .split_once(':')
+                .ok_or_else(|| HttpError::Malformed(format!("invalid header {}", line)))?;
+            let value = value.trim();
+            match name.trim().to_ascii_lowercase().as_str() {
+                "content-length" => {
+                    content_length = value
+                        .parse::<usize>()
+                        .ok()
+                        .filter(|length| *length <= MAX_BODY_LENGTH)
+                        .ok_or_else(|| {
+                            HttpError::Malformed(format!("invalid content length {}", value))
+                        })?
+                }
+                "transfer-encoding" => {
+                    return Err(HttpError::Malformed(
+                        "transfer encodings are not supported".to_string(),
+                    ))
+                }
+                "connection" => match value.to_ascii_lowercase().as_str() {
+                    "close" => keep_alive = false,
+                    "keep-alive" => keep_alive = true,
+                    _ => {}
+                },
+                _ => {}
+            }
+        }
+        Err(HttpError::Malformed("too many headers".to_string()))
+    }
+
+    /// Line without its line ending, None at the end of the input
+    fn read_line(reader: &mut impl BufRead) -> Result<Option<String>, HttpError> {
+        let mut line = vec![];
+        if reader
+            .read_until(b'\n', &mut line)
+            .map_err(HttpError::IoError)?
+            == 0
+        {
+            return Ok(None);
+        }
+        while line
+            .last()
+            .is_some_and(|byte| *byte == b'\n' || *byte == b'\r')
+        {
+            line.pop();
+        }
+        String::from_utf8(line)
+            .map(Some)
+            .map_err(|_| HttpError::Malformed("header is not UTF-8".to_string()))
+    }
+
+    fn parameter(&self, name: &str) -> Option<String> {
+        self.parameters
+            .iter()
+            .find(|(parameter, _)| parameter == name)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+#[derive(Debug)]
+struct Response {
+    status: u16,
+    body: Json,
+}
+
+impl Response {
+    fn ok(body: Json) -> Self {
+        Self { status: 200, body }
+    }
+
+    fn error(status: u16, kind: &str, reason: &str) -> Self {
+        Self {
+            status,
+            body: Json::object([
+                (
+                    "error",
+                    Json::object([("type", Json::from(kind)), ("reason", Json::from(reason))]),
+                ),
+                ("status", Json::from(status as usize)),
+            ]),
+        }
+    }
+
+    fn write(&self, writer: &mut impl Write, keep_alive: bool) -> Result<(), HttpError> {
+        let reason = match self.status {
+            200 => "OK",
+            201 => "Created",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            _ => "Internal Server Error",
+        };
+        let body = format!("{}\n", self.body);
+        let connection = if keep_alive { "keep-alive" } else { "close" };
+        write!(
+            writer,
+            "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+             Connection: {}\r\n\r\n{}",
+            self.status,
+            reason,
+            body.len(),
+            connection,
+            body
+        )
+        .and_then(|_| writer.flush())
+        .map_err(HttpError::IoError)
+    }
+}
+
+#[derive(Debug)]
+pub enum HttpError {
+    IoError(std::io::Error),
+    /// The client sent something not following the protocol, the connection is closed
+    Malformed(String),
+}
+
+impl Display for HttpError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            HttpError::IoError(e) => write!(f, "HTTP IoError {}", e),
+            HttpError::Malformed(message) => write!(f, "HTTP: malformed request, {}", message),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::server::http::HttpServer;
+    use crate::utils::json::Json;
+    use crate::utils::logging::init_logging;
+    use std::io::Cursor;
+
+    /// Sends the requests (method, target, body) on one connection, returns the statuses and
+    /// bodies of the responses
+    fn converse(server: &HttpServer, requests: &[(&str, &str, &str)]) -> Vec<(u16, Json)> {
+        let input: String = requests
+            .iter()
+            .map(|(method, target, body)| {
+                format!(
+                    "{} {} HTTP/1.1\r\nHost: localhost\r\nContent-Length: {}\r\n\r\n{}",
+                    method,
+                    target,
+                    body.len(),
+                    body
+                )
+            })
+            .collect();
+        let mut output = vec![];
+        server
+            .serve_connection(Cursor::new(input.into_bytes()), &mut output)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        output
+            .split("HTTP/1.1 ")
+            .skip(1)
+            .map(|response| {
+                let status = response[..3].parse().unwrap();
+                let (_, body) = response.split_once("\r\n\r\n").unwrap();
+                (status, Json::parse(body).unwrap())
+            })
+            .collect()
+    }
+
+    fn ids(response: &Json) -> Vec<String> {
+        response
+            .get("hits")
+            .and_then(|hits| hits.get("hits"))
+            .and_then(Json::as_array)
+            .unwrap()
+            .iter()
+            .map(|hit| hit.get("_id").unwrap().as_str().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_documents_and_search() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("http_documents")
+            .build()
+            .unwrap();
+        let server = HttpServer::new(&hfdb);
+        let responses = converse(
+            &server,
+            &[
+                (
+                    "PUT",
+                    "/books/_doc/1",
+                    r#"{"title": "The Quick Brown Fox", "year": 1999, "tags": ["animals"]}"#,
+                ),
+                (
+                    "PUT",
+                    "/books/_doc/2",
+                    r#"{"title": "Brown bread baking", "year": 2010, "author": {"name": "Fox"}}"#,
+                ),
+                (
+                    "PUT",
+                    "/books/_doc/3",
+                    r#"{"title": "A fox, a fox, a quick brown fox!", "year": 2020}"#,
+                ),
+                ("POST", "/books/_doc", r#"{"title": "Nothing in common"}"#),
+                (
+                    "PUT",
+                    "/books/_doc/2",
+                    r#"{"title": "Brown bread", "year": 2011}"#,
+                ),
+                ("GET", "/books/_doc/2", ""),
+                ("GET", "/books/_search?q=fox", ""),
+                (
+                    "POST",
+                    "/books/_search",
+                    r#"{"query": {"match_phrase": {"title": "quick brown"}}}"#,
+                ),
+                (
+                    "GET",
+                    "/books/_search",
+                    r#"{"query": {"bool": {"must": {"match": {"title": "brown"}},
+                        "filter": [{"range": {"year": {"gte": 2000}}}],
+                        "must_not": {"term": {"year": 2020}}}}}"#,
+                ),
+                ("DELETE", "/books/_doc/1", ""),
+                ("DELETE", "/books/_doc/1", ""),
+                ("GET", "/books/_search?q=title:brown+fox&size=1&from=1", ""),
+            ],
+        );
+        let statuses: Vec<u16> = responses.iter().map(|(status, _)| *status).collect();
+        assert_eq!(
+            statuses,
+            vec![201, 201, 201, 201, 200, 200, 200, 200, 200, 200, 404, 200]
+        );
+        assert_eq!(
+            responses[5].1.get("_source").unwrap().to_string(),
+            r#"{"title":"Brown bread","year":2011}"#
+        );
+        // Document 3 has "fox" three times, the author of document 2 named Fox was replaced
+        assert_eq!(ids(&responses[6].1), vec!["3", "1"]);
+        assert_eq!(ids(&responses[7].1), vec!["1", "3"]);
+        assert_eq!(ids(&responses[8].1), vec!["2"]);
+        let hits = responses[11].1.get("hits").unwrap();
+        assert_eq!(
+            hits.get("total").unwrap().get("value"),
+            Some(&Json::from(2))
+        );
+        assert_eq!(ids(&responses[11].1), vec!["2"]);
+
+        let responses = converse(
+            &server,
+            &[
+                ("GET", "/_collections", ""),
+                ("GET", "/missing/_search", ""),
+                ("PUT", "/Bad-Name/_doc/1", "{}"),
+                ("PUT", "/books/_doc/9", "[1, 2]"),
+                ("POST", "/books/_search", r#"{"query": {"fuzzy": {}}}"#),
+                ("PATCH", "/books/_doc/1", ""),
+                ("GET", "/books/_stats", ""),
+            ],
+        );
+        assert_eq!(
+            responses[0].1.to_string(),
+            r#"{"collections":[{"name":"books","documents":3}]}"#
+        );
+        let statuses: Vec<u16> = responses.iter().map(|(status, _)| *status).collect();
+        assert_eq!(statuses, vec![200, 404, 400, 400, 400, 405, 404]);
+    }
+}
@@ -0,0 +1,677 @@
+use crate::metadata_management::catalog::{Catalog, CatalogError, IndexType};
+use crate::record_management::layout::{LayoutError, Row};
+use crate::record_management::schema::{ColumnType, Schema};
+use crate::record_management::table::{Table, TableError};
+use crate::record_management::table_scan::RecordId;
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::TransactionError;
+use crate::transaction_management::transaction_manager::TransactionManager;
+use std::fmt::{Display, Formatter};
+use std::ops::Bound;
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Kind of value stored under a key
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum KeyKind {
+    String,
+    Hash,
+    List,
+}
+
+impl KeyKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            KeyKind::String => "string",
+            KeyKind::Hash => "hash",
+            KeyKind::List => "list",
+        }
+    }
+
+    fn number(&self) -> i64 {
+        match self {
+            KeyKind::String => 0,
+            KeyKind::Hash => 1,
+            KeyKind::List => 2,
+        }
+    }
+
+    fn from_number(number: i64) -> Option<KeyKind> {
+        match number {
+            0 => Some(KeyKind::String),
+            1 => Some(KeyKind::Hash),
+            2 => Some(KeyKind::List),
+            _ => None,
+        }
+    }
+}
+
+/// Key-value data stored in two tables, so it is logged, recovered and visible to SQL like any
+/// other table:
+/// * `kv_keys` holds every key with its kind, the value of strings and the expiry time in
+///   milliseconds since the epoch
+/// * `kv_elements` holds the fields of hashes and the elements of lists, ordered by position
+///
+/// Commands run one after the other in their own transaction, as in Redis, so they never
+/// conflict. Expired keys are ignored and removed when they are accessed.
+#[derive(Debug, Clone)]
+pub struct KeyValueStore {
+    transaction_manager: TransactionManager,
+    catalog: Catalog,
+    serializer: Arc<Mutex<()>>,
+}
+
+impl KeyValueStore {
+    pub const KEYS_TABLE: &'static str = "kv_keys";
+    pub const ELEMENTS_TABLE: &'static str = "kv_elements";
+    const KEYS_INDEX: &'static str = "kv_keys_key";
+    const ELEMENTS_INDEX: &'static str = "kv_elements_key";
+
+    /// Store on the tables, which are created if they do not exist yet
+    pub fn new(
+        transaction_manager: &TransactionManager,
+        catalog: &Catalog,
+    ) -> Result<Self, KeyValueError> {
+        let transaction = transaction_manager
+            .begin()
+            .map_err(KeyValueError::TransactionError)?;
+        for (table_name, index_name, schema) in [
+            (
+                Self::KEYS_TABLE,
+                Self::KEYS_INDEX,
+                Schema::new()
+                    .with_column("key", ColumnType::Varbinary)
+                    .with_column("kind", ColumnType::TinyInteger)
+                    .with_nullable_column("value", ColumnType::Varbinary)
+                    .with_nullable_column("expires_at", ColumnType::BigInteger),
+            ),
+            (
+                Self::ELEMENTS_TABLE,
+                Self::ELEMENTS_INDEX,
+                Schema::new()
+                    .with_column("key", ColumnType::Varbinary)
+                    .with_nullable_column("field", ColumnType::Varbinary)
+                    .with_nullable_column("position", ColumnType::BigInteger)
+                    .with_column("value", ColumnType::Varbinary),
+            ),
+        ] {
+            if catalog
+                .table(&transaction, table_name)
+                .map_err(KeyValueError::CatalogError)?
+                .is_none()
+            {
+                catalog
+                    .create_table(&transaction, table_name, schema)
+                    .map_err(KeyValueError::CatalogError)?;
+                Table::open(&transaction, catalog, table_name)
+                    .and_then(|mut table| {
+                        table.create_index(catalog, index_name, "key", IndexType::BTree)
+                    })
+                    .map_err(KeyValueError::TableError)?;
+            }
+        }
+        transaction
+            .commit()
+            .map_err(KeyValueError::TransactionError)?;
+        Ok(Self {
+            transaction_manager: transaction_manager.clone(),
+            catalog: catalog.clone(),
+            serializer: Arc::new(Mutex::new(())),
+        })
+    }
+
+    /// Runs the command in a transaction, committed if the command succeeded
+    pub fn run<R>(
+        &self,
+        command: impl FnOnce(&mut KeyValueTransaction) -> Result<R, KeyValueError>,
+    ) -> Result<R, KeyValueError> {
+        let _serialized = self.serializer.lock().unwrap();
+        let transaction = self
+            .transaction_manager
+            .begin()
+            .map_err(KeyValueError::TransactionError)?;
+        let tables = Table::open(&transaction, &self.catalog, Self::KEYS_TABLE).and_then(|keys| {
+            Table::open(&transaction, &self.catalog, Self::ELEMENTS_TABLE)
+                .map(|elements| (keys, elements))
+        });
+        let result = tables
+            .map_err(KeyValueError::TableError)
+            .and_then(|(keys, elements)| {
+                command(&mut KeyValueTransaction {
+                    keys,
+                    elements,
+                    now: now_milliseconds(),
+                })
+            });
+        match result {
+            Ok(result) => {
+                transaction
+                    .commit()
+                    .map_err(KeyValueError::TransactionError)?;
+                Ok(result)
+            }
+            Err(e) => {
+                transaction
+                    .rollback()
+                    .map_err(KeyValueError::TransactionError)?;
+                Err(e)
+            }
+        }
+    }
+}
+
+/// Milliseconds since the epoch, the unit of expiry times
+pub fn now_milliseconds() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_millis() as i64)
+        .unwrap_or(0)
+}
+
+/// Field and value of a hash
+pub type HashEntry = (Vec<u8>, Vec<u8>);
+/// Keys found and the key to go on from, None when there are no more keys
+pub type KeysPage = (Vec<Vec<u8>>, Option<Vec<u8>>);
+
+/// Operations of one command on the key-value tables
+pub struct KeyValueTransaction {
+    keys: Table,
+    elements: Table,
+    now: i64,
+}
+
+impl KeyValueTransaction {
+    /// Time the command started, in milliseconds since the epoch
+    pub fn now(&self) -> i64 {
+        self.now
+    }
+
+    pub fn kind(&mut self, key: &[u8]) -> Result<Option<KeyKind>, KeyValueError> {
+        match self.entry(key)? {
+            Some((_, row)) => Self::kind_of(&row).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    pub fn exists(&mut self, key: &[u8]) -> Result<bool, KeyValueError> {
+        Ok(self.entry(key)?.is_some())
+    }
+
+    /// Removes the key with its fields or elements, false if it did not exist
+    pub fn delete(&mut self, key: &[u8]) -> Result<bool, KeyValueError> {
+        match self.entry(key)? {
+            Some((record_id, _)) => {
+                self.remove(key, &record_id)?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Expiry time of the key: None if the key does not exist, Some(None) if it does not expire
+    pub fn expires_at(&mut self, key: &[u8]) -> Result<Option<Option<i64>>, KeyValueError> {
+        match self.entry(key)? {
+            Some((_, row)) => Ok(Some(row.get_int("expires_at").map_err(layout_error)?)),
+            None => Ok(None),
+        }
+    }
+
+    /// Sets or removes the expiry time, false if the key does not exist
+    pub fn set_expires_at(
+        &mut self,
+        key: &[u8],
+        expires_at: Option<i64>,
+    ) -> Result<bool, KeyValueError> {
+        let Some((record_id, mut row)) = self.entry(key)? else {
+            return Ok(false);
+        };
+        match expires_at {
+            Some(expires_at) => row.set_int("expires_at", expires_at),
+            None => row.set_null("expires_at"),
+        }
+        .map_err(layout_error)?;
+        self.keys
+            .update(&record_id, &row)
+            .map_err(KeyValueError::TableError)?;
+        Ok(true)
+    }
+
+    /// Keys not expired, in byte order
+    pub fn keys(&mut self) -> Result<Vec<Vec<u8>>, KeyValueError> {
+        let mut keys = vec![];
+        for (_, row) in self
+            .keys
+            .range(KeyValueStore::KEYS_INDEX, ..)
+            .map_err(KeyValueError::TableError)?
+        {
+            if !self.is_expired(&row)? {
+                keys.push(Self::bytes(&row, "key")?);
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Up to count live keys in order, starting at the given key, and the key to go on from
+    pub fn keys_from(
+        &mut self,
+        start: Option<&[u8]>,
+        count: usize,
+    ) -> Result<KeysPage, KeyValueError> {
+        let start = start.map(|key| Value::from(key.to_vec()));
+        let lower = match &start {
+            Some(key) => Bound::Included(key),
+            None => Bound::Unbounded,
+        };
+        let mut rows = self
+            .keys
+            .range_limited(
+                KeyValueStore::KEYS_INDEX,
+                (lower, Bound::Unbounded),
+                count.saturating_add(1),
+            )
+            .map_err(KeyValueError::TableError)?;
+        let next = match rows.get(count) {
+            Some((_, row)) => Some(Self::bytes(row, "key")?),
+            None => None,
+        };
+        rows.truncate(count);
+        let mut keys = vec![];
+        for (_, row) in rows {
+            if !self.is_expired(&row)? {
+                keys.push(Self::bytes(&row, "key")?);
+            }
+        }
+        Ok((keys, next))
+    }
+
+    pub fn get(&mut self, key: &[u8]) -> Result<Option<Vec<u8>>, KeyValueError> {
+        match self.typed_entry(key, KeyKind::String)? {
+            Some((_, row)) => Self::bytes(&row, "value").map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Stores the string, replacing the key whatever kind of value it held
+    pub fn set(
+        &mut self,
+        key: &[u8],
+        value: &[u8],
+        expires_at: Option<i64>,
+    ) -> Result<(), KeyValueError> {
+        let existing = self.entry(key)?;
+        let mut row = self.keys.layout().row();
+        row.set_bytes("key", key)
+            .and_then(|_| row.set_int("kind", KeyKind::String.number()))
+            .and_then(|_| row.set_bytes("value", value))
+            .and_then(|_| match expires_at {
+                Some(expires_at) => row.set_int("expires_at", expires_at),
+                None => row.set_null("expires_at"),
+            })
+            .map_err(layout_error)?;
+        match existing {
+            Some((record_id, existing_row)) => {
+                if Self::kind_of(&existing_row)? != KeyKind::String {
+                    self.remove_elements(key)?;
+                }
+                self.keys.update(&record_id, &row)
+            }
+            None => self.keys.insert(&row),
+        }
+        .map_err(KeyValueError::TableError)?;
+        Ok(())
+    }
+
+    pub fn hash_get(&mut self, key: &[u8], field: &[u8]) -> Result<Option<Vec<u8>>, KeyValueError> {
+        if self.typed_entry(key, KeyKind::Hash)?.is_none() {
+            return Ok(None);
+        }
+        match self.field(key, field)? {
+            Some((_, row)) => Self::bytes(&row, "value").map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Sets the field of the hash, created if needed, true if the field is new
+    pub fn hash_set(
+        &mut self,
+        key: &[u8],
+        field: &[u8],
+        value: &[u8],
+    ) -> Result<bool, KeyValueError> {
+        if self.typed_entry(key, KeyKind::Hash)?.is_none() {
+            self.create(key, KeyKind::Hash)?;
+        }
+        let existing = self.field(key, field)?;
+        let mut row = self.elements.layout().row();
+        row.set_bytes("key", key)
+            .and_then(|_| row.set_bytes("field", field))
+            .and_then(|_| row.set_bytes("value", value))
+            .map_err(layout_error)?;
+        match &existing {
+            Some((record_id, _)) => self.elements.update(record_id, &row),
+            None => self.elements.insert(&row),
+        }
+        .map_err(KeyValueError::TableError)?;
+        Ok(existing.is_none())
+    }
+
+    /// Removes the field, and the hash with its last field, false if there was no such field
+    pub fn hash_delete(&mut self, key: &[u8], field: &[u8]) -> Result<bool, KeyValueError> {
+        let Some((key_record_id, _)) = self.typed_entry(key, KeyKind::Hash)? else {
+            return Ok(false);
+        };
+        let Some((record_id, _)) = self.field(key, field)? else {
+            return Ok(false);
+        };
+        self.elements
+            .delete(&record_id)
+            .map_err(KeyValueError::TableError)?;
+        if self.element_rows(key)?.is_empty() {
+            self.remove(key, &key_record_id)?;
+        }
+        Ok(true)
+    }
+
+    /// Fields and values of the hash, ordered by field
+    pub fn hash_entries(&mut self, key: &[u8]) -> Result<Vec<HashEntry>, KeyValueError> {
+        if self.typed_entry(key, KeyKind::Hash)?.is_none() {
+            return Ok(vec![]);
+        }
+        let mut entries = self
+            .element_rows(key)?
+            .iter()
+            .map(|(_, row)| Ok((Self::bytes(row, "field")?, Self::bytes(row, "value")?)))
+            .collect::<Result<Vec<_>, KeyValueError>>()?;
+        entries.sort();
+        Ok(entries)
+    }
+
+    /// Adds the values one after the other at the front or the back of the list, created if
+    /// needed, returns the length of the list
+    pub fn list_push(
+        &mut self,
+        key: &[u8],
+        values: &[Vec<u8>],
+        front: bool,
+    ) -> Result<usize, KeyValueError> {
+        if self.typed_entry(key, KeyKind::List)?.is_none() {
+            self.create(key, KeyKind::List)?;
+        }
+        let elements = self.list_elements(key)?;
+        let mut position = match (front, elements.first(), elements.last()) {
+            (true, Some((_, first, _)), _) => *first,
+            (false, _, Some((_, last, _))) => *last,
+            _ => 0,
+        };
+        for (index, value) in values.iter().enumerate() {
+            if index > 0 || !elements.is_empty() {
+                position += if front { -1 } else { 1 };
+            }
+            let mut row = self.elements.layout().row();
+            row.set_bytes("key", key)
+                .and_then(|_| row.set_int("position", position))
+                .and_then(|_| row.set_bytes("value", value))
+                .map_err(layout_error)?;
+            self.elements
+                .insert(&row)
+                .map_err(KeyValueError::TableError)?;
+        }
+        Ok(elements.len() + values.len())
+    }
+
+    /// Removes up to count values from the front or the back of the list, and the list with its
+    /// last value
+    pub fn list_pop(
+        &mut self,
+        key: &[u8],
+        count: usize,
+        front: bool,
+    ) -> Result<Vec<Vec<u8>>, KeyValueError> {
+        let Some((key_record_id, _)) = self.typed_entry(key, KeyKind::List)? else {
+            return Ok(vec![]);
+        };
+        let mut elements = self.list_elements(key)?;
+        if !front {
+            elements.reverse();
+        }
+        let mut values = vec![];
+        for (record_id, _, value) in elements.iter().take(count) {
+            self.elements
+                .delete(record_id)
+                .map_err(KeyValueError::TableError)?;
+            values.push(value.clone());
+        }
+        if values.len() == elements.len() {
+            self.remove(key, &key_record_id)?;
+        }
+        Ok(values)
+    }
+
+    /// Values of the list from front to back
+    pub fn list_values(&mut self, key: &[u8]) -> Result<Vec<Vec<u8>>, KeyValueError> {
+        if self.typed_entry(key, KeyKind::List)?.is_none() {
+            return Ok(vec![]);
+        }
+        Ok(self
+            .list_elements(key)?
+            .into_iter()
+            .map(|(_, _, value)| value)
+            .collect())
+    }
+
+    /// Entry of the key if it exists, an expired one is removed
+    fn entry(&mut self, key: &[u8]) -> Result<Option<(RecordId, Row)>, KeyValueError> {
+        let Some((record_id, row)) = self
+            .keys
+            .lookup(KeyValueStore::KEYS_INDEX, &Value::from(key.to_vec()))
+            .map_err(KeyValueError::TableError)?
+            .pop()
+        else {
+            return Ok(None);
+        };
+        if self.is_expired(&row)? {
+            self.remove(key, &record_id)?;
+            return Ok(None);
+        }
+        Ok(Some((record_id, row)))
+    }
+
+    /// Entry of the key if it exists, failing if it holds another kind of value
+    fn typed_entry(
+        &mut self,
+        key: &[u8],
+        kind: KeyKind,
+    ) -> Result<Option<(RecordId, Row)>, KeyValueError> {
+        match self.entry(key)? {
+            Some((record_id, row)) => {
+                if Self::kind_of(&row)? != kind {
+                    return Err(KeyValueError::WrongType);
+                }
+                Ok(Some((record_id, row)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn create(&mut self, key: &[u8], kind: KeyKind) -> Result<(), KeyValueError> {
+        let mut row = self.keys.layout().row();
+        row.set_bytes("key", key)
+            .and_then(|_| row.set_int("kind", kind.number()))
+            .map_err(layout_error)?;
+        self.keys.insert(&row).map_err(KeyValueError::TableError)?;
+        Ok(())
+    }
+
+    fn remove(&mut self, key: &[u8], record_id: &RecordId) -> Result<(), KeyValueError> {
+        self.keys
+            .delete(record_id)
+            .map_err(KeyValueError::TableError)?;
+        self.remove_elements(key)
+    }
+
+    fn remove_elements(&mut self, key: &[u8]) -> Result<(), KeyValueError> {
+        for (record_id, _) in self.element_rows(key)? {
+            self.elements
+                .delete(&record_id)
+                .map_err(KeyValueError::TableError)?;
+        }
+        Ok(())
+    }
+
+    fn element_rows(&self, key: &[u8]) -> Result<Vec<(RecordId, Row)>, KeyValueError> {
+        self.elements
+            .lookup(KeyValueStore::ELEMENTS_INDEX, &Value::from(key.to_vec()))
+            .map_err(KeyValueError::TableError)
+    }
+
+    fn field(&self, key: &[u8], field: &[u8]) -> Result<Option<(RecordId, Row)>, KeyValueError> {
+        for (record_id, row) in self.element_rows(key)? {
+            if Self::bytes(&row, "field")? == field {
+                return Ok(Some((record_id, row)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Elements of the list with their positions, from front to back
+    fn list_elements(&self, key: &[u8]) -> Result<Vec<(RecordId, i64, Vec<u8>)>, KeyValueError> {
+        let mut elements = self
+            .element_rows(key)?
+            .into_iter()
+            .map(|(record_id, row)| {
+                let position = row
+                    .get_int("position")
+                    .map_err(layout_error)?
+                    .unwrap_or_default();
+                Ok((record_id, position, Self::bytes(&row, "value")?))
+            })
+            .collect::<Result<Vec<_>, KeyValueError>>()?;
+        elements.sort_by_key(|(_, position, _)| *position);
+        Ok(elements)
+    }
+
+    fn is_expired(&self, row: &Row) -> Result<bool, KeyValueError> {
+        Ok(row
+            .get_int("expires_at")
+            .map_err(layout_error)?
+            .is_some_and(|expires_at| expires_at <= self.now))
+    }
+
+    fn kind_of(row: &Row) -> Result<KeyKind, KeyValueError> {
+        let number = row
+            .get_int("kind")
+            .map_err(layout_error)?
+            .unwrap_or_default();
+        KeyKind::from_number(number).ok_or(KeyValueError::UnknownKind(number))
+    }
+
+    fn bytes(row: &Row, name: &str) -> Result<Vec<u8>, KeyValueError> {
+        Ok(row
+            .get_bytes(name)
+            .map_err(layout_error)?
+            .unwrap_or_default())
+    }
+}
+
+fn layout_error(error: LayoutError) -> KeyValueError {
+    KeyValueError::TableError(TableError::LayoutError(error))
+}
+
+#[derive(Debug)]
+pub enum KeyValueError {
+    TransactionError(TransactionError),
+    CatalogError(CatalogError),
+    TableError(TableError),
+    UnknownKind(i64),
+    /// The key holds another kind of value than the operation needs
+    WrongType,
+}
+
+impl Display for KeyValueError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            KeyValueError::TransactionError(e) => write!(f, "KeyValue {}", e),
+            KeyValueError::CatalogError(e) => write!(f, "KeyValue {}", e),
+            KeyValueError::TableError(e) => write!(f, "KeyValue {}", e),
+            KeyValueError::UnknownKind(number) => {
+                write!(f, "KeyValue: unknown kind {} of key", number)
+            }
+            KeyValueError::WrongType => {
+                write!(f, "KeyValue: key holds the wrong kind of value")
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::server::key_value::{KeyKind, KeyValueError, KeyValueStore};
+    use crate::utils::logging::init_logging;
+
+    fn values(values: &[&str]) -> Vec<Vec<u8>> {
+        values
+            .iter()
+            .map(|value| value.as_bytes().to_vec())
+            .collect()
+    }
+
+    #[test]
+    fn test_key_value_store() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("key_value_store")
+            .build()
+            .unwrap();
+        let store = KeyValueStore::new(&hfdb.transaction_manager, &hfdb.catalog).unwrap();
+        store
+            .run(|kv| {
+                kv.set(b"name", b"Ada", None)?;
+                kv.set(b"gone", b"soon", Some(kv.now() - 1))?;
+                kv.hash_set(b"person", b"age", b"36")?;
+                kv.list_push(b"queue", &values(&["b", "c"]), false)?;
+                kv.list_push(b"queue", &values(&["a"]), true)
+            })
+            .unwrap();
+
+        store
+            .run(|kv| {
+                assert_eq!(kv.get(b"name")?, Some(b"Ada".to_vec()));
+                assert_eq!(kv.get(b"gone")?, None);
+                assert_eq!(kv.kind(b"person")?, Some(KeyKind::Hash));
+                assert!(kv.hash_set(b"person", b"name", b"Ada")?);
+                assert!(!kv.hash_set(b"person", b"age", b"37")?);
+                assert_eq!(
+                    kv.hash_entries(b"person")?,
+                    vec![
+                        (b"age".to_vec(), b"37".to_vec()),
+                        (b"name".to_vec(), b"Ada".to_vec())
+                    ]
+                );
+                assert!(matches!(kv.get(b"person"), Err(KeyValueError::WrongType)));
+                assert_eq!(kv.list_values(b"queue")?, values(&["a", "b", "c"]));
+                assert_eq!(kv.list_pop(b"queue", 1, false)?, values(&["c"]));
+                assert_eq!(kv.keys()?, values(&["name", "person", "queue"]));
+                Ok(())
+            })
+            .unwrap();
+
+        // A failing command is rolled back
+        assert!(store
+            .run(|kv| {
+                kv.delete(b"name")?;
+                kv.get(b"queue")
+            })
+            .is_err());
+        store
+            .run(|kv| {
+                assert!(kv.exists(b"name")?);
+                assert_eq!(kv.list_pop(b"queue", 5, true)?, values(&["a", "b"]));
+                assert!(!kv.exists(b"queue")?);
+                assert!(kv.hash_delete(b"person", b"age")?);
+                assert!(kv.hash_delete(b"person", b"name")?);
+                assert_eq!(kv.keys()?, values(&["name"]));
+                Ok(())
+            })
+            .unwrap();
+    }
+}
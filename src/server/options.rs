@@ -0,0 +1,239 @@
+use crate::db_management_system::hfdb::HanfriedDb;
+use crate::file_management::file_manager::Durability;
+use crate::transaction_management::recovery_manager::RecoveryError;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// Command line options shared by the server binaries:
+/// `--listen ADDRESS --db-directory DIR --block-size BYTES --pool-size BUFFERS
+/// --log-file NAME --max-open-files N --durability sync|group-commit:MILLIS|relaxed`, every
+/// option has a default. `-h` or `--help` asks for the usage.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ServerOptions {
+    pub listen: String,
+    pub db_directory: String,
+    pub block_size: usize,
+    pub pool_size: usize,
+    pub log_file: String,
+    pub max_open_files: usize,
+    pub durability: Durability,
+    pub help: bool,
+}
+
+impl ServerOptions {
+    const DEFAULT_DB_DIRECTORY: &'static str = "/data/hanfried-db";
+    const DEFAULT_BLOCK_SIZE: usize = 4096;
+    const DEFAULT_POOL_SIZE: usize = 1024;
+    const DEFAULT_LOG_FILE: &'static str = "hfdb.log";
+    const DEFAULT_MAX_OPEN_FILES: usize = 512;
+
+    pub fn new(listen: &str) -> Self {
+        Self {
+            listen: listen.to_string(),
+            db_directory: Self::DEFAULT_DB_DIRECTORY.to_string(),
+            block_size: Self::DEFAULT_BLOCK_SIZE,
+            pool_size: Self::DEFAULT_POOL_SIZE,
+            log_file: Self::DEFAULT_LOG_FILE.to_string(),
+            max_open_files: Self::DEFAULT_MAX_OPEN_FILES,
+            durability: Durability::Sync,
+            help: false,
+        }
+    }
+
+    /// Options given as arguments (without the program name), the others keep the defaults
+    pub fn from_args(
+        default_listen: &str,
+        args: impl IntoIterator<Item = String>,
+    ) -> Result<Self, OptionsError> {
+        let mut options = Self::new(default_listen);
+        let mut args = args.into_iter();
+        while let Some(option) = args.next() {
+            if option == "-h" || option == "--help" {
+                options.help = true;
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| OptionsError::MissingValue(option.clone()))?;
+            match option.as_str() {
+                "--listen" => options.listen = value,
+                _ => {
+                    if !options.set_database_option(&option, value)? {
+                        return Err(OptionsError::UnknownOption(option));
+                    }
+                }
+            }
+        }
+        Ok(options)
+    }
+
+    /// Sets one of the options of the database (all but `--listen`), false if the option is
+    /// none of them
+    pub fn set_database_option(
+        &mut self,
+        option: &str,
+        value: String,
+    ) -> Result<bool, OptionsError> {
+        match option {
+            "--db-directory" => self.db_directory = value,
+            "--block-size" => self.block_size = Self::positive(option, &value)?,
+            "--pool-size" => self.pool_size = Self::positive(option, &value)?,
+            "--log-file" => self.log_file = value,
+            "--max-open-files" => self.max_open_files = Self::positive(option, &value)?,
+            "--durability" => self.durability = Self::durability(option, &value)?,
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    fn positive(option: &str, value: &str) -> Result<usize, OptionsError> {
+        match value.parse::<usize>() {
+            Ok(number) if number > 0 => Ok(number),
+            _ => Err(OptionsError::InvalidValue(
+                option.to_string(),
+                value.to_string(),
+            )),
+        }
+    }
+
+    /// `sync`, `relaxed` or `group-commit:MILLIS` with the delay of a log flush
+    fn durability(option: &str, value: &str) -> Result<Durability, OptionsError> {
+        match value.split_once(':') {
+            None if value == "sync" => Ok(Durability::Sync),
+            None if value == "relaxed" => Ok(Durability::Relaxed),
+            Some(("group-commit", delay)) => Ok(Durability::GroupCommit(Duration::from_millis(
+                Self::positive(option, delay)? as u64,
+            ))),
+            _ => Err(OptionsError::InvalidValue(
+                option.to_string(),
+                value.to_string(),
+            )),
+        }
+    }
+
+    pub fn usage(program: &str, default_listen: &str) -> String {
+        format!(
+            "Usage: {program} [-h|--help] [--listen ADDRESS (default {default_listen})] {}",
+            Self::database_usage()
+        )
+    }
+
+    /// Usage of the options of the database
+    pub fn database_usage() -> String {
+        format!(
+            "[--db-directory DIR (default {})] [--block-size BYTES (default {})] \
+             [--pool-size BUFFERS (default {})] [--log-file NAME (default {})] \
+             [--max-open-files N (default {})] \
+             [--durability sync|group-commit:MILLIS|relaxed (default sync)]",
+            Self::DEFAULT_DB_DIRECTORY,
+            Self::DEFAULT_BLOCK_SIZE,
+            Self::DEFAULT_POOL_SIZE,
+            Self::DEFAULT_LOG_FILE,
+            Self::DEFAULT_MAX_OPEN_FILES
+        )
+    }
+
+    /// Opens the database, recovering it if needed
+    pub fn open_db(&self) -> Result<HanfriedDb, RecoveryError> {
+        HanfriedDb::new(
+            self.db_directory.clone(),
+            self.block_size,
+            self.log_file.clone(),
+            self.pool_size,
+            self.max_open_files,
+            self.durability,
+        )
+    }
+}
+
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum OptionsError {
+    UnknownOption(String),
+    MissingValue(String),
+    InvalidValue(String, String),
+}
+
+impl Display for OptionsError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OptionsError::UnknownOption(option) => write!(f, "Unknown option {}", option),
+            OptionsError::MissingValue(option) => write!(f, "Missing value of option {}", option),
+            OptionsError::InvalidValue(option, value) => {
+                write!(f, "Invalid value {} of option {}", value, option)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_management::file_manager::Durability;
+    use crate::server::options::{OptionsError, ServerOptions};
+    use std::time::Duration;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_server_options_from_args() {
+        assert_eq!(
+            ServerOptions::from_args("127.0.0.1:5432", args(&[])).unwrap(),
+            ServerOptions::new("127.0.0.1:5432")
+        );
+        let options = ServerOptions::from_args(
+            "127.0.0.1:5432",
+            args(&["--listen", "0.0.0.0:6543", "--pool-size", "3"]),
+        )
+        .unwrap();
+        assert_eq!(options.listen, "0.0.0.0:6543");
+        assert_eq!(options.pool_size, 3);
+        assert_eq!(options.block_size, 4096);
+        assert_eq!(options.durability, Durability::Sync);
+        assert!(!options.help);
+        assert!(
+            ServerOptions::from_args("", args(&["--help"]))
+                .unwrap()
+                .help
+        );
+        assert!(
+            ServerOptions::from_args("", args(&["--pool-size", "3", "-h"]))
+                .unwrap()
+                .help
+        );
+        assert_eq!(
+            ServerOptions::from_args("", args(&["--block-size", "0"])),
+            Err(OptionsError::InvalidValue(
+                "--block-size".to_string(),
+                "0".to_string()
+            ))
+        );
+        assert_eq!(
+            ServerOptions::from_args("", args(&["--durability", "group-commit:5"]))
+                .unwrap()
+                .durability,
+            Durability::GroupCommit(Duration::from_millis(5))
+        );
+        assert_eq!(
+            ServerOptions::from_args("", args(&["--durability", "relaxed"]))
+                .unwrap()
+                .durability,
+            Durability::Relaxed
+        );
+        assert_eq!(
+            ServerOptions::from_args("", args(&["--durability", "group-commit"])),
+            Err(OptionsError::InvalidValue(
+                "--durability".to_string(),
+                "group-commit".to_string()
+            ))
+        );
+        assert_eq!(
+            ServerOptions::from_args("", args(&["--verbose"])),
+            Err(OptionsError::MissingValue("--verbose".to_string()))
+        );
+        assert_eq!(
+            ServerOptions::from_args("", args(&["--verbose", "yes"])),
+            Err(OptionsError::UnknownOption("--verbose".to_string()))
+        );
+    }
+}
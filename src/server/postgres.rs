@@ -0,0 +1,1429 @@
+use crate::db_management_system::hfdb::HanfriedDb;
+use crate::index_management::btree::BTreeError;
+use crate::index_management::hash_index::HashIndexError;
+use crate::index_management::index::IndexError;
+use crate::metadata_management::catalog::{Catalog, CatalogError};
+use crate::metadata_management::statistics::StatisticsError;
+use crate::query_processing::ast::Statement;
+use crate::query_processing::executor::{ExecutionError, QueryResult, Tuple};
+use crate::query_processing::parser::parse;
+use crate::query_processing::plan::PlanColumn;
+use crate::query_processing::planner::PlannerError;
+use crate::query_processing::session::{Session, SessionError};
+use crate::record_management::record_page::RecordPageError;
+use crate::record_management::schema::ColumnType;
+use crate::record_management::table::TableError;
+use crate::record_management::table_scan::TableScanError;
+use crate::record_management::value::Value;
+use crate::transaction_management::transaction::TransactionError;
+use crate::transaction_management::transaction_manager::TransactionManager;
+use log::{debug, info, warn};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::io::{BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Arc;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const PROTOCOL_VERSION_3: i32 = 196608;
+const SSL_REQUEST: i32 = 80877103;
+const GSSENC_REQUEST: i32 = 80877104;
+const CANCEL_REQUEST: i32 = 80877102;
+/// Messages larger than this are rejected instead of allocating their length
+const MAX_MESSAGE_LENGTH: usize = 64 * 1024 * 1024;
+
+const BYTEA_OID: i32 = 17;
+const INT8_OID: i32 = 20;
+const INT2_OID: i32 = 21;
+const INT4_OID: i32 = 23;
+const TEXT_OID: i32 = 25;
+const VARCHAR_OID: i32 = 1043;
+const NUMERIC_OID: i32 = 1700;
+
+const TEXT_FORMAT: i16 = 0;
+const BINARY_FORMAT: i16 = 1;
+
+/// Server speaking the PostgreSQL frontend/backend protocol version 3, so psql and Postgres
+/// drivers can connect. Every connection gets its own thread and Session on the shared database.
+///
+/// Supported are the simple query protocol and the extended query protocol with prepared
+/// statements and portals. Parameters ($1, $2, ...) are bound by replacing them with SQL
+/// literals, in text format or binary for integers, text and bytea. There is no
+/// authentication, SSL or query cancellation. SET statements are accepted and ignored, as
+/// drivers send them when connecting.
+#[derive(Debug, Clone)]
+pub struct PostgresServer {
+    transaction_manager: TransactionManager,
+    catalog: Catalog,
+    connections: Arc<AtomicI32>,
+}
+
+impl PostgresServer {
+    pub const DEFAULT_LISTEN: &'static str = "127.0.0.1:5432";
+
+    pub fn new(hfdb: &HanfriedDb) -> Self {
+        Self {
+            transaction_manager: hfdb.transaction_manager.clone(),
+            catalog: hfdb.catalog.clone(),
+            connections: Arc::new(AtomicI32::new(0)),
+        }
+    }
+
+    /// Accepts connections until accepting fails
+    pub fn serve(&self, listener: TcpListener) -> std::io::Result<()> {
+        info!("Postgres server listening on {}", listener.local_addr()?);
+        loop {
+            let (stream, address) = listener.accept()?;
+            let server = self.clone();
+            thread::spawn(move || {
+                info!("Postgres connection from {}", address);
+                match server.serve_stream(stream) {
+                    Ok(()) => info!("Postgres connection from {} closed", address),
+                    Err(e) => warn!("Postgres connection from {} failed: {}", address, e),
+                }
+            });
+        }
+    }
+
+    pub fn serve_stream(&self, stream: TcpStream) -> Result<(), ProtocolError> {
+        stream.set_nodelay(true).map_err(ProtocolError::IoError)?;
+        let reader = stream.try_clone().map_err(ProtocolError::IoError)?;
+        self.serve_connection(reader, stream)
+    }
+
+    /// Serves one client until it terminates or disconnects
+    pub fn serve_connection<R: Read, W: Write>(
+        &self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), ProtocolError> {
+        let mut connection = Connection {
+            reader: BufReader::new(reader),
+            writer,
+            output: vec![],
+            session: Session::new(&self.transaction_manager, &self.catalog),
+            statements: HashMap::new(),
+            portals: HashMap::new(),
+            failed: false,
+        };
+        let process_id = self.connections.fetch_add(1, Ordering::Relaxed) + 1;
+        if connection.start(process_id)? {
+            connection.run()?;
+        }
+        Ok(())
+    }
+}
+
+/// Statement prepared by a Parse message, the parameter types are the ones the client declared
+struct PreparedStatement {
+    sql: String,
+    parameter_types: Vec<i32>,
+}
+
+/// Statement with bound parameters, its rows are kept while its execution is suspended
+struct Portal {
+    statement: Option<Statement>,
+    columns: Option<Vec<PlanColumn>>,
+    result_formats: Vec<i16>,
+    rows: Option<VecDeque<Tuple>>,
+    rows_sent: usize,
+}
+
+struct Connection<R: Read, W: Write> {
+    reader: BufReader<R>,
+    writer: W,
+    /// Messages not sent yet, written when the client waits for them
+    output: Vec<u8>,
+    session: Session,
+    statements: HashMap<String, PreparedStatement>,
+    portals: HashMap<String, Portal>,
+    /// An extended query message failed, messages are ignored until the next Sync
+    failed: bool,
+}
+
+impl<R: Read, W: Write> Connection<R, W> {
+    /// Handles the startup messages, false if the client does not continue with queries
+    fn start(&mut self, process_id: i32) -> Result<bool, ProtocolError> {
+        loop {
+            let Some(length) = self.read_i32_or_eof()? else {
+                return Ok(false);
+            };
+            let body = self.read_body(length, 4)?;
+            let mut message = MessageReader::new(&body);
+            let code = message.i32()?;
+            match code {
+                SSL_REQUEST | GSSENC_REQUEST => {
+                    self.writer
+                        .write_all(b"N")
+                        .and_then(|_| self.writer.flush())
+                        .map_err(ProtocolError::IoError)?;
+                }
+                CANCEL_REQUEST => return Ok(false),
+                PROTOCOL_VERSION_3 => {
+                    let mut parameters = vec![];
+                    loop {
+                        let name = message.cstring()?;
+                        if name.is_empty() {
+                            break;
+                        }
+                        parameters.push((name, message.cstring()?));
+                    }
+                    debug!("Postgres startup parameters {:?}", parameters);
+                    let mut authentication_ok = MessageWriter::new(b'R');
+                    authentication_ok.i32(0);
+                    self.send(authentication_ok);
+                    for (name, value) in [
+                        ("server_version", "14.0 (hanfried-db)"),
+                        ("server_encoding", "UTF8"),
+                        ("client_encoding", "UTF8"),
+                        ("DateStyle", "ISO, MDY"),
+                        ("integer_datetimes", "on"),
+                        ("standard_conforming_strings", "on"),
+                    ] {
+                        let mut parameter_status = MessageWriter::new(b'S');
+                        parameter_status.cstring(name);
+                        parameter_status.cstring(value);
+                        self.send(parameter_status);
+                    }
+                    let mut backend_key_data = MessageWriter::new(b'K');
+                    backend_key_data.i32(process_id);
+                    backend_key_data.i32(
+                        SystemTime::now()
+                            .duration_since(UNIX_EPOCH)
+                            .map(|duration| duration.subsec_nanos() as i32)
+                            .unwrap_or(0),
+                    );
+                    self.send(backend_key_data);
+                    self.ready_for_query()?;
+                    return Ok(true);
+                }
+                version => {
+                    self.send_error(
+                        "0A000",
+                        &format!("unsupported protocol version {}", version),
+                    );
+                    self.flush()?;
+                    return Ok(false);
+                }
+            }
+        }
+    }
+
+    fn run(&mut self) -> Result<(), ProtocolError> {
+        loop {
+            let mut tag = [0u8; 1];
+            match self.reader.read_exact(&mut tag) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(()),
+                Err(e) => return Err(ProtocolError::IoError(e)),
+            }
+            let length = self.read_i32()?;
+            let body = self.read_body(length, 4)?;
+            debug!("Postgres message {:?}", tag[0] as char);
+            if tag[0] == b'X' {
+                return Ok(());
+            }
+            if self.failed && tag[0] != b'S' {
+                continue;
+            }
+            let mut message = MessageReader::new(&body);
+            let result = match tag[0] {
+                b'Q' => {
+                    let sql = message.cstring()?;
+                    self.simple_query(&sql);
+                    self.ready_for_query()
+                }
+                b'P' => self.parse(&mut message),
+                b'B' => self.bind(&mut message),
+                b'D' => self.describe(&mut message),
+                b'E' => self.execute(&mut message),
+                b'C' => self.close(&mut message),
+                b'H' => self.flush(),
+                b'S' => {
+                    self.failed = false;
+                    self.ready_for_query()
+                }
+                tag => Err(ProtocolError::Malformed(format!(
+                    "unsupported message type {:?}",
+                    tag as char
+                ))),
+            };
+            match result {
+                Err(ProtocolError::QueryError(code, message)) => {
+                    self.send_error(code, &message);
+                    self.failed = true;
+                    if let Err(e) = self.session.fail_transaction() {
+                        warn!("Postgres rolling back the failed transaction: {}", e);
+                    }
+                }
+                Err(ProtocolError::Malformed(message)) => {
+                    self.send_error("08P01", &message);
+                    self.flush()?;
+                    return Err(ProtocolError::Malformed(message));
+                }
+                result => result?,
+            }
+        }
+    }
+
+    /// Executes the statements one after the other, stopping at the first error
+    fn simple_query(&mut self, sql: &str) {
+        if is_set_command(sql) {
+            return self.command_complete("SET");
+        }
+        let statements = match parse(sql) {
+            Ok(statements) => statements,
+            Err(e) => {
+                let error = match self.session.fail_transaction() {
+                    Ok(()) => SessionError::ParserError(e),
+                    Err(rollback_error) => rollback_error,
+                };
+                return self.send_session_error(&error);
+            }
+        };
+        if statements.is_empty() {
+            self.send(MessageWriter::new(b'I'));
+        }
+        for statement in statements {
+            match self.session.execute_statement(&statement) {
+                Ok(QueryResult::Rows(result_set)) => {
+                    self.row_description(&result_set.columns, &[]);
+                    let row_count = result_set.rows.len();
+                    for row in &result_set.rows {
+                        if let Err(ProtocolError::QueryError(code, message)) =
+                            self.data_row(row, &result_set.columns, &[])
+                        {
+                            return self.send_error(code, &message);
+                        }
+                    }
+                    self.command_complete(&format!("SELECT {}", row_count));
+                }
+                Ok(result) => self.command_complete(&command_tag(&result)),
+                Err(e) => return self.send_session_error(&e),
+            }
+        }
+    }
+
+    fn parse(&mut self, message: &mut MessageReader) -> Result<(), ProtocolError> {
+        let name = message.cstring()?;
+        let sql = message.cstring()?;
+        let parameter_count = message.i16()?;
+        let parameter_types = (0..parameter_count)
+            .map(|_| message.i32())
+            .collect::<Result<Vec<_>, _>>()?;
+        let statement = PreparedStatement {
+            sql,
+            parameter_types,
+        };
+        // Syntax errors are reported when preparing already
+        statement.statement(&[])?;
+        if !name.is_empty() && self.statements.contains_key(&name) {
+            return Err(ProtocolError::QueryError(
+                "42P05",
+                format!("prepared statement \"{}\" already exists", name),
+            ));
+        }
+        self.statements.insert(name, statement);
+        self.send(MessageWriter::new(b'1'));
+        Ok(())
+    }
+
+    fn bind(&mut self, message: &mut MessageReader) -> Result<(), ProtocolError> {
+        let portal_name = message.cstring()?;
+        let statement_name = message.cstring()?;
+        let parameter_formats = message.formats()?;
+        let parameter_count = message.i16()? as usize;
+        let mut parameters = vec![];
+        for _ in 0..parameter_count {
+            parameters.push(message.value()?);
+        }
+        let result_formats = message.formats()?;
+        let prepared = self.prepared_statement(&statement_name)?;
+        let literals = parameters
+            .iter()
+            .enumerate()
+            .map(|(index, value)| {
+                parameter_literal(
+                    value.as_deref(),
+                    format_of(&parameter_formats, index),
+                    prepared.parameter_types.get(index).copied().unwrap_or(0),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let statement = prepared.statement(&literals)?;
+        let columns = match &statement {
+            Some(statement) => self.session.describe(statement).map_err(query_error)?,
+            None => None,
+        };
+        self.portals.insert(
+            portal_name,
+            Portal {
+                statement,
+                columns,
+                result_formats,
+                rows: None,
+                rows_sent: 0,
+            },
+        );
+        self.send(MessageWriter::new(b'2'));
+        Ok(())
+    }
+
+    fn describe(&mut self, message: &mut MessageReader) -> Result<(), ProtocolError> {
+        let kind = message.u8()?;
+        let name = message.cstring()?;
+        if kind == b'S' {
+            let prepared = self.prepared_statement(&name)?;
+            let mut parameter_description = MessageWriter::new(b't');
+            let parameter_count = prepared.parameter_count();
+            parameter_description.i16(parameter_count as i16);
+            for index in 0..parameter_count {
+                parameter_description.i32(
+                    match prepared.parameter_types.get(index).copied().unwrap_or(0) {
+                        0 => TEXT_OID,
+                        oid => oid,
+                    },
+                );
+            }
+            let nulls = vec!["NULL".to_string(); parameter_count];
+            let columns = match prepared.statement(&nulls)? {
+                Some(statement) => self.session.describe(&statement).map_err(query_error)?,
+                None => None,
+            };
+            self.send(parameter_description);
+            match columns {
+                Some(columns) => self.row_description(&columns, &[]),
+                None => self.send(MessageWriter::new(b'n')),
+            }
+        } else {
+            let portal = self.portal(&name)?;
+            match portal.columns.clone() {
+                Some(columns) => {
+                    let formats = portal.result_formats.clone();
+                    self.row_description(&columns, &formats)
+                }
+                None => self.send(MessageWriter::new(b'n')),
+            }
+        }
+        Ok(())
+    }
+
+    fn execute(&mut self, message: &mut MessageReader) -> Result<(), ProtocolError> {
+        let name = message.cstring()?;
+        let max_rows = message.i32()?.max(0) as usize;
+        let portal = self.portal(&name)?;
+        let Some(statement) = portal.statement.clone() else {
+            self.send(MessageWriter::new(b'I'));
+            return Ok(());
+        };
+        if portal.rows.is_none() {
+            if let Statement::Select(_) | Statement::Explain(_) = statement {
+            } else if portal.rows_sent > 0 {
+                return Err(ProtocolError::QueryError(
+                    "55000",
+                    format!("portal \"{}\" cannot be run", name),
+                ));
+            }
+            let result = match self.session.execute_statement(&statement) {
+                Ok(result) => result,
+                Err(e) => return Err(query_error(e)),
+            };
+            let portal = self.portals.get_mut(&name).expect("portal exists");
+            match result {
+                QueryResult::Rows(result_set) => portal.rows = Some(result_set.rows.into()),
+                result => {
+                    portal.rows_sent = 1;
+                    self.command_complete(&command_tag(&result));
+                    return Ok(());
+                }
+            }
+        }
+        let portal = self.portals.get_mut(&name).expect("portal exists");
+        let rows = portal.rows.as_mut().expect("portal has rows");
+        let count = if max_rows == 0 {
+            rows.len()
+        } else {
+            max_rows.min(rows.len())
+        };
+        let batch: Vec<Tuple> = rows.drain(..count).collect();
+        let suspended = !rows.is_empty();
+        portal.rows_sent += count;
+        let rows_sent = portal.rows_sent;
+        let columns = portal.columns.clone().unwrap_or_default();
+        let formats = portal.result_formats.clone();
+        for row in &batch {
+            self.data_row(row, &columns, &formats)?;
+        }
+        if suspended {
+            self.send(MessageWriter::new(b's'));
+        } else {
+            self.command_complete(&format!("SELECT {}", rows_sent));
+        }
+        Ok(())
+    }
+
+    fn close(&mut self, message: &mut MessageReader) -> Result<(), ProtocolError> {
+        let kind = message.u8()?;
+        let name = message.cstring()?;
+        if kind == b'S' {
+            self.statements.remove(&name);
+        } else {
+            self.portals.remove(&name);
+        }
+        self.send(MessageWriter::new(b'3'));
+        Ok(())
+    }
+
+    fn prepared_statement(&self, name: &str) -> Result<&PreparedStatement, ProtocolError> {
+        self.statements.get(name).ok_or_else(|| {
+            ProtocolError::QueryError(
+                "26000",
+                format!("prepared statement \"{}\" does not exist", name),
+            )
+        })
+    }
+
+    fn portal(&self, name: &str) -> Result<&Portal, ProtocolError> {
+        self.portals.get(name).ok_or_else(|| {
+            ProtocolError::QueryError("34000", format!("portal \"{}\" does not exist", name))
+        })
+    }
+
+    fn row_description(&mut self, columns: &[PlanColumn], formats: &[i16]) {
+        let mut row_description = MessageWriter::new(b'T');
+        row_description.i16(columns.len() as i16);
+        for (index, column) in columns.iter().enumerate() {
+            let oid = type_oid(column.column_type);
+            row_description.cstring(&column.name);
+            row_description.i32(0);
+            row_description.i16(0);
+            row_description.i32(oid);
+            row_description.i16(match oid {
+                INT2_OID => 2,
+                INT4_OID => 4,
+                INT8_OID => 8,
+                _ => -1,
+            });
+            row_description.i32(-1);
+            row_description.i16(format_of(formats, index));
+        }
+        self.send(row_description);
+    }
+
+    fn data_row(
+        &mut self,
+        row: &[Value],
+        columns: &[PlanColumn],
+        formats: &[i16],
+    ) -> Result<(), ProtocolError> {
+        let mut data_row = MessageWriter::new(b'D');
+        data_row.i16(row.len() as i16);
+        for (index, value) in row.iter().enumerate() {
+            let oid = columns
+                .get(index)
+                .map(|column| type_oid(column.column_type))
+                .unwrap_or(TEXT_OID);
+            match encode_value(value, oid, format_of(formats, index))? {
+                Some(bytes) => {
+                    data_row.i32(bytes.len() as i32);
+                    data_row.bytes(&bytes);
+                }
+                None => data_row.i32(-1),
+            }
+        }
+        self.send(data_row);
+        Ok(())
+    }
+
+    fn command_complete(&mut self, tag: &str) {
+        let mut command_complete = MessageWriter::new(b'C');
+        command_complete.cstring(tag);
+        self.send(command_complete);
+    }
+
+    fn send_session_error(&mut self, error: &SessionError) {
+        self.send_error(sqlstate(error), &error.to_string());
+    }
+
+    fn send_error(&mut self, code: &str, message: &str) {
+        debug!("Postgres error {} {}", code, message);
+        let mut error_response = MessageWriter::new(b'E');
+        for (field, value) in [
+            (b'S', "ERROR"),
+            (b'V', "ERROR"),
+            (b'C', code),
+            (b'M', message),
+        ] {
+            error_response.u8(field);
+            error_response.cstring(value);
+        }
+        error_response.u8(0);
+        self.send(error_response);
+    }
+
+    fn ready_for_query(&mut self) -> Result<(), ProtocolError> {
+        let mut ready_for_query = MessageWriter::new(b'Z');
+        ready_for_query.u8(if self.session.in_failed_transaction() {
+            b'E'
+        } else if self.session.in_transaction() {
+            b'T'
+        } else {
+            b'I'
+        });
+        self.send(ready_for_query);
+        self.flush()
+    }
+
+    fn send(&mut self, message: MessageWriter) {
+        message.append_to(&mut self.output);
+    }
+
+    fn flush(&mut self) -> Result<(), ProtocolError> {
+        self.writer
+            .write_all(&self.output)
+            .and_then(|_| self.writer.flush())
+            .map_err(ProtocolError::IoError)?;
+        self.output.clear();
+        Ok(())
+    }
+
+    fn read_i32(&mut self) -> Result<i32, ProtocolError> {
+        let mut bytes = [0u8; 4];
+        self.reader
+            .read_exact(&mut bytes)
+            .map_err(ProtocolError::IoError)?;
+        Ok(i32::from_be_bytes(bytes))
+    }
+
+    fn read_i32_or_eof(&mut self) -> Result<Option<i32>, ProtocolError> {
+        let mut bytes = [0u8; 4];
+        match self.reader.read_exact(&mut bytes) {
+            Ok(()) => Ok(Some(i32::from_be_bytes(bytes))),
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(None),
+            Err(e) => Err(ProtocolError::IoError(e)),
+        }
+    }
+
+    /// Rest of the message, the length includes the length field itself
+    fn read_body(&mut self, length: i32, read: usize) -> Result<Vec<u8>, ProtocolError> {
+        let length = usize::try_from(length)
+            .ok()
+            .filter(|length| (read..=MAX_MESSAGE_LENGTH).contains(length))
+            .ok_or_else(|| ProtocolError::Malformed(format!("invalid length {}", length)))?;
+        let mut body = vec![0u8; length - read];
+        self.reader
+            .read_exact(&mut body)
+            .map_err(ProtocolError::IoError)?;
+        Ok(body)
+    }
+}
+
+impl PreparedStatement {
+    /// Highest parameter number used in the SQL text
+    fn parameter_count(&self) -> usize {
+        let mut count = 0;
+        substitute_parameters(&self.sql, |number| {
+            count = count.max(number);
+            Ok(String::new())
+        })
+        .ok();
+        count.max(self.parameter_types.len())
+    }
+
+    /// The statement with the parameters replaced by the literals, None for an empty query.
+    /// Without literals only the syntax is checked, parameters being NULL.
+    fn statement(&self, literals: &[String]) -> Result<Option<Statement>, ProtocolError> {
+        if is_set_command(&self.sql) {
+            return Ok(None);
+        }
+        let parameter_count = self.parameter_count();
+        if !literals.is_empty() && literals.len() != parameter_count {
+            return Err(ProtocolError::QueryError(
+                "08P01",
+                format!(
+                    "bind message supplies {} parameters, but prepared statement requires {}",
+                    literals.len(),
+                    parameter_count
+                ),
+            ));
+        }
+        let sql = substitute_parameters(&self.sql, |number| {
+            Ok(literals
+                .get(number - 1)
+                .cloned()
+                .unwrap_or_else(|| "NULL".to_string()))
+        })?;
+        let mut statements = parse(&sql)
+            .map_err(|e| query_error(SessionError::ParserError(e)))?
+            .into_iter();
+        let statement = statements.next();
+        if statements.next().is_some() {
+            return Err(ProtocolError::QueryError(
+                "42601",
+                "cannot insert multiple commands into a prepared statement".to_string(),
+            ));
+        }
+        Ok(statement)
+    }
+}
+
+/// SQL text with each parameter ($ followed by its number) replaced, except within string
+/// literals, quoted identifiers and comments
+fn substitute_parameters(
+    sql: &str,
+    mut replacement: impl FnMut(usize) -> Result<String, ProtocolError>,
+) -> Result<String, ProtocolError> {
+    let mut substituted = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                substituted.push(c);
+                for quoted in chars.by_ref() {
+                    substituted.push(quoted);
+                    if quoted == c {
+                        break;
+                    }
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                substituted.push(c);
+                for commented in chars.by_ref() {
+                    substituted.push(commented);
+                    if commented == '\n' {
+                        break;
+                    }
+                }
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                substituted.push(c);
+                let mut previous = ' ';
+                for commented in chars.by_ref() {
+                    substituted.push(commented);
+                    if previous == '*' && commented == '/' {
+                        break;
+                    }
+                    previous = commented;
+                }
+            }
+            '$' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                let mut number = String::new();
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    number.push(digit);
+                }
+                let number = number
+                    .parse::<usize>()
+                    .ok()
+                    .filter(|number| *number > 0)
+                    .ok_or_else(|| {
+                        ProtocolError::QueryError(
+                            "42P02",
+                            format!("there is no parameter ${}", number),
+                        )
+                    })?;
+                substituted.push_str(&replacement(number)?);
+            }
+            c => substituted.push(c),
+        }
+    }
+    Ok(substituted)
+}
+
+fn is_set_command(sql: &str) -> bool {
+    sql.trim_start()
+        .get(..4)
+        .is_some_and(|prefix| prefix.eq_ignore_ascii_case("set "))
+}
+
+/// SQL literal of a bound parameter. Parameters of undeclared type sent as text are integers
+/// if they look like one, strings otherwise.
+fn parameter_literal(value: Option<&[u8]>, format: i16, oid: i32) -> Result<String, ProtocolError> {
+    let Some(value) = value else {
+        return Ok("NULL".to_string());
+    };
+    let invalid = || {
+        ProtocolError::QueryError(
+            "22P02",
+            format!(
+                "invalid parameter of type {} in format {}: {:?}",
+                oid, format, value
+            ),
+        )
+    };
+    let text = || std::str::from_utf8(value).map_err(|_| invalid());
+    match (format, oid) {
+        (BINARY_FORMAT, INT2_OID | INT4_OID | INT8_OID) => match value.len() {
+            2 => Ok(i16::from_be_bytes([value[0], value[1]]) as i128),
+            4 => Ok(i32::from_be_bytes(value.try_into().unwrap()) as i128),
+            8 => Ok(i64::from_be_bytes(value.try_into().unwrap()) as i128),
+            _ => Err(invalid()),
+        }
+        .map(integer_literal),
+        (BINARY_FORMAT, BYTEA_OID) => Ok(bytes_literal(value)),
+        (BINARY_FORMAT, 0 | TEXT_OID | VARCHAR_OID) => Ok(string_literal(text()?)),
+        (BINARY_FORMAT, _) => Err(invalid()),
+        (_, INT2_OID | INT4_OID | INT8_OID | NUMERIC_OID) => text()?
+            .trim()
+            .parse::<i128>()
+            .map(integer_literal)
+            .map_err(|_| invalid()),
+        (_, BYTEA_OID) => {
+            let hex = text()?.strip_prefix("\\x").ok_or_else(invalid)?;
+            (0..hex.len())
+                .step_by(2)
+                .map(|index| {
+                    hex.get(index..index + 2)
+                        .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                        .ok_or_else(invalid)
+                })
+                .collect::<Result<Vec<u8>, _>>()
+                .map(|bytes| bytes_literal(&bytes))
+        }
+        (_, 0) => {
+            let text = text()?;
+            Ok(match text.parse::<i128>() {
+                Ok(integer) => integer_literal(integer),
+                Err(_) => string_literal(text),
+            })
+        }
+        _ => Ok(string_literal(text()?)),
+    }
+}
+
+fn integer_literal(value: i128) -> String {
+    if value < 0 {
+        format!("({})", value)
+    } else {
+        value.to_string()
+    }
+}
+
+fn string_literal(value: &str) -> String {
+    format!("'{}'", value.replace('\'', "''"))
+}
+
+fn bytes_literal(value: &[u8]) -> String {
+    let hex: String = value.iter().map(|byte| format!("{:02x}", byte)).collect();
+    format!("X'{}'", hex)
+}
+
+fn type_oid(column_type: ColumnType) -> i32 {
+    match column_type {
+        ColumnType::TinyInteger | ColumnType::SmallInteger | ColumnType::TinyCount => INT2_OID,
+        ColumnType::Integer | ColumnType::SmallCount => INT4_OID,
+        ColumnType::BigInteger
+        | ColumnType::Count
+        | ColumnType::BigCount
+        | ColumnType::Varint
+        | ColumnType::Varcount => INT8_OID,
+        ColumnType::HugeInteger | ColumnType::HugeCount => NUMERIC_OID,
+        ColumnType::Varchar => VARCHAR_OID,
+        ColumnType::Varbinary => BYTEA_OID,
+    }
+}
+
+/// Format of the column: no formats means text, a single format applies to all columns
+fn format_of(formats: &[i16], index: usize) -> i16 {
+    match formats {
+        [] => TEXT_FORMAT,
+        [format] => *format,
+        formats => formats.get(index).copied().unwrap_or(TEXT_FORMAT),
+    }
+}
+
+/// Value as sent in a DataRow, None for NULL
+fn encode_value(value: &Value, oid: i32, format: i16) -> Result<Option<Vec<u8>>, ProtocolError> {
+    let out_of_range = || {
+        ProtocolError::QueryError(
+            "22003",
+            format!("value {} out of range for type {}", value, oid),
+        )
+    };
+    Ok(Some(match (value, format) {
+        (Value::Null, _) => return Ok(None),
+        (Value::Integer(integer), BINARY_FORMAT) => match oid {
+            INT2_OID => i16::try_from(*integer)
+                .map_err(|_| out_of_range())?
+                .to_be_bytes()
+                .to_vec(),
+            INT4_OID => i32::try_from(*integer)
+                .map_err(|_| out_of_range())?
+                .to_be_bytes()
+                .to_vec(),
+            INT8_OID => i64::try_from(*integer)
+                .map_err(|_| out_of_range())?
+                .to_be_bytes()
+                .to_vec(),
+            _ => numeric_binary(*integer),
+        },
+        (Value::Integer(integer), _) => integer.to_string().into_bytes(),
+        (Value::Text(text), _) => text.as_bytes().to_vec(),
+        (Value::Bytes(bytes), BINARY_FORMAT) => bytes.clone(),
+        (Value::Bytes(bytes), _) => {
+            let hex: String = bytes.iter().map(|byte| format!("{:02x}", byte)).collect();
+            format!("\\x{}", hex).into_bytes()
+        }
+    }))
+}
+
+/// Binary numeric: digit count, weight of the first digit, sign and display scale, followed by
+/// the base 10000 digits
+fn numeric_binary(integer: i128) -> Vec<u8> {
+    let mut digits = vec![];
+    let mut rest = integer.unsigned_abs();
+    while rest > 0 {
+        digits.insert(0, (rest % 10000) as i16);
+        rest /= 10000;
+    }
+    let weight = digits.len() as i16 - 1;
+    while digits.last() == Some(&0) {
+        digits.pop();
+    }
+    let sign: u16 = if integer < 0 { 0x4000 } else { 0 };
+    let mut bytes = vec![];
+    bytes.extend((digits.len() as i16).to_be_bytes());
+    bytes.extend(weight.max(0).to_be_bytes());
+    bytes.extend(sign.to_be_bytes());
+    bytes.extend(0i16.to_be_bytes());
+    for digit in digits {
+        bytes.extend(digit.to_be_bytes());
+    }
+    bytes
+}
+
+fn command_tag(result: &QueryResult) -> String {
+    match result {
+        QueryResult::Rows(result_set) => format!("SELECT {}", result_set.rows.len()),
+        QueryResult::Inserted(count) => format!("INSERT 0 {}", count),
+        QueryResult::Updated(count) => format!("UPDATE {}", count),
+        QueryResult::Deleted(count) => format!("DELETE {}", count),
+        QueryResult::Analyzed(_) => "ANALYZE".to_string(),
+        QueryResult::TableCreated => "CREATE TABLE".to_string(),
+        QueryResult::TableDropped => "DROP TABLE".to_string(),
+        QueryResult::IndexCreated => "CREATE INDEX".to_string(),
+        QueryResult::Begin => "BEGIN".to_string(),
+        QueryResult::Commit => "COMMIT".to_string(),
+        QueryResult::Rollback => "ROLLBACK".to_string(),
+    }
+}
+
+/// SQLSTATE of the error. Only deadlock victims and write conflicts are reported as failures
+/// the client may retry.
+fn sqlstate(error: &SessionError) -> &'static str {
+    if let Some(sqlstate) = storage_sqlstate(error) {
+        return sqlstate;
+    }
+    match error {
+        SessionError::ParserError(_) => "42601",
+        SessionError::ExecutionError(ExecutionError::PlannerError(_)) => "42000",
+        SessionError::ExecutionError(ExecutionError::ExpressionError(_)) => "22000",
+        SessionError::ExecutionError(ExecutionError::LayoutError(_))
+        | SessionError::ExecutionError(ExecutionError::ValueCount(_, _)) => "23000",
+        SessionError::ExecutionError(_) => "XX000",
+        SessionError::TransactionError(e) => transaction_sqlstate(e),
+        SessionError::AlreadyInTransaction => "25001",
+        SessionError::NoTransaction => "25P01",
+        SessionError::FailedTransaction => "25P02",
+    }
+}
+
+/// SQLSTATE of an error of the transaction or storage layer the statement failed with
+fn storage_sqlstate(error: &SessionError) -> Option<&'static str> {
+    let SessionError::ExecutionError(error) = error else {
+        return None;
+    };
+    match error {
+        ExecutionError::PlannerError(PlannerError::CatalogError(e))
+        | ExecutionError::CatalogError(e) => catalog_sqlstate(e),
+        ExecutionError::PlannerError(PlannerError::StatisticsError(e))
+        | ExecutionError::StatisticsError(e) => statistics_sqlstate(e),
+        ExecutionError::TableError(e) => table_sqlstate(e),
+        ExecutionError::TableScanError(e) => table_scan_sqlstate(e),
+        ExecutionError::IoError(_) => Some("58030"),
+        _ => None,
+    }
+}
+
+fn transaction_sqlstate(error: &TransactionError) -> &'static str {
+    match error {
+        e if e.is_deadlock_victim() => "40P01",
+        TransactionError::WriteConflict(_, _) => "40001",
+        TransactionError::StdIoError(_) => "58030",
+        _ => "XX000",
+    }
+}
+
+fn table_scan_sqlstate(error: &TableScanError) -> Option<&'static str> {
+    match error {
+        TableScanError::TransactionError(e)
+        | TableScanError::RecordPageError(RecordPageError::TransactionError(e)) => {
+            Some(transaction_sqlstate(e))
+        }
+        TableScanError::WriteConflict(_) => Some("40001"),
+        _ => None,
+    }
+}
+
+fn catalog_sqlstate(error: &CatalogError) -> Option<&'static str> {
+    match error {
+        CatalogError::IoError(_) => Some("58030"),
+        CatalogError::TransactionError(e) => Some(transaction_sqlstate(e)),
+        CatalogError::TableScanError(e) => table_scan_sqlstate(e),
+        _ => None,
+    }
+}
+
+fn statistics_sqlstate(error: &StatisticsError) -> Option<&'static str> {
+    match error {
+        StatisticsError::CatalogError(e) => catalog_sqlstate(e),
+        StatisticsError::TransactionError(e) => Some(transaction_sqlstate(e)),
+        StatisticsError::TableScanError(e) => table_scan_sqlstate(e),
+        StatisticsError::LayoutError(_) => None,
+    }
+}
+
+fn table_sqlstate(error: &TableError) -> Option<&'static str> {
+    match error {
+        TableError::CatalogError(e) => catalog_sqlstate(e),
+        TableError::TableScanError(e) => table_scan_sqlstate(e),
+        TableError::IndexError(IndexError::BTreeError(BTreeError::TransactionError(e)))
+        | TableError::IndexError(IndexError::HashIndexError(HashIndexError::TransactionError(e))) => {
+            Some(transaction_sqlstate(e))
+        }
+        _ => None,
+    }
+}
+
+fn query_error(error: SessionError) -> ProtocolError {
+    ProtocolError::QueryError(sqlstate(&error), error.to_string())
+}
+
+/// Fields of a message sent by the client
+struct MessageReader<'a> {
+    body: &'a [u8],
+    position: usize,
+}
+
+impl<'a> MessageReader<'a> {
+    fn new(body: &'a [u8]) -> Self {
+        Self { body, position: 0 }
+    }
+
+    fn bytes(&mut self, length: usize) -> Result<&'a [u8], ProtocolError> {
+        let bytes = self
+            .body
+            .get(self.position..self.position + length)
+            .ok_or_else(|| ProtocolError::Malformed("message too short".to_string()))?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, ProtocolError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn i16(&mut self) -> Result<i16, ProtocolError> {
+        Ok(i16::from_be_bytes(self.bytes(2)?.try_into().unwrap()))
+    }
+
+    fn i32(&mut self) -> Result<i32, ProtocolError> {
+        Ok(i32::from_be_bytes(self.bytes(4)?.try_into().unwrap()))
+    }
+
+    fn cstring(&mut self) -> Result<String, ProtocolError> {
+        let length = self.body[self.position..]
+            .iter()
+            .position(|byte| *byte == 0)
+            .ok_or_else(|| ProtocolError::Malformed("unterminated string".to_string()))?;
+        let string = String::from_utf8(self.bytes(length)?.to_vec())
+            .map_err(|_| ProtocolError::Malformed("invalid UTF-8".to_string()))?;
+        self.position += 1;
+        Ok(string)
+    }
+
+    fn formats(&mut self) -> Result<Vec<i16>, ProtocolError> {
+        let count = self.i16()?;
+        (0..count).map(|_| self.i16()).collect()
+    }
+
+    /// Length prefixed value, None for NULL (length -1)
+    fn value(&mut self) -> Result<Option<Vec<u8>>, ProtocolError> {
+        let length = self.i32()?;
+        if length < 0 {
+            return Ok(None);
+        }
+        Ok(Some(self.bytes(length as usize)?.to_vec()))
+    }
+}
+
+/// Message sent to the client: its type, the length and the fields
+struct MessageWriter {
+    tag: u8,
+    body: Vec<u8>,
+}
+
+impl MessageWriter {
+    fn new(tag: u8) -> Self {
+        Self { tag, body: vec![] }
+    }
+
+    fn u8(&mut self, value: u8) {
+        self.body.push(value);
+    }
+
+    fn i16(&mut self, value: i16) {
+        self.body.extend(value.to_be_bytes());
+    }
+
+    fn i32(&mut self, value: i32) {
+        self.body.extend(value.to_be_bytes());
+    }
+
+    fn cstring(&mut self, value: &str) {
+        self.body.extend(value.as_bytes());
+        self.body.push(0);
+    }
+
+    fn bytes(&mut self, value: &[u8]) {
+        self.body.extend(value);
+    }
+
+    fn append_to(self, output: &mut Vec<u8>) {
+        output.push(self.tag);
+        output.extend((self.body.len() as i32 + 4).to_be_bytes());
+        output.extend(self.body);
+    }
+}
+
+#[derive(Debug)]
+pub enum ProtocolError {
+    IoError(std::io::Error),
+    /// The client sent something not following the protocol, the connection is closed
+    Malformed(String),
+    /// A statement failed with the SQLSTATE code and message, the connection stays open
+    QueryError(&'static str, String),
+}
+
+impl Display for ProtocolError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProtocolError::IoError(e) => write!(f, "Postgres protocol IoError {}", e),
+            ProtocolError::Malformed(message) => {
+                write!(f, "Postgres protocol: malformed message, {}", message)
+            }
+            ProtocolError::QueryError(code, message) => {
+                write!(f, "Postgres protocol: query failed {} {}", code, message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::file_management::block_id::{BlockId, DbFilename};
+    use crate::file_management::file_manager::IoError;
+    use crate::memory_management::buffer::TransactionNumber;
+    use crate::memory_management::buffer_manager::BufferManagerError;
+    use crate::metadata_management::catalog::CatalogError;
+    use crate::query_processing::executor::ExecutionError;
+    use crate::query_processing::session::SessionError;
+    use crate::record_management::record_page::RecordPageError;
+    use crate::record_management::table_scan::TableScanError;
+    use crate::server::postgres::{
+        is_set_command, parameter_literal, sqlstate, substitute_parameters, MessageWriter,
+        PostgresServer, BINARY_FORMAT, INT4_OID, PROTOCOL_VERSION_3, TEXT_FORMAT,
+    };
+    use crate::transaction_management::lock_table::LockTableError;
+    use crate::transaction_management::transaction::TransactionError;
+    use crate::utils::logging::init_logging;
+    use std::io::Cursor;
+
+    fn startup() -> Vec<u8> {
+        let mut body = vec![];
+        body.extend(PROTOCOL_VERSION_3.to_be_bytes());
+        body.extend(b"user\0test\0\0");
+        let mut message = ((body.len() + 4) as i32).to_be_bytes().to_vec();
+        message.extend(body);
+        message
+    }
+
+    fn message(tag: u8, fill: impl FnOnce(&mut MessageWriter)) -> Vec<u8> {
+        let mut message = MessageWriter::new(tag);
+        fill(&mut message);
+        let mut output = vec![];
+        message.append_to(&mut output);
+        output
+    }
+
+    fn query(sql: &str) -> Vec<u8> {
+        message(b'Q', |message| message.cstring(sql))
+    }
+
+    /// Runs a connection sending the messages, returns the messages received as type and body
+    fn converse(server: &PostgresServer, messages: &[Vec<u8>]) -> Vec<(char, Vec<u8>)> {
+        let mut input = startup();
+        for message in messages {
+            input.extend(message);
+        }
+        input.extend(message(b'X', |_| {}));
+        let mut output = vec![];
+        server
+            .serve_connection(Cursor::new(input), &mut output)
+            .unwrap();
+        let mut received = vec![];
+        let mut position = 0;
+        while position < output.len() {
+            let tag = output[position] as char;
+            let length =
+                i32::from_be_bytes(output[position + 1..position + 5].try_into().unwrap()) as usize;
+            received.push((tag, output[position + 5..position + 1 + length].to_vec()));
+            position += 1 + length;
+        }
+        received
+    }
+
+    fn tags(received: &[(char, Vec<u8>)]) -> String {
+        received.iter().map(|(tag, _)| *tag).collect()
+    }
+
+    /// Values of the data rows as text, NULL for missing values
+    fn data_rows(received: &[(char, Vec<u8>)]) -> Vec<Vec<String>> {
+        received
+            .iter()
+            .filter(|(tag, _)| *tag == 'D')
+            .map(|(_, body)| {
+                let count = i16::from_be_bytes([body[0], body[1]]);
+                let mut position = 2;
+                (0..count)
+                    .map(|_| {
+                        let length =
+                            i32::from_be_bytes(body[position..position + 4].try_into().unwrap());
+                        position += 4;
+                        if length < 0 {
+                            return "NULL".to_string();
+                        }
+                        let value = &body[position..position + length as usize];
+                        position += length as usize;
+                        String::from_utf8_lossy(value).to_string()
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn command_tags(received: &[(char, Vec<u8>)]) -> Vec<String> {
+        received
+            .iter()
+            .filter(|(tag, _)| *tag == 'C')
+            .map(|(_, body)| String::from_utf8_lossy(&body[..body.len() - 1]).to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_simple_query() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("postgres_simple_query")
+            .build()
+            .unwrap();
+        let server = PostgresServer::new(&hfdb);
+        let received = converse(
+            &server,
+            &[
+                query(
+                    "CREATE TABLE persons (id INT NOT NULL, name VARCHAR);
+                     INSERT INTO persons VALUES (1, 'Ada'), (2, NULL);",
+                ),
+                query("SELECT id, name FROM persons ORDER BY id"),
+                query("BEGIN; DELETE FROM persons WHERE id = 2"),
+                query("SELECT name FROM nowhere"),
+                query("SELECT 1"),
+                query("SET extra_float_digits = 3"),
+                query(""),
+                query("COMMIT"),
+                query("SELECT count(*) FROM persons"),
+            ],
+        );
+        assert_eq!(tags(&received), "RSSSSSSKZCCZTDDCZCCZEZEZCZIZCZTDCZ");
+        assert_eq!(
+            data_rows(&received),
+            vec![vec!["1", "Ada"], vec!["2", "NULL"], vec!["2"]]
+        );
+        assert_eq!(
+            command_tags(&received),
+            vec![
+                "CREATE TABLE",
+                "INSERT 0 2",
+                "SELECT 2",
+                "BEGIN",
+                "DELETE 1",
+                "SET",
+                "ROLLBACK",
+                "SELECT 1"
+            ]
+        );
+        // The failing statement after BEGIN rolled back the transaction, the following ones
+        // are rejected until it is ended
+        let statuses: Vec<u8> = received
+            .iter()
+            .filter(|(tag, _)| *tag == 'Z')
+            .map(|(_, body)| body[0])
+            .collect();
+        assert_eq!(statuses, b"IIITEEEEII");
+        let errors: Vec<&[u8]> = received
+            .iter()
+            .filter(|(tag, _)| *tag == 'E')
+            .map(|(_, body)| body.as_slice())
+            .collect();
+        assert!(errors[1].windows(6).any(|field| field == b"C25P02"));
+    }
+
+    #[test]
+    fn test_extended_query() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("postgres_extended_query")
+            .build()
+            .unwrap();
+        let server = PostgresServer::new(&hfdb);
+        let received = converse(
+            &server,
+            &[
+                query(
+                    "CREATE TABLE persons (id INT NOT NULL, name VARCHAR);
+                     INSERT INTO persons VALUES (1, 'Ada'), (2, 'Bob'), (3, 'Cid');",
+                ),
+                message(b'P', |message| {
+                    message.cstring("by_id");
+                    message.cstring("SELECT name FROM persons WHERE id >= $1 ORDER BY id");
+                    message.i16(1);
+                    message.i32(INT4_OID);
+                }),
+                message(b'D', |message| {
+                    message.u8(b'S');
+                    message.cstring("by_id");
+                }),
+                message(b'B', |message| {
+                    message.cstring("");
+                    message.cstring("by_id");
+                    message.i16(1);
+                    message.i16(BINARY_FORMAT);
+                    message.i16(1);
+                    message.i32(4);
+                    message.bytes(&2i32.to_be_bytes());
+                    message.i16(0);
+                }),
+                message(b'E', |message| {
+                    message.cstring("");
+                    message.i32(1);
+                }),
+                message(b'E', |message| {
+                    message.cstring("");
+                    message.i32(0);
+                }),
+                message(b'S', |_| {}),
+                message(b'B', |message| {
+                    message.cstring("");
+                    message.cstring("missing");
+                    message.i16(0);
+                    message.i16(0);
+                    message.i16(0);
+                }),
+                message(b'E', |message| {
+                    message.cstring("");
+                    message.i32(0);
+                }),
+                message(b'S', |_| {}),
+            ],
+        );
+        assert_eq!(tags(&received), "RSSSSSSKZCCZ1tT2DsDCZEZ");
+        assert_eq!(data_rows(&received), vec![vec!["Bob"], vec!["Cid"]]);
+        assert_eq!(
+            command_tags(&received),
+            vec!["CREATE TABLE", "INSERT 0 3", "SELECT 2"]
+        );
+    }
+
+    #[test]
+    fn test_parameters() {
+        let literals: Vec<String> = vec!["1".to_string(), "'it''s'".to_string()];
+        assert_eq!(
+            substitute_parameters("SELECT $1, '$2', $2 -- $1", |number| Ok(literals
+                [number - 1]
+                .clone()))
+            .unwrap(),
+            "SELECT 1, '$2', 'it''s' -- $1"
+        );
+        assert_eq!(
+            parameter_literal(Some(&(-7i64).to_be_bytes()), BINARY_FORMAT, 20).unwrap(),
+            "(-7)"
+        );
+        assert_eq!(
+            parameter_literal(Some(b"42"), TEXT_FORMAT, 0).unwrap(),
+            "42"
+        );
+        assert_eq!(
+            parameter_literal(Some(b"it's"), TEXT_FORMAT, 0).unwrap(),
+            "'it''s'"
+        );
+        assert_eq!(
+            parameter_literal(Some(b"\\x0aff"), TEXT_FORMAT, 17).unwrap(),
+            "X'0aff'"
+        );
+        assert_eq!(parameter_literal(None, TEXT_FORMAT, 23).unwrap(), "NULL");
+        assert!(parameter_literal(Some(b"abc"), TEXT_FORMAT, INT4_OID).is_err());
+    }
+
+    #[test]
+    fn test_set_commands_and_sqlstates() {
+        assert!(is_set_command("  set extra_float_digits = 3"));
+        assert!(!is_set_command("SELECT 1"));
+        assert!(!is_set_command("SET"));
+        assert!(!is_set_command("€€"));
+        assert!(!is_set_command("se€"));
+
+        let block = BlockId::new(DbFilename::from("table.tbl"), 0);
+        let transaction_error = |e| sqlstate(&SessionError::TransactionError(e));
+        assert_eq!(
+            transaction_error(TransactionError::LockTableError(
+                LockTableError::DeadLockVictim(TransactionNumber::from(1))
+            )),
+            "40P01"
+        );
+        assert_eq!(
+            transaction_error(TransactionError::WriteConflict(block.clone(), 0)),
+            "40001"
+        );
+        assert_eq!(
+            transaction_error(TransactionError::LockTableError(
+                LockTableError::DeadLockTimeout
+            )),
+            "XX000"
+        );
+        assert_eq!(
+            transaction_error(TransactionError::StdIoError(IoError::BlockBeyondEndOfFile(
+                block.clone()
+            ))),
+            "58030"
+        );
+        assert_eq!(
+            sqlstate(&SessionError::ExecutionError(
+                ExecutionError::TableScanError(TableScanError::RecordPageError(
+                    RecordPageError::TransactionError(TransactionError::BufferManagerError(
+                        BufferManagerError::DeadLockVictim(TransactionNumber::from(2))
+                    ))
+                ))
+            )),
+            "40P01"
+        );
+        assert_eq!(
+            sqlstate(&SessionError::ExecutionError(ExecutionError::CatalogError(
+                CatalogError::TableScanError(TableScanError::TransactionError(
+                    TransactionError::NotActive(TransactionNumber::from(3))
+                ))
+            ))),
+            "XX000"
+        );
+    }
+}
@@ -0,0 +1,1034 @@
+use crate::db_management_system::hfdb::HanfriedDb;
+use crate::server::key_value::{KeyValueError, KeyValueStore, KeyValueTransaction};
+use log::{debug, info, warn};
+use std::collections::{HashMap, VecDeque};
+use std::fmt::{Display, Formatter};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+/// Bulk strings and arrays larger than this are rejected instead of allocating their length
+const MAX_BULK_LENGTH: usize = 512 * 1024 * 1024;
+const MAX_ARRAY_LENGTH: usize = 1024 * 1024;
+const DEFAULT_SCAN_COUNT: usize = 10;
+/// Unfinished SCAN iterations remembered, the oldest are forgotten first
+const MAX_SCAN_CURSORS: usize = 1024;
+
+/// Server speaking the Redis serialization protocol RESP2, and RESP3 after HELLO 3, so
+/// redis-cli and Redis clients can connect. Every connection gets its own thread, all share
+/// the [KeyValueStore], so the data is durable like any other table.
+///
+/// Supported are strings (GET, SET, DEL, EXISTS, INCR, DECR, MGET, MSET), expiry (EXPIRE,
+/// TTL), key iteration (KEYS, SCAN), hashes (HSET, HGET, HDEL, HGETALL) and lists (LPUSH,
+/// RPUSH, LPOP, RPOP, LRANGE, LLEN). There is a single database and no authentication.
+#[derive(Debug, Clone)]
+pub struct RedisServer {
+    store: KeyValueStore,
+    cursors: Arc<Mutex<ScanCursors>>,
+}
+
+impl RedisServer {
+    pub const DEFAULT_LISTEN: &'static str = "127.0.0.1:6379";
+
+    pub fn new(hfdb: &HanfriedDb) -> Result<Self, KeyValueError> {
+        Ok(Self {
+            store: KeyValueStore::new(&hfdb.transaction_manager, &hfdb.catalog)?,
+            cursors: Arc::new(Mutex::new(ScanCursors::default())),
+        })
+    }
+
+    /// Accepts connections until accepting fails
+    pub fn serve(&self, listener: TcpListener) -> std::io::Result<()> {
+        info!("Redis server listening on {}", listener.local_addr()?);
+        loop {
+            let (stream, address) = listener.accept()?;
+            let server = self.clone();
+            thread::spawn(move || {
+                info!("Redis connection from {}", address);
+                match server.serve_stream(stream) {
+                    Ok(()) => info!("Redis connection from {} closed", address),
+                    Err(e) => warn!("Redis connection from {} failed: {}", address, e),
+                }
+            });
+        }
+    }
+
+    pub fn serve_stream(&self, stream: TcpStream) -> Result<(), RespError> {
+        stream.set_nodelay(true).map_err(RespError::IoError)?;
+        let reader = stream.try_clone().map_err(RespError::IoError)?;
+        self.serve_connection(reader, stream)
+    }
+
+    /// Serves one client until it quits or disconnects
+    pub fn serve_connection<R: Read, W: Write>(
+        &self,
+        reader: R,
+        writer: W,
+    ) -> Result<(), RespError> {
+        Connection {
+            reader: BufReader::new(reader),
+            writer,
+            output: vec![],
+            protocol: 2,
+            store: &self.store,
+            cursors: &self.cursors,
+        }
+        .run()
+    }
+}
+
+/// Reply to a command, encoded depending on the protocol version
+#[derive(Debug, Clone, Eq, PartialEq)]
+enum Reply {
+    Simple(&'static str),
+    Error(String),
+    Integer(i64),
+    Bulk(Vec<u8>),
+    Null,
+    Array(Vec<Reply>),
+    /// Map in RESP3, flattened to an array of keys and values in RESP2
+    Map(Vec<(Reply, Reply)>),
+}
+
+impl Reply {
+    fn bulk_or_null(value: Option<Vec<u8>>) -> Reply {
+        value.map(Reply::Bulk).unwrap_or(Reply::Null)
+    }
+
+    fn bulks(values: Vec<Vec<u8>>) -> Reply {
+        Reply::Array(values.into_iter().map(Reply::Bulk).collect())
+    }
+
+    fn encode(&self, protocol: u8, output: &mut Vec<u8>) {
+        match self {
+            Reply::Simple(text) => output.extend(format!("+{}\r\n", text).as_bytes()),
+            Reply::Error(message) => output.extend(format!("-{}\r\n", message).as_bytes()),
+            Reply::Integer(integer) => output.extend(format!(":{}\r\n", integer).as_bytes()),
+            Reply::Bulk(bytes) => {
+                output.extend(format!("${}\r\n", bytes.len()).as_bytes());
+                output.extend(bytes);
+                output.extend(b"\r\n");
+            }
+            Reply::Null if protocol >= 3 => output.extend(b"_\r\n"),
+            Reply::Null => output.extend(b"$-1\r\n"),
+            Reply::Array(replies) => {
+                output.extend(format!("*{}\r\n", replies.len()).as_bytes());
+                for reply in replies {
+                    reply.encode(protocol, output);
+                }
+            }
+            Reply::Map(entries) => {
+                if protocol >= 3 {
+                    output.extend(format!("%{}\r\n", entries.len()).as_bytes());
+                } else {
+                    output.extend(format!("*{}\r\n", entries.len() * 2).as_bytes());
+                }
+                for (key, value) in entries {
+                    key.encode(protocol, output);
+                    value.encode(protocol, output);
+                }
+            }
+        }
+    }
+}
+
+/// Command failing without changing anything, its Display is the error reply
+#[derive(Debug)]
+enum CommandError {
+    UnknownCommand(String),
+    WrongArity(String),
+    Syntax,
+    NotAnInteger,
+    InvalidExpireTime(String),
+    UnsupportedProtocol,
+    InvalidCursor,
+    KeyValueError(KeyValueError),
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::UnknownCommand(name) => write!(f, "ERR unknown command '{}'", name),
+            CommandError::WrongArity(name) => write!(
+                f,
+                "ERR wrong number of arguments for '{}' command",
+                name.to_lowercase()
+            ),
+            CommandError::Syntax => write!(f, "ERR syntax error"),
+            CommandError::NotAnInteger => write!(f, "ERR value is not an integer or out of range"),
+            CommandError::InvalidExpireTime(name) => write!(
+                f,
+                "ERR invalid expire time in '{}' command",
+                name.to_lowercase()
+            ),
+            CommandError::UnsupportedProtocol => write!(f, "NOPROTO unsupported protocol version"),
+            CommandError::InvalidCursor => write!(f, "ERR invalid cursor"),
+            CommandError::KeyValueError(KeyValueError::WrongType) => write!(
+                f,
+                "WRONGTYPE Operation against a key holding the wrong kind of value"
+            ),
+            CommandError::KeyValueError(e) => write!(f, "ERR {}", e),
+        }
+    }
+}
+
+impl From<KeyValueError> for CommandError {
+    fn from(error: KeyValueError) -> Self {
+        CommandError::KeyValueError(error)
+    }
+}
+
+struct Connection<'a, R: Read, W: Write> {
+    reader: BufReader<R>,
+    writer: W,
+    /// Replies not sent yet, written when no pipelined command is waiting
+    output: Vec<u8>,
+    /// RESP version, changed by HELLO
+    protocol: u8,
+    store: &'a KeyValueStore,
+    cursors: &'a Mutex<ScanCursors>,
+}
+
+impl<R: Read, W: Write> Connection<'_, R, W> {
+    fn run(&mut self) -> Result<(), RespError> {
+        loop {
+            let arguments = match self.read_command() {
+                Ok(Some(arguments)) => arguments,
+                Ok(None) => return self.flush(),
+                Err(RespError::Malformed(message)) => {
+                    Reply::Error(format!("ERR Protocol error: {}", message))
+                        .encode(self.protocol, &mut self.output);
+                    self.flush()?;
+                    return Err(RespError::Malformed(message));
+                }
+                Err(e) => return Err(e),
+            };
+            if arguments.is_empty() {
+                continue;
+            }
+            let name = String::from_utf8_lossy(&arguments[0]).to_uppercase();
+            debug!("Redis command {}", name);
+            let reply = self
+                .command(&name, &arguments[1..])
+                .unwrap_or_else(|e| Reply::Error(e.to_string()));
+            reply.encode(self.protocol, &mut self.output);
+            if name == "QUIT" {
+                return self.flush();
+            }
+            if self.reader.buffer().is_empty() {
+                self.flush()?;
+            }
+        }
+    }
+
+    fn command(&mut self, name: &str, arguments: &[Vec<u8>]) -> Result<Reply, CommandError> {
+        let arity = |min: usize, max: Option<usize>| {
+            if arguments.len() < min || max.is_some_and(|max| arguments.len() > max) {
+                Err(CommandError::WrongArity(name.to_string()))
+            } else {
+                Ok(())
+            }
+        };
+        match name {
+            "PING" => {
+                arity(0, Some(1))?;
+                Ok(match arguments.first() {
+                    Some(message) => Reply::Bulk(message.clone()),
+                    None => Reply::Simple("PONG"),
+                })
+            }
+            "ECHO" => {
+                arity(1, Some(1))?;
+                Ok(Reply::Bulk(arguments[0].clone()))
+            }
+            "QUIT" => Ok(Reply::Simple("OK")),
+            "HELLO" => self.hello(arguments),
+            "SELECT" => {
+                arity(1, Some(1))?;
+                match integer(&arguments[0])? {
+                    0 => Ok(Reply::Simple("OK")),
+                    _ => Ok(Reply::Error("ERR DB index is out of range".to_string())),
+                }
+            }
+            // Clients send these when connecting, there is nothing to configure
+            "CLIENT" => Ok(Reply::Simple("OK")),
+            "COMMAND" => Ok(Reply::Array(vec![])),
+            "GET" => {
+                arity(1, Some(1))?;
+                Ok(Reply::bulk_or_null(
+                    self.store.run(|kv| kv.get(&arguments[0]))?,
+                ))
+            }
+            "SET" => {
+                arity(2, None)?;
+                self.set(arguments)
+            }
+            "MGET" => {
+                arity(1, None)?;
+                Ok(Reply::Array(self.store.run(|kv| {
+                    arguments
+                        .iter()
+                        .map(|key| match kv.get(key) {
+                            Ok(value) => Ok(Reply::bulk_or_null(value)),
+                            // Keys of other kinds are reported as missing, as in Redis
+                            Err(KeyValueError::WrongType) => Ok(Reply::Null),
+                            Err(e) => Err(e),
+                        })
+                        .collect()
+                })?))
+            }
+            "MSET" => {
+                if arguments.is_empty() || !arguments.len().is_multiple_of(2) {
+                    return Err(CommandError::WrongArity(name.to_string()));
+                }
+                self.store.run(|kv| {
+                    arguments
+                        .chunks(2)
+                        .try_for_each(|pair| kv.set(&pair[0], &pair[1], None))
+                })?;
+                Ok(Reply::Simple("OK"))
+            }
+            "DEL" | "EXISTS" => {
+                arity(1, None)?;
+                let delete = name == "DEL";
+                let count = self.store.run(|kv| {
+                    let mut count = 0;
+                    for key in arguments {
+                        if if delete {
+                            kv.delete(key)?
+                        } else {
+                            kv.exists(key)?
+                        } {
+                            count += 1;
+                        }
+                    }
+                    Ok(count)
+                })?;
+                Ok(Reply::Integer(count))
+            }
+            "INCR" | "DECR" => {
+                arity(1, Some(1))?;
+                self.increment(&arguments[0], if name == "INCR" { 1 } else { -1 })
+            }
+            "INCRBY" | "DECRBY" => {
+                arity(2, Some(2))?;
+                let by = integer(&arguments[1])?;
+                let by = if name == "INCRBY" {
+                    by
+                } else {
+                    by.checked_neg().ok_or(CommandError::NotAnInteger)?
+                };
+                self.increment(&arguments[0], by)
+            }
+            "EXPIRE" | "PEXPIRE" => {
+                arity(2, Some(2))?;
+                let milliseconds = match name {
+                    "EXPIRE" => integer(&arguments[1])?.checked_mul(1000),
+                    _ => Some(integer(&arguments[1])?),
+                }
+                .ok_or_else(|| CommandError::InvalidExpireTime(name.to_string()))?;
+                let done = self.store.run(|kv| {
+                    if milliseconds <= 0 {
+                        return kv.delete(&arguments[0]);
+                    }
+                    let expires_at = kv.now().saturating_add(milliseconds);
+                    kv.set_expires_at(&arguments[0], Some(expires_at))
+                })?;
+                Ok(Reply::Integer(done as i64))
+            }
+            "PERSIST" => {
+                arity(1, Some(1))?;
+                let done = self.store.run(|kv| {
+                    Ok(kv.expires_at(&arguments[0])? != Some(None)
+                        && kv.set_expires_at(&arguments[0], None)?)
+                })?;
+                Ok(Reply::Integer(done as i64))
+            }
+            "TTL" | "PTTL" => {
+                arity(1, Some(1))?;
+                let ttl = self.store.run(|kv| {
+                    Ok(match kv.expires_at(&arguments[0])? {
+                        None => -2,
+                        Some(None) => -1,
+                        Some(Some(expires_at)) if name == "TTL" => {
+                            (expires_at - kv.now() + 500) / 1000
+                        }
+                        Some(Some(expires_at)) => expires_at - kv.now(),
+                    })
+                })?;
+                Ok(Reply::Integer(ttl))
+            }
+            "TYPE" => {
+                arity(1, Some(1))?;
+                let kind = self.store.run(|kv| kv.kind(&arguments[0]))?;
+                Ok(Reply::Simple(
+                    kind.map(|kind| kind.name()).unwrap_or("none"),
+                ))
+            }
+            "DBSIZE" => {
+                arity(0, Some(0))?;
+                let keys = self.store.run(|kv| kv.keys())?;
+                Ok(Reply::Integer(keys.len() as i64))
+            }
+            "KEYS" => {
+                arity(1, Some(1))?;
+                let keys = self.store.run(|kv| kv.keys())?;
+                Ok(Reply::bulks(
+                    keys.into_iter()
+                        .filter(|key| glob_match(&arguments[0], key))
+                        .collect(),
+                ))
+            }
+            "SCAN" => {
+                arity(1, None)?;
+                self.scan(arguments)
+            }
+            "HSET" => {
+                if arguments.len() < 3 || arguments.len().is_multiple_of(2) {
+                    return Err(CommandError::WrongArity(name.to_string()));
+                }
+                let added = self.store.run(|kv| {
+                    let mut added = 0;
+                    for pair in arguments[1..].chunks(2) {
+                        if kv.hash_set(&arguments[0], &pair[0], &pair[1])? {
+                            added += 1;
+                        }
+                    }
+                    Ok(added)
+                })?;
+                Ok(Reply::Integer(added))
+            }
+            "HGET" => {
+                arity(2, Some(2))?;
+                Ok(Reply::bulk_or_null(
+                    self.store
+                        .run(|kv| kv.hash_get(&arguments[0], &arguments[1]))?,
+                ))
+            }
+            "HDEL" => {
+                arity(2, None)?;
+                let removed = self.store.run(|kv| {
+                    let mut removed = 0;
+                    for field in &arguments[1..] {
+                        if kv.hash_delete(&arguments[0], field)? {
+                            removed += 1;
+                        }
+                    }
+                    Ok(removed)
+                })?;
+                Ok(Reply::Integer(removed))
+            }
+            "HGETALL" => {
+                arity(1, Some(1))?;
+                let entries = self.store.run(|kv| kv.hash_entries(&arguments[0]))?;
+                Ok(Reply::Map(
+                    entries
+                        .into_iter()
+                        .map(|(field, value)| (Reply::Bulk(field), Reply::Bulk(value)))
+                        .collect(),
+                ))
+            }
+            "HLEN" => {
+                arity(1, Some(1))?;
+                let entries = self.store.run(|kv| kv.hash_entries(&arguments[0]))?;
+                Ok(Reply::Integer(entries.len() as i64))
+            }
+            "LPUSH" | "RPUSH" => {
+                arity(2, None)?;
+                let length = self
+                    .store
+                    .run(|kv| kv.list_push(&arguments[0], &arguments[1..], name == "LPUSH"))?;
+                Ok(Reply::Integer(length as i64))
+            }
+            "LPOP" | "RPOP" => {
+                arity(1, Some(2))?;
+                let count = match arguments.get(1) {
+                    Some(count) => Some(
+                        usize::try_from(integer(count)?).map_err(|_| CommandError::NotAnInteger)?,
+                    ),
+                    None => None,
+                };
+                let values = self
+                    .store
+                    .run(|kv| kv.list_pop(&arguments[0], count.unwrap_or(1), name == "LPOP"))?;
+                Ok(match count {
+                    Some(_) if values.is_empty() => Reply::Null,
+                    Some(_) => Reply::bulks(values),
+                    None => Reply::bulk_or_null(values.into_iter().next()),
+                })
+            }
+            "LRANGE" => {
+                arity(3, Some(3))?;
+                let start = integer(&arguments[1])?;
+                let stop = integer(&arguments[2])?;
+                let values = self.store.run(|kv| kv.list_values(&arguments[0]))?;
+                let length = values.len() as i64;
+                let clamp = |index: i64| if index < 0 { length + index } else { index };
+                let start = clamp(start).max(0);
+                let stop = clamp(stop).min(length - 1);
+                if start > stop {
+                    return Ok(Reply::Array(vec![]));
+                }
+                Ok(Reply::bulks(
+                    values[start as usize..=stop as usize].to_vec(),
+                ))
+            }
+            "LLEN" => {
+                arity(1, Some(1))?;
+                let values = self.store.run(|kv| kv.list_values(&arguments[0]))?;
+                Ok(Reply::Integer(values.len() as i64))
+            }
+            _ => Err(CommandError::UnknownCommand(name.to_lowercase())),
+        }
+    }
+
+    /// HELLO [protocol version [AUTH user password] [SETNAME name]]
+    fn hello(&mut self, arguments: &[Vec<u8>]) -> Result<Reply, CommandError> {
+        if let Some(version) = arguments.first() {
+            match integer(version)? {
+                2 => self.protocol = 2,
+                3 => self.protocol = 3,
+                _ => return Err(CommandError::UnsupportedProtocol),
+            }
+        }
+        Ok(Reply::Map(vec![
+            (
+                Reply::Bulk(b"server".to_vec()),
+                Reply::Bulk(b"hanfried-db".to_vec()),
+            ),
+            (
+                Reply::Bulk(b"version".to_vec()),
+                Reply::Bulk(env!("CARGO_PKG_VERSION").as_bytes().to_vec()),
+            ),
+            (
+                Reply::Bulk(b"proto".to_vec()),
+                Reply::Integer(self.protocol as i64),
+            ),
+            (
+                Reply::Bulk(b"mode".to_vec()),
+                Reply::Bulk(b"standalone".to_vec()),
+            ),
+            (
+                Reply::Bulk(b"role".to_vec()),
+                Reply::Bulk(b"master".to_vec()),
+            ),
+            (Reply::Bulk(b"modules".to_vec()), Reply::Array(vec![])),
+        ]))
+    }
+
+    /// SET key value [NX | XX] [GET] [EX seconds | PX milliseconds | KEEPTTL]
+    fn set(&mut self, arguments: &[Vec<u8>]) -> Result<Reply, CommandError> {
+        let (key, value) = (&arguments[0], &arguments[1]);
+        let mut only_if_exists = None;
+        let mut get = false;
+        let mut keep_ttl = false;
+        let mut expire_milliseconds = None;
+        let mut options = arguments[2..].iter();
+        while let Some(option) = options.next() {
+            match String::from_utf8_lossy(option).to_uppercase().as_str() {
+                "NX" if only_if_exists.is_none() => only_if_exists = Some(false),
+                "XX" if only_if_exists.is_none() => only_if_exists = Some(true),
+                "GET" => get = true,
+                "KEEPTTL" if expire_milliseconds.is_none() => keep_ttl = true,
+                unit @ ("EX" | "PX") if expire_milliseconds.is_none() && !keep_ttl => {
+                    let amount = integer(options.next().ok_or(CommandError::Syntax)?)?;
+                    expire_milliseconds = Some(
+                        match unit {
+                            "EX" => amount.checked_mul(1000),
+                            _ => Some(amount),
+                        }
+                        .filter(|milliseconds| *milliseconds > 0)
+                        .ok_or_else(|| CommandError::InvalidExpireTime("set".to_string()))?,
+                    );
+                }
+                _ => return Err(CommandError::Syntax),
+            }
+        }
+        let (done, previous) = self.store.run(|kv| {
+            let previous = if get { kv.get(key)? } else { None };
+            if let Some(only_if_exists) = only_if_exists {
+                if only_if_exists != kv.exists(key)? {
+                    return Ok((false, previous));
+                }
+            }
+            let expires_at = match expire_milliseconds {
+                Some(milliseconds) => Some(kv.now().saturating_add(milliseconds)),
+                None if keep_ttl => kv.expires_at(key)?.flatten(),
+                None => None,
+            };
+            kv.set(key, value, expires_at)?;
+            Ok((true, previous))
+        })?;
+        Ok(match (get, done) {
+            (true, _) => Reply::bulk_or_null(previous),
+            (false, true) => Reply::Simple("OK"),
+            (false, false) => Reply::Null,
+        })
+    }
+
+    /// Adds to the integer stored as string, keeping its expiry time
+    fn increment(&mut self, key: &[u8], by: i64) -> Result<Reply, CommandError> {
+        let result = self.store.run(|kv: &mut KeyValueTransaction| {
+            let current = match kv.get(key)? {
+                Some(value) => match parse_integer(&value) {
+                    Some(current) => current,
+                    None => return Ok(None),
+                },
+                None => 0,
+            };
+            let Some(incremented) = current.checked_add(by) else {
+                return Ok(None);
+            };
+            let expires_at = kv.expires_at(key)?.flatten();
+            kv.set(key, incremented.to_string().as_bytes(), expires_at)?;
+            Ok(Some(incremented))
+        })?;
+        result.map(Reply::Integer).ok_or(CommandError::NotAnInteger)
+    }
+
+    /// SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]. A cursor stands for the key the
+    /// iteration goes on from, so every key existing during the whole iteration is returned once.
+    fn scan(&mut self, arguments: &[Vec<u8>]) -> Result<Reply, CommandError> {
+        let cursor = u64::try_from(integer(&arguments[0])?).map_err(|_| CommandError::Syntax)?;
+        let mut pattern = None;
+        let mut count = DEFAULT_SCAN_COUNT;
+        let mut kind = None;
+        let mut options = arguments[1..].iter();
+        while let Some(option) = options.next() {
+            let value = options.next().ok_or(CommandError::Syntax)?;
+            match String::from_utf8_lossy(option).to_uppercase().as_str() {
+                "MATCH" => pattern = Some(value),
+                "COUNT" => {
+                    count = usize::try_from(integer(value)?)
+                        .ok()
+                        .filter(|count| *count > 0)
+                        .ok_or(CommandError::Syntax)?
+                }
+                "TYPE" => kind = Some(String::from_utf8_lossy(value).to_lowercase()),
+                _ => return Err(CommandError::Syntax),
+            }
+        }
+        let start = match cursor {
+            0 => None,
+            cursor => Some(
+                self.cursors
+                    .lock()
+                    .unwrap()
+                    .get(cursor)
+                    .ok_or(CommandError::InvalidCursor)?,
+            ),
+        };
+        let (next, keys) = self.store.run(|kv| {
+            let (keys, next) = kv.keys_from(start.as_deref(), count)?;
+            let mut found = vec![];
+            for key in keys {
+                if pattern.is_some_and(|pattern| !glob_match(pattern, &key)) {
+                    continue;
+                }
+                if let Some(kind) = &kind {
+                    if kv.kind(&key)?.map(|kind| kind.name()) != Some(kind.as_str()) {
+                        continue;
+                    }
+                }
+                found.push(key);
+            }
+            Ok((next, found))
+        })?;
+        let next = match next {
+            Some(key) => self.cursors.lock().unwrap().insert(key),
+            None => 0,
+        };
+        Ok(Reply::Array(vec![
+            Reply::Bulk(next.to_string().into_bytes()),
+            Reply::bulks(keys),
+        ]))
+    }
+
+    /// Next command as its arguments, None at the end of the input. Commands are arrays of
+    /// bulk strings, or inline commands of space separated words as typed in telnet.
+    fn read_command(&mut self) -> Result<Option<Vec<Vec<u8>>>, RespError> {
+        let Some(line) = self.read_line()? else {
+            return Ok(None);
+        };
+        if line.first() != Some(&b'*') {
+            return Ok(Some(
+                line.split(|byte| byte.is_ascii_whitespace())
+                    .filter(|word| !word.is_empty())
+                    .map(|word| word.to_vec())
+                    .collect(),
+            ));
+        }
+        let count = length(&line[1..], MAX_ARRAY_LENGTH)?;
+        let mut arguments = Vec::with_capacity(count);
+        for _ in 0..count {
+            let line = self
+                .read_line()?
+                .ok_or_else(|| RespError::Malformed("unexpected end of input".to_string()))?;
+            if line.first() != Some(&b'$') {
+                return Err(RespError::Malformed(format!(
+                    "expected '$', got '{}'",
+                    line.first().map(|byte| *byte as char).unwrap_or(' ')
+                )));
+            }
+            let length = length(&line[1..], MAX_BULK_LENGTH)?;
+            let mut argument = vec![0u8; length + 2];
+            self.reader
+                .read_exact(&mut argument)
+                .map_err(RespError::IoError)?;
+            if !argument.ends_with(b"\r\n") {
+                return Err(RespError::Malformed(
+                    "bulk string not terminated".to_string(),
+                ));
+            }
+            argument.truncate(length);
+            arguments.push(argument);
+        }
+        Ok(Some(arguments))
+    }
+
+    /// Line without its line ending, None at the end of the input
+    fn read_line(&mut self) -> Result<Option<Vec<u8>>, RespError> {
+        let mut line = vec![];
+        if self
+            .reader
+            .read_until(b'\n', &mut line)
+            .map_err(RespError::IoError)?
+            == 0
+        {
+            return Ok(None);
+        }
+        while line
+            .last()
+            .is_some_and(|byte| *byte == b'\n' || *byte == b'\r')
+        {
+            line.pop();
+        }
+        Ok(Some(line))
+    }
+
+    fn flush(&mut self) -> Result<(), RespError> {
+        self.writer
+            .write_all(&self.output)
+            .and_then(|_| self.writer.flush())
+            .map_err(RespError::IoError)?;
+        self.output.clear();
+        Ok(())
+    }
+}
+
+fn length(text: &[u8], max: usize) -> Result<usize, RespError> {
+    std::str::from_utf8(text)
+        .ok()
+        .and_then(|text| text.parse::<usize>().ok())
+        .filter(|length| *length <= max)
+        .ok_or_else(|| {
+            RespError::Malformed(format!("invalid length {}", String::from_utf8_lossy(text)))
+        })
+}
+
+fn parse_integer(bytes: &[u8]) -> Option<i64> {
+    std::str::from_utf8(bytes).ok()?.parse::<i64>().ok()
+}
+
+fn integer(bytes: &[u8]) -> Result<i64, CommandError> {
+    parse_integer(bytes).ok_or(CommandError::NotAnInteger)
+}
+
+/// Glob-style matching as in KEYS: `*` matches any bytes, `?` one byte, `[abc]`, `[a-z]` and
+/// `[^abc]` one of the bytes, `\` escapes the next byte. On a mismatch only the last `*` takes
+/// one more byte, which covers what earlier stars could take, so matching is linear per star.
+fn glob_match(pattern: &[u8], text: &[u8]) -> bool {
+    let (mut pattern_index, mut text_index) = (0, 0);
+    // Pattern index after the last star and the text index it matched up to
+    let mut star = None;
+    while text_index < text.len() {
+        if pattern.get(pattern_index) == Some(&b'*') {
+            pattern_index += 1;
+            star = Some((pattern_index, text_index));
+            continue;
+        }
+        if let Some(length) = match_byte(&pattern[pattern_index..], text[text_index]) {
+            pattern_index += length;
+            text_index += 1;
+            continue;
+        }
+        let Some((star_pattern_index, star_text_index)) = star else {
+            return false;
+        };
+        pattern_index = star_pattern_index;
+        text_index = star_text_index + 1;
+        star = Some((star_pattern_index, text_index));
+    }
+    pattern[pattern_index..].iter().all(|byte| *byte == b'*')
+}
+
+/// Length of the pattern element at the start of the pattern if it matches the byte
+fn match_byte(pattern: &[u8], byte: u8) -> Option<usize> {
+    match pattern.first()? {
+        b'*' => None,
+        b'?' => Some(1),
+        b'[' => {
+            let mut class = &pattern[1..];
+            let negated = class.first() == Some(&b'^');
+            if negated {
+                class = &class[1..];
+            }
+            let mut matched = false;
+            let mut index = 0;
+            while index < class.len() && class[index] != b']' {
+                if class[index] == b'\\' && index + 1 < class.len() {
+                    index += 1;
+                    matched |= class[index] == byte;
+                } else if index + 2 < class.len() && class[index + 1] == b'-' {
+                    let (low, high) = (
+                        class[index].min(class[index + 2]),
+                        class[index].max(class[index + 2]),
+                    );
+                    matched |= (low..=high).contains(&byte);
+                    index += 2;
+                } else {
+                    matched |= class[index] == byte;
+                }
+                index += 1;
+            }
+            let length = pattern.len() - class.len() + (index + 1).min(class.len());
+            (matched != negated).then_some(length)
+        }
+        b'\\' if pattern.len() > 1 => (pattern[1] == byte).then_some(2),
+        expected => (*expected == byte).then_some(1),
+    }
+}
+
+/// Keys the unfinished SCAN iterations go on from, by cursor
+#[derive(Debug, Default)]
+struct ScanCursors {
+    last_cursor: u64,
+    keys: HashMap<u64, Vec<u8>>,
+    order: VecDeque<u64>,
+}
+
+impl ScanCursors {
+    /// New cursor for the key, never 0 which starts and ends iterations
+    fn insert(&mut self, key: Vec<u8>) -> u64 {
+        self.last_cursor = self.last_cursor.checked_add(1).unwrap_or(1);
+        self.keys.insert(self.last_cursor, key);
+        self.order.push_back(self.last_cursor);
+        while self.order.len() > MAX_SCAN_CURSORS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.keys.remove(&oldest);
+            }
+        }
+        self.last_cursor
+    }
+
+    /// Key of the cursor, a client may repeat a call with the same cursor
+    fn get(&self, cursor: u64) -> Option<Vec<u8>> {
+        self.keys.get(&cursor).cloned()
+    }
+}
+
+#[derive(Debug)]
+pub enum RespError {
+    IoError(std::io::Error),
+    /// The client sent something not following the protocol, the connection is closed
+    Malformed(String),
+}
+
+impl Display for RespError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RespError::IoError(e) => write!(f, "Redis protocol IoError {}", e),
+            RespError::Malformed(message) => {
+                write!(f, "Redis protocol: malformed command, {}", message)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::server::redis::{glob_match, RedisServer};
+    use crate::utils::logging::init_logging;
+    use std::io::Cursor;
+
+    fn command(arguments: &[&str]) -> String {
+        let mut command = format!("*{}\r\n", arguments.len());
+        for argument in arguments {
+            command.push_str(&format!("${}\r\n{}\r\n", argument.len(), argument));
+        }
+        command
+    }
+
+    /// Runs a connection sending the commands, returns the replies received
+    fn converse(server: &RedisServer, commands: &[&[&str]]) -> String {
+        let input: String = commands
+            .iter()
+            .map(|arguments| command(arguments))
+            .collect();
+        let mut output = vec![];
+        server
+            .serve_connection(Cursor::new(input.into_bytes()), &mut output)
+            .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_strings_and_expiry() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("redis_strings")
+            .build()
+            .unwrap();
+        let server = RedisServer::new(&hfdb).unwrap();
+        assert_eq!(
+            converse(
+                &server,
+                &[
+                    &["PING"],
+                    &["SET", "name", "Ada"],
+                    &["SET", "name", "Bob", "NX"],
+                    &["SET", "other", "Bob", "XX"],
+                    &["get", "name"],
+                    &["GET", "missing"],
+                    &["SET", "counter", "41", "EX", "100"],
+                    &["INCR", "counter"],
+                    &["TTL", "counter"],
+                    &["DECRBY", "counter", "2"],
+                    &["INCR", "name"],
+                    &["MSET", "a", "1", "b", "2"],
+                    &["MGET", "a", "missing", "b"],
+                    &["DEL", "a", "missing"],
+                    &["EXISTS", "a", "b", "name"],
+                    &["EXPIRE", "b", "0"],
+                    &["TTL", "b"],
+                    &["TTL", "name"],
+                    &["KEYS", "*"],
+                    &["SCAN", "0", "COUNT", "1"],
+                    &["SCAN", "1", "MATCH", "n*"],
+                    &["SET", "name"],
+                    &["NOSUCHCOMMAND"],
+                ]
+            ),
+            "+PONG\r\n+OK\r\n$-1\r\n$-1\r\n$3\r\nAda\r\n$-1\r\n+OK\r\n:42\r\n:100\r\n:40\r\n\
+             -ERR value is not an integer or out of range\r\n+OK\r\n\
+             *3\r\n$1\r\n1\r\n$-1\r\n$1\r\n2\r\n:1\r\n:2\r\n:1\r\n:-2\r\n:-1\r\n\
+             *2\r\n$7\r\ncounter\r\n$4\r\nname\r\n\
+             *2\r\n$1\r\n1\r\n*1\r\n$7\r\ncounter\r\n\
+             *2\r\n$1\r\n0\r\n*1\r\n$4\r\nname\r\n\
+             -ERR wrong number of arguments for 'set' command\r\n\
+             -ERR unknown command 'nosuchcommand'\r\n"
+        );
+
+        // Data is kept in the tables, a new connection sees it
+        assert_eq!(converse(&server, &[&["GET", "counter"]]), "$2\r\n40\r\n");
+    }
+
+    #[test]
+    fn test_hashes_lists_and_resp3() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("redis_hashes_lists")
+            .build()
+            .unwrap();
+        let server = RedisServer::new(&hfdb).unwrap();
+        assert_eq!(
+            converse(
+                &server,
+                &[
+                    &["HSET", "person", "name", "Ada", "age", "36"],
+                    &["HGET", "person", "age"],
+                    &["HGETALL", "person"],
+                    &["HDEL", "person", "age", "missing"],
+                    &["RPUSH", "queue", "b", "c"],
+                    &["LPUSH", "queue", "a"],
+                    &["LRANGE", "queue", "0", "-1"],
+                    &["RPOP", "queue"],
+                    &["LPOP", "queue", "5"],
+                    &["LLEN", "queue"],
+                    &["GET", "person"],
+                    &["TYPE", "person"],
+                ]
+            ),
+            ":2\r\n$2\r\n36\r\n*4\r\n$3\r\nage\r\n$2\r\n36\r\n$4\r\nname\r\n$3\r\nAda\r\n\
+             :1\r\n:2\r\n:3\r\n*3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n$1\r\nc\r\n\
+             *2\r\n$1\r\na\r\n$1\r\nb\r\n:0\r\n\
+             -WRONGTYPE Operation against a key holding the wrong kind of value\r\n+hash\r\n"
+        );
+
+        // RESP3 has maps and its own null
+        let output = converse(
+            &server,
+            &[&["HELLO", "3"], &["HGETALL", "person"], &["GET", "missing"]],
+        );
+        assert!(output.starts_with("%6\r\n$6\r\nserver\r\n"));
+        assert!(output.ends_with("%1\r\n$4\r\nname\r\n$3\r\nAda\r\n_\r\n"));
+    }
+
+    #[test]
+    fn test_inline_commands_and_glob_match() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("redis_inline").build().unwrap();
+        let server = RedisServer::new(&hfdb).unwrap();
+        let mut output = vec![];
+        server
+            .serve_connection(
+                Cursor::new(b"SET greeting hello\r\nGET greeting\r\nQUIT\r\nPING\r\n".to_vec()),
+                &mut output,
+            )
+            .unwrap();
+        assert_eq!(output, b"+OK\r\n$5\r\nhello\r\n+OK\r\n");
+
+        assert!(glob_match(b"h?llo", b"hello"));
+        assert!(glob_match(b"h*o", b"hello"));
+        assert!(glob_match(b"h[ae]llo", b"hallo"));
+        assert!(!glob_match(b"h[^e]llo", b"hello"));
+        assert!(glob_match(b"h[a-f]llo", b"hello"));
+        assert!(glob_match(b"h\\*", b"h*"));
+        assert!(!glob_match(b"h\\*", b"hx"));
+        assert!(glob_match(b"*llo*", b"hello"));
+        assert!(glob_match(b"h[el", b"he"));
+        assert!(!glob_match(b"*?*?", b"h"));
+        let started = std::time::Instant::now();
+        assert!(!glob_match(b"*a*a*a*a*a*a*a*a*a*a*b", &[b'a'; 10_000]));
+        assert!(started.elapsed() < std::time::Duration::from_secs(1));
+    }
+
+    #[test]
+    fn test_scan_returns_every_key_once() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("redis_scan").build().unwrap();
+        let server = RedisServer::new(&hfdb).unwrap();
+        let keys: Vec<String> = (0..25).map(|nr| format!("key{:02}", nr)).collect();
+        let mut mset = vec!["MSET"];
+        for key in &keys {
+            mset.extend([key.as_str(), "value"]);
+        }
+        assert_eq!(converse(&server, &[&mset]), "+OK\r\n");
+
+        let mut cursor = "0".to_string();
+        let mut scanned = vec![];
+        loop {
+            let reply = converse(&server, &[&["SCAN", &cursor, "COUNT", "4"]]);
+            let lines: Vec<&str> = reply.split("\r\n").collect();
+            cursor = lines[2].to_string();
+            scanned.extend(
+                lines[4..]
+                    .iter()
+                    .filter(|line| !line.is_empty() && !line.starts_with('$'))
+                    .map(|line| line.to_string()),
+            );
+            if cursor == "0" {
+                break;
+            }
+            // A repeated call with the same cursor continues at the same key
+            let first = converse(&server, &[&["SCAN", &cursor, "COUNT", "1"]]);
+            let again = converse(&server, &[&["SCAN", &cursor, "COUNT", "1"]]);
+            assert_eq!(first.rsplit("\r\n").nth(1), again.rsplit("\r\n").nth(1));
+        }
+        assert_eq!(scanned, keys);
+        assert_eq!(
+            converse(&server, &[&["SCAN", "999", "COUNT", "4"]]),
+            "-ERR invalid cursor\r\n"
+        );
+    }
+}
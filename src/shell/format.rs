@@ -0,0 +1,244 @@
+use crate::query_processing::executor::{QueryResult, ResultSet};
+use crate::record_management::value::Value;
+use crate::utils::json::Json;
+
+/// Largest integer a JSON number holds exactly, larger ones are written as strings
+const MAX_SAFE_INTEGER: i128 = (1 << 53) - 1;
+
+/// How the shell prints the results of statements
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum OutputMode {
+    /// Aligned columns in a box, followed by the number of rows
+    Table,
+    /// Header line and one line per row, as in RFC 4180, NULL is an empty field
+    Csv,
+    /// Array of one object per row
+    Json,
+}
+
+impl OutputMode {
+    pub const ALL: [OutputMode; 3] = [OutputMode::Table, OutputMode::Csv, OutputMode::Json];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputMode::Table => "table",
+            OutputMode::Csv => "csv",
+            OutputMode::Json => "json",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<OutputMode> {
+        Self::ALL
+            .into_iter()
+            .find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+}
+
+/// Text printed for the result of a statement, empty if there is nothing to print. Only the table
+/// mode reports results other than rows, so CSV and JSON output can be processed further.
+pub fn format_result(result: &QueryResult, mode: OutputMode) -> String {
+    match (result, mode) {
+        (QueryResult::Rows(result_set), OutputMode::Table) => table(result_set),
+        (QueryResult::Rows(result_set), OutputMode::Csv) => csv(result_set),
+        (QueryResult::Rows(result_set), OutputMode::Json) => format!("{}\n", json(result_set)),
+        (result, OutputMode::Table) => format!("{}\n", message(result)),
+        _ => String::new(),
+    }
+}
+
+fn message(result: &QueryResult) -> String {
+    let rows = |count: usize| match count {
+        1 => "1 row".to_string(),
+        count => format!("{} rows", count),
+    };
+    match result {
+        QueryResult::Rows(result_set) => format!("({})", rows(result_set.rows.len())),
+        QueryResult::Inserted(count) => format!("{} inserted", rows(*count)),
+        QueryResult::Updated(count) => format!("{} updated", rows(*count)),
+        QueryResult::Deleted(count) => format!("{} deleted", rows(*count)),
+        QueryResult::Analyzed(1) => "1 table analyzed".to_string(),
+        QueryResult::Analyzed(count) => format!("{} tables analyzed", count),
+        QueryResult::TableCreated => "Table created".to_string(),
+        QueryResult::TableDropped => "Table dropped".to_string(),
+        QueryResult::IndexCreated => "Index created".to_string(),
+        QueryResult::Begin => "Transaction started".to_string(),
+        QueryResult::Commit => "Transaction committed".to_string(),
+        QueryResult::Rollback => "Transaction rolled back".to_string(),
+    }
+}
+
+/// Text of a value in a table or CSV field, None for NULL
+fn text(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        value => Some(value.to_string()),
+    }
+}
+
+fn table(result_set: &ResultSet) -> String {
+    let header: Vec<String> = result_set
+        .columns
+        .iter()
+        .map(|column| column.name.clone())
+        .collect();
+    let cells: Vec<Vec<String>> = result_set
+        .rows
+        .iter()
+        .map(|row| {
+            row.iter()
+                .map(|value| text(value).unwrap_or_else(|| "NULL".to_string()))
+                .collect()
+        })
+        .collect();
+    let mut widths: Vec<usize> = header.iter().map(|name| name.chars().count()).collect();
+    for row in &cells {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    let separator: String = widths
+        .iter()
+        .map(|width| format!("+{}", "-".repeat(width + 2)))
+        .collect::<String>()
+        + "+\n";
+    let line = |cells: &[String], right_aligned: &dyn Fn(usize) -> bool| {
+        cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(index, (cell, width))| match right_aligned(index) {
+                true => format!("| {:>width$} ", cell, width = width),
+                false => format!("| {:<width$} ", cell, width = width),
+            })
+            .collect::<String>()
+            + "|\n"
+    };
+
+    let mut output = separator.clone();
+    output.push_str(&line(&header, &|_| false));
+    output.push_str(&separator);
+    for (row, values) in cells.iter().zip(&result_set.rows) {
+        output.push_str(&line(row, &|index| {
+            matches!(values[index], Value::Integer(_))
+        }));
+    }
+    if !cells.is_empty() {
+        output.push_str(&separator);
+    }
+    output.push_str(&message(&QueryResult::Rows(result_set.clone())));
+    output.push('\n');
+    output
+}
+
+fn csv(result_set: &ResultSet) -> String {
+    let field = |text: &str| {
+        if text.contains([',', '"', '\n', '\r']) {
+            format!("\"{}\"", text.replace('"', "\"\""))
+        } else {
+            text.to_string()
+        }
+    };
+    let mut output = result_set
+        .columns
+        .iter()
+        .map(|column| field(&column.name))
+        .collect::<Vec<_>>()
+        .join(",");
+    output.push('\n');
+    for row in &result_set.rows {
+        let fields: Vec<String> = row
+            .iter()
+            .map(|value| text(value).map(|text| field(&text)).unwrap_or_default())
+            .collect();
+        output.push_str(&fields.join(","));
+        output.push('\n');
+    }
+    output
+}
+
+fn json(result_set: &ResultSet) -> Json {
+    Json::Array(
+        result_set
+            .rows
+            .iter()
+            .map(|row| {
+                Json::Object(
+                    result_set
+                        .columns
+                        .iter()
+                        .zip(row)
+                        .map(|(column, value)| {
+                            let value = match value {
+                                Value::Null => Json::Null,
+                                Value::Integer(integer) if integer.abs() <= MAX_SAFE_INTEGER => {
+                                    Json::Number(*integer as f64)
+                                }
+                                Value::Text(text) => Json::from(text.as_str()),
+                                value => Json::from(value.to_string()),
+                            };
+                            (column.name.clone(), value)
+                        })
+                        .collect(),
+                )
+            })
+            .collect(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::query_processing::executor::{QueryResult, ResultSet};
+    use crate::query_processing::plan::PlanColumn;
+    use crate::record_management::schema::ColumnType;
+    use crate::record_management::value::Value;
+    use crate::shell::format::{format_result, OutputMode};
+
+    #[test]
+    fn test_format_result() {
+        let column = |name: &str, column_type| PlanColumn {
+            table: None,
+            name: name.to_string(),
+            column_type,
+        };
+        let result = QueryResult::Rows(ResultSet {
+            columns: vec![
+                column("id", ColumnType::BigInteger),
+                column("name", ColumnType::Varchar),
+            ],
+            rows: vec![
+                vec![Value::from(7i64), Value::from("Ada, \"the first\"")],
+                vec![Value::from(12345i64), Value::Null],
+                vec![Value::Integer(1 << 60), Value::from(vec![1u8, 255])],
+            ],
+        });
+        assert_eq!(
+            format_result(&result, OutputMode::Table),
+            "+---------------------+------------------+\n\
+             | id                  | name             |\n\
+             +---------------------+------------------+\n\
+             |                   7 | Ada, \"the first\" |\n\
+             |               12345 | NULL             |\n\
+             | 1152921504606846976 | 0x01ff           |\n\
+             +---------------------+------------------+\n\
+             (3 rows)\n"
+        );
+        assert_eq!(
+            format_result(&result, OutputMode::Csv),
+            "id,name\n7,\"Ada, \"\"the first\"\"\"\n12345,\n1152921504606846976,0x01ff\n"
+        );
+        assert_eq!(
+            format_result(&result, OutputMode::Json),
+            "[{\"id\":7,\"name\":\"Ada, \\\"the first\\\"\"},{\"id\":12345,\"name\":null},\
+             {\"id\":\"1152921504606846976\",\"name\":\"0x01ff\"}]\n"
+        );
+        assert_eq!(
+            format_result(&QueryResult::Inserted(1), OutputMode::Table),
+            "1 row inserted\n"
+        );
+        assert_eq!(
+            format_result(&QueryResult::Inserted(1), OutputMode::Csv),
+            ""
+        );
+        assert_eq!(OutputMode::from_name("JSON"), Some(OutputMode::Json));
+    }
+}
@@ -0,0 +1,129 @@
+use crate::server::options::{OptionsError, ServerOptions};
+use crate::shell::format::OutputMode;
+
+/// SQL and meta-commands to run instead of the interactive shell
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum ShellInput {
+    /// Given with `-c`
+    Command(String),
+    /// Script file given with `-f`
+    File(String),
+}
+
+/// Command line options of the shell: `[DB_DIRECTORY] [--mode table|csv|json] [-c SQL]...
+/// [-f FILE]...` and the options of the database as for the servers, the database directory
+/// may be given as option or as argument. `-h` or `--help` asks for the usage, as for the
+/// servers.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct ShellOptions {
+    /// Options of the database, without a listen address
+    pub database: ServerOptions,
+    pub mode: OutputMode,
+    /// Inputs to run in order, the shell is interactive if there are none
+    pub inputs: Vec<ShellInput>,
+    pub help: bool,
+}
+
+impl ShellOptions {
+    /// Options given as arguments (without the program name), the others keep the defaults
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> Result<Self, OptionsError> {
+        let mut options = Self {
+            database: ServerOptions::new(""),
+            mode: OutputMode::Table,
+            inputs: vec![],
+            help: false,
+        };
+        let mut db_directory = None;
+        let mut args = args.into_iter();
+        while let Some(option) = args.next() {
+            if option == "-h" || option == "--help" {
+                options.help = true;
+                continue;
+            }
+            if !option.starts_with('-') && db_directory.is_none() {
+                db_directory = Some(option);
+                continue;
+            }
+            let value = args
+                .next()
+                .ok_or_else(|| OptionsError::MissingValue(option.clone()))?;
+            match option.as_str() {
+                "-c" | "--command" => options.inputs.push(ShellInput::Command(value)),
+                "-f" | "--file" => options.inputs.push(ShellInput::File(value)),
+                "--mode" => {
+                    options.mode = OutputMode::from_name(&value)
+                        .ok_or(OptionsError::InvalidValue(option, value))?
+                }
+                _ => {
+                    if !options.database.set_database_option(&option, value)? {
+                        return Err(OptionsError::UnknownOption(option));
+                    }
+                }
+            }
+        }
+        if let Some(db_directory) = db_directory {
+            options.database.db_directory = db_directory;
+        }
+        Ok(options)
+    }
+
+    pub fn usage(program: &str) -> String {
+        format!(
+            "Usage: {program} [-h|--help] [DB_DIRECTORY] \
+             [--mode table|csv|json (default table)] [-c SQL]... [-f FILE]... {}\n\
+             Runs the SQL and files given in order, else the statements read from stdin, \
+             interactively if stdin is a terminal.",
+            ServerOptions::database_usage()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::server::options::OptionsError;
+    use crate::shell::format::OutputMode;
+    use crate::shell::options::{ShellInput, ShellOptions};
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn test_shell_options_from_args() {
+        let options = ShellOptions::from_args(args(&[
+            "/tmp/db",
+            "--mode",
+            "csv",
+            "-c",
+            "SELECT 1",
+            "--pool-size",
+            "8",
+            "-f",
+            "script.sql",
+        ]))
+        .unwrap();
+        assert_eq!(options.database.db_directory, "/tmp/db");
+        assert_eq!(options.database.pool_size, 8);
+        assert_eq!(options.mode, OutputMode::Csv);
+        assert_eq!(
+            options.inputs,
+            vec![
+                ShellInput::Command("SELECT 1".to_string()),
+                ShellInput::File("script.sql".to_string())
+            ]
+        );
+        assert!(!options.help);
+        assert!(ShellOptions::from_args(args(&["--help"])).unwrap().help);
+        assert_eq!(
+            ShellOptions::from_args(args(&["--mode", "xml"])),
+            Err(OptionsError::InvalidValue(
+                "--mode".to_string(),
+                "xml".to_string()
+            ))
+        );
+        assert_eq!(
+            ShellOptions::from_args(args(&["--listen", "0.0.0.0:1"])),
+            Err(OptionsError::UnknownOption("--listen".to_string()))
+        );
+    }
+}
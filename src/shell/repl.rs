@@ -0,0 +1,607 @@
+use crate::db_management_system::hfdb::HanfriedDb;
+use crate::metadata_management::catalog::{Catalog, CatalogError};
+use crate::metadata_management::statistics::{StatisticsError, TableStatistics};
+use crate::query_processing::executor::{QueryResult, ResultSet};
+use crate::query_processing::lexer::Keyword;
+use crate::query_processing::parser::parse;
+use crate::query_processing::plan::PlanColumn;
+use crate::query_processing::session::{Session, SessionError};
+use crate::record_management::schema::ColumnType;
+use crate::record_management::table::{Table, TableError};
+use crate::record_management::table_scan::TableScanError;
+use crate::record_management::value::Value;
+use crate::shell::format::{format_result, OutputMode};
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use crate::transaction_management::transaction_manager::TransactionManager;
+use std::fmt::{Display, Formatter};
+use std::fs::OpenOptions;
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+
+const HELP: &str = "\
+.dump [TABLE]      SQL text recreating all tables or the given one
+.exit, .quit       Leave the shell
+.help              Show this help
+.history           Show the statements entered before
+.mode [MODE]       Show or set the output mode: table, csv or json
+.read FILE         Run the statements in the file
+.schema [TABLE]    CREATE statements of all tables or the given one
+.stats [TABLE]     Statistics of all tables or the given one, as collected by ANALYZE
+.tables            Names of the tables
+SQL statements end with ';' and may span several lines.
+";
+
+/// Whether the shell goes on reading input
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Flow {
+    Continue,
+    Quit,
+}
+
+/// SQL shell in the style of sqlite3: reads SQL statements, which may span several lines and are
+/// executed when terminated by a semicolon, and meta-commands starting with a dot on a line of
+/// their own. Results are printed in the output mode.
+///
+/// Meta-commands run in their own transaction, so within an explicit transaction they do not
+/// see its changes. The history holds the statements and meta-commands entered, it is kept in a
+/// file if one is given. There is no line editing, run the shell with rlwrap for it.
+pub struct Shell {
+    transaction_manager: TransactionManager,
+    catalog: Catalog,
+    session: Session,
+    mode: OutputMode,
+    /// Lines of the statement not terminated yet
+    pending: String,
+    history: Vec<String>,
+    history_file: Option<PathBuf>,
+}
+
+impl Shell {
+    pub fn new(hfdb: &HanfriedDb, mode: OutputMode) -> Self {
+        Self {
+            transaction_manager: hfdb.transaction_manager.clone(),
+            catalog: hfdb.catalog.clone(),
+            session: Session::new(&hfdb.transaction_manager, &hfdb.catalog),
+            mode,
+            pending: String::new(),
+            history: vec![],
+            history_file: None,
+        }
+    }
+
+    /// Appends the history to the file, starting with the entries already in it
+    pub fn with_history_file(mut self, path: PathBuf) -> Self {
+        if let Ok(history) = std::fs::read_to_string(&path) {
+            self.history = history
+                .lines()
+                .map(|line| line.replace('\r', "\n"))
+                .collect();
+        }
+        self.history_file = Some(path);
+        self
+    }
+
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    pub fn prompt(&self) -> &'static str {
+        match self.pending.is_empty() {
+            true => "hfdb> ",
+            false => "  ...> ",
+        }
+    }
+
+    /// Reads and handles lines until the end of the input or `.quit`. Errors are printed and
+    /// the shell goes on.
+    pub fn interactive(
+        &mut self,
+        input: &mut impl BufRead,
+        output: &mut impl Write,
+        errors: &mut impl Write,
+    ) -> Result<(), ShellError> {
+        loop {
+            write!(output, "{}", self.prompt()).map_err(ShellError::IoError)?;
+            output.flush().map_err(ShellError::IoError)?;
+            let mut line = String::new();
+            if input.read_line(&mut line).map_err(ShellError::IoError)? == 0 {
+                writeln!(output).map_err(ShellError::IoError)?;
+                return Ok(());
+            }
+            match self.handle_line(&line, output) {
+                Ok(Flow::Continue) => {}
+                Ok(Flow::Quit) => return Ok(()),
+                Err(ShellError::IoError(e)) => return Err(ShellError::IoError(e)),
+                Err(e) => writeln!(errors, "Error: {}", e).map_err(ShellError::IoError)?,
+            }
+        }
+    }
+
+    /// Runs the statements and meta-commands of the text, stopping at the first error. A last
+    /// statement without semicolon is executed, too.
+    pub fn run_script(&mut self, text: &str, output: &mut impl Write) -> Result<Flow, ShellError> {
+        for line in text.lines() {
+            if self.handle_line(line, output)? == Flow::Quit {
+                return Ok(Flow::Quit);
+            }
+        }
+        if self.pending.trim().is_empty() {
+            self.pending.clear();
+            return Ok(Flow::Continue);
+        }
+        let sql = std::mem::take(&mut self.pending);
+        self.execute(&sql, output)?;
+        Ok(Flow::Continue)
+    }
+
+    /// Handles a line of input: a meta-command, or a part of SQL executed once the statement is
+    /// complete
+    pub fn handle_line(&mut self, line: &str, output: &mut impl Write) -> Result<Flow, ShellError> {
+        let line = line.trim_end_matches(['\n', '\r']);
+        if self.pending.is_empty() && line.trim_start().starts_with('.') {
+            self.remember(line.trim())?;
+            return self.meta_command(line.trim(), output);
+        }
+        if self.pending.is_empty() && line.trim().is_empty() {
+            return Ok(Flow::Continue);
+        }
+        self.pending.push_str(line);
+        self.pending.push('\n');
+        if !is_complete(&self.pending) {
+            return Ok(Flow::Continue);
+        }
+        let sql = std::mem::take(&mut self.pending);
+        self.execute(&sql, output)?;
+        Ok(Flow::Continue)
+    }
+
+    /// Executes the statements one after the other, printing their results
+    fn execute(&mut self, sql: &str, output: &mut impl Write) -> Result<(), ShellError> {
+        self.remember(sql.trim())?;
+        let statements =
+            parse(sql).map_err(|e| ShellError::SessionError(SessionError::ParserError(e)))?;
+        for statement in statements {
+            let result = self
+                .session
+                .execute_statement(&statement)
+                .map_err(ShellError::SessionError)?;
+            self.print(&result, output)?;
+        }
+        Ok(())
+    }
+
+    fn print(&self, result: &QueryResult, output: &mut impl Write) -> Result<(), ShellError> {
+        output
+            .write_all(format_result(result, self.mode).as_bytes())
+            .map_err(ShellError::IoError)
+    }
+
+    fn remember(&mut self, entry: &str) -> Result<(), ShellError> {
+        if entry.is_empty() || self.history.last().is_some_and(|last| last == entry) {
+            return Ok(());
+        }
+        self.history.push(entry.to_string());
+        if let Some(path) = &self.history_file {
+            // One line per entry, line breaks within statements are kept as carriage returns
+            let mut file = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .map_err(ShellError::IoError)?;
+            writeln!(file, "{}", entry.replace('\n', "\r")).map_err(ShellError::IoError)?;
+        }
+        Ok(())
+    }
+
+    fn meta_command(&mut self, line: &str, output: &mut impl Write) -> Result<Flow, ShellError> {
+        let mut words = line.split_whitespace();
+        let command = words.next().unwrap_or_default();
+        let argument = words.next();
+        if words.next().is_some() {
+            return Err(ShellError::Usage(format!(
+                "too many arguments for {}",
+                command
+            )));
+        }
+        let text = match (command, argument) {
+            (".exit" | ".quit", None) => return Ok(Flow::Quit),
+            (".help", None) => HELP.to_string(),
+            (".history", None) => self
+                .history
+                .iter()
+                .enumerate()
+                .map(|(number, entry)| format!("{:5}  {}\n", number + 1, entry))
+                .collect(),
+            (".mode", None) => format!("{}\n", self.mode.name()),
+            (".mode", Some(name)) => {
+                self.mode = OutputMode::from_name(name).ok_or_else(|| {
+                    ShellError::Usage(format!("unknown mode {}, use table, csv or json", name))
+                })?;
+                String::new()
+            }
+            (".read", Some(path)) => {
+                let script = std::fs::read_to_string(path).map_err(ShellError::IoError)?;
+                return self.run_script(&script, output);
+            }
+            (".tables", None) => self.in_transaction(|transaction| {
+                Ok(self
+                    .tables(transaction, None)?
+                    .into_iter()
+                    .map(|table| format!("{}\n", table))
+                    .collect())
+            })?,
+            (".schema", table) => self.in_transaction(|transaction| {
+                let mut text = String::new();
+                for table in self.tables(transaction, table)? {
+                    text.push_str(&self.schema(transaction, &table)?);
+                }
+                Ok(text)
+            })?,
+            (".dump", table) => self.in_transaction(|transaction| {
+                let mut text = "BEGIN;\n".to_string();
+                for table in self.tables(transaction, table)? {
+                    text.push_str(&self.schema(transaction, &table)?);
+                    text.push_str(&self.rows(transaction, &table)?);
+                }
+                text.push_str("COMMIT;\n");
+                Ok(text)
+            })?,
+            (".stats", table) => {
+                let result_set = self.in_transaction(|transaction| {
+                    let mut rows = vec![];
+                    for table in self.tables(transaction, table)? {
+                        let info = self
+                            .catalog
+                            .table(transaction, &table)
+                            .map_err(ShellError::CatalogError)?
+                            .unwrap();
+                        let statistics = TableStatistics::load(transaction, &self.catalog, &info)
+                            .map_err(ShellError::StatisticsError)?;
+                        let count = |count: u64| Value::Integer(count as i128);
+                        rows.push(vec![
+                            Value::from(table.as_str()),
+                            Value::Null,
+                            count(statistics.row_count),
+                            count(statistics.block_count),
+                            count(statistics.row_length),
+                            Value::Null,
+                            Value::Null,
+                        ]);
+                        for column in info.layout.schema().columns() {
+                            if let Some(column_statistics) = statistics.columns.get(&column.name) {
+                                rows.push(vec![
+                                    Value::from(table.as_str()),
+                                    Value::from(column.name.as_str()),
+                                    Value::Null,
+                                    Value::Null,
+                                    Value::Null,
+                                    count(column_statistics.distinct_values),
+                                    count(column_statistics.null_count),
+                                ]);
+                            }
+                        }
+                    }
+                    Ok(ResultSet {
+                        columns: [
+                            ("table", ColumnType::Varchar),
+                            ("column", ColumnType::Varchar),
+                            ("rows", ColumnType::BigCount),
+                            ("blocks", ColumnType::BigCount),
+                            ("row_length", ColumnType::BigCount),
+                            ("distinct_values", ColumnType::BigCount),
+                            ("null_count", ColumnType::BigCount),
+                        ]
+                        .into_iter()
+                        .map(|(name, column_type)| PlanColumn {
+                            table: None,
+                            name: name.to_string(),
+                            column_type,
+                        })
+                        .collect(),
+                        rows,
+                    })
+                })?;
+                self.print(&QueryResult::Rows(result_set), output)?;
+                String::new()
+            }
+            _ => return Err(ShellError::UnknownCommand(line.to_string())),
+        };
+        output
+            .write_all(text.as_bytes())
+            .map_err(ShellError::IoError)?;
+        Ok(Flow::Continue)
+    }
+
+    fn in_transaction<R>(
+        &self,
+        action: impl FnOnce(&Transaction) -> Result<R, ShellError>,
+    ) -> Result<R, ShellError> {
+        let transaction = self
+            .transaction_manager
+            .begin()
+            .map_err(ShellError::TransactionError)?;
+        let result = action(&transaction);
+        match result {
+            Ok(_) => transaction.commit(),
+            Err(_) => transaction.rollback(),
+        }
+        .map_err(ShellError::TransactionError)?;
+        result
+    }
+
+    /// Names of the tables without the catalog tables, or the given table if it exists
+    fn tables(
+        &self,
+        transaction: &Transaction,
+        table: Option<&str>,
+    ) -> Result<Vec<String>, ShellError> {
+        let names = self
+            .catalog
+            .table_names(transaction)
+            .map_err(ShellError::CatalogError)?;
+        match table {
+            Some(table) => match names.iter().any(|name| name == table) {
+                true => Ok(vec![table.to_string()]),
+                false => Err(ShellError::TableNotFound(table.to_string())),
+            },
+            None => Ok(names
+                .into_iter()
+                .filter(|name| !name.starts_with("hfdb_"))
+                .collect()),
+        }
+    }
+
+    fn schema(&self, transaction: &Transaction, table: &str) -> Result<String, ShellError> {
+        let info = self
+            .catalog
+            .table(transaction, table)
+            .map_err(ShellError::CatalogError)?
+            .ok_or_else(|| ShellError::TableNotFound(table.to_string()))?;
+        let columns: Vec<String> = info
+            .layout
+            .schema()
+            .columns()
+            .iter()
+            .map(|column| {
+                format!(
+                    "{} {}{}",
+                    quote_identifier(&column.name),
+                    column.column_type.name(),
+                    if column.nullable { "" } else { " NOT NULL" }
+                )
+            })
+            .collect();
+        let mut text = format!(
+            "CREATE TABLE {} ({});\n",
+            quote_identifier(table),
+            columns.join(", ")
+        );
+        for index in self
+            .catalog
+            .indexes(transaction, table)
+            .map_err(ShellError::CatalogError)?
+        {
+            text.push_str(&format!(
+                "CREATE INDEX {} ON {} ({}) USING {};\n",
+                quote_identifier(&index.name),
+                quote_identifier(table),
+                quote_identifier(&index.column_name),
+                index.index_type.name()
+            ));
+        }
+        Ok(text)
+    }
+
+    /// INSERT statements for the rows of the table
+    fn rows(&self, transaction: &Transaction, table: &str) -> Result<String, ShellError> {
+        let table =
+            Table::open(transaction, &self.catalog, table).map_err(ShellError::TableError)?;
+        let mut scan = table.scan();
+        let mut text = String::new();
+        while scan.next_row().map_err(ShellError::TableScanError)? {
+            let values: Vec<String> = scan
+                .row()
+                .unwrap()
+                .values()
+                .iter()
+                .map(sql_literal)
+                .collect();
+            text.push_str(&format!(
+                "INSERT INTO {} VALUES ({});\n",
+                quote_identifier(&table.info().name),
+                values.join(", ")
+            ));
+        }
+        Ok(text)
+    }
+}
+
+/// Whether the SQL ends with a semicolon outside of literals, quoted identifiers and comments
+fn is_complete(sql: &str) -> bool {
+    let mut chars = sql.chars().peekable();
+    let mut complete = false;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                complete = false;
+                // A doubled quote within the literal ends it and starts it again
+                if !chars.any(|next| next == c) {
+                    return false;
+                }
+            }
+            '-' if chars.peek() == Some(&'-') => {
+                chars.find(|next| *next == '\n');
+            }
+            '/' if chars.peek() == Some(&'*') => {
+                chars.next();
+                let mut previous = ' ';
+                loop {
+                    match chars.next() {
+                        Some('/') if previous == '*' => break,
+                        Some(next) => previous = next,
+                        None => return false,
+                    }
+                }
+            }
+            ';' => complete = true,
+            c if c.is_whitespace() => {}
+            _ => complete = false,
+        }
+    }
+    complete
+}
+
+/// The identifier, quoted if the lexer would not read it as it is
+fn quote_identifier(name: &str) -> String {
+    let plain = name
+        .chars()
+        .next()
+        .is_some_and(|c| c.is_ascii_lowercase() || c == '_')
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && Keyword::from_name(name).is_none();
+    match plain {
+        true => name.to_string(),
+        false => format!("\"{}\"", name.replace('"', "\"\"")),
+    }
+}
+
+fn sql_literal(value: &Value) -> String {
+    match value {
+        Value::Text(text) => format!("'{}'", text.replace('\'', "''")),
+        Value::Bytes(bytes) => format!(
+            "X'{}'",
+            bytes
+                .iter()
+                .map(|byte| format!("{:02x}", byte))
+                .collect::<String>()
+        ),
+        value => value.to_string(),
+    }
+}
+
+#[derive(Debug)]
+pub enum ShellError {
+    IoError(std::io::Error),
+    SessionError(SessionError),
+    TransactionError(TransactionError),
+    CatalogError(CatalogError),
+    TableError(TableError),
+    TableScanError(TableScanError),
+    StatisticsError(StatisticsError),
+    UnknownCommand(String),
+    Usage(String),
+    TableNotFound(String),
+}
+
+impl Display for ShellError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShellError::IoError(e) => write!(f, "Shell IoError {}", e),
+            ShellError::SessionError(e) => write!(f, "{}", e),
+            ShellError::TransactionError(e) => write!(f, "Shell {}", e),
+            ShellError::CatalogError(e) => write!(f, "Shell {}", e),
+            ShellError::TableError(e) => write!(f, "Shell {}", e),
+            ShellError::TableScanError(e) => write!(f, "Shell {}", e),
+            ShellError::StatisticsError(e) => write!(f, "Shell {}", e),
+            ShellError::UnknownCommand(command) => {
+                write!(f, "Shell: unknown command {}, see .help", command)
+            }
+            ShellError::Usage(message) => write!(f, "Shell: {}", message),
+            ShellError::TableNotFound(table) => write!(f, "Shell: table {} not found", table),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::db_management_system::hfdb::HanfriedDbBuilder;
+    use crate::shell::format::OutputMode;
+    use crate::shell::repl::{is_complete, Flow, Shell, ShellError};
+    use crate::utils::logging::init_logging;
+    use std::io::Cursor;
+
+    fn run(shell: &mut Shell, script: &str) -> String {
+        let mut output = vec![];
+        shell.run_script(script, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn test_is_complete() {
+        assert!(is_complete("SELECT 1;"));
+        assert!(is_complete("SELECT 1; -- done\n"));
+        assert!(!is_complete("SELECT ';'"));
+        assert!(!is_complete("SELECT 'it''s;"));
+        assert!(is_complete("SELECT 'it''s';"));
+        assert!(!is_complete("SELECT 1 /* ; */"));
+        assert!(!is_complete("SELECT 1; SELECT"));
+    }
+
+    #[test]
+    fn test_shell() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("shell").build().unwrap();
+        let mut shell = Shell::new(&hfdb, OutputMode::Table);
+        assert_eq!(
+            run(
+                &mut shell,
+                "CREATE TABLE persons (id BIGINT NOT NULL, name VARCHAR, \"order\" INT);\n\
+                 CREATE INDEX persons_id ON persons (id);\n\
+                 INSERT INTO persons\n  VALUES (1, 'Ada', NULL),\n  (2, 'O''Brien', 7);\n\
+                 ANALYZE persons;"
+            ),
+            "Table created\nIndex created\n2 rows inserted\n1 table analyzed\n"
+        );
+        assert_eq!(
+            run(
+                &mut shell,
+                ".tables\n.mode csv\nSELECT name FROM persons ORDER BY id"
+            ),
+            "persons\nname\nAda\nO'Brien\n"
+        );
+        let dump = run(&mut shell, ".dump persons");
+        assert_eq!(
+            dump,
+            "BEGIN;\n\
+             CREATE TABLE persons (id BigInteger NOT NULL, name Varchar, \"order\" Integer);\n\
+             CREATE INDEX persons_id ON persons (id) USING BTree;\n\
+             INSERT INTO persons VALUES (1, 'Ada', NULL);\n\
+             INSERT INTO persons VALUES (2, 'O''Brien', 7);\n\
+             COMMIT;\n"
+        );
+        assert!(run(&mut shell, ".stats").starts_with(
+            "table,column,rows,blocks,row_length,distinct_values,null_count\npersons,,2,1,"
+        ));
+
+        // The dump recreates the table in another database
+        let copy = HanfriedDbBuilder::unittest("shell_copy").build().unwrap();
+        let mut copy_shell = Shell::new(&copy, OutputMode::Json);
+        run(&mut copy_shell, &dump);
+        assert_eq!(
+            run(&mut copy_shell, "SELECT * FROM persons ORDER BY id;"),
+            "[{\"id\":1,\"name\":\"Ada\",\"order\":null},{\"id\":2,\"name\":\"O'Brien\",\"order\":7}]\n"
+        );
+
+        // Interactively errors are reported and the shell goes on
+        let mut input = Cursor::new(
+            "SELECT name\n  FROM nowhere;\n.bogus\n.mode table\nSELECT count(*)\nFROM persons;\n\
+             .quit\nSELECT 1;\n",
+        );
+        let (mut output, mut errors) = (vec![], vec![]);
+        shell
+            .interactive(&mut input, &mut output, &mut errors)
+            .unwrap();
+        let output = String::from_utf8(output).unwrap();
+        assert!(output.contains("  ...> "));
+        assert!(output.contains("| count(*) |\n+----------+\n|        2 |"));
+        assert_eq!(String::from_utf8(errors).unwrap().lines().count(), 2);
+        assert_eq!(shell.history().last().unwrap(), ".quit");
+        assert!(matches!(
+            shell.run_script(".schema nowhere", &mut vec![]),
+            Err(ShellError::TableNotFound(_))
+        ));
+        assert_eq!(shell.run_script(".exit", &mut vec![]).unwrap(), Flow::Quit);
+    }
+}
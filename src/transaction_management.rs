@@ -0,0 +1,8 @@
+pub mod concurrency_manager;
+pub mod lock_table;
+pub mod log_record;
+pub mod mvcc;
+pub mod recovery_manager;
+pub mod transaction;
+pub mod transaction_manager;
+pub mod wait_for_graph;
@@ -0,0 +1,45 @@
+use crate::file_management::block_id::BlockId;
+use crate::memory_management::buffer::TransactionNumber;
+use crate::transaction_management::lock_table::{LockMode, LockTable, LockTableError};
+use std::collections::HashMap;
+
+/// Locks held by a single transaction, only asking the shared [LockTable] for locks
+/// the transaction does not hold yet
+#[derive(Debug)]
+pub struct ConcurrencyManager {
+    lock_table: LockTable,
+    transaction_number: TransactionNumber,
+    locks: HashMap<BlockId, LockMode>,
+}
+
+impl ConcurrencyManager {
+    pub fn new(lock_table: &LockTable, transaction_number: TransactionNumber) -> Self {
+        Self {
+            lock_table: lock_table.clone(),
+            transaction_number,
+            locks: HashMap::new(),
+        }
+    }
+
+    pub fn slock(&mut self, block: &BlockId) -> Result<(), LockTableError> {
+        if !self.locks.contains_key(block) {
+            self.lock_table.slock(block, self.transaction_number)?;
+            self.locks.insert(block.clone(), LockMode::Shared);
+        }
+        Ok(())
+    }
+
+    pub fn xlock(&mut self, block: &BlockId) -> Result<(), LockTableError> {
+        if self.locks.get(block) != Some(&LockMode::Exclusive) {
+            self.lock_table.xlock(block, self.transaction_number)?;
+            self.locks.insert(block.clone(), LockMode::Exclusive);
+        }
+        Ok(())
+    }
+
+    pub fn release(&mut self) {
+        for (block, _) in self.locks.drain() {
+            self.lock_table.unlock(&block, self.transaction_number);
+        }
+    }
+}
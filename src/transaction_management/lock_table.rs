@@ -0,0 +1,280 @@
+use crate::file_management::block_id::BlockId;
+use crate::memory_management::buffer::TransactionNumber;
+use crate::transaction_management::lock_table::LockTableError::{DeadLockTimeout, DeadLockVictim};
+use crate::transaction_management::wait_for_graph::{WaitForGraph, WaitKind};
+use log::{debug, warn};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd)]
+pub enum LockMode {
+    Shared,
+    Exclusive,
+}
+
+#[derive(Debug, Default)]
+struct BlockLocks {
+    shared: BTreeSet<TransactionNumber>,
+    exclusive: Option<TransactionNumber>,
+}
+
+impl BlockLocks {
+    fn is_free(&self) -> bool {
+        self.shared.is_empty() && self.exclusive.is_none()
+    }
+
+    fn conflicting_holders(
+        &self,
+        transaction_number: TransactionNumber,
+        mode: LockMode,
+    ) -> BTreeSet<TransactionNumber> {
+        let mut holders: BTreeSet<TransactionNumber> = self.exclusive.into_iter().collect();
+        if mode == LockMode::Exclusive {
+            holders.extend(self.shared.iter().copied());
+        }
+        holders.remove(&transaction_number);
+        holders
+    }
+}
+
+/// Shared and exclusive locks on blocks, held by transactions until they release them all at once
+/// on commit or rollback (strict two-phase locking). Deadlocks are detected via the WaitForGraph,
+/// waiting for a lock longer than deadlock_waiting_duration is treated as a deadlock, too.
+#[derive(Debug, Clone)]
+pub struct LockTable {
+    locks: Arc<Mutex<HashMap<BlockId, BlockLocks>>>,
+    lock_released: Arc<Condvar>,
+    wait_for_graph: WaitForGraph,
+    deadlock_waiting_duration: Duration,
+}
+
+pub struct LockTableBuilder {
+    deadlock_waiting_duration: Duration,
+}
+
+impl Default for LockTableBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LockTableBuilder {
+    const DEFAULT_DEADLOCK_WAITING_DURATION: Duration = Duration::from_secs(10);
+    const UNITTEST_DEADLOCK_WAITING_DURATION: Duration = Duration::from_millis(200);
+
+    pub fn new() -> Self {
+        Self {
+            deadlock_waiting_duration: Self::DEFAULT_DEADLOCK_WAITING_DURATION,
+        }
+    }
+
+    pub fn unittest() -> Self {
+        Self {
+            deadlock_waiting_duration: Self::UNITTEST_DEADLOCK_WAITING_DURATION,
+        }
+    }
+
+    pub fn deadlock_waiting_duration(mut self, duration: Duration) -> Self {
+        self.deadlock_waiting_duration = duration;
+        self
+    }
+
+    pub fn build(self, wait_for_graph: &WaitForGraph) -> LockTable {
+        LockTable::new(self.deadlock_waiting_duration, wait_for_graph)
+    }
+}
+
+impl LockTable {
+    pub fn new(deadlock_waiting_duration: Duration, wait_for_graph: &WaitForGraph) -> Self {
+        Self {
+            locks: Arc::new(Mutex::new(HashMap::new())),
+            lock_released: Arc::new(Condvar::new()),
+            wait_for_graph: wait_for_graph.clone(),
+            deadlock_waiting_duration,
+        }
+    }
+
+    pub fn slock(
+        &self,
+        block: &BlockId,
+        transaction_number: TransactionNumber,
+    ) -> Result<(), LockTableError> {
+        self.lock(block, transaction_number, LockMode::Shared)
+    }
+
+    /// Exclusive lock, an already held shared lock of the same transaction is upgraded
+    pub fn xlock(
+        &self,
+        block: &BlockId,
+        transaction_number: TransactionNumber,
+    ) -> Result<(), LockTableError> {
+        self.lock(block, transaction_number, LockMode::Exclusive)
+    }
+
+    fn lock(
+        &self,
+        block: &BlockId,
+        transaction_number: TransactionNumber,
+        mode: LockMode,
+    ) -> Result<(), LockTableError> {
+        let start_time = Instant::now();
+        let mut locks = self.locks.lock().unwrap();
+        loop {
+            let block_locks = locks.entry(block.clone()).or_default();
+            let conflicting_holders = block_locks.conflicting_holders(transaction_number, mode);
+            if conflicting_holders.is_empty() {
+                self.wait_for_graph.stop_waiting(transaction_number);
+                match mode {
+                    LockMode::Shared => {
+                        if block_locks.exclusive != Some(transaction_number) {
+                            block_locks.shared.insert(transaction_number);
+                        }
+                    }
+                    LockMode::Exclusive => {
+                        block_locks.shared.remove(&transaction_number);
+                        block_locks.exclusive = Some(transaction_number);
+                    }
+                }
+                debug!(
+                    "LockTable: {:?} lock on {:?} for transaction {}",
+                    mode, block, transaction_number
+                );
+                return Ok(());
+            }
+            self.wait_for_graph
+                .wait_for(
+                    transaction_number,
+                    WaitKind::AllOf,
+                    conflicting_holders,
+                    &self.lock_released,
+                )
+                .map_err(|victim| DeadLockVictim(victim.0))?;
+            locks = self
+                .wait(locks, start_time, block, transaction_number, mode)
+                .inspect_err(|_| self.wait_for_graph.stop_waiting(transaction_number))?;
+        }
+    }
+
+    fn wait<'a>(
+        &self,
+        locks: MutexGuard<'a, HashMap<BlockId, BlockLocks>>,
+        start_time: Instant,
+        block: &BlockId,
+        transaction_number: TransactionNumber,
+        mode: LockMode,
+    ) -> Result<MutexGuard<'a, HashMap<BlockId, BlockLocks>>, LockTableError> {
+        let waited = start_time.elapsed();
+        if waited >= self.deadlock_waiting_duration {
+            warn!(
+                "LockTable: Deadlock Timeout trying to get {:?} lock on {:?} for transaction {}",
+                mode, block, transaction_number
+            );
+            return Err(DeadLockTimeout);
+        }
+        debug!(
+            "LockTable: transaction {} waits for {:?} lock on {:?}",
+            transaction_number, mode, block
+        );
+        Ok(self
+            .lock_released
+            .wait_timeout(locks, self.deadlock_waiting_duration - waited)
+            .map_err(|_| DeadLockTimeout)?
+            .0)
+    }
+
+    pub fn unlock(&self, block: &BlockId, transaction_number: TransactionNumber) {
+        let mut locks = self.locks.lock().unwrap();
+        if let Some(block_locks) = locks.get_mut(block) {
+            block_locks.shared.remove(&transaction_number);
+            if block_locks.exclusive == Some(transaction_number) {
+                block_locks.exclusive = None;
+            }
+            if block_locks.is_free() {
+                locks.remove(block);
+            }
+        }
+        self.lock_released.notify_all();
+    }
+
+    pub fn lock_mode(
+        &self,
+        block: &BlockId,
+        transaction_number: TransactionNumber,
+    ) -> Option<LockMode> {
+        let locks = self.locks.lock().unwrap();
+        let block_locks = locks.get(block)?;
+        if block_locks.exclusive == Some(transaction_number) {
+            Some(LockMode::Exclusive)
+        } else if block_locks.shared.contains(&transaction_number) {
+            Some(LockMode::Shared)
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum LockTableError {
+    DeadLockTimeout,
+    DeadLockVictim(TransactionNumber),
+}
+
+impl Display for LockTableError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DeadLockTimeout => write!(f, "LockTable: DeadlockTimeout"),
+            DeadLockVictim(transaction_number) => write!(
+                f,
+                "LockTable: transaction {} chosen as deadlock victim",
+                transaction_number
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::file_management::block_id::{BlockId, DbFilename};
+    use crate::memory_management::buffer::TransactionNumber;
+    use crate::transaction_management::lock_table::LockTableError::DeadLockTimeout;
+    use crate::transaction_management::lock_table::{LockMode, LockTableBuilder};
+    use crate::transaction_management::wait_for_graph::WaitForGraph;
+    use std::thread;
+    use std::time::Duration;
+
+    #[test]
+    fn test_lock_table_shared_and_exclusive() {
+        let lock_table = LockTableBuilder::unittest().build(&WaitForGraph::new());
+        let block = BlockId::new(DbFilename::from("lock_table.tbl"), 0);
+        let tx1 = TransactionNumber::from(1);
+        let tx2 = TransactionNumber::from(2);
+
+        lock_table.slock(&block, tx1).unwrap();
+        lock_table.slock(&block, tx2).unwrap();
+        assert!(matches!(
+            lock_table.xlock(&block, tx1),
+            Err(DeadLockTimeout)
+        ));
+
+        lock_table.unlock(&block, tx2);
+        lock_table.xlock(&block, tx1).unwrap();
+        assert_eq!(lock_table.lock_mode(&block, tx1), Some(LockMode::Exclusive));
+        lock_table.slock(&block, tx1).unwrap();
+        assert_eq!(lock_table.lock_mode(&block, tx1), Some(LockMode::Exclusive));
+        assert!(matches!(
+            lock_table.slock(&block, tx2),
+            Err(DeadLockTimeout)
+        ));
+        assert_eq!(lock_table.lock_mode(&block, tx2), None);
+
+        let lt = lock_table.clone();
+        let b = block.clone();
+        let waiting = thread::spawn(move || lt.slock(&b, tx2));
+        thread::sleep(Duration::from_millis(50));
+        lock_table.unlock(&block, tx1);
+        waiting.join().unwrap().unwrap();
+        assert_eq!(lock_table.lock_mode(&block, tx2), Some(LockMode::Shared));
+    }
+}
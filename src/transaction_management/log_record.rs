@@ -0,0 +1,356 @@
+use crate::datatypes::fixed_length_counts::TinyCount;
+use crate::datatypes::varbinary::Varbinary;
+use crate::datatypes::varchar::Varchar;
+use crate::datatypes::varcount::Varcount;
+use crate::datatypes::HfdbSerializableDatatype;
+use crate::file_management::block_id::{BlockId, DbFilename};
+use crate::file_management::file_manager::IoError;
+use crate::memory_management::buffer::TransactionNumber;
+use crate::memory_management::log_manager::{LogManager, LogManagerIter, LogPosition};
+
+const START: u8 = 1;
+const COMMIT: u8 = 2;
+const ROLLBACK: u8 = 3;
+const SET_BYTES: u8 = 4;
+const CHECKPOINT: u8 = 5;
+
+/// Typed records written through [LogManager::append], serialized with a leading record type
+/// byte followed by the fields as [HfdbSerializableDatatype]s.
+///
+/// A checkpoint is written when all modified buffers are flushed: changes before it never have
+/// to be redone, only the changes of the listed (then active) transactions might need an undo.
+/// It also remembers the highest transaction number handed out so far, so that numbers are not
+/// reused after a restart even when recovery stops reading at the checkpoint.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum LogRecord {
+    Start {
+        transaction_number: TransactionNumber,
+    },
+    Commit {
+        transaction_number: TransactionNumber,
+    },
+    Rollback {
+        transaction_number: TransactionNumber,
+    },
+    SetBytes {
+        transaction_number: TransactionNumber,
+        block: BlockId,
+        offset: usize,
+        old_value: Vec<u8>,
+        new_value: Vec<u8>,
+    },
+    Checkpoint {
+        active_transactions: Vec<TransactionNumber>,
+        max_transaction_number: Option<TransactionNumber>,
+    },
+}
+
+impl LogRecord {
+    pub fn transaction_number(&self) -> Option<TransactionNumber> {
+        match self {
+            LogRecord::Start { transaction_number }
+            | LogRecord::Commit { transaction_number }
+            | LogRecord::Rollback { transaction_number }
+            | LogRecord::SetBytes {
+                transaction_number, ..
+            } => Some(*transaction_number),
+            LogRecord::Checkpoint { .. } => None,
+        }
+    }
+
+    pub fn append_to(&self, log_manager: &LogManager) -> Result<LogPosition, IoError> {
+        log_manager.append(self.to_bytes().as_slice())
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![0u8; self.serialized_length()];
+        self.serialize(bytes.as_mut_slice());
+        bytes
+    }
+
+    fn record_type(&self) -> u8 {
+        match self {
+            LogRecord::Start { .. } => START,
+            LogRecord::Commit { .. } => COMMIT,
+            LogRecord::Rollback { .. } => ROLLBACK,
+            LogRecord::SetBytes { .. } => SET_BYTES,
+            LogRecord::Checkpoint { .. } => CHECKPOINT,
+        }
+    }
+
+    fn transaction_number_field(transaction_number: Option<TransactionNumber>) -> Varcount {
+        Varcount::from(transaction_number.map(u64::from).unwrap_or(0))
+    }
+
+    fn checkpoint_fields(
+        active_transactions: &[TransactionNumber],
+        max_transaction_number: Option<TransactionNumber>,
+    ) -> (Varcount, Vec<Varcount>) {
+        (
+            Self::transaction_number_field(max_transaction_number),
+            active_transactions
+                .iter()
+                .map(|transaction_number| Self::transaction_number_field(Some(*transaction_number)))
+                .collect(),
+        )
+    }
+
+    fn set_bytes_fields(
+        block: &BlockId,
+        offset: usize,
+        old_value: &[u8],
+        new_value: &[u8],
+    ) -> (Varchar, Varcount, Varcount, Varbinary, Varbinary) {
+        (
+            Varchar::from(block.filename().as_str()),
+            Varcount::from(block.block_number()),
+            Varcount::from(offset),
+            Varbinary::from(old_value),
+            Varbinary::from(new_value),
+        )
+    }
+}
+
+impl HfdbSerializableDatatype for LogRecord {
+    fn serialized_length(&self) -> usize {
+        let header_length =
+            1 + Self::transaction_number_field(self.transaction_number()).serialized_length();
+        match self {
+            LogRecord::SetBytes {
+                block,
+                offset,
+                old_value,
+                new_value,
+                ..
+            } => {
+                let (filename, block_number, offset, old_value, new_value) =
+                    Self::set_bytes_fields(block, *offset, old_value, new_value);
+                header_length
+                    + filename.serialized_length()
+                    + block_number.serialized_length()
+                    + offset.serialized_length()
+                    + old_value.serialized_length()
+                    + new_value.serialized_length()
+            }
+            LogRecord::Checkpoint {
+                active_transactions,
+                max_transaction_number,
+            } => {
+                let (max_transaction_number, active_transactions) =
+                    Self::checkpoint_fields(active_transactions, *max_transaction_number);
+                header_length
+                    + max_transaction_number.serialized_length()
+                    + Varcount::from(active_transactions.len()).serialized_length()
+                    + active_transactions
+                        .iter()
+                        .map(|transaction_number| transaction_number.serialized_length())
+                        .sum::<usize>()
+            }
+            _ => header_length,
+        }
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) {
+        TinyCount::from(self.record_type()).serialize(buffer);
+        let transaction_number = Self::transaction_number_field(self.transaction_number());
+        transaction_number.serialize(&mut buffer[1..]);
+        let mut pos = 1 + transaction_number.serialized_length();
+        match self {
+            LogRecord::SetBytes {
+                block,
+                offset,
+                old_value,
+                new_value,
+                ..
+            } => {
+                let (filename, block_number, offset, old_value, new_value) =
+                    Self::set_bytes_fields(block, *offset, old_value, new_value);
+                filename.serialize(&mut buffer[pos..]);
+                pos += filename.serialized_length();
+                block_number.serialize(&mut buffer[pos..]);
+                pos += block_number.serialized_length();
+                offset.serialize(&mut buffer[pos..]);
+                pos += offset.serialized_length();
+                old_value.serialize(&mut buffer[pos..]);
+                pos += old_value.serialized_length();
+                new_value.serialize(&mut buffer[pos..]);
+            }
+            LogRecord::Checkpoint {
+                active_transactions,
+                max_transaction_number,
+            } => {
+                let (max_transaction_number, active_transactions) =
+                    Self::checkpoint_fields(active_transactions, *max_transaction_number);
+                max_transaction_number.serialize(&mut buffer[pos..]);
+                pos += max_transaction_number.serialized_length();
+                let count = Varcount::from(active_transactions.len());
+                count.serialize(&mut buffer[pos..]);
+                pos += count.serialized_length();
+                for transaction_number in active_transactions {
+                    transaction_number.serialize(&mut buffer[pos..]);
+                    pos += transaction_number.serialized_length();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn deserialize(buffer: &[u8]) -> Self {
+        let record_type = u8::from(&TinyCount::deserialize(buffer));
+        let mut offset = 1;
+        let transaction_number_field = Varcount::deserialize(&buffer[offset..]);
+        offset += transaction_number_field.serialized_length();
+        let transaction_number = || TransactionNumber::from(u64::from(&transaction_number_field));
+        match record_type {
+            START => LogRecord::Start {
+                transaction_number: transaction_number(),
+            },
+            COMMIT => LogRecord::Commit {
+                transaction_number: transaction_number(),
+            },
+            ROLLBACK => LogRecord::Rollback {
+                transaction_number: transaction_number(),
+            },
+            SET_BYTES => {
+                let filename = Varchar::deserialize(&buffer[offset..]);
+                offset += filename.serialized_length();
+                let block_number = Varcount::deserialize(&buffer[offset..]);
+                offset += block_number.serialized_length();
+                let block_offset = Varcount::deserialize(&buffer[offset..]);
+                offset += block_offset.serialized_length();
+                let old_value = Varbinary::deserialize(&buffer[offset..]);
+                offset += old_value.serialized_length();
+                let new_value = Varbinary::deserialize(&buffer[offset..]);
+                LogRecord::SetBytes {
+                    transaction_number: transaction_number(),
+                    block: BlockId::new(
+                        DbFilename::from(String::from(&filename)),
+                        usize::from(&block_number),
+                    ),
+                    offset: usize::from(&block_offset),
+                    old_value: Vec::from(&old_value),
+                    new_value: Vec::from(&new_value),
+                }
+            }
+            CHECKPOINT => {
+                let max_transaction_number = Varcount::deserialize(&buffer[offset..]);
+                offset += max_transaction_number.serialized_length();
+                let count = Varcount::deserialize(&buffer[offset..]);
+                offset += count.serialized_length();
+                let mut active_transactions = Vec::with_capacity(usize::from(&count));
+                for _ in 0..usize::from(&count) {
+                    let transaction_number = Varcount::deserialize(&buffer[offset..]);
+                    offset += transaction_number.serialized_length();
+                    active_transactions
+                        .push(TransactionNumber::from(u64::from(&transaction_number)));
+                }
+                LogRecord::Checkpoint {
+                    active_transactions,
+                    max_transaction_number: match u64::from(&max_transaction_number) {
+                        0 => None,
+                        nr => Some(TransactionNumber::from(nr)),
+                    },
+                }
+            }
+            unknown => panic!("LogRecord: unknown log record type {unknown}"),
+        }
+    }
+}
+
+/// Typed view on [LogManagerIter], returning the records from the latest to the oldest one
+pub struct LogRecordIter {
+    log_manager_iter: LogManagerIter,
+}
+
+impl LogRecordIter {
+    pub fn new(log_manager: &LogManager) -> Result<Self, IoError> {
+        Ok(Self {
+            log_manager_iter: log_manager.iter()?,
+        })
+    }
+}
+
+impl Iterator for LogRecordIter {
+    type Item = Result<LogRecord, IoError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.log_manager_iter
+            .next()
+            .map(|bytes| bytes.map(|bytes| LogRecord::deserialize(bytes.as_slice())))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datatypes::HfdbSerializableDatatype;
+    use crate::file_management::block_id::{BlockId, DbFilename};
+    use crate::file_management::file_manager::FileManagerBuilder;
+    use crate::memory_management::buffer::TransactionNumber;
+    use crate::memory_management::log_manager::LogManager;
+    use crate::transaction_management::log_record::{LogRecord, LogRecordIter};
+    use std::num::NonZeroUsize;
+
+    #[test]
+    fn test_log_record_serialize_deserialize() {
+        let transaction_number = TransactionNumber::from(4711);
+        let records = vec![
+            LogRecord::Start { transaction_number },
+            LogRecord::Commit { transaction_number },
+            LogRecord::Rollback { transaction_number },
+            LogRecord::SetBytes {
+                transaction_number,
+                block: BlockId::new(DbFilename::from("some_table.tbl"), 1234),
+                offset: 80,
+                old_value: vec![1, 2, 3, 4],
+                new_value: vec![5, 6, 7, 8],
+            },
+            LogRecord::Checkpoint {
+                active_transactions: vec![],
+                max_transaction_number: None,
+            },
+            LogRecord::Checkpoint {
+                active_transactions: vec![TransactionNumber::from(1), transaction_number],
+                max_transaction_number: Some(transaction_number),
+            },
+        ];
+        for record in records {
+            let bytes = record.to_bytes();
+            assert_eq!(bytes.len(), record.serialized_length());
+            assert_eq!(LogRecord::deserialize(bytes.as_slice()), record);
+        }
+    }
+
+    #[test]
+    fn test_log_record_iter() {
+        let file_manager = FileManagerBuilder::unittest("log_record_iter")
+            .block_size(NonZeroUsize::new(100).unwrap())
+            .build()
+            .unwrap();
+        let log_manager =
+            LogManager::new(&file_manager, &DbFilename::from("log_record_iter.log")).unwrap();
+        let records = (1..=20u64)
+            .map(|nr| LogRecord::SetBytes {
+                transaction_number: TransactionNumber::from(nr),
+                block: BlockId::new(DbFilename::from("log_record_iter.tbl"), nr as usize),
+                offset: nr as usize,
+                old_value: vec![0; nr as usize],
+                new_value: vec![nr as u8; nr as usize],
+            })
+            .collect::<Vec<_>>();
+        let mut latest = None;
+        for record in records.iter() {
+            latest = Some(record.append_to(&log_manager).unwrap().latest);
+        }
+        log_manager.flush(latest.unwrap()).unwrap();
+
+        let records_read = LogRecordIter::new(&log_manager)
+            .unwrap()
+            .map(|record| record.unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            records_read,
+            records.into_iter().rev().collect::<Vec<_>>(),
+            "Log records are iterated from latest to oldest"
+        );
+    }
+}
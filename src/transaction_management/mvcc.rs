@@ -0,0 +1,183 @@
+use crate::datatypes::fixed_length_counts::BigCount;
+use crate::datatypes::HfdbSerializableDatatype;
+use crate::memory_management::buffer::TransactionNumber;
+use std::collections::BTreeSet;
+
+/// What a transaction sees: its own changes and those of transactions committed before it
+/// started. Transactions are numbered in order of their start, rolled back transactions have
+/// their changes undone before they finish, so every older transaction that was not active at
+/// the start anymore is a committed one.
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Snapshot {
+    transaction_number: TransactionNumber,
+    active_transactions: BTreeSet<TransactionNumber>,
+}
+
+impl Snapshot {
+    pub fn new(
+        transaction_number: TransactionNumber,
+        active_transactions: BTreeSet<TransactionNumber>,
+    ) -> Self {
+        Self {
+            transaction_number,
+            active_transactions,
+        }
+    }
+
+    pub fn transaction_number(&self) -> TransactionNumber {
+        self.transaction_number
+    }
+
+    /// Oldest transaction whose changes might be invisible for this snapshot
+    pub fn oldest_active(&self) -> TransactionNumber {
+        self.active_transactions
+            .first()
+            .copied()
+            .unwrap_or(self.transaction_number)
+            .min(self.transaction_number)
+    }
+
+    pub fn sees(&self, transaction_number: TransactionNumber) -> bool {
+        transaction_number == self.transaction_number
+            || (transaction_number < self.transaction_number
+                && !self.active_transactions.contains(&transaction_number))
+    }
+
+    /// Only visible versions not deleted by any transaction yet can be deleted: a version deleted
+    /// by a transaction committed (or still active) after the snapshot was taken is a write
+    /// conflict (first updater wins)
+    pub fn can_delete(&self, version: &RecordVersion) -> bool {
+        version.deleted_by.is_none() && self.is_visible(version)
+    }
+
+    pub fn is_visible(&self, version: &RecordVersion) -> bool {
+        self.sees(version.created_by)
+            && !version
+                .deleted_by
+                .is_some_and(|deleted_by| self.sees(deleted_by))
+    }
+}
+
+/// Header of every version of a record. Updating a record means deleting the old version and
+/// creating a new one, so readers with an older snapshot still find the old version.
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct RecordVersion {
+    pub created_by: TransactionNumber,
+    pub deleted_by: Option<TransactionNumber>,
+}
+
+impl RecordVersion {
+    pub const SERIALIZED_LENGTH: usize = 16;
+
+    pub fn new(created_by: TransactionNumber) -> Self {
+        Self {
+            created_by,
+            deleted_by: None,
+        }
+    }
+
+    /// Deleted by a transaction committed before all active snapshots were taken, so no
+    /// snapshot can see this version anymore
+    pub fn is_garbage(&self, horizon: TransactionNumber) -> bool {
+        self.deleted_by
+            .is_some_and(|deleted_by| deleted_by < horizon)
+    }
+}
+
+impl HfdbSerializableDatatype for RecordVersion {
+    fn serialized_length(&self) -> usize {
+        Self::SERIALIZED_LENGTH
+    }
+
+    fn serialize(&self, buffer: &mut [u8]) {
+        BigCount::from(u64::from(self.created_by)).serialize(&mut buffer[..8]);
+        BigCount::from(self.deleted_by.map(u64::from).unwrap_or(0)).serialize(&mut buffer[8..16]);
+    }
+
+    fn deserialize(buffer: &[u8]) -> Self {
+        let created_by = u64::from(&BigCount::deserialize(&buffer[..8]));
+        let deleted_by = u64::from(&BigCount::deserialize(&buffer[8..16]));
+        Self {
+            created_by: TransactionNumber::from(created_by),
+            deleted_by: match deleted_by {
+                0 => None,
+                nr => Some(TransactionNumber::from(nr)),
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datatypes::HfdbSerializableDatatype;
+    use crate::memory_management::buffer::TransactionNumber;
+    use crate::transaction_management::mvcc::{RecordVersion, Snapshot};
+    use std::collections::BTreeSet;
+
+    #[test]
+    fn test_mvcc_visibility() {
+        let tx = |nr: u64| TransactionNumber::from(nr);
+        let snapshot = Snapshot::new(tx(5), BTreeSet::from([tx(2), tx(4)]));
+        assert_eq!(snapshot.oldest_active(), tx(2));
+
+        assert!(snapshot.sees(tx(1)));
+        assert!(!snapshot.sees(tx(2)));
+        assert!(snapshot.sees(tx(3)));
+        assert!(snapshot.sees(tx(5)));
+        assert!(!snapshot.sees(tx(6)));
+
+        let visible = [
+            (RecordVersion::new(tx(1)), true),
+            (RecordVersion::new(tx(2)), false),
+            (RecordVersion::new(tx(5)), true),
+            (RecordVersion::new(tx(6)), false),
+            (
+                RecordVersion {
+                    created_by: tx(1),
+                    deleted_by: Some(tx(3)),
+                },
+                false,
+            ),
+            (
+                RecordVersion {
+                    created_by: tx(1),
+                    deleted_by: Some(tx(4)),
+                },
+                true,
+            ),
+            (
+                RecordVersion {
+                    created_by: tx(5),
+                    deleted_by: Some(tx(5)),
+                },
+                false,
+            ),
+        ];
+        for (version, expected) in visible {
+            assert_eq!(snapshot.is_visible(&version), expected, "{:?}", version);
+        }
+    }
+
+    #[test]
+    fn test_serialize_deserialize_record_version() {
+        for version in [
+            RecordVersion::new(TransactionNumber::from(1)),
+            RecordVersion {
+                created_by: TransactionNumber::from(42),
+                deleted_by: Some(TransactionNumber::from(u64::MAX >> 1)),
+            },
+        ] {
+            let mut buffer = vec![0u8; version.serialized_length()];
+            version.serialize(&mut buffer);
+            assert_eq!(RecordVersion::deserialize(&buffer), version);
+        }
+        assert!(
+            !RecordVersion::new(TransactionNumber::from(1)).is_garbage(TransactionNumber::from(5))
+        );
+        assert!(RecordVersion {
+            created_by: TransactionNumber::from(1),
+            deleted_by: Some(TransactionNumber::from(4)),
+        }
+        .is_garbage(TransactionNumber::from(5)));
+    }
+}
@@ -0,0 +1,639 @@
+use crate::file_management::block_id::BlockId;
+use crate::file_management::file_manager::{FileManager, IoError};
+use crate::memory_management::buffer::TransactionNumber;
+use crate::memory_management::buffer_manager::{BufferManager, BufferManagerError};
+use crate::memory_management::log_manager::LogManager;
+use crate::metadata_management::catalog::CatalogError;
+use crate::transaction_management::log_record::{LogRecord, LogRecordIter};
+use log::{debug, info};
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone)]
+pub struct RecoveryManager {
+    file_manager: FileManager,
+    log_manager: LogManager,
+    buffer_manager: BufferManager,
+}
+
+#[derive(Debug, Default, Clone, Eq, PartialEq)]
+pub struct RecoveryStatistics {
+    pub log_records: usize,
+    pub redone_transactions: BTreeSet<TransactionNumber>,
+    pub undone_transactions: BTreeSet<TransactionNumber>,
+    pub max_transaction_number: Option<TransactionNumber>,
+}
+
+impl RecoveryManager {
+    pub fn new(
+        file_manager: &FileManager,
+        log_manager: &LogManager,
+        buffer_manager: &BufferManager,
+    ) -> Self {
+        Self {
+            file_manager: file_manager.clone(),
+            log_manager: log_manager.clone(),
+            buffer_manager: buffer_manager.clone(),
+        }
+    }
+
+    /// Undo-redo recovery: the log is walked backwards once, changes of transactions without
+    /// commit or rollback record are undone on the way, changes of committed transactions are
+    /// collected and redone in log order afterwards. The walk stops at the latest checkpoint,
+    /// unless transactions active at checkpoint time are still unfinished, then it continues
+    /// until their start records to undo their older changes.
+    /// Finally, undone transactions get a rollback record and a new checkpoint is written,
+    /// so the next start does not have to look at the same records again.
+    pub fn recover(&self) -> Result<RecoveryStatistics, RecoveryError> {
+        let mut statistics = RecoveryStatistics::default();
+        let mut committed: HashSet<TransactionNumber> = HashSet::new();
+        let mut rolled_back: HashSet<TransactionNumber> = HashSet::new();
+        let mut redo_records: Vec<LogRecord> = Vec::new();
+        let mut checkpoint_reached = false;
+        let mut unfinished_at_checkpoint: HashSet<TransactionNumber> = HashSet::new();
+
+        for record in LogRecordIter::new(&self.log_manager).map_err(RecoveryError::StdIoError)? {
+            let record = record.map_err(RecoveryError::StdIoError)?;
+            debug!("Recovery: read log record {:?}", record);
+            statistics.log_records += 1;
+            statistics.max_transaction_number = statistics
+                .max_transaction_number
+                .max(record.transaction_number());
+            match record {
+                LogRecord::Checkpoint {
+                    active_transactions,
+                    max_transaction_number,
+                } => {
+                    statistics.max_transaction_number = statistics
+                        .max_transaction_number
+                        .max(max_transaction_number);
+                    if !checkpoint_reached {
+                        checkpoint_reached = true;
+                        unfinished_at_checkpoint = active_transactions
+                            .into_iter()
+                            .filter(|transaction_number| {
+                                !committed.contains(transaction_number)
+                                    && !rolled_back.contains(transaction_number)
+                            })
+                            .collect();
+                    }
+                }
+                LogRecord::Start { transaction_number } => {
+                    unfinished_at_checkpoint.remove(&transaction_number);
+                }
+                LogRecord::Commit { transaction_number } => {
+                    committed.insert(transaction_number);
+                }
+                LogRecord::Rollback { transaction_number } => {
+                    rolled_back.insert(transaction_number);
+                }
+                LogRecord::SetBytes {
+                    transaction_number, ..
+                } if committed.contains(&transaction_number) => {
+                    if !checkpoint_reached {
+                        statistics.redone_transactions.insert(transaction_number);
+                        redo_records.push(record);
+                    }
+                }
+                LogRecord::SetBytes {
+                    transaction_number,
+                    ref block,
+                    offset,
+                    ref old_value,
+                    ..
+                } => {
+                    if !rolled_back.contains(&transaction_number) {
+                        statistics.undone_transactions.insert(transaction_number);
+                        self.undo_bytes(transaction_number, block, offset, old_value)?;
+                    }
+                }
+            }
+            if checkpoint_reached && unfinished_at_checkpoint.is_empty() {
+                break;
+            }
+        }
+
+        for record in redo_records.iter().rev() {
+            if let LogRecord::SetBytes {
+                transaction_number,
+                block,
+                offset,
+                new_value,
+                ..
+            } = record
+            {
+                self.redo_bytes(*transaction_number, block, *offset, new_value)?;
+            }
+        }
+
+        for transaction_number in statistics
+            .redone_transactions
+            .iter()
+            .chain(statistics.undone_transactions.iter())
+        {
+            self.buffer_manager
+                .flush_all(*transaction_number)
+                .map_err(RecoveryError::StdIoError)?;
+        }
+        for transaction_number in statistics.undone_transactions.iter() {
+            LogRecord::Rollback {
+                transaction_number: *transaction_number,
+            }
+            .append_to(&self.log_manager)
+            .map_err(RecoveryError::StdIoError)?;
+        }
+        // The checkpoint ends the next recovery, so the pages written before must be durable
+        self.file_manager
+            .sync_all()
+            .map_err(RecoveryError::StdIoError)?;
+        let checkpoint = LogRecord::Checkpoint {
+            active_transactions: vec![],
+            max_transaction_number: statistics.max_transaction_number,
+        };
+        let latest = checkpoint
+            .append_to(&self.log_manager)
+            .map_err(RecoveryError::StdIoError)?
+            .latest;
+        self.log_manager
+            .flush(latest)
+            .map_err(RecoveryError::StdIoError)?;
+
+        info!(
+            "Recovery finished: log_records={} redone={:?} undone={:?}",
+            statistics.log_records, statistics.redone_transactions, statistics.undone_transactions
+        );
+        Ok(statistics)
+    }
+
+    /// Writes the old value back, unless the block never reached the data file: an appended
+    /// block lost in the crash held nothing of the transaction either
+    fn undo_bytes(
+        &self,
+        transaction_number: TransactionNumber,
+        block: &BlockId,
+        offset: usize,
+        value: &[u8],
+    ) -> Result<(), RecoveryError> {
+        match self.write_bytes(transaction_number, block, offset, value) {
+            Err(e) if e.is_missing_block() => {
+                debug!("Recovery: skip undo in missing block {:?}", block);
+                Ok(())
+            }
+            result => result,
+        }
+    }
+
+    /// Writes the new value, first extending the data file by empty blocks up to the block if
+    /// the crash lost the appends, a truncated last block is overwritten by an empty one
+    fn redo_bytes(
+        &self,
+        transaction_number: TransactionNumber,
+        block: &BlockId,
+        offset: usize,
+        value: &[u8],
+    ) -> Result<(), RecoveryError> {
+        match self.write_bytes(transaction_number, block, offset, value) {
+            Err(e) if e.is_missing_block() => {
+                debug!(
+                    "Recovery: extend file for redo in missing block {:?}",
+                    block
+                );
+                while self
+                    .file_manager
+                    .block_length(block.filename())
+                    .map_err(RecoveryError::StdIoError)?
+                    <= block.block_number()
+                {
+                    self.file_manager
+                        .append(block.filename())
+                        .map_err(RecoveryError::StdIoError)?;
+                }
+                self.write_bytes(transaction_number, block, offset, value)
+            }
+            result => result,
+        }
+    }
+
+    fn write_bytes(
+        &self,
+        transaction_number: TransactionNumber,
+        block: &BlockId,
+        offset: usize,
+        value: &[u8],
+    ) -> Result<(), RecoveryError> {
+        let mut buffer = self
+            .buffer_manager
+            .pin(block)
+            .map_err(RecoveryError::BufferManagerError)?;
+        buffer.modify_page(
+            |page| page.set_raw_bytes(offset, value),
+            transaction_number,
+            None,
+        );
+        self.buffer_manager.unpin(&buffer);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+pub enum RecoveryError {
+    StdIoError(IoError),
+    BufferManagerError(BufferManagerError),
+    /// Creating the catalog tables after recovery failed
+    CatalogError(CatalogError),
+}
+
+impl RecoveryError {
+    /// The block to change is not or not completely in its data file
+    fn is_missing_block(&self) -> bool {
+        matches!(
+            self,
+            Self::BufferManagerError(BufferManagerError::StdIoError(
+                IoError::BlockBeyondEndOfFile(_) | IoError::TruncatedBlock { .. }
+            ))
+        )
+    }
+}
+
+impl Display for RecoveryError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StdIoError(e) => write!(f, "RecoveryManager IoError {}", e),
+            Self::BufferManagerError(e) => write!(f, "RecoveryManager {}", e),
+            Self::CatalogError(e) => write!(f, "RecoveryManager bootstrapping catalog {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datatypes::fixed_length_integers::Integer;
+    use crate::db_management_system::hfdb::{HanfriedDb, HanfriedDbBuilder};
+    use crate::file_management::block_id::{BlockId, DbFilename};
+    use crate::file_management::file_manager::PAGE_HEADER_LENGTH;
+    use crate::file_management::page::Page;
+    use crate::memory_management::buffer::TransactionNumber;
+    use crate::transaction_management::log_record::LogRecord;
+    use crate::utils::logging::init_logging;
+    use std::collections::BTreeSet;
+    use std::fs::OpenOptions;
+    use std::io::{Seek, SeekFrom, Write};
+    use std::num::NonZeroUsize;
+
+    fn reopen(sub_directory_name: &str) -> HanfriedDb {
+        HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| {
+                fm.block_size(NonZeroUsize::new(100).unwrap())
+                    .fresh_db_directory(false)
+            })
+            .build()
+            .unwrap()
+    }
+
+    fn read_integer(hfdb: &HanfriedDb, block: &BlockId, offset: usize) -> i32 {
+        let page = Page::new(hfdb.file_manager.block_size);
+        hfdb.file_manager.read(block, &page).unwrap();
+        i32::from(page.get::<Integer>(offset))
+    }
+
+    fn log_and_write(
+        hfdb: &HanfriedDb,
+        transaction_number: TransactionNumber,
+        block: &BlockId,
+        offset: usize,
+        old_value: i32,
+        new_value: i32,
+    ) {
+        let old_page = Page::new(NonZeroUsize::new(4).unwrap());
+        old_page.set(0, &Integer::from(old_value));
+        let new_page = Page::new(NonZeroUsize::new(4).unwrap());
+        new_page.set(0, &Integer::from(new_value));
+        let record = LogRecord::SetBytes {
+            transaction_number,
+            block: block.clone(),
+            offset,
+            old_value: old_page.get_contents(),
+            new_value: new_page.get_contents(),
+        };
+        let lsn = record.append_to(&hfdb.log_manager).unwrap().latest;
+        let mut buffer = hfdb.buffer_manager.pin(block).unwrap();
+        buffer.modify_page(
+            |page| page.set(offset, &Integer::from(new_value)),
+            transaction_number,
+            Some(lsn),
+        );
+        hfdb.buffer_manager.unpin(&buffer);
+    }
+
+    #[test]
+    fn test_recovery_redo_committed_and_undo_uncommitted() {
+        init_logging();
+
+        let sub_directory_name = "recovery_manager_redo_undo";
+        let hfdb = HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .build()
+            .unwrap();
+        let block = hfdb
+            .file_manager
+            .append(&DbFilename::from("recovery.tbl"))
+            .unwrap();
+        let other_block = hfdb.file_manager.append(block.filename()).unwrap();
+        let committed = TransactionNumber::from(1);
+        let uncommitted = TransactionNumber::from(2);
+
+        for transaction_number in [committed, uncommitted] {
+            LogRecord::Start { transaction_number }
+                .append_to(&hfdb.log_manager)
+                .unwrap();
+        }
+        // The uncommitted change reached the data file (e.g. flushed by buffer replacement),
+        // the committed change did not, before the crash
+        log_and_write(&hfdb, uncommitted, &other_block, 20, 0, 4711);
+        hfdb.buffer_manager.flush_all(uncommitted).unwrap();
+        log_and_write(&hfdb, committed, &block, 0, 0, 42);
+        let lsn = LogRecord::Commit {
+            transaction_number: committed,
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap()
+        .latest;
+        hfdb.log_manager.flush(lsn).unwrap();
+
+        assert_eq!(read_integer(&hfdb, &block, 0), 0);
+        assert_eq!(read_integer(&hfdb, &other_block, 20), 4711);
+        drop(hfdb);
+
+        let hfdb = reopen(sub_directory_name);
+        assert_eq!(read_integer(&hfdb, &block, 0), 42);
+        assert_eq!(read_integer(&hfdb, &other_block, 20), 0);
+        let statistics = &hfdb.recovery_statistics;
+        assert_eq!(statistics.redone_transactions, BTreeSet::from([committed]));
+        assert_eq!(
+            statistics.undone_transactions,
+            BTreeSet::from([uncommitted])
+        );
+        assert_eq!(statistics.max_transaction_number, Some(uncommitted));
+        drop(hfdb);
+
+        let hfdb = reopen(sub_directory_name);
+        assert_eq!(read_integer(&hfdb, &block, 0), 42);
+        assert_eq!(read_integer(&hfdb, &other_block, 20), 0);
+        let statistics = &hfdb.recovery_statistics;
+        assert_eq!(
+            statistics.log_records, 1,
+            "Recovery should stop at the checkpoint written by the former recovery"
+        );
+        assert!(statistics.redone_transactions.is_empty());
+        assert!(statistics.undone_transactions.is_empty());
+        assert_eq!(statistics.max_transaction_number, Some(uncommitted));
+    }
+
+    #[test]
+    fn test_recovery_undo_transactions_active_at_checkpoint() {
+        init_logging();
+
+        let sub_directory_name = "recovery_manager_checkpoint";
+        let hfdb = HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .build()
+            .unwrap();
+        let block = hfdb
+            .file_manager
+            .append(&DbFilename::from("checkpoint.tbl"))
+            .unwrap();
+        let other_block = hfdb.file_manager.append(block.filename()).unwrap();
+        let long_running = TransactionNumber::from(1);
+        let committed = TransactionNumber::from(2);
+
+        LogRecord::Start {
+            transaction_number: long_running,
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap();
+        log_and_write(&hfdb, long_running, &block, 0, 0, 13);
+        hfdb.buffer_manager.flush_all(long_running).unwrap();
+        LogRecord::Checkpoint {
+            active_transactions: vec![long_running],
+            max_transaction_number: Some(long_running),
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap();
+        LogRecord::Start {
+            transaction_number: committed,
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap();
+        log_and_write(&hfdb, committed, &other_block, 0, 0, 42);
+        let lsn = LogRecord::Commit {
+            transaction_number: committed,
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap()
+        .latest;
+        hfdb.log_manager.flush(lsn).unwrap();
+        assert_eq!(read_integer(&hfdb, &block, 0), 13);
+        assert_eq!(read_integer(&hfdb, &other_block, 0), 0);
+        drop(hfdb);
+
+        let hfdb = reopen(sub_directory_name);
+        assert_eq!(read_integer(&hfdb, &block, 0), 0);
+        assert_eq!(read_integer(&hfdb, &other_block, 0), 42);
+        let statistics = &hfdb.recovery_statistics;
+        assert_eq!(statistics.redone_transactions, BTreeSet::from([committed]));
+        assert_eq!(
+            statistics.undone_transactions,
+            BTreeSet::from([long_running])
+        );
+        assert_eq!(statistics.max_transaction_number, Some(committed));
+    }
+
+    #[test]
+    fn test_recovery_with_truncated_data_files() {
+        init_logging();
+
+        let sub_directory_name = "recovery_manager_truncated";
+        let hfdb = HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .build()
+            .unwrap();
+        let committed_file = DbFilename::from("committed.tbl");
+        let uncommitted_file = DbFilename::from("uncommitted.tbl");
+        let first_block = hfdb.file_manager.append(&committed_file).unwrap();
+        let appended_block = hfdb.file_manager.append(&committed_file).unwrap();
+        let last_block = hfdb.file_manager.append(&committed_file).unwrap();
+        let uncommitted_block = hfdb.file_manager.append(&uncommitted_file).unwrap();
+        let committed = TransactionNumber::from(1);
+        let uncommitted = TransactionNumber::from(2);
+
+        for transaction_number in [committed, uncommitted] {
+            LogRecord::Start { transaction_number }
+                .append_to(&hfdb.log_manager)
+                .unwrap();
+        }
+        log_and_write(&hfdb, committed, &first_block, 0, 0, 1);
+        log_and_write(&hfdb, committed, &last_block, 8, 0, 42);
+        log_and_write(&hfdb, uncommitted, &uncommitted_block, 0, 0, 4711);
+        let lsn = LogRecord::Commit {
+            transaction_number: committed,
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap()
+        .latest;
+        hfdb.log_manager.flush(lsn).unwrap();
+        drop(hfdb);
+
+        // The crash lost the appends: the committed file ends within its second block,
+        // the uncommitted one is empty
+        let directory = format!("/data/hanfried-db-unittest/{}", sub_directory_name);
+        let disk_block_size = PAGE_HEADER_LENGTH + 100;
+        OpenOptions::new()
+            .write(true)
+            .open(format!("{}/{}", directory, committed_file))
+            .unwrap()
+            .set_len((disk_block_size + 10) as u64)
+            .unwrap();
+        OpenOptions::new()
+            .write(true)
+            .open(format!("{}/{}", directory, uncommitted_file))
+            .unwrap()
+            .set_len(0)
+            .unwrap();
+
+        let hfdb = reopen(sub_directory_name);
+        assert_eq!(read_integer(&hfdb, &first_block, 0), 1);
+        assert_eq!(read_integer(&hfdb, &appended_block, 0), 0);
+        assert_eq!(read_integer(&hfdb, &last_block, 8), 42);
+        assert_eq!(hfdb.file_manager.block_length(&committed_file).unwrap(), 3);
+        assert_eq!(
+            hfdb.file_manager.block_length(&uncommitted_file).unwrap(),
+            0
+        );
+        let statistics = &hfdb.recovery_statistics;
+        assert_eq!(statistics.redone_transactions, BTreeSet::from([committed]));
+        assert_eq!(
+            statistics.undone_transactions,
+            BTreeSet::from([uncommitted])
+        );
+    }
+
+    #[test]
+    fn test_recovery_with_torn_log_tail() {
+        init_logging();
+
+        let sub_directory_name = "recovery_manager_torn_log";
+        let hfdb = HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .build()
+            .unwrap();
+        let block = hfdb
+            .file_manager
+            .append(&DbFilename::from("torn.tbl"))
+            .unwrap();
+        let committed = TransactionNumber::from(1);
+        let unfinished = TransactionNumber::from(2);
+        LogRecord::Start {
+            transaction_number: committed,
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap();
+        log_and_write(&hfdb, committed, &block, 0, 0, 42);
+        LogRecord::Commit {
+            transaction_number: committed,
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap();
+        // Fill more than a log block after the commit, so the last block holds none of its records
+        let mut lsn = None;
+        for _ in 0..20 {
+            let position = LogRecord::Start {
+                transaction_number: unfinished,
+            }
+            .append_to(&hfdb.log_manager)
+            .unwrap();
+            lsn = Some(position.latest);
+        }
+        hfdb.log_manager.flush(lsn.unwrap()).unwrap();
+        drop(hfdb);
+
+        // The crash tore the last log block while it was written and left part of another one
+        let log_path = format!(
+            "/data/hanfried-db-unittest/{}/hfdb_unittest.log",
+            sub_directory_name
+        );
+        let mut log = OpenOptions::new().write(true).open(&log_path).unwrap();
+        let length = log.seek(SeekFrom::End(0)).unwrap();
+        log.seek(SeekFrom::Start(length - 50)).unwrap();
+        log.write_all(&[0xff; 10]).unwrap();
+        log.seek(SeekFrom::End(0)).unwrap();
+        log.write_all(&[0xff; 10]).unwrap();
+        drop(log);
+
+        let hfdb = reopen(sub_directory_name);
+        assert_eq!(read_integer(&hfdb, &block, 0), 42);
+        let statistics = &hfdb.recovery_statistics;
+        assert_eq!(statistics.redone_transactions, BTreeSet::from([committed]));
+        drop(hfdb);
+
+        // The log goes on after the cut off block
+        let hfdb = reopen(sub_directory_name);
+        assert_eq!(read_integer(&hfdb, &block, 0), 42);
+        assert_eq!(hfdb.recovery_statistics.log_records, 1);
+    }
+
+    #[test]
+    fn test_recovery_keeps_synced_commit_before_torn_log_tail() {
+        init_logging();
+
+        let sub_directory_name = "recovery_manager_torn_log_commit";
+        let hfdb = HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .build()
+            .unwrap();
+        let block = hfdb
+            .file_manager
+            .append(&DbFilename::from("torn_commit.tbl"))
+            .unwrap();
+        let committed = TransactionNumber::from(1);
+        let started = TransactionNumber::from(2);
+        LogRecord::Start {
+            transaction_number: committed,
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap();
+        log_and_write(&hfdb, committed, &block, 0, 0, 42);
+        let lsn = LogRecord::Commit {
+            transaction_number: committed,
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap()
+        .latest;
+        hfdb.log_manager.flush(lsn).unwrap();
+        // A small record that would fit into the block holding the synced commit
+        let lsn = LogRecord::Start {
+            transaction_number: started,
+        }
+        .append_to(&hfdb.log_manager)
+        .unwrap()
+        .latest;
+        hfdb.log_manager.flush(lsn).unwrap();
+        drop(hfdb);
+
+        // The crash tore the last log block while the second flush wrote it
+        let log_path = format!(
+            "/data/hanfried-db-unittest/{}/hfdb_unittest.log",
+            sub_directory_name
+        );
+        let mut log = OpenOptions::new().write(true).open(&log_path).unwrap();
+        let length = log.seek(SeekFrom::End(0)).unwrap();
+        log.seek(SeekFrom::Start(length - 50)).unwrap();
+        log.write_all(&[0xff; 10]).unwrap();
+        drop(log);
+
+        let hfdb = reopen(sub_directory_name);
+        assert_eq!(read_integer(&hfdb, &block, 0), 42);
+        let statistics = &hfdb.recovery_statistics;
+        assert_eq!(statistics.redone_transactions, BTreeSet::from([committed]));
+        assert!(statistics.undone_transactions.is_empty());
+    }
+}
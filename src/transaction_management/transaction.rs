@@ -0,0 +1,1035 @@
+use crate::datatypes::HfdbSerializableDatatype;
+use crate::file_management::block_id::{BlockId, DbFilename};
+use crate::file_management::file_manager::{FileManager, IoError};
+use crate::memory_management::buffer::{Buffer, TransactionNumber};
+use crate::memory_management::buffer_manager::BufferManagerError;
+use crate::memory_management::log_manager::LogSequenceNumber;
+use crate::transaction_management::concurrency_manager::ConcurrencyManager;
+use crate::transaction_management::lock_table::LockTableError;
+use crate::transaction_management::log_record::{LogRecord, LogRecordIter};
+use crate::transaction_management::mvcc::{RecordVersion, Snapshot};
+use crate::transaction_management::transaction_manager::TransactionManager;
+use log::{debug, warn};
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Display, Formatter};
+use std::num::NonZeroUsize;
+use std::ops::DerefMut;
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum TransactionStatus {
+    Active,
+    Committed,
+    RolledBack,
+}
+
+#[derive(Debug)]
+struct PinnedBuffer {
+    buffer: Buffer,
+    pins_count: usize,
+}
+
+/// Counters of the buffer and log work done by a transaction. Pinning a block the transaction
+/// already holds or one that is still in the buffer pool counts as a buffer hit, otherwise the
+/// block is read from disk.
+#[derive(Debug, Default, Copy, Clone, Eq, PartialEq)]
+pub struct IoStatistics {
+    pub blocks_pinned: usize,
+    pub buffer_hits: usize,
+    pub disk_reads: usize,
+    pub log_bytes: usize,
+}
+
+impl IoStatistics {
+    /// Work done since the earlier snapshot was taken
+    pub fn since(&self, earlier: &IoStatistics) -> IoStatistics {
+        IoStatistics {
+            blocks_pinned: self.blocks_pinned - earlier.blocks_pinned,
+            buffer_hits: self.buffer_hits - earlier.buffer_hits,
+            disk_reads: self.disk_reads - earlier.disk_reads,
+            log_bytes: self.log_bytes - earlier.log_bytes,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TransactionState {
+    status: TransactionStatus,
+    pinned_buffers: HashMap<BlockId, PinnedBuffer>,
+    concurrency_manager: ConcurrencyManager,
+    latest_log_sequence_number: Option<LogSequenceNumber>,
+    io_statistics: IoStatistics,
+    /// Files extended by the transaction, their new length is synced on commit
+    appended_files: HashSet<DbFilename>,
+}
+
+#[derive(Debug)]
+struct TransactionInner {
+    transaction_number: TransactionNumber,
+    snapshot: Snapshot,
+    transaction_manager: TransactionManager,
+    state: Mutex<TransactionState>,
+}
+
+/// Handle on a transaction, clones share the same transaction.
+/// Reading a block takes a shared lock, writing an exclusive lock on it, both are held until
+/// commit or rollback. All locks are released and all pinned buffers are unpinned on commit or
+/// rollback, a transaction that is neither committed nor rolled back when its last handle is
+/// dropped is rolled back.
+///
+/// Versioned data (prefixed by a RecordVersion header) is read from the snapshot taken at the
+/// start of the transaction without any shared lock, so readers never block writers.
+#[derive(Debug, Clone)]
+pub struct Transaction {
+    inner: Arc<TransactionInner>,
+}
+
+impl Transaction {
+    pub(crate) fn new(transaction_manager: &TransactionManager, snapshot: Snapshot) -> Self {
+        let transaction_number = snapshot.transaction_number();
+        Self {
+            inner: Arc::new(TransactionInner {
+                transaction_number,
+                snapshot,
+                transaction_manager: transaction_manager.clone(),
+                state: Mutex::new(TransactionState {
+                    status: TransactionStatus::Active,
+                    pinned_buffers: HashMap::new(),
+                    concurrency_manager: ConcurrencyManager::new(
+                        transaction_manager.lock_table(),
+                        transaction_number,
+                    ),
+                    latest_log_sequence_number: None,
+                    io_statistics: IoStatistics::default(),
+                    appended_files: HashSet::new(),
+                }),
+            }),
+        }
+    }
+
+    pub fn transaction_number(&self) -> TransactionNumber {
+        self.inner.transaction_number
+    }
+
+    pub fn snapshot(&self) -> &Snapshot {
+        &self.inner.snapshot
+    }
+
+    pub fn status(&self) -> TransactionStatus {
+        self.inner.state.lock().unwrap().status
+    }
+
+    pub fn block_size(&self) -> NonZeroUsize {
+        self.inner.transaction_manager.file_manager().block_size
+    }
+
+    pub fn file_manager(&self) -> &FileManager {
+        self.inner.transaction_manager.file_manager()
+    }
+
+    /// Number of buffers in the buffer pool, the memory available to queries is estimated from it
+    pub fn buffer_pool_size(&self) -> usize {
+        self.inner.transaction_manager.buffer_manager().pool_size()
+    }
+
+    pub fn io_statistics(&self) -> IoStatistics {
+        self.inner.state.lock().unwrap().io_statistics
+    }
+
+    pub fn pin(&self, block: &BlockId) -> Result<(), TransactionError> {
+        let mut state = self.inner.active_state()?;
+        state.io_statistics.blocks_pinned += 1;
+        if let Some(pinned_buffer) = state.pinned_buffers.get_mut(block) {
+            pinned_buffer.pins_count += 1;
+            state.io_statistics.buffer_hits += 1;
+            return Ok(());
+        }
+        let (buffer, read_from_disk) = self
+            .inner
+            .transaction_manager
+            .buffer_manager()
+            .pin_for_transaction(block, self.transaction_number())
+            .map_err(TransactionError::BufferManagerError)?;
+        if read_from_disk {
+            state.io_statistics.disk_reads += 1;
+        } else {
+            state.io_statistics.buffer_hits += 1;
+        }
+        state.pinned_buffers.insert(
+            block.clone(),
+            PinnedBuffer {
+                buffer,
+                pins_count: 1,
+            },
+        );
+        Ok(())
+    }
+
+    pub fn unpin(&self, block: &BlockId) -> Result<(), TransactionError> {
+        let mut state = self.inner.active_state()?;
+        let pinned_buffer = state
+            .pinned_buffers
+            .get_mut(block)
+            .ok_or_else(|| TransactionError::BlockNotPinned(block.clone()))?;
+        pinned_buffer.pins_count -= 1;
+        if pinned_buffer.pins_count == 0 {
+            let pinned_buffer = state.pinned_buffers.remove(block).unwrap();
+            self.inner
+                .transaction_manager
+                .buffer_manager()
+                .unpin_for_transaction(&pinned_buffer.buffer, self.transaction_number());
+        }
+        Ok(())
+    }
+
+    pub fn get<T: HfdbSerializableDatatype>(
+        &self,
+        block: &BlockId,
+        offset: usize,
+    ) -> Result<T, TransactionError> {
+        let buffer = self.inner.pinned_buffer(block)?;
+        self.inner.slock(block)?;
+        Ok(buffer.page().get::<T>(offset))
+    }
+
+    pub fn get_raw_bytes(
+        &self,
+        block: &BlockId,
+        offset: usize,
+        length: usize,
+    ) -> Result<Vec<u8>, TransactionError> {
+        let buffer = self.inner.pinned_buffer(block)?;
+        self.inner.slock(block)?;
+        Ok(buffer.page().get_raw_bytes(offset, length))
+    }
+
+    /// Snapshot read without shared lock, only to be used for versioned data
+    pub fn read<T: HfdbSerializableDatatype>(
+        &self,
+        block: &BlockId,
+        offset: usize,
+    ) -> Result<T, TransactionError> {
+        Ok(self.inner.pinned_buffer(block)?.page().get::<T>(offset))
+    }
+
+    /// Snapshot read without shared lock, only to be used for versioned data
+    pub fn read_raw_bytes(
+        &self,
+        block: &BlockId,
+        offset: usize,
+        length: usize,
+    ) -> Result<Vec<u8>, TransactionError> {
+        Ok(self
+            .inner
+            .pinned_buffer(block)?
+            .page()
+            .get_raw_bytes(offset, length))
+    }
+
+    /// Whether the record version with its header at offset is visible in this snapshot
+    pub fn is_visible(&self, block: &BlockId, offset: usize) -> Result<bool, TransactionError> {
+        let version = self.read::<RecordVersion>(block, offset)?;
+        Ok(self.inner.snapshot.is_visible(&version))
+    }
+
+    /// Writes the header of a new record version created by this transaction
+    pub fn create_version(&self, block: &BlockId, offset: usize) -> Result<(), TransactionError> {
+        self.set(
+            block,
+            offset,
+            &RecordVersion::new(self.transaction_number()),
+        )
+    }
+
+    /// Marks the record version with its header at offset as deleted by this transaction.
+    /// The exclusive lock makes concurrent deleters wait until the first one finishes, if it
+    /// committed the others fail with a WriteConflict.
+    pub fn delete_version(&self, block: &BlockId, offset: usize) -> Result<(), TransactionError> {
+        self.inner.xlock(block)?;
+        let mut version = self.read::<RecordVersion>(block, offset)?;
+        if !self.inner.snapshot.can_delete(&version) {
+            return Err(TransactionError::WriteConflict(block.clone(), offset));
+        }
+        version.deleted_by = Some(self.transaction_number());
+        self.set(block, offset, &version)
+    }
+
+    /// Exclusive lock on the block held until commit or rollback, e.g. to check a record before
+    /// changing it
+    pub fn xlock(&self, block: &BlockId) -> Result<(), TransactionError> {
+        self.inner.xlock(block)
+    }
+
+    /// Number of blocks of the file
+    pub fn block_length(&self, filename: &DbFilename) -> Result<usize, TransactionError> {
+        self.inner
+            .transaction_manager
+            .file_manager()
+            .block_length(filename)
+            .map_err(TransactionError::StdIoError)
+    }
+
+    /// Extends the file by an empty block. Appends are not logged, the file is synced on commit
+    /// instead, so a committed transaction never refers to blocks lost by a crash.
+    pub fn append(&self, filename: &DbFilename) -> Result<BlockId, TransactionError> {
+        let mut state = self.inner.active_state()?;
+        let block = self
+            .inner
+            .transaction_manager
+            .file_manager()
+            .append(filename)
+            .map_err(TransactionError::StdIoError)?;
+        state.appended_files.insert(filename.clone());
+        Ok(block)
+    }
+
+    /// Record versions deleted by transactions older than the horizon can be removed
+    pub fn gc_horizon(&self) -> TransactionNumber {
+        self.inner.transaction_manager.gc_horizon()
+    }
+
+    /// Runs reader holding the shared latch of the block, so changes done by write_latched are
+    /// seen either completely or not at all
+    pub fn read_latched<R>(
+        &self,
+        block: &BlockId,
+        reader: impl FnOnce() -> R,
+    ) -> Result<R, TransactionError> {
+        let buffer = self.inner.pinned_buffer(block)?;
+        let _latch = buffer.latch_shared();
+        Ok(reader())
+    }
+
+    /// Takes the exclusive lock on the block first (waiting for a lock while holding a latch
+    /// could deadlock undetected), then runs writer holding the exclusive latch of the block
+    pub fn write_latched<R>(
+        &self,
+        block: &BlockId,
+        writer: impl FnOnce() -> R,
+    ) -> Result<R, TransactionError> {
+        let buffer = self.inner.pinned_buffer(block)?;
+        self.inner.xlock(block)?;
+        let _latch = buffer.latch_exclusive();
+        Ok(writer())
+    }
+
+    pub fn set<T: HfdbSerializableDatatype>(
+        &self,
+        block: &BlockId,
+        offset: usize,
+        value: &T,
+    ) -> Result<(), TransactionError> {
+        let mut bytes = vec![0u8; value.serialized_length()];
+        value.serialize(bytes.as_mut_slice());
+        self.set_raw_bytes(block, offset, bytes.as_slice())
+    }
+
+    /// Changes are logged with old and new value before the page is modified. Values are logged
+    /// in chunks small enough to always fit into a single log block.
+    pub fn set_raw_bytes(
+        &self,
+        block: &BlockId,
+        offset: usize,
+        value: &[u8],
+    ) -> Result<(), TransactionError> {
+        let mut buffer = self.inner.pinned_buffer(block)?;
+        self.inner.xlock(block)?;
+        let max_chunk_length = self.max_log_chunk_length(block);
+        for (chunk_nr, new_value) in value.chunks(max_chunk_length).enumerate() {
+            let chunk_offset = offset + chunk_nr * max_chunk_length;
+            let old_value = buffer.page().get_raw_bytes(chunk_offset, new_value.len());
+            let log_record = LogRecord::SetBytes {
+                transaction_number: self.transaction_number(),
+                block: block.clone(),
+                offset: chunk_offset,
+                old_value,
+                new_value: new_value.to_vec(),
+            };
+            let log_sequence_number = log_record
+                .append_to(self.inner.transaction_manager.log_manager())
+                .map_err(TransactionError::StdIoError)?
+                .latest;
+            let mut state = self.inner.active_state()?;
+            state.latest_log_sequence_number = Some(log_sequence_number);
+            state.io_statistics.log_bytes += log_record.serialized_length();
+            drop(state);
+            buffer.modify_page(
+                |page| page.set_raw_bytes(chunk_offset, new_value),
+                self.transaction_number(),
+                Some(log_sequence_number),
+            );
+        }
+        Ok(())
+    }
+
+    fn max_log_chunk_length(&self, block: &BlockId) -> usize {
+        const LOG_RECORD_OVERHEAD: usize = 64;
+        (usize::from(self.block_size())
+            .saturating_sub(LOG_RECORD_OVERHEAD + block.filename().as_str().len())
+            / 2)
+        .max(1)
+    }
+
+    pub fn commit(&self) -> Result<(), TransactionError> {
+        self.inner.commit()
+    }
+
+    pub fn rollback(&self) -> Result<(), TransactionError> {
+        self.inner.rollback()
+    }
+}
+
+impl TransactionInner {
+    fn active_state(
+        &self,
+    ) -> Result<std::sync::MutexGuard<'_, TransactionState>, TransactionError> {
+        let state = self.state.lock().unwrap();
+        match state.status {
+            TransactionStatus::Active => Ok(state),
+            _ => Err(TransactionError::NotActive(self.transaction_number)),
+        }
+    }
+
+    fn slock(&self, block: &BlockId) -> Result<(), TransactionError> {
+        self.active_state()?
+            .concurrency_manager
+            .slock(block)
+            .map_err(TransactionError::LockTableError)
+    }
+
+    fn xlock(&self, block: &BlockId) -> Result<(), TransactionError> {
+        self.active_state()?
+            .concurrency_manager
+            .xlock(block)
+            .map_err(TransactionError::LockTableError)
+    }
+
+    fn pinned_buffer(&self, block: &BlockId) -> Result<Buffer, TransactionError> {
+        self.active_state()?
+            .pinned_buffers
+            .get(block)
+            .map(|pinned_buffer| pinned_buffer.buffer.clone())
+            .ok_or_else(|| TransactionError::BlockNotPinned(block.clone()))
+    }
+
+    /// The commit record is synced before the changed pages are written, so their write-ahead
+    /// log flushes find the log synced already and only the commit waits for a group commit
+    fn commit(&self) -> Result<(), TransactionError> {
+        let mut state = self.active_state()?;
+        for filename in state.appended_files.iter() {
+            self.transaction_manager
+                .file_manager()
+                .sync(filename)
+                .map_err(TransactionError::StdIoError)?;
+        }
+        let log_manager = self.transaction_manager.log_manager();
+        let commit = LogRecord::Commit {
+            transaction_number: self.transaction_number,
+        };
+        let log_sequence_number = commit
+            .append_to(log_manager)
+            .map_err(TransactionError::StdIoError)?
+            .latest;
+        log_manager
+            .flush(log_sequence_number)
+            .map_err(TransactionError::StdIoError)?;
+        debug!("Transaction {}: committed", self.transaction_number);
+        // The commit is durable, pages not written now are written on replacement or redone
+        if let Err(e) = self
+            .transaction_manager
+            .buffer_manager()
+            .flush_all(self.transaction_number)
+        {
+            warn!(
+                "Transaction {}: writing pages after commit failed: {}",
+                self.transaction_number, e
+            );
+        }
+        self.finish(state.deref_mut(), TransactionStatus::Committed);
+        Ok(())
+    }
+
+    fn rollback(&self) -> Result<(), TransactionError> {
+        let mut state = self.active_state()?;
+        let log_manager = self.transaction_manager.log_manager();
+        if let Some(log_sequence_number) = state.latest_log_sequence_number {
+            log_manager
+                .flush(log_sequence_number)
+                .map_err(TransactionError::StdIoError)?;
+            self.undo()?;
+        }
+        self.transaction_manager
+            .buffer_manager()
+            .flush_all(self.transaction_number)
+            .map_err(TransactionError::StdIoError)?;
+        // Recovery does not undo a rolled back transaction again, so the undo must be durable
+        self.transaction_manager
+            .file_manager()
+            .sync_all()
+            .map_err(TransactionError::StdIoError)?;
+        let rollback = LogRecord::Rollback {
+            transaction_number: self.transaction_number,
+        };
+        let log_sequence_number = rollback
+            .append_to(log_manager)
+            .map_err(TransactionError::StdIoError)?
+            .latest;
+        log_manager
+            .flush(log_sequence_number)
+            .map_err(TransactionError::StdIoError)?;
+        debug!("Transaction {}: rolled back", self.transaction_number);
+        self.finish(state.deref_mut(), TransactionStatus::RolledBack);
+        Ok(())
+    }
+
+    fn undo(&self) -> Result<(), TransactionError> {
+        let buffer_manager = self.transaction_manager.buffer_manager();
+        for record in LogRecordIter::new(self.transaction_manager.log_manager())
+            .map_err(TransactionError::StdIoError)?
+        {
+            match record.map_err(TransactionError::StdIoError)? {
+                LogRecord::Start { transaction_number }
+                    if transaction_number == self.transaction_number =>
+                {
+                    break;
+                }
+                LogRecord::SetBytes {
+                    transaction_number,
+                    block,
+                    offset,
+                    old_value,
+                    ..
+                } if transaction_number == self.transaction_number => {
+                    let mut buffer = buffer_manager
+                        .pin(&block)
+                        .map_err(TransactionError::BufferManagerError)?;
+                    {
+                        let latched_buffer = buffer.clone();
+                        let _latch = latched_buffer.latch_exclusive();
+                        buffer.modify_page(
+                            |page| page.set_raw_bytes(offset, old_value.as_slice()),
+                            self.transaction_number,
+                            None,
+                        );
+                    }
+                    buffer_manager.unpin(&buffer);
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn finish(&self, state: &mut TransactionState, status: TransactionStatus) {
+        let buffer_manager = self.transaction_manager.buffer_manager();
+        for (_, pinned_buffer) in state.pinned_buffers.drain() {
+            buffer_manager.unpin_for_transaction(&pinned_buffer.buffer, self.transaction_number);
+        }
+        state.concurrency_manager.release();
+        state.status = status;
+        self.transaction_manager.finished(self.transaction_number);
+    }
+}
+
+impl Drop for TransactionInner {
+    fn drop(&mut self) {
+        if self.state.lock().unwrap().status == TransactionStatus::Active {
+            warn!(
+                "Transaction {}: dropped without commit or rollback, rolling back",
+                self.transaction_number
+            );
+            if let Err(e) = self.rollback() {
+                warn!(
+                    "Transaction {}: rollback on drop failed: {}",
+                    self.transaction_number, e
+                );
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum TransactionError {
+    StdIoError(IoError),
+    BufferManagerError(BufferManagerError),
+    LockTableError(LockTableError),
+    BlockNotPinned(BlockId),
+    NotActive(TransactionNumber),
+    WriteConflict(BlockId, usize),
+}
+
+impl TransactionError {
+    /// The transaction was aborted to resolve a deadlock, it should be rolled back and retried
+    pub fn is_deadlock_victim(&self) -> bool {
+        matches!(
+            self,
+            Self::LockTableError(LockTableError::DeadLockVictim(_))
+                | Self::BufferManagerError(BufferManagerError::DeadLockVictim(_))
+        )
+    }
+}
+
+impl Display for TransactionError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::StdIoError(e) => write!(f, "Transaction IoError {}", e),
+            Self::BufferManagerError(e) => write!(f, "Transaction {}", e),
+            Self::LockTableError(e) => write!(f, "Transaction {}", e),
+            Self::BlockNotPinned(block) => {
+                write!(f, "Transaction: block {:?} is not pinned", block)
+            }
+            Self::NotActive(transaction_number) => write!(
+                f,
+                "Transaction {} is already committed or rolled back",
+                transaction_number
+            ),
+            Self::WriteConflict(block, offset) => write!(
+                f,
+                "Transaction: record version at {:?} offset {} was changed concurrently",
+                block, offset
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::datatypes::fixed_length_integers::Integer;
+    use crate::datatypes::varchar::Varchar;
+    use crate::db_management_system::hfdb::{HanfriedDb, HanfriedDbBuilder};
+    use crate::file_management::block_id::{BlockId, DbFilename};
+    use crate::file_management::file_manager::Durability;
+    use crate::file_management::page::Page;
+    use crate::memory_management::buffer::TransactionNumber;
+    use crate::transaction_management::mvcc::RecordVersion;
+    use crate::transaction_management::transaction::{TransactionError, TransactionStatus};
+    use crate::utils::logging::init_logging;
+    use std::num::NonZeroUsize;
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    fn hfdb(sub_directory_name: &str) -> HanfriedDb {
+        let hfdb = HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .buffer_manager(|bm| bm.pool_size(3))
+            .build()
+            .unwrap();
+        append_blocks(&hfdb);
+        hfdb
+    }
+
+    /// Blocks 0 to 3 of transaction.tbl, which the tests use without appending them first
+    fn append_blocks(hfdb: &HanfriedDb) {
+        for _ in 0..4 {
+            hfdb.file_manager
+                .append(&DbFilename::from("transaction.tbl"))
+                .unwrap();
+        }
+    }
+
+    #[test]
+    fn test_transaction_commit_and_rollback() {
+        init_logging();
+        let hfdb = hfdb("transaction_commit_rollback");
+        let block = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+
+        let tx1 = hfdb.transaction_manager.begin().unwrap();
+        tx1.pin(&block).unwrap();
+        tx1.set(&block, 0, &Integer::from(42)).unwrap();
+        tx1.set(&block, 10, &Varchar::from("committed")).unwrap();
+        tx1.commit().unwrap();
+        assert_eq!(tx1.status(), TransactionStatus::Committed);
+        assert_eq!(hfdb.buffer_manager.num_available(), 3);
+
+        let tx2 = hfdb.transaction_manager.begin().unwrap();
+        assert!(tx2.transaction_number() > tx1.transaction_number());
+        tx2.pin(&block).unwrap();
+        assert_eq!(i32::from(tx2.get::<Integer>(&block, 0).unwrap()), 42);
+        tx2.set(&block, 0, &Integer::from(4711)).unwrap();
+        tx2.set(
+            &block,
+            10,
+            &Varchar::from("a much longer, rolled back text"),
+        )
+        .unwrap();
+        assert_eq!(i32::from(tx2.get::<Integer>(&block, 0).unwrap()), 4711);
+        tx2.rollback().unwrap();
+        assert_eq!(hfdb.buffer_manager.num_available(), 3);
+        assert!(matches!(
+            tx2.get::<Integer>(&block, 0),
+            Err(TransactionError::NotActive(_))
+        ));
+
+        let tx3 = hfdb.transaction_manager.begin().unwrap();
+        tx3.pin(&block).unwrap();
+        assert_eq!(i32::from(tx3.get::<Integer>(&block, 0).unwrap()), 42);
+        assert_eq!(
+            String::from(&tx3.get::<Varchar>(&block, 10).unwrap()),
+            "committed"
+        );
+        tx3.commit().unwrap();
+    }
+
+    #[test]
+    fn test_transaction_commit_syncs_appended_files() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("transaction_commit_syncs")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .durability(Durability::Sync)
+            .build()
+            .unwrap();
+        append_blocks(&hfdb);
+        let block = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+        let syncs_for_commit = |append: bool| {
+            let syncs_before = hfdb.file_manager.syncs_count();
+            let tx = hfdb.transaction_manager.begin().unwrap();
+            let block = if append {
+                tx.append(&DbFilename::from("appended.tbl")).unwrap()
+            } else {
+                block.clone()
+            };
+            tx.pin(&block).unwrap();
+            tx.set(&block, 0, &Integer::from(42)).unwrap();
+            tx.commit().unwrap();
+            hfdb.file_manager.syncs_count() - syncs_before
+        };
+        // The log is synced in both cases, the appended file only if there is one
+        assert_eq!(syncs_for_commit(true), syncs_for_commit(false) + 1);
+    }
+
+    #[test]
+    fn test_transaction_dropped_is_rolled_back() {
+        init_logging();
+        let hfdb = hfdb("transaction_dropped");
+        let block = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+        {
+            let tx = hfdb.transaction_manager.begin().unwrap();
+            tx.pin(&block).unwrap();
+            tx.pin(&block).unwrap();
+            tx.set(&block, 0, &Integer::from(13)).unwrap();
+        }
+        assert_eq!(hfdb.buffer_manager.num_available(), 3);
+        assert!(hfdb.transaction_manager.active_transactions().is_empty());
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        assert!(matches!(
+            tx.get::<Integer>(&block, 0),
+            Err(TransactionError::BlockNotPinned(_))
+        ));
+        tx.pin(&block).unwrap();
+        assert_eq!(i32::from(tx.get::<Integer>(&block, 0).unwrap()), 0);
+        tx.unpin(&block).unwrap();
+        assert_eq!(hfdb.buffer_manager.num_available(), 3);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_transaction_recovery_after_crash() {
+        init_logging();
+        let sub_directory_name = "transaction_recovery";
+        let hfdb = hfdb(sub_directory_name);
+        let block = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+        let other_block = block.with_other_block_number(1);
+
+        let committed = hfdb.transaction_manager.begin().unwrap();
+        committed.pin(&block).unwrap();
+        committed.set(&block, 0, &Integer::from(42)).unwrap();
+        committed.commit().unwrap();
+
+        let uncommitted = hfdb.transaction_manager.begin().unwrap();
+        uncommitted.pin(&other_block).unwrap();
+        uncommitted
+            .set(&other_block, 0, &Integer::from(13))
+            .unwrap();
+        hfdb.buffer_manager
+            .flush_all(uncommitted.transaction_number())
+            .unwrap();
+        let latest_transaction_number = uncommitted.transaction_number();
+        // Crash: neither commit nor rollback (nor drop) happens
+        std::mem::forget(uncommitted);
+        drop(hfdb);
+
+        let hfdb = HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| {
+                fm.block_size(NonZeroUsize::new(100).unwrap())
+                    .fresh_db_directory(false)
+            })
+            .buffer_manager(|bm| bm.pool_size(3))
+            .build()
+            .unwrap();
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        assert_eq!(
+            tx.transaction_number(),
+            TransactionNumber::from(u64::from(latest_transaction_number) + 1),
+            "Transaction numbers must not be reused after a restart"
+        );
+        tx.pin(&block).unwrap();
+        tx.pin(&other_block).unwrap();
+        assert_eq!(i32::from(tx.get::<Integer>(&block, 0).unwrap()), 42);
+        assert_eq!(i32::from(tx.get::<Integer>(&other_block, 0).unwrap()), 0);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_transaction_checkpoint() {
+        init_logging();
+        let sub_directory_name = "transaction_checkpoint";
+        let hfdb = hfdb(sub_directory_name);
+        let block = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        tx.pin(&block).unwrap();
+        tx.set(&block, 0, &Integer::from(42)).unwrap();
+        hfdb.transaction_manager.checkpoint().unwrap();
+        tx.set(&block, 4, &Integer::from(43)).unwrap();
+        hfdb.buffer_manager
+            .flush_all(tx.transaction_number())
+            .unwrap();
+        let transaction_number = tx.transaction_number();
+        std::mem::forget(tx);
+        drop(hfdb);
+
+        let hfdb = HanfriedDbBuilder::unittest(sub_directory_name)
+            .file_manager(|fm| {
+                fm.block_size(NonZeroUsize::new(100).unwrap())
+                    .fresh_db_directory(false)
+            })
+            .build()
+            .unwrap();
+        assert!(hfdb
+            .recovery_statistics
+            .undone_transactions
+            .contains(&transaction_number));
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        tx.pin(&block).unwrap();
+        assert_eq!(i32::from(tx.get::<Integer>(&block, 0).unwrap()), 0);
+        assert_eq!(i32::from(tx.get::<Integer>(&block, 4).unwrap()), 0);
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_transaction_rollback_and_checkpoint_sync_data_files() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("transaction_rollback_checkpoint_sync")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .durability(Durability::Sync)
+            .build()
+            .unwrap();
+        append_blocks(&hfdb);
+        hfdb.file_manager.sync_all().unwrap();
+        let block = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        tx.pin(&block).unwrap();
+        tx.set(&block, 0, &Integer::from(42)).unwrap();
+        tx.rollback().unwrap();
+        let syncs = hfdb.file_manager.syncs_count();
+        hfdb.file_manager.sync_all().unwrap();
+        assert_eq!(hfdb.file_manager.syncs_count(), syncs, "undo not synced");
+
+        // A page changed by a finished transaction and not written yet
+        let mut buffer = hfdb.buffer_manager.pin(&block).unwrap();
+        buffer.modify_page(
+            |page| page.set(0, &Integer::from(4711)),
+            tx.transaction_number(),
+            None,
+        );
+        hfdb.buffer_manager.unpin(&buffer);
+        hfdb.transaction_manager.checkpoint().unwrap();
+        let syncs = hfdb.file_manager.syncs_count();
+        hfdb.file_manager.sync_all().unwrap();
+        assert_eq!(hfdb.file_manager.syncs_count(), syncs, "page not synced");
+        let page = Page::new(hfdb.file_manager.block_size);
+        hfdb.file_manager.read(&block, &page).unwrap();
+        assert_eq!(i32::from(page.get::<Integer>(0)), 4711);
+    }
+
+    #[test]
+    fn test_transaction_locks_serialize_concurrent_increments() {
+        init_logging();
+        let hfdb = hfdb("transaction_locks");
+        let block = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+        let threads = 4;
+        let increments = 5;
+
+        let handles: Vec<_> = (0..threads)
+            .map(|_| {
+                let transaction_manager = hfdb.transaction_manager.clone();
+                let block = block.clone();
+                thread::spawn(move || {
+                    let mut committed = 0;
+                    while committed < increments {
+                        let tx = transaction_manager.begin().unwrap();
+                        tx.pin(&block).unwrap();
+                        let incremented = tx.get::<Integer>(&block, 0).and_then(|counter| {
+                            tx.set(&block, 0, &Integer::from(i32::from(counter) + 1))
+                        });
+                        match incremented {
+                            Ok(()) => {
+                                tx.commit().unwrap();
+                                committed += 1;
+                            }
+                            Err(TransactionError::LockTableError(_)) => tx.rollback().unwrap(),
+                            Err(e) => panic!("Unexpected error {}", e),
+                        }
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        tx.pin(&block).unwrap();
+        assert_eq!(
+            i32::from(tx.get::<Integer>(&block, 0).unwrap()),
+            threads * increments
+        );
+        tx.commit().unwrap();
+    }
+
+    #[test]
+    fn test_transaction_lock_deadlock_victim_is_youngest() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("transaction_lock_deadlock")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .buffer_manager(|bm| bm.pool_size(3))
+            .lock_table(|lt| lt.deadlock_waiting_duration(Duration::from_secs(10)))
+            .build()
+            .unwrap();
+        append_blocks(&hfdb);
+        let block1 = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+        let block2 = block1.with_other_block_number(1);
+        let start_time = Instant::now();
+
+        let older = hfdb.transaction_manager.begin().unwrap();
+        let younger = hfdb.transaction_manager.begin().unwrap();
+        for (tx, block) in [(&older, &block1), (&younger, &block2)] {
+            tx.pin(&block1).unwrap();
+            tx.pin(&block2).unwrap();
+            tx.set(block, 0, &Integer::from(13)).unwrap();
+        }
+        let waiting_older = older.clone();
+        let waiting_block = block2.clone();
+        let waiting = thread::spawn(move || {
+            waiting_older
+                .get::<Integer>(&waiting_block, 0)
+                .map(i32::from)
+        });
+        thread::sleep(Duration::from_millis(50));
+
+        let error = younger.get::<Integer>(&block1, 0).unwrap_err();
+        assert!(error.is_deadlock_victim(), "Unexpected error {}", error);
+        younger.rollback().unwrap();
+        assert_eq!(waiting.join().unwrap().unwrap(), 0);
+        older.commit().unwrap();
+        assert!(
+            start_time.elapsed() < Duration::from_secs(5),
+            "Deadlock has to be detected before the deadlock timeout"
+        );
+    }
+
+    #[test]
+    fn test_transaction_buffer_deadlock_victim_is_youngest() {
+        init_logging();
+        let hfdb = HanfriedDbBuilder::unittest("transaction_buffer_deadlock")
+            .file_manager(|fm| fm.block_size(NonZeroUsize::new(100).unwrap()))
+            .buffer_manager(|bm| {
+                bm.pool_size(2)
+                    .deadlock_waiting_duration(Duration::from_secs(10))
+            })
+            .build()
+            .unwrap();
+        append_blocks(&hfdb);
+        let block = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+        let start_time = Instant::now();
+
+        let older = hfdb.transaction_manager.begin().unwrap();
+        let younger = hfdb.transaction_manager.begin().unwrap();
+        older.pin(&block).unwrap();
+        younger.pin(&block.with_other_block_number(1)).unwrap();
+        let waiting_older = older.clone();
+        let waiting_block = block.with_other_block_number(2);
+        let waiting = thread::spawn(move || waiting_older.pin(&waiting_block));
+        thread::sleep(Duration::from_millis(50));
+
+        let error = younger.pin(&block.with_other_block_number(3)).unwrap_err();
+        assert!(error.is_deadlock_victim(), "Unexpected error {}", error);
+        younger.rollback().unwrap();
+        waiting.join().unwrap().unwrap();
+        older.commit().unwrap();
+        assert_eq!(hfdb.buffer_manager.num_available(), 2);
+        assert!(
+            start_time.elapsed() < Duration::from_secs(5),
+            "Deadlock has to be detected before the deadlock timeout"
+        );
+    }
+
+    #[test]
+    fn test_transaction_snapshot_reads_do_not_block_writers() {
+        init_logging();
+        let hfdb = hfdb("transaction_snapshot");
+        let block = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+        let (old_version, new_version) = (0, 20);
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        tx.pin(&block).unwrap();
+        tx.create_version(&block, old_version).unwrap();
+        tx.commit().unwrap();
+
+        let reader = hfdb.transaction_manager.begin().unwrap();
+        reader.pin(&block).unwrap();
+        assert!(reader.is_visible(&block, old_version).unwrap());
+
+        let writer = hfdb.transaction_manager.begin().unwrap();
+        writer.pin(&block).unwrap();
+        writer.delete_version(&block, old_version).unwrap();
+        writer.create_version(&block, new_version).unwrap();
+        assert!(!writer.is_visible(&block, old_version).unwrap());
+        assert!(writer.is_visible(&block, new_version).unwrap());
+        assert!(reader.is_visible(&block, old_version).unwrap());
+        assert!(!reader.is_visible(&block, new_version).unwrap());
+        writer.commit().unwrap();
+
+        assert!(reader.is_visible(&block, old_version).unwrap());
+        assert!(!reader.is_visible(&block, new_version).unwrap());
+        let deleted = reader.read::<RecordVersion>(&block, old_version).unwrap();
+        assert!(!deleted.is_garbage(hfdb.transaction_manager.gc_horizon()));
+
+        let later_reader = hfdb.transaction_manager.begin().unwrap();
+        later_reader.pin(&block).unwrap();
+        assert!(!later_reader.is_visible(&block, old_version).unwrap());
+        assert!(later_reader.is_visible(&block, new_version).unwrap());
+
+        reader.commit().unwrap();
+        later_reader.commit().unwrap();
+        assert!(deleted.is_garbage(hfdb.transaction_manager.gc_horizon()));
+    }
+
+    #[test]
+    fn test_transaction_first_updater_wins() {
+        init_logging();
+        let hfdb = hfdb("transaction_write_conflict");
+        let block = BlockId::new(DbFilename::from("transaction.tbl"), 0);
+
+        let tx = hfdb.transaction_manager.begin().unwrap();
+        tx.pin(&block).unwrap();
+        tx.create_version(&block, 0).unwrap();
+        tx.commit().unwrap();
+
+        let first = hfdb.transaction_manager.begin().unwrap();
+        let second = hfdb.transaction_manager.begin().unwrap();
+        first.pin(&block).unwrap();
+        second.pin(&block).unwrap();
+        first.delete_version(&block, 0).unwrap();
+        first.commit().unwrap();
+
+        assert!(second.is_visible(&block, 0).unwrap());
+        assert!(matches!(
+            second.delete_version(&block, 0),
+            Err(TransactionError::WriteConflict(_, 0))
+        ));
+        second.rollback().unwrap();
+    }
+}
@@ -0,0 +1,135 @@
+use crate::file_management::file_manager::{FileManager, IoError};
+use crate::memory_management::buffer::TransactionNumber;
+use crate::memory_management::buffer_manager::BufferManager;
+use crate::memory_management::log_manager::LogManager;
+use crate::transaction_management::lock_table::LockTable;
+use crate::transaction_management::log_record::LogRecord;
+use crate::transaction_management::mvcc::Snapshot;
+use crate::transaction_management::transaction::{Transaction, TransactionError};
+use log::debug;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub struct TransactionManager {
+    file_manager: FileManager,
+    log_manager: LogManager,
+    buffer_manager: BufferManager,
+    lock_table: LockTable,
+    latest_transaction_number: Arc<AtomicU64>,
+    /// Active transactions with the oldest transaction active at their start
+    active_transactions: Arc<Mutex<BTreeMap<TransactionNumber, TransactionNumber>>>,
+}
+
+impl TransactionManager {
+    pub fn new(
+        file_manager: &FileManager,
+        log_manager: &LogManager,
+        buffer_manager: &BufferManager,
+        lock_table: &LockTable,
+        max_transaction_number: Option<TransactionNumber>,
+    ) -> Self {
+        Self {
+            file_manager: file_manager.clone(),
+            log_manager: log_manager.clone(),
+            buffer_manager: buffer_manager.clone(),
+            lock_table: lock_table.clone(),
+            latest_transaction_number: Arc::new(AtomicU64::new(
+                max_transaction_number.map(u64::from).unwrap_or(0),
+            )),
+            active_transactions: Arc::new(Mutex::new(BTreeMap::new())),
+        }
+    }
+
+    pub fn file_manager(&self) -> &FileManager {
+        &self.file_manager
+    }
+
+    pub fn log_manager(&self) -> &LogManager {
+        &self.log_manager
+    }
+
+    pub fn buffer_manager(&self) -> &BufferManager {
+        &self.buffer_manager
+    }
+
+    pub fn lock_table(&self) -> &LockTable {
+        &self.lock_table
+    }
+
+    /// Numbering the transaction and taking its snapshot happens while holding the lock on the
+    /// active transactions, so every snapshot sees exactly the transactions finished before
+    pub fn begin(&self) -> Result<Transaction, TransactionError> {
+        let mut active_transactions = self.active_transactions.lock().unwrap();
+        let transaction_number = TransactionNumber::from(
+            self.latest_transaction_number
+                .fetch_add(1, Ordering::SeqCst)
+                + 1,
+        );
+        LogRecord::Start { transaction_number }
+            .append_to(&self.log_manager)
+            .map_err(TransactionError::StdIoError)?;
+        let snapshot = Snapshot::new(
+            transaction_number,
+            active_transactions.keys().copied().collect(),
+        );
+        active_transactions.insert(transaction_number, snapshot.oldest_active());
+        debug!(
+            "TransactionManager: Begin transaction {}",
+            transaction_number
+        );
+        Ok(Transaction::new(self, snapshot))
+    }
+
+    pub fn active_transactions(&self) -> Vec<TransactionNumber> {
+        self.active_transactions
+            .lock()
+            .unwrap()
+            .keys()
+            .copied()
+            .collect()
+    }
+
+    /// Record versions deleted by transactions older than the horizon are invisible to all
+    /// active and future snapshots and can be garbage collected
+    pub fn gc_horizon(&self) -> TransactionNumber {
+        let active_transactions = self.active_transactions.lock().unwrap();
+        active_transactions
+            .values()
+            .min()
+            .copied()
+            .unwrap_or_else(|| {
+                TransactionNumber::from(self.latest_transaction_number.load(Ordering::SeqCst) + 1)
+            })
+    }
+
+    pub(crate) fn finished(&self, transaction_number: TransactionNumber) {
+        self.active_transactions
+            .lock()
+            .unwrap()
+            .remove(&transaction_number);
+        self.buffer_manager
+            .wait_for_graph()
+            .stop_waiting(transaction_number);
+    }
+
+    /// Non-quiescent checkpoint: flushes all modified buffers, also those of finished
+    /// transactions not written yet, syncs the files written and records which transactions
+    /// are still active, so recovery only has to look beyond the checkpoint for their changes
+    pub fn checkpoint(&self) -> Result<(), IoError> {
+        let active_transactions = self.active_transactions.lock().unwrap();
+        self.buffer_manager.flush_modified()?;
+        self.file_manager.sync_all()?;
+        let latest_transaction_number = self.latest_transaction_number.load(Ordering::SeqCst);
+        let checkpoint = LogRecord::Checkpoint {
+            active_transactions: active_transactions.keys().copied().collect(),
+            max_transaction_number: match latest_transaction_number {
+                0 => None,
+                nr => Some(TransactionNumber::from(nr)),
+            },
+        };
+        let latest = checkpoint.append_to(&self.log_manager)?.latest;
+        self.log_manager.flush(latest)
+    }
+}
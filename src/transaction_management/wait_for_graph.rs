@@ -0,0 +1,220 @@
+use crate::memory_management::buffer::TransactionNumber;
+use log::{debug, warn};
+use std::collections::{BTreeSet, HashMap};
+use std::fmt::{Display, Formatter};
+use std::sync::{Arc, Condvar, Mutex};
+
+/// How a waiting transaction depends on the transactions it waits for
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum WaitKind {
+    /// All holders have to finish, e.g. conflicting holders of a lock
+    AllOf,
+    /// Any holder finishing is enough, e.g. transactions pinning buffers of a full buffer pool
+    AnyOf,
+}
+
+#[derive(Debug)]
+struct Waiting {
+    kind: WaitKind,
+    holders: BTreeSet<TransactionNumber>,
+    wake_up: Arc<Condvar>,
+}
+
+#[derive(Debug, Default)]
+struct WaitForGraphState {
+    waiting: HashMap<TransactionNumber, Waiting>,
+    victims: BTreeSet<TransactionNumber>,
+}
+
+/// Transactions waiting for other transactions (for locks in the LockTable or for buffers in the
+/// BufferManager). Every time a transaction starts or continues waiting the graph is checked for
+/// deadlocks. The youngest deadlocked transaction the waiting one depends on is chosen as victim,
+/// it gets a DeadLockVictim error instead of waiting for its deadlock timeout.
+///
+/// A victim waiting on another condition variable than the one of the detecting waiter might miss
+/// its wake up call, the deadlock timeout is the fallback for this (rare) case.
+#[derive(Debug, Clone, Default)]
+pub struct WaitForGraph {
+    state: Arc<Mutex<WaitForGraphState>>,
+}
+
+impl WaitForGraph {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers (or updates) that waiter waits for holders and will be woken up via wake_up.
+    /// Fails if waiter has been chosen as a deadlock victim.
+    pub fn wait_for(
+        &self,
+        waiter: TransactionNumber,
+        kind: WaitKind,
+        holders: BTreeSet<TransactionNumber>,
+        wake_up: &Arc<Condvar>,
+    ) -> Result<(), DeadLockVictim> {
+        let mut state = self.state.lock().unwrap();
+        if state.victims.remove(&waiter) {
+            state.waiting.remove(&waiter);
+            return Err(DeadLockVictim(waiter));
+        }
+        state.waiting.insert(
+            waiter,
+            Waiting {
+                kind,
+                holders,
+                wake_up: wake_up.clone(),
+            },
+        );
+        let deadlocked = state.deadlocked();
+        if !deadlocked.contains(&waiter) {
+            return Ok(());
+        }
+        let victim = state.youngest_dependency(waiter, &deadlocked);
+        warn!(
+            "WaitForGraph: Deadlock of transactions {:?} detected by transaction {}, victim is {}",
+            deadlocked, waiter, victim
+        );
+        if victim == waiter {
+            state.waiting.remove(&waiter);
+            return Err(DeadLockVictim(waiter));
+        }
+        state.victims.insert(victim);
+        if let Some(waiting) = state.waiting.get(&victim) {
+            waiting.wake_up.notify_all();
+        }
+        Ok(())
+    }
+
+    /// Transaction got what it waited for or finished, it is no (longer a) victim
+    pub fn stop_waiting(&self, transaction_number: TransactionNumber) {
+        let mut state = self.state.lock().unwrap();
+        state.waiting.remove(&transaction_number);
+        state.victims.remove(&transaction_number);
+    }
+}
+
+impl WaitForGraphState {
+    /// Waiting transactions that can never continue: A transaction can continue if it does not
+    /// wait, is a victim (it will release all it holds) or the holders it waits for can continue.
+    fn deadlocked(&self) -> BTreeSet<TransactionNumber> {
+        let mut continuing: BTreeSet<TransactionNumber> = BTreeSet::new();
+        loop {
+            let can_continue = |holder: &TransactionNumber| {
+                !self.waiting.contains_key(holder) || continuing.contains(holder)
+            };
+            let newly_continuing: Vec<TransactionNumber> = self
+                .waiting
+                .iter()
+                .filter(|(transaction_number, _)| !continuing.contains(transaction_number))
+                .filter(|(transaction_number, waiting)| {
+                    self.victims.contains(transaction_number)
+                        || match waiting.kind {
+                            WaitKind::AllOf => waiting.holders.iter().all(can_continue),
+                            WaitKind::AnyOf => waiting.holders.iter().any(can_continue),
+                        }
+                })
+                .map(|(transaction_number, _)| *transaction_number)
+                .collect();
+            if newly_continuing.is_empty() {
+                break;
+            }
+            continuing.extend(newly_continuing);
+        }
+        self.waiting
+            .keys()
+            .filter(|transaction_number| !continuing.contains(transaction_number))
+            .copied()
+            .collect()
+    }
+
+    fn youngest_dependency(
+        &self,
+        waiter: TransactionNumber,
+        deadlocked: &BTreeSet<TransactionNumber>,
+    ) -> TransactionNumber {
+        let mut reachable = BTreeSet::from([waiter]);
+        let mut to_visit = vec![waiter];
+        while let Some(transaction_number) = to_visit.pop() {
+            for holder in self.waiting[&transaction_number].holders.iter() {
+                if deadlocked.contains(holder) && reachable.insert(*holder) {
+                    to_visit.push(*holder);
+                }
+            }
+        }
+        debug!(
+            "WaitForGraph: transaction {} depends on deadlocked transactions {:?}",
+            waiter, reachable
+        );
+        *reachable.iter().next_back().unwrap()
+    }
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub struct DeadLockVictim(pub TransactionNumber);
+
+impl Display for DeadLockVictim {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "transaction {} is a deadlock victim", self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::memory_management::buffer::TransactionNumber;
+    use crate::transaction_management::wait_for_graph::{DeadLockVictim, WaitForGraph, WaitKind};
+    use std::collections::BTreeSet;
+    use std::sync::{Arc, Condvar};
+
+    #[test]
+    fn test_wait_for_graph_chooses_youngest_victim() {
+        let graph = WaitForGraph::new();
+        let wake_up = Arc::new(Condvar::new());
+        let tx = |nr: u64| TransactionNumber::from(nr);
+
+        graph
+            .wait_for(tx(1), WaitKind::AllOf, BTreeSet::from([tx(2)]), &wake_up)
+            .unwrap();
+        graph
+            .wait_for(
+                tx(3),
+                WaitKind::AnyOf,
+                BTreeSet::from([tx(1), tx(4)]),
+                &wake_up,
+            )
+            .unwrap();
+        // Closing the cycle 1 -> 2 -> 1, transaction 3 may continue as 4 is not waiting
+        assert_eq!(
+            graph.wait_for(tx(2), WaitKind::AllOf, BTreeSet::from([tx(1)]), &wake_up),
+            Err(DeadLockVictim(tx(2)))
+        );
+        graph
+            .wait_for(tx(4), WaitKind::AllOf, BTreeSet::from([tx(3)]), &wake_up)
+            .unwrap();
+
+        // Cycle 1 -> 5 -> 1 detected by 5, with 3 and 4 only waiting for the cycle. They are
+        // no dependencies of 5, so 5 itself is the youngest one and the victim
+        graph
+            .wait_for(tx(1), WaitKind::AllOf, BTreeSet::from([tx(5)]), &wake_up)
+            .unwrap();
+        assert_eq!(
+            graph.wait_for(tx(5), WaitKind::AllOf, BTreeSet::from([tx(1)]), &wake_up),
+            Err(DeadLockVictim(tx(5)))
+        );
+
+        // Cycle 6 -> 1 -> 6 detected by 1, victim 6 is told on its next wait
+        graph
+            .wait_for(tx(6), WaitKind::AllOf, BTreeSet::from([tx(1)]), &wake_up)
+            .unwrap();
+        graph
+            .wait_for(tx(1), WaitKind::AllOf, BTreeSet::from([tx(6)]), &wake_up)
+            .unwrap();
+        assert_eq!(
+            graph.wait_for(tx(6), WaitKind::AllOf, BTreeSet::from([tx(1)]), &wake_up),
+            Err(DeadLockVictim(tx(6)))
+        );
+        graph.stop_waiting(tx(6));
+        graph
+            .wait_for(tx(1), WaitKind::AllOf, BTreeSet::from([tx(6)]), &wake_up)
+            .unwrap();
+    }
+}
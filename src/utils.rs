@@ -1,2 +1,4 @@
+pub mod crc32c;
+pub mod json;
 pub mod logging;
 pub mod sync_resource_cache;
@@ -1,5 +1,5 @@
 use log::{warn, LevelFilter};
-use log4rs::append::console::ConsoleAppender;
+use log4rs::append::console::{ConsoleAppender, Target};
 use log4rs::config::runtime::{Appender, Config, Root};
 use log4rs::encode::pattern::PatternEncoder;
 use std::env;
@@ -9,7 +9,18 @@ use std::sync::Once;
 static INIT: Once = Once::new();
 
 pub fn init_logging() {
+    init(Target::Stdout, LevelFilter::Debug)
+}
+
+/// Logging of interactive programs: to stderr, so it does not mix with their output, and only
+/// warnings unless HFDB_LOG_LEVEL is set
+pub fn init_logging_to_stderr() {
+    init(Target::Stderr, LevelFilter::Warn)
+}
+
+fn init(target: Target, default_level: LevelFilter) {
     let stdout = ConsoleAppender::builder()
+        .target(target)
         .encoder(Box::new(PatternEncoder::new(
             "{h({d(%Y-%m-%d %H:%M:%S)(utc)} - {l}: {m}{n})}",
         )))
@@ -22,7 +33,7 @@ pub fn init_logging() {
 
     let log_level = match env::var("HFDB_LOG_LEVEL") {
         Ok(level) => LevelFilter::from_str(&level).unwrap(),
-        Err(_) => LevelFilter::Debug,
+        Err(_) => default_level,
     };
 
     let config = Config::builder()
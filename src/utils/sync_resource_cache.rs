@@ -122,6 +122,17 @@ where
         Ok(resource.clone())
     }
 
+    /// Forgets the key, closing its resource if it is open
+    pub fn remove(&self, key: &K) {
+        let mut cache_write_lock = self.internal_hash_map.write().unwrap();
+        if let Some(Item {
+            resource: Some(_), ..
+        }) = cache_write_lock.remove(key)
+        {
+            self.open_resources.fetch_sub(1, Relaxed);
+        }
+    }
+
     pub fn for_each(&self, mut f: impl FnMut(&V)) {
         self.internal_hash_map
             .read()
@@ -180,10 +191,10 @@ mod tests {
         );
         assert_eq!(cache.len_known(), 4);
         assert_eq!(cache.len_open(), 3);
-        assert_eq!(cache.resource_is_open(&String::from("foo")), false);
-        assert_eq!(cache.resource_is_open(&String::from("bar")), true);
-        assert_eq!(cache.resource_is_open(&String::from("foobar")), true);
-        assert_eq!(cache.resource_is_open(&String::from("new1")), true);
+        assert!(!cache.resource_is_open(&String::from("foo")));
+        assert!(cache.resource_is_open(&String::from("bar")));
+        assert!(cache.resource_is_open(&String::from("foobar")));
+        assert!(cache.resource_is_open(&String::from("new1")));
         println!("cache {:?}", cache)
     }
 }